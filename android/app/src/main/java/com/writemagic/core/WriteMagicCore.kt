@@ -38,13 +38,89 @@ data class AIResponse(
     val success: Boolean
 )
 
+/**
+ * Why a native core call failed, decoded from the JSON left in its error slot
+ */
+@Serializable
+data class CoreError(
+    val code: String,
+    val message: String
+)
+
+class CoreException(val error: CoreError) : Exception(error.message)
+
 /**
  * Main interface to the WriteMagic Rust core engine.
- * This version uses a working in-memory implementation while the native library is being built.
+ * Documents go through the native library when it is bundled; otherwise a
+ * working in-memory implementation stands in.
  */
 object WriteMagicCore {
     private const val TAG = "WriteMagicCore"
     private var isInitialized = false
+    private val json = Json { ignoreUnknownKeys = true }
+
+    /** Whether the Rust core library could be loaded */
+    private val nativeAvailable: Boolean = try {
+        System.loadLibrary("writemagic_android_ffi")
+        true
+    } catch (e: UnsatisfiedLinkError) {
+        Log.w(TAG, "Native core not available, using the in-memory implementation")
+        false
+    }
+
+    /** Error of the most recent failed native call */
+    @Volatile
+    var lastError: CoreError? = null
+        private set
+
+    @JvmStatic
+    private external fun nativeInitialize(claudeKey: String, openaiKey: String): Boolean
+
+    @JvmStatic
+    private external fun nativeCreateDocumentWithError(
+        title: String,
+        content: String,
+        contentType: String,
+        idempotencyKey: String?,
+        errorOut: Array<String?>
+    ): String?
+
+    @JvmStatic
+    private external fun nativeUpdateDocumentContentWithError(
+        documentId: String,
+        content: String,
+        errorOut: Array<String?>
+    ): Boolean
+
+    @JvmStatic
+    private external fun nativeGetDocumentWithError(documentId: String, errorOut: Array<String?>): String?
+
+    /**
+     * Decode the error JSON a `*WithError` native call left in its error slot
+     */
+    internal fun decodeCoreError(errorJson: String?): CoreError =
+        errorJson?.let { runCatching { json.decodeFromString<CoreError>(it) }.getOrNull() }
+            ?: CoreError(code = "INTERNAL", message = errorJson ?: "Native call failed without reporting an error")
+
+    /**
+     * Run a `*WithError` native call, throwing the error it reports as a [CoreException]
+     */
+    private inline fun <T : Any> callNative(call: (Array<String?>) -> T?): T {
+        val errorOut = arrayOfNulls<String>(1)
+        return call(errorOut) ?: throw CoreException(decodeCoreError(errorOut[0]))
+    }
+
+    /**
+     * Run a native document call, logging and remembering its error instead of throwing
+     */
+    private inline fun <T : Any> nativeOrNull(action: String, call: (Array<String?>) -> T?): T? =
+        try {
+            callNative(call)
+        } catch (e: CoreException) {
+            lastError = e.error
+            Log.e(TAG, "Failed to $action: ${e.error.code} ${e.error.message}")
+            null
+        }
     private val documents = mutableMapOf<String, Document>()
     private val projects = mutableMapOf<String, String>()
     private val dateFormat = SimpleDateFormat("yyyy-MM-dd'T'HH:mm:ss'Z'", Locale.US)
@@ -128,7 +204,15 @@ object WriteMagicCore {
             return@withContext true
         }
         
-        Log.i(TAG, "Initializing WriteMagic core (in-memory mode)...")
+        if (nativeAvailable) {
+            Log.i(TAG, "Initializing WriteMagic core...")
+            if (!nativeInitialize(claudeKey, openaiKey)) {
+                Log.e(TAG, "Failed to initialize WriteMagic core")
+                return@withContext false
+            }
+        } else {
+            Log.i(TAG, "Initializing WriteMagic core (in-memory mode)...")
+        }
         isInitialized = true
         Log.i(TAG, "WriteMagic core initialized successfully")
         true
@@ -136,16 +220,25 @@ object WriteMagicCore {
     
     /**
      * Create a new document
+     *
+     * Retrying with the same [idempotencyKey] returns the document the first attempt created.
      */
     suspend fun createDocument(
         title: String, 
         content: String = "", 
-        contentType: String = "markdown"
+        contentType: String = "markdown",
+        idempotencyKey: String? = null
     ): Document? = withContext(Dispatchers.IO) {
         if (!isInitialized) {
             Log.e(TAG, "Core not initialized")
             return@withContext null
         }
+
+        if (nativeAvailable) {
+            return@withContext nativeOrNull("create document") {
+                nativeCreateDocumentWithError(title, content, contentType, idempotencyKey, it)
+            }?.let { json.decodeFromString<Document>(it) }
+        }
         
         try {
             val id = UUID.randomUUID().toString()
@@ -178,6 +271,12 @@ object WriteMagicCore {
             Log.e(TAG, "Core not initialized")
             return@withContext false
         }
+
+        if (nativeAvailable) {
+            return@withContext nativeOrNull("update document $documentId") {
+                nativeUpdateDocumentContentWithError(documentId, content, it).takeIf { updated -> updated }
+            } ?: false
+        }
         
         val doc = documents[documentId]
         if (doc != null) {
@@ -206,6 +305,12 @@ object WriteMagicCore {
             Log.e(TAG, "Core not initialized")
             return@withContext null
         }
+
+        if (nativeAvailable) {
+            return@withContext nativeOrNull("load document $documentId") {
+                nativeGetDocumentWithError(documentId, it)
+            }?.let { json.decodeFromString<Document>(it) }
+        }
         
         documents[documentId]
     }
@@ -262,6 +367,12 @@ object WriteMagicCore {
             Log.e(TAG, "Core not initialized")
             return@withContext null
         }
+
+        if (nativeAvailable) {
+            return@withContext nativeOrNull("create document") {
+                nativeCreateDocumentWithError(title, content, contentType, null, it)
+            }?.let { json.decodeFromString<Document>(it) }
+        }
         
         try {
             val id = UUID.randomUUID().toString()
@@ -280,4 +280,23 @@ class WriteMagicDataTest {
         assertNotNull(response.error)
         assertEquals("API request failed", response.error)
     }
-}
\ No newline at end of file
+
+    @Test
+    fun `decodeCoreError should read the code and message reported by native calls`() {
+        val error = WriteMagicCore.decodeCoreError(
+            """{"code":"NOT_FOUND","message":"Document 42 not found","details":{"resource":"Document 42"}}"""
+        )
+
+        assertEquals("NOT_FOUND", error.code)
+        assertEquals("Document 42 not found", error.message)
+    }
+
+    @Test
+    fun `decodeCoreError should fall back to an internal error when nothing usable was reported`() {
+        assertEquals("INTERNAL", WriteMagicCore.decodeCoreError(null).code)
+
+        val garbled = WriteMagicCore.decodeCoreError("not json")
+        assertEquals("INTERNAL", garbled.code)
+        assertEquals("not json", garbled.message)
+    }
+}
@@ -1,8 +1,8 @@
 //! Agent domain aggregates
 
 use writemagic_shared::{EntityId, WritemagicError, Result};
-use crate::entities::{Agent, AgentWorkflow, AgentStatus, ExecutionContext, ExecutionResult, TriggerType, WorkflowTrigger};
-use crate::value_objects::ExecutionPriority;
+use crate::entities::{Agent, AgentWorkflow, AgentStatus, ExecutionContext, ExecutionResult, ExecutionUsage, TriggerType, WorkflowTrigger};
+use crate::value_objects::{ExecutionPriority, QuotaLimit};
 use chrono::{DateTime, Utc};
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
@@ -44,6 +44,11 @@ pub struct ExecutionRecord {
     pub trigger_type: TriggerType,
     pub context: BTreeMap<String, serde_json::Value>,
     pub resource_usage: ExecutionResourceUsage,
+    /// Outputs of the steps that ran, including those of a failed execution
+    #[serde(default)]
+    pub step_outputs: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub usage: ExecutionUsage,
 }
 
 /// Resource usage for a single execution
@@ -180,6 +185,11 @@ impl AgentAggregate {
         self.version
     }
     
+    /// Get completed executions, oldest first
+    pub fn execution_history(&self) -> &[ExecutionRecord] {
+        &self.execution_history
+    }
+    
     /// Get pending events
     pub fn events(&self) -> &[AgentEvent] {
         &self.events
@@ -355,6 +365,8 @@ impl AgentAggregate {
                 .and_then(|v| v.as_str())
                 .and_then(|s| EntityId::from_string(s).ok()),
             environment: crate::entities::ExecutionEnvironment::Production,
+            step_outputs: BTreeMap::new(),
+            usage: ExecutionUsage::default(),
         };
         
         self.add_event(AgentEvent::ExecutionStarted {
@@ -369,7 +381,7 @@ impl AgentAggregate {
     /// Complete execution
     pub fn complete_execution(
         &mut self,
-        execution_id: EntityId,
+        context: &ExecutionContext,
         result: ExecutionResult,
         resource_usage: ExecutionResourceUsage,
     ) -> Result<()> {
@@ -382,15 +394,18 @@ impl AgentAggregate {
         self.update_resource_usage(&resource_usage);
         
         // Record execution history
+        let execution_id = context.execution_id;
         let record = ExecutionRecord {
             execution_id,
             agent_id: self.agent.id,
-            started_at: Utc::now() - resource_usage.duration, // Approximate start time
+            started_at: context.started_at,
             completed_at: Some(Utc::now()),
             result: Some(result.clone()),
-            trigger_type: TriggerType::Manual, // Would need to store this from context
-            context: BTreeMap::new(), // Would need to store this from context
+            trigger_type: context.trigger.trigger_type.clone(),
+            context: context.variables.clone(),
             resource_usage,
+            step_outputs: context.step_outputs.clone(),
+            usage: context.usage.clone(),
         };
         
         self.execution_history.push(record);
@@ -442,6 +457,17 @@ impl AgentAggregate {
         Ok(retry_count < 3 && self.agent.config.retry_failed_executions)
     }
     
+    /// Record that an execution was stopped by its quota
+    pub fn record_limit_exceeded(&mut self, limit: QuotaLimit, allowed: String, actual: String) {
+        self.add_event(AgentEvent::ResourceLimitExceeded {
+            agent_id: self.agent.id,
+            resource_type: limit.to_string(),
+            limit: allowed,
+            actual,
+            timestamp: Utc::now(),
+        });
+    }
+    
     /// Get execution statistics
     pub fn get_execution_statistics(&self) -> ExecutionStatistics {
         let total_executions = self.execution_history.len() as u64;
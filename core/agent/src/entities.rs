@@ -1,11 +1,13 @@
 //! Agent domain entities
 
-use writemagic_shared::{EntityId, WritemagicError, Result};
+use writemagic_shared::{render_placeholders, EntityId, WritemagicError, Result};
 use chrono::{DateTime, Utc};
+use std::borrow::Cow;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use garde::Validate;
+use crate::value_objects::QuotaLimit;
 
 /// An agent that executes YAML-based workflows and automation tasks
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -165,6 +167,16 @@ pub enum WorkflowAction {
         temperature: Option<f32>,
     },
     
+    /// Draft text with an AI model from a template over the execution variables
+    ///
+    /// The output is appended to `target_document`, or saved as a new document
+    /// when none is given, and stored in the variable named after the step.
+    AiComplete {
+        prompt_template: String,
+        target_document: Option<EntityId>,
+        model: String,
+    },
+    
     /// File operations
     WriteFile {
         path: String,
@@ -192,6 +204,38 @@ pub enum WorkflowAction {
     },
 }
 
+impl WorkflowAction {
+    /// Check the action's own settings
+    pub fn validate(&self) -> Result<()> {
+        if let WorkflowAction::AiComplete { prompt_template, model, .. } = self {
+            if prompt_template.trim().is_empty() {
+                return Err(WritemagicError::validation("AI completion prompt template cannot be empty"));
+            }
+            if model.trim().is_empty() {
+                return Err(WritemagicError::validation("AI completion model cannot be empty"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AgentWorkflow {
+    /// Check the settings of every action the workflow can run
+    pub fn validate_actions(&self) -> Result<()> {
+        for (job_name, job) in &self.jobs {
+            for step in &job.steps {
+                step.action.validate().map_err(|error| {
+                    WritemagicError::validation(format!("Step '{}.{}': {}", job_name, step.id, error.message()))
+                })?;
+            }
+        }
+        for action in self.on_success.iter().chain(&self.on_failure).flatten() {
+            action.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// Agent configuration settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
@@ -268,6 +312,9 @@ pub enum ExecutionResult {
         error: String,
         step_id: Option<String>,
         duration: Duration,
+        /// Quota limit that stopped the execution, if any
+        #[serde(default)]
+        quota_exceeded: Option<QuotaLimit>,
     },
     Cancelled {
         reason: String,
@@ -315,6 +362,42 @@ pub struct ExecutionContext {
     pub project_id: Option<EntityId>,
     pub document_id: Option<EntityId>,
     pub environment: ExecutionEnvironment,
+    /// Outputs of the steps run so far, keyed by `job.step`
+    #[serde(default)]
+    pub step_outputs: BTreeMap<String, serde_json::Value>,
+    /// Resources spent so far, checked against the execution quota
+    #[serde(default)]
+    pub usage: ExecutionUsage,
+}
+
+/// Cumulative work done by an execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionUsage {
+    pub actions_executed: u32,
+    pub ai_tokens_used: u64,
+}
+
+impl ExecutionContext {
+    /// Substitute `{{name}}` placeholders with execution variables
+    ///
+    /// String variables are inserted as is and others as JSON; placeholders
+    /// naming no variable are left untouched.
+    pub fn render_template(&self, template: &str) -> String {
+        render_placeholders(template, |name| {
+            self.variables.get(name).map(|value| match value {
+                serde_json::Value::String(text) => Cow::Borrowed(text.as_str()),
+                value => Cow::Owned(value.to_string()),
+            })
+        })
+    }
+}
+
+impl ExecutionUsage {
+    /// Account for one completed action
+    pub fn record_action(&mut self, tokens_used: u64) {
+        self.actions_executed += 1;
+        self.ai_tokens_used += tokens_used;
+    }
 }
 
 /// Environment for agent execution
@@ -413,7 +496,7 @@ impl Agent {
             }
         }
         
-        Ok(())
+        workflow.validate_actions()
     }
     
     /// Check if the agent can be triggered by the given conditions
@@ -604,6 +687,53 @@ mod tests {
         assert!(agent.is_active);
     }
     
+    #[test]
+    fn test_ai_complete_requires_template_and_model() {
+        let complete = |prompt_template: &str, model: &str| WorkflowAction::AiComplete {
+            prompt_template: prompt_template.to_string(),
+            target_document: None,
+            model: model.to_string(),
+        };
+        assert!(complete("Summarize {{document}}", "claude-3-haiku").validate().is_ok());
+        assert!(complete("  ", "claude-3-haiku").validate().is_err());
+        assert!(complete("Summarize", "").validate().is_err());
+
+        let workflow = AgentWorkflow {
+            version: "1.0".to_string(),
+            name: "Drafts".to_string(),
+            description: None,
+            triggers: vec![WorkflowTrigger { trigger_type: TriggerType::Manual, conditions: vec![], schedule: None }],
+            variables: BTreeMap::new(),
+            jobs: BTreeMap::new(),
+            on_success: Some(vec![complete("Summarize", "")]),
+            on_failure: None,
+        };
+        let agent = Agent::new("Drafter".to_string(), workflow.clone(), EntityId::new());
+        assert!(agent.validate_workflow(&workflow).is_err());
+    }
+
+    #[test]
+    fn test_render_template_substitutes_known_variables() {
+        let context = ExecutionContext {
+            execution_id: EntityId::new(),
+            agent_id: EntityId::new(),
+            trigger: WorkflowTrigger { trigger_type: TriggerType::Manual, conditions: vec![], schedule: None },
+            variables: BTreeMap::from([("topic".to_string(), json!("owls")), ("count".to_string(), json!(3))]),
+            started_at: Utc::now(),
+            user_id: None,
+            project_id: None,
+            document_id: None,
+            environment: ExecutionEnvironment::Development,
+            step_outputs: BTreeMap::new(),
+            usage: ExecutionUsage::default(),
+        };
+
+        assert_eq!(
+            context.render_template("{{count}} facts about {{ topic }}, {{unknown}} and {{unclosed"),
+            "3 facts about owls, {{unknown}} and {{unclosed"
+        );
+    }
+
     #[test]
     fn test_trigger_evaluation() {
         let mut context = BTreeMap::new();
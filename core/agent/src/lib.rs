@@ -12,7 +12,10 @@ pub mod repositories;
 
 // Re-export main types for convenience
 pub use entities::{Agent, AgentWorkflow, ExecutionContext, ExecutionResult, TriggerType, WorkflowAction};
-pub use value_objects::{ExecutionPriority, ExecutionStrategy, ResourceQuota, AgentVersion};
+pub use value_objects::{ExecutionPriority, ExecutionStrategy, QuotaLimit, ResourceQuota, AgentVersion};
 pub use aggregates::{AgentAggregate, QueuedExecution, ExecutionRecord};
-pub use services::{AgentManagementService, AgentExecutionService, AgentOrchestrationService};
+pub use services::{
+    AgentManagementService, AgentExecutionService, AgentOrchestrationService, ActionOutcome, WorkflowActionExecutor,
+    AgentCompletion, AgentCompletionProvider, AgentDocumentWriter,
+};
 pub use repositories::{AgentRepository, AgentWorkflowRepository, ExecutionRepository};
\ No newline at end of file
@@ -2,9 +2,10 @@
 
 use writemagic_shared::{EntityId, WritemagicError, Result};
 use crate::aggregates::{AgentAggregate, QueuedExecution, ExecutionStatistics, ResourceUsage};
-use crate::entities::{Agent, AgentWorkflow, ExecutionContext, ExecutionResult, TriggerType, AgentStatus};
+use crate::entities::{Agent, AgentWorkflow, ExecutionContext, ExecutionResult, TriggerType, AgentStatus, WorkflowAction, WorkflowJob, WorkflowStep};
 use crate::repositories::{AgentRepository, AgentWorkflowRepository, ExecutionRepository, AgentSearchCriteria, WorkflowSearchCriteria};
-use crate::value_objects::{ExecutionPriority, ExecutionStrategy, WorkflowValidation};
+use crate::value_objects::{ExecutionPriority, ExecutionStrategy, QuotaLimit, ResourceQuota, WorkflowValidation};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use std::time::Duration;
 use serde_json::Value;
@@ -211,13 +212,93 @@ impl AgentManagementService {
             return Err(WritemagicError::validation("Workflow must have at least one trigger"));
         }
         
-        Ok(())
+        workflow.validate_actions()
     }
 }
 
 /// Type alias for running agents map to reduce complexity
 type RunningAgents = Arc<RwLock<HashMap<EntityId, Arc<Mutex<AgentAggregate>>>>>;
 
+/// Result of running a single workflow action
+#[derive(Debug, Clone, Default)]
+pub struct ActionOutcome {
+    pub output: Value,
+    /// AI tokens spent by the action
+    pub tokens_used: u64,
+}
+
+/// Performs the actions of workflow steps
+#[async_trait]
+pub trait WorkflowActionExecutor: Send + Sync {
+    /// Perform `action`, spending at most `max_tokens` AI tokens when set
+    async fn execute(&self, action: &WorkflowAction, context: &ExecutionContext, max_tokens: Option<u64>) -> Result<ActionOutcome>;
+}
+
+/// Executor that waits out `Sleep` actions and has no other side effects
+#[derive(Debug, Default)]
+pub struct NoopActionExecutor;
+
+#[async_trait]
+impl WorkflowActionExecutor for NoopActionExecutor {
+    async fn execute(&self, action: &WorkflowAction, _context: &ExecutionContext, _max_tokens: Option<u64>) -> Result<ActionOutcome> {
+        if let WorkflowAction::Sleep { duration } = action {
+            tokio::time::sleep(*duration).await;
+        }
+        Ok(ActionOutcome::default())
+    }
+}
+
+/// Text produced by an AI completion for a workflow step
+#[derive(Debug, Clone, Default)]
+pub struct AgentCompletion {
+    pub text: String,
+    pub tokens_used: u64,
+}
+
+/// Runs the AI completions of `AiComplete` steps
+#[async_trait]
+pub trait AgentCompletionProvider: Send + Sync {
+    /// Complete `prompt` with `model`, spending at most `max_tokens` when set
+    async fn complete(&self, prompt: &str, model: &str, max_tokens: Option<u64>) -> Result<AgentCompletion>;
+}
+
+/// Stores the output of `AiComplete` steps
+#[async_trait]
+pub trait AgentDocumentWriter: Send + Sync {
+    async fn append(&self, document_id: &EntityId, text: &str) -> Result<()>;
+
+    /// Create a document, returning its ID
+    async fn create(&self, title: &str, content: &str, project_id: Option<EntityId>) -> Result<EntityId>;
+}
+
+/// Quota limit hit by an execution
+struct QuotaViolation {
+    limit: QuotaLimit,
+    allowed: String,
+    actual: String,
+}
+
+/// Why a workflow stopped before running all of its steps
+struct StepFailure {
+    error: String,
+    step_id: Option<String>,
+    quota_exceeded: Option<QuotaViolation>,
+}
+
+impl StepFailure {
+    fn error(step_id: Option<String>, error: WritemagicError) -> Self {
+        Self { error: error.to_string(), step_id, quota_exceeded: None }
+    }
+
+    fn quota(limit: QuotaLimit, step_id: Option<String>, allowed: String, actual: String) -> Self {
+        Self {
+            error: format!("Resource quota exceeded: {} limit of {} reached", limit, allowed),
+            step_id,
+            quota_exceeded: Some(QuotaViolation { limit, allowed, actual }),
+        }
+    }
+}
+
 /// Service for executing agent workflows
 pub struct AgentExecutionService {
     agent_repository: Arc<dyn AgentRepository>,
@@ -226,6 +307,11 @@ pub struct AgentExecutionService {
     running_agents: RunningAgents,
     #[allow(dead_code)] // TODO: Implement execution queue processing in Phase 2
     execution_queue: Arc<Mutex<VecDeque<QueuedExecution>>>,
+    action_executor: Arc<dyn WorkflowActionExecutor>,
+    completions: Option<Arc<dyn AgentCompletionProvider>>,
+    documents: Option<Arc<dyn AgentDocumentWriter>>,
+    quota: ResourceQuota,
+    user_quotas: HashMap<EntityId, ResourceQuota>,
 }
 
 impl AgentExecutionService {
@@ -240,9 +326,52 @@ impl AgentExecutionService {
             execution_repository,
             running_agents,
             execution_queue: Arc::new(Mutex::new(VecDeque::new())),
+            action_executor: Arc::new(NoopActionExecutor),
+            completions: None,
+            documents: None,
+            quota: ResourceQuota::unlimited(),
+            user_quotas: HashMap::new(),
         }
     }
     
+    /// Use `executor` to perform workflow actions
+    pub fn with_action_executor(mut self, executor: Arc<dyn WorkflowActionExecutor>) -> Self {
+        self.action_executor = executor;
+        self
+    }
+    
+    /// Run `AiComplete` steps with `completions`, saving their output through `documents`
+    pub fn with_ai_completion(
+        mut self,
+        completions: Arc<dyn AgentCompletionProvider>,
+        documents: Arc<dyn AgentDocumentWriter>,
+    ) -> Self {
+        self.completions = Some(completions);
+        self.documents = Some(documents);
+        self
+    }
+    
+    /// Limit every execution to `quota`, on top of the agent's own execution timeout
+    ///
+    /// Applies to the agents of users without a quota of their own.
+    pub fn with_quota(mut self, quota: ResourceQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+    
+    /// Limit executions of agents owned by `user_id` to `quota` instead of the default
+    pub fn with_user_quota(mut self, user_id: EntityId, quota: ResourceQuota) -> Self {
+        self.user_quotas.insert(user_id, quota);
+        self
+    }
+    
+    /// Quota for an execution on behalf of `user_id`
+    fn quota_for(&self, user_id: Option<&EntityId>) -> &ResourceQuota {
+        user_id
+            .and_then(|user_id| self.user_quotas.get(user_id))
+            .unwrap_or(&self.quota)
+    }
+    
     /// Trigger agent execution
     pub async fn trigger_execution(
         &self,
@@ -288,25 +417,51 @@ impl AgentExecutionService {
         let agent_mutex = self.get_running_agent(&agent_id).await
             .ok_or_else(|| WritemagicError::not_found(format!("Running agent not found: {}", agent_id)))?;
         
-        let mut agent = agent_mutex.lock().await;
-        
-        // Start execution
-        let context = agent.start_execution(&execution)?;
+        // Start execution, releasing the agent while its steps run
+        let (mut context, workflow, agent_timeout) = {
+            let mut agent = agent_mutex.lock().await;
+            let context = agent.start_execution(&execution)?;
+            self.agent_repository.save(&mut agent).await?;
+            (context, agent.agent().workflow.clone(), agent.agent().config.execution_timeout)
+        };
+        let quota = self.quota_for(context.user_id.as_ref());
+        let time_limit = quota.max_execution_time()
+            .map_or(agent_timeout, |limit| limit.min(agent_timeout));
         let start_time = Utc::now();
         
-        // Execute workflow steps
-        let result = self.execute_workflow_steps(&context).await;
+        // Execute workflow steps, abandoning them once the time limit passes
+        let mut current_step = None;
+        let outcome = tokio::time::timeout(
+            time_limit,
+            self.execute_workflow_steps(&workflow, quota, &mut context, &mut current_step),
+        ).await;
         let end_time = Utc::now();
         let duration = (end_time - start_time).to_std().unwrap_or(std::time::Duration::from_secs(0));
         
+        let result = outcome.unwrap_or_else(|_| Err(StepFailure::quota(
+            QuotaLimit::ExecutionTime,
+            current_step,
+            format!("{}ms", time_limit.as_millis()),
+            format!("{}ms", duration.as_millis()),
+        )));
+        
+        let mut agent = agent_mutex.lock().await;
+        
         // Create execution result
         let execution_result = match result {
-            Ok(outputs) => ExecutionResult::Success { duration, outputs },
-            Err(error) => ExecutionResult::Failure {
-                error: error.to_string(),
-                step_id: None, // Would be set during step execution
-                duration,
-            },
+            Ok(()) => ExecutionResult::Success { duration, outputs: context.step_outputs.clone() },
+            Err(failure) => {
+                let quota_exceeded = failure.quota_exceeded.map(|violation| {
+                    agent.record_limit_exceeded(violation.limit, violation.allowed, violation.actual);
+                    violation.limit
+                });
+                ExecutionResult::Failure {
+                    error: failure.error,
+                    step_id: failure.step_id,
+                    duration,
+                    quota_exceeded,
+                }
+            }
         };
         
         // Record execution completion
@@ -318,7 +473,7 @@ impl AgentExecutionService {
             duration,
         };
         
-        agent.complete_execution(execution.id, execution_result.clone(), resource_usage)?;
+        agent.complete_execution(&context, execution_result.clone(), resource_usage)?;
         
         // Save updated agent state
         self.agent_repository.save(&mut agent).await?;
@@ -326,23 +481,106 @@ impl AgentExecutionService {
         Ok(execution_result)
     }
     
-    /// Execute workflow steps
+    /// Execute the steps of every job under `quota`, recording outputs and usage in `context`
+    ///
+    /// `current_step` names the step in progress so a timed out run can report it.
+    /// Each action is given what is left of the token quota and none is
+    /// started once it is spent.
     async fn execute_workflow_steps(
         &self,
-        _context: &ExecutionContext,
-    ) -> Result<BTreeMap<String, Value>> {
-        let outputs = BTreeMap::new();
+        workflow: &AgentWorkflow,
+        quota: &ResourceQuota,
+        context: &mut ExecutionContext,
+        current_step: &mut Option<String>,
+    ) -> std::result::Result<(), StepFailure> {
+        let jobs = job_order(workflow).map_err(|error| StepFailure::error(None, error))?;
+        
+        for (job_name, job) in jobs {
+            for step in &job.steps {
+                let step_id = format!("{}.{}", job_name, step.id);
+                *current_step = Some(step_id.clone());
+                
+                if let Some(max_actions) = quota.max_actions() {
+                    if context.usage.actions_executed >= max_actions {
+                        return Err(StepFailure::quota(
+                            QuotaLimit::Actions,
+                            Some(step_id),
+                            max_actions.to_string(),
+                            (context.usage.actions_executed + 1).to_string(),
+                        ));
+                    }
+                }
+                
+                // Cap the action at what is left of the token quota
+                let max_tokens = match quota.max_ai_tokens() {
+                    Some(max_tokens) if context.usage.ai_tokens_used >= max_tokens => {
+                        return Err(StepFailure::quota(
+                            QuotaLimit::AiTokens,
+                            Some(step_id),
+                            max_tokens.to_string(),
+                            context.usage.ai_tokens_used.to_string(),
+                        ));
+                    }
+                    Some(max_tokens) => Some(max_tokens - context.usage.ai_tokens_used),
+                    None => None,
+                };
+                
+                let outcome = match &step.action {
+                    WorkflowAction::AiComplete { prompt_template, target_document, model } => {
+                        self.run_ai_completion(step, prompt_template, target_document.as_ref(), model, max_tokens, context).await
+                    }
+                    action => self.action_executor.execute(action, context, max_tokens).await,
+                }
+                .map_err(|error| StepFailure::error(Some(step_id.clone()), error))?;
+                context.usage.record_action(outcome.tokens_used);
+                context.step_outputs.insert(step_id.clone(), outcome.output);
+                
+                // An action that overspent its allowance still stops the run
+                if let Some(max_tokens) = quota.max_ai_tokens() {
+                    if context.usage.ai_tokens_used > max_tokens {
+                        return Err(StepFailure::quota(
+                            QuotaLimit::AiTokens,
+                            Some(step_id),
+                            max_tokens.to_string(),
+                            context.usage.ai_tokens_used.to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Run an `AiComplete` step and save its output, storing the text in the variable named after the step
+    async fn run_ai_completion(
+        &self,
+        step: &WorkflowStep,
+        prompt_template: &str,
+        target_document: Option<&EntityId>,
+        model: &str,
+        max_tokens: Option<u64>,
+        context: &mut ExecutionContext,
+    ) -> Result<ActionOutcome> {
+        let (Some(completions), Some(documents)) = (&self.completions, &self.documents) else {
+            return Err(WritemagicError::configuration("AI completion is not configured for agent executions"));
+        };
         
-        // For now, just return success with empty outputs
-        // In a real implementation, this would:
-        // 1. Get the workflow from the agent
-        // 2. Execute each job in the workflow
-        // 3. Handle dependencies between jobs
-        // 4. Execute steps within each job
-        // 5. Handle workflow variables and outputs
-        // 6. Implement retry logic and error handling
+        let prompt = context.render_template(prompt_template);
+        let completion = completions.complete(&prompt, model, max_tokens).await?;
+        let document_id = match target_document {
+            Some(document_id) => {
+                documents.append(document_id, &completion.text).await?;
+                *document_id
+            }
+            None => documents.create(&step.name, &completion.text, context.project_id).await?,
+        };
         
-        Ok(outputs)
+        context.variables.insert(step.id.clone(), Value::String(completion.text.clone()));
+        Ok(ActionOutcome {
+            output: serde_json::json!({ "text": completion.text, "document_id": document_id.to_string() }),
+            tokens_used: completion.tokens_used,
+        })
     }
     
     /// Find the next execution to process
@@ -387,6 +625,24 @@ impl AgentExecutionService {
     }
 }
 
+/// Jobs of `workflow` ordered so each runs after the jobs it depends on
+fn job_order(workflow: &AgentWorkflow) -> Result<Vec<(&String, &WorkflowJob)>> {
+    let mut ordered: Vec<(&String, &WorkflowJob)> = Vec::with_capacity(workflow.jobs.len());
+    
+    while ordered.len() < workflow.jobs.len() {
+        let ready: Vec<(&String, &WorkflowJob)> = workflow.jobs.iter()
+            .filter(|(name, _)| !ordered.iter().any(|(done, _)| done == name))
+            .filter(|(_, job)| job.depends_on.iter().all(|dependency| ordered.iter().any(|(done, _)| *done == dependency)))
+            .collect();
+        if ready.is_empty() {
+            return Err(WritemagicError::validation("Workflow jobs have missing or circular dependencies"));
+        }
+        ordered.extend(ready);
+    }
+    
+    Ok(ordered)
+}
+
 /// Service for managing agent workflows and templates
 pub struct AgentWorkflowService {
     workflow_repository: Arc<dyn AgentWorkflowRepository>,
@@ -572,9 +828,366 @@ pub struct ComprehensiveSystemStatus {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::entities::{WorkflowTrigger, TriggerType};
+    use crate::aggregates::AgentEvent;
+    use crate::entities::{WorkflowStep, WorkflowTrigger, TriggerType};
     use crate::repositories::SqliteAgentRepository;
     use std::collections::BTreeMap;
+    
+    /// Agent repository that accepts every save
+    struct MemoryAgentRepository;
+    
+    #[async_trait]
+    impl AgentRepository for MemoryAgentRepository {
+        async fn save(&self, _aggregate: &mut AgentAggregate) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn load(&self, _agent_id: &EntityId) -> Result<Option<AgentAggregate>> {
+            Ok(None)
+        }
+        
+        async fn delete(&self, _agent_id: &EntityId) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn find_by_criteria(&self, _criteria: AgentSearchCriteria) -> Result<Vec<Agent>> {
+            Ok(Vec::new())
+        }
+        
+        async fn list_active(&self) -> Result<Vec<Agent>> {
+            Ok(Vec::new())
+        }
+        
+        async fn count_by_status(&self) -> Result<HashMap<String, u64>> {
+            Ok(HashMap::new())
+        }
+        
+        async fn find_by_workflow_version(&self, _version: &str) -> Result<Vec<Agent>> {
+            Ok(Vec::new())
+        }
+    }
+    
+    /// Executor that spends the same number of AI tokens on every action, within its allowance
+    struct TokenSpendingExecutor {
+        tokens_per_action: u64,
+    }
+    
+    #[async_trait]
+    impl WorkflowActionExecutor for TokenSpendingExecutor {
+        async fn execute(&self, _action: &WorkflowAction, context: &ExecutionContext, max_tokens: Option<u64>) -> Result<ActionOutcome> {
+            Ok(ActionOutcome {
+                output: Value::from(context.usage.actions_executed),
+                tokens_used: max_tokens.map_or(self.tokens_per_action, |max| max.min(self.tokens_per_action)),
+            })
+        }
+    }
+    
+    fn step(id: &str, name: &str, action: WorkflowAction) -> WorkflowStep {
+        WorkflowStep {
+            id: id.to_string(),
+            name: name.to_string(),
+            action,
+            if_condition: None,
+            with: None,
+            env: None,
+        }
+    }
+    
+    /// Run one execution of an agent that repeats `action` twenty times
+    async fn run_runaway_agent(
+        action: WorkflowAction,
+        service: impl FnOnce(AgentExecutionService) -> AgentExecutionService,
+    ) -> (ExecutionResult, AgentAggregate) {
+        let steps = (0..20)
+            .map(|index| step(&format!("step-{}", index), &format!("Step {}", index), action.clone()))
+            .collect();
+        run_agent(steps, BTreeMap::new(), service).await
+    }
+    
+    /// User that the agents run by [`run_agent`] belong to
+    fn agent_owner() -> EntityId {
+        EntityId::seeded(1)
+    }
+    
+    /// Start an agent of [`agent_owner`] whose single `loop` job runs `steps`
+    async fn running_agent(steps: Vec<WorkflowStep>) -> (EntityId, RunningAgents) {
+        let job = WorkflowJob {
+            name: "loop".to_string(),
+            description: None,
+            depends_on: vec![],
+            if_condition: None,
+            timeout: None,
+            retry: None,
+            steps,
+        };
+        let workflow = AgentWorkflow {
+            version: "1.0".to_string(),
+            name: "Runaway".to_string(),
+            description: None,
+            triggers: vec![WorkflowTrigger {
+                trigger_type: TriggerType::Manual,
+                conditions: vec![],
+                schedule: None,
+            }],
+            variables: BTreeMap::new(),
+            jobs: BTreeMap::from([("loop".to_string(), job)]),
+            on_success: None,
+            on_failure: None,
+        };
+        
+        let aggregate = AgentAggregate::new("Runaway".to_string(), workflow, agent_owner()).unwrap();
+        let agent_id = aggregate.id();
+        let running_agents: RunningAgents = Arc::new(RwLock::new(HashMap::new()));
+        running_agents.write().await.insert(agent_id, Arc::new(Mutex::new(aggregate)));
+        (agent_id, running_agents)
+    }
+    
+    /// Run one execution of an agent whose single `loop` job runs `steps`
+    async fn run_agent(
+        steps: Vec<WorkflowStep>,
+        variables: BTreeMap<String, Value>,
+        service: impl FnOnce(AgentExecutionService) -> AgentExecutionService,
+    ) -> (ExecutionResult, AgentAggregate) {
+        let (agent_id, running_agents) = running_agent(steps).await;
+        let service = service(AgentExecutionService::new(
+            Arc::new(MemoryAgentRepository),
+            Arc::new(crate::repositories::SqliteExecutionRepository::new()),
+            running_agents.clone(),
+        ));
+        service
+            .trigger_execution(&agent_id, TriggerType::Manual, variables, ExecutionPriority::Normal, None)
+            .await
+            .unwrap();
+        let result = service.execute_next().await.unwrap().unwrap();
+        
+        let agent = running_agents.read().await[&agent_id].lock().await.clone();
+        (result, agent)
+    }
+    
+    #[tokio::test]
+    async fn test_runaway_agent_stopped_by_token_quota() {
+        let action = WorkflowAction::AIGenerate {
+            prompt: "Keep going".to_string(),
+            provider: None,
+            max_tokens: None,
+            temperature: None,
+        };
+        let quota = ResourceQuota::unlimited().with_token_limit(100).unwrap();
+        let (result, agent) = run_runaway_agent(action, |service| {
+            service
+                .with_quota(quota)
+                .with_action_executor(Arc::new(TokenSpendingExecutor { tokens_per_action: 40 }))
+        })
+        .await;
+        
+        // The third action gets the last 20 tokens and the fourth is never started
+        match result {
+            ExecutionResult::Failure { step_id, quota_exceeded, .. } => {
+                assert_eq!(step_id.as_deref(), Some("loop.step-3"));
+                assert_eq!(quota_exceeded, Some(QuotaLimit::AiTokens));
+            }
+            other => panic!("expected the quota to stop the agent, got {:?}", other),
+        }
+        
+        // The partial run is kept in the execution history
+        let record = agent.execution_history().last().unwrap();
+        assert_eq!(record.usage.actions_executed, 3);
+        assert_eq!(record.usage.ai_tokens_used, 100);
+        assert_eq!(record.step_outputs.keys().collect::<Vec<_>>(), ["loop.step-0", "loop.step-1", "loop.step-2"]);
+        assert!(agent.events().iter().any(|event| matches!(event, AgentEvent::ResourceLimitExceeded { .. })));
+        assert_eq!(agent.agent().state.status, AgentStatus::Active);
+    }
+    
+    #[tokio::test]
+    async fn test_execution_quota_limits_actions_and_time() {
+        let action = WorkflowAction::Sleep { duration: Duration::from_millis(0) };
+        let quota = ResourceQuota::unlimited().with_action_limit(5).unwrap();
+        let (result, agent) = run_runaway_agent(action, |service| service.with_quota(quota)).await;
+        assert!(matches!(
+            result,
+            ExecutionResult::Failure { quota_exceeded: Some(QuotaLimit::Actions), step_id: Some(ref step), .. } if step == "loop.step-5"
+        ));
+        assert_eq!(agent.execution_history().last().unwrap().usage.actions_executed, 5);
+        
+        let action = WorkflowAction::Sleep { duration: Duration::from_secs(3600) };
+        let quota = ResourceQuota::unlimited().with_time_limit(Duration::from_millis(50)).unwrap();
+        let (result, agent) = run_runaway_agent(action, |service| service.with_quota(quota)).await;
+        assert!(matches!(
+            result,
+            ExecutionResult::Failure { quota_exceeded: Some(QuotaLimit::ExecutionTime), step_id: Some(ref step), .. } if step == "loop.step-0"
+        ));
+        assert!(agent.execution_history().last().unwrap().step_outputs.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_quota_of_the_agent_owner_overrides_the_default() {
+        let action = WorkflowAction::Sleep { duration: Duration::from_millis(0) };
+        let default_quota = ResourceQuota::unlimited().with_action_limit(5).unwrap();
+        let owner_quota = ResourceQuota::unlimited().with_action_limit(2).unwrap();
+        let other_quota = ResourceQuota::unlimited().with_action_limit(1).unwrap();
+        let (result, agent) = run_runaway_agent(action, |service| {
+            service
+                .with_quota(default_quota)
+                .with_user_quota(agent_owner(), owner_quota)
+                .with_user_quota(EntityId::seeded(2), other_quota)
+        })
+        .await;
+        assert!(matches!(
+            result,
+            ExecutionResult::Failure { quota_exceeded: Some(QuotaLimit::Actions), step_id: Some(ref step), .. } if step == "loop.step-2"
+        ));
+        assert_eq!(agent.execution_history().last().unwrap().usage.actions_executed, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_agent_is_not_locked_while_its_steps_run() {
+        let action = WorkflowAction::Sleep { duration: Duration::from_millis(200) };
+        let (agent_id, running_agents) = running_agent(vec![step("wait", "Wait", action)]).await;
+        let service = Arc::new(AgentExecutionService::new(
+            Arc::new(MemoryAgentRepository),
+            Arc::new(crate::repositories::SqliteExecutionRepository::new()),
+            running_agents.clone(),
+        ));
+        service
+            .trigger_execution(&agent_id, TriggerType::Manual, BTreeMap::new(), ExecutionPriority::Normal, None)
+            .await
+            .unwrap();
+        
+        let running = tokio::spawn({
+            let service = service.clone();
+            async move { service.execute_next().await }
+        });
+        while running_agents.read().await[&agent_id].lock().await.agent().state.status != AgentStatus::Running {
+            tokio::task::yield_now().await;
+        }
+        
+        // Queueing another execution does not wait for the running one
+        let queued = tokio::time::timeout(
+            Duration::from_millis(100),
+            service.trigger_execution(&agent_id, TriggerType::Manual, BTreeMap::new(), ExecutionPriority::Normal, None),
+        )
+        .await;
+        assert!(queued.is_ok());
+        assert!(matches!(running.await.unwrap().unwrap(), Some(ExecutionResult::Success { .. })));
+    }
+
+    /// Completion provider that echoes the prompt, recording the token budget it was given
+    #[derive(Default)]
+    struct EchoCompletions {
+        budgets: std::sync::Mutex<Vec<Option<u64>>>,
+    }
+    
+    #[async_trait]
+    impl AgentCompletionProvider for EchoCompletions {
+        async fn complete(&self, prompt: &str, model: &str, max_tokens: Option<u64>) -> Result<AgentCompletion> {
+            self.budgets.lock().unwrap().push(max_tokens);
+            Ok(AgentCompletion { text: format!("[{}] {}", model, prompt), tokens_used: 30 })
+        }
+    }
+    
+    /// Document writer that keeps created documents and appended text in memory
+    #[derive(Default)]
+    struct RecordingDocuments {
+        created: std::sync::Mutex<Vec<(EntityId, String, String)>>,
+        appended: std::sync::Mutex<Vec<(EntityId, String)>>,
+    }
+    
+    #[async_trait]
+    impl AgentDocumentWriter for RecordingDocuments {
+        async fn append(&self, document_id: &EntityId, text: &str) -> Result<()> {
+            self.appended.lock().unwrap().push((*document_id, text.to_string()));
+            Ok(())
+        }
+        
+        async fn create(&self, title: &str, content: &str, _project_id: Option<EntityId>) -> Result<EntityId> {
+            let id = EntityId::new();
+            self.created.lock().unwrap().push((id, title.to_string(), content.to_string()));
+            Ok(id)
+        }
+    }
+    
+    /// Executor that saves `WriteFile` actions, rendered against the execution variables
+    #[derive(Default)]
+    struct RecordingFiles {
+        written: std::sync::Mutex<Vec<(String, String)>>,
+    }
+    
+    #[async_trait]
+    impl WorkflowActionExecutor for RecordingFiles {
+        async fn execute(&self, action: &WorkflowAction, context: &ExecutionContext, _max_tokens: Option<u64>) -> Result<ActionOutcome> {
+            if let WorkflowAction::WriteFile { path, content, .. } = action {
+                self.written.lock().unwrap().push((context.render_template(path), context.render_template(content)));
+            }
+            Ok(ActionOutcome::default())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_ai_completion_output_flows_into_the_next_step() {
+        let completions = Arc::new(EchoCompletions::default());
+        let documents = Arc::new(RecordingDocuments::default());
+        let files = Arc::new(RecordingFiles::default());
+        let chapter = EntityId::new();
+        let steps = vec![
+            step("draft", "Owl draft", WorkflowAction::AiComplete {
+                prompt_template: "Write about {{ topic }}".to_string(),
+                target_document: None,
+                model: "drafting-model".to_string(),
+            }),
+            step("extend", "Extend chapter", WorkflowAction::AiComplete {
+                prompt_template: "Continue: {{draft}}".to_string(),
+                target_document: Some(chapter),
+                model: "drafting-model".to_string(),
+            }),
+            step("save", "Save draft", WorkflowAction::WriteFile {
+                path: "drafts/{{topic}}.md".to_string(),
+                content: "{{draft}}".to_string(),
+                append: None,
+            }),
+        ];
+        let variables = BTreeMap::from([("topic".to_string(), Value::from("owls"))]);
+        
+        let quota = ResourceQuota::unlimited().with_token_limit(100).unwrap();
+        let (result, _) = run_agent(steps, variables, |service| {
+            service
+                .with_quota(quota)
+                .with_action_executor(files.clone())
+                .with_ai_completion(completions.clone(), documents.clone())
+        })
+        .await;
+        
+        let ExecutionResult::Success { outputs, .. } = result else {
+            panic!("expected the workflow to succeed, got {:?}", result);
+        };
+        let created = documents.created.lock().unwrap().clone();
+        assert_eq!(created.len(), 1);
+        assert_eq!((created[0].1.as_str(), created[0].2.as_str()), ("Owl draft", "[drafting-model] Write about owls"));
+        assert_eq!(outputs["loop.draft"]["document_id"], Value::from(created[0].0.to_string()));
+        assert_eq!(
+            documents.appended.lock().unwrap().clone(),
+            vec![(chapter, "[drafting-model] Continue: [drafting-model] Write about owls".to_string())]
+        );
+        assert_eq!(
+            files.written.lock().unwrap().clone(),
+            vec![("drafts/owls.md".to_string(), "[drafting-model] Write about owls".to_string())]
+        );
+        // Each completion is capped at what is left of the token quota
+        assert_eq!(completions.budgets.lock().unwrap().clone(), vec![Some(100), Some(70)]);
+    }
+    
+    #[tokio::test]
+    async fn test_ai_completion_needs_a_provider() {
+        let action = WorkflowAction::AiComplete {
+            prompt_template: "Write".to_string(),
+            target_document: None,
+            model: "drafting-model".to_string(),
+        };
+        let (result, _) = run_agent(vec![step("draft", "Draft", action)], BTreeMap::new(), |service| service).await;
+        assert!(matches!(
+            result,
+            ExecutionResult::Failure { step_id: Some(ref step), quota_exceeded: None, .. } if step == "loop.draft"
+        ));
+    }
 
     #[tokio::test]
     async fn test_agent_management_service() {
@@ -300,6 +300,10 @@ pub struct ResourceQuota {
     max_disk_io_mbps: Option<u64>,
     max_network_io_mbps: Option<u64>,
     max_execution_time: Option<Duration>,
+    #[serde(default)]
+    max_actions: Option<u32>,
+    #[serde(default)]
+    max_ai_tokens: Option<u64>,
 }
 
 impl ResourceQuota {
@@ -311,6 +315,8 @@ impl ResourceQuota {
             max_disk_io_mbps: None,
             max_network_io_mbps: None,
             max_execution_time: None,
+            max_actions: None,
+            max_ai_tokens: None,
         }
     }
     
@@ -322,6 +328,8 @@ impl ResourceQuota {
             max_disk_io_mbps: Some(10),
             max_network_io_mbps: Some(5),
             max_execution_time: Some(Duration::from_secs(300)), // 5 minutes
+            max_actions: Some(100),
+            max_ai_tokens: Some(50_000),
         }
     }
     
@@ -333,6 +341,8 @@ impl ResourceQuota {
             max_disk_io_mbps: Some(50),
             max_network_io_mbps: Some(25),
             max_execution_time: Some(Duration::from_secs(1800)), // 30 minutes
+            max_actions: Some(1000),
+            max_ai_tokens: Some(500_000),
         }
     }
     
@@ -363,6 +373,39 @@ impl ResourceQuota {
         Ok(self)
     }
     
+    /// Set the number of workflow actions a single execution may run
+    pub fn with_action_limit(mut self, actions: u32) -> Result<Self> {
+        if actions == 0 {
+            return Err(WritemagicError::validation("Action limit must be greater than 0"));
+        }
+        self.max_actions = Some(actions);
+        Ok(self)
+    }
+    
+    /// Set the number of AI tokens a single execution may spend
+    pub fn with_token_limit(mut self, tokens: u64) -> Result<Self> {
+        if tokens == 0 {
+            return Err(WritemagicError::validation("Token limit must be greater than 0"));
+        }
+        self.max_ai_tokens = Some(tokens);
+        Ok(self)
+    }
+    
+    /// Wall-clock time allowed per execution
+    pub fn max_execution_time(&self) -> Option<Duration> {
+        self.max_execution_time
+    }
+    
+    /// Workflow actions allowed per execution
+    pub fn max_actions(&self) -> Option<u32> {
+        self.max_actions
+    }
+    
+    /// AI tokens allowed per execution
+    pub fn max_ai_tokens(&self) -> Option<u64> {
+        self.max_ai_tokens
+    }
+    
     /// Check if quota allows the given resource usage
     pub fn allows_usage(&self, cpu: f32, memory_mb: u64, duration: Duration) -> bool {
         if let Some(max_cpu) = self.max_cpu_cores {
@@ -387,6 +430,24 @@ impl ResourceQuota {
     }
 }
 
+/// Per-execution limit of a [`ResourceQuota`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuotaLimit {
+    ExecutionTime,
+    Actions,
+    AiTokens,
+}
+
+impl fmt::Display for QuotaLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaLimit::ExecutionTime => write!(f, "execution time"),
+            QuotaLimit::Actions => write!(f, "actions"),
+            QuotaLimit::AiTokens => write!(f, "AI tokens"),
+        }
+    }
+}
+
 /// Agent permission level for security
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PermissionLevel {
@@ -585,6 +646,14 @@ mod tests {
             .with_memory_limit(512).unwrap();
         assert!(custom.allows_usage(0.8, 400, Duration::from_secs(3600)));
         assert!(!custom.allows_usage(1.2, 400, Duration::from_secs(3600)));
+        
+        let execution = ResourceQuota::unlimited()
+            .with_action_limit(3).unwrap()
+            .with_token_limit(100).unwrap();
+        assert_eq!(execution.max_actions(), Some(3));
+        assert_eq!(execution.max_ai_tokens(), Some(100));
+        assert!(ResourceQuota::unlimited().with_action_limit(0).is_err());
+        assert!(ResourceQuota::unlimited().with_token_limit(0).is_err());
     }
     
     #[test]
@@ -16,6 +16,25 @@ pub enum CircuitState {
     HalfOpen { attempts: usize },
 }
 
+impl CircuitState {
+    /// Short lowercase label, e.g. for status displays
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open { .. } => "open",
+            Self::HalfOpen { .. } => "half_open",
+        }
+    }
+}
+
+/// Observable status of a circuit breaker
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    /// Time until an open breaker lets a trial request through
+    pub retry_in: Option<Duration>,
+}
+
 /// Circuit breaker configuration
 #[derive(Debug, Clone)]
 pub struct CircuitBreakerConfig {
@@ -159,6 +178,24 @@ impl CircuitBreaker {
 
     /// Execute operation with circuit breaker protection
     pub async fn execute<F, Fut, T, E>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = std::result::Result<T, E>>,
+        E: std::fmt::Display + Send + 'static,
+    {
+        self.execute_mapped(operation, |e| WritemagicError::internal(e.to_string())).await
+    }
+
+    /// Execute operation with circuit breaker protection, passing its error through unchanged
+    pub async fn execute_result<F, Fut, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        self.execute_mapped(operation, |e| e).await
+    }
+
+    async fn execute_mapped<F, Fut, T, E>(&self, operation: F, map_err: impl FnOnce(E) -> WritemagicError) -> Result<T>
     where
         F: FnOnce() -> Fut,
         Fut: std::future::Future<Output = std::result::Result<T, E>>,
@@ -166,10 +203,7 @@ impl CircuitBreaker {
     {
         if !self.can_execute().await {
             self.update_metrics_request_blocked();
-            return Err(WritemagicError::internal(format!(
-                "Circuit breaker '{}' is open",
-                self.name
-            )));
+            return Err(WritemagicError::circuit_open(self.name.clone()));
         }
 
         let start = Instant::now();
@@ -197,8 +231,12 @@ impl CircuitBreaker {
                 Ok(value)
             }
             Ok(Err(e)) => {
-                self.record_failure(duration, Some(e.to_string())).await;
-                Err(WritemagicError::internal(e.to_string()))
+                let error = map_err(e);
+                // A rejected request says nothing about the provider's health
+                if !error.is_request_rejection() {
+                    self.record_failure(duration, Some(error.to_string())).await;
+                }
+                Err(error)
             }
             Err(_) => {
                 self.record_failure(duration, Some("timeout".to_string())).await;
@@ -334,6 +372,16 @@ impl CircuitBreaker {
         self.state.read().clone()
     }
 
+    /// Current state, with the time left before an open breaker half-opens
+    pub fn status(&self) -> CircuitBreakerStatus {
+        let state = self.state();
+        let retry_in = match &state {
+            CircuitState::Open { opened_at } => Some(self.config.timeout.saturating_sub(opened_at.elapsed())),
+            _ => None,
+        };
+        CircuitBreakerStatus { state, retry_in }
+    }
+
     /// Get circuit breaker metrics
     pub fn metrics(&self) -> CircuitMetrics {
         self.metrics.read().clone()
@@ -480,6 +528,15 @@ impl CircuitBreakerRegistry {
             .collect()
     }
 
+    /// Get the status of every circuit breaker
+    pub fn get_all_statuses(&self) -> HashMap<String, CircuitBreakerStatus> {
+        let breakers = self.breakers.read();
+        breakers
+            .iter()
+            .map(|(name, breaker)| (name.clone(), breaker.status()))
+            .collect()
+    }
+
     /// Get all circuit breaker metrics
     pub fn get_all_metrics(&self) -> HashMap<String, CircuitMetrics> {
         let breakers = self.breakers.read();
@@ -489,6 +546,33 @@ impl CircuitBreakerRegistry {
             .collect()
     }
 
+    /// Open a breaker by hand, keeping its provider out of fallback until reset or the breaker's timeout elapses
+    ///
+    /// Returns false when no breaker is registered under `name`.
+    pub fn trip(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(breaker) => {
+                breaker.force_open();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Close a breaker immediately and forget its failure history
+    ///
+    /// Returns false when no breaker is registered under `name`.
+    pub fn reset(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(breaker) => {
+                breaker.force_close();
+                breaker.reset();
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Force open all circuit breakers
     pub fn force_open_all(&self) {
         let breakers = self.breakers.read();
@@ -616,6 +700,41 @@ mod tests {
         assert_eq!(states["test2"], CircuitState::Closed);
     }
 
+    #[tokio::test]
+    async fn test_status_reports_time_until_retry_for_open_breakers() {
+        let registry = CircuitBreakerRegistry::new();
+        let config = CircuitBreakerConfig { timeout: Duration::from_secs(60), ..Default::default() };
+        registry.register("open".to_string(), config).force_open();
+        registry.register("closed".to_string(), CircuitBreakerConfig::default());
+
+        let statuses = registry.get_all_statuses();
+
+        let open = &statuses["open"];
+        assert_eq!(open.state.label(), "open");
+        let retry_in = open.retry_in.expect("open breakers report a retry time");
+        assert!(retry_in > Duration::from_secs(55) && retry_in <= Duration::from_secs(60));
+        assert_eq!(statuses["closed"], CircuitBreakerStatus { state: CircuitState::Closed, retry_in: None });
+    }
+
+    #[tokio::test]
+    async fn test_trip_and_reset_by_name() {
+        let registry = CircuitBreakerRegistry::new();
+        let config = CircuitBreakerConfig { failure_threshold: 1, ..Default::default() };
+        let breaker = registry.register("claude".to_string(), config);
+
+        assert!(registry.trip("claude"));
+        assert!(!breaker.can_execute().await);
+
+        breaker.record_failure(Duration::from_millis(10), Some("error".to_string())).await;
+        assert!(registry.reset("claude"));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.current_failure_rate(), 0.0);
+        assert!(breaker.can_execute().await);
+
+        assert!(!registry.trip("unknown"));
+        assert!(!registry.reset("unknown"));
+    }
+
     #[tokio::test]
     async fn test_failure_rate_calculation() {
         let config = CircuitBreakerConfig {
@@ -0,0 +1,175 @@
+//! Estimated spend on AI providers, and the monthly budget it counts against
+
+use chrono::{DateTime, Datelike, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use writemagic_shared::{Result, WritemagicError};
+use crate::tokenization::{pattern_matches, pattern_specificity, TokenUsage};
+
+/// Price of a model's tokens, in USD
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+}
+
+impl ModelPrice {
+    /// Price from USD per million input and output tokens, the unit providers publish
+    pub fn per_million_tokens(input_usd: f64, output_usd: f64) -> Self {
+        Self {
+            input_cost_per_token: input_usd / 1_000_000.0,
+            output_cost_per_token: output_usd / 1_000_000.0,
+        }
+    }
+
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        usage.input_tokens as f64 * self.input_cost_per_token + usage.output_tokens as f64 * self.output_cost_per_token
+    }
+}
+
+/// Price registered for one provider's models matching `model_pattern`
+struct RegisteredPrice {
+    provider: String,
+    model_pattern: String,
+    price: ModelPrice,
+}
+
+/// Estimates the USD cost of a completion from a provider's price table
+///
+/// Prices are looked up by provider and model name pattern, as tokenizers
+/// are: the most specific matching pattern wins, and among equally specific
+/// ones the latest set.
+pub struct CostEstimator {
+    prices: RwLock<Vec<RegisteredPrice>>,
+}
+
+impl Default for CostEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CostEstimator {
+    /// Estimator with the published prices of common models
+    pub fn new() -> Self {
+        let estimator = Self::empty();
+        let table = [
+            ("claude", "claude-3-opus", 15.0, 75.0),
+            ("claude", "claude-3-sonnet", 3.0, 15.0),
+            ("claude", "claude-3-5-sonnet", 3.0, 15.0),
+            ("claude", "claude-3-haiku", 0.25, 1.25),
+            ("claude", "claude-3-5-haiku", 0.8, 4.0),
+            ("openai", "gpt-4", 30.0, 60.0),
+            ("openai", "gpt-4-turbo", 10.0, 30.0),
+            ("openai", "gpt-4o", 2.5, 10.0),
+            ("openai", "gpt-4o-mini", 0.15, 0.6),
+            ("openai", "gpt-3.5-turbo", 0.5, 1.5),
+        ];
+        for (provider, model_pattern, input_usd, output_usd) in table {
+            estimator.set_price(provider, model_pattern, ModelPrice::per_million_tokens(input_usd, output_usd));
+        }
+        estimator
+    }
+
+    /// Estimator without any price, leaving every model unpriced
+    pub fn empty() -> Self {
+        Self { prices: RwLock::new(Vec::new()) }
+    }
+
+    /// Price `provider`'s models matching `model_pattern`, a prefix or a glob with `*` and `?`
+    ///
+    /// Setting a pattern again replaces its price.
+    pub fn set_price(&self, provider: &str, model_pattern: &str, price: ModelPrice) {
+        let mut prices = self.prices.write();
+        prices.retain(|registered| registered.provider != provider || registered.model_pattern != model_pattern);
+        prices.push(RegisteredPrice {
+            provider: provider.to_string(),
+            model_pattern: model_pattern.to_string(),
+            price,
+        });
+    }
+
+    /// Price of `model` at `provider`, if the table has one
+    pub fn price(&self, provider: &str, model: &str) -> Option<ModelPrice> {
+        let prices = self.prices.read();
+        prices
+            .iter()
+            .enumerate()
+            .filter(|(_, registered)| registered.provider == provider && pattern_matches(&registered.model_pattern, model))
+            .max_by_key(|(position, registered)| (pattern_specificity(&registered.model_pattern), *position))
+            .map(|(_, registered)| registered.price)
+    }
+
+    /// Estimated cost in USD of `usage` on `model` at `provider`, if the model is priced
+    pub fn estimate(&self, provider: &str, model: &str, usage: &TokenUsage) -> Option<f64> {
+        self.price(provider, model).map(|price| price.cost(usage))
+    }
+}
+
+/// Estimated spend per provider in one calendar month (UTC)
+#[derive(Debug, Default)]
+struct SpendPeriod {
+    /// Year and month the spend was made in
+    month: (i32, u32),
+    by_provider: HashMap<String, f64>,
+}
+
+/// Running estimated spend for the current month, against an optional budget
+///
+/// The total starts again from zero on the first spend or check in a new month,
+/// and spend stamped in an earlier month than the current one is not counted.
+#[derive(Debug, Default)]
+pub struct SpendTracker {
+    period: Mutex<SpendPeriod>,
+}
+
+impl SpendTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `cost_usd` spent at `provider` at time `at`
+    pub fn record(&self, provider: &str, cost_usd: f64, at: DateTime<Utc>) {
+        let mut period = self.period.lock();
+        roll_over(&mut period, at);
+        if (at.year(), at.month()) < period.month {
+            return;
+        }
+        *period.by_provider.entry(provider.to_string()).or_default() += cost_usd;
+    }
+
+    /// Total spent in the month of `at`
+    pub fn spent(&self, at: DateTime<Utc>) -> f64 {
+        let mut period = self.period.lock();
+        roll_over(&mut period, at);
+        period.by_provider.values().sum()
+    }
+
+    /// Spent at each provider in the month of `at`
+    pub fn spent_by_provider(&self, at: DateTime<Utc>) -> HashMap<String, f64> {
+        let mut period = self.period.lock();
+        roll_over(&mut period, at);
+        period.by_provider.clone()
+    }
+
+    /// Fail with `BudgetExceeded` once the month's spend exceeds `budget_usd`
+    pub fn check_budget(&self, budget_usd: Option<f64>, at: DateTime<Utc>) -> Result<()> {
+        let Some(budget_usd) = budget_usd else {
+            return Ok(());
+        };
+        let spent_usd = self.spent(at);
+        if spent_usd > budget_usd {
+            return Err(WritemagicError::budget_exceeded(spent_usd, budget_usd));
+        }
+        Ok(())
+    }
+}
+
+/// Start a new period when `at` falls in a later month than the current one
+fn roll_over(period: &mut SpendPeriod, at: DateTime<Utc>) {
+    let month = (at.year(), at.month());
+    if month > period.month {
+        *period = SpendPeriod { month, by_provider: HashMap::new() };
+    }
+}
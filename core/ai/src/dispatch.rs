@@ -0,0 +1,192 @@
+//! Priority dispatch of AI requests under a global concurrency limit
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use crate::providers::RequestPriority;
+
+/// Dispatch class of an AI request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiPriority {
+    /// A user is waiting on the result
+    Interactive,
+    #[default]
+    Normal,
+    /// Batch and maintenance work
+    Background,
+}
+
+impl AiPriority {
+    fn rank(self) -> u32 {
+        match self {
+            Self::Interactive => 2,
+            Self::Normal => 1,
+            Self::Background => 0,
+        }
+    }
+}
+
+impl From<&RequestPriority> for AiPriority {
+    fn from(priority: &RequestPriority) -> Self {
+        match priority {
+            RequestPriority::Critical | RequestPriority::High => Self::Interactive,
+            RequestPriority::Normal => Self::Normal,
+            RequestPriority::Low => Self::Background,
+        }
+    }
+}
+
+impl From<AiPriority> for RequestPriority {
+    fn from(priority: AiPriority) -> Self {
+        match priority {
+            AiPriority::Interactive => Self::High,
+            AiPriority::Normal => Self::Normal,
+            AiPriority::Background => Self::Low,
+        }
+    }
+}
+
+/// Dispatch configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DispatchConfig {
+    /// Requests sent to providers at the same time
+    pub max_concurrent_requests: usize,
+    /// Waiting this long raises a request by one priority level, so
+    /// background work cannot be starved
+    pub aging_interval_ms: u64,
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 8,
+            aging_interval_ms: 10_000,
+        }
+    }
+}
+
+struct Waiter {
+    priority: AiPriority,
+    enqueued_at: Instant,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct DispatchState {
+    in_flight: usize,
+    waiting: Vec<Waiter>,
+}
+
+/// Admits requests in priority order once the concurrency limit is reached
+pub struct PriorityDispatcher {
+    max_concurrent: usize,
+    aging_interval: Duration,
+    state: Mutex<DispatchState>,
+}
+
+impl PriorityDispatcher {
+    pub fn new(config: &DispatchConfig) -> Self {
+        Self {
+            max_concurrent: config.max_concurrent_requests.max(1),
+            aging_interval: Duration::from_millis(config.aging_interval_ms),
+            state: Mutex::new(DispatchState::default()),
+        }
+    }
+
+    /// Wait for a dispatch slot, held until the returned permit is dropped
+    pub async fn acquire(self: &Arc<Self>, priority: AiPriority) -> DispatchPermit {
+        let receiver = {
+            let mut state = self.state.lock();
+            if state.in_flight < self.max_concurrent && state.waiting.is_empty() {
+                state.in_flight += 1;
+                return DispatchPermit { dispatcher: self.clone() };
+            }
+
+            let (grant, receiver) = oneshot::channel();
+            state.waiting.push(Waiter { priority, enqueued_at: Instant::now(), grant });
+            receiver
+        };
+
+        let mut pending = PendingGrant { dispatcher: self.clone(), receiver: Some(receiver) };
+        if let Some(receiver) = pending.receiver.as_mut() {
+            // The sender lives in the queue until a slot is handed over
+            let _ = receiver.await;
+        }
+        pending.receiver = None;
+
+        DispatchPermit { dispatcher: self.clone() }
+    }
+
+    /// Requests waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.state.lock().waiting.len()
+    }
+
+    /// Requests holding a slot
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().in_flight
+    }
+
+    /// Ranking of a waiter: its priority level, raised by one per aging interval waited
+    fn score(&self, waiter: &Waiter, now: Instant) -> Duration {
+        self.aging_interval * waiter.priority.rank() + now.duration_since(waiter.enqueued_at)
+    }
+
+    /// Hand a freed slot to the best waiter, or return it to the pool
+    fn release(&self) {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+
+        while !state.waiting.is_empty() {
+            let best = (0..state.waiting.len())
+                .max_by(|&a, &b| {
+                    let (a, b) = (&state.waiting[a], &state.waiting[b]);
+                    self.score(a, now)
+                        .cmp(&self.score(b, now))
+                        // Earlier arrivals win ties
+                        .then_with(|| b.enqueued_at.cmp(&a.enqueued_at))
+                })
+                .unwrap_or_default();
+
+            let waiter = state.waiting.swap_remove(best);
+            if waiter.grant.send(()).is_ok() {
+                return;
+            }
+            // The waiter gave up; try the next one
+        }
+
+        state.in_flight = state.in_flight.saturating_sub(1);
+    }
+}
+
+/// A dispatch slot, returned to the dispatcher on drop
+pub struct DispatchPermit {
+    dispatcher: Arc<PriorityDispatcher>,
+}
+
+impl Drop for DispatchPermit {
+    fn drop(&mut self) {
+        self.dispatcher.release();
+    }
+}
+
+/// Passes on a slot granted after the waiting request was cancelled
+struct PendingGrant {
+    dispatcher: Arc<PriorityDispatcher>,
+    receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PendingGrant {
+    fn drop(&mut self) {
+        if let Some(mut receiver) = self.receiver.take() {
+            receiver.close();
+            if receiver.try_recv().is_ok() {
+                self.dispatcher.release();
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Reusable few-shot example sets
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use writemagic_shared::Result;
+
+/// Named set of input/output pairs shown to the model as examples
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FewShotExampleSet {
+    pub name: String,
+    /// Example pairs, oldest first
+    pub examples: Vec<(String, String)>,
+}
+
+impl FewShotExampleSet {
+    pub fn new(name: impl Into<String>, examples: Vec<(String, String)>) -> Self {
+        Self {
+            name: name.into(),
+            examples,
+        }
+    }
+}
+
+/// Storage for few-shot example sets, keyed by name
+#[async_trait]
+pub trait FewShotExampleRepository: Send + Sync {
+    /// Insert a set, replacing any set with the same name
+    async fn save(&self, set: &FewShotExampleSet) -> Result<()>;
+
+    async fn find_by_name(&self, name: &str) -> Result<Option<FewShotExampleSet>>;
+
+    /// All sets, ordered by name
+    async fn list(&self) -> Result<Vec<FewShotExampleSet>>;
+
+    async fn delete(&self, name: &str) -> Result<bool>;
+}
+
+/// In-memory example set repository for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryFewShotExampleRepository {
+    sets: Arc<RwLock<HashMap<String, FewShotExampleSet>>>,
+}
+
+impl InMemoryFewShotExampleRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl FewShotExampleRepository for InMemoryFewShotExampleRepository {
+    async fn save(&self, set: &FewShotExampleSet) -> Result<()> {
+        self.sets.write().await.insert(set.name.clone(), set.clone());
+        Ok(())
+    }
+
+    async fn find_by_name(&self, name: &str) -> Result<Option<FewShotExampleSet>> {
+        Ok(self.sets.read().await.get(name).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<FewShotExampleSet>> {
+        let mut sets: Vec<FewShotExampleSet> = self.sets.read().await.values().cloned().collect();
+        sets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(sets)
+    }
+
+    async fn delete(&self, name: &str) -> Result<bool> {
+        Ok(self.sets.write().await.remove(name).is_some())
+    }
+}
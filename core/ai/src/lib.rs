@@ -14,6 +14,10 @@ pub mod security;
 pub mod circuit_breaker;
 pub mod performance_monitor;
 pub mod request_batcher;
+pub mod dispatch;
+pub mod few_shot;
+pub mod prompt_templates;
+pub mod cost;
 
 #[cfg(test)]
 mod test_basic;
@@ -31,8 +35,12 @@ pub use services::*;
 pub use repositories::*;
 pub use writing_service::*;
 pub use retry_patterns::{RetryConfig, with_retry, with_timeout};
-pub use tokenization::{TokenizationService, ModelTokenizer, TokenUsage, ModelTokenizerConfig};
-pub use security::{SecureKeyManager, PIIDetectionService, ContentSanitizationService, SecurityAuditLogger};
-pub use circuit_breaker::{CircuitBreaker, CircuitBreakerRegistry, CircuitBreakerConfig, CircuitState};
+pub use tokenization::{TokenizationService, Tokenizer, ModelTokenizer, HeuristicTokenizer, TokenUsage, ModelTokenizerConfig};
+pub use security::{SecureKeyManager, PIIDetectionService, ContentSanitizationService, SecurityAuditLogger, DocumentEncryptionService, SealedContent};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerRegistry, CircuitBreakerConfig, CircuitBreakerStatus, CircuitState};
 pub use performance_monitor::{PerformanceMonitor, PerformanceStats, PerformanceThresholds, PerformanceAlerting};
-pub use request_batcher::{RequestBatcher, RequestScheduler, BatchConfig};
\ No newline at end of file
+pub use request_batcher::{RequestBatcher, RequestScheduler, BatchConfig};
+pub use dispatch::{AiPriority, DispatchConfig, DispatchPermit, PriorityDispatcher};
+pub use few_shot::{FewShotExampleSet, FewShotExampleRepository, InMemoryFewShotExampleRepository};
+pub use prompt_templates::{PromptTemplate, PromptTemplateRepository, InMemoryPromptTemplateRepository};
+pub use cost::{CostEstimator, ModelPrice, SpendTracker};
\ No newline at end of file
@@ -12,6 +12,8 @@ pub struct AIPerformanceMetrics {
     pub provider_name: String,
     pub model_name: String,
     pub request_id: String,
+    /// Wall-clock time the request started
+    pub recorded_at: SystemTime,
     pub start_time: Instant,
     pub end_time: Option<Instant>,
     pub duration: Option<Duration>,
@@ -34,6 +36,8 @@ pub struct PerformanceStats {
     pub cache_hits: u64,
     pub total_tokens: u64,
     pub total_cost: f64,
+    /// Estimated spend in the current budget month; filled in by the orchestration service
+    pub month_to_date_cost: f64,
     pub avg_response_time: Duration,
     pub p50_response_time: Duration,
     pub p95_response_time: Duration,
@@ -72,6 +76,7 @@ impl PerformanceMonitor {
             provider_name,
             model_name,
             request_id,
+            recorded_at: SystemTime::now(),
             start_time: Instant::now(),
             end_time: None,
             duration: None,
@@ -150,6 +155,16 @@ impl PerformanceMonitor {
         metrics[start..].to_vec()
     }
 
+    /// Get metrics of requests started in `[start, end)`, oldest first
+    pub fn metrics_between(&self, start: SystemTime, end: SystemTime) -> Vec<AIPerformanceMetrics> {
+        self.metrics
+            .read()
+            .iter()
+            .filter(|m| m.recorded_at >= start && m.recorded_at < end)
+            .cloned()
+            .collect()
+    }
+
     /// Get performance trends over time
     pub fn get_performance_trends(&self, hours: u64) -> HashMap<String, Vec<f64>> {
         let metrics = self.metrics.read();
@@ -367,6 +382,7 @@ impl PerformanceMonitor {
             cache_hits,
             total_tokens,
             total_cost,
+            month_to_date_cost: 0.0,
             avg_response_time,
             p50_response_time,
             p95_response_time,
@@ -0,0 +1,190 @@
+//! Reusable prompt templates with `{{variable}}` placeholders
+//!
+//! A backslash makes the next brace or backslash literal, so `\{{name}}`
+//! renders as `{{name}}`. Substituted values are inserted as-is and never
+//! expanded again.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use writemagic_shared::{EntityId, Result, WritemagicError};
+
+/// Piece of a parsed template body
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Variable(String),
+}
+
+/// Named prompt with placeholders filled in at completion time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: EntityId,
+    pub name: String,
+    pub body: String,
+    /// Variables that must be provided to render, in addition to those in the body
+    pub required_variables: Vec<String>,
+}
+
+impl PromptTemplate {
+    /// Create a template, rejecting a malformed body or variable name
+    pub fn new(name: impl Into<String>, body: impl Into<String>, required_variables: Vec<String>) -> Result<Self> {
+        let name = name.into().trim().to_string();
+        if name.is_empty() {
+            return Err(WritemagicError::validation("Prompt template name cannot be empty"));
+        }
+        let body = body.into();
+        parse(&body)?;
+        if let Some(invalid) = required_variables.iter().find(|variable| !is_variable_name(variable)) {
+            return Err(WritemagicError::validation(format!("Invalid template variable name: {:?}", invalid)));
+        }
+
+        Ok(Self {
+            id: EntityId::new(),
+            name,
+            body,
+            required_variables,
+        })
+    }
+
+    /// Variables named by placeholders in the body, in order of first appearance
+    pub fn placeholders(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = Vec::new();
+        for segment in parse(&self.body)? {
+            if let Segment::Variable(name) = segment {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Render the body with `variables`, failing with every missing variable listed
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String> {
+        let segments = parse(&self.body)?;
+
+        let mut missing: Vec<&str> = Vec::new();
+        let placeholders = segments.iter().filter_map(|segment| match segment {
+            Segment::Variable(name) => Some(name.as_str()),
+            Segment::Literal(_) => None,
+        });
+        for name in self.required_variables.iter().map(String::as_str).chain(placeholders) {
+            if !variables.contains_key(name) && !missing.contains(&name) {
+                missing.push(name);
+            }
+        }
+        if !missing.is_empty() {
+            return Err(WritemagicError::validation(format!(
+                "Missing template variables: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(segments
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text,
+                Segment::Variable(name) => variables[&name].clone(),
+            })
+            .collect())
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Split a template body into literal text and placeholders
+fn parse(body: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = body;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if let Some(escaped @ ('{' | '}' | '\\')) = rest[1..].chars().next() {
+                literal.push(escaped);
+                rest = &rest[2..];
+                continue;
+            }
+        } else if let Some(after_open) = rest.strip_prefix("{{") {
+            let close = after_open.find("}}").ok_or_else(|| {
+                WritemagicError::validation("Unterminated placeholder in prompt template")
+            })?;
+            let name = &after_open[..close];
+            if name.contains("{{") {
+                return Err(WritemagicError::validation("Nested placeholders are not supported in prompt templates"));
+            }
+            let name = name.trim();
+            if !is_variable_name(name) {
+                return Err(WritemagicError::validation(format!("Invalid template variable name: {:?}", name)));
+            }
+
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Variable(name.to_string()));
+            rest = &after_open[close + 2..];
+            continue;
+        }
+
+        literal.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// Storage for prompt templates
+#[async_trait]
+pub trait PromptTemplateRepository: Send + Sync {
+    /// Insert a template, replacing any template with the same id
+    async fn save(&self, template: &PromptTemplate) -> Result<()>;
+
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<PromptTemplate>>;
+
+    /// All templates, ordered by name
+    async fn list(&self) -> Result<Vec<PromptTemplate>>;
+
+    async fn delete(&self, id: &EntityId) -> Result<bool>;
+}
+
+/// In-memory prompt template repository for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryPromptTemplateRepository {
+    templates: Arc<RwLock<HashMap<EntityId, PromptTemplate>>>,
+}
+
+impl InMemoryPromptTemplateRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PromptTemplateRepository for InMemoryPromptTemplateRepository {
+    async fn save(&self, template: &PromptTemplate) -> Result<()> {
+        self.templates.write().await.insert(template.id, template.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<PromptTemplate>> {
+        Ok(self.templates.read().await.get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<PromptTemplate>> {
+        let mut templates: Vec<PromptTemplate> = self.templates.read().await.values().cloned().collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.templates.write().await.remove(id).is_some())
+    }
+}
@@ -2,14 +2,15 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use writemagic_shared::{Result, WritemagicError};
-use std::collections::{HashMap, hash_map::DefaultHasher};
+use writemagic_shared::{current_request_id, ProviderError, Result, WritemagicError};
+use std::collections::{BTreeMap, HashMap, hash_map::DefaultHasher};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, Semaphore};
-use dashmap::DashMap;
+use metrics::counter;
+use zeroize::Zeroizing;
 
 /// AI provider trait following the pattern from CLAUDE.md
 #[async_trait]
@@ -60,6 +61,11 @@ pub trait StreamingResponse: Send + Sync {
     
     /// Get accumulated response so far
     fn get_partial_response(&self) -> String;
+
+    /// Whether the stream was cut off by a safety limit rather than finishing
+    fn is_truncated(&self) -> bool {
+        false
+    }
 }
 
 /// Streaming chunk from AI provider
@@ -103,6 +109,86 @@ pub struct CompletionRequest {
     pub compress_response: bool,
     /// Request batching hint
     pub batchable: bool,
+    /// Caller-supplied credentials used instead of the provider's shared key
+    #[serde(skip)]
+    pub credentials_override: Option<ProviderCredentials>,
+    /// Example input/output pairs shown to the model ahead of the prompt, oldest first
+    #[serde(default)]
+    pub few_shot: Vec<(String, String)>,
+    /// Providers to try for this request, in order, instead of the service's fallback order
+    #[serde(default)]
+    pub provider_preference: Vec<String>,
+    /// Cache the response even though a temperature above 0 makes it one sample of many
+    #[serde(default)]
+    pub cache_nondeterministic: bool,
+}
+
+/// Credentials supplied with a single request, e.g. a user's own API key
+///
+/// Never serialized, and redacted from `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProviderCredentials {
+    /// Name of the provider the credentials are for, e.g. `claude`
+    pub provider: String,
+    api_key: Zeroizing<String>,
+    /// Endpoint to send the request to instead of the provider's default
+    pub base_url: Option<String>,
+}
+
+impl ProviderCredentials {
+    pub fn new(provider: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            api_key: Zeroizing::new(api_key.into()),
+            base_url: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    /// Check that the base URL, if any, points at one of the `allowed` endpoints
+    ///
+    /// Only scheme, host and port are compared, so any path on an allowed
+    /// endpoint is accepted.
+    pub fn check_base_url<S: AsRef<str>>(&self, allowed: &[S]) -> Result<()> {
+        let Some(base_url) = &self.base_url else {
+            return Ok(());
+        };
+        let origin = reqwest::Url::parse(base_url)
+            .map_err(|_| WritemagicError::validation("Credentials base URL is not a valid URL"))?
+            .origin();
+        let is_allowed = allowed
+            .iter()
+            .filter_map(|endpoint| reqwest::Url::parse(endpoint.as_ref()).ok())
+            .any(|endpoint| endpoint.origin() == origin);
+        if !is_allowed {
+            return Err(WritemagicError::validation(format!(
+                "Credentials base URL {} is not an allowed provider endpoint",
+                origin.ascii_serialization()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Endpoints caller-supplied credentials may send requests to unless the service allows others
+pub const DEFAULT_CREDENTIAL_ENDPOINTS: &[&str] = &["https://api.anthropic.com", "https://api.openai.com"];
+
+impl std::fmt::Debug for ProviderCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderCredentials")
+            .field("provider", &self.provider)
+            .field("api_key", &"[REDACTED]")
+            .field("base_url", &self.base_url)
+            .finish()
+    }
 }
 
 /// Request priority levels for intelligent routing
@@ -131,6 +217,10 @@ impl CompletionRequest {
             timeout: None,
             compress_response: false,
             batchable: false,
+            credentials_override: None,
+            few_shot: Vec::new(),
+            provider_preference: Vec::new(),
+            cache_nondeterministic: false,
         }
     }
 
@@ -173,6 +263,45 @@ impl CompletionRequest {
         self.stream = stream;
         self
     }
+
+    pub fn with_credentials(mut self, credentials: ProviderCredentials) -> Self {
+        self.credentials_override = Some(credentials);
+        self
+    }
+
+    pub fn with_few_shot(mut self, examples: Vec<(String, String)>) -> Self {
+        self.few_shot = examples;
+        self
+    }
+
+    pub fn with_provider_preference(mut self, providers: Vec<String>) -> Self {
+        self.provider_preference = providers;
+        self
+    }
+
+    pub fn with_cache_nondeterministic(mut self, cache: bool) -> Self {
+        self.cache_nondeterministic = cache;
+        self
+    }
+
+    /// Whether a response may be served from or stored in a cache
+    ///
+    /// Only deterministic requests are by default; at a temperature above 0
+    /// repeating the request is expected to give a different answer.
+    pub fn is_cacheable(&self) -> bool {
+        self.cache_nondeterministic || !self.temperature.is_some_and(|temperature| temperature > 0.0)
+    }
+
+    /// API key and base URL to call `provider` with, preferring caller-supplied credentials
+    pub fn endpoint_for<'a>(&'a self, provider: &str, api_key: &'a str, base_url: &'a str) -> (&'a str, &'a str) {
+        match &self.credentials_override {
+            Some(credentials) if credentials.provider == provider => (
+                credentials.api_key(),
+                credentials.base_url.as_deref().unwrap_or(base_url),
+            ),
+            _ => (api_key, base_url),
+        }
+    }
 }
 
 /// Completion response structure
@@ -186,6 +315,16 @@ pub struct CompletionResponse {
     pub metadata: HashMap<String, String>,
 }
 
+/// Metadata key recording which provider served a completion
+pub const PROVIDER_METADATA_KEY: &str = "provider";
+
+impl CompletionResponse {
+    /// Name of the provider that served this response, if recorded
+    pub fn provider(&self) -> Option<&str> {
+        self.metadata.get(PROVIDER_METADATA_KEY).map(String::as_str)
+    }
+}
+
 /// Message in conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -347,6 +486,87 @@ impl AtomicUsageStats {
     }
 }
 
+/// Parse a Claude API error body into structured provider details
+///
+/// Claude errors look like `{"type":"error","error":{"type":"...","message":"..."}}`.
+pub fn parse_claude_error(status: u16, body: &str) -> ProviderError {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(body).ok();
+    let error = parsed.as_ref().and_then(|value| value.get("error"));
+
+    build_provider_error(
+        status,
+        error.and_then(|e| e.get("type")).and_then(|t| t.as_str()),
+        error.and_then(|e| e.get("message")).and_then(|m| m.as_str()),
+        body,
+    )
+}
+
+/// Parse an OpenAI API error body into structured provider details
+///
+/// OpenAI errors look like `{"error":{"message":"...","type":"...","code":"..."}}`.
+pub fn parse_openai_error(status: u16, body: &str) -> ProviderError {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(body).ok();
+    let error = parsed.as_ref().and_then(|value| value.get("error"));
+    let code = error
+        .and_then(|e| e.get("code"))
+        .and_then(|c| c.as_str())
+        .or_else(|| error.and_then(|e| e.get("type")).and_then(|t| t.as_str()));
+
+    build_provider_error(
+        status,
+        code,
+        error.and_then(|e| e.get("message")).and_then(|m| m.as_str()),
+        body,
+    )
+}
+
+/// Error for a failed Claude API response, keeping the structured details
+/// so callers can decide whether to retry
+pub fn claude_status_error(status: u16, body: &str) -> WritemagicError {
+    if status == 401 {
+        return WritemagicError::authentication("Invalid Claude API key");
+    }
+
+    let details = parse_claude_error(status, body);
+    let message = match details.status {
+        429 => "Claude API rate limit exceeded".to_string(),
+        500..=599 => "Claude API server error".to_string(),
+        _ => format!("Claude API error: {}", details.message),
+    };
+    WritemagicError::ai_provider_with_details(message, details)
+}
+
+/// Error for a failed OpenAI API response, keeping the structured details
+/// so callers can decide whether to retry
+pub fn openai_status_error(status: u16, body: &str) -> WritemagicError {
+    if status == 401 {
+        return WritemagicError::authentication("Invalid OpenAI API key");
+    }
+
+    let details = parse_openai_error(status, body);
+    let message = match details.status {
+        429 => "OpenAI API rate limit exceeded".to_string(),
+        500..=599 => "OpenAI API server error".to_string(),
+        _ => format!("OpenAI API error: {}", details.message),
+    };
+    WritemagicError::ai_provider_with_details(message, details)
+}
+
+fn build_provider_error(status: u16, code: Option<&str>, message: Option<&str>, body: &str) -> ProviderError {
+    let message = message.unwrap_or(body).to_string();
+    let lowered = format!("{} {}", code.unwrap_or_default(), message).to_lowercase();
+    let policy_violation = ["content_policy", "content policy", "content filtering", "safety system"]
+        .iter()
+        .any(|marker| lowered.contains(marker));
+
+    ProviderError {
+        status,
+        provider_code: code.map(str::to_string),
+        message,
+        retryable: !policy_violation && matches!(status, 408 | 409 | 429 | 500..=599),
+    }
+}
+
 /// Claude AI provider implementation
 #[derive(Clone)]
 pub struct ClaudeProvider {
@@ -399,8 +619,8 @@ impl AIProvider for ClaudeProvider {
 
     async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
         // Check cache first
-        let cache_key = ResponseCache::generate_cache_key(request);
-        if let Some(cached_response) = self.cache.get(&cache_key) {
+        let cache_key = ResponseCache::shared_cache_key(request);
+        if let Some(cached_response) = cache_key.as_ref().and_then(|key| self.cache.get(key)) {
             log::debug!("Cache hit for Claude request");
             return Ok(cached_response);
         }
@@ -408,25 +628,26 @@ impl AIProvider for ClaudeProvider {
         // Rate limiting
         let _permit = self.rate_limiter.acquire().await?;
 
-        let url = format!("{}/v1/messages", self.base_url);
+        let (api_key, base_url) = request.endpoint_for(self.name(), &self.api_key, &self.base_url);
+        let url = format!("{}/v1/messages", base_url);
         
         // Convert to Claude API format
         let claude_request = self.convert_to_claude_format(request)?;
         
-        log::debug!("Making Claude API request to: {}", url);
+        tracing::debug!(request_id = current_request_id().as_deref(), url = %url, "Making Claude API request");
         let start_time = Instant::now();
         
         let response = self.client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Authorization", format!("Bearer {}", api_key))
             .header("Content-Type", "application/json")
             .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
+            .header("x-api-key", api_key)
             .json(&claude_request)
             .send()
             .await
             .map_err(|e| {
-                log::error!("Claude API network error: {}", e);
+                tracing::error!(request_id = current_request_id().as_deref(), error = %e, "Claude API network error");
                 WritemagicError::network(format!("Claude API request failed: {}", e))
             })?;
 
@@ -435,14 +656,7 @@ impl AIProvider for ClaudeProvider {
 
         if !status.is_success() {
             log::error!("Claude API error (status {}): {}", status, response_text);
-            
-            // Handle specific error types
-            match status.as_u16() {
-                401 => return Err(WritemagicError::authentication("Invalid Claude API key")),
-                429 => return Err(WritemagicError::ai_provider("Claude API rate limit exceeded")),
-                500..=599 => return Err(WritemagicError::ai_provider("Claude API server error")),
-                _ => return Err(WritemagicError::ai_provider(format!("Claude API error: {}", response_text))),
-            }
+            return Err(claude_status_error(status.as_u16(), &response_text));
         }
 
         let claude_response: serde_json::Value = serde_json::from_str(&response_text)
@@ -458,7 +672,9 @@ impl AIProvider for ClaudeProvider {
         self.update_usage_stats(&completion_response, request_duration).await;
 
         // Cache the response
-        self.cache.insert(cache_key, completion_response.clone(), None);
+        if let Some(cache_key) = cache_key {
+            self.cache.insert(cache_key, completion_response.clone(), None);
+        }
 
         log::debug!("Claude request completed in {:?}", request_duration);
         Ok(completion_response)
@@ -496,26 +712,29 @@ impl AIProvider for ClaudeProvider {
     async fn stream(&self, request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
         let _permit = self.rate_limiter.acquire().await?;
         
-        let url = format!("{}/v1/messages", self.base_url);
+        let (api_key, base_url) = request.endpoint_for(self.name(), &self.api_key, &self.base_url);
+        let url = format!("{}/v1/messages", base_url);
         let mut claude_request = self.convert_to_claude_format(request)?;
         claude_request["stream"] = serde_json::Value::Bool(true);
         
         let response = self.client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Authorization", format!("Bearer {}", api_key))
             .header("Content-Type", "application/json")
-            .header("Anthropic-Version", "2023-06-01")
+            .header("anthropic-version", "2023-06-01")
+            .header("x-api-key", api_key)
             .json(&claude_request)
             .send()
             .await
             .map_err(|e| WritemagicError::network(format!("Claude API request failed: {}", e)))?;
 
-        if response.status().is_success() {
+        let status = response.status();
+        if status.is_success() {
             Ok(Box::new(ClaudeStreamingResponse::new(response)))
         } else {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(WritemagicError::ai_provider(format!("Claude streaming failed: {}", error_text)))
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("Claude streaming error (status {}): {}", status, error_text);
+            Err(claude_status_error(status.as_u16(), &error_text))
         }
     }
 
@@ -694,8 +913,8 @@ impl AIProvider for OpenAIProvider {
 
     async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
         // Check cache first
-        let cache_key = ResponseCache::generate_cache_key(request);
-        if let Some(cached_response) = self.cache.get(&cache_key) {
+        let cache_key = ResponseCache::shared_cache_key(request);
+        if let Some(cached_response) = cache_key.as_ref().and_then(|key| self.cache.get(key)) {
             log::debug!("Cache hit for OpenAI request");
             return Ok(cached_response);
         }
@@ -703,22 +922,23 @@ impl AIProvider for OpenAIProvider {
         // Rate limiting
         let _permit = self.rate_limiter.acquire().await?;
 
-        let url = format!("{}/v1/chat/completions", self.base_url);
+        let (api_key, base_url) = request.endpoint_for(self.name(), &self.api_key, &self.base_url);
+        let url = format!("{}/v1/chat/completions", base_url);
         
-        log::debug!("Making OpenAI API request to: {}", url);
+        tracing::debug!(request_id = current_request_id().as_deref(), url = %url, "Making OpenAI API request");
         let start_time = Instant::now();
 
         let openai_request = self.convert_to_openai_format(request);
         
         let response = self.client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Authorization", format!("Bearer {}", api_key))
             .header("Content-Type", "application/json")
             .json(&openai_request)
             .send()
             .await
             .map_err(|e| {
-                log::error!("OpenAI API network error: {}", e);
+                tracing::error!(request_id = current_request_id().as_deref(), error = %e, "OpenAI API network error");
                 WritemagicError::network(format!("OpenAI API request failed: {}", e))
             })?;
 
@@ -727,14 +947,7 @@ impl AIProvider for OpenAIProvider {
 
         if !status.is_success() {
             log::error!("OpenAI API error (status {}): {}", status, response_text);
-            
-            // Handle specific error types
-            match status.as_u16() {
-                401 => return Err(WritemagicError::authentication("Invalid OpenAI API key")),
-                429 => return Err(WritemagicError::ai_provider("OpenAI API rate limit exceeded")),
-                500..=599 => return Err(WritemagicError::ai_provider("OpenAI API server error")),
-                _ => return Err(WritemagicError::ai_provider(format!("OpenAI API error: {}", response_text))),
-            }
+            return Err(openai_status_error(status.as_u16(), &response_text));
         }
 
         let completion_response: CompletionResponse = serde_json::from_str(&response_text)
@@ -748,7 +961,9 @@ impl AIProvider for OpenAIProvider {
         self.update_usage_stats(&completion_response, request_duration).await;
 
         // Cache the response
-        self.cache.insert(cache_key, completion_response.clone(), None);
+        if let Some(cache_key) = cache_key {
+            self.cache.insert(cache_key, completion_response.clone(), None);
+        }
 
         log::debug!("OpenAI request completed in {:?}", request_duration);
         Ok(completion_response)
@@ -785,25 +1000,27 @@ impl AIProvider for OpenAIProvider {
     async fn stream(&self, request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
         let _permit = self.rate_limiter.acquire().await?;
         
-        let url = format!("{}/v1/chat/completions", self.base_url);
+        let (api_key, base_url) = request.endpoint_for(self.name(), &self.api_key, &self.base_url);
+        let url = format!("{}/v1/chat/completions", base_url);
         let mut openai_request = self.convert_to_openai_format(request);
         openai_request["stream"] = serde_json::Value::Bool(true);
         
         let response = self.client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Authorization", format!("Bearer {}", api_key))
             .header("Content-Type", "application/json")
             .json(&openai_request)
             .send()
             .await
             .map_err(|e| WritemagicError::network(format!("OpenAI API request failed: {}", e)))?;
 
-        if response.status().is_success() {
+        let status = response.status();
+        if status.is_success() {
             Ok(Box::new(OpenAIStreamingResponse::new(response)))
         } else {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(WritemagicError::ai_provider(format!("OpenAI streaming failed: {}", error_text)))
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("OpenAI streaming error (status {}): {}", status, error_text);
+            Err(openai_status_error(status.as_u16(), &error_text))
         }
     }
 
@@ -886,16 +1103,19 @@ impl RateLimiter {
         let permit = self.semaphore.acquire().await
             .map_err(|_| WritemagicError::network("Rate limiter semaphore closed".to_string()))?;
         
-        // Enforce minimum interval between requests
-        let last = self.last_request.write().await;
-        let elapsed = last.elapsed();
-        if elapsed < self.min_interval {
-            let sleep_duration = self.min_interval - elapsed;
-            drop(last);
-            tokio::time::sleep(sleep_duration).await;
+        // Enforce minimum interval between requests by reserving the next free
+        // slot, then waiting for it without holding the lock
+        let wait = {
+            let mut last = self.last_request.write().await;
+            let now = Instant::now();
+            let slot = (*last + self.min_interval).max(now);
+            *last = slot;
+            slot - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
         }
-        *self.last_request.write().await = Instant::now();
-        
+
         Ok(permit)
     }
 }
@@ -914,32 +1134,109 @@ impl CacheEntry {
     }
 }
 
+/// Responses a cache holds before evicting the least recently used
+pub const DEFAULT_CACHE_CAPACITY: usize = 1000;
+
+/// Cache entries in least-recently-used order
+#[derive(Debug, Default)]
+struct LruEntries {
+    entries: HashMap<String, (CacheEntry, u64)>,
+    /// Keys by the tick of their last use, oldest first
+    recency: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl LruEntries {
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    /// The entry for `key`, marked as most recently used
+    fn touch(&mut self, key: &str) -> Option<&CacheEntry> {
+        let tick = self.tick();
+        let (_, last_used) = self.entries.get_mut(key)?;
+        self.recency.remove(last_used);
+        *last_used = tick;
+        self.recency.insert(tick, key.to_string());
+        self.entries.get(key).map(|(entry, _)| entry)
+    }
+
+    fn insert(&mut self, key: String, entry: CacheEntry) {
+        let tick = self.tick();
+        self.remove(&key);
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (entry, tick));
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((_, last_used)) = self.entries.remove(key) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    fn evict_least_recent(&mut self) {
+        if let Some((_, key)) = self.recency.pop_first() {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Hit, miss and eviction counts of a response cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within capacity; expired entries aren't counted
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
 /// Response cache for AI providers
+///
+/// Bounded to `capacity` entries, evicting the least recently used first.
 #[derive(Debug)]
 pub struct ResponseCache {
-    entries: DashMap<String, CacheEntry>,
+    entries: parking_lot::Mutex<LruEntries>,
     default_ttl: Duration,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
 impl ResponseCache {
     pub fn new(default_ttl_seconds: u64) -> Self {
+        Self::with_capacity(default_ttl_seconds, DEFAULT_CACHE_CAPACITY)
+    }
+
+    pub fn with_capacity(default_ttl_seconds: u64, capacity: usize) -> Self {
         Self {
-            entries: DashMap::new(),
+            entries: parking_lot::Mutex::new(LruEntries::default()),
             default_ttl: Duration::from_secs(default_ttl_seconds),
+            capacity: capacity.max(1),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
+    /// TTL of entries inserted without one
+    pub fn default_ttl(&self) -> Duration {
+        self.default_ttl
+    }
+
     pub fn get(&self, key: &str) -> Option<CompletionResponse> {
-        if let Some(entry) = self.entries.get(key) {
-            if !entry.is_expired() {
-                return Some(entry.response.clone());
-            } else {
-                // Remove expired entry
-                drop(entry);
-                self.entries.remove(key);
-            }
+        let mut entries = self.entries.lock();
+        let lookup = entries.touch(key).map(|entry| (!entry.is_expired()).then(|| entry.response.clone()));
+        if let Some(None) = lookup {
+            entries.remove(key);
         }
-        None
+        let cached = lookup.flatten();
+        let counter = if cached.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
     }
 
     pub fn insert(&self, key: String, response: CompletionResponse, ttl: Option<Duration>) {
@@ -948,11 +1245,45 @@ impl ResponseCache {
             created_at: Instant::now(),
             ttl: ttl.unwrap_or(self.default_ttl),
         };
-        self.entries.insert(key, entry);
+        let mut entries = self.entries.lock();
+        entries.insert(key, entry);
+        while entries.entries.len() > self.capacity {
+            entries.evict_least_recent();
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
     }
 
     pub fn clear_expired(&self) {
-        self.entries.retain(|_, entry| !entry.is_expired());
+        let mut entries = self.entries.lock();
+        let expired: Vec<String> = entries
+            .entries
+            .iter()
+            .filter(|(_, (entry, _))| entry.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            entries.remove(&key);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.lock().entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Cache key for `request`, or `None` if its response must not be shared
+    ///
+    /// Responses to requests made with caller-supplied credentials belong to
+    /// that caller alone, so they are neither served from nor stored in a cache.
+    /// Nor are sampled responses, unless the request opts in.
+    pub fn shared_cache_key(request: &CompletionRequest) -> Option<String> {
+        (request.credentials_override.is_none() && request.is_cacheable())
+            .then(|| Self::generate_cache_key(request))
     }
 
     pub fn generate_cache_key(request: &CompletionRequest) -> String {
@@ -971,6 +1302,9 @@ impl ResponseCache {
             };
             key_parts.push(format!("{}:{}", role_str, message.content));
         }
+        for (input, output) in &request.few_shot {
+            key_parts.push(format!("example:{}=>{}", input, output));
+        }
         
         let mut hasher = DefaultHasher::new();
         key_parts.join("|").hash(&mut hasher);
@@ -978,6 +1312,23 @@ impl ResponseCache {
     }
 }
 
+/// Next complete Server-Sent Event from `response`, or `None` once the body ends
+async fn next_sse_event(response: &mut reqwest::Response, buffer: &mut String) -> Result<Option<String>> {
+    loop {
+        if let Some(event_end) = buffer.find("\n\n") {
+            let event = buffer[..event_end].to_string();
+            buffer.drain(..event_end + 2);
+            return Ok(Some(event));
+        }
+
+        match response.chunk().await {
+            Ok(Some(chunk)) if !chunk.is_empty() => buffer.push_str(&String::from_utf8_lossy(&chunk)),
+            Ok(_) => return Ok(None),
+            Err(e) => return Err(WritemagicError::network(format!("Streaming error: {}", e))),
+        }
+    }
+}
+
 /// Claude streaming response implementation
 pub struct ClaudeStreamingResponse {
     response: reqwest::Response,
@@ -995,76 +1346,81 @@ impl ClaudeStreamingResponse {
             accumulated_content: String::new(),
         }
     }
-}
 
-#[async_trait]
-impl StreamingResponse for ClaudeStreamingResponse {
-    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
-        if self.is_complete {
-            return Ok(None);
-        }
-
-        // Read next bytes from response
-        let chunk = match self.response.chunk().await {
-            Ok(Some(chunk)) => {
-                self.buffer.push_str(&String::from_utf8_lossy(&chunk));
-                chunk
-            }
-            Ok(None) => {
+    /// Parse one Server-Sent Event, returning its text delta if it carries one
+    fn parse_event(&mut self, event_data: &str) -> Result<Option<StreamingChunk>> {
+        for line in event_data.lines() {
+            let Some(json_data) = line.strip_prefix("data: ") else {
+                continue;
+            };
+            if json_data == "[DONE]" {
                 self.is_complete = true;
                 return Ok(None);
             }
-            Err(e) => {
-                self.is_complete = true;
-                return Err(WritemagicError::network(format!("Streaming error: {}", e)));
+
+            let parsed = match serde_json::from_str::<serde_json::Value>(json_data) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    log::warn!("Failed to parse streaming JSON: {}", e);
+                    continue;
+                }
+            };
+
+            match parsed["type"].as_str() {
+                Some("message_stop") => {
+                    self.is_complete = true;
+                    return Ok(None);
+                }
+                Some("error") => {
+                    self.is_complete = true;
+                    let message = parsed["error"]["message"].as_str().unwrap_or("Unknown error");
+                    return Err(WritemagicError::ai_provider(format!("Claude streaming failed: {}", message)));
+                }
+                _ => {}
             }
-        };
 
-        if chunk.is_empty() {
-            self.is_complete = true;
-            return Ok(None);
+            if let Some(content) = parsed["delta"]["text"].as_str() {
+                self.accumulated_content.push_str(content);
+
+                return Ok(Some(StreamingChunk {
+                    content: content.to_string(),
+                    finish_reason: parsed["delta"]["stop_reason"].as_str()
+                        .and_then(|r| match r {
+                            "end_turn" => Some(FinishReason::Stop),
+                            "max_tokens" => Some(FinishReason::Length),
+                            _ => None,
+                        }),
+                    usage: None, // Usage typically comes at the end
+                }));
+            }
         }
 
-        // Parse Server-Sent Events format
-        if let Some(event_end) = self.buffer.find("\n\n") {
-            let event_data = self.buffer[..event_end].to_string();
-            self.buffer.drain(..event_end + 2);
-
-            // Parse the event data
-            for line in event_data.lines() {
-                if line.starts_with("data: ") {
-                    let json_data = &line[6..];
-                    if json_data == "[DONE]" {
-                        self.is_complete = true;
-                        return Ok(None);
-                    }
+        Ok(None)
+    }
+}
 
-                    match serde_json::from_str::<serde_json::Value>(json_data) {
-                        Ok(parsed) => {
-                            if let Some(content) = parsed["delta"]["text"].as_str() {
-                                self.accumulated_content.push_str(content);
-                                
-                                return Ok(Some(StreamingChunk {
-                                    content: content.to_string(),
-                                    finish_reason: parsed["delta"]["stop_reason"].as_str()
-                                        .and_then(|r| match r {
-                                            "end_turn" => Some(FinishReason::Stop),
-                                            "max_tokens" => Some(FinishReason::Length),
-                                            _ => None,
-                                        }),
-                                    usage: None, // Usage typically comes at the end
-                                }));
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to parse streaming JSON: {}", e);
-                        }
-                    }
+#[async_trait]
+impl StreamingResponse for ClaudeStreamingResponse {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        // Events without text (pings, block starts) are skipped until one carries a delta
+        while !self.is_complete {
+            let event = match next_sse_event(&mut self.response, &mut self.buffer).await {
+                Ok(Some(event)) => event,
+                Ok(None) => {
+                    self.is_complete = true;
+                    break;
                 }
+                Err(e) => {
+                    self.is_complete = true;
+                    return Err(e);
+                }
+            };
+
+            if let Some(chunk) = self.parse_event(&event)? {
+                return Ok(Some(chunk));
             }
         }
 
-        // If no complete event found, continue reading
         Ok(None)
     }
 
@@ -1094,103 +1450,391 @@ impl OpenAIStreamingResponse {
             accumulated_content: String::new(),
         }
     }
+
+    /// Parse one Server-Sent Event, returning its content delta if it carries one
+    fn parse_event(&mut self, event_data: &str) -> Result<Option<StreamingChunk>> {
+        for line in event_data.lines() {
+            let Some(json_data) = line.strip_prefix("data: ") else {
+                continue;
+            };
+            if json_data == "[DONE]" {
+                self.is_complete = true;
+                return Ok(None);
+            }
+
+            let parsed = match serde_json::from_str::<serde_json::Value>(json_data) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    log::warn!("Failed to parse OpenAI streaming JSON: {}", e);
+                    continue;
+                }
+            };
+
+            if let Some(message) = parsed["error"]["message"].as_str() {
+                self.is_complete = true;
+                return Err(WritemagicError::ai_provider(format!("OpenAI streaming failed: {}", message)));
+            }
+
+            let Some(choice) = parsed["choices"].as_array().and_then(|choices| choices.first()) else {
+                continue;
+            };
+            if let Some(content) = choice["delta"]["content"].as_str() {
+                self.accumulated_content.push_str(content);
+
+                return Ok(Some(StreamingChunk {
+                    content: content.to_string(),
+                    finish_reason: choice["finish_reason"].as_str()
+                        .and_then(|r| match r {
+                            "stop" => Some(FinishReason::Stop),
+                            "length" => Some(FinishReason::Length),
+                            "content_filter" => Some(FinishReason::ContentFilter),
+                            "tool_calls" => Some(FinishReason::ToolCalls),
+                            "function_call" => Some(FinishReason::FunctionCall),
+                            _ => None,
+                        }),
+                    usage: parsed["usage"].as_object().map(|usage| {
+                        Usage {
+                            prompt_tokens: usage.get("prompt_tokens")
+                                .and_then(|v| v.as_u64())
+                                .unwrap_or(0) as u32,
+                            completion_tokens: usage.get("completion_tokens")
+                                .and_then(|v| v.as_u64())
+                                .unwrap_or(0) as u32,
+                            total_tokens: usage.get("total_tokens")
+                                .and_then(|v| v.as_u64())
+                                .unwrap_or(0) as u32,
+                        }
+                    }),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
 }
 
 #[async_trait]
 impl StreamingResponse for OpenAIStreamingResponse {
     async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
-        if self.is_complete {
-            return Ok(None);
-        }
+        // Events without content (role announcements, bare finish reasons) are skipped
+        while !self.is_complete {
+            let event = match next_sse_event(&mut self.response, &mut self.buffer).await {
+                Ok(Some(event)) => event,
+                Ok(None) => {
+                    self.is_complete = true;
+                    break;
+                }
+                Err(e) => {
+                    self.is_complete = true;
+                    return Err(e);
+                }
+            };
 
-        // Read next bytes from response
-        let chunk = match self.response.chunk().await {
-            Ok(Some(chunk)) => {
-                self.buffer.push_str(&String::from_utf8_lossy(&chunk));
-                chunk
-            }
-            Ok(None) => {
-                self.is_complete = true;
-                return Ok(None);
+            if let Some(chunk) = self.parse_event(&event)? {
+                return Ok(Some(chunk));
             }
-            Err(e) => {
-                self.is_complete = true;
-                return Err(WritemagicError::network(format!("Streaming error: {}", e)));
+        }
+
+        Ok(None)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete
+    }
+
+    fn get_partial_response(&self) -> String {
+        self.accumulated_content.clone()
+    }
+}
+
+/// Streaming response whose first chunk was read ahead, e.g. to choose a provider
+pub struct PrimedStreamingResponse {
+    first: Option<StreamingChunk>,
+    inner: Box<dyn StreamingResponse>,
+}
+
+impl PrimedStreamingResponse {
+    pub fn new(first: Option<StreamingChunk>, inner: Box<dyn StreamingResponse>) -> Self {
+        Self { first, inner }
+    }
+}
+
+#[async_trait]
+impl StreamingResponse for PrimedStreamingResponse {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        match self.first.take() {
+            Some(chunk) => Ok(Some(chunk)),
+            None => self.inner.next_chunk().await,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.first.is_none() && self.inner.is_complete()
+    }
+
+    fn get_partial_response(&self) -> String {
+        self.inner.get_partial_response()
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.inner.is_truncated()
+    }
+}
+
+/// Absolute ceiling on streamed output, enforced whatever `max_tokens` was requested
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamOutputLimit {
+    pub max_tokens: u32,
+    pub max_bytes: usize,
+}
+
+impl Default for StreamOutputLimit {
+    fn default() -> Self {
+        Self {
+            max_tokens: 32_768,
+            max_bytes: 256 * 1024,
+        }
+    }
+}
+
+/// Streaming response that stops consuming its provider once a `StreamOutputLimit` is reached
+pub struct GuardedStreamingResponse {
+    inner: Box<dyn StreamingResponse>,
+    limit: StreamOutputLimit,
+    provider_name: String,
+    model: String,
+    tokenizer: Arc<crate::tokenization::TokenizationService>,
+    delivered: String,
+    delivered_tokens: u32,
+    truncated: bool,
+}
+
+impl GuardedStreamingResponse {
+    pub fn new(
+        inner: Box<dyn StreamingResponse>,
+        limit: StreamOutputLimit,
+        provider_name: impl Into<String>,
+        model: impl Into<String>,
+        tokenizer: Arc<crate::tokenization::TokenizationService>,
+    ) -> Self {
+        Self {
+            inner,
+            limit,
+            provider_name: provider_name.into(),
+            model: model.into(),
+            tokenizer,
+            delivered: String::new(),
+            delivered_tokens: 0,
+            truncated: false,
+        }
+    }
+
+    /// Longest prefix of `content` within the remaining token and byte budget
+    fn fitting_prefix<'a>(&self, content: &'a str) -> Result<&'a str> {
+        let remaining_bytes = self.limit.max_bytes.saturating_sub(self.delivered.len());
+        let remaining_tokens = self.limit.max_tokens.saturating_sub(self.delivered_tokens);
+
+        let mut boundaries: Vec<usize> = content.char_indices().map(|(i, _)| i).skip(1).collect();
+        boundaries.push(content.len());
+        boundaries.retain(|&end| end <= remaining_bytes);
+
+        // Token counts grow with the prefix, so binary search the longest one that fits
+        let (mut low, mut high) = (0, boundaries.len());
+        while low < high {
+            let mid = (low + high + 1) / 2;
+            if self.tokenizer.count_tokens(&content[..boundaries[mid - 1]], &self.model)? <= remaining_tokens {
+                low = mid;
+            } else {
+                high = mid - 1;
             }
-        };
+        }
 
-        if chunk.is_empty() {
-            self.is_complete = true;
+        Ok(if low == 0 { "" } else { &content[..boundaries[low - 1]] })
+    }
+
+    fn cut_off(&mut self) {
+        self.truncated = true;
+        let limit = if self.delivered.len() >= self.limit.max_bytes { "bytes" } else { "tokens" };
+        counter!("ai_stream_truncated_total", 1, &[("provider", self.provider_name.clone()), ("limit", limit.to_string())]);
+        tracing::warn!(
+            "Stream from provider '{}' cut off at the {} ceiling after {} bytes and {} tokens",
+            self.provider_name,
+            limit,
+            self.delivered.len(),
+            self.delivered_tokens
+        );
+    }
+}
+
+#[async_trait]
+impl StreamingResponse for GuardedStreamingResponse {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        if self.truncated {
             return Ok(None);
         }
 
-        // Parse Server-Sent Events format
-        if let Some(event_end) = self.buffer.find("\n\n") {
-            let event_data = self.buffer[..event_end].to_string();
-            self.buffer.drain(..event_end + 2);
-
-            // Parse the event data
-            for line in event_data.lines() {
-                if line.starts_with("data: ") {
-                    let json_data = &line[6..];
-                    if json_data == "[DONE]" {
-                        self.is_complete = true;
-                        return Ok(None);
-                    }
+        let Some(mut chunk) = self.inner.next_chunk().await? else {
+            return Ok(None);
+        };
 
-                    match serde_json::from_str::<serde_json::Value>(json_data) {
-                        Ok(parsed) => {
-                            if let Some(choices) = parsed["choices"].as_array() {
-                                if let Some(choice) = choices.first() {
-                                    if let Some(content) = choice["delta"]["content"].as_str() {
-                                        self.accumulated_content.push_str(content);
-                                        
-                                        return Ok(Some(StreamingChunk {
-                                            content: content.to_string(),
-                                            finish_reason: choice["finish_reason"].as_str()
-                                                .and_then(|r| match r {
-                                                    "stop" => Some(FinishReason::Stop),
-                                                    "length" => Some(FinishReason::Length),
-                                                    "content_filter" => Some(FinishReason::ContentFilter),
-                                                    "tool_calls" => Some(FinishReason::ToolCalls),
-                                                    "function_call" => Some(FinishReason::FunctionCall),
-                                                    _ => None,
-                                                }),
-                                            usage: parsed["usage"].as_object().map(|usage| {
-                                                Usage {
-                                                    prompt_tokens: usage.get("prompt_tokens")
-                                                        .and_then(|v| v.as_u64())
-                                                        .unwrap_or(0) as u32,
-                                                    completion_tokens: usage.get("completion_tokens")
-                                                        .and_then(|v| v.as_u64())
-                                                        .unwrap_or(0) as u32,
-                                                    total_tokens: usage.get("total_tokens")
-                                                        .and_then(|v| v.as_u64())
-                                                        .unwrap_or(0) as u32,
-                                                }
-                                            }),
-                                        }));
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to parse OpenAI streaming JSON: {}", e);
-                        }
+        let chunk_tokens = self.tokenizer.count_tokens(&chunk.content, &self.model)?;
+        let fits = self.delivered.len() + chunk.content.len() <= self.limit.max_bytes
+            && self.delivered_tokens + chunk_tokens <= self.limit.max_tokens;
+        if fits {
+            self.delivered.push_str(&chunk.content);
+            self.delivered_tokens += chunk_tokens;
+            return Ok(Some(chunk));
+        }
+
+        let prefix = self.fitting_prefix(&chunk.content)?.to_string();
+        self.delivered_tokens += self.tokenizer.count_tokens(&prefix, &self.model)?;
+        self.delivered.push_str(&prefix);
+        self.cut_off();
+
+        chunk.content = prefix;
+        chunk.finish_reason = Some(FinishReason::Length);
+        Ok(Some(chunk))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.truncated || self.inner.is_complete()
+    }
+
+    fn get_partial_response(&self) -> String {
+        self.delivered.clone()
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// Longest text held back while waiting for the end of a word, so PII split
+/// across chunks is still masked
+const MAX_HELD_BACK_BYTES: usize = 256;
+
+/// Called once with a stream's raw output to bill it
+pub type SpendRecorder = Box<dyn FnOnce(&str) + Send + Sync>;
+
+/// Stream as handed to callers: dispatched, sanitized and paid for
+///
+/// Holds its dispatch slot until the stream ends or is dropped. Text is
+/// released a word at a time with PII masked, and `record_spend` is called
+/// once with the provider's raw output when the stream ends or is dropped.
+pub struct AccountedStreamingResponse {
+    inner: Box<dyn StreamingResponse>,
+    permit: Option<crate::dispatch::DispatchPermit>,
+    sanitizer: Arc<crate::security::ContentSanitizationService>,
+    record_spend: Option<SpendRecorder>,
+    /// Received text not yet released, ending in a possibly unfinished word
+    held_back: String,
+    delivered: String,
+    finished: bool,
+    /// Provider error returned once the text received before it is released
+    error: Option<WritemagicError>,
+}
+
+impl AccountedStreamingResponse {
+    pub fn new(
+        inner: Box<dyn StreamingResponse>,
+        permit: crate::dispatch::DispatchPermit,
+        sanitizer: Arc<crate::security::ContentSanitizationService>,
+        record_spend: SpendRecorder,
+    ) -> Self {
+        Self {
+            inner,
+            permit: Some(permit),
+            sanitizer,
+            record_spend: Some(record_spend),
+            held_back: String::new(),
+            delivered: String::new(),
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// Sanitized text ready to release, keeping an unfinished trailing word unless `flush`
+    fn release(&mut self, flush: bool) -> String {
+        let end = if flush || self.held_back.len() > MAX_HELD_BACK_BYTES {
+            self.held_back.len()
+        } else {
+            self.held_back
+                .rfind(char::is_whitespace)
+                .map(|start| start + self.held_back[start..].chars().next().map_or(1, char::len_utf8))
+                .unwrap_or(0)
+        };
+        let text: String = self.held_back.drain(..end).collect();
+        let text = self.sanitizer.sanitize_output_text(&text);
+        self.delivered.push_str(&text);
+        text
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        if let Some(record_spend) = self.record_spend.take() {
+            record_spend(&self.inner.get_partial_response());
+        }
+        self.permit.take();
+    }
+}
+
+#[async_trait]
+impl StreamingResponse for AccountedStreamingResponse {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        while !self.finished {
+            let chunk = match self.inner.next_chunk().await {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    self.finish();
+                    let content = self.release(true);
+                    if content.is_empty() {
+                        return Err(e);
                     }
+                    self.error = Some(e);
+                    return Ok(Some(StreamingChunk { content, finish_reason: None, usage: None }));
                 }
+            };
+
+            let Some(mut chunk) = chunk else {
+                self.finish();
+                let content = self.release(true);
+                return Ok((!content.is_empty()).then_some(StreamingChunk { content, finish_reason: None, usage: None }));
+            };
+
+            self.held_back.push_str(&chunk.content);
+            chunk.content = self.release(chunk.finish_reason.is_some());
+            if chunk.finish_reason.is_some() {
+                self.finish();
+            }
+            if !chunk.content.is_empty() || chunk.finish_reason.is_some() {
+                return Ok(Some(chunk));
             }
         }
-
-        // If no complete event found, continue reading
         Ok(None)
     }
 
     fn is_complete(&self) -> bool {
-        self.is_complete
+        self.finished
     }
 
     fn get_partial_response(&self) -> String {
-        self.accumulated_content.clone()
+        self.delivered.clone()
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.inner.is_truncated()
+    }
+}
+
+impl Drop for AccountedStreamingResponse {
+    fn drop(&mut self) {
+        // Tokens generated before a caller gave up are still billed
+        if let Some(record_spend) = self.record_spend.take() {
+            record_spend(&self.inner.get_partial_response());
+        }
     }
-}
\ No newline at end of file
+}
@@ -49,6 +49,28 @@ impl RetryConfig {
             jitter: false,
         }
     }
+
+    /// A single attempt with no retries
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the retry following the zero-based `attempt`, with up to 10% jitter
+    pub fn delay_for_attempt(&self, attempt: usize) -> Duration {
+        let base = self.initial_delay.as_millis() as f64
+            * (self.backoff_multiplier as f64).powi(attempt.min(i32::MAX as usize) as i32);
+        let mut delay = Duration::from_millis(base.min(self.max_delay.as_millis() as f64) as u64);
+
+        if self.jitter {
+            let jitter_factor = (uuid::Uuid::new_v4().as_u128() % 100) as f64 / 100.0;
+            delay += Duration::from_millis((delay.as_millis() as f64 * 0.1 * jitter_factor) as u64);
+        }
+
+        delay.min(self.max_delay)
+    }
 }
 
 /// Circuit breaker state
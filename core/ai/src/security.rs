@@ -4,7 +4,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use regex::Regex;
-use writemagic_shared::{Result, WritemagicError};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use writemagic_shared::{EntityId, Result, WritemagicError};
+use zeroize::Zeroizing;
 use crate::providers::{CompletionRequest, CompletionResponse};
 
 /// Secure API key storage with automatic rotation support
@@ -168,6 +172,116 @@ impl Default for SecureKeyManager {
     }
 }
 
+/// Document content encrypted under its owner's derived key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedContent {
+    /// Owner whose key sealed the content; `None` uses the default key
+    pub owner: Option<EntityId>,
+    /// Random nonce followed by the AES-256-GCM ciphertext
+    pub ciphertext: Vec<u8>,
+}
+
+/// Per-user document encryption
+///
+/// Each user's key is derived from the master key held by the
+/// [`SecureKeyManager`] and the user id, so a leaked user key exposes only
+/// that user's documents. Documents without an owner use a default key
+/// derived from the master key alone.
+pub struct DocumentEncryptionService {
+    key_manager: Arc<SecureKeyManager>,
+}
+
+impl std::fmt::Debug for DocumentEncryptionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentEncryptionService")
+            .field("key_manager", &self.key_manager)
+            .finish()
+    }
+}
+
+impl DocumentEncryptionService {
+    /// Key manager entry holding the document master key
+    pub const MASTER_KEY_ID: &'static str = "document_master";
+
+    const USER_KEY_CONTEXT: &'static str = "writemagic 2024-06 document encryption user key";
+    const DEFAULT_KEY_CONTEXT: &'static str = "writemagic 2024-06 document encryption default key";
+    const NONCE_LEN: usize = 12;
+
+    /// Create the service; the master key must be stored under [`Self::MASTER_KEY_ID`]
+    pub fn new(key_manager: Arc<SecureKeyManager>) -> Self {
+        Self { key_manager }
+    }
+
+    /// Derive the encryption key for a document owner
+    fn derive_key(&self, owner: Option<&EntityId>) -> Result<Zeroizing<[u8; 32]>> {
+        let master = self.key_manager.get_key(Self::MASTER_KEY_ID)?;
+
+        let key = match owner {
+            Some(owner) => {
+                let mut material = Zeroizing::new(master.value().as_bytes().to_vec());
+                material.extend_from_slice(owner.as_uuid().as_bytes());
+                blake3::derive_key(Self::USER_KEY_CONTEXT, &material)
+            }
+            None => blake3::derive_key(Self::DEFAULT_KEY_CONTEXT, master.value().as_bytes()),
+        };
+        Ok(Zeroizing::new(key))
+    }
+
+    /// Encrypt raw bytes with the key of `owner`
+    pub fn encrypt_for(&self, owner: Option<&EntityId>, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let key = self.derive_key(owner)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_slice()));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| WritemagicError::security("Failed to encrypt document content"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt bytes produced by [`Self::encrypt_for`] with the key of `owner`
+    pub fn decrypt_for(&self, owner: Option<&EntityId>, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < Self::NONCE_LEN {
+            return Err(WritemagicError::security("Encrypted document content is truncated"));
+        }
+
+        let key = self.derive_key(owner)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_slice()));
+        let (nonce, ciphertext) = sealed.split_at(Self::NONCE_LEN);
+
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| WritemagicError::security("Failed to decrypt document content"))
+    }
+
+    /// Seal document content for its owner, as recorded in `created_by`
+    pub fn seal(&self, created_by: Option<EntityId>, content: &str) -> Result<SealedContent> {
+        Ok(SealedContent {
+            owner: created_by,
+            ciphertext: self.encrypt_for(created_by.as_ref(), content.as_bytes())?,
+        })
+    }
+
+    /// Open sealed content with its owner's key
+    pub fn open(&self, sealed: &SealedContent) -> Result<String> {
+        let plaintext = Zeroizing::new(self.decrypt_for(sealed.owner.as_ref(), &sealed.ciphertext)?);
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|_| WritemagicError::security("Decrypted document content is not valid UTF-8"))
+    }
+
+    /// Re-encrypt sealed content under a new owner's key on ownership transfer
+    pub fn transfer_ownership(&self, sealed: &SealedContent, new_owner: Option<EntityId>) -> Result<SealedContent> {
+        let plaintext = Zeroizing::new(self.decrypt_for(sealed.owner.as_ref(), &sealed.ciphertext)?);
+        Ok(SealedContent {
+            owner: new_owner,
+            ciphertext: self.encrypt_for(new_owner.as_ref(), &plaintext)?,
+        })
+    }
+}
+
 /// PII detection patterns with confidence scoring
 #[derive(Debug, Clone)]
 pub struct PIIPattern {
@@ -469,16 +583,7 @@ impl ContentSanitizationService {
         
         // Sanitize response choices
         for choice in &mut sanitized.choices {
-            let pii_matches = self.pii_detector.scan_text(&choice.message.content);
-            
-            if !pii_matches.is_empty() {
-                tracing::warn!(
-                    "PII detected in AI response: {} matches",
-                    pii_matches.len()
-                );
-                
-                choice.message.content = self.pii_detector.sanitize_text(&choice.message.content);
-            }
+            choice.message.content = self.sanitize_output_text(&choice.message.content);
         }
         
         // Clean metadata
@@ -487,6 +592,17 @@ impl ContentSanitizationService {
         Ok(sanitized)
     }
 
+    /// Mask PII in model output, such as one choice or a streamed chunk
+    pub fn sanitize_output_text(&self, text: &str) -> String {
+        let pii_matches = self.pii_detector.scan_text(text);
+        if pii_matches.is_empty() {
+            return text.to_string();
+        }
+
+        tracing::warn!("PII detected in AI response: {} matches", pii_matches.len());
+        self.pii_detector.sanitize_text(text)
+    }
+
     /// Sanitize text for logging
     pub fn sanitize_for_logging(&self, text: &str) -> String {
         // More aggressive sanitization for logs
@@ -502,6 +618,14 @@ impl ContentSanitizationService {
         }
     }
 
+    /// Sanitize HTML before it is rendered in a browser
+    ///
+    /// Only an allowlist of elements and attributes is kept; scripts, event
+    /// handlers and `javascript:` URLs are removed.
+    pub fn sanitize_html(&self, html: &str) -> String {
+        writemagic_shared::sanitize_html(html)
+    }
+
     /// Check if content contains sensitive information
     pub fn contains_sensitive_content(&self, content: &str) -> bool {
         self.pii_detector
@@ -533,6 +657,7 @@ pub enum SecurityEventType {
     KeyRotated,
     SecurityViolation,
     SuspiciousActivity,
+    CircuitBreakerTripped,
 }
 
 impl SecurityAuditLogger {
@@ -578,6 +703,15 @@ impl SecurityAuditLogger {
         events[start..].to_vec()
     }
 
+    /// Get events logged in `[start, end)`, oldest first
+    pub fn events_between(&self, start: std::time::SystemTime, end: std::time::SystemTime) -> Vec<SecurityEvent> {
+        let events = self.events.read();
+        events.iter()
+            .filter(|e| e.timestamp >= start && e.timestamp < end)
+            .cloned()
+            .collect()
+    }
+
     /// Get events by severity
     pub fn get_events_by_severity(&self, severity: PIISeverity) -> Vec<SecurityEvent> {
         let events = self.events.read();
@@ -598,6 +732,60 @@ impl Default for SecurityAuditLogger {
 mod tests {
     use super::*;
 
+    fn encryption_service() -> DocumentEncryptionService {
+        let key_manager = Arc::new(SecureKeyManager::new());
+        key_manager
+            .add_key(
+                DocumentEncryptionService::MASTER_KEY_ID.to_string(),
+                SecureApiKey::new("master".to_string(), "master-key-0123456789abcdef".to_string()),
+            )
+            .unwrap();
+        DocumentEncryptionService::new(key_manager)
+    }
+
+    #[test]
+    fn test_user_keys_do_not_cross_decrypt() {
+        let service = encryption_service();
+        let alice = EntityId::new();
+        let bob = EntityId::new();
+
+        let sealed = service.seal(Some(alice), "alice's draft").unwrap();
+
+        assert_eq!(service.open(&sealed).unwrap(), "alice's draft");
+        assert!(service.decrypt_for(Some(&bob), &sealed.ciphertext).is_err());
+        assert!(service.decrypt_for(None, &sealed.ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_ownership_transfer_reencrypts_for_new_owner() {
+        let service = encryption_service();
+        let alice = EntityId::new();
+        let bob = EntityId::new();
+        let sealed = service.seal(Some(alice), "shared chapter").unwrap();
+
+        let transferred = service.transfer_ownership(&sealed, Some(bob)).unwrap();
+
+        assert_eq!(transferred.owner, Some(bob));
+        assert_eq!(service.open(&transferred).unwrap(), "shared chapter");
+        assert!(service.decrypt_for(Some(&alice), &transferred.ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_unowned_documents_use_default_key() {
+        let service = encryption_service();
+
+        let sealed = service.seal(None, "orphaned note").unwrap();
+
+        assert_eq!(service.decrypt_for(None, &sealed.ciphertext).unwrap(), b"orphaned note");
+        assert!(service.decrypt_for(Some(&EntityId::new()), &sealed.ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_missing_master_key_is_an_error() {
+        let service = DocumentEncryptionService::new(Arc::new(SecureKeyManager::new()));
+        assert!(service.seal(None, "text").is_err());
+    }
+
     #[test]
     fn test_secure_api_key() {
         let mut key = SecureApiKey::new(
@@ -1,11 +1,16 @@
 //! AI domain services
 
-use writemagic_shared::{Result, WritemagicError};
-use crate::providers::{AIProvider, CompletionRequest, CompletionResponse, Message, ClaudeProvider, OpenAIProvider, ResponseCache};
+use writemagic_shared::{
+    current_request_id, CheckpointId, CheckpointRetention, ContextCheckpoint, ContextCheckpointStore, EntityId,
+    InMemoryContextCheckpointStore, Result, Timestamp, WritemagicError,
+};
+use crate::providers::{AIProvider, CompletionRequest, CompletionResponse, Message, ClaudeProvider, OpenAIProvider, ResponseCache, SpendRecorder};
+use crate::tokenization::{TokenUsage, Tokenizer};
 use std::sync::Arc;
 use std::collections::{HashMap, hash_map::DefaultHasher};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use std::hash::{Hash, Hasher};
 use uuid::Uuid;
 
@@ -100,8 +105,26 @@ pub struct AIOrchestrationService {
     performance_monitor: Arc<crate::performance_monitor::PerformanceMonitor>,
     performance_alerting: Arc<crate::performance_monitor::PerformanceAlerting>,
     request_scheduler: Arc<RwLock<crate::request_batcher::RequestScheduler>>,
+    stream_output_limit: crate::providers::StreamOutputLimit,
+    dispatcher: Arc<crate::dispatch::PriorityDispatcher>,
+    retry_config: crate::retry_patterns::RetryConfig,
+    cost_estimator: Arc<crate::cost::CostEstimator>,
+    spend: Arc<crate::cost::SpendTracker>,
+    monthly_budget_usd: Option<f64>,
+    credential_endpoints: Vec<String>,
+    /// Last credential check of every provider, and when it ran
+    credential_checks: Arc<RwLock<Option<CredentialChecks>>>,
 }
 
+/// When a round of provider credential checks ran, and whether each provider passed
+type CredentialChecks = (Instant, HashMap<String, bool>);
+
+/// How long a round of provider credential checks is reused by health probes
+const CREDENTIAL_CHECK_TTL: Duration = Duration::from_secs(60);
+
+/// How long one provider's credential check may take before it counts as failed
+const CREDENTIAL_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
 impl AIOrchestrationService {
     /// Create new orchestration service with all security and performance features
     pub fn new() -> Result<Self> {
@@ -133,6 +156,14 @@ impl AIOrchestrationService {
             performance_monitor,
             performance_alerting,
             request_scheduler: Arc::new(RwLock::new(crate::request_batcher::RequestScheduler::new())),
+            stream_output_limit: crate::providers::StreamOutputLimit::default(),
+            dispatcher: Arc::new(crate::dispatch::PriorityDispatcher::new(&crate::dispatch::DispatchConfig::default())),
+            retry_config: crate::retry_patterns::RetryConfig::no_retry(),
+            cost_estimator: Arc::new(crate::cost::CostEstimator::new()),
+            spend: Arc::new(crate::cost::SpendTracker::new()),
+            monthly_budget_usd: None,
+            credential_endpoints: crate::providers::DEFAULT_CREDENTIAL_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
+            credential_checks: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -169,6 +200,14 @@ impl AIOrchestrationService {
             performance_monitor,
             performance_alerting,
             request_scheduler: Arc::new(RwLock::new(crate::request_batcher::RequestScheduler::new())),
+            stream_output_limit: crate::providers::StreamOutputLimit::default(),
+            dispatcher: Arc::new(crate::dispatch::PriorityDispatcher::new(&crate::dispatch::DispatchConfig::default())),
+            retry_config: crate::retry_patterns::RetryConfig::no_retry(),
+            cost_estimator: Arc::new(crate::cost::CostEstimator::new()),
+            spend: Arc::new(crate::cost::SpendTracker::new()),
+            monthly_budget_usd: None,
+            credential_endpoints: crate::providers::DEFAULT_CREDENTIAL_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
+            credential_checks: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -186,6 +225,104 @@ impl AIOrchestrationService {
         self.fallback_order = order;
     }
 
+    /// Set the concurrency limit and aging under which requests are dispatched by priority
+    pub fn set_dispatch_config(&mut self, config: &crate::dispatch::DispatchConfig) {
+        self.dispatcher = Arc::new(crate::dispatch::PriorityDispatcher::new(config));
+    }
+
+    /// Dispatcher admitting requests to providers
+    pub fn dispatcher(&self) -> &Arc<crate::dispatch::PriorityDispatcher> {
+        &self.dispatcher
+    }
+
+    /// Set the absolute ceiling applied to every streamed response
+    pub fn set_stream_output_limit(&mut self, limit: crate::providers::StreamOutputLimit) {
+        self.stream_output_limit = limit;
+    }
+
+    /// Replace the response cache with one keeping at most `capacity` entries for `ttl_seconds`
+    pub fn set_response_cache(&mut self, ttl_seconds: u64, capacity: usize) {
+        self.global_cache = Arc::new(ResponseCache::with_capacity(ttl_seconds, capacity));
+    }
+
+    /// Hit and miss counts of the response cache
+    pub fn cache_stats(&self) -> crate::providers::CacheStats {
+        self.global_cache.stats()
+    }
+
+    /// Set how transient failures of a single provider call are retried before falling back
+    pub fn set_retry_config(&mut self, config: crate::retry_patterns::RetryConfig) {
+        self.retry_config = config;
+    }
+
+    /// Refuse completions with `BudgetExceeded` once this month's estimated spend exceeds `budget_usd`
+    ///
+    /// Requests carrying the caller's own credentials are billed to the caller,
+    /// so they neither count towards the budget nor are refused by it.
+    pub fn set_monthly_budget(&mut self, budget_usd: Option<f64>) {
+        self.monthly_budget_usd = budget_usd;
+    }
+
+    /// Set the endpoints a request's own credentials may point its base URL at
+    ///
+    /// Defaults to the official provider APIs, so callers cannot make the
+    /// service send requests to arbitrary hosts.
+    pub fn set_credential_endpoints(&mut self, endpoints: Vec<String>) {
+        self.credential_endpoints = endpoints;
+    }
+
+    /// Price table completions are costed with; prices set on it apply from the next completion
+    pub fn cost_estimator(&self) -> &crate::cost::CostEstimator {
+        &self.cost_estimator
+    }
+
+    /// Estimated spend for the current month
+    pub fn spend_tracker(&self) -> &crate::cost::SpendTracker {
+        &self.spend
+    }
+
+    /// Estimated cost of `usage`, from the price table or else the provider's advertised rates
+    fn estimate_cost(&self, provider_name: &str, model: &str, usage: &TokenUsage) -> f64 {
+        self.cost_estimator.estimate(provider_name, model, usage).unwrap_or(usage.estimated_cost)
+    }
+
+    /// Records what a stream from `provider` cost, given the text it produced
+    ///
+    /// Streams on the caller's own credentials are billed to the caller and not recorded.
+    fn stream_spend_recorder(
+        &self,
+        provider_name: &str,
+        provider: &dyn AIProvider,
+        request: CompletionRequest,
+    ) -> SpendRecorder {
+        if request.credentials_override.is_some() {
+            return Box::new(|_: &str| {});
+        }
+
+        let capabilities = provider.capabilities();
+        let provider_name = provider_name.to_string();
+        let tokenizer = self.tokenization_service.clone();
+        let cost_estimator = self.cost_estimator.clone();
+        let spend = self.spend.clone();
+
+        Box::new(move |output: &str| {
+            let usage = match tokenizer.calculate_usage(
+                &request,
+                output,
+                capabilities.input_cost_per_token,
+                capabilities.output_cost_per_token,
+            ) {
+                Ok(usage) => usage,
+                Err(e) => {
+                    log::warn!("Failed to count tokens of a stream from {}, its spend is not recorded: {}", provider_name, e);
+                    return;
+                }
+            };
+            let cost = cost_estimator.estimate(&provider_name, &request.model, &usage).unwrap_or(usage.estimated_cost);
+            spend.record(&provider_name, cost, chrono::Utc::now());
+        })
+    }
+
     /// Get the best available provider based on health and performance
     pub async fn get_best_provider(&self) -> Option<String> {
         let health_map = self.provider_health.read().await;
@@ -216,8 +353,12 @@ impl AIOrchestrationService {
     }
 
     /// Complete with comprehensive security, tokenization, and circuit breaker protection
+    ///
+    /// Tracked under the caller's correlation id when one is in scope.
+    #[tracing::instrument(skip_all, fields(request_id = tracing::field::Empty, model = %request.model))]
     pub async fn complete_with_fallback(&self, mut request: CompletionRequest) -> Result<CompletionResponse> {
-        let request_id = Uuid::new_v4().to_string();
+        let request_id = current_request_id().unwrap_or_else(|| Uuid::new_v4().to_string());
+        tracing::Span::current().record("request_id", request_id.as_str());
         let request_priority = request.priority.clone();
         
         // Start performance tracking
@@ -228,6 +369,12 @@ impl AIOrchestrationService {
             request_priority.clone(),
         );
 
+        // Few-shot examples become ordinary turns, so they are sanitized and fitted like the prompt
+        request = self.context_manager.expand_few_shot(request).map_err(|e| {
+            self.performance_monitor.fail_request(perf_metric.clone(), "context_management".to_string());
+            e
+        })?;
+
         // Security: Sanitize request first
         request = self.content_sanitizer.sanitize_request(&request).map_err(|e| {
             self.security_logger.log_event(
@@ -247,23 +394,49 @@ impl AIOrchestrationService {
 
         // Context Management: Apply context optimization
         let optimized_messages = self.context_manager
-            .manage_context(request.messages.clone(), &request.model)
+            .fit_context_with(request.messages.clone(), &request.model, |provider, summary| self.complete_summary(provider, summary))
+            .await
             .map_err(|e| {
                 self.performance_monitor.fail_request(perf_metric.clone(), "context_management".to_string());
                 e
             })?;
         request.messages = optimized_messages;
 
-        // Generate secure cache key
-        let cache_key = self.generate_secure_cache_key(&request);
+        self.validate_provider_preference(&request).map_err(|e| {
+            self.performance_monitor.fail_request(perf_metric.clone(), "unknown_provider".to_string());
+            e
+        })?;
+
+        // Caller-supplied credentials go straight to the provider they name, bypassing
+        // the shared cache and the shared provider's health tracking
+        if let Some(credentials) = &request.credentials_override {
+            let provider_name = credentials.provider.clone();
+            credentials.check_base_url(&self.credential_endpoints).map_err(|e| {
+                self.performance_monitor.fail_request(perf_metric.clone(), "credentials_endpoint".to_string());
+                e
+            })?;
+            let _permit = self.dispatcher.acquire(crate::dispatch::AiPriority::from(&request_priority)).await;
+            return self.complete_with_credentials(&request, &provider_name, perf_metric).await;
+        }
+
+        // Sampled responses skip the cache unless the request opts in
+        let cache_key = request.is_cacheable().then(|| self.generate_secure_cache_key(&request));
         
         // Check cache first
-        if let Some(cached_response) = self.global_cache.get(&cache_key) {
+        if let Some(cached_response) = cache_key.as_ref().and_then(|key| self.global_cache.get(key)) {
             log::debug!("Global cache hit for model: {}", request.model);
             self.performance_monitor.record_cache_hit(perf_metric);
             return Ok(cached_response);
         }
 
+        self.spend.check_budget(self.monthly_budget_usd, chrono::Utc::now()).map_err(|e| {
+            self.performance_monitor.fail_request(perf_metric.clone(), "budget_exceeded".to_string());
+            e
+        })?;
+
+        // Wait for a dispatch slot, interactive requests first
+        let _permit = self.dispatcher.acquire(crate::dispatch::AiPriority::from(&request_priority)).await;
+
         let mut last_error = None;
         let mut providers_tried = Vec::new();
         let request_start = Instant::now();
@@ -274,13 +447,7 @@ impl AIOrchestrationService {
         for provider_name in ordered_providers {
             if let Some(provider) = self.providers.get(&provider_name) {
                 // Circuit breaker check
-                let circuit_breaker = self.circuit_breakers
-                    .get(&provider_name)
-                    .unwrap_or_else(|| {
-                        // Create circuit breaker for provider if not exists
-                        let config = self.get_circuit_breaker_config(&provider_name);
-                        self.circuit_breakers.register(provider_name.clone(), config)
-                    });
+                let circuit_breaker = self.circuit_breaker(&provider_name);
 
                 if !circuit_breaker.can_execute().await {
                     log::debug!("Circuit breaker open for provider: {}", provider_name);
@@ -290,12 +457,8 @@ impl AIOrchestrationService {
 
                 let provider_start = Instant::now();
                 
-                // Execute with circuit breaker protection
-                let result = circuit_breaker.execute(|| {
-                    let req = request.clone();
-                    let prov = provider.clone();
-                    async move { prov.complete(&req).await }
-                }).await;
+                // Execute with circuit breaker protection, retrying transient failures
+                let result = self.complete_with_retry(&circuit_breaker, provider, &request).await;
 
                 match result {
                     Ok(mut response) => {
@@ -316,15 +479,20 @@ impl AIOrchestrationService {
                         response.usage.prompt_tokens = usage.input_tokens;
                         response.usage.completion_tokens = usage.output_tokens;
                         response.usage.total_tokens = usage.total_tokens;
+                        response.metadata.insert(crate::providers::PROVIDER_METADATA_KEY.to_string(), provider_name.clone());
 
                         // Record success
                         self.record_provider_success(&provider_name, duration).await;
+
+                        let cost = self.estimate_cost(&provider_name, &request.model, &usage);
+                        self.spend.record(&provider_name, cost, chrono::Utc::now());
                         
-                        // Update performance metrics
+                        // Update performance metrics, under the provider that served the request
+                        perf_metric.provider_name = provider_name.clone();
                         perf_metric.input_tokens = usage.input_tokens;
                         perf_metric.output_tokens = usage.output_tokens;
                         perf_metric.total_tokens = usage.total_tokens;
-                        perf_metric.cost = usage.estimated_cost;
+                        perf_metric.cost = cost;
                         
                         self.performance_monitor.complete_request(perf_metric);
                         
@@ -334,8 +502,10 @@ impl AIOrchestrationService {
                         }
                         
                         // Cache with content-sensitive TTL
-                        let cache_ttl = self.calculate_cache_ttl(&response);
-                        self.global_cache.insert(cache_key, response.clone(), cache_ttl);
+                        if let Some(cache_key) = cache_key {
+                            let cache_ttl = self.calculate_cache_ttl(&response);
+                            self.global_cache.insert(cache_key, response.clone(), cache_ttl);
+                        }
                         
                         // Log performance metrics
                         tracing::info!(
@@ -343,7 +513,7 @@ impl AIOrchestrationService {
                             duration_ms = duration.as_millis(),
                             input_tokens = usage.input_tokens,
                             output_tokens = usage.output_tokens,
-                            estimated_cost = usage.estimated_cost,
+                            estimated_cost = cost,
                             "AI request completed successfully"
                         );
                         
@@ -352,9 +522,6 @@ impl AIOrchestrationService {
                     Err(e) => {
                         let duration = provider_start.elapsed();
                         
-                        // Record failure - circuit breaker already recorded it
-                        self.record_provider_failure(&provider_name).await;
-                        
                         providers_tried.push(provider_name.clone());
                         
                         // Log sanitized error (no sensitive data)
@@ -365,6 +532,16 @@ impl AIOrchestrationService {
                             error = sanitized_error,
                             "Provider request failed"
                         );
+
+                        // A rejected request (e.g. content policy) fails the same way everywhere,
+                        // so neither retry nor fall back, and don't count it against the provider
+                        if e.is_request_rejection() {
+                            self.performance_monitor.fail_request(perf_metric.clone(), "request_rejected".to_string());
+                            return Err(e);
+                        }
+                        
+                        // Record failure - circuit breaker already recorded it
+                        self.record_provider_failure(&provider_name).await;
                         
                         last_error = Some(e);
                         continue;
@@ -373,6 +550,21 @@ impl AIOrchestrationService {
             }
         }
 
+        // Nothing was attempted because every remaining provider's breaker is open
+        if last_error.is_none() {
+            let mut open: Vec<String> = self.circuit_breakers
+                .get_all_statuses()
+                .into_iter()
+                .filter(|(_, status)| matches!(status.state, crate::circuit_breaker::CircuitState::Open { .. }))
+                .map(|(name, _)| name)
+                .collect();
+            if !open.is_empty() {
+                open.sort();
+                self.performance_monitor.fail_request(perf_metric, "circuit_open".to_string());
+                return Err(WritemagicError::circuit_open(open.join(", ")));
+            }
+        }
+
         // All providers failed - record performance failure and log security event
         self.performance_monitor.fail_request(perf_metric.clone(), "all_providers_failed".to_string());
         
@@ -394,7 +586,209 @@ impl AIOrchestrationService {
             sanitized_error
         );
         
-        Err(WritemagicError::ai_provider(error_msg))
+        match last_error.as_ref().and_then(|e| e.provider_error()) {
+            Some(details) => Err(WritemagicError::ai_provider_with_details(error_msg, details.clone())),
+            None => Err(WritemagicError::ai_provider(error_msg)),
+        }
+    }
+
+    /// Complete with fallback, abandoning the in-flight provider request once `cancel` fires
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn complete_with_fallback_cancellable(
+        &self,
+        request: CompletionRequest,
+        cancel: CancellationToken,
+    ) -> Result<CompletionResponse> {
+        let model = request.model.clone();
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                tracing::debug!(model = model, "AI request cancelled by caller");
+                Err(WritemagicError::cancelled())
+            }
+            result = self.complete_with_fallback(request) => result,
+        }
+    }
+
+    /// Check that every provider a request asks for is registered
+    fn validate_provider_preference(&self, request: &CompletionRequest) -> Result<()> {
+        match request.provider_preference.iter().find(|name| !self.providers.contains_key(name.as_str())) {
+            Some(name) => Err(WritemagicError::configuration(format!("Unknown AI provider: {}", name))),
+            None => Ok(()),
+        }
+    }
+
+    /// Complete a context summary request under the same guards as any other completion
+    ///
+    /// The request is sanitized and checked against the budget, and goes
+    /// through the summary provider's circuit breaker; the response is
+    /// sanitized and its cost counted towards the month's spend.
+    async fn complete_summary(&self, provider: Arc<dyn AIProvider>, request: CompletionRequest) -> Result<CompletionResponse> {
+        let request = self.content_sanitizer.sanitize_request(&request)?;
+        self.spend.check_budget(self.monthly_budget_usd, chrono::Utc::now())?;
+
+        let provider_name = provider.name().to_string();
+        let circuit_breaker = self.circuit_breaker(&provider_name);
+        if !circuit_breaker.can_execute().await {
+            return Err(WritemagicError::ai_provider(format!("Circuit breaker open for summary provider {}", provider_name)));
+        }
+        let response = self.complete_with_retry(&circuit_breaker, &provider, &request).await?;
+        let response = self.content_sanitizer.sanitize_response(&response)?;
+
+        let usage = self.tokenization_service.calculate_usage(
+            &request,
+            response.choices.first().map(|c| &c.message.content).unwrap_or(&String::new()),
+            provider.capabilities().input_cost_per_token,
+            provider.capabilities().output_cost_per_token,
+        )?;
+        self.spend.record(&provider_name, self.estimate_cost(&provider_name, &request.model, &usage), chrono::Utc::now());
+        Ok(response)
+    }
+
+    /// Call one provider through its circuit breaker, retrying transient failures per the retry config
+    async fn complete_with_retry(
+        &self,
+        circuit_breaker: &crate::circuit_breaker::CircuitBreaker,
+        provider: &Arc<dyn AIProvider>,
+        request: &CompletionRequest,
+    ) -> Result<CompletionResponse> {
+        let mut attempt = 0;
+        loop {
+            let result = circuit_breaker.execute_result(|| {
+                let req = request.clone();
+                let prov = provider.clone();
+                async move { prov.complete(&req).await }
+            }).await;
+
+            match result {
+                Err(e) if e.is_retryable()
+                    && attempt + 1 < self.retry_config.max_attempts
+                    && circuit_breaker.can_execute().await =>
+                {
+                    let delay = self.retry_config.delay_for_attempt(attempt);
+                    tracing::debug!(
+                        provider = provider.name(),
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis(),
+                        "Retrying provider request"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Complete a request carrying its own credentials on the provider they are for
+    async fn complete_with_credentials(
+        &self,
+        request: &CompletionRequest,
+        provider_name: &str,
+        mut perf_metric: crate::performance_monitor::AIPerformanceMetrics,
+    ) -> Result<CompletionResponse> {
+        let provider = match self.provider_named(provider_name)? {
+            Some(provider) => provider,
+            None => {
+                self.performance_monitor.fail_request(perf_metric, "unknown_provider".to_string());
+                return Err(WritemagicError::validation(format!("Unknown AI provider for credentials: {}", provider_name)));
+            }
+        };
+
+        let provider_start = Instant::now();
+        let mut response = match provider.complete(request).await {
+            Ok(response) => self.content_sanitizer.sanitize_response(&response)?,
+            Err(e) => {
+                self.performance_monitor.fail_request(perf_metric, "provider_error".to_string());
+                return Err(e);
+            }
+        };
+
+        let usage = self.tokenization_service.calculate_usage(
+            request,
+            response.choices.first().map(|c| &c.message.content).unwrap_or(&String::new()),
+            provider.capabilities().input_cost_per_token,
+            provider.capabilities().output_cost_per_token,
+        )?;
+        response.usage.prompt_tokens = usage.input_tokens;
+        response.usage.completion_tokens = usage.output_tokens;
+        response.usage.total_tokens = usage.total_tokens;
+        response.metadata.insert(crate::providers::PROVIDER_METADATA_KEY.to_string(), provider_name.to_string());
+
+        perf_metric.provider_name = provider_name.to_string();
+        perf_metric.input_tokens = usage.input_tokens;
+        perf_metric.output_tokens = usage.output_tokens;
+        perf_metric.total_tokens = usage.total_tokens;
+        perf_metric.cost = usage.estimated_cost;
+        self.performance_monitor.complete_request(perf_metric);
+
+        tracing::info!(
+            provider = provider_name,
+            duration_ms = provider_start.elapsed().as_millis(),
+            input_tokens = usage.input_tokens,
+            output_tokens = usage.output_tokens,
+            "AI request with caller credentials completed"
+        );
+
+        Ok(response)
+    }
+
+    /// Stream a completion on the caller's own credentials, as `complete_with_credentials` completes one
+    async fn stream_with_credentials(
+        &self,
+        request: CompletionRequest,
+        provider_name: &str,
+        permit: crate::dispatch::DispatchPermit,
+    ) -> Result<Box<dyn crate::providers::StreamingResponse>> {
+        let provider = self.provider_named(provider_name)?.ok_or_else(|| {
+            WritemagicError::validation(format!("Unknown AI provider for credentials: {}", provider_name))
+        })?;
+        if !provider.supports_streaming() {
+            return Err(WritemagicError::validation(format!("AI provider {} does not support streaming", provider_name)));
+        }
+
+        let stream = provider.stream(&request).await?;
+        let guarded = crate::providers::GuardedStreamingResponse::new(
+            stream,
+            self.stream_output_limit,
+            provider_name.to_string(),
+            request.model.clone(),
+            self.tokenization_service.clone(),
+        );
+        let record_spend = self.stream_spend_recorder(provider_name, provider.as_ref(), request);
+        Ok(Box::new(crate::providers::AccountedStreamingResponse::new(
+            Box::new(guarded),
+            permit,
+            self.content_sanitizer.clone(),
+            record_spend,
+        )))
+    }
+
+    /// Provider registered under `name`, or a keyless one callers can bring their own credentials to
+    fn provider_named(&self, name: &str) -> Result<Option<Arc<dyn AIProvider>>> {
+        Ok(match (self.providers.get(name), name) {
+            (Some(provider), _) => Some(provider.clone()),
+            (None, "claude") => Some(Arc::new(ClaudeProvider::new(String::new())?)),
+            (None, "openai") => Some(Arc::new(OpenAIProvider::new(String::new())?)),
+            (None, _) => None,
+        })
+    }
+
+    /// Token counts and cost of a completed response, priced at the provider that served it
+    pub fn token_usage(&self, response: &CompletionResponse) -> Result<TokenUsage> {
+        let capabilities = match response.provider() {
+            Some(name) => self.provider_named(name)?.map(|provider| provider.capabilities()),
+            None => None,
+        };
+        let (input_cost, output_cost) = capabilities
+            .map(|c| (c.input_cost_per_token, c.output_cost_per_token))
+            .unwrap_or((0.0, 0.0));
+
+        let mut usage = TokenUsage::new(response.usage.prompt_tokens, response.usage.completion_tokens, input_cost, output_cost);
+        if let Some(name) = response.provider() {
+            usage.estimated_cost = self.estimate_cost(name, &response.model, &usage);
+        }
+        Ok(usage)
     }
 
     /// Generate secure cache key using BLAKE3 hash
@@ -407,7 +801,8 @@ impl AIOrchestrationService {
         key_data.extend(&request.max_tokens.unwrap_or(0).to_le_bytes());
         key_data.extend(&request.temperature.unwrap_or(0.0).to_le_bytes());
         
-        // Hash messages content (not including metadata which might contain sensitive data)
+        // Hash messages content (not including metadata which might contain sensitive data),
+        // with whitespace normalized so reformatted prompts share an entry
         for message in &request.messages {
             key_data.push(match message.role {
                 crate::providers::MessageRole::System => 0,
@@ -415,7 +810,19 @@ impl AIOrchestrationService {
                 crate::providers::MessageRole::Assistant => 2,
                 crate::providers::MessageRole::Function => 3,
             });
-            key_data.extend(message.content.as_bytes());
+            for (i, word) in message.content.split_whitespace().enumerate() {
+                if i > 0 {
+                    key_data.push(b' ');
+                }
+                key_data.extend(word.as_bytes());
+            }
+            key_data.push(0);
+        }
+
+        // Responses from different providers are not interchangeable
+        for provider in &request.provider_preference {
+            key_data.extend(provider.as_bytes());
+            key_data.push(0);
         }
         
         // Use BLAKE3 for secure, fast hashing
@@ -435,12 +842,21 @@ impl AIOrchestrationService {
             .contains_sensitive_content(content);
 
         if contains_sensitive {
-            Some(Duration::from_secs(60)) // 1 minute for sensitive content
+            // At most 1 minute for sensitive content
+            Some(self.global_cache.default_ttl().min(Duration::from_secs(60)))
         } else {
-            Some(Duration::from_secs(600)) // 10 minutes for regular content
+            None // The configured TTL for regular content
         }
     }
 
+    /// Circuit breaker of a provider, registered on first use
+    fn circuit_breaker(&self, provider_name: &str) -> Arc<crate::circuit_breaker::CircuitBreaker> {
+        self.circuit_breakers.get(provider_name).unwrap_or_else(|| {
+            let config = self.get_circuit_breaker_config(provider_name);
+            self.circuit_breakers.register(provider_name.to_string(), config)
+        })
+    }
+
     /// Get circuit breaker configuration for provider
     fn get_circuit_breaker_config(&self, provider_name: &str) -> crate::circuit_breaker::CircuitBreakerConfig {
         match provider_name {
@@ -482,6 +898,15 @@ impl AIOrchestrationService {
     /// Get optimal providers considering circuit breaker state, cost, and performance
     async fn get_optimal_providers_for_request(&self, request: &CompletionRequest) -> Vec<String> {
         let health_map = self.provider_health.read().await;
+
+        // A caller's explicit preference is kept in its order, skipping only providers backing off
+        if !request.provider_preference.is_empty() {
+            return request.provider_preference
+                .iter()
+                .filter(|name| health_map.get(name.as_str()).map_or(true, |health| health.should_retry()))
+                .cloned()
+                .collect();
+        }
         
         let mut available_providers = Vec::new();
         
@@ -592,23 +1017,37 @@ impl AIOrchestrationService {
         self.provider_health.read().await.clone()
     }
 
-    /// Force health check on all providers
+    /// Check every provider's credentials, concurrently and each within a timeout
+    ///
+    /// Results are reused for a minute, so frequent readiness probes don't
+    /// call the providers each time.
     pub async fn health_check_all_providers(&self) -> Result<HashMap<String, bool>> {
-        let mut results = HashMap::new();
-        
-        for provider_name in &self.fallback_order {
-            if let Some(provider) = self.providers.get(provider_name) {
-                let is_healthy = provider.validate_credentials().await.unwrap_or(false);
-                results.insert(provider_name.clone(), is_healthy);
-                
-                if is_healthy {
-                    self.record_provider_success(provider_name, Duration::from_millis(100)).await;
-                } else {
-                    self.record_provider_failure(provider_name).await;
-                }
+        if let Some((checked_at, results)) = self.credential_checks.read().await.as_ref() {
+            if checked_at.elapsed() < CREDENTIAL_CHECK_TTL {
+                return Ok(results.clone());
             }
         }
-        
+
+        let checks = self.fallback_order.iter().filter_map(|provider_name| {
+            let provider = self.providers.get(provider_name)?.clone();
+            Some(async move {
+                let is_healthy = tokio::time::timeout(CREDENTIAL_CHECK_TIMEOUT, provider.validate_credentials())
+                    .await
+                    .is_ok_and(|valid| valid.unwrap_or(false));
+                (provider_name.clone(), is_healthy)
+            })
+        });
+        let results: HashMap<String, bool> = futures::future::join_all(checks).await.into_iter().collect();
+
+        for (provider_name, is_healthy) in &results {
+            if *is_healthy {
+                self.record_provider_success(provider_name, Duration::from_millis(100)).await;
+            } else {
+                self.record_provider_failure(provider_name).await;
+            }
+        }
+
+        *self.credential_checks.write().await = Some((Instant::now(), results.clone()));
         Ok(results)
     }
 
@@ -627,7 +1066,50 @@ impl AIOrchestrationService {
         }
     }
 
+    /// Circuit breaker status of each provider; providers without a breaker yet report closed
+    pub fn circuit_breaker_statuses(&self) -> HashMap<String, crate::circuit_breaker::CircuitBreakerStatus> {
+        let mut statuses = self.circuit_breakers.get_all_statuses();
+        for name in self.providers.keys() {
+            statuses.entry(name.clone()).or_insert(crate::circuit_breaker::CircuitBreakerStatus {
+                state: crate::circuit_breaker::CircuitState::Closed,
+                retry_in: None,
+            });
+        }
+        statuses
+    }
+
+    /// Open a provider's circuit breaker by hand so fallback skips it until reset or the breaker's timeout elapses
+    pub fn trip_provider(&self, provider_name: &str) -> Result<crate::circuit_breaker::CircuitBreakerStatus> {
+        self.known_provider(provider_name)?;
+        let breaker = self.circuit_breaker(provider_name);
+        self.circuit_breakers.trip(provider_name);
+        self.security_logger.log_event(
+            crate::security::SecurityEventType::CircuitBreakerTripped,
+            format!("Circuit breaker for provider '{}' tripped manually", provider_name),
+            crate::security::PIISeverity::Medium,
+        );
+        Ok(breaker.status())
+    }
+
+    /// Close a provider's circuit breaker immediately, putting it back into fallback
+    pub fn reset_provider(&self, provider_name: &str) -> Result<crate::circuit_breaker::CircuitBreakerStatus> {
+        self.known_provider(provider_name)?;
+        let breaker = self.circuit_breaker(provider_name);
+        self.circuit_breakers.reset(provider_name);
+        log::info!("Circuit breaker for provider '{}' reset manually", provider_name);
+        Ok(breaker.status())
+    }
+
+    fn known_provider(&self, provider_name: &str) -> Result<()> {
+        if self.providers.contains_key(provider_name) {
+            Ok(())
+        } else {
+            Err(WritemagicError::not_found(format!("AI provider '{}'", provider_name)))
+        }
+    }
+
     /// Get cost estimates for request with different providers
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn estimate_costs(&self, request: &CompletionRequest) -> Result<HashMap<String, CostEstimate>> {
         let mut estimates = HashMap::new();
         
@@ -635,9 +1117,15 @@ impl AIOrchestrationService {
         let output_tokens = request.max_tokens.unwrap_or(1000);
         
         for (provider_name, provider) in &self.providers {
-            let capabilities = provider.capabilities();
-            let input_cost = input_tokens as f64 * capabilities.input_cost_per_token;
-            let output_cost = output_tokens as f64 * capabilities.output_cost_per_token;
+            let price = self.cost_estimator.price(provider_name, &request.model).unwrap_or_else(|| {
+                let capabilities = provider.capabilities();
+                crate::cost::ModelPrice {
+                    input_cost_per_token: capabilities.input_cost_per_token,
+                    output_cost_per_token: capabilities.output_cost_per_token,
+                }
+            });
+            let input_cost = input_tokens as f64 * price.input_cost_per_token;
+            let output_cost = output_tokens as f64 * price.output_cost_per_token;
             let total_cost = input_cost + output_cost;
             
             estimates.insert(provider_name.clone(), CostEstimate {
@@ -698,14 +1186,28 @@ impl AIOrchestrationService {
         &self.context_manager
     }
 
+    /// Replace how conversations are fitted into the context window
+    pub fn set_context_manager(&mut self, context_manager: ContextManagementService) {
+        self.context_manager = Arc::new(context_manager);
+    }
+
     /// Get comprehensive performance statistics
     pub async fn get_performance_stats(&self) -> crate::performance_monitor::PerformanceStats {
-        self.performance_monitor.get_overall_stats()
+        let mut stats = self.performance_monitor.get_overall_stats();
+        stats.month_to_date_cost = self.spend.spent(chrono::Utc::now());
+        stats
     }
 
-    /// Get performance statistics for a specific provider
+    /// Get performance statistics for a specific provider, with its spend this month
     pub async fn get_provider_performance(&self, provider_name: &str) -> Option<crate::performance_monitor::PerformanceStats> {
-        self.performance_monitor.get_provider_stats(provider_name)
+        let mut stats = self.performance_monitor.get_provider_stats(provider_name)?;
+        stats.month_to_date_cost = self
+            .spend
+            .spent_by_provider(chrono::Utc::now())
+            .get(provider_name)
+            .copied()
+            .unwrap_or(0.0);
+        Some(stats)
     }
 
     /// Get recent performance alerts
@@ -724,25 +1226,117 @@ impl AIOrchestrationService {
     }
 
     /// Stream a completion request (returns async stream of partial responses)
+    ///
+    /// Goes through [`Self::complete_with_fallback_stream`], so the stream is
+    /// checked against the budget and its spend recorded when it ends.
     pub async fn stream_completion(&self, request: CompletionRequest) -> Result<Box<dyn crate::providers::StreamingResponse>> {
-        // Use best available provider for streaming
-        let providers = self.get_optimal_providers_for_request(&request).await;
-        let provider_name = providers.first().cloned()
-            .ok_or_else(|| WritemagicError::internal("No providers available for streaming"))?;
-        
-        if let Some(provider) = self.providers.get(&provider_name) {
+        self.complete_with_fallback_stream(request).await
+    }
+
+    /// Stream a completion, falling back to the next provider until one starts producing output
+    ///
+    /// A provider counts as started once its first chunk arrives. Errors after
+    /// that are returned from the stream rather than retried elsewhere, since
+    /// switching providers mid-completion would splice two different texts.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref(), model = %request.model))]
+    pub async fn complete_with_fallback_stream(&self, mut request: CompletionRequest) -> Result<Box<dyn crate::providers::StreamingResponse>> {
+        request = self.context_manager.expand_few_shot(request)?;
+        request = self.content_sanitizer.sanitize_request(&request)?;
+        self.tokenization_service.validate_request(&request)?;
+        request.messages = self.context_manager
+            .fit_context_with(request.messages.clone(), &request.model, |provider, summary| self.complete_summary(provider, summary))
+            .await?;
+        self.validate_provider_preference(&request)?;
+
+        // Caller-supplied credentials go straight to the provider they name, bypassing
+        // the shared provider's circuit breaker and health tracking
+        if let Some(credentials) = &request.credentials_override {
+            let provider_name = credentials.provider.clone();
+            credentials.check_base_url(&self.credential_endpoints)?;
+            let permit = self.dispatcher.acquire(crate::dispatch::AiPriority::from(&request.priority)).await;
+            return self.stream_with_credentials(request, &provider_name, permit).await;
+        }
+
+        self.spend.check_budget(self.monthly_budget_usd, chrono::Utc::now())?;
+        let ordered_providers = self.get_optimal_providers_for_request(&request).await;
+
+        // Held by the returned stream until it ends, not just until it starts
+        let permit = self.dispatcher.acquire(crate::dispatch::AiPriority::from(&request.priority)).await;
+
+        let mut last_error = None;
+        for provider_name in ordered_providers {
+            let Some(provider) = self.providers.get(&provider_name) else {
+                continue;
+            };
             if !provider.supports_streaming() {
-                return Err(WritemagicError::validation("Selected provider does not support streaming"));
+                continue;
             }
-            
-            // For now, just call the provider directly - circuit breaker implementation needed
-            provider.stream(&request).await
-        } else {
-            Err(WritemagicError::internal(format!("Provider '{}' not found", provider_name)))
+
+            let circuit_breaker = self.circuit_breaker(&provider_name);
+
+            if !circuit_breaker.can_execute().await {
+                log::debug!("Circuit breaker open for provider: {}", provider_name);
+                continue;
+            }
+
+            let provider_start = Instant::now();
+
+            // Read the first chunk before committing to the provider
+            let result = circuit_breaker.execute_result(|| {
+                let req = request.clone();
+                let prov = provider.clone();
+                async move {
+                    let mut stream = prov.stream(&req).await?;
+                    let first = stream.next_chunk().await?;
+                    Ok((stream, first))
+                }
+            }).await;
+
+            match result {
+                Ok((stream, first)) => {
+                    self.record_provider_success(&provider_name, provider_start.elapsed()).await;
+
+                    let guarded = crate::providers::GuardedStreamingResponse::new(
+                        Box::new(crate::providers::PrimedStreamingResponse::new(first, stream)),
+                        self.stream_output_limit,
+                        provider_name.clone(),
+                        request.model.clone(),
+                        self.tokenization_service.clone(),
+                    );
+                    let record_spend = self.stream_spend_recorder(&provider_name, provider.as_ref(), request);
+                    return Ok(Box::new(crate::providers::AccountedStreamingResponse::new(
+                        Box::new(guarded),
+                        permit,
+                        self.content_sanitizer.clone(),
+                        record_spend,
+                    )));
+                }
+                Err(e) => {
+                    let sanitized_error = self.content_sanitizer.sanitize_for_logging(&e.to_string());
+                    tracing::warn!(
+                        provider = provider_name,
+                        error = sanitized_error,
+                        "Provider stream failed before its first chunk"
+                    );
+
+                    if e.is_request_rejection() {
+                        return Err(e);
+                    }
+
+                    self.record_provider_failure(&provider_name).await;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) => Err(e),
+            None => Err(WritemagicError::internal("No providers available for streaming")),
         }
     }
 
     /// Batch multiple completion requests for efficient processing
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn batch_complete(&self, requests: Vec<CompletionRequest>) -> Result<Vec<Result<CompletionResponse>>> {
         if requests.is_empty() {
             return Ok(Vec::new());
@@ -841,6 +1435,7 @@ pub enum EmergencyAction {
 /// Provider registry and factory service with secure key management
 pub struct AIProviderRegistry {
     key_manager: Arc<crate::security::SecureKeyManager>,
+    retry_config: crate::retry_patterns::RetryConfig,
 }
 
 impl Default for AIProviderRegistry {
@@ -851,13 +1446,20 @@ impl Default for AIProviderRegistry {
 
 impl AIProviderRegistry {
     pub fn new() -> Self {
+        Self::with_key_manager(Arc::new(crate::security::SecureKeyManager::new()))
+    }
+
+    pub fn with_key_manager(key_manager: Arc<crate::security::SecureKeyManager>) -> Self {
         Self {
-            key_manager: Arc::new(crate::security::SecureKeyManager::new()),
+            key_manager,
+            retry_config: crate::retry_patterns::RetryConfig::default(),
         }
     }
 
-    pub fn with_key_manager(key_manager: Arc<crate::security::SecureKeyManager>) -> Self {
-        Self { key_manager }
+    /// Retry policy for each provider call made by services this registry creates
+    pub fn with_retry_config(mut self, retry_config: crate::retry_patterns::RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
     }
 
     pub fn add_claude_key(&self, api_key: String) -> Result<()> {
@@ -881,6 +1483,7 @@ impl AIProviderRegistry {
             100000, // 100k token context limit
             self.key_manager.clone()
         )?;
+        service.set_retry_config(self.retry_config.clone());
 
         let mut fallback_order = Vec::new();
 
@@ -949,9 +1552,50 @@ impl AIProviderRegistry {
     }
 }
 
+fn is_system(message: &Message) -> bool {
+    matches!(message.role, crate::providers::MessageRole::System)
+}
+
+fn latest_user_index(messages: &[Message]) -> Option<usize> {
+    messages.iter().rposition(|msg| matches!(msg.role, crate::providers::MessageRole::User))
+}
+
+/// Tokens a message takes in the context, including its formatting overhead
+fn message_tokens(tokenizer: &dyn Tokenizer, message: &Message) -> Result<u32> {
+    let overhead = match message.role {
+        crate::providers::MessageRole::Function => 6,
+        _ => 4,
+    };
+    Ok(tokenizer.count_tokens(&message.content)? + overhead)
+}
+
 /// Type alias for context cache to reduce complexity
 type ContextCache = Arc<std::sync::RwLock<HashMap<String, (Vec<Message>, std::time::Instant)>>>;
 
+/// How `ContextManagementService` fits a conversation into the context window
+///
+/// Every strategy keeps the system prompt and the latest user message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextTrimStrategy {
+    /// Keep the newest messages that fit, dropping older ones
+    #[default]
+    DropOldest,
+    /// Drop like `DropOldest`, then condense the dropped messages into one summary message
+    SummarizeOldest,
+    /// Keep at most the last `max_messages` non-system messages, within the token budget
+    SlidingWindow { max_messages: usize },
+}
+
+/// Fewest tokens worth asking the summary provider for
+const MIN_SUMMARY_TOKENS: u32 = 32;
+
+/// Provider and model used to condense trimmed messages
+#[derive(Clone)]
+struct ContextSummarizer {
+    provider: Arc<dyn AIProvider>,
+    model: String,
+}
+
 /// Context management service with accurate tokenization
 #[derive(Clone)]
 pub struct ContextManagementService {
@@ -959,6 +1603,18 @@ pub struct ContextManagementService {
     tokenization_service: Arc<crate::tokenization::TokenizationService>,
     context_cache: ContextCache,
     cache_ttl: std::time::Duration,
+    sessions: Arc<RwLock<HashMap<EntityId, Vec<Message>>>>,
+    checkpoint_store: Arc<dyn ContextCheckpointStore>,
+    checkpoint_retention: CheckpointRetention,
+    trim_strategy: ContextTrimStrategy,
+    summarizer: Option<ContextSummarizer>,
+}
+
+/// Session context brought back from a checkpoint
+#[derive(Debug, Clone)]
+pub struct RestoredContext {
+    pub session_id: EntityId,
+    pub messages: Vec<Message>,
 }
 
 impl ContextManagementService {
@@ -970,6 +1626,11 @@ impl ContextManagementService {
             tokenization_service,
             context_cache: Arc::new(std::sync::RwLock::new(HashMap::new())),
             cache_ttl: std::time::Duration::from_secs(300),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            checkpoint_store: Arc::new(InMemoryContextCheckpointStore::new()),
+            checkpoint_retention: CheckpointRetention::default(),
+            trim_strategy: ContextTrimStrategy::default(),
+            summarizer: None,
         })
     }
 
@@ -979,10 +1640,107 @@ impl ContextManagementService {
             tokenization_service,
             context_cache: Arc::new(std::sync::RwLock::new(HashMap::new())),
             cache_ttl: std::time::Duration::from_secs(300),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            checkpoint_store: Arc::new(InMemoryContextCheckpointStore::new()),
+            checkpoint_retention: CheckpointRetention::default(),
+            trim_strategy: ContextTrimStrategy::default(),
+            summarizer: None,
+        }
+    }
+
+    /// Choose how conversations are trimmed to fit the context window
+    pub fn with_trim_strategy(mut self, strategy: ContextTrimStrategy) -> Self {
+        self.trim_strategy = strategy;
+        self
+    }
+
+    /// Condense trimmed messages with `provider` under `SummarizeOldest`
+    ///
+    /// Without a summary provider, `SummarizeOldest` drops messages like `DropOldest`.
+    pub fn with_summary_provider(mut self, provider: Arc<dyn AIProvider>, model: impl Into<String>) -> Self {
+        self.summarizer = Some(ContextSummarizer { provider, model: model.into() });
+        self
+    }
+
+    pub fn trim_strategy(&self) -> ContextTrimStrategy {
+        self.trim_strategy
+    }
+
+    /// Persist checkpoints in `store`, keeping them according to `retention`
+    pub fn with_checkpoint_store(mut self, store: Arc<dyn ContextCheckpointStore>, retention: CheckpointRetention) -> Self {
+        self.checkpoint_store = store;
+        self.checkpoint_retention = retention;
+        self
+    }
+
+    /// Replace the current context of a session
+    pub async fn set_session_context(&self, session_id: EntityId, messages: Vec<Message>) {
+        self.sessions.write().await.insert(session_id, messages);
+    }
+
+    /// Current context of a session, if any has been recorded
+    pub async fn session_context(&self, session_id: &EntityId) -> Option<Vec<Message>> {
+        self.sessions.read().await.get(session_id).cloned()
+    }
+
+    /// Persist the current context of a session so it survives a restart
+    pub async fn checkpoint(&self, session_id: EntityId) -> Result<CheckpointId> {
+        let messages = self.session_context(&session_id).await
+            .ok_or_else(|| WritemagicError::not_found(format!("Context for session {}", session_id)))?;
+
+        let checkpoint = ContextCheckpoint {
+            id: CheckpointId::new(),
+            session_id,
+            context: serde_json::to_string(&messages)
+                .map_err(|e| WritemagicError::internal(format!("Failed to serialize session context: {}", e)))?,
+            created_at: Timestamp::now(),
+        };
+        self.checkpoint_store.save(&checkpoint).await?;
+
+        let pruned = self.prune_checkpoints(&session_id).await?;
+        log::debug!("Checkpointed session {} as {}, pruned {} old checkpoints", session_id, checkpoint.id, pruned);
+
+        Ok(checkpoint.id)
+    }
+
+    /// Resume a session from a checkpoint, replacing its current context
+    pub async fn restore(&self, checkpoint_id: &CheckpointId) -> Result<RestoredContext> {
+        let checkpoint = self.checkpoint_store.find_by_id(checkpoint_id).await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Context checkpoint {}", checkpoint_id)))?;
+
+        let messages: Vec<Message> = serde_json::from_str(&checkpoint.context)
+            .map_err(|e| WritemagicError::internal(format!("Failed to deserialize checkpoint {}: {}", checkpoint_id, e)))?;
+        self.set_session_context(checkpoint.session_id, messages.clone()).await;
+
+        Ok(RestoredContext {
+            session_id: checkpoint.session_id,
+            messages,
+        })
+    }
+
+    /// Remove checkpoints beyond the retention cap or older than the retention age
+    pub async fn prune_checkpoints(&self, session_id: &EntityId) -> Result<u64> {
+        let mut pruned = 0;
+
+        let checkpoints = self.checkpoint_store.find_by_session(session_id).await?;
+        for checkpoint in checkpoints.iter().skip(self.checkpoint_retention.max_per_session) {
+            if self.checkpoint_store.delete(&checkpoint.id).await? {
+                pruned += 1;
+            }
+        }
+
+        if let Some(max_age_secs) = self.checkpoint_retention.max_age_secs {
+            let cutoff = chrono::Utc::now() - chrono::Duration::seconds(max_age_secs as i64);
+            pruned += self.checkpoint_store.delete_older_than(session_id, &Timestamp::from_datetime(cutoff)).await?;
         }
+
+        Ok(pruned)
     }
 
     /// Manage context with accurate token counting for specific model
+    ///
+    /// Trims without calling the AI, so `SummarizeOldest` drops messages here;
+    /// use `fit_context` to have them summarized.
     pub fn manage_context(&self, messages: Vec<Message>, model_name: &str) -> Result<Vec<Message>> {
         // Create cache key
         let cache_key = self.create_cache_key(&messages, model_name);
@@ -998,91 +1756,223 @@ impl ContextManagementService {
             }
         }
 
+        let (keep, current_tokens) = self.plan_context(&messages, model_name)?;
+        let final_messages: Vec<Message> = messages.into_iter()
+            .zip(keep)
+            .filter_map(|(msg, keep)| keep.then_some(msg))
+            .collect();
+
+        // Cache result
+        {
+            let mut cache = self.context_cache.write()
+                .map_err(|e| WritemagicError::internal(format!("Failed to write context cache: {}", e)))?;
+            cache.insert(cache_key, (final_messages.clone(), std::time::Instant::now()));
+            
+            // Clean expired entries
+            let now = std::time::Instant::now();
+            cache.retain(|_, (_, timestamp)| now.duration_since(*timestamp) < self.cache_ttl);
+        }
+
+        log::debug!("Context managed: {} messages, ~{} tokens for model {}", 
+            final_messages.len(), current_tokens, model_name);
+
+        Ok(final_messages)
+    }
+
+    /// Fit `messages` into the context window using the configured trim strategy
+    ///
+    /// Under `SummarizeOldest` with a summary provider, trimmed messages are
+    /// replaced by a system message summarizing them, placed after the system
+    /// prompt. The summary is skipped if there is no room left for one. The
+    /// summary provider is called directly; `AIOrchestrationService` uses
+    /// `fit_context_with` to guard that call like any other completion.
+    pub async fn fit_context(&self, messages: Vec<Message>, model_name: &str) -> Result<Vec<Message>> {
+        self.fit_context_with(messages, model_name, |provider, request| async move { provider.complete(&request).await })
+            .await
+    }
+
+    /// Same as `fit_context`, asking `complete` to run the summary request on the summary provider
+    ///
+    /// If the summary can't be had, the messages are trimmed as `DropOldest`
+    /// would, without a summary.
+    pub async fn fit_context_with<F, Fut>(&self, messages: Vec<Message>, model_name: &str, complete: F) -> Result<Vec<Message>>
+    where
+        F: FnOnce(Arc<dyn AIProvider>, CompletionRequest) -> Fut,
+        Fut: std::future::Future<Output = Result<CompletionResponse>>,
+    {
+        let summarizer = match (&self.trim_strategy, &self.summarizer) {
+            (ContextTrimStrategy::SummarizeOldest, Some(summarizer)) => summarizer,
+            _ => return self.manage_context(messages, model_name),
+        };
+
         let tokenizer = self.tokenization_service.get_tokenizer(model_name);
-        let mut managed_messages = Vec::new();
-        let mut current_tokens = 0u32;
+        let (planned, mut used_tokens) = self.plan_context(&messages, model_name)?;
+        if planned.iter().all(|&keep| keep) {
+            return Ok(messages);
+        }
+        let mut keep = planned.clone();
 
-        // Always include system messages first
-        let mut system_messages = Vec::new();
-        let mut non_system_messages = Vec::new();
+        // Give up the oldest kept history if that's what it takes to fit a useful summary
+        let latest_user = latest_user_index(&messages);
+        let kept_history: Vec<usize> = (0..messages.len())
+            .filter(|&i| keep[i] && Some(i) != latest_user && !is_system(&messages[i]))
+            .collect();
+        let mut kept_history = kept_history.into_iter();
+        while self.max_context_tokens - used_tokens < MIN_SUMMARY_TOKENS {
+            let Some(oldest) = kept_history.next() else { break };
+            keep[oldest] = false;
+            used_tokens -= message_tokens(&*tokenizer, &messages[oldest])?;
+        }
 
-        for msg in messages {
-            match msg.role {
-                crate::providers::MessageRole::System => system_messages.push(msg),
-                _ => non_system_messages.push(msg),
+        let budget = self.max_context_tokens - used_tokens;
+        let dropped: Vec<&Message> = messages.iter().zip(&keep).filter(|(_, keep)| !**keep).map(|(msg, _)| msg).collect();
+        let summary = if budget >= MIN_SUMMARY_TOKENS {
+            match self.summarize(summarizer, &dropped, budget, complete).await {
+                Ok(summary) => {
+                    let summary = Message::system(format!("Summary of the earlier conversation: {}", summary));
+                    if message_tokens(&*tokenizer, &summary)? <= budget {
+                        Some(summary)
+                    } else {
+                        log::warn!("Summary of {} trimmed messages overran its {} token budget, dropping it", dropped.len(), budget);
+                        None
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to summarize {} trimmed messages, sending them trimmed: {}", dropped.len(), e);
+                    None
+                }
             }
+        } else {
+            log::warn!("No room to summarize {} trimmed messages in {} tokens", dropped.len(), self.max_context_tokens);
+            None
+        };
+        // History given up only to make room for the summary is kept when there is none
+        if summary.is_none() {
+            keep = planned;
         }
 
-        // Add system messages and count their tokens
-        for msg in system_messages {
-            let msg_tokens = tokenizer.count_tokens(&msg.content)?;
-            if current_tokens + msg_tokens <= self.max_context_tokens {
-                current_tokens += msg_tokens;
-                managed_messages.push(msg);
+        let mut fitted: Vec<Message> = messages.into_iter()
+            .zip(keep)
+            .filter_map(|(msg, keep)| keep.then_some(msg))
+            .collect();
+        if let Some(summary) = summary {
+            let position = fitted.iter().position(|msg| !is_system(msg)).unwrap_or(fitted.len());
+            fitted.insert(position, summary);
+        }
+        Ok(fitted)
+    }
+
+    /// Which messages to keep under the trim strategy, and the tokens they take
+    ///
+    /// Fails if the system prompt and latest user message alone exceed the window.
+    fn plan_context(&self, messages: &[Message], model_name: &str) -> Result<(Vec<bool>, u32)> {
+        let tokenizer = self.tokenization_service.get_tokenizer(model_name);
+        let latest_user = latest_user_index(messages);
+
+        let mut keep = vec![false; messages.len()];
+        let mut used_tokens = 0u32;
+        let mut history = Vec::new();
+        for (i, msg) in messages.iter().enumerate() {
+            if is_system(msg) || Some(i) == latest_user {
+                keep[i] = true;
+                used_tokens += message_tokens(&*tokenizer, msg)?;
             } else {
-                log::warn!("System message too long, truncating context");
-                break;
+                history.push(i);
             }
         }
 
-        // Add non-system messages in reverse order (most recent first)
-        for msg in non_system_messages.into_iter().rev() {
-            let msg_tokens = tokenizer.count_tokens(&msg.content)?;
-            
-            // Add message formatting overhead
-            let total_msg_tokens = msg_tokens + match msg.role {
-                crate::providers::MessageRole::User => 4,
-                crate::providers::MessageRole::Assistant => 4,
-                crate::providers::MessageRole::Function => 6,
-                crate::providers::MessageRole::System => 4, // Already handled above
-            };
-            
-            if current_tokens + total_msg_tokens <= self.max_context_tokens {
-                current_tokens += total_msg_tokens;
-                managed_messages.push(msg);
-            } else {
+        if used_tokens > self.max_context_tokens {
+            return Err(WritemagicError::validation(format!(
+                "System prompt and latest user message need {} tokens, more than the {} token context window",
+                used_tokens, self.max_context_tokens
+            )));
+        }
+
+        let window = match self.trim_strategy {
+            ContextTrimStrategy::SlidingWindow { max_messages } => max_messages.saturating_sub(latest_user.is_some() as usize),
+            ContextTrimStrategy::DropOldest | ContextTrimStrategy::SummarizeOldest => history.len(),
+        };
+
+        // Keep the newest history that fits, so the oldest is trimmed first
+        for &i in history.iter().rev().take(window) {
+            let msg_tokens = message_tokens(&*tokenizer, &messages[i])?;
+            if used_tokens + msg_tokens > self.max_context_tokens {
                 log::debug!("Dropping message to fit context window. Current tokens: {}, Message tokens: {}, Max: {}", 
-                    current_tokens, total_msg_tokens, self.max_context_tokens);
+                    used_tokens, msg_tokens, self.max_context_tokens);
                 break;
             }
+            used_tokens += msg_tokens;
+            keep[i] = true;
         }
 
-        // Reverse non-system messages back to chronological order
-        let mut final_messages = Vec::new();
-        let system_count = managed_messages.iter().filter(|m| matches!(m.role, crate::providers::MessageRole::System)).count();
-        
-        // Add system messages first
-        for (i, msg) in managed_messages.iter().enumerate() {
-            if matches!(msg.role, crate::providers::MessageRole::System) {
-                final_messages.push(msg.clone());
-            } else if i >= system_count {
+        Ok((keep, used_tokens))
+    }
+
+    /// Ask the summary provider, through `complete`, to condense `dropped` into at most `budget` tokens
+    async fn summarize<F, Fut>(&self, summarizer: &ContextSummarizer, dropped: &[&Message], budget: u32, complete: F) -> Result<String>
+    where
+        F: FnOnce(Arc<dyn AIProvider>, CompletionRequest) -> Fut,
+        Fut: std::future::Future<Output = Result<CompletionResponse>>,
+    {
+        let transcript = dropped.iter()
+            .map(|msg| format!("{:?}: {}", msg.role, msg.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let request = CompletionRequest::new(
+            vec![
+                Message::system("Condense the conversation below into a brief summary that keeps names, decisions and open questions. Reply with the summary only."),
+                Message::user(transcript),
+            ],
+            summarizer.model.clone(),
+        )
+        .with_max_tokens(budget.saturating_sub(MIN_SUMMARY_TOKENS / 2).max(1));
+
+        let response = complete(summarizer.provider.clone(), request).await?;
+        response.choices.first()
+            .map(|choice| choice.message.content.trim().to_string())
+            .filter(|summary| !summary.is_empty())
+            .ok_or_else(|| WritemagicError::ai_provider("Summary provider returned no summary"))
+    }
+
+    /// Expand a request's few-shot examples into user/assistant turns before its prompt
+    ///
+    /// Examples are dropped oldest first until the request fits the context
+    /// window; the expanded request carries no `few_shot` pairs.
+    pub fn expand_few_shot(&self, mut request: CompletionRequest) -> Result<CompletionRequest> {
+        let examples = std::mem::take(&mut request.few_shot);
+        if examples.is_empty() {
+            return Ok(request);
+        }
+
+        let tokenizer = self.tokenization_service.get_tokenizer(&request.model);
+        let mut used_tokens = 0u32;
+        for msg in &request.messages {
+            used_tokens += tokenizer.count_tokens(&msg.content)? + 4;
+        }
+
+        // Keep the newest examples that fit, as the oldest are trimmed first
+        let mut kept = Vec::new();
+        for (input, output) in examples.iter().rev() {
+            let pair_tokens = tokenizer.count_tokens(input)? + tokenizer.count_tokens(output)? + 8;
+            if used_tokens + pair_tokens > self.max_context_tokens {
                 break;
             }
+            used_tokens += pair_tokens;
+            kept.push((input, output));
         }
-        
-        // Add non-system messages in reverse order to restore chronology
-        let mut non_system: Vec<Message> = managed_messages
-            .into_iter()
-            .filter(|m| !matches!(m.role, crate::providers::MessageRole::System))
-            .collect();
-        non_system.reverse();
-        final_messages.extend(non_system);
-
-        // Cache result
-        {
-            let mut cache = self.context_cache.write()
-                .map_err(|e| WritemagicError::internal(format!("Failed to write context cache: {}", e)))?;
-            cache.insert(cache_key, (final_messages.clone(), std::time::Instant::now()));
-            
-            // Clean expired entries
-            let now = std::time::Instant::now();
-            cache.retain(|_, (_, timestamp)| now.duration_since(*timestamp) < self.cache_ttl);
+        if kept.len() < examples.len() {
+            log::debug!("Trimmed {} of {} few-shot examples to fit context window of {} tokens",
+                examples.len() - kept.len(), examples.len(), self.max_context_tokens);
         }
 
-        log::debug!("Context managed: {} messages, ~{} tokens for model {}", 
-            final_messages.len(), current_tokens, model_name);
+        let prompt_index = request.messages.iter()
+            .rposition(|msg| matches!(msg.role, crate::providers::MessageRole::User))
+            .unwrap_or(request.messages.len());
+        let turns = kept.into_iter().rev()
+            .flat_map(|(input, output)| [Message::user(input), Message::assistant(output)]);
+        request.messages.splice(prompt_index..prompt_index, turns);
 
-        Ok(final_messages)
+        Ok(request)
     }
 
     /// Get optimal context window for a specific model
@@ -1184,15 +2074,119 @@ pub struct ContextStats {
     pub utilization: f64, // Percentage of max context used
 }
 
+/// Kind of problem a content filter flag reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentCategory {
+    Profanity,
+    Pii,
+    InjectionAttempt,
+}
+
+impl ContentCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Profanity => "profanity",
+            Self::Pii => "pii",
+            Self::InjectionAttempt => "injection_attempt",
+        }
+    }
+}
+
+/// How serious a flagged span is, least serious first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl From<&crate::security::PIISeverity> for ContentSeverity {
+    fn from(severity: &crate::security::PIISeverity) -> Self {
+        match severity {
+            crate::security::PIISeverity::Low => Self::Low,
+            crate::security::PIISeverity::Medium => Self::Medium,
+            crate::security::PIISeverity::High => Self::High,
+            crate::security::PIISeverity::Critical => Self::Critical,
+        }
+    }
+}
+
+/// One flagged span of filtered content
+///
+/// Holds the byte range rather than the matched text, so flags can be logged.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ContentFlag {
+    pub category: ContentCategory,
+    pub severity: ContentSeverity,
+    /// Name of the rule that matched, e.g. `email`
+    pub rule: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Content with flagged spans redacted, and the flags themselves
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FilterResult {
+    pub filtered: String,
+    pub flags: Vec<ContentFlag>,
+}
+
+impl FilterResult {
+    pub fn is_clean(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    pub fn highest_severity(&self) -> Option<ContentSeverity> {
+        self.flags.iter().map(|flag| flag.severity).max()
+    }
+
+    /// Flags severe enough to refuse the content under `FilterMode::Reject`
+    pub fn blocking_flags(&self) -> impl Iterator<Item = &ContentFlag> {
+        self.flags.iter().filter(|flag| flag.severity >= ContentSeverity::High)
+    }
+}
+
+/// What to do with content that raises filter flags
+///
+/// Rejecting is the default, as filtering refused sensitive content before
+/// modes existed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    /// Redact flagged spans and carry on
+    Strip,
+    /// Refuse content with a high-severity flag; redact the rest
+    #[default]
+    Reject,
+    /// Pass content through unchanged, logging the flags
+    Warn,
+}
+
+/// Credentials written out as `name: value`
+const CREDENTIAL_PATTERN: &str = r"(?i)(password|api[_-]?key|secret|token)\s*[:=]\s*[^\s]+";
+
+/// A pattern flagged under one category and severity
+struct FilterRule {
+    name: &'static str,
+    category: ContentCategory,
+    severity: ContentSeverity,
+    regex: regex::Regex,
+}
+
 /// Content filtering service
 pub struct ContentFilteringService {
     prohibited_patterns: Vec<regex::Regex>,
+    rules: Vec<FilterRule>,
+    pii_detector: crate::security::PIIDetectionService,
 }
 
 impl ContentFilteringService {
     pub fn new() -> Result<Self> {
         let patterns = vec![
-            r"(?i)(password|api[_-]?key|secret|token)\s*[:=]\s*[^\s]+",
+            CREDENTIAL_PATTERN,
             r"(?i)(credit[_-]?card|ssn|social[_-]?security)",
         ];
 
@@ -1202,16 +2196,104 @@ impl ContentFilteringService {
                 .map_err(|e| WritemagicError::internal(format!("Invalid regex: {}", e)))?);
         }
 
-        Ok(Self { prohibited_patterns })
+        let rule_patterns = [
+            ("credential", ContentCategory::Pii, ContentSeverity::High, CREDENTIAL_PATTERN),
+            ("profanity", ContentCategory::Profanity, ContentSeverity::Medium,
+                r"(?i)\b(fuck\w*|shit\w*|bitch\w*|asshole\w*|bastard\w*|cunt\w*)\b"),
+            ("ignore_instructions", ContentCategory::InjectionAttempt, ContentSeverity::High,
+                r"(?i)\b(ignore|disregard|forget)\s+(all\s+)?(the\s+|your\s+)?(previous|prior|above|earlier)\s+(instructions|prompts|rules|directions)"),
+            ("system_prompt_probe", ContentCategory::InjectionAttempt, ContentSeverity::High,
+                r"(?i)\b(reveal|print|show|repeat)\s+(me\s+)?(your|the)\s+(system\s+prompt|hidden\s+instructions)"),
+            ("role_markup", ContentCategory::InjectionAttempt, ContentSeverity::Medium,
+                r"(?i)</?\s*(system|assistant)\s*>"),
+        ];
+        let mut rules = Vec::new();
+        for (name, category, severity, pattern) in rule_patterns {
+            rules.push(FilterRule {
+                name,
+                category,
+                severity,
+                regex: regex::Regex::new(pattern)
+                    .map_err(|e| WritemagicError::internal(format!("Invalid regex: {}", e)))?,
+            });
+        }
+
+        Ok(Self {
+            prohibited_patterns,
+            rules,
+            pii_detector: crate::security::PIIDetectionService::new()?,
+        })
     }
 
-    pub fn filter_content(&self, content: &str) -> Result<String> {
-        for pattern in &self.prohibited_patterns {
-            if pattern.is_match(content) {
-                return Err(WritemagicError::validation("Content contains sensitive information"));
+    /// Flag profanity, PII and prompt-injection attempts, redacting each flagged span
+    pub fn filter_content(&self, content: &str) -> FilterResult {
+        let mut flags: Vec<ContentFlag> = self.pii_detector
+            .scan_text(content)
+            .into_iter()
+            .map(|found| ContentFlag {
+                category: ContentCategory::Pii,
+                severity: ContentSeverity::from(&found.severity),
+                rule: found.pattern_name,
+                start: found.start,
+                end: found.end,
+            })
+            .collect();
+        for rule in &self.rules {
+            flags.extend(rule.regex.find_iter(content).map(|found| ContentFlag {
+                category: rule.category,
+                severity: rule.severity,
+                rule: rule.name.to_string(),
+                start: found.start(),
+                end: found.end(),
+            }));
+        }
+        flags.sort_by_key(|flag| (flag.start, std::cmp::Reverse(flag.end)));
+
+        // Overlapping flags are redacted as one span
+        let mut filtered = String::with_capacity(content.len());
+        let mut copied_to = 0;
+        for flag in &flags {
+            if flag.start >= copied_to {
+                filtered.push_str(&content[copied_to..flag.start]);
+                filtered.push_str(&format!("[FILTERED:{}]", flag.category.label()));
+                copied_to = flag.end;
+            } else if flag.end > copied_to {
+                copied_to = flag.end;
+            }
+        }
+        filtered.push_str(&content[copied_to..]);
+
+        FilterResult { filtered, flags }
+    }
+
+    /// Filter `content` as `mode` says
+    ///
+    /// Fails validation under `FilterMode::Reject` when a high-severity flag fires.
+    pub fn apply(&self, content: &str, mode: FilterMode) -> Result<FilterResult> {
+        let result = self.filter_content(content);
+        match mode {
+            FilterMode::Strip => Ok(result),
+            FilterMode::Reject => {
+                let mut blocked: Vec<&str> = result.blocking_flags().map(|flag| flag.category.label()).collect();
+                if blocked.is_empty() {
+                    return Ok(result);
+                }
+                blocked.sort_unstable();
+                blocked.dedup();
+                Err(WritemagicError::validation(format!("Content rejected by filter: {}", blocked.join(", "))))
+            }
+            FilterMode::Warn => {
+                for flag in &result.flags {
+                    tracing::warn!(
+                        category = flag.category.label(),
+                        severity = ?flag.severity,
+                        rule = %flag.rule,
+                        "Content filter flag"
+                    );
+                }
+                Ok(FilterResult { filtered: content.to_string(), flags: result.flags })
             }
         }
-        Ok(content.to_string())
     }
 
     pub fn detect_sensitive_info(&self, content: &str) -> Vec<String> {
@@ -1229,7 +2311,11 @@ impl Default for ContentFilteringService {
     fn default() -> Self {
         Self::new().unwrap_or_else(|_| {
             log::error!("Failed to create content filtering service, using minimal implementation");
-            Self { prohibited_patterns: Vec::new() }
+            Self {
+                prohibited_patterns: Vec::new(),
+                rules: Vec::new(),
+                pii_detector: crate::security::PIIDetectionService::default(),
+            }
         })
     }
 }
\ No newline at end of file
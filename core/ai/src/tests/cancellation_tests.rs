@@ -0,0 +1,79 @@
+//! Tests for cancelling in-flight completions
+
+use super::support::{FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message};
+use crate::services::AIOrchestrationService;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use writemagic_shared::{Result, WritemagicError};
+
+/// Requests that never finish on their own
+struct Hanging {
+    started: Arc<Notify>,
+    dropped: Arc<AtomicBool>,
+}
+
+/// Marks the in-flight request as dropped
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Hanging {
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        let _flag = DropFlag(self.dropped.clone());
+        self.started.notify_one();
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Err(WritemagicError::timeout(3_600_000))
+    }
+}
+
+fn request() -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user("Write a long essay")], "claude-3-haiku-20240307".to_string())
+}
+
+#[tokio::test]
+async fn test_cancelling_drops_the_in_flight_request() {
+    let started = Arc::new(Notify::new());
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("hanging", Hanging { started: started.clone(), dropped: dropped.clone() }))).await;
+
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        started.notified().await;
+        trigger.cancel();
+    });
+
+    let error = tokio::time::timeout(Duration::from_secs(5), service.complete_with_fallback_cancellable(request(), cancel))
+        .await
+        .expect("cancellation should end the request promptly")
+        .unwrap_err();
+
+    assert!(matches!(error, WritemagicError::Cancelled));
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_already_cancelled_token_never_reaches_a_provider() {
+    let started = Arc::new(Notify::new());
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("hanging", Hanging { started, dropped: dropped.clone() }))).await;
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let error = service.complete_with_fallback_cancellable(request(), cancel).await.unwrap_err();
+
+    assert!(matches!(error, WritemagicError::Cancelled));
+    assert!(!dropped.load(Ordering::SeqCst));
+}
@@ -0,0 +1,73 @@
+//! Tests for tripping and resetting provider circuit breakers by hand
+
+use crate::circuit_breaker::CircuitState;
+use super::support::{reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message};
+use crate::security::SecurityEventType;
+use crate::services::AIOrchestrationService;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Records each call in a log shared with the other providers
+struct Named {
+    name: &'static str,
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Named {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.log.lock().push(self.name);
+        Ok(reply(request, format!("from {}", self.name)))
+    }
+}
+
+/// Service falling back from `openai` to `claude`, with a shared call log
+async fn service() -> (AIOrchestrationService, Arc<Mutex<Vec<&'static str>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut service = AIOrchestrationService::new().unwrap();
+    for name in ["openai", "claude"] {
+        service.add_provider(Arc::new(FakeProvider::new(name, Named { name, log: log.clone() }))).await;
+    }
+    (service, log)
+}
+
+/// Request preferring openai, so only its circuit decides whether it serves
+fn request(prompt: &str) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], MODEL.to_string())
+        .with_provider_preference(vec!["openai".to_string(), "claude".to_string()])
+}
+
+#[tokio::test]
+async fn test_tripped_provider_is_skipped_until_reset() {
+    let (service, log) = service().await;
+
+    let status = service.trip_provider("openai").unwrap();
+    assert!(matches!(status.state, CircuitState::Open { .. }));
+    assert!(status.retry_in.is_some());
+    let events = service.security_logger().get_recent_events(1);
+    assert!(matches!(events[0].event_type, SecurityEventType::CircuitBreakerTripped));
+
+    let response = service.complete_with_fallback(request("Facts about tides")).await.unwrap();
+    assert_eq!(response.provider(), Some("claude"));
+    assert_eq!(*log.lock(), vec!["claude"]);
+
+    let status = service.reset_provider("openai").unwrap();
+    assert_eq!(status.state, CircuitState::Closed);
+    assert_eq!(service.circuit_breaker_statuses()["openai"].state, CircuitState::Closed);
+
+    let response = service.complete_with_fallback(request("Facts about moons")).await.unwrap();
+    assert_eq!(response.provider(), Some("openai"));
+    assert_eq!(*log.lock(), vec!["claude", "openai"]);
+}
+
+#[tokio::test]
+async fn test_unknown_provider_cannot_be_tripped() {
+    let (service, _) = service().await;
+
+    assert!(matches!(service.trip_provider("gemini"), Err(WritemagicError::NotFound { .. })));
+    assert!(matches!(service.reset_provider("gemini"), Err(WritemagicError::NotFound { .. })));
+}
@@ -0,0 +1,128 @@
+//! Tests for checkpointing and restoring session context
+
+use crate::providers::Message;
+use crate::services::ContextManagementService;
+use std::sync::Arc;
+use writemagic_shared::{
+    CheckpointId, CheckpointRetention, ContextCheckpoint, ContextCheckpointStore, EntityId,
+    InMemoryContextCheckpointStore, Timestamp, WritemagicError,
+};
+
+fn conversation() -> Vec<Message> {
+    let mut assistant = Message::assistant("Here is a tighter opening paragraph.");
+    assistant.metadata.insert("model".to_string(), "claude-3-haiku".to_string());
+
+    vec![
+        Message::system("You are a careful editor."),
+        Message::user("Tighten the opening of chapter two."),
+        assistant,
+        Message::user("Now match the tone of chapter one."),
+    ]
+}
+
+fn as_json(messages: &[Message]) -> serde_json::Value {
+    serde_json::to_value(messages).unwrap()
+}
+
+#[tokio::test]
+async fn test_restore_reproduces_checkpointed_context() {
+    let service = ContextManagementService::new(4000).unwrap();
+    let session_id = EntityId::new();
+    service.set_session_context(session_id, conversation()).await;
+
+    let checkpoint_id = service.checkpoint(session_id).await.unwrap();
+    service.set_session_context(session_id, vec![Message::user("Unrelated")]).await;
+
+    let restored = service.restore(&checkpoint_id).await.unwrap();
+
+    assert_eq!(restored.session_id, session_id);
+    assert_eq!(as_json(&restored.messages), as_json(&conversation()));
+    assert_eq!(as_json(&service.session_context(&session_id).await.unwrap()), as_json(&conversation()));
+}
+
+#[tokio::test]
+async fn test_restore_after_restart_uses_persisted_store() {
+    let store = Arc::new(InMemoryContextCheckpointStore::new());
+    let session_id = EntityId::new();
+
+    let before_restart = ContextManagementService::new(4000).unwrap()
+        .with_checkpoint_store(store.clone(), CheckpointRetention::default());
+    before_restart.set_session_context(session_id, conversation()).await;
+    let checkpoint_id = before_restart.checkpoint(session_id).await.unwrap();
+    drop(before_restart);
+
+    let after_restart = ContextManagementService::new(4000).unwrap()
+        .with_checkpoint_store(store, CheckpointRetention::default());
+    let restored = after_restart.restore(&checkpoint_id).await.unwrap();
+
+    assert_eq!(as_json(&restored.messages), as_json(&conversation()));
+}
+
+#[tokio::test]
+async fn test_checkpoints_respect_retention_cap() {
+    let store = Arc::new(InMemoryContextCheckpointStore::new());
+    let service = ContextManagementService::new(4000).unwrap()
+        .with_checkpoint_store(store.clone(), CheckpointRetention { max_per_session: 2, max_age_secs: None });
+    let session_id = EntityId::new();
+
+    let mut checkpoint_ids = Vec::new();
+    for turn in 0..4 {
+        service.set_session_context(session_id, vec![Message::user(format!("Turn {}", turn))]).await;
+        checkpoint_ids.push(service.checkpoint(session_id).await.unwrap());
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+
+    let kept: Vec<_> = store.find_by_session(&session_id).await.unwrap().iter().map(|c| c.id).collect();
+    assert_eq!(kept, vec![checkpoint_ids[3], checkpoint_ids[2]]);
+}
+
+#[tokio::test]
+async fn test_age_pruning_leaves_other_sessions_alone() {
+    let store = Arc::new(InMemoryContextCheckpointStore::new());
+    let service = ContextManagementService::new(4000).unwrap()
+        .with_checkpoint_store(store.clone(), CheckpointRetention { max_per_session: 10, max_age_secs: Some(60) });
+
+    let idle_session = EntityId::new();
+    let idle_checkpoint = ContextCheckpoint {
+        id: CheckpointId::new(),
+        session_id: idle_session,
+        context: "[]".to_string(),
+        created_at: Timestamp::from_datetime(chrono::Utc::now() - chrono::Duration::hours(1)),
+    };
+    store.save(&idle_checkpoint).await.unwrap();
+
+    let active_session = EntityId::new();
+    service.set_session_context(active_session, conversation()).await;
+    service.checkpoint(active_session).await.unwrap();
+
+    assert!(store.find_by_id(&idle_checkpoint.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_restoring_pruned_checkpoint_is_not_found() {
+    let service = ContextManagementService::new(4000).unwrap()
+        .with_checkpoint_store(
+            Arc::new(InMemoryContextCheckpointStore::new()),
+            CheckpointRetention { max_per_session: 1, max_age_secs: None },
+        );
+    let session_id = EntityId::new();
+
+    service.set_session_context(session_id, conversation()).await;
+    let pruned = service.checkpoint(session_id).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    service.checkpoint(session_id).await.unwrap();
+
+    let error = service.restore(&pruned).await.unwrap_err();
+    assert!(matches!(error, WritemagicError::NotFound { .. }));
+    assert!(error.to_string().contains(&pruned.to_string()));
+}
+
+#[tokio::test]
+async fn test_checkpoint_without_context_fails() {
+    let service = ContextManagementService::new(4000).unwrap();
+
+    assert!(matches!(
+        service.checkpoint(EntityId::new()).await,
+        Err(WritemagicError::NotFound { .. })
+    ));
+}
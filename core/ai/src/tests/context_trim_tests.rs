@@ -0,0 +1,207 @@
+//! Tests for context window trimming strategies
+
+use super::support::{last_prompt, reply, FakeBehavior, FakeProvider};
+use crate::cost::ModelPrice;
+use crate::providers::{CompletionRequest, CompletionResponse, Message, MessageRole};
+use crate::services::{AIOrchestrationService, ContextManagementService, ContextTrimStrategy};
+use crate::tokenization::{ModelTokenizer, ModelTokenizerConfig};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "gpt-4";
+
+/// Answers every request with a fixed summary, recording the prompts
+struct Summarizer {
+    prompts: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Summarizer {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.prompts.lock().push(last_prompt(request));
+        Ok(reply(request, "They settled on a title."))
+    }
+}
+
+/// Summary provider that is down
+struct Unavailable;
+
+#[async_trait::async_trait]
+impl FakeBehavior for Unavailable {
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        Err(WritemagicError::network("connection refused"))
+    }
+}
+
+/// Summarizes with a contact address the response sanitizer must mask
+struct LeakySummarizer;
+
+#[async_trait::async_trait]
+impl FakeBehavior for LeakySummarizer {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        Ok(reply(request, "Send the title to jane.doe@example.com."))
+    }
+}
+
+/// Main provider answering every question the same way
+struct Answer;
+
+#[async_trait::async_trait]
+impl FakeBehavior for Answer {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        Ok(reply(request, "Yes."))
+    }
+}
+
+/// Tokens `message` takes in the context, counted the way the service counts them
+fn tokens(message: &Message) -> u32 {
+    let tokenizer = ModelTokenizer::new(ModelTokenizerConfig::gpt_4()).unwrap();
+    tokenizer.count_tokens(&message.content).unwrap() + 4
+}
+
+/// A system prompt, three long early turns, a short reply and the latest question
+fn conversation() -> Vec<Message> {
+    vec![
+        Message::system("You are a helpful editor."),
+        Message::user(format!("Here is my outline. {}", "Chapter notes go on. ".repeat(40))),
+        Message::assistant(format!("Thoughts on the outline. {}", "More feedback here. ".repeat(40))),
+        Message::user(format!("And the draft title list. {}", "Another option. ".repeat(40))),
+        Message::assistant("The second title is strongest."),
+        Message::user("Shall we go with it?"),
+    ]
+}
+
+fn contents(messages: &[Message]) -> Vec<&str> {
+    messages.iter().map(|m| m.content.as_str()).collect()
+}
+
+/// Budget for the system prompt, latest question and short reply, plus `extra` tokens
+fn budget(messages: &[Message], extra: u32) -> u32 {
+    tokens(&messages[0]) + tokens(&messages[4]) + tokens(&messages[5]) + extra
+}
+
+#[test]
+fn test_drop_oldest_keeps_newest_messages_that_fit() {
+    let messages = conversation();
+    let service = ContextManagementService::new(budget(&messages, 10)).unwrap();
+
+    let fitted = service.manage_context(messages.clone(), MODEL).unwrap();
+    assert_eq!(contents(&fitted), contents(&[messages[0].clone(), messages[4].clone(), messages[5].clone()]));
+}
+
+#[test]
+fn test_sliding_window_limits_message_count() {
+    let messages = conversation();
+    let service = ContextManagementService::new(100_000).unwrap()
+        .with_trim_strategy(ContextTrimStrategy::SlidingWindow { max_messages: 3 });
+
+    let fitted = service.manage_context(messages.clone(), MODEL).unwrap();
+    let roles: Vec<MessageRole> = fitted.iter().map(|m| m.role.clone()).collect();
+    assert_eq!(roles, vec![MessageRole::System, MessageRole::User, MessageRole::Assistant, MessageRole::User]);
+    assert_eq!(fitted[1].content, messages[3].content);
+}
+
+#[test]
+fn test_minimal_context_over_window_is_an_error() {
+    let messages = conversation();
+    let minimal = tokens(&messages[0]) + tokens(&messages[5]);
+    let service = ContextManagementService::new(minimal - 1).unwrap();
+
+    let error = service.manage_context(messages.clone(), MODEL).unwrap_err();
+    assert!(matches!(error, WritemagicError::Validation { .. }));
+
+    let exact = ContextManagementService::new(minimal).unwrap();
+    assert_eq!(exact.manage_context(messages, MODEL).unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_summarize_oldest_condenses_dropped_messages() {
+    let messages = conversation();
+    let provider = Arc::new(
+        FakeProvider::new("summary", Summarizer { prompts: Mutex::new(Vec::new()) }).with_context_window(8192),
+    );
+    let max_tokens = budget(&messages, 40);
+    let service = ContextManagementService::new(max_tokens).unwrap()
+        .with_trim_strategy(ContextTrimStrategy::SummarizeOldest)
+        .with_summary_provider(provider.clone(), "summary-model");
+
+    let fitted = service.fit_context(messages.clone(), MODEL).await.unwrap();
+    assert_eq!(fitted.len(), 4);
+    assert_eq!(fitted[0].content, messages[0].content);
+    assert!(matches!(fitted[1].role, MessageRole::System));
+    assert!(fitted[1].content.ends_with("They settled on a title."));
+    assert_eq!(contents(&fitted[2..]), contents(&messages[4..]));
+    assert!(fitted.iter().map(tokens).sum::<u32>() <= max_tokens);
+
+    let prompts = provider.prompts.lock();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("Here is my outline."));
+    assert!(prompts[0].contains("And the draft title list."));
+}
+
+#[tokio::test]
+async fn test_failed_summary_falls_back_to_the_trimmed_context() {
+    let messages = conversation();
+    let max_tokens = budget(&messages, 40);
+    let service = ContextManagementService::new(max_tokens).unwrap()
+        .with_trim_strategy(ContextTrimStrategy::SummarizeOldest)
+        .with_summary_provider(Arc::new(FakeProvider::new("summary", Unavailable)), "summary-model");
+
+    let fitted = service.fit_context(messages.clone(), MODEL).await.unwrap();
+
+    let dropped = ContextManagementService::new(max_tokens).unwrap().manage_context(messages, MODEL).unwrap();
+    assert_eq!(contents(&fitted), contents(&dropped));
+}
+
+#[tokio::test]
+async fn test_orchestrated_summary_counts_towards_spend() {
+    let messages = conversation();
+    let summary_provider = Arc::new(FakeProvider::new("summary", LeakySummarizer).with_context_window(8192));
+    let context = ContextManagementService::new(budget(&messages, 40)).unwrap()
+        .with_trim_strategy(ContextTrimStrategy::SummarizeOldest)
+        .with_summary_provider(summary_provider, "summary-model");
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("main", Answer))).await;
+    service.set_context_manager(context);
+    let price = ModelPrice { input_cost_per_token: 0.001, output_cost_per_token: 0.002 };
+    service.cost_estimator().set_price("summary", "summary-model", price);
+
+    let response = service.complete_with_fallback(CompletionRequest::new(messages, MODEL.to_string())).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Yes.");
+    // Only the summary was priced, so any spend is the summary's
+    assert!(service.spend_tracker().spent(chrono::Utc::now()) > 0.0);
+}
+
+#[tokio::test]
+async fn test_sanitized_summary_reaches_the_main_provider() {
+    /// Records the messages the main provider is sent
+    struct Recording {
+        sent: Mutex<Vec<Message>>,
+    }
+
+    #[async_trait::async_trait]
+    impl FakeBehavior for Recording {
+        async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+            *self.sent.lock() = request.messages.clone();
+            Ok(reply(request, "Yes."))
+        }
+    }
+
+    let messages = conversation();
+    let context = ContextManagementService::new(budget(&messages, 40)).unwrap()
+        .with_trim_strategy(ContextTrimStrategy::SummarizeOldest)
+        .with_summary_provider(Arc::new(FakeProvider::new("summary", LeakySummarizer)), "summary-model");
+    let main = Arc::new(FakeProvider::new("main", Recording { sent: Mutex::new(Vec::new()) }));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(main.clone()).await;
+    service.set_context_manager(context);
+
+    service.complete_with_fallback(CompletionRequest::new(messages, MODEL.to_string())).await.unwrap();
+
+    let sent = main.sent.lock();
+    let summary = sent.iter().find(|m| m.content.starts_with("Summary of the earlier conversation")).unwrap();
+    assert!(summary.content.contains("[REDACTED"), "{}", summary.content);
+    assert!(!summary.content.contains("jane.doe@example.com"));
+}
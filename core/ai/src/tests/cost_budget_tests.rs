@@ -0,0 +1,141 @@
+//! Tests for cost estimation and the monthly spend budget
+
+use crate::cost::{CostEstimator, ModelPrice, SpendTracker};
+use super::support::{reply, FakeBehavior, FakeProvider, OneChunk};
+use crate::providers::{CompletionRequest, CompletionResponse, Message, StreamingResponse};
+use crate::services::AIOrchestrationService;
+use crate::tokenization::TokenUsage;
+use chrono::{TimeZone, Utc};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Counts the completions it serves, streamed or not
+struct Counting {
+    calls: Arc<Mutex<u32>>,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Counting {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        *self.calls.lock() += 1;
+        Ok(reply(request, "A short answer"))
+    }
+
+    async fn stream(&self, _request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        *self.calls.lock() += 1;
+        Ok(Box::new(OneChunk(Some("A short streamed answer".to_string()))))
+    }
+}
+
+fn request(prompt: &str) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], MODEL.to_string())
+}
+
+#[test]
+fn test_estimator_prefers_the_most_specific_price_and_accepts_new_models() {
+    let estimator = CostEstimator::new();
+    let usage = TokenUsage::new(1_000_000, 1_000_000, 0.0, 0.0);
+
+    assert_eq!(estimator.estimate("openai", "gpt-4o-mini-2024-07-18", &usage), Some(0.15 + 0.6));
+    assert_eq!(estimator.estimate("openai", "gpt-4o-2024-08-06", &usage), Some(2.5 + 10.0));
+    assert_eq!(estimator.estimate("openai", "gpt-4-0613", &usage), Some(30.0 + 60.0));
+    assert_eq!(estimator.estimate("claude", "gpt-4", &usage), None);
+    assert_eq!(estimator.estimate("claude", "claude-4-sonnet", &usage), None);
+
+    estimator.set_price("claude", "claude-4*", ModelPrice::per_million_tokens(3.0, 15.0));
+    assert_eq!(estimator.estimate("claude", "claude-4-sonnet", &usage), Some(18.0));
+    estimator.set_price("claude", "claude-4*", ModelPrice::per_million_tokens(1.0, 1.0));
+    assert_eq!(estimator.estimate("claude", "claude-4-sonnet", &usage), Some(2.0));
+}
+
+#[test]
+fn test_budget_allows_spend_up_to_the_limit_and_refuses_beyond_it() {
+    let now = Utc.with_ymd_and_hms(2026, 3, 14, 12, 0, 0).unwrap();
+    let tracker = SpendTracker::new();
+    tracker.record("claude", 4.0, now);
+    tracker.record("openai", 6.0, now);
+
+    assert_eq!(tracker.spent(now), 10.0);
+    assert!(tracker.check_budget(Some(10.0), now).is_ok());
+    assert!(tracker.check_budget(None, now).is_ok());
+
+    tracker.record("claude", 0.01, now);
+    let error = tracker.check_budget(Some(10.0), now).unwrap_err();
+    assert!(matches!(error, WritemagicError::BudgetExceeded { budget_usd, .. } if budget_usd == 10.0));
+    assert_eq!(tracker.spent_by_provider(now)["claude"], 4.01);
+}
+
+#[test]
+fn test_spend_starts_again_in_a_new_month() {
+    let end_of_january = Utc.with_ymd_and_hms(2026, 1, 31, 23, 59, 59).unwrap();
+    let start_of_february = Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap();
+    let tracker = SpendTracker::new();
+    tracker.record("claude", 25.0, end_of_january);
+    assert!(tracker.check_budget(Some(20.0), end_of_january).is_err());
+
+    assert_eq!(tracker.spent(start_of_february), 0.0);
+    assert!(tracker.check_budget(Some(20.0), start_of_february).is_ok());
+    assert!(tracker.spent_by_provider(start_of_february).is_empty());
+
+    // A late record from the previous month is not counted towards the current one
+    tracker.record("claude", 2.0, end_of_january);
+    assert_eq!(tracker.spent(start_of_february), 0.0);
+
+    let next_year = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(tracker.spent(next_year), 0.0);
+}
+
+#[tokio::test]
+async fn test_completions_are_refused_once_spend_exceeds_the_budget() {
+    let calls = Arc::new(Mutex::new(0));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("claude", Counting { calls: calls.clone() }))).await;
+    service.cost_estimator().set_price("claude", MODEL, ModelPrice {
+        input_cost_per_token: 0.01,
+        output_cost_per_token: 0.01,
+    });
+
+    service.complete_with_fallback(request("First question")).await.unwrap();
+    let spent = service.spend_tracker().spent(Utc::now());
+    assert!(spent > 0.0);
+    let stats = service.get_provider_performance("claude").await.unwrap();
+    assert_eq!(stats.month_to_date_cost, spent);
+    assert_eq!(stats.total_cost, spent);
+
+    // Reaching the budget exactly still allows a completion
+    service.set_monthly_budget(Some(spent));
+    service.complete_with_fallback(request("Second question")).await.unwrap();
+
+    let error = service.complete_with_fallback(request("Third question")).await.unwrap_err();
+    assert!(matches!(error, WritemagicError::BudgetExceeded { budget_usd, .. } if budget_usd == spent));
+    assert_eq!(*calls.lock(), 2);
+
+    service.set_monthly_budget(None);
+    service.complete_with_fallback(request("Third question")).await.unwrap();
+    assert_eq!(*calls.lock(), 3);
+}
+
+#[tokio::test]
+async fn test_streamed_completions_count_towards_the_budget() {
+    let calls = Arc::new(Mutex::new(0));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("claude", Counting { calls: calls.clone() }).streaming())).await;
+    service.cost_estimator().set_price("claude", MODEL, ModelPrice {
+        input_cost_per_token: 0.01,
+        output_cost_per_token: 0.01,
+    });
+
+    let mut stream = service.stream_completion(request("First question")).await.unwrap();
+    assert_eq!(service.spend_tracker().spent(Utc::now()), 0.0);
+    while stream.next_chunk().await.unwrap().is_some() {}
+    let spent = service.spend_tracker().spent(Utc::now());
+    assert!(spent > 0.0);
+
+    service.set_monthly_budget(Some(spent / 2.0));
+    let error = service.stream_completion(request("Second question")).await.err().unwrap();
+    assert!(matches!(error, WritemagicError::BudgetExceeded { .. }));
+    assert_eq!(*calls.lock(), 1);
+}
@@ -0,0 +1,258 @@
+//! Tests for per-request provider credentials
+
+use super::support::{last_prompt, reply, FakeBehavior, FakeProvider, OneChunk};
+use crate::providers::{
+    AIProvider, ClaudeProvider, CompletionRequest, CompletionResponse, Message, ProviderCredentials, ResponseCache,
+    StreamingResponse,
+};
+use crate::services::AIOrchestrationService;
+use parking_lot::Mutex;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use writemagic_shared::{Result, WritemagicError};
+
+/// Records the API key each prompt was sent with
+struct Recording {
+    calls: Mutex<Vec<(String, String)>>,
+}
+
+impl Recording {
+    fn provider() -> Arc<FakeProvider<Self>> {
+        Arc::new(FakeProvider::new("claude", Self { calls: Mutex::new(Vec::new()) }).streaming())
+    }
+
+    fn calls(&self) -> Vec<(String, String)> {
+        self.calls.lock().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Recording {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let (api_key, _) = request.endpoint_for("claude", "shared-key", "https://api.anthropic.com");
+        self.calls.lock().push((last_prompt(request), api_key.to_string()));
+
+        // Stay in flight long enough for concurrent requests to overlap
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        Ok(reply(request, format!("answered with {}", api_key)))
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        let (api_key, _) = request.endpoint_for("claude", "shared-key", "https://api.anthropic.com");
+        self.calls.lock().push((last_prompt(request), api_key.to_string()));
+        Ok(Box::new(OneChunk(Some(format!("streamed with {}", api_key)))))
+    }
+}
+
+fn request(prompt: &str) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], "claude-3-haiku-20240307".to_string())
+}
+
+fn user_credentials(api_key: &str) -> ProviderCredentials {
+    ProviderCredentials::new("claude", api_key)
+}
+
+const CLAUDE_REPLY: &str = r#"{"id":"msg_mock","model":"claude-3-haiku-20240307","content":[{"type":"text","text":"hello from mock"}],"usage":{"input_tokens":3,"output_tokens":4}}"#;
+
+/// Answer one Claude messages request on a local port with `status` and
+/// `body`, returning its URL and a handle yielding the raw request that was received
+fn serve_one_claude_response(status: &'static str, body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            if read == 0 {
+                break;
+            }
+            received.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&received).to_lowercase();
+            if let Some(head_end) = text.find("\r\n\r\n") {
+                let content_length = text[..head_end]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|value| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if received.len() >= head_end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        String::from_utf8_lossy(&received).to_string()
+    });
+
+    (url, server)
+}
+
+#[tokio::test]
+async fn test_override_credentials_are_sent_to_the_provider() {
+    let (url, server) = serve_one_claude_response("200 OK", CLAUDE_REPLY);
+    // The shared endpoint refuses connections, so only the override can answer
+    let provider = ClaudeProvider::new("shared-key".to_string())
+        .unwrap()
+        .with_base_url("http://127.0.0.1:9".to_string());
+
+    let request = request("Hello").with_credentials(user_credentials("user-key").with_base_url(url));
+    let response = provider.complete(&request).await.unwrap();
+    assert_eq!(response.choices[0].message.content, "hello from mock");
+
+    let received = server.join().unwrap().to_lowercase();
+    assert!(received.contains("x-api-key: user-key"));
+    assert!(received.contains("authorization: bearer user-key"));
+    assert!(!received.contains("shared-key"));
+}
+
+#[tokio::test]
+async fn test_override_credentials_are_sent_with_streams() {
+    let (url, server) = serve_one_claude_response(
+        "400 Bad Request",
+        r#"{"type":"error","error":{"type":"invalid_request_error","message":"Output blocked by content filtering policy"}}"#,
+    );
+    let provider = ClaudeProvider::new("shared-key".to_string())
+        .unwrap()
+        .with_base_url("http://127.0.0.1:9".to_string());
+
+    let request = request("Hello").with_credentials(user_credentials("user-key").with_base_url(url));
+    let error = provider.stream(&request).await.err().unwrap();
+    assert!(error.is_request_rejection(), "{:?}", error);
+
+    let received = server.join().unwrap().to_lowercase();
+    assert!(received.contains("x-api-key: user-key"));
+    assert!(received.contains("authorization: bearer user-key"));
+    assert!(received.contains("anthropic-version: 2023-06-01"));
+    assert!(!received.contains("shared-key"));
+}
+
+#[tokio::test]
+async fn test_override_does_not_leak_into_concurrent_requests() {
+    let provider = Recording::provider();
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+
+    let (alice, bob, shared) = tokio::join!(
+        service.complete_with_fallback(request("Draft for alice").with_credentials(user_credentials("alice-key"))),
+        service.complete_with_fallback(request("Draft for bob").with_credentials(user_credentials("bob-key"))),
+        service.complete_with_fallback(request("Draft for everyone")),
+    );
+
+    assert_eq!(alice.unwrap().choices[0].message.content, "answered with alice-key");
+    assert_eq!(bob.unwrap().choices[0].message.content, "answered with bob-key");
+    assert_eq!(shared.unwrap().choices[0].message.content, "answered with shared-key");
+
+    let mut calls = provider.calls();
+    calls.sort();
+    assert_eq!(calls, vec![
+        ("Draft for alice".to_string(), "alice-key".to_string()),
+        ("Draft for bob".to_string(), "bob-key".to_string()),
+        ("Draft for everyone".to_string(), "shared-key".to_string()),
+    ]);
+}
+
+#[tokio::test]
+async fn test_override_responses_bypass_the_shared_cache() {
+    let provider = Recording::provider();
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+
+    // A shared response is cached for later shared requests...
+    service.complete_with_fallback(request("Same prompt")).await.unwrap();
+    service.complete_with_fallback(request("Same prompt")).await.unwrap();
+    assert_eq!(provider.calls().len(), 1);
+
+    // ...but never served to, or replaced by, callers bringing their own key
+    let own = service
+        .complete_with_fallback(request("Same prompt").with_credentials(user_credentials("alice-key")))
+        .await
+        .unwrap();
+    assert_eq!(own.choices[0].message.content, "answered with alice-key");
+    service
+        .complete_with_fallback(request("Same prompt").with_credentials(user_credentials("alice-key")))
+        .await
+        .unwrap();
+    assert_eq!(provider.calls().len(), 3);
+
+    let shared = service.complete_with_fallback(request("Same prompt")).await.unwrap();
+    assert_eq!(shared.choices[0].message.content, "answered with shared-key");
+    assert_eq!(provider.calls().len(), 3);
+}
+
+#[test]
+fn test_override_is_excluded_from_cache_keys() {
+    let plain = request("Hello");
+    let overridden = request("Hello").with_credentials(user_credentials("user-key"));
+
+    assert_eq!(ResponseCache::generate_cache_key(&plain), ResponseCache::generate_cache_key(&overridden));
+    assert!(ResponseCache::shared_cache_key(&plain).is_some());
+    assert!(ResponseCache::shared_cache_key(&overridden).is_none());
+}
+
+#[test]
+fn test_override_credentials_are_never_logged_or_serialized() {
+    let request = request("Hello").with_credentials(user_credentials("user-secret-key"));
+
+    assert!(!format!("{:?}", request).contains("user-secret-key"));
+    assert!(!serde_json::to_string(&request).unwrap().contains("user-secret-key"));
+}
+
+#[tokio::test]
+async fn test_override_base_url_must_be_an_allowed_endpoint() {
+    let provider = Recording::provider();
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+
+    for base_url in ["http://169.254.169.254/latest/meta-data", "http://api.anthropic.com", "not a url"] {
+        let credentials = user_credentials("alice-key").with_base_url(base_url);
+        let result = service.complete_with_fallback(request("Hello").with_credentials(credentials)).await;
+        assert!(matches!(result, Err(WritemagicError::Validation { .. })), "{} was accepted", base_url);
+    }
+    assert!(provider.calls().is_empty());
+
+    let official = user_credentials("alice-key").with_base_url("https://api.anthropic.com/");
+    service.complete_with_fallback(request("Hello").with_credentials(official)).await.unwrap();
+
+    service.set_credential_endpoints(vec!["http://127.0.0.1:8080".to_string()]);
+    let local = user_credentials("alice-key").with_base_url("http://127.0.0.1:8080/v1");
+    service.complete_with_fallback(request("Hello").with_credentials(local)).await.unwrap();
+    assert_eq!(provider.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn test_override_streams_bypass_the_shared_circuit_breaker() {
+    let provider = Recording::provider();
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+    service.trip_provider("claude").unwrap();
+
+    // The shared provider is out of service, but the caller's own key is not
+    assert!(service.stream_completion(request("Shared")).await.is_err());
+    let mut stream = service
+        .stream_completion(request("Own").with_credentials(user_credentials("alice-key")))
+        .await
+        .unwrap();
+    let mut output = String::new();
+    while let Some(chunk) = stream.next_chunk().await.unwrap() {
+        output.push_str(&chunk.content);
+    }
+
+    assert_eq!(output, "streamed with alice-key");
+    assert_eq!(provider.calls(), vec![("Own".to_string(), "alice-key".to_string())]);
+}
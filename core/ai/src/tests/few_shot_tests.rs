@@ -0,0 +1,157 @@
+//! Tests for few-shot examples in completion requests
+
+use crate::few_shot::FewShotExampleSet;
+use super::support::{reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message, MessageRole};
+use crate::services::{AIOrchestrationService, ContentFilteringService, ContextManagementService};
+use crate::tokenization::TokenizationService;
+use crate::writing_service::{
+    AIWritingService, WritingAssistanceRequest, WritingAssistanceType, WritingContext, WritingPreferences,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use writemagic_shared::{EntityId, Result};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Records the messages of every request it receives
+struct Recording {
+    requests: Mutex<Vec<Vec<Message>>>,
+}
+
+impl Recording {
+    fn provider() -> Arc<FakeProvider<Self>> {
+        Arc::new(FakeProvider::new("claude", Self { requests: Mutex::new(Vec::new()) }))
+    }
+
+    fn last_messages(&self) -> Vec<Message> {
+        self.requests.lock().last().cloned().unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Recording {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.requests.lock().push(request.messages.clone());
+        Ok(reply(request, "A bright, brisk morning."))
+    }
+}
+
+async fn writing_service(provider: Arc<FakeProvider<Recording>>, max_context_tokens: u32) -> AIWritingService {
+    let mut orchestration = AIOrchestrationService::new().unwrap();
+    orchestration.add_provider(provider).await;
+
+    AIWritingService::new(
+        Arc::new(orchestration),
+        Arc::new(ContextManagementService::new(max_context_tokens).unwrap()),
+        Arc::new(ContentFilteringService::new().unwrap()),
+    )
+}
+
+fn examples(count: usize) -> Vec<(String, String)> {
+    (1..=count)
+        .map(|i| (format!("Describe evening {}", i), format!("A quiet, amber evening number {}.", i)))
+        .collect()
+}
+
+fn roles_and_contents(messages: &[Message]) -> Vec<(MessageRole, String)> {
+    messages.iter().map(|m| (m.role.clone(), m.content.clone())).collect()
+}
+
+#[tokio::test]
+async fn test_few_shot_pairs_become_alternating_messages_before_the_prompt() {
+    let provider = Recording::provider();
+    let service = writing_service(provider.clone(), 8000).await;
+    service
+        .example_repository()
+        .save(&FewShotExampleSet::new("evenings", examples(2)))
+        .await
+        .unwrap();
+
+    let request = WritingAssistanceRequest {
+        context: WritingContext {
+            document_id: EntityId::new(),
+            document_title: "Diary".to_string(),
+            document_content: "Dear diary,".to_string(),
+            content_type: writemagic_shared::ContentType::Markdown,
+            selection: None,
+            project_context: None,
+            conversation_history: Vec::new(),
+            user_preferences: WritingPreferences::default(),
+        },
+        assistance_type: WritingAssistanceType::ContentGeneration,
+        user_input: Some("Describe the morning".to_string()),
+        model_config: None,
+        stream_response: false,
+        example_set: Some("evenings".to_string()),
+    };
+    service.provide_assistance(request).await.unwrap();
+
+    let messages = provider.last_messages();
+    assert_eq!(messages[0].role, MessageRole::System);
+    let turns = roles_and_contents(&messages[1..]);
+    assert_eq!(&turns[..4], &[
+        (MessageRole::User, "Describe evening 1".to_string()),
+        (MessageRole::Assistant, "A quiet, amber evening number 1.".to_string()),
+        (MessageRole::User, "Describe evening 2".to_string()),
+        (MessageRole::Assistant, "A quiet, amber evening number 2.".to_string()),
+    ]);
+    assert_eq!(turns.len(), 5);
+    assert_eq!(turns[4].0, MessageRole::User);
+    assert!(turns[4].1.contains("Describe the morning"));
+}
+
+#[tokio::test]
+async fn test_few_shot_examples_are_trimmed_oldest_first_over_budget() {
+    let tokenizer = TokenizationService::new().unwrap();
+    let count = |text: &str| tokenizer.count_tokens(text, MODEL).unwrap();
+    let prompt = "Describe the morning";
+    let pair_tokens = |(input, output): &(String, String)| count(input) + count(output) + 8;
+
+    // Room for the prompt and the two newest examples only
+    let all = examples(3);
+    let budget = count(prompt) + 4 + pair_tokens(&all[1]) + pair_tokens(&all[2]);
+    let service = writing_service(Recording::provider(), budget).await;
+
+    let request = CompletionRequest::new(vec![Message::user(prompt)], MODEL.to_string()).with_few_shot(all);
+    let expanded = service.expand_few_shot(request).unwrap();
+
+    assert_eq!(roles_and_contents(&expanded.messages), vec![
+        (MessageRole::User, "Describe evening 2".to_string()),
+        (MessageRole::Assistant, "A quiet, amber evening number 2.".to_string()),
+        (MessageRole::User, "Describe evening 3".to_string()),
+        (MessageRole::Assistant, "A quiet, amber evening number 3.".to_string()),
+        (MessageRole::User, prompt.to_string()),
+    ]);
+    assert!(expanded.few_shot.is_empty());
+}
+
+#[tokio::test]
+async fn test_empty_example_set_leaves_a_single_message_request() {
+    let service = writing_service(Recording::provider(), 8000).await;
+
+    let request = CompletionRequest::new(vec![Message::user("Describe the morning")], MODEL.to_string())
+        .with_few_shot(Vec::new());
+    let expanded = service.expand_few_shot(request).unwrap();
+
+    assert_eq!(roles_and_contents(&expanded.messages), vec![
+        (MessageRole::User, "Describe the morning".to_string()),
+    ]);
+}
+
+#[tokio::test]
+async fn test_few_shot_examples_reach_the_provider_without_the_writing_service() {
+    let provider = Recording::provider();
+    let mut orchestration = AIOrchestrationService::new().unwrap();
+    orchestration.add_provider(provider.clone()).await;
+
+    let request = CompletionRequest::new(vec![Message::user("Describe the morning")], MODEL.to_string())
+        .with_few_shot(examples(1));
+    orchestration.complete_with_fallback(request).await.unwrap();
+
+    assert_eq!(roles_and_contents(&provider.last_messages()), vec![
+        (MessageRole::User, "Describe evening 1".to_string()),
+        (MessageRole::Assistant, "A quiet, amber evening number 1.".to_string()),
+        (MessageRole::User, "Describe the morning".to_string()),
+    ]);
+}
@@ -1,3 +1,20 @@
 //! Unit tests for the AI crate
 
-mod atomic_stats_tests;
\ No newline at end of file
+mod support;
+
+mod atomic_stats_tests;
+mod provider_error_tests;
+mod context_checkpoint_tests;
+mod stream_limit_tests;
+mod priority_dispatch_tests;
+mod credentials_override_tests;
+mod few_shot_tests;
+mod prompt_template_tests;
+mod stream_fallback_tests;
+mod cancellation_tests;
+mod provider_preference_tests;
+mod context_trim_tests;
+mod response_cache_tests;
+mod content_filter_tests;
+mod circuit_control_tests;
+mod cost_budget_tests;
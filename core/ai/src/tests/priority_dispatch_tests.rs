@@ -0,0 +1,131 @@
+//! Tests for priority dispatch of requests under the concurrency limit
+
+use crate::dispatch::{AiPriority, DispatchConfig, PriorityDispatcher};
+use super::support::{last_prompt, reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message};
+use crate::services::AIOrchestrationService;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use writemagic_shared::{Result, WritemagicError};
+
+/// Records each prompt, then holds the request until the gate opens
+struct Gated {
+    prompts: Mutex<Vec<String>>,
+    gate: Semaphore,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Gated {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let prompt = last_prompt(request);
+        self.prompts.lock().push(prompt.clone());
+        self.gate.acquire().await.map_err(|_| WritemagicError::internal("gate closed"))?.forget();
+
+        Ok(reply(request, format!("done: {}", prompt)))
+    }
+}
+
+fn request(prompt: &str, priority: AiPriority) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], "claude-3-haiku-20240307".to_string())
+        .with_priority(priority.into())
+}
+
+/// Wait until `expected` requests are queued behind the held slot
+async fn wait_for_queue(dispatcher: &PriorityDispatcher, expected: usize) {
+    while dispatcher.queued() < expected {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_interactive_requests_dispatched_before_background_under_contention() {
+    let provider = Arc::new(FakeProvider::new("gated", Gated { prompts: Mutex::new(Vec::new()), gate: Semaphore::new(0) }));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+    service.set_dispatch_config(&DispatchConfig { max_concurrent_requests: 1, aging_interval_ms: 3_600_000 });
+    let service = Arc::new(service);
+
+    // Occupies the only slot until the gate opens
+    let mut handles = vec![tokio::spawn({
+        let service = service.clone();
+        async move { service.complete_with_fallback(request("warm up", AiPriority::Interactive)).await }
+    })];
+    while provider.prompts.lock().is_empty() {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let submissions = [
+        ("background 1", AiPriority::Background),
+        ("interactive 1", AiPriority::Interactive),
+        ("background 2", AiPriority::Background),
+        ("normal 1", AiPriority::Normal),
+        ("interactive 2", AiPriority::Interactive),
+    ];
+    for (queued, (prompt, priority)) in submissions.iter().copied().enumerate() {
+        let submitter = service.clone();
+        handles.push(tokio::spawn(async move { submitter.complete_with_fallback(request(prompt, priority)).await }));
+        wait_for_queue(service.dispatcher(), queued + 1).await;
+    }
+
+    provider.gate.add_permits(submissions.len() + 1);
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+
+    assert_eq!(
+        *provider.prompts.lock(),
+        vec!["warm up", "interactive 1", "interactive 2", "normal 1", "background 1", "background 2"]
+    );
+}
+
+#[tokio::test]
+async fn test_waiting_background_request_ages_past_new_interactive_ones() {
+    let dispatcher = Arc::new(PriorityDispatcher::new(&DispatchConfig { max_concurrent_requests: 1, aging_interval_ms: 50 }));
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let held = dispatcher.acquire(AiPriority::Interactive).await;
+
+    let submit = |label: &'static str, priority: AiPriority| {
+        let dispatcher = dispatcher.clone();
+        let order = order.clone();
+        tokio::spawn(async move {
+            let _permit = dispatcher.acquire(priority).await;
+            order.lock().push(label);
+        })
+    };
+
+    let background = submit("background", AiPriority::Background);
+    wait_for_queue(&dispatcher, 1).await;
+    // Waiting six aging intervals lifts it well above a fresh interactive request
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let interactive = submit("interactive", AiPriority::Interactive);
+    wait_for_queue(&dispatcher, 2).await;
+
+    drop(held);
+    background.await.unwrap();
+    interactive.await.unwrap();
+
+    assert_eq!(*order.lock(), vec!["background", "interactive"]);
+    assert_eq!(dispatcher.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_cancelled_waiter_does_not_leak_its_slot() {
+    let dispatcher = Arc::new(PriorityDispatcher::new(&DispatchConfig { max_concurrent_requests: 1, aging_interval_ms: 50 }));
+    let held = dispatcher.acquire(AiPriority::Normal).await;
+
+    let abandoned = tokio::spawn({
+        let dispatcher = dispatcher.clone();
+        async move { dispatcher.acquire(AiPriority::Interactive).await }
+    });
+    wait_for_queue(&dispatcher, 1).await;
+    abandoned.abort();
+    let _ = abandoned.await;
+
+    drop(held);
+    assert_eq!(dispatcher.in_flight(), 0);
+    let _permit = tokio::time::timeout(Duration::from_secs(1), dispatcher.acquire(AiPriority::Background))
+        .await
+        .expect("slot should be free");
+}
@@ -0,0 +1,87 @@
+//! Tests for prompt template rendering and completion
+
+use crate::prompt_templates::PromptTemplate;
+use super::support::{last_prompt, reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse};
+use crate::services::{AIOrchestrationService, ContentFilteringService, ContextManagementService};
+use crate::writing_service::AIWritingService;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use writemagic_shared::{EntityId, Result, WritemagicError};
+
+/// Echoes back the prompt of every request it receives
+struct Echo {
+    prompts: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Echo {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let prompt = last_prompt(request);
+        self.prompts.lock().push(prompt.clone());
+        Ok(reply(request, prompt))
+    }
+}
+
+fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn test_render_substitutes_placeholders_and_keeps_escaped_braces() {
+    let template = PromptTemplate::new(
+        "rewrite",
+        r"Rewrite {{ text }} in a {{tone}} tone. Keep \{{markers}} and \\ as written.",
+        Vec::new(),
+    )
+    .unwrap();
+
+    let rendered = template.render(&variables(&[("text", "this {{tone}}"), ("tone", "warm")])).unwrap();
+    // Values are inserted verbatim rather than expanded again
+    assert_eq!(rendered, r"Rewrite this {{tone}} in a warm tone. Keep {{markers}} and \ as written.");
+    assert_eq!(template.placeholders().unwrap(), vec!["text", "tone"]);
+}
+
+#[test]
+fn test_render_lists_every_missing_variable() {
+    let template = PromptTemplate::new("outline", "Outline {{topic}} for {{audience}}", vec!["length".to_string()]).unwrap();
+
+    let error = template.render(&variables(&[("audience", "students")])).unwrap_err();
+    assert!(matches!(error, WritemagicError::Validation { .. }));
+    assert!(error.to_string().contains("length, topic"), "{}", error);
+}
+
+#[test]
+fn test_malformed_bodies_are_rejected() {
+    assert!(PromptTemplate::new("nested", "Say {{outer {{inner}} }}", Vec::new()).is_err());
+    assert!(PromptTemplate::new("open", "Say {{greeting", Vec::new()).is_err());
+    assert!(PromptTemplate::new("blank", "Say {{ }}", Vec::new()).is_err());
+    assert!(PromptTemplate::new("bad", "Say hi", vec!["two words".to_string()]).is_err());
+}
+
+#[tokio::test]
+async fn test_complete_from_template_sends_the_rendered_prompt() {
+    let provider = Arc::new(FakeProvider::new("claude", Echo { prompts: Mutex::new(Vec::new()) }));
+    let mut orchestration = AIOrchestrationService::new().unwrap();
+    orchestration.add_provider(provider.clone()).await;
+    let service = AIWritingService::new(
+        Arc::new(orchestration),
+        Arc::new(ContextManagementService::new(8000).unwrap()),
+        Arc::new(ContentFilteringService::new().unwrap()),
+    );
+
+    let template = PromptTemplate::new("greeting", "Write a greeting for {{name}}", Vec::new()).unwrap();
+    service.template_repository().save(&template).await.unwrap();
+
+    let response = service
+        .complete_from_template(&template.id, variables(&[("name", "Ada")]), None)
+        .await
+        .unwrap();
+    assert_eq!(response.choices[0].message.content, "Write a greeting for Ada");
+    assert_eq!(provider.prompts.lock().as_slice(), ["Write a greeting for Ada"]);
+
+    let missing = service.complete_from_template(&template.id, HashMap::new(), None).await;
+    assert!(matches!(missing, Err(WritemagicError::Validation { .. })));
+    assert!(service.complete_from_template(&EntityId::new(), HashMap::new(), None).await.is_err());
+}
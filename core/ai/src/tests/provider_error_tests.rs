@@ -0,0 +1,197 @@
+//! Tests for structured provider errors and their effect on fallback
+
+use super::support::{reply, FakeBehavior, FakeProvider};
+use crate::circuit_breaker::CircuitState;
+use crate::providers::{
+    openai_status_error, parse_claude_error, parse_openai_error, CompletionRequest, CompletionResponse, Message,
+};
+use crate::retry_patterns::RetryConfig;
+use crate::services::AIOrchestrationService;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use writemagic_shared::{ProviderError, Result, WritemagicError};
+
+const OPENAI_POLICY_BODY: &str = r#"{"error":{"message":"Your request was rejected as a result of our safety system.","type":"invalid_request_error","param":null,"code":"content_policy_violation"}}"#;
+
+/// Fails with a fixed provider error, or succeeds when none is set
+struct Scripted {
+    name: &'static str,
+    failure: Option<ProviderError>,
+    calls: AtomicUsize,
+}
+
+impl Scripted {
+    fn provider(name: &'static str, failure: Option<ProviderError>) -> Arc<FakeProvider<Self>> {
+        Arc::new(FakeProvider::new(name, Self { name, failure, calls: AtomicUsize::new(0) }))
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Scripted {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(failure) = &self.failure {
+            return Err(WritemagicError::ai_provider_with_details(failure.message.clone(), failure.clone()));
+        }
+        Ok(reply(request, format!("answer from {}", self.name)))
+    }
+}
+
+async fn orchestration(primary: Arc<FakeProvider<Scripted>>, backup: Arc<FakeProvider<Scripted>>) -> AIOrchestrationService {
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(primary).await;
+    service.add_provider(backup).await;
+    service
+}
+
+fn request() -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user("Write a haiku about autumn")], "claude-3-haiku-20240307".to_string())
+}
+
+#[test]
+fn test_parse_openai_content_policy_error() {
+    let details = parse_openai_error(400, OPENAI_POLICY_BODY);
+
+    assert_eq!(details.status, 400);
+    assert_eq!(details.provider_code.as_deref(), Some("content_policy_violation"));
+    assert_eq!(details.message, "Your request was rejected as a result of our safety system.");
+    assert!(!details.retryable);
+}
+
+#[test]
+fn test_parse_claude_errors() {
+    let overloaded = parse_claude_error(
+        529,
+        r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+    );
+    assert_eq!(overloaded.provider_code.as_deref(), Some("overloaded_error"));
+    assert_eq!(overloaded.message, "Overloaded");
+    assert!(overloaded.retryable);
+
+    let blocked = parse_claude_error(
+        400,
+        r#"{"type":"error","error":{"type":"invalid_request_error","message":"Output blocked by content filtering policy"}}"#,
+    );
+    assert_eq!(blocked.provider_code.as_deref(), Some("invalid_request_error"));
+    assert!(!blocked.retryable);
+}
+
+#[test]
+fn test_status_errors_keep_the_structured_details() {
+    let rejected = openai_status_error(400, OPENAI_POLICY_BODY);
+    assert!(rejected.is_request_rejection());
+    assert_eq!(
+        rejected.provider_error().and_then(|details| details.provider_code.as_deref()),
+        Some("content_policy_violation")
+    );
+
+    assert!(matches!(openai_status_error(401, ""), WritemagicError::Authentication { .. }));
+    assert!(!openai_status_error(503, "").is_request_rejection());
+}
+
+#[test]
+fn test_unparseable_error_body_is_kept_as_message() {
+    let details = parse_claude_error(502, "<html>Bad Gateway</html>");
+
+    assert_eq!(details.provider_code, None);
+    assert_eq!(details.message, "<html>Bad Gateway</html>");
+    assert!(details.retryable);
+}
+
+#[tokio::test]
+async fn test_policy_violation_is_not_retried_on_fallback_provider() {
+    let rejecting = Scripted::provider("rejecting", Some(parse_openai_error(400, OPENAI_POLICY_BODY)));
+    let backup = Scripted::provider("backup", None);
+    let service = orchestration(rejecting.clone(), backup.clone()).await;
+
+    let error = service.complete_with_fallback(request()).await.unwrap_err();
+
+    let details = error.provider_error().expect("structured provider error");
+    assert_eq!(details.provider_code.as_deref(), Some("content_policy_violation"));
+    assert_eq!(rejecting.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_retryable_error_falls_back_to_next_provider() {
+    let unavailable = Scripted::provider(
+        "unavailable",
+        Some(parse_claude_error(529, r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#)),
+    );
+    let backup = Scripted::provider("backup", None);
+    let service = orchestration(unavailable.clone(), backup.clone()).await;
+
+    let response = service.complete_with_fallback(request()).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "answer from backup");
+    assert_eq!(response.provider(), Some("backup"));
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 1);
+
+    let usage = service.token_usage(&response).unwrap();
+    assert_eq!(usage.input_tokens, response.usage.prompt_tokens);
+    assert_eq!(usage.total_tokens, response.usage.total_tokens);
+}
+
+#[tokio::test]
+async fn test_rejections_do_not_open_the_circuit_breaker() {
+    let rejecting = Scripted::provider("rejecting", Some(parse_openai_error(400, OPENAI_POLICY_BODY)));
+    let backup = Scripted::provider("backup", None);
+    let service = orchestration(rejecting.clone(), backup.clone()).await;
+
+    for _ in 0..10 {
+        assert!(service.complete_with_fallback(request()).await.is_err());
+    }
+
+    // Every request still reached the provider, whose breaker stayed closed
+    assert_eq!(rejecting.calls.load(Ordering::SeqCst), 10);
+    assert_eq!(service.circuit_breaker_statuses()["rejecting"].state, CircuitState::Closed);
+}
+
+fn quick_retries(max_attempts: usize) -> RetryConfig {
+    RetryConfig {
+        max_attempts,
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+        backoff_multiplier: 2.0,
+        jitter: true,
+    }
+}
+
+#[tokio::test]
+async fn test_retryable_error_is_retried_before_falling_back() {
+    let unavailable = Scripted::provider(
+        "unavailable",
+        Some(parse_claude_error(529, r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#)),
+    );
+    let backup = Scripted::provider("backup", None);
+    let mut service = orchestration(unavailable.clone(), backup.clone()).await;
+    service.set_retry_config(quick_retries(3));
+
+    let response = service.complete_with_fallback(request()).await.unwrap();
+
+    assert_eq!(response.provider(), Some("backup"));
+    assert_eq!(unavailable.calls.load(Ordering::SeqCst), 3);
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_terminal_error_is_not_retried() {
+    let rejecting = Scripted::provider("rejecting", Some(parse_openai_error(400, OPENAI_POLICY_BODY)));
+    let backup = Scripted::provider("backup", None);
+    let mut service = orchestration(rejecting.clone(), backup.clone()).await;
+    service.set_retry_config(quick_retries(5));
+
+    assert!(service.complete_with_fallback(request()).await.is_err());
+    assert_eq!(rejecting.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_retry_delay_grows_and_is_capped() {
+    let config = RetryConfig { jitter: false, ..quick_retries(5) };
+
+    assert_eq!(config.delay_for_attempt(0), Duration::from_millis(1));
+    assert_eq!(config.delay_for_attempt(2), Duration::from_millis(4));
+    assert_eq!(config.delay_for_attempt(10), Duration::from_millis(5));
+}
@@ -0,0 +1,89 @@
+//! Tests for per-request provider selection and ordering
+
+use super::support::{reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message};
+use crate::services::AIOrchestrationService;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Records each call in a log shared with the other providers
+struct Named {
+    name: &'static str,
+    fails: bool,
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Named {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.log.lock().push(self.name);
+        if self.fails {
+            return Err(WritemagicError::network("connection refused"));
+        }
+        Ok(reply(request, format!("from {}", self.name)))
+    }
+}
+
+/// Service falling back from `openai` to `claude`, with a shared call log
+async fn service(failing: &[&'static str]) -> (AIOrchestrationService, Arc<Mutex<Vec<&'static str>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut service = AIOrchestrationService::new().unwrap();
+    for name in ["openai", "claude"] {
+        service.add_provider(Arc::new(FakeProvider::new(name, Named { name, fails: failing.contains(&name), log: log.clone() }))).await;
+    }
+    (service, log)
+}
+
+fn request(prompt: &str) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], MODEL.to_string())
+}
+
+#[tokio::test]
+async fn test_preference_overrides_fallback_order() {
+    let (service, log) = service(&[]).await;
+
+    let default = service.complete_with_fallback(request("Facts about tides")).await.unwrap();
+    assert_eq!(default.provider(), Some("openai"));
+
+    let preferred = service
+        .complete_with_fallback(request("A poem about tides").with_provider_preference(vec!["claude".to_string()]))
+        .await
+        .unwrap();
+    assert_eq!(preferred.provider(), Some("claude"));
+    assert_eq!(*log.lock(), vec!["openai", "claude"]);
+}
+
+#[tokio::test]
+async fn test_preference_falls_back_only_within_listed_providers() {
+    let (service, log) = service(&["claude"]).await;
+
+    let result = service
+        .complete_with_fallback(request("A poem about rain").with_provider_preference(vec!["claude".to_string()]))
+        .await;
+    assert!(result.is_err());
+    assert!(!log.lock().contains(&"openai"));
+
+    let response = service
+        .complete_with_fallback(
+            request("A poem about snow").with_provider_preference(vec!["claude".to_string(), "openai".to_string()]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.provider(), Some("openai"));
+}
+
+#[tokio::test]
+async fn test_unregistered_preference_is_a_configuration_error() {
+    let (service, log) = service(&[]).await;
+
+    let err = service
+        .complete_with_fallback(request("Hello").with_provider_preference(vec!["gemini".to_string()]))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, WritemagicError::Configuration { .. }));
+    assert!(log.lock().is_empty());
+}
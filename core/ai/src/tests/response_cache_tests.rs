@@ -0,0 +1,107 @@
+//! Tests for the bounded response cache and which requests it serves
+
+use super::support::{reply, FakeBehavior, FakeProvider};
+use crate::providers::{CompletionRequest, CompletionResponse, Message, ResponseCache};
+use crate::services::AIOrchestrationService;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use writemagic_shared::Result;
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Counts how often the provider is actually called
+#[derive(Default)]
+struct Counting {
+    calls: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Counting {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(reply(request, format!("answer {}", call)))
+    }
+}
+
+fn response(text: &str) -> CompletionResponse {
+    reply(&request("cached", 0.0), text)
+}
+
+fn request(prompt: &str, temperature: f32) -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user(prompt)], MODEL.to_string()).with_temperature(temperature)
+}
+
+async fn service() -> (AIOrchestrationService, Arc<FakeProvider<Counting>>) {
+    let provider = Arc::new(FakeProvider::new("claude", Counting::default()));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(provider.clone()).await;
+    (service, provider)
+}
+
+#[test]
+fn test_entries_expire_after_their_ttl() {
+    let cache = ResponseCache::new(60);
+    cache.insert("short".to_string(), response("soon gone"), Some(Duration::from_millis(20)));
+    cache.insert("long".to_string(), response("still here"), None);
+    assert!(cache.get("short").is_some());
+
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(cache.get("short").is_none());
+    assert!(cache.get("long").is_some());
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 1, 1));
+}
+
+#[test]
+fn test_least_recently_used_entry_is_evicted_at_capacity() {
+    let cache = ResponseCache::with_capacity(60, 2);
+    cache.insert("a".to_string(), response("a"), None);
+    cache.insert("b".to_string(), response("b"), None);
+    assert!(cache.get("a").is_some());
+
+    cache.insert("c".to_string(), response("c"), None);
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("c").is_some());
+
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.capacity, stats.evictions), (2, 2, 1));
+}
+
+#[tokio::test]
+async fn test_deterministic_requests_are_served_from_cache() {
+    let (service, provider) = service().await;
+
+    let first = service.complete_with_fallback(request("Define entropy", 0.0)).await.unwrap();
+    let again = service.complete_with_fallback(request("  Define\n entropy ", 0.0)).await.unwrap();
+    assert_eq!(again.choices[0].message.content, first.choices[0].message.content);
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+    // A different temperature is a different request
+    service.complete_with_fallback(request("Define entropy", 0.7).with_cache_nondeterministic(true)).await.unwrap();
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+    let stats = service.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+}
+
+#[tokio::test]
+async fn test_sampled_requests_bypass_cache_unless_opted_in() {
+    let (service, provider) = service().await;
+
+    for _ in 0..2 {
+        service.complete_with_fallback(request("A haiku about fog", 0.9)).await.unwrap();
+    }
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    assert_eq!(service.cache_stats().misses, 0);
+
+    for _ in 0..2 {
+        service
+            .complete_with_fallback(request("A haiku about fog", 0.9).with_cache_nondeterministic(true))
+            .await
+            .unwrap();
+    }
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+}
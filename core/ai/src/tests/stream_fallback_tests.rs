@@ -0,0 +1,189 @@
+//! Tests for provider fallback on streamed completions
+
+use super::support::{FakeBehavior, FakeProvider};
+use crate::cost::ModelPrice;
+use crate::providers::{CompletionRequest, CompletionResponse, Message, StreamingChunk, StreamingResponse};
+use crate::services::AIOrchestrationService;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// How a scripted provider's stream behaves
+#[derive(Clone, Copy)]
+enum Script {
+    /// The stream can't be opened at all
+    FailToOpen,
+    /// The stream opens but errors before its first chunk
+    FailBeforeFirstChunk,
+    /// The stream yields its chunks, then errors
+    FailAfter(&'static [&'static str]),
+    /// The stream yields its chunks, then ends
+    Succeed(&'static [&'static str]),
+}
+
+/// Streams according to its script, counting how often a stream was opened
+struct Scripted {
+    script: Script,
+    opened: Arc<AtomicUsize>,
+}
+
+struct ScriptedStream {
+    script: Script,
+    position: usize,
+    received: String,
+}
+
+#[async_trait::async_trait]
+impl StreamingResponse for ScriptedStream {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        let (chunks, fails) = match self.script {
+            Script::FailToOpen | Script::FailBeforeFirstChunk => (&[][..], true),
+            Script::FailAfter(chunks) => (chunks, true),
+            Script::Succeed(chunks) => (chunks, false),
+        };
+
+        let Some(content) = chunks.get(self.position) else {
+            return if fails {
+                Err(WritemagicError::network("connection reset"))
+            } else {
+                Ok(None)
+            };
+        };
+        self.position += 1;
+        self.received.push_str(content);
+        Ok(Some(StreamingChunk { content: content.to_string(), finish_reason: None, usage: None }))
+    }
+
+    fn is_complete(&self) -> bool {
+        false
+    }
+
+    fn get_partial_response(&self) -> String {
+        self.received.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Scripted {
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        Err(WritemagicError::not_implemented("Scripted provider only streams"))
+    }
+
+    async fn stream(&self, _request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        if let Script::FailToOpen = self.script {
+            return Err(WritemagicError::network("connection refused"));
+        }
+        Ok(Box::new(ScriptedStream { script: self.script, position: 0, received: String::new() }))
+    }
+}
+
+/// Service with a primary provider following `primary` and a backup that always succeeds
+async fn service_with(primary: Script) -> (AIOrchestrationService, Arc<AtomicUsize>) {
+    let backup_opened = Arc::new(AtomicUsize::new(0));
+    let mut service = AIOrchestrationService::new().unwrap();
+    let primary = Scripted { script: primary, opened: Arc::new(AtomicUsize::new(0)) };
+    service.add_provider(Arc::new(FakeProvider::new("primary", primary).streaming())).await;
+    let backup = Scripted { script: Script::Succeed(&["from ", "backup"]), opened: backup_opened.clone() };
+    service.add_provider(Arc::new(FakeProvider::new("backup", backup).streaming())).await;
+    service.set_fallback_order(vec!["primary".to_string(), "backup".to_string()]);
+    (service, backup_opened)
+}
+
+fn request() -> CompletionRequest {
+    CompletionRequest::new(vec![Message::user("Tell me a story")], MODEL.to_string())
+}
+
+async fn drain(stream: &mut Box<dyn StreamingResponse>) -> Result<String> {
+    let mut output = String::new();
+    while let Some(chunk) = stream.next_chunk().await? {
+        output.push_str(&chunk.content);
+    }
+    Ok(output)
+}
+
+#[tokio::test]
+async fn test_stream_falls_back_when_provider_fails_to_open() {
+    let (service, backup_opened) = service_with(Script::FailToOpen).await;
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+
+    assert_eq!(drain(&mut stream).await.unwrap(), "from backup");
+    assert_eq!(backup_opened.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_stream_falls_back_when_provider_fails_before_first_chunk() {
+    let (service, backup_opened) = service_with(Script::FailBeforeFirstChunk).await;
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+
+    assert_eq!(drain(&mut stream).await.unwrap(), "from backup");
+    assert_eq!(backup_opened.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_stream_error_after_first_chunk_does_not_fall_back() {
+    let (service, backup_opened) = service_with(Script::FailAfter(&["Once ", "upon"])).await;
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+
+    // The chunk read ahead to pick the provider is still delivered first
+    assert_eq!(stream.next_chunk().await.unwrap().unwrap().content, "Once ");
+    assert_eq!(stream.next_chunk().await.unwrap().unwrap().content, "upon");
+    assert!(stream.next_chunk().await.is_err());
+    assert_eq!(backup_opened.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_stream_from_healthy_primary_is_complete() {
+    let (service, backup_opened) = service_with(Script::Succeed(&["Once ", "upon ", "a time"])).await;
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+
+    assert_eq!(drain(&mut stream).await.unwrap(), "Once upon a time");
+    assert_eq!(backup_opened.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_stream_keeps_its_dispatch_slot_and_records_spend_when_drained() {
+    let (service, _) = service_with(Script::Succeed(&["Once ", "upon ", "a time"])).await;
+    let price = ModelPrice { input_cost_per_token: 0.001, output_cost_per_token: 0.002 };
+    service.cost_estimator().set_price("primary", MODEL, price);
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+    assert_eq!(service.dispatcher().in_flight(), 1);
+    assert_eq!(service.spend_tracker().spent(chrono::Utc::now()), 0.0);
+
+    drain(&mut stream).await.unwrap();
+    assert_eq!(service.dispatcher().in_flight(), 0);
+    assert!(service.spend_tracker().spent(chrono::Utc::now()) > 0.0);
+}
+
+#[tokio::test]
+async fn test_dropped_stream_frees_its_slot_and_records_what_it_produced() {
+    let (service, _) = service_with(Script::Succeed(&["Once ", "upon ", "a time"])).await;
+    let price = ModelPrice { input_cost_per_token: 0.001, output_cost_per_token: 0.002 };
+    service.cost_estimator().set_price("primary", MODEL, price);
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+    stream.next_chunk().await.unwrap();
+    drop(stream);
+
+    assert_eq!(service.dispatcher().in_flight(), 0);
+    assert!(service.spend_tracker().spent(chrono::Utc::now()) > 0.0);
+}
+
+#[tokio::test]
+async fn test_streamed_pii_is_masked_even_when_split_across_chunks() {
+    let (service, _) = service_with(Script::Succeed(&["Write to jane.doe@exa", "mple.com today"])).await;
+
+    let mut stream = service.complete_with_fallback_stream(request()).await.unwrap();
+    let output = drain(&mut stream).await.unwrap();
+
+    assert!(!output.contains("jane.doe"), "{}", output);
+    assert!(output.starts_with("Write to [REDACTED"), "{}", output);
+    assert!(output.ends_with(" today"), "{}", output);
+}
@@ -0,0 +1,135 @@
+//! Tests for the absolute ceiling on streamed output
+
+use super::support::{FakeBehavior, FakeProvider};
+use crate::providers::{
+    CompletionRequest, CompletionResponse, FinishReason, Message, StreamOutputLimit, StreamingChunk, StreamingResponse,
+};
+use crate::services::AIOrchestrationService;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use writemagic_shared::{Result, WritemagicError};
+
+const MODEL: &str = "claude-3-haiku-20240307";
+
+/// Streams the same chunk far beyond any sensible length
+struct Runaway {
+    chunk: &'static str,
+    chunks: usize,
+    pulled: Arc<AtomicUsize>,
+}
+
+struct RunawayStream {
+    chunk: &'static str,
+    remaining: usize,
+    pulled: Arc<AtomicUsize>,
+    received: String,
+}
+
+#[async_trait::async_trait]
+impl StreamingResponse for RunawayStream {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        self.pulled.fetch_add(1, Ordering::SeqCst);
+        self.received.push_str(self.chunk);
+        Ok(Some(StreamingChunk {
+            content: self.chunk.to_string(),
+            finish_reason: (self.remaining == 0).then_some(FinishReason::Stop),
+            usage: None,
+        }))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn get_partial_response(&self) -> String {
+        self.received.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl FakeBehavior for Runaway {
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        Err(WritemagicError::not_implemented("Runaway provider only streams"))
+    }
+
+    async fn stream(&self, _request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        Ok(Box::new(RunawayStream {
+            chunk: self.chunk,
+            remaining: self.chunks,
+            pulled: self.pulled.clone(),
+            received: String::new(),
+        }))
+    }
+}
+
+async fn guarded_stream(chunk: &'static str, limit: StreamOutputLimit) -> (Box<dyn StreamingResponse>, Arc<AtomicUsize>) {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let mut service = AIOrchestrationService::new().unwrap();
+    service.add_provider(Arc::new(FakeProvider::new("runaway", Runaway { chunk, chunks: 1000, pulled: pulled.clone() }).streaming())).await;
+    service.set_stream_output_limit(limit);
+
+    // max_tokens fits the model's context window but is far above the
+    // ceiling, so only the guard can stop the stream
+    let mut request = CompletionRequest::new(vec![Message::user("Keep going")], MODEL.to_string());
+    request.max_tokens = Some(100_000);
+    (service.stream_completion(request).await.unwrap(), pulled)
+}
+
+async fn drain(stream: &mut Box<dyn StreamingResponse>) -> (String, Option<FinishReason>) {
+    let mut output = String::new();
+    let mut last_reason = None;
+    while let Some(chunk) = stream.next_chunk().await.unwrap() {
+        output.push_str(&chunk.content);
+        last_reason = chunk.finish_reason;
+    }
+    (output, last_reason)
+}
+
+#[tokio::test]
+async fn test_stream_cut_off_at_byte_ceiling() {
+    let limit = StreamOutputLimit { max_tokens: u32::MAX, max_bytes: 100 };
+    let (mut stream, pulled) = guarded_stream("abcdefghijklmnop", limit).await;
+
+    let (output, reason) = drain(&mut stream).await;
+
+    assert_eq!(output.len(), 100);
+    assert_eq!(output, "abcdefghijklmnop".repeat(7)[..100]);
+    assert!(matches!(reason, Some(FinishReason::Length)));
+    assert!(stream.is_truncated());
+    assert!(stream.is_complete());
+    assert_eq!(stream.get_partial_response(), output);
+    // The chunk crossing the ceiling is the last one consumed
+    assert_eq!(pulled.load(Ordering::SeqCst), 7);
+}
+
+#[tokio::test]
+async fn test_stream_cut_off_at_token_ceiling() {
+    let limit = StreamOutputLimit { max_tokens: 50, max_bytes: usize::MAX };
+    let (mut stream, pulled) = guarded_stream(" and the story went on", limit).await;
+
+    let (output, reason) = drain(&mut stream).await;
+
+    let tokenizer = crate::tokenization::TokenizationService::new().unwrap();
+    let chunk_tokens = tokenizer.count_tokens(" and the story went on", MODEL).unwrap() as usize;
+    assert!(tokenizer.count_tokens(&output, MODEL).unwrap() <= 50);
+    assert!(output.len() > " and the story went on".len() * (50 / chunk_tokens - 1));
+    assert!(" and the story went on".repeat(1000).starts_with(&output));
+    assert!(matches!(reason, Some(FinishReason::Length)));
+    assert!(stream.is_truncated());
+    assert!(pulled.load(Ordering::SeqCst) <= 50 / chunk_tokens + 1);
+}
+
+#[tokio::test]
+async fn test_stream_within_ceiling_is_untouched() {
+    let (mut stream, _) = guarded_stream("short", StreamOutputLimit::default()).await;
+
+    let (output, reason) = drain(&mut stream).await;
+
+    assert_eq!(output, "short".repeat(1000));
+    assert!(matches!(reason, Some(FinishReason::Stop)));
+    assert!(!stream.is_truncated());
+}
@@ -0,0 +1,164 @@
+//! Test double shared by the AI crate tests
+
+use crate::providers::{
+    AIProvider, Choice, CompletionRequest, CompletionResponse, FinishReason, Message, ModelCapabilities,
+    ProviderHealthMetrics, StreamingChunk, StreamingResponse, Usage, UsageStats,
+};
+use std::collections::HashMap;
+use std::ops::Deref;
+use writemagic_shared::{Result, WritemagicError};
+
+/// What a [`FakeProvider`] does when asked for a completion or a stream
+#[async_trait::async_trait]
+pub(super) trait FakeBehavior: Send + Sync {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse>;
+
+    async fn stream(&self, _request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        Err(WritemagicError::not_implemented("Fake provider does not stream"))
+    }
+}
+
+/// Provider that is always healthy and free, answering through its behavior `B`
+///
+/// Derefs to the behavior so tests can read what it recorded.
+pub(super) struct FakeProvider<B> {
+    name: &'static str,
+    context_window: u32,
+    supports_streaming: bool,
+    behavior: B,
+}
+
+impl<B: FakeBehavior> FakeProvider<B> {
+    pub(super) fn new(name: &'static str, behavior: B) -> Self {
+        Self {
+            name,
+            context_window: 200_000,
+            supports_streaming: false,
+            behavior,
+        }
+    }
+
+    pub(super) fn with_context_window(mut self, context_window: u32) -> Self {
+        self.context_window = context_window;
+        self
+    }
+
+    /// Advertise streaming support, for behaviors that implement `stream`
+    pub(super) fn streaming(mut self) -> Self {
+        self.supports_streaming = true;
+        self
+    }
+}
+
+impl<B> Deref for FakeProvider<B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        &self.behavior
+    }
+}
+
+#[async_trait::async_trait]
+impl<B: FakeBehavior> AIProvider for FakeProvider<B> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        self.behavior.complete(request).await
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<Box<dyn StreamingResponse>> {
+        self.behavior.stream(request).await
+    }
+
+    async fn batch_complete(&self, requests: Vec<CompletionRequest>) -> Result<Vec<Result<CompletionResponse>>> {
+        let mut results = Vec::new();
+        for request in &requests {
+            results.push(self.complete(request).await);
+        }
+        Ok(results)
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities {
+            max_tokens: 4096,
+            supports_streaming: self.supports_streaming,
+            supports_functions: false,
+            supports_vision: false,
+            context_window: self.context_window,
+            input_cost_per_token: 0.0,
+            output_cost_per_token: 0.0,
+        }
+    }
+
+    async fn validate_credentials(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn get_usage_stats(&self) -> Result<UsageStats> {
+        Ok(UsageStats {
+            total_requests: 0,
+            total_tokens: 0,
+            total_cost: 0.0,
+            requests_today: 0,
+            tokens_today: 0,
+            cost_today: 0.0,
+        })
+    }
+
+    async fn health_check(&self) -> Result<ProviderHealthMetrics> {
+        Ok(ProviderHealthMetrics {
+            is_healthy: true,
+            response_time_ms: 1,
+            success_rate: 1.0,
+            error_count: 0,
+            last_error: None,
+            timestamp: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// Completed answer of `text` to `request`, using 5 prompt and 5 completion tokens
+pub(super) fn reply(request: &CompletionRequest, text: impl Into<String>) -> CompletionResponse {
+    let text = text.into();
+    CompletionResponse {
+        id: format!("fake-{}", text.len()),
+        choices: vec![Choice {
+            index: 0,
+            message: Message::assistant(text),
+            finish_reason: Some(FinishReason::Stop),
+        }],
+        usage: Usage { prompt_tokens: 5, completion_tokens: 5, total_tokens: 10 },
+        model: request.model.clone(),
+        created: chrono::Utc::now().timestamp(),
+        metadata: HashMap::new(),
+    }
+}
+
+/// Text of the last message in `request`, usually the user's prompt
+pub(super) fn last_prompt(request: &CompletionRequest) -> String {
+    request.messages.last().map(|message| message.content.clone()).unwrap_or_default()
+}
+
+/// Stream answering in a single chunk
+pub(super) struct OneChunk(pub(super) Option<String>);
+
+#[async_trait::async_trait]
+impl StreamingResponse for OneChunk {
+    async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+        Ok(self.0.take().map(|content| StreamingChunk {
+            content,
+            finish_reason: Some(FinishReason::Stop),
+            usage: None,
+        }))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.0.is_none()
+    }
+
+    fn get_partial_response(&self) -> String {
+        String::new()
+    }
+}
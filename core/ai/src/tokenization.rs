@@ -1,8 +1,8 @@
 //! Accurate tokenization system with model-specific support
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use tiktoken_rs::{CoreBPE, get_bpe_from_model};
 use writemagic_shared::{Result, WritemagicError};
 use crate::providers::CompletionRequest;
@@ -50,6 +50,110 @@ impl ModelTokenizerConfig {
             special_tokens: HashMap::new(),
         }
     }
+
+    /// Create config for models without a known tokenizer
+    ///
+    /// Their context window is unknown too, so it is left unbounded here and
+    /// enforced by the configured context limits and the provider itself.
+    pub fn heuristic() -> Self {
+        Self {
+            name: "heuristic".to_string(),
+            encoding_name: "chars/4".to_string(),
+            max_tokens: 4096,
+            context_window: u32::MAX,
+            special_tokens: HashMap::new(),
+        }
+    }
+}
+
+/// Counts the tokens a model sees in text
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> Result<u32>;
+
+    fn config(&self) -> &ModelTokenizerConfig;
+
+    /// Count tokens in a completion request
+    fn count_request_tokens(&self, request: &CompletionRequest) -> Result<u32> {
+        let mut total = 0;
+        
+        // Count message tokens
+        for message in &request.messages {
+            total += self.count_tokens(&message.content)?;
+            
+            // Add overhead tokens for message formatting
+            total += match message.role {
+                crate::providers::MessageRole::System => 4, // <|start|>system<|message|>{content}<|end|>
+                crate::providers::MessageRole::User => 4,   // <|start|>user<|message|>{content}<|end|>
+                crate::providers::MessageRole::Assistant => 4, // <|start|>assistant<|message|>{content}<|end|>
+                crate::providers::MessageRole::Function => 6, // Additional overhead for function calls
+            };
+        }
+        
+        // Add base conversation overhead
+        total += 3; // Base conversation tokens
+        
+        Ok(total)
+    }
+
+    /// Check if request fits within context window
+    fn validate_context_window(&self, request: &CompletionRequest) -> Result<()> {
+        let input_tokens = self.count_request_tokens(request)?;
+        let max_output_tokens = request.max_tokens.unwrap_or(self.config().max_tokens);
+        let total_tokens = input_tokens.saturating_add(max_output_tokens);
+        
+        if total_tokens > self.config().context_window {
+            return Err(WritemagicError::validation(format!(
+                "Request exceeds context window: {} tokens (max: {})",
+                total_tokens, self.config().context_window
+            )));
+        }
+        
+        Ok(())
+    }
+
+    /// Get optimal max_tokens for request within budget
+    fn optimize_max_tokens(&self, request: &CompletionRequest, token_budget: u32) -> Result<u32> {
+        let input_tokens = self.count_request_tokens(request)?;
+        
+        if input_tokens >= token_budget {
+            return Err(WritemagicError::validation(
+                "Input tokens exceed available budget"
+            ));
+        }
+        
+        let available_output_tokens = token_budget - input_tokens;
+        let requested_output_tokens = request.max_tokens.unwrap_or(self.config().max_tokens);
+        
+        Ok(available_output_tokens.min(requested_output_tokens).min(self.config().max_tokens))
+    }
+}
+
+/// Estimates one token per four characters, rounding up, for models without a tokenizer
+#[derive(Debug, Clone)]
+pub struct HeuristicTokenizer {
+    config: ModelTokenizerConfig,
+}
+
+impl HeuristicTokenizer {
+    pub fn new() -> Self {
+        Self { config: ModelTokenizerConfig::heuristic() }
+    }
+}
+
+impl Default for HeuristicTokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> Result<u32> {
+        Ok(((text.chars().count() + 3) / 4) as u32)
+    }
+
+    fn config(&self) -> &ModelTokenizerConfig {
+        &self.config
+    }
 }
 
 /// Token usage statistics with accurate counting
@@ -90,7 +194,7 @@ impl ModelTokenizer {
             Ok(encoder) => encoder,
             Err(_) => {
                 // Fallback to cl100k_base encoding if model-specific fails
-                tiktoken_rs::get_bpe_from_model("cl100k_base")
+                tiktoken_rs::cl100k_base()
                     .map_err(|e| WritemagicError::internal(format!("Failed to load tokenizer: {}", e)))?
             }
         };
@@ -136,122 +240,148 @@ impl ModelTokenizer {
         Ok(count)
     }
 
-    /// Count tokens in a completion request
-    pub fn count_request_tokens(&self, request: &CompletionRequest) -> Result<u32> {
-        let mut total = 0;
-        
-        // Count message tokens
-        for message in &request.messages {
-            total += self.count_tokens(&message.content)?;
-            
-            // Add overhead tokens for message formatting
-            total += match message.role {
-                crate::providers::MessageRole::System => 4, // <|start|>system<|message|>{content}<|end|>
-                crate::providers::MessageRole::User => 4,   // <|start|>user<|message|>{content}<|end|>
-                crate::providers::MessageRole::Assistant => 4, // <|start|>assistant<|message|>{content}<|end|>
-                crate::providers::MessageRole::Function => 6, // Additional overhead for function calls
-            };
-        }
-        
-        // Add base conversation overhead
-        total += 3; // Base conversation tokens
-        
-        Ok(total)
+    /// Get model configuration
+    pub fn config(&self) -> &ModelTokenizerConfig {
+        &self.config
     }
 
-    /// Check if request fits within context window
-    pub fn validate_context_window(&self, request: &CompletionRequest) -> Result<()> {
-        let input_tokens = self.count_request_tokens(request)?;
-        let max_output_tokens = request.max_tokens.unwrap_or(self.config.max_tokens);
-        let total_tokens = input_tokens + max_output_tokens;
-        
-        if total_tokens > self.config.context_window {
-            return Err(WritemagicError::validation(format!(
-                "Request exceeds context window: {} tokens (max: {})",
-                total_tokens, self.config.context_window
-            )));
-        }
-        
-        Ok(())
+    /// Clear token cache
+    pub fn clear_cache(&self) {
+        self.cache.write().clear();
     }
+}
 
-    /// Get optimal max_tokens for request within budget
-    pub fn optimize_max_tokens(&self, request: &CompletionRequest, token_budget: u32) -> Result<u32> {
-        let input_tokens = self.count_request_tokens(request)?;
-        
-        if input_tokens >= token_budget {
-            return Err(WritemagicError::validation(
-                "Input tokens exceed available budget"
-            ));
-        }
-        
-        let available_output_tokens = token_budget - input_tokens;
-        let requested_output_tokens = request.max_tokens.unwrap_or(self.config.max_tokens);
-        
-        Ok(available_output_tokens.min(requested_output_tokens).min(self.config.max_tokens))
+impl Tokenizer for ModelTokenizer {
+    fn count_tokens(&self, text: &str) -> Result<u32> {
+        ModelTokenizer::count_tokens(self, text)
     }
 
-    /// Get model configuration
-    pub fn config(&self) -> &ModelTokenizerConfig {
+    fn config(&self) -> &ModelTokenizerConfig {
         &self.config
     }
+}
 
-    /// Clear token cache
-    pub fn clear_cache(&self) {
-        self.cache.write().clear();
+/// Tokenizer registered for the model names matching `pattern`
+struct RegisteredTokenizer {
+    pattern: String,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+/// Whether `pattern` matches `model_name`
+///
+/// Patterns with `*` or `?` are globs over the whole name; other patterns
+/// match as a prefix, so `claude-3` covers `claude-3-haiku-20240307`.
+pub(crate) fn pattern_matches(pattern: &str, model_name: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return model_name.starts_with(pattern);
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = model_name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    backtrack = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
     }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How specific a pattern is: the number of characters it fixes
+pub(crate) fn pattern_specificity(pattern: &str) -> usize {
+    pattern.chars().filter(|c| !matches!(c, '*' | '?')).count()
 }
 
+/// Most unknown model names remembered for warning once each
+const MAX_WARNED_UNKNOWN_MODELS: usize = 256;
+
 /// Multi-model tokenization service
+///
+/// Tokenizers are looked up by model name pattern. The most specific matching
+/// pattern wins, and among equally specific ones the latest registered.
 pub struct TokenizationService {
-    tokenizers: HashMap<String, Arc<ModelTokenizer>>,
-    default_tokenizer: Arc<ModelTokenizer>,
+    tokenizers: RwLock<Vec<RegisteredTokenizer>>,
+    fallback: Arc<dyn Tokenizer>,
+    /// Models already warned about having no tokenizer, at most `MAX_WARNED_UNKNOWN_MODELS`
+    unknown_models: Mutex<HashSet<String>>,
 }
 
 impl TokenizationService {
     /// Create new tokenization service with common models
     pub fn new() -> Result<Self> {
-        let mut tokenizers = HashMap::new();
+        let service = Self::empty();
         
         // Initialize tokenizers for common models
-        let claude_3 = Arc::new(ModelTokenizer::new(ModelTokenizerConfig::claude_3())?);
-        let gpt_4 = Arc::new(ModelTokenizer::new(ModelTokenizerConfig::gpt_4())?);
-        let gpt_3_5 = Arc::new(ModelTokenizer::new(ModelTokenizerConfig::gpt_3_5_turbo())?);
-        
-        // Map model names to tokenizers
-        tokenizers.insert("claude-3-sonnet".to_string(), claude_3.clone());
-        tokenizers.insert("claude-3-opus".to_string(), claude_3.clone());
-        tokenizers.insert("claude-3-haiku".to_string(), claude_3.clone());
+        service.register_tokenizer("claude", Arc::new(ModelTokenizer::new(ModelTokenizerConfig::claude_3())?));
+        service.register_tokenizer("gpt-4", Arc::new(ModelTokenizer::new(ModelTokenizerConfig::gpt_4())?));
+        service.register_tokenizer("gpt-3.5-turbo", Arc::new(ModelTokenizer::new(ModelTokenizerConfig::gpt_3_5_turbo())?));
         
-        tokenizers.insert("gpt-4".to_string(), gpt_4.clone());
-        tokenizers.insert("gpt-4-turbo".to_string(), gpt_4.clone());
-        tokenizers.insert("gpt-4o".to_string(), gpt_4.clone());
-        
-        tokenizers.insert("gpt-3.5-turbo".to_string(), gpt_3_5.clone());
-        
-        Ok(Self {
-            tokenizers,
-            default_tokenizer: gpt_4, // Use GPT-4 as default
-        })
+        Ok(service)
+    }
+
+    /// Service without any registered tokenizer, estimating every model
+    fn empty() -> Self {
+        Self {
+            tokenizers: RwLock::new(Vec::new()),
+            fallback: Arc::new(HeuristicTokenizer::new()),
+            unknown_models: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Use `tokenizer` for models matching `model_pattern`, a prefix or a glob with `*` and `?`
+    ///
+    /// Registering a pattern again replaces its tokenizer.
+    pub fn register_tokenizer(&self, model_pattern: &str, tokenizer: Arc<dyn Tokenizer>) {
+        let mut tokenizers = self.tokenizers.write();
+        tokenizers.retain(|registered| registered.pattern != model_pattern);
+        tokenizers.push(RegisteredTokenizer {
+            pattern: model_pattern.to_string(),
+            tokenizer,
+        });
     }
 
     /// Get tokenizer for specific model
-    pub fn get_tokenizer(&self, model_name: &str) -> Arc<ModelTokenizer> {
-        // Try exact match first
-        if let Some(tokenizer) = self.tokenizers.get(model_name) {
-            return tokenizer.clone();
+    ///
+    /// Models no pattern matches get a character-count estimate.
+    pub fn get_tokenizer(&self, model_name: &str) -> Arc<dyn Tokenizer> {
+        let tokenizers = self.tokenizers.read();
+        let best = tokenizers
+            .iter()
+            .enumerate()
+            .filter(|(_, registered)| pattern_matches(&registered.pattern, model_name))
+            .max_by_key(|(position, registered)| (pattern_specificity(&registered.pattern), *position));
+        if let Some((_, registered)) = best {
+            return registered.tokenizer.clone();
         }
         
-        // Try prefix matching for model families
-        for (registered_model, tokenizer) in &self.tokenizers {
-            if model_name.starts_with(registered_model.split('-').next().unwrap_or("")) {
-                return tokenizer.clone();
+        // Model names come from callers, so past the cap further ones are only logged at debug
+        let mut unknown_models = self.unknown_models.lock();
+        if !unknown_models.contains(model_name) {
+            if unknown_models.len() < MAX_WARNED_UNKNOWN_MODELS {
+                unknown_models.insert(model_name.to_string());
+                tracing::warn!("No tokenizer registered for model '{}', estimating tokens from characters", model_name);
+            } else {
+                tracing::debug!("No tokenizer registered for model '{}', estimating tokens from characters", model_name);
             }
         }
-        
-        // Return default tokenizer
-        tracing::warn!("No specific tokenizer found for model '{}', using default", model_name);
-        self.default_tokenizer.clone()
+        self.fallback.clone()
     }
 
     /// Count tokens for any model
@@ -286,25 +416,17 @@ impl TokenizationService {
         ))
     }
 
-    /// Get all available models
+    /// Model patterns with a registered tokenizer
     pub fn available_models(&self) -> Vec<String> {
-        self.tokenizers.keys().cloned().collect()
+        self.tokenizers.read().iter().map(|registered| registered.pattern.clone()).collect()
     }
 }
 
 impl Default for TokenizationService {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| {
-            log::error!("Failed to create tokenization service, using minimal implementation");
-            // Create a minimal default tokenizer
-            let default_config = ModelTokenizerConfig::gpt_4();
-            let default_tokenizer = ModelTokenizer::new(default_config)
-                .unwrap_or_else(|_| panic!("Failed to create default tokenizer"));
-            
-            Self {
-                tokenizers: std::collections::HashMap::new(),
-                default_tokenizer: Arc::new(default_tokenizer),
-            }
+        Self::new().unwrap_or_else(|e| {
+            log::error!("Failed to create tokenization service, estimating all models: {}", e);
+            Self::empty()
         })
     }
 }
@@ -370,4 +492,92 @@ mod tests {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Tokenizer reporting the same count for any text, to tell registrations apart
+    struct FixedTokenizer {
+        count: u32,
+        config: ModelTokenizerConfig,
+    }
+
+    fn fixed(count: u32) -> Arc<dyn Tokenizer> {
+        Arc::new(FixedTokenizer { count, config: ModelTokenizerConfig::heuristic() })
+    }
+
+    impl Tokenizer for FixedTokenizer {
+        fn count_tokens(&self, _text: &str) -> Result<u32> {
+            Ok(self.count)
+        }
+
+        fn config(&self) -> &ModelTokenizerConfig {
+            &self.config
+        }
+    }
+
+    #[test]
+    fn test_most_specific_pattern_wins() -> Result<()> {
+        let service = TokenizationService::new()?;
+        service.register_tokenizer("acme", fixed(1));
+        service.register_tokenizer("acme-large", fixed(2));
+        service.register_tokenizer("acme-*-v2", fixed(3));
+        
+        assert_eq!(service.count_tokens("text", "acme-small")?, 1);
+        assert_eq!(service.count_tokens("text", "acme-large-v1")?, 2);
+        assert_eq!(service.count_tokens("text", "acme-xl-v2")?, 3);
+        // The longer prefix fixes more characters than the glob
+        assert_eq!(service.count_tokens("text", "acme-large-v2")?, 2);
+        // Globs must match the whole name
+        assert_eq!(service.count_tokens("text", "acme-xl-v2-preview")?, 1);
+        
+        // Equally specific patterns go to the latest registration
+        service.register_tokenizer("acme-*", fixed(4));
+        service.register_tokenizer("acme-", fixed(5));
+        assert_eq!(service.count_tokens("text", "acme-tiny")?, 5);
+        
+        // Re-registering a pattern replaces its tokenizer and makes it the latest
+        service.register_tokenizer("acme-*", fixed(6));
+        assert_eq!(service.count_tokens("text", "acme-tiny")?, 6);
+        assert_eq!(service.available_models().iter().filter(|pattern| *pattern == "acme-*").count(), 1);
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_models_can_be_overridden() -> Result<()> {
+        let service = TokenizationService::new()?;
+        assert_eq!(service.get_tokenizer("claude-3-haiku-20240307").config().name, "claude-3");
+        
+        service.register_tokenizer("claude-4*", fixed(7));
+        assert_eq!(service.count_tokens("text", "claude-4-sonnet")?, 7);
+        assert_eq!(service.get_tokenizer("claude-3-opus").config().name, "claude-3");
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_models_use_heuristic() -> Result<()> {
+        let service = TokenizationService::new()?;
+        
+        assert_eq!(service.count_tokens("", "mystery-model")?, 0);
+        assert_eq!(service.count_tokens("abcd", "mystery-model")?, 1);
+        assert_eq!(service.count_tokens("abcde", "mystery-model")?, 2);
+        assert_eq!(service.get_tokenizer("mystery-model").config().name, "heuristic");
+        
+        // An unknown model's context window isn't guessed at
+        let request = CompletionRequest::new(vec![Message::user("word ".repeat(50_000))], "mystery-model".to_string());
+        service.validate_request(&request)?;
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_models_remembered_for_warnings_are_bounded() {
+        let service = TokenizationService::empty();
+
+        for index in 0..MAX_WARNED_UNKNOWN_MODELS + 10 {
+            service.get_tokenizer(&format!("mystery-{}", index));
+        }
+        service.get_tokenizer("mystery-0");
+
+        assert_eq!(service.unknown_models.lock().len(), MAX_WARNED_UNKNOWN_MODELS);
+    }
+}
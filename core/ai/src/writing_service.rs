@@ -3,11 +3,13 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use writemagic_shared::{EntityId, Result, WritemagicError};
+use writemagic_shared::{DocumentTag, EntityId, Result, WritemagicError};
 use serde::{Serialize, Deserialize};
 
+use crate::few_shot::{FewShotExampleRepository, InMemoryFewShotExampleRepository};
+use crate::prompt_templates::{InMemoryPromptTemplateRepository, PromptTemplateRepository};
 use crate::providers::{CompletionRequest, CompletionResponse, Message};
-use crate::services::{AIOrchestrationService, ContextManagementService, ContentFilteringService};
+use crate::services::{AIOrchestrationService, ContextManagementService, ContentFilteringService, FilterMode};
 use crate::value_objects::{ModelConfiguration, TokenCount};
 
 // Forward declarations to avoid circular dependencies
@@ -97,6 +99,8 @@ pub struct ProjectContext {
     pub project_id: EntityId,
     pub project_name: String,
     pub project_description: Option<String>,
+    /// The project's own instructions, sent ahead of the assistant's
+    pub system_prompt: Option<String>,
     pub related_documents: Vec<RelatedDocument>,
 }
 
@@ -156,6 +160,8 @@ pub struct WritingAssistanceRequest {
     pub user_input: Option<String>,
     pub model_config: Option<ModelConfiguration>,
     pub stream_response: bool,
+    /// Name of a stored few-shot example set to show the model
+    pub example_set: Option<String>,
 }
 
 /// Writing assistance response
@@ -299,6 +305,8 @@ pub struct AIWritingService {
     orchestration_service: Arc<AIOrchestrationService>,
     context_service: Arc<ContextManagementService>,
     content_filter: Arc<ContentFilteringService>,
+    example_repository: Arc<dyn FewShotExampleRepository>,
+    template_repository: Arc<dyn PromptTemplateRepository>,
     conversation_sessions: Arc<RwLock<HashMap<EntityId, ConversationSession>>>,
     #[allow(dead_code)] // Used for user preference fallbacks and initialization
     default_preferences: WritingPreferences,
@@ -314,11 +322,38 @@ impl AIWritingService {
             orchestration_service,
             context_service,
             content_filter,
+            example_repository: Arc::new(InMemoryFewShotExampleRepository::new()),
+            template_repository: Arc::new(InMemoryPromptTemplateRepository::new()),
             conversation_sessions: Arc::new(RwLock::new(HashMap::new())),
             default_preferences: WritingPreferences::default(),
         }
     }
 
+    /// Look up few-shot example sets in `repository`
+    pub fn with_example_repository(mut self, repository: Arc<dyn FewShotExampleRepository>) -> Self {
+        self.example_repository = repository;
+        self
+    }
+
+    pub fn example_repository(&self) -> &Arc<dyn FewShotExampleRepository> {
+        &self.example_repository
+    }
+
+    /// Look up prompt templates in `repository`
+    pub fn with_template_repository(mut self, repository: Arc<dyn PromptTemplateRepository>) -> Self {
+        self.template_repository = repository;
+        self
+    }
+
+    pub fn template_repository(&self) -> &Arc<dyn PromptTemplateRepository> {
+        &self.template_repository
+    }
+
+    /// The orchestration service completions are routed through
+    pub fn orchestration_service(&self) -> &AIOrchestrationService {
+        &self.orchestration_service
+    }
+
     /// Get or create a conversation session for a document
     pub async fn get_conversation_session(&self, document_id: EntityId) -> ConversationSession {
         let mut sessions = self.conversation_sessions.write().await;
@@ -327,13 +362,19 @@ impl AIWritingService {
             .clone()
     }
 
+    /// Expand a request's few-shot examples into messages ahead of its prompt,
+    /// trimming the oldest examples to fit the context window
+    pub fn expand_few_shot(&self, request: CompletionRequest) -> Result<CompletionRequest> {
+        self.context_service.expand_few_shot(request)
+    }
+
     /// Provide writing assistance based on request
     pub async fn provide_assistance(
         &self,
         request: WritingAssistanceRequest,
     ) -> Result<WritingAssistanceResponse> {
         // Validate and filter content
-        self.content_filter.filter_content(&request.context.document_content)?;
+        self.content_filter.apply(&request.context.document_content, FilterMode::Reject)?;
 
         // Get conversation session
         let mut session = self.get_conversation_session(request.context.document_id).await;
@@ -347,7 +388,13 @@ impl AIWritingService {
         });
 
         // Create completion request
-        let completion_request = self.build_completion_request(messages, model_config)?;
+        let mut completion_request = self.build_completion_request(messages, model_config)?;
+        if let Some(set_name) = &request.example_set {
+            let set = self.example_repository.find_by_name(set_name).await?
+                .ok_or_else(|| WritemagicError::validation(format!("Unknown few-shot example set: {}", set_name)))?;
+            completion_request.few_shot = set.examples;
+        }
+        let completion_request = self.expand_few_shot(completion_request)?;
 
         // Get AI response
         let completion_response = self.orchestration_service
@@ -395,6 +442,7 @@ impl AIWritingService {
                 }
             }),
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -412,6 +460,7 @@ impl AIWritingService {
             user_input: continuation_hint,
             model_config: None,
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -435,6 +484,7 @@ impl AIWritingService {
                 }
             }),
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -452,6 +502,7 @@ impl AIWritingService {
             user_input: improvement_focus,
             model_config: None,
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -472,6 +523,7 @@ impl AIWritingService {
                     .with_temperature(0.1) // Lower temperature for grammar checking
             ),
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -489,6 +541,7 @@ impl AIWritingService {
             user_input: None,
             model_config: None,
             stream_response: false,
+            example_set: None,
         };
 
         self.provide_assistance(request).await
@@ -516,6 +569,7 @@ impl AIWritingService {
                     .with_temperature(0.3)
             ),
             stream_response: false,
+            example_set: None,
         };
 
         let response = self.provide_assistance(request).await?;
@@ -535,6 +589,72 @@ impl AIWritingService {
         })
     }
 
+    /// Render a stored prompt template with `variables` and complete it
+    ///
+    /// Fails with a validation error listing every missing variable.
+    pub async fn complete_from_template(
+        &self,
+        template_id: &EntityId,
+        variables: HashMap<String, String>,
+        model: Option<String>,
+    ) -> Result<CompletionResponse> {
+        let template = self.template_repository.find_by_id(template_id).await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Prompt template {}", template_id)))?;
+        let prompt = template.render(&variables)?;
+        self.content_filter.apply(&prompt, FilterMode::Reject)?;
+
+        let model_config = ModelConfiguration::new(model.as_deref().unwrap_or("claude-3-5-sonnet-20241022"))?;
+        let completion_request = self.build_completion_request(vec![Message::user(prompt)], model_config)?
+            .with_metadata("prompt_template".to_string(), template.name);
+
+        self.orchestration_service.complete_with_fallback(completion_request).await
+    }
+
+    /// Suggest topical tags for content
+    ///
+    /// Suggestions that do not satisfy the tag format are dropped. When no AI
+    /// provider can serve the request, falls back to keyword-frequency extraction.
+    pub async fn suggest_tags(&self, content: &str, max: usize) -> Result<Vec<DocumentTag>> {
+        if max == 0 || content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.content_filter.apply(content, FilterMode::Reject)?;
+
+        let messages = vec![
+            Message::system(format!(
+                "You extract topical tags from documents. Respond with at most {} tags as a \
+                 comma-separated list. Each tag must be lowercase, contain only letters, digits, \
+                 '-' or '_', have no spaces and be at most {} characters long. Respond with the \
+                 tags only.",
+                max,
+                DocumentTag::MAX_LENGTH
+            )),
+            Message::user(content.to_string()),
+        ];
+
+        let model_config = ModelConfiguration::new("claude-3-haiku-20240307")
+            .unwrap_or_else(|_| ModelConfiguration::default())
+            .with_max_tokens(200)
+            .with_temperature(0.2);
+        let completion_request = self.build_completion_request(messages, model_config)?;
+
+        match self.orchestration_service.complete_with_fallback(completion_request).await {
+            Ok(response) => {
+                let raw = response
+                    .choices
+                    .first()
+                    .map(|choice| choice.message.content.as_str())
+                    .unwrap_or_default();
+                Ok(self.parse_tag_suggestions(raw, max))
+            }
+            Err(e) => {
+                log::warn!("AI tag suggestion unavailable, using keyword extraction: {}", e);
+                Ok(DocumentTag::from_keywords(content, max))
+            }
+        }
+    }
+
     /// Build messages for AI completion based on request type
     async fn build_messages(
         &self,
@@ -628,13 +748,19 @@ impl AIWritingService {
             }
         };
 
-        Ok(format!(
+        let assistant_prompt = format!(
             "{}\n\n{}\n\n{}\n\nTask: {}",
             base_prompt,
             context_prompt,
             preferences_prompt,
             task_specific_prompt
-        ))
+        );
+
+        // The project's prompt leads and the task instructions still follow it
+        match request.context.project_context.as_ref().and_then(|project| project.system_prompt.as_deref()) {
+            Some(project_prompt) => Ok(format!("{}\n\n{}", project_prompt, assistant_prompt)),
+            None => Ok(assistant_prompt),
+        }
     }
 
     /// Build user message with content and specific request
@@ -837,6 +963,30 @@ impl AIWritingService {
         Ok(suggestions)
     }
 
+    fn parse_tag_suggestions(&self, content: &str, max: usize) -> Vec<DocumentTag> {
+        let mut tags: Vec<DocumentTag> = Vec::new();
+
+        for candidate in content.split([',', '\n']) {
+            let candidate = candidate
+                .trim()
+                .trim_start_matches(|c: char| c == '-' || c == '*' || c == '#' || c.is_whitespace())
+                .trim()
+                .to_lowercase();
+
+            match DocumentTag::new(candidate.as_str()) {
+                Ok(tag) if !tags.contains(&tag) => tags.push(tag),
+                Ok(_) => {}
+                Err(_) => log::debug!("Dropping invalid tag suggestion: {:?}", candidate),
+            }
+
+            if tags.len() >= max {
+                break;
+            }
+        }
+
+        tags
+    }
+
     fn calculate_confidence_score(
         &self,
         _content: &str,
@@ -1035,4 +1185,161 @@ mod tests {
         assert_eq!(extracted, Some("This is a ".to_string()));
         Ok(())
     }
-}
\ No newline at end of file
+
+    struct MockTagProvider {
+        response: Option<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::providers::AIProvider for MockTagProvider {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+            let content = self
+                .response
+                .clone()
+                .ok_or_else(|| WritemagicError::ai_provider("Mock provider unavailable"))?;
+            Ok(CompletionResponse {
+                id: "mock-completion".to_string(),
+                choices: vec![crate::providers::Choice {
+                    index: 0,
+                    message: Message::assistant(content),
+                    finish_reason: Some(crate::providers::FinishReason::Stop),
+                }],
+                usage: crate::providers::Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 10,
+                    total_tokens: 20,
+                },
+                model: request.model.clone(),
+                created: chrono::Utc::now().timestamp(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(&self, _request: &CompletionRequest) -> Result<Box<dyn crate::providers::StreamingResponse>> {
+            Err(WritemagicError::not_implemented("Mock provider does not stream"))
+        }
+
+        async fn batch_complete(&self, requests: Vec<CompletionRequest>) -> Result<Vec<Result<CompletionResponse>>> {
+            let mut results = Vec::new();
+            for request in &requests {
+                results.push(self.complete(request).await);
+            }
+            Ok(results)
+        }
+
+        fn capabilities(&self) -> crate::providers::ModelCapabilities {
+            crate::providers::ModelCapabilities {
+                max_tokens: 4096,
+                supports_streaming: false,
+                supports_functions: false,
+                supports_vision: false,
+                context_window: 200000,
+                input_cost_per_token: 0.0,
+                output_cost_per_token: 0.0,
+            }
+        }
+
+        async fn validate_credentials(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn get_usage_stats(&self) -> Result<crate::providers::UsageStats> {
+            Ok(crate::providers::UsageStats {
+                total_requests: 0,
+                total_tokens: 0,
+                total_cost: 0.0,
+                requests_today: 0,
+                tokens_today: 0,
+                cost_today: 0.0,
+            })
+        }
+
+        async fn health_check(&self) -> Result<crate::providers::ProviderHealthMetrics> {
+            Ok(crate::providers::ProviderHealthMetrics {
+                is_healthy: true,
+                response_time_ms: 1,
+                success_rate: 1.0,
+                error_count: 0,
+                last_error: None,
+                timestamp: std::time::SystemTime::now(),
+            })
+        }
+    }
+
+    async fn create_writing_service(response: Option<&str>) -> AIWritingService {
+        let mut orchestration = AIOrchestrationService::new().unwrap();
+        orchestration
+            .add_provider(Arc::new(MockTagProvider {
+                response: response.map(|r| r.to_string()),
+            }))
+            .await;
+
+        AIWritingService::new(
+            Arc::new(orchestration),
+            Arc::new(ContextManagementService::new(100000).unwrap()),
+            Arc::new(ContentFilteringService::new().unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_parses_and_validates() {
+        let service = create_writing_service(Some("Rust, #async-io\n- web_dev, Not Valid, way-too-long-tag-name-exceeding-the-limit, rust")).await;
+
+        let tags = service
+            .suggest_tags("Writing async web services in Rust.", 5)
+            .await
+            .unwrap();
+        let tags: Vec<&str> = tags.iter().map(|t| t.as_str()).collect();
+
+        assert_eq!(tags, vec!["rust", "async-io", "web_dev"]);
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_respects_max() {
+        let service = create_writing_service(Some("alpha, beta, gamma, delta")).await;
+
+        let tags = service.suggest_tags("Greek letters in mathematics.", 2).await.unwrap();
+
+        assert_eq!(tags.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_project_system_prompt_leads_the_task_instructions() {
+        let service = create_writing_service(None).await;
+        let mut context = create_mock_writing_context();
+        context.project_context = Some(ProjectContext {
+            project_id: EntityId::new(),
+            project_name: "Field guide".to_string(),
+            project_description: None,
+            system_prompt: Some("Write as a park ranger.".to_string()),
+            related_documents: Vec::new(),
+        });
+        let request = WritingAssistanceRequest {
+            context,
+            assistance_type: WritingAssistanceType::Summarization,
+            user_input: None,
+            model_config: None,
+            stream_response: false,
+            example_set: None,
+        };
+
+        let prompt = service.build_system_prompt(&request).unwrap();
+        assert!(prompt.starts_with("Write as a park ranger.\n\nYou are an expert writing assistant"));
+        assert!(prompt.contains("Task: Create a concise, accurate summary"));
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_falls_back_to_keywords() {
+        let service = create_writing_service(None).await;
+        let content = "Gardening tips: tomatoes love sun. Tomatoes drink water. Gardening is fun.";
+
+        let tags = service.suggest_tags(content, 2).await.unwrap();
+        let tags: Vec<&str> = tags.iter().map(|t| t.as_str()).collect();
+
+        assert_eq!(tags, vec!["gardening", "tomatoes"]);
+    }
+}
@@ -52,7 +52,7 @@ pub struct PaneConfig {
 }
 
 /// Types of panes available in the workspace
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PaneType {
     Editor,
     Preview,
@@ -72,6 +72,16 @@ pub struct PanePosition {
     pub height: f32,
 }
 
+impl PanePosition {
+    /// Whether the two rectangles share any area; touching edges do not count
+    pub fn overlaps(&self, other: &PanePosition) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
 /// Project metadata and statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectMetadata {
@@ -254,9 +264,70 @@ pub struct ProjectTemplate {
     pub workspace_config: WorkspaceConfig,
     pub default_documents: Vec<String>,
     pub tags: Vec<String>,
+    /// Panes to open in the workspace created alongside a project from this template
+    #[serde(default)]
+    pub pane_layout: Option<Vec<PaneConfig>>,
 }
 
 impl ProjectTemplate {
+    /// Identifiers of the templates that ship with WriteMagic
+    pub const BUILT_IN_IDS: [&'static str; 4] = ["writing", "novel", "screenplay", "blog"];
+
+    /// Look up a built-in template by identifier
+    pub fn built_in(template_id: &str) -> Option<Self> {
+        match template_id {
+            "writing" => Some(Self::writing_template()),
+            "novel" => Some(Self::novel_template()),
+            "screenplay" => Some(Self::screenplay_template()),
+            "blog" => Some(Self::blog_template()),
+            _ => None,
+        }
+    }
+
+    /// Check the pane layout: unique ids, panes inside the workspace and no overlaps
+    ///
+    /// Positions are percentages of the workspace. Pane types are a closed set, so
+    /// unknown types are already rejected when a template is deserialized.
+    pub fn validate_pane_layout(&self) -> Result<()> {
+        let Some(panes) = &self.pane_layout else {
+            return Ok(());
+        };
+        if panes.is_empty() {
+            return Err(WritemagicError::validation("Pane layout must contain at least one pane"));
+        }
+
+        for (index, pane) in panes.iter().enumerate() {
+            if pane.id.trim().is_empty() {
+                return Err(WritemagicError::validation("Pane id cannot be empty"));
+            }
+            if panes[..index].iter().any(|other| other.id == pane.id) {
+                return Err(WritemagicError::validation(format!("Duplicate pane id '{}'", pane.id)));
+            }
+
+            let PanePosition { x, y, width, height } = pane.position;
+            let inside = [x, y, width, height].iter().all(|value| value.is_finite())
+                && x >= 0.0
+                && y >= 0.0
+                && width > 0.0
+                && height > 0.0
+                && x + width <= 100.0
+                && y + height <= 100.0;
+            if !inside {
+                return Err(WritemagicError::validation(format!(
+                    "Pane '{}' must lie within the workspace", pane.id
+                )));
+            }
+
+            if let Some(other) = panes[..index].iter().find(|other| other.position.overlaps(&pane.position)) {
+                return Err(WritemagicError::validation(format!(
+                    "Panes '{}' and '{}' overlap", other.id, pane.id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Create a writing template
     pub fn writing_template() -> Self {
         Self {
@@ -286,6 +357,86 @@ impl ProjectTemplate {
             },
             default_documents: vec!["Main Document".to_string(), "Notes".to_string()],
             tags: vec!["writing".to_string()],
+            pane_layout: None,
+        }
+    }
+
+    /// Create a novel template: outline, manuscript and research notes side by side
+    pub fn novel_template() -> Self {
+        Self::with_pane_layout(
+            "Novel",
+            "Template for long-form fiction organised by chapter",
+            vec![
+                Self::pane("outline", PaneType::Outline, PanePosition { x: 0.0, y: 0.0, width: 20.0, height: 100.0 }),
+                Self::pane("manuscript", PaneType::Editor, PanePosition { x: 20.0, y: 0.0, width: 55.0, height: 100.0 }),
+                Self::pane("notes", PaneType::Notes, PanePosition { x: 75.0, y: 0.0, width: 25.0, height: 60.0 }),
+                Self::pane("research", PaneType::Reference, PanePosition { x: 75.0, y: 60.0, width: 25.0, height: 40.0 }),
+            ],
+            vec!["Chapter 1".to_string(), "Characters".to_string(), "Notes".to_string()],
+            vec!["novel".to_string(), "fiction".to_string()],
+        )
+    }
+
+    /// Create a screenplay template: script with a scene outline and preview
+    pub fn screenplay_template() -> Self {
+        Self::with_pane_layout(
+            "Screenplay",
+            "Template for scripts written scene by scene",
+            vec![
+                Self::pane("scenes", PaneType::Outline, PanePosition { x: 0.0, y: 0.0, width: 20.0, height: 100.0 }),
+                Self::pane("script", PaneType::Editor, PanePosition { x: 20.0, y: 0.0, width: 50.0, height: 100.0 }),
+                Self::pane("preview", PaneType::Preview, PanePosition { x: 70.0, y: 0.0, width: 30.0, height: 100.0 }),
+            ],
+            vec!["Script".to_string(), "Scene List".to_string()],
+            vec!["screenplay".to_string()],
+        )
+    }
+
+    /// Create a blog template: editor, rendered preview and the AI assistant
+    pub fn blog_template() -> Self {
+        Self::with_pane_layout(
+            "Blog",
+            "Template for blog posts and articles",
+            vec![
+                Self::pane("post", PaneType::Editor, PanePosition { x: 0.0, y: 0.0, width: 50.0, height: 100.0 }),
+                Self::pane("preview", PaneType::Preview, PanePosition { x: 50.0, y: 0.0, width: 50.0, height: 70.0 }),
+                Self::pane("assistant", PaneType::AIAssistant, PanePosition { x: 50.0, y: 70.0, width: 50.0, height: 30.0 }),
+            ],
+            vec!["Draft Post".to_string()],
+            vec!["blog".to_string()],
+        )
+    }
+
+    fn with_pane_layout(
+        name: &str,
+        description: &str,
+        panes: Vec<PaneConfig>,
+        default_documents: Vec<String>,
+        tags: Vec<String>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            workspace_config: WorkspaceConfig {
+                layout: WorkspaceLayout::MultiPane,
+                panes: panes.clone(),
+                theme: None,
+                auto_save_enabled: true,
+                focus_mode_enabled: false,
+            },
+            default_documents,
+            tags,
+            pane_layout: Some(panes),
+        }
+    }
+
+    fn pane(id: &str, pane_type: PaneType, position: PanePosition) -> PaneConfig {
+        PaneConfig {
+            id: id.to_string(),
+            pane_type,
+            size_percentage: position.width * position.height / 100.0,
+            document_id: None,
+            position,
         }
     }
 }
@@ -325,4 +476,44 @@ mod tests {
         // Test duplicate document
         assert!(project.add_document(doc_id, None).is_err());
     }
+
+    #[test]
+    fn test_built_in_templates_have_valid_layouts() {
+        for id in ProjectTemplate::BUILT_IN_IDS {
+            let template = ProjectTemplate::built_in(id).unwrap();
+            assert!(template.validate_pane_layout().is_ok(), "{} layout is invalid", id);
+        }
+        assert!(ProjectTemplate::built_in("unknown").is_none());
+    }
+
+    #[test]
+    fn test_pane_layout_validation() {
+        let mut template = ProjectTemplate::blog_template();
+        let panes = template.pane_layout.as_mut().unwrap();
+        panes[1].position.x = 40.0;
+        assert!(matches!(template.validate_pane_layout(), Err(WritemagicError::Validation { .. })));
+
+        let mut template = ProjectTemplate::blog_template();
+        template.pane_layout.as_mut().unwrap()[2].position.height = 40.0;
+        assert!(template.validate_pane_layout().is_err());
+
+        let mut template = ProjectTemplate::blog_template();
+        template.pane_layout.as_mut().unwrap()[2].id = "post".to_string();
+        assert!(template.validate_pane_layout().is_err());
+
+        template.pane_layout = Some(Vec::new());
+        assert!(template.validate_pane_layout().is_err());
+    }
+
+    #[test]
+    fn test_unknown_pane_type_is_rejected() {
+        let mut value = serde_json::to_value(ProjectTemplate::novel_template()).unwrap();
+        value["pane_layout"][0]["pane_type"] = serde_json::json!("Timeline");
+        assert!(serde_json::from_value::<ProjectTemplate>(value).is_err());
+
+        let mut value = serde_json::to_value(ProjectTemplate::writing_template()).unwrap();
+        value.as_object_mut().unwrap().remove("pane_layout");
+        let template: ProjectTemplate = serde_json::from_value(value).unwrap();
+        assert!(template.pane_layout.is_none());
+    }
 }
\ No newline at end of file
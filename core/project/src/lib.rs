@@ -5,15 +5,19 @@ pub mod value_objects;
 pub mod aggregates;
 pub mod services;
 pub mod repositories;
+pub mod search;
 
 pub use entities::{Project, WorkspaceConfig, ProjectMetadata, ProjectTemplate, PaneConfig, PaneType};
 pub use value_objects::{ProjectStatus, ProjectPriority, ProjectColor, ProjectTag, ProjectGoal, GoalType};
 pub use aggregates::{ProjectAggregate, ProjectEvent};
 pub use services::{ProjectManagementService, ProjectTemplateService, ProjectAnalyticsService, CreateProjectRequest, UpdateProjectRequest, ProjectAnalytics, ProductivityMetrics};
-pub use repositories::{ProjectRepository, ProjectTemplateRepository, ProjectFilter, ProjectSearchCriteria, ProjectSortBy, SortOrder, RecentActivity, ActivityType};
+pub use repositories::{ProjectRepository, ProjectTemplateRepository, ProjectFilter, ProjectSearchCriteria, ProjectSortBy, SortOrder, RecentActivity, ActivityType, WorkspaceRepository, InMemoryWorkspaceRepository, InMemoryProjectRepository};
+pub use search::{ProjectPredicate, ProjectSearchBuilder};
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub use repositories::{SqliteWorkspaceRepository, SqliteProjectRepository};
 
 /// Workspace entity for managing multiple panes
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Workspace {
     pub id: writemagic_shared::EntityId,
     pub name: String,
@@ -25,34 +29,110 @@ pub struct Workspace {
 }
 
 /// Individual pane within a workspace
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Pane {
     pub id: writemagic_shared::EntityId,
     pub document_id: Option<writemagic_shared::EntityId>,
     pub branch_name: Option<String>,
+    /// What the pane shows when it was opened from a template layout
+    #[serde(default)]
+    pub pane_type: Option<PaneType>,
     pub position: PanePosition,
     pub size: PaneSize,
     pub is_active: bool,
 }
 
 /// Pane position and size
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PanePosition {
     pub x: f32,
     pub y: f32,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PaneSize {
     pub width: f32,
     pub height: f32,
 }
 
 /// Workspace layout configuration
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum WorkspaceLayout {
     Horizontal,
     Vertical,
     Grid { columns: u32, rows: u32 },
     Custom,
-}
\ No newline at end of file
+}
+
+impl Workspace {
+    /// Create an empty workspace
+    pub fn new(name: String, layout: WorkspaceLayout) -> Self {
+        let now = writemagic_shared::Timestamp::now();
+        Self {
+            id: writemagic_shared::EntityId::new(),
+            name,
+            panes: Vec::new(),
+            active_pane_id: None,
+            layout,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// Add a pane, making it active if no other pane is
+    pub fn add_pane(&mut self, mut pane: Pane) {
+        pane.is_active = self.active_pane_id.is_none();
+        if pane.is_active {
+            self.active_pane_id = Some(pane.id);
+        }
+        self.panes.push(pane);
+        self.updated_at = writemagic_shared::Timestamp::now();
+    }
+
+    /// Remove a pane; if it was active, the first remaining pane takes over
+    pub fn remove_pane(&mut self, pane_id: &writemagic_shared::EntityId) -> writemagic_shared::Result<Pane> {
+        let index = self.panes
+            .iter()
+            .position(|pane| pane.id == *pane_id)
+            .ok_or_else(|| writemagic_shared::WritemagicError::not_found(format!("Pane {}", pane_id)))?;
+        let removed = self.panes.remove(index);
+
+        if self.active_pane_id == Some(removed.id) {
+            self.active_pane_id = None;
+            if let Some(first) = self.panes.first() {
+                let first_id = first.id;
+                self.set_active_pane(&first_id)?;
+            }
+        }
+        self.updated_at = writemagic_shared::Timestamp::now();
+        Ok(removed)
+    }
+
+    /// Make a pane the single active pane
+    pub fn set_active_pane(&mut self, pane_id: &writemagic_shared::EntityId) -> writemagic_shared::Result<()> {
+        if !self.panes.iter().any(|pane| pane.id == *pane_id) {
+            return Err(writemagic_shared::WritemagicError::not_found(format!("Pane {}", pane_id)));
+        }
+        for pane in &mut self.panes {
+            pane.is_active = pane.id == *pane_id;
+        }
+        self.active_pane_id = Some(*pane_id);
+        self.updated_at = writemagic_shared::Timestamp::now();
+        Ok(())
+    }
+}
+
+impl Pane {
+    /// Create an inactive pane, optionally showing a document
+    pub fn new(document_id: Option<writemagic_shared::EntityId>, position: PanePosition, size: PaneSize) -> Self {
+        Self {
+            id: writemagic_shared::EntityId::new(),
+            document_id,
+            branch_name: None,
+            pane_type: None,
+            position,
+            size,
+            is_active: false,
+        }
+    }
+}
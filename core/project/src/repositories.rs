@@ -3,7 +3,9 @@
 use writemagic_shared::{EntityId, WritemagicError, Result};
 use crate::aggregates::{ProjectAggregate, ProjectEvent};
 use crate::entities::ProjectTemplate;
+use crate::search::{self, ProjectPredicate};
 use crate::value_objects::{ProjectStatus, ProjectPriority};
+use crate::Workspace;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -65,6 +67,389 @@ pub trait ProjectTemplateRepository: Send + Sync {
     async fn delete_template(&self, name: &str) -> Result<()>;
 }
 
+/// Repository trait for multi-pane workspaces
+#[async_trait]
+pub trait WorkspaceRepository: Send + Sync {
+    /// Save a workspace, replacing any stored version
+    async fn save(&self, workspace: &Workspace) -> Result<()>;
+
+    /// Load a workspace by ID
+    async fn find_by_id(&self, workspace_id: &EntityId) -> Result<Option<Workspace>>;
+
+    /// All workspaces, most recently updated first
+    async fn find_all(&self) -> Result<Vec<Workspace>>;
+
+    /// Delete a workspace, returning whether it existed
+    async fn delete(&self, workspace_id: &EntityId) -> Result<bool>;
+}
+
+/// In-memory workspace storage for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryWorkspaceRepository {
+    workspaces: std::sync::Arc<std::sync::RwLock<std::collections::HashMap<EntityId, Workspace>>>,
+}
+
+impl InMemoryWorkspaceRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WorkspaceRepository for InMemoryWorkspaceRepository {
+    async fn save(&self, workspace: &Workspace) -> Result<()> {
+        let mut workspaces = self.workspaces.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        workspaces.insert(workspace.id, workspace.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, workspace_id: &EntityId) -> Result<Option<Workspace>> {
+        let workspaces = self.workspaces.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(workspaces.get(workspace_id).cloned())
+    }
+
+    async fn find_all(&self) -> Result<Vec<Workspace>> {
+        let workspaces = self.workspaces.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        let mut all: Vec<Workspace> = workspaces.values().cloned().collect();
+        all.sort_by_key(|workspace| std::cmp::Reverse(workspace.updated_at.as_datetime()));
+        Ok(all)
+    }
+
+    async fn delete(&self, workspace_id: &EntityId) -> Result<bool> {
+        let mut workspaces = self.workspaces.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        Ok(workspaces.remove(workspace_id).is_some())
+    }
+}
+
+/// SQLite-backed workspace storage; panes and layout are stored as JSON
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub struct SqliteWorkspaceRepository {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+impl SqliteWorkspaceRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Workspace> {
+        use sqlx::Row;
+        use writemagic_shared::Timestamp;
+
+        let id: String = row.get("id");
+        let layout: String = row.get("layout");
+        let panes: String = row.get("panes");
+        let active_pane_id: Option<String> = row.get("active_pane_id");
+        let created_at: String = row.get("created_at");
+        let updated_at: String = row.get("updated_at");
+
+        Ok(Workspace {
+            id: EntityId::from_string(&id)
+                .map_err(|e| WritemagicError::database(format!("Invalid workspace id: {}", e)))?,
+            name: row.get("name"),
+            panes: serde_json::from_str(&panes)
+                .map_err(|e| WritemagicError::database(format!("Invalid workspace panes: {}", e)))?,
+            active_pane_id: active_pane_id
+                .map(|id| EntityId::from_string(&id))
+                .transpose()
+                .map_err(|e| WritemagicError::database(format!("Invalid active pane id: {}", e)))?,
+            layout: serde_json::from_str(&layout)
+                .map_err(|e| WritemagicError::database(format!("Invalid workspace layout: {}", e)))?,
+            created_at: Timestamp::from_string(&created_at)
+                .map_err(|e| WritemagicError::database(format!("Invalid workspace timestamp: {}", e)))?,
+            updated_at: Timestamp::from_string(&updated_at)
+                .map_err(|e| WritemagicError::database(format!("Invalid workspace timestamp: {}", e)))?,
+        })
+    }
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+#[async_trait]
+impl WorkspaceRepository for SqliteWorkspaceRepository {
+    async fn save(&self, workspace: &Workspace) -> Result<()> {
+        let layout = serde_json::to_string(&workspace.layout)
+            .map_err(|e| WritemagicError::database(format!("Failed to serialize workspace layout: {}", e)))?;
+        let panes = serde_json::to_string(&workspace.panes)
+            .map_err(|e| WritemagicError::database(format!("Failed to serialize workspace panes: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO workspaces (id, name, layout, panes, active_pane_id, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(workspace.id.to_string())
+        .bind(&workspace.name)
+        .bind(layout)
+        .bind(panes)
+        .bind(workspace.active_pane_id.map(|id| id.to_string()))
+        .bind(workspace.created_at.to_sortable_string())
+        .bind(workspace.updated_at.to_sortable_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to save workspace: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, workspace_id: &EntityId) -> Result<Option<Workspace>> {
+        let row = sqlx::query("SELECT * FROM workspaces WHERE id = ?")
+            .bind(workspace_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to find workspace: {}", e)))?;
+
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_all(&self) -> Result<Vec<Workspace>> {
+        let rows = sqlx::query("SELECT * FROM workspaces ORDER BY updated_at DESC")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to list workspaces: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn delete(&self, workspace_id: &EntityId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM workspaces WHERE id = ?")
+            .bind(workspace_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to delete workspace: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// In-memory project storage for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryProjectRepository {
+    projects: std::sync::Arc<std::sync::RwLock<std::collections::HashMap<EntityId, ProjectAggregate>>>,
+}
+
+impl InMemoryProjectRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn query(&self, predicate: &ProjectPredicate, filter: Option<&ProjectFilter>) -> Result<Vec<ProjectAggregate>> {
+        let projects = self.projects.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        let mut matching: Vec<ProjectAggregate> = projects
+            .values()
+            .filter(|aggregate| predicate.matches(aggregate))
+            .cloned()
+            .collect();
+
+        let (sort_by, sort_order) = search::sort_of(filter);
+        search::sort_projects(&mut matching, &sort_by, &sort_order);
+
+        let offset = filter.and_then(|filter| filter.offset).unwrap_or(0);
+        let limit = filter.and_then(|filter| filter.limit).unwrap_or(usize::MAX);
+        Ok(matching.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for InMemoryProjectRepository {
+    async fn save(&self, aggregate: &mut ProjectAggregate) -> Result<()> {
+        let mut projects = self.projects.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        aggregate.clear_events();
+        projects.insert(aggregate.id(), aggregate.clone());
+        Ok(())
+    }
+
+    async fn load(&self, project_id: &EntityId) -> Result<Option<ProjectAggregate>> {
+        let projects = self.projects.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(projects.get(project_id).cloned())
+    }
+
+    async fn delete(&self, project_id: &EntityId) -> Result<()> {
+        let mut projects = self.projects.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        projects.remove(project_id);
+        Ok(())
+    }
+
+    async fn list(&self, filter: ProjectFilter) -> Result<Vec<ProjectAggregate>> {
+        self.query(&filter.to_predicate(), Some(&filter))
+    }
+
+    async fn search(&self, criteria: ProjectSearchCriteria) -> Result<Vec<ProjectAggregate>> {
+        self.query(&criteria.to_predicate(), criteria.filter.as_ref())
+    }
+
+    async fn get_statistics(&self, _project_id: &EntityId) -> Result<ProjectStatistics> {
+        let projects = self.query(&ProjectPredicate::All(Vec::new()), None)?;
+        Ok(ProjectStatistics::from_projects(&projects))
+    }
+
+    async fn exists(&self, project_id: &EntityId) -> Result<bool> {
+        let projects = self.projects.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(projects.contains_key(project_id))
+    }
+}
+
+/// SQLite-backed project storage
+///
+/// The aggregate is stored as JSON next to the columns searches filter and
+/// sort on; searches are compiled to a single parameterized query.
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub struct SqliteProjectRepository {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+impl SqliteProjectRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn query(&self, predicate: &ProjectPredicate, filter: Option<&ProjectFilter>) -> Result<Vec<ProjectAggregate>> {
+        use crate::search::SqlParam;
+
+        let (sort_by, sort_order) = search::sort_of(filter);
+        let mut sql = String::from("SELECT aggregate FROM project_aggregates WHERE ");
+        let mut params = Vec::new();
+        predicate.write_sql(&mut sql, &mut params);
+        sql.push_str(" ORDER BY ");
+        sql.push_str(&search::order_by_sql(&sort_by, &sort_order));
+        sql.push_str(" LIMIT ? OFFSET ?");
+
+        let limit = filter.and_then(|filter| filter.limit).and_then(|limit| i64::try_from(limit).ok());
+        let offset = filter.and_then(|filter| filter.offset).unwrap_or(0);
+        params.push(SqlParam::Integer(limit.unwrap_or(-1)));
+        params.push(SqlParam::Integer(i64::try_from(offset).unwrap_or(i64::MAX)));
+
+        let mut query = sqlx::query(&sql);
+        for param in params {
+            query = match param {
+                SqlParam::Text(value) => query.bind(value),
+                SqlParam::Integer(value) => query.bind(value),
+            };
+        }
+
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to search projects: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ProjectAggregate> {
+        use sqlx::Row;
+
+        let aggregate: String = row.get("aggregate");
+        serde_json::from_str(&aggregate)
+            .map_err(|e| WritemagicError::database(format!("Invalid project aggregate: {}", e)))
+    }
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+#[async_trait]
+impl ProjectRepository for SqliteProjectRepository {
+    async fn save(&self, aggregate: &mut ProjectAggregate) -> Result<()> {
+        aggregate.clear_events();
+
+        let tags: Vec<&str> = aggregate.tags().iter().map(|tag| tag.value()).collect();
+        let tags = serde_json::to_string(&tags)
+            .map_err(|e| WritemagicError::database(format!("Failed to serialize project tags: {}", e)))?;
+        let serialized = serde_json::to_string(&*aggregate)
+            .map_err(|e| WritemagicError::database(format!("Failed to serialize project: {}", e)))?;
+        let project = aggregate.project();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO project_aggregates (
+                id, name, description, status, priority, tags, created_by, is_archived,
+                document_count, created_at, updated_at, last_activity, aggregate
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(project.id.to_string())
+        .bind(&project.name)
+        .bind(project.description.as_deref())
+        .bind(aggregate.status().to_string())
+        .bind(search::priority_rank(aggregate.priority()))
+        .bind(tags)
+        .bind(project.created_by.map(|id| id.to_string()))
+        .bind(project.is_archived)
+        .bind(project.document_count() as i64)
+        .bind(writemagic_shared::sortable_datetime(&project.created_at))
+        .bind(writemagic_shared::sortable_datetime(&project.updated_at))
+        .bind(writemagic_shared::sortable_datetime(&project.metadata.last_activity))
+        .bind(serialized)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to save project: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load(&self, project_id: &EntityId) -> Result<Option<ProjectAggregate>> {
+        let row = sqlx::query("SELECT aggregate FROM project_aggregates WHERE id = ?")
+            .bind(project_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to load project: {}", e)))?;
+
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn delete(&self, project_id: &EntityId) -> Result<()> {
+        sqlx::query("DELETE FROM project_aggregates WHERE id = ?")
+            .bind(project_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to delete project: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn list(&self, filter: ProjectFilter) -> Result<Vec<ProjectAggregate>> {
+        self.query(&filter.to_predicate(), Some(&filter)).await
+    }
+
+    async fn search(&self, criteria: ProjectSearchCriteria) -> Result<Vec<ProjectAggregate>> {
+        self.query(&criteria.to_predicate(), criteria.filter.as_ref()).await
+    }
+
+    async fn get_statistics(&self, _project_id: &EntityId) -> Result<ProjectStatistics> {
+        let projects = self.query(&ProjectPredicate::All(Vec::new()), None).await?;
+        Ok(ProjectStatistics::from_projects(&projects))
+    }
+
+    async fn exists(&self, project_id: &EntityId) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM project_aggregates WHERE id = ?")
+            .bind(project_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to check project: {}", e)))?;
+
+        Ok(row.is_some())
+    }
+}
+
 /// Filter criteria for listing projects
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProjectFilter {
@@ -91,6 +476,9 @@ pub struct ProjectSearchCriteria {
     pub search_in_description: bool,
     pub search_in_tags: bool,
     pub filter: Option<ProjectFilter>,
+    /// Further conditions, e.g. from `ProjectSearchCriteria::builder()`
+    #[serde(default)]
+    pub predicate: Option<ProjectPredicate>,
 }
 
 /// Project sorting options
@@ -125,6 +513,42 @@ pub struct ProjectStatistics {
     pub recent_activity: Vec<RecentActivity>,
 }
 
+impl ProjectStatistics {
+    /// Statistics across `projects`; recent activity is not tracked yet
+    pub fn from_projects(projects: &[ProjectAggregate]) -> Self {
+        let count_status = |status: ProjectStatus| projects.iter().filter(|aggregate| *aggregate.status() == status).count();
+        let total_documents: usize = projects.iter().map(|aggregate| aggregate.project().document_count()).sum();
+
+        let mut projects_by_priority = std::collections::HashMap::new();
+        let mut tag_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for aggregate in projects {
+            *projects_by_priority.entry(aggregate.priority().clone()).or_insert(0) += 1;
+            for tag in aggregate.tags() {
+                *tag_counts.entry(tag.value().to_string()).or_insert(0) += 1;
+            }
+        }
+        let mut most_common_tags: Vec<(String, usize)> = tag_counts.into_iter().collect();
+        most_common_tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        most_common_tags.truncate(10);
+
+        Self {
+            total_projects: projects.len(),
+            active_projects: count_status(ProjectStatus::Active),
+            completed_projects: count_status(ProjectStatus::Completed),
+            archived_projects: projects.iter().filter(|aggregate| aggregate.project().is_archived).count(),
+            average_documents_per_project: if projects.is_empty() {
+                0.0
+            } else {
+                total_documents as f32 / projects.len() as f32
+            },
+            total_documents,
+            projects_by_priority,
+            most_common_tags,
+            recent_activity: Vec::new(),
+        }
+    }
+}
+
 /// Recent project activity
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecentActivity {
@@ -150,76 +574,6 @@ pub enum ActivityType {
 pub mod implementations {
     use super::*;
     
-    /// SQLite implementation of ProjectRepository
-    /// Note: This is a placeholder implementation for future SQLite integration
-    pub struct SqliteProjectRepository {
-        // TODO: Add actual SQLite connection pool when implementing persistence
-        _phantom: std::marker::PhantomData<()>,
-    }
-    
-    impl SqliteProjectRepository {
-        pub fn new(_db_path: String) -> Self {
-            Self { 
-                _phantom: std::marker::PhantomData,
-            }
-        }
-    }
-    
-    #[async_trait]
-    impl ProjectRepository for SqliteProjectRepository {
-        async fn save(&self, aggregate: &mut ProjectAggregate) -> Result<()> {
-            // TODO: Implement actual SQLite persistence
-            // For now, this is a placeholder that logs the operation
-            
-            // Clear events to simulate successful save
-            if !aggregate.events().is_empty() {
-                aggregate.clear_events();
-            }
-            
-            // Return NotImplemented error to be explicit about current state
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository save operation not yet implemented"
-            ))
-        }
-        
-        async fn load(&self, _project_id: &EntityId) -> Result<Option<ProjectAggregate>> {
-            // TODO: Implement actual SQLite loading
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository load operation not yet implemented"
-            ))
-        }
-        
-        async fn delete(&self, _project_id: &EntityId) -> Result<()> {
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository delete operation not yet implemented"
-            ))
-        }
-        
-        async fn list(&self, _filter: ProjectFilter) -> Result<Vec<ProjectAggregate>> {
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository list operation not yet implemented"
-            ))
-        }
-        
-        async fn search(&self, _criteria: ProjectSearchCriteria) -> Result<Vec<ProjectAggregate>> {
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository search operation not yet implemented"
-            ))
-        }
-        
-        async fn get_statistics(&self, _project_id: &EntityId) -> Result<ProjectStatistics> {
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository statistics operation not yet implemented"
-            ))
-        }
-        
-        async fn exists(&self, _project_id: &EntityId) -> Result<bool> {
-            Err(WritemagicError::not_implemented(
-                "SQLite project repository exists operation not yet implemented"
-            ))
-        }
-    }
-    
     /// IndexedDB implementation for web applications
     /// Note: This is a placeholder implementation for future IndexedDB integration
     pub struct IndexedDBProjectRepository {
@@ -289,27 +643,133 @@ pub mod implementations {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repositories::implementations::SqliteProjectRepository;
+    use crate::search::ProjectSearchBuilder;
+    use crate::value_objects::ProjectTag;
+
+    /// Projects covering every searchable field, created a day apart from `fixture_start()`
+    fn search_fixture() -> Vec<ProjectAggregate> {
+        let author = EntityId::new();
+        let specs = [
+            ("Novel Draft", Some("A long 100%_ story"), ProjectStatus::Active, ProjectPriority::High, &["fiction", "draft"][..], Some(author)),
+            ("Research Notes", None, ProjectStatus::Paused, ProjectPriority::Low, &["research"][..], None),
+            ("novel outline", Some("Chapter plan"), ProjectStatus::Completed, ProjectPriority::High, &["fiction"][..], Some(author)),
+            ("Blog", Some("Weekly posts"), ProjectStatus::Active, ProjectPriority::Medium, &[][..], None),
+            ("Archive", Some("Old drafts"), ProjectStatus::Archived, ProjectPriority::Critical, &["draft"][..], None),
+        ];
+
+        specs.iter().enumerate().map(|(index, (name, description, status, priority, tags, created_by))| {
+            let mut aggregate = ProjectAggregate::new(name.to_string(), description.map(str::to_string), *created_by).unwrap();
+            for tag in tags.iter() {
+                aggregate.add_tag(ProjectTag::new(tag.to_string()).unwrap()).unwrap();
+            }
+            aggregate.update_priority(priority.clone());
+            if *status == ProjectStatus::Archived {
+                aggregate.archive().unwrap();
+            } else {
+                aggregate.update_status(status.clone()).unwrap();
+            }
+
+            // Backdate so date ranges and ordering don't depend on the clock
+            let created_at = fixture_start() + chrono::Duration::days(index as i64);
+            let mut value = serde_json::to_value(&aggregate).unwrap();
+            value["project"]["created_at"] = serde_json::json!(created_at);
+            value["project"]["updated_at"] = serde_json::json!(created_at + chrono::Duration::hours(48 - index as i64 * 12));
+            serde_json::from_value(value).unwrap()
+        }).collect()
+    }
+
+    fn fixture_start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    fn names(projects: &[ProjectAggregate]) -> Vec<&str> {
+        projects.iter().map(|aggregate| aggregate.project().name.as_str()).collect()
+    }
+
+    fn search_cases() -> Vec<ProjectSearchCriteria> {
+        let day = |days: i64| Some(fixture_start() + chrono::Duration::days(days));
+        let builders = [
+            ProjectSearchCriteria::builder(),
+            ProjectSearchCriteria::builder().status(ProjectStatus::Active).sort_by(ProjectSortBy::Name, SortOrder::Ascending),
+            ProjectSearchCriteria::builder().tag("Fiction").priority(ProjectPriority::High).sort_by(ProjectSortBy::CreatedAt, SortOrder::Descending),
+            ProjectSearchCriteria::builder()
+                .matching(ProjectPredicate::Tag("draft".to_string()).or(ProjectPredicate::Status(ProjectStatus::Paused)))
+                .matching(ProjectPredicate::Archived(true).negate())
+                .sort_by(ProjectSortBy::UpdatedAt, SortOrder::Descending),
+            ProjectSearchCriteria::builder().created_between(day(1), day(3)).sort_by(ProjectSortBy::Priority, SortOrder::Descending),
+            ProjectSearchCriteria::builder().updated_between(day(2), None).sort_by(ProjectSortBy::LastActivity, SortOrder::Ascending),
+            ProjectSearchCriteria::builder().name_contains("NOVEL").limit(1).offset(1),
+            ProjectSearchCriteria::builder().matching(ProjectPredicate::DescriptionContains("100%_".to_string())),
+            ProjectSearchCriteria::builder().matching(ProjectPredicate::DescriptionContains("draft".to_string()).negate()),
+            ProjectSearchCriteria::builder().matching(ProjectPredicate::Any(Vec::new())),
+            ProjectSearchCriteria::builder().sort_by(ProjectSortBy::Name, SortOrder::Descending).limit(2).offset(1),
+        ];
+
+        let mut cases: Vec<ProjectSearchCriteria> = builders.into_iter().map(ProjectSearchBuilder::build).collect();
+        cases.push(ProjectSearchCriteria {
+            query: "draft".to_string(),
+            search_in_name: false,
+            search_in_description: true,
+            search_in_tags: true,
+            filter: Some(ProjectFilter {
+                is_archived: Some(false),
+                sort_by: Some(ProjectSortBy::DocumentCount),
+                ..Default::default()
+            }),
+            predicate: None,
+        });
+        cases
+    }
 
     #[tokio::test]
-    async fn test_sqlite_repository() {
-        let repo = SqliteProjectRepository::new(":memory:".to_string());
-        
-        // Test basic repository operations
-        let mut aggregate = ProjectAggregate::new(
-            "Test Project".to_string(),
-            None,
-            None,
-        ).unwrap();
-        
-        // Save should not error
-        assert!(repo.save(&mut aggregate).await.is_ok());
-        
-        // Load should return None for non-existent project
-        let loaded = repo.load(&EntityId::new()).await.unwrap();
-        assert!(loaded.is_none());
+    async fn test_search_builder_combines_predicates() {
+        let repo = InMemoryProjectRepository::new();
+        for mut aggregate in search_fixture() {
+            repo.save(&mut aggregate).await.unwrap();
+        }
+        let cases = search_cases();
+        let search = |index: usize| repo.search(cases[index].clone());
+
+        assert_eq!(names(&search(0).await.unwrap()), ["Novel Draft", "Research Notes", "novel outline", "Blog", "Archive"]);
+        assert_eq!(names(&search(1).await.unwrap()), ["Blog", "Novel Draft"]);
+        assert_eq!(names(&search(2).await.unwrap()), ["novel outline", "Novel Draft"]);
+        assert_eq!(names(&search(3).await.unwrap()), ["Research Notes", "Novel Draft"]);
+        assert_eq!(names(&search(4).await.unwrap()), ["novel outline", "Research Notes"]);
+        assert_eq!(names(&search(6).await.unwrap()), ["novel outline"]);
+        assert_eq!(names(&search(7).await.unwrap()), ["Novel Draft"]);
+        assert!(search(9).await.unwrap().is_empty());
+        assert_eq!(names(&search(11).await.unwrap()), ["Novel Draft"]);
     }
-    
+
+    #[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_sqlite_and_in_memory_searches_return_identical_results() {
+        let database = writemagic_shared::DatabaseManager::new_in_memory().await.unwrap();
+        let sqlite = SqliteProjectRepository::new(database.pool().clone());
+        let memory = InMemoryProjectRepository::new();
+        let fixture = search_fixture();
+        for aggregate in &fixture {
+            sqlite.save(&mut aggregate.clone()).await.unwrap();
+            memory.save(&mut aggregate.clone()).await.unwrap();
+        }
+
+        for criteria in search_cases() {
+            let expected = memory.search(criteria.clone()).await.unwrap();
+            let actual = sqlite.search(criteria.clone()).await.unwrap();
+            let ids = |projects: &[ProjectAggregate]| projects.iter().map(|aggregate| aggregate.id()).collect::<Vec<_>>();
+            assert_eq!(ids(&actual), ids(&expected), "results differ for {:?}", criteria);
+        }
+
+        let filter = ProjectFilter { tags: vec!["Draft".to_string()], ..Default::default() };
+        assert_eq!(names(&sqlite.list(filter.clone()).await.unwrap()), names(&memory.list(filter).await.unwrap()));
+
+        let loaded = sqlite.load(&fixture[0].id()).await.unwrap().unwrap();
+        assert_eq!(loaded.tags(), fixture[0].tags());
+        assert!(sqlite.exists(&fixture[0].id()).await.unwrap());
+        sqlite.delete(&fixture[0].id()).await.unwrap();
+        assert!(!sqlite.exists(&fixture[0].id()).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_project_filter() {
         let filter = ProjectFilter {
@@ -331,10 +791,34 @@ mod tests {
             search_in_description: true,
             search_in_tags: false,
             filter: None,
+            predicate: None,
         };
         
         assert_eq!(criteria.query, "writing");
         assert!(criteria.search_in_name);
         assert!(!criteria.search_in_tags);
     }
+    #[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_sqlite_workspace_round_trip() {
+        use crate::{Pane, PanePosition, PaneSize, WorkspaceLayout};
+
+        let database = writemagic_shared::DatabaseManager::new_in_memory().await.unwrap();
+        let repo = SqliteWorkspaceRepository::new(database.pool().clone());
+
+        let mut workspace = Workspace::new("Drafting".to_string(), WorkspaceLayout::Grid { columns: 2, rows: 1 });
+        workspace.add_pane(Pane::new(Some(EntityId::new()), PanePosition { x: 0.0, y: 0.0 }, PaneSize { width: 0.5, height: 1.0 }));
+        workspace.add_pane(Pane::new(None, PanePosition { x: 0.5, y: 0.0 }, PaneSize { width: 0.5, height: 1.0 }));
+        repo.save(&workspace).await.unwrap();
+
+        let loaded = repo.find_by_id(&workspace.id).await.unwrap().unwrap();
+        assert_eq!(loaded.panes, workspace.panes);
+        assert_eq!(loaded.layout, workspace.layout);
+        assert_eq!(loaded.active_pane_id, Some(workspace.panes[0].id));
+        assert_eq!(repo.find_all().await.unwrap().len(), 1);
+
+        assert!(repo.delete(&workspace.id).await.unwrap());
+        assert!(repo.find_by_id(&workspace.id).await.unwrap().is_none());
+        assert!(!repo.delete(&workspace.id).await.unwrap());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,434 @@
+//! Composable project search predicates
+//!
+//! Search criteria are reduced to a single [`ProjectPredicate`], which the
+//! in-memory repository evaluates directly and the SQLite repository turns
+//! into a `WHERE` clause with every value bound as a parameter. Text matching
+//! ignores ASCII case only, as SQLite's `LIKE` does, so both agree.
+
+use crate::aggregates::ProjectAggregate;
+use crate::repositories::{ProjectFilter, ProjectSearchCriteria, ProjectSortBy, SortOrder};
+use crate::value_objects::{ProjectPriority, ProjectStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use writemagic_shared::EntityId;
+
+/// Condition a project must meet to match a search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProjectPredicate {
+    Status(ProjectStatus),
+    Priority(ProjectPriority),
+    /// Has exactly this tag
+    Tag(String),
+    CreatedBy(EntityId),
+    Archived(bool),
+    /// Created at or after `from` and before `to`
+    CreatedBetween {
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+    /// Last updated at or after `from` and before `to`
+    UpdatedBetween {
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+    NameContains(String),
+    DescriptionContains(String),
+    /// Some tag contains the text
+    TagContains(String),
+    All(Vec<ProjectPredicate>),
+    Any(Vec<ProjectPredicate>),
+    Not(Box<ProjectPredicate>),
+}
+
+impl ProjectPredicate {
+    /// Match projects meeting both predicates
+    pub fn and(self, other: ProjectPredicate) -> Self {
+        match self {
+            ProjectPredicate::All(mut predicates) => {
+                predicates.push(other);
+                ProjectPredicate::All(predicates)
+            }
+            predicate => ProjectPredicate::All(vec![predicate, other]),
+        }
+    }
+
+    /// Match projects meeting either predicate
+    pub fn or(self, other: ProjectPredicate) -> Self {
+        match self {
+            ProjectPredicate::Any(mut predicates) => {
+                predicates.push(other);
+                ProjectPredicate::Any(predicates)
+            }
+            predicate => ProjectPredicate::Any(vec![predicate, other]),
+        }
+    }
+
+    /// Match projects not meeting the predicate
+    pub fn negate(self) -> Self {
+        ProjectPredicate::Not(Box::new(self))
+    }
+
+    /// Whether `aggregate` meets the predicate
+    pub fn matches(&self, aggregate: &ProjectAggregate) -> bool {
+        let project = aggregate.project();
+        match self {
+            ProjectPredicate::Status(status) => aggregate.status() == status,
+            ProjectPredicate::Priority(priority) => aggregate.priority() == priority,
+            ProjectPredicate::Tag(tag) => aggregate.tags().iter().any(|candidate| candidate.value() == tag),
+            ProjectPredicate::CreatedBy(user_id) => project.created_by.as_ref() == Some(user_id),
+            ProjectPredicate::Archived(archived) => project.is_archived == *archived,
+            ProjectPredicate::CreatedBetween { from, to } => in_range(&project.created_at, from, to),
+            ProjectPredicate::UpdatedBetween { from, to } => in_range(&project.updated_at, from, to),
+            ProjectPredicate::NameContains(text) => contains_ignore_ascii_case(&project.name, text),
+            ProjectPredicate::DescriptionContains(text) => {
+                contains_ignore_ascii_case(project.description.as_deref().unwrap_or(""), text)
+            }
+            ProjectPredicate::TagContains(text) => {
+                aggregate.tags().iter().any(|tag| contains_ignore_ascii_case(tag.value(), text))
+            }
+            ProjectPredicate::All(predicates) => predicates.iter().all(|predicate| predicate.matches(aggregate)),
+            ProjectPredicate::Any(predicates) => predicates.iter().any(|predicate| predicate.matches(aggregate)),
+            ProjectPredicate::Not(predicate) => !predicate.matches(aggregate),
+        }
+    }
+
+    /// Append the predicate as a condition on the `project_aggregates` table
+    ///
+    /// Only column names and operators are written into `sql`; every value
+    /// goes to `params` in placeholder order.
+    #[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+    pub(crate) fn write_sql(&self, sql: &mut String, params: &mut Vec<SqlParam>) {
+        match self {
+            ProjectPredicate::Status(status) => {
+                sql.push_str("status = ?");
+                params.push(SqlParam::Text(status.to_string()));
+            }
+            ProjectPredicate::Priority(priority) => {
+                sql.push_str("priority = ?");
+                params.push(SqlParam::Integer(priority_rank(priority)));
+            }
+            ProjectPredicate::Tag(tag) => {
+                sql.push_str("EXISTS (SELECT 1 FROM json_each(project_aggregates.tags) WHERE json_each.value = ?)");
+                params.push(SqlParam::Text(tag.clone()));
+            }
+            ProjectPredicate::CreatedBy(user_id) => {
+                sql.push_str("created_by IS ?");
+                params.push(SqlParam::Text(user_id.to_string()));
+            }
+            ProjectPredicate::Archived(archived) => {
+                sql.push_str("is_archived = ?");
+                params.push(SqlParam::Integer(i64::from(*archived)));
+            }
+            ProjectPredicate::CreatedBetween { from, to } => write_range_sql("created_at", from, to, sql, params),
+            ProjectPredicate::UpdatedBetween { from, to } => write_range_sql("updated_at", from, to, sql, params),
+            ProjectPredicate::NameContains(text) => {
+                sql.push_str("name LIKE ? ESCAPE '\\'");
+                params.push(SqlParam::Text(like_pattern(text)));
+            }
+            ProjectPredicate::DescriptionContains(text) => {
+                sql.push_str("COALESCE(description, '') LIKE ? ESCAPE '\\'");
+                params.push(SqlParam::Text(like_pattern(text)));
+            }
+            ProjectPredicate::TagContains(text) => {
+                sql.push_str(
+                    "EXISTS (SELECT 1 FROM json_each(project_aggregates.tags) WHERE json_each.value LIKE ? ESCAPE '\\')",
+                );
+                params.push(SqlParam::Text(like_pattern(text)));
+            }
+            ProjectPredicate::All(predicates) => write_joined_sql(predicates, " AND ", "1", sql, params),
+            ProjectPredicate::Any(predicates) => write_joined_sql(predicates, " OR ", "0", sql, params),
+            ProjectPredicate::Not(predicate) => {
+                sql.push_str("NOT (");
+                predicate.write_sql(sql, params);
+                sql.push(')');
+            }
+        }
+    }
+}
+
+/// Value bound to a placeholder of a generated query
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SqlParam {
+    Text(String),
+    Integer(i64),
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+fn write_joined_sql(
+    predicates: &[ProjectPredicate],
+    separator: &str,
+    empty: &str,
+    sql: &mut String,
+    params: &mut Vec<SqlParam>,
+) {
+    if predicates.is_empty() {
+        sql.push_str(empty);
+        return;
+    }
+
+    sql.push('(');
+    for (index, predicate) in predicates.iter().enumerate() {
+        if index > 0 {
+            sql.push_str(separator);
+        }
+        predicate.write_sql(sql, params);
+    }
+    sql.push(')');
+}
+
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+fn write_range_sql(
+    column: &str,
+    from: &Option<DateTime<Utc>>,
+    to: &Option<DateTime<Utc>>,
+    sql: &mut String,
+    params: &mut Vec<SqlParam>,
+) {
+    sql.push_str("(1");
+    if let Some(from) = from {
+        sql.push_str(&format!(" AND {} >= ?", column));
+        params.push(SqlParam::Text(writemagic_shared::sortable_datetime(from)));
+    }
+    if let Some(to) = to {
+        sql.push_str(&format!(" AND {} < ?", column));
+        params.push(SqlParam::Text(writemagic_shared::sortable_datetime(to)));
+    }
+    sql.push(')');
+}
+
+/// `LIKE` pattern matching `text` anywhere, with its wildcards escaped
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+fn like_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+fn contains_ignore_ascii_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_ascii_lowercase().contains(&needle.to_ascii_lowercase())
+}
+
+/// Timestamps are compared at the microsecond precision they are stored with
+fn in_range(timestamp: &DateTime<Utc>, from: &Option<DateTime<Utc>>, to: &Option<DateTime<Utc>>) -> bool {
+    let micros = timestamp.timestamp_micros();
+    let after_from = match from {
+        Some(from) => micros >= from.timestamp_micros(),
+        None => true,
+    };
+    let before_to = match to {
+        Some(to) => micros < to.timestamp_micros(),
+        None => true,
+    };
+    after_from && before_to
+}
+
+pub(crate) fn priority_rank(priority: &ProjectPriority) -> i64 {
+    match priority {
+        ProjectPriority::Low => 0,
+        ProjectPriority::Medium => 1,
+        ProjectPriority::High => 2,
+        ProjectPriority::Critical => 3,
+    }
+}
+
+/// Sort order of results, oldest first unless the filter says otherwise
+pub(crate) fn sort_of(filter: Option<&ProjectFilter>) -> (ProjectSortBy, SortOrder) {
+    let sort_by = filter.and_then(|filter| filter.sort_by.clone()).unwrap_or(ProjectSortBy::CreatedAt);
+    let sort_order = filter.and_then(|filter| filter.sort_order.clone()).unwrap_or(SortOrder::Ascending);
+    (sort_by, sort_order)
+}
+
+/// `ORDER BY` terms for a sort, ties broken by ascending id
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub(crate) fn order_by_sql(sort_by: &ProjectSortBy, sort_order: &SortOrder) -> String {
+    let column = match sort_by {
+        ProjectSortBy::Name => "name",
+        ProjectSortBy::CreatedAt => "created_at",
+        ProjectSortBy::UpdatedAt => "updated_at",
+        ProjectSortBy::Priority => "priority",
+        ProjectSortBy::DocumentCount => "document_count",
+        ProjectSortBy::LastActivity => "last_activity",
+    };
+    let direction = match sort_order {
+        SortOrder::Ascending => "ASC",
+        SortOrder::Descending => "DESC",
+    };
+    format!("{} {}, id ASC", column, direction)
+}
+
+/// Sort projects as `order_by_sql` would
+pub(crate) fn sort_projects(projects: &mut [ProjectAggregate], sort_by: &ProjectSortBy, sort_order: &SortOrder) {
+    projects.sort_by(|a, b| {
+        let (a_project, b_project) = (a.project(), b.project());
+        let by_key = match sort_by {
+            ProjectSortBy::Name => a_project.name.cmp(&b_project.name),
+            ProjectSortBy::CreatedAt => a_project.created_at.timestamp_micros().cmp(&b_project.created_at.timestamp_micros()),
+            ProjectSortBy::UpdatedAt => a_project.updated_at.timestamp_micros().cmp(&b_project.updated_at.timestamp_micros()),
+            ProjectSortBy::Priority => priority_rank(a.priority()).cmp(&priority_rank(b.priority())),
+            ProjectSortBy::DocumentCount => a_project.document_count().cmp(&b_project.document_count()),
+            ProjectSortBy::LastActivity => a_project.metadata.last_activity.timestamp_micros()
+                .cmp(&b_project.metadata.last_activity.timestamp_micros()),
+        };
+        let by_key = match sort_order {
+            SortOrder::Ascending => by_key,
+            SortOrder::Descending => by_key.reverse(),
+        };
+        by_key.then_with(|| compare_ids(&a.id(), &b.id()))
+    });
+}
+
+fn compare_ids(a: &EntityId, b: &EntityId) -> Ordering {
+    a.to_string().cmp(&b.to_string())
+}
+
+impl ProjectFilter {
+    /// Conditions of the filter, leaving out sorting and pagination
+    pub fn to_predicate(&self) -> ProjectPredicate {
+        let mut predicates = Vec::new();
+        if let Some(status) = &self.status {
+            predicates.push(ProjectPredicate::Status(status.clone()));
+        }
+        if let Some(priority) = &self.priority {
+            predicates.push(ProjectPredicate::Priority(priority.clone()));
+        }
+        if let Some(created_by) = self.created_by {
+            predicates.push(ProjectPredicate::CreatedBy(created_by));
+        }
+        for tag in &self.tags {
+            predicates.push(ProjectPredicate::Tag(tag.trim().to_lowercase()));
+        }
+        if self.created_after.is_some() || self.created_before.is_some() {
+            predicates.push(ProjectPredicate::CreatedBetween { from: self.created_after, to: self.created_before });
+        }
+        if self.updated_after.is_some() || self.updated_before.is_some() {
+            predicates.push(ProjectPredicate::UpdatedBetween { from: self.updated_after, to: self.updated_before });
+        }
+        if let Some(archived) = self.is_archived {
+            predicates.push(ProjectPredicate::Archived(archived));
+        }
+        ProjectPredicate::All(predicates)
+    }
+}
+
+impl ProjectSearchCriteria {
+    /// Start building criteria from combinable predicates
+    pub fn builder() -> ProjectSearchBuilder {
+        ProjectSearchBuilder::default()
+    }
+
+    /// Everything the criteria require of a project, as one predicate
+    pub fn to_predicate(&self) -> ProjectPredicate {
+        let mut predicates = Vec::new();
+
+        let query = self.query.trim();
+        if !query.is_empty() {
+            let mut fields = Vec::new();
+            if self.search_in_name {
+                fields.push(ProjectPredicate::NameContains(query.to_string()));
+            }
+            if self.search_in_description {
+                fields.push(ProjectPredicate::DescriptionContains(query.to_string()));
+            }
+            if self.search_in_tags {
+                fields.push(ProjectPredicate::TagContains(query.to_string()));
+            }
+            predicates.push(ProjectPredicate::Any(fields));
+        }
+        if let Some(filter) = &self.filter {
+            predicates.push(filter.to_predicate());
+        }
+        if let Some(predicate) = &self.predicate {
+            predicates.push(predicate.clone());
+        }
+
+        ProjectPredicate::All(predicates)
+    }
+}
+
+/// Fluent builder of [`ProjectSearchCriteria`]; every condition added must hold
+#[derive(Debug, Clone, Default)]
+pub struct ProjectSearchBuilder {
+    predicates: Vec<ProjectPredicate>,
+    sort: Option<(ProjectSortBy, SortOrder)>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl ProjectSearchBuilder {
+    pub fn status(self, status: ProjectStatus) -> Self {
+        self.matching(ProjectPredicate::Status(status))
+    }
+
+    pub fn priority(self, priority: ProjectPriority) -> Self {
+        self.matching(ProjectPredicate::Priority(priority))
+    }
+
+    /// Require a tag, normalized the way `ProjectTag` stores it
+    pub fn tag(self, tag: impl AsRef<str>) -> Self {
+        self.matching(ProjectPredicate::Tag(tag.as_ref().trim().to_lowercase()))
+    }
+
+    /// Created at or after `from` and before `to`; either end may be open
+    pub fn created_between(self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Self {
+        self.matching(ProjectPredicate::CreatedBetween { from, to })
+    }
+
+    /// Last updated at or after `from` and before `to`; either end may be open
+    pub fn updated_between(self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Self {
+        self.matching(ProjectPredicate::UpdatedBetween { from, to })
+    }
+
+    pub fn name_contains(self, text: impl Into<String>) -> Self {
+        self.matching(ProjectPredicate::NameContains(text.into()))
+    }
+
+    /// Require an arbitrary predicate, e.g. one combined with `or`
+    pub fn matching(mut self, predicate: ProjectPredicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    pub fn sort_by(mut self, sort_by: ProjectSortBy, sort_order: SortOrder) -> Self {
+        self.sort = Some((sort_by, sort_order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn build(self) -> ProjectSearchCriteria {
+        let (sort_by, sort_order) = self.sort.unzip();
+        let filter = (sort_by.is_some() || self.limit.is_some() || self.offset.is_some()).then(|| ProjectFilter {
+            limit: self.limit,
+            offset: self.offset,
+            sort_by,
+            sort_order,
+            ..ProjectFilter::default()
+        });
+
+        ProjectSearchCriteria {
+            query: String::new(),
+            search_in_name: true,
+            search_in_description: false,
+            search_in_tags: false,
+            filter,
+            predicate: (!self.predicates.is_empty()).then_some(ProjectPredicate::All(self.predicates)),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 //! Project domain services
 
-use writemagic_shared::{EntityId, WritemagicError, Result};
+use writemagic_shared::{EntityId, Repository, WritemagicError, Result};
 use crate::aggregates::{self, ProjectAggregate};
 use crate::entities::{ProjectTemplate};
 use crate::value_objects::{ProjectStatus, ProjectPriority, ProjectGoal, ProjectTag, GoalType};
-use crate::repositories::{ProjectRepository, ProjectTemplateRepository, ProjectFilter, ProjectSearchCriteria};
+use crate::repositories::{ProjectRepository, ProjectTemplateRepository, ProjectFilter, ProjectSearchCriteria, WorkspaceRepository};
+use crate::{Pane, PanePosition, PaneSize, PaneType, Workspace, WorkspaceLayout};
+use writemagic_shared::ContentType;
+use writemagic_writing::{Document, DocumentRepository};
 use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -13,6 +16,8 @@ use serde::{Deserialize, Serialize};
 pub struct ProjectManagementService {
     project_repository: Arc<dyn ProjectRepository>,
     template_repository: Arc<dyn ProjectTemplateRepository>,
+    workspace_repository: Option<Arc<dyn WorkspaceRepository>>,
+    document_repository: Option<Arc<dyn DocumentRepository>>,
 }
 
 impl ProjectManagementService {
@@ -24,8 +29,21 @@ impl ProjectManagementService {
         Self {
             project_repository,
             template_repository,
+            workspace_repository: None,
+            document_repository: None,
         }
     }
+
+    /// Enable workspace management; panes may only show documents found in `document_repository`
+    pub fn with_workspaces(
+        mut self,
+        workspace_repository: Arc<dyn WorkspaceRepository>,
+        document_repository: Arc<dyn DocumentRepository>,
+    ) -> Self {
+        self.workspace_repository = Some(workspace_repository);
+        self.document_repository = Some(document_repository);
+        self
+    }
     
     /// Create a new project
     pub async fn create_project(
@@ -229,21 +247,119 @@ impl ProjectManagementService {
         
         Ok(aggregate)
     }
+
+    fn workspace_repository(&self) -> Result<&Arc<dyn WorkspaceRepository>> {
+        self.workspace_repository
+            .as_ref()
+            .ok_or_else(|| WritemagicError::configuration("Workspace repository not configured"))
+    }
+
+    async fn load_workspace(&self, workspace_id: &EntityId) -> Result<Workspace> {
+        self.workspace_repository()?
+            .find_by_id(workspace_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found("Workspace not found"))
+    }
+
+    /// Create and persist an empty workspace
+    pub async fn create_workspace(&self, name: String, layout: WorkspaceLayout) -> Result<Workspace> {
+        if name.trim().is_empty() {
+            return Err(WritemagicError::validation("Workspace name cannot be empty"));
+        }
+
+        let workspace = Workspace::new(name, layout);
+        self.workspace_repository()?.save(&workspace).await?;
+        Ok(workspace)
+    }
+
+    /// Load a workspace by ID
+    pub async fn get_workspace(&self, workspace_id: &EntityId) -> Result<Option<Workspace>> {
+        self.workspace_repository()?.find_by_id(workspace_id).await
+    }
+
+    /// Add a pane to a workspace; a pane's document must exist and not be deleted
+    pub async fn add_pane(
+        &self,
+        workspace_id: &EntityId,
+        document_id: Option<EntityId>,
+        position: PanePosition,
+        size: PaneSize,
+    ) -> Result<Workspace> {
+        let mut workspace = self.load_workspace(workspace_id).await?;
+
+        if let Some(document_id) = document_id {
+            let document_repository = self.document_repository
+                .as_ref()
+                .ok_or_else(|| WritemagicError::configuration("Document repository not configured"))?;
+            let exists = document_repository
+                .find_by_id(&document_id)
+                .await?
+                .is_some_and(|document| !document.is_deleted);
+            if !exists {
+                return Err(WritemagicError::validation(format!(
+                    "Pane references unknown document {}", document_id
+                )));
+            }
+        }
+
+        workspace.add_pane(Pane::new(document_id, position, size));
+        self.workspace_repository()?.save(&workspace).await?;
+        Ok(workspace)
+    }
+
+    /// Remove a pane from a workspace
+    pub async fn remove_pane(&self, workspace_id: &EntityId, pane_id: &EntityId) -> Result<Workspace> {
+        let mut workspace = self.load_workspace(workspace_id).await?;
+        workspace.remove_pane(pane_id)?;
+        self.workspace_repository()?.save(&workspace).await?;
+        Ok(workspace)
+    }
+
+    /// Set the workspace's active pane
+    pub async fn set_active_pane(&self, workspace_id: &EntityId, pane_id: &EntityId) -> Result<Workspace> {
+        let mut workspace = self.load_workspace(workspace_id).await?;
+        workspace.set_active_pane(pane_id)?;
+        self.workspace_repository()?.save(&workspace).await?;
+        Ok(workspace)
+    }
 }
 
 /// Project template service - manages project templates
 pub struct ProjectTemplateService {
     template_repository: Arc<dyn ProjectTemplateRepository>,
+    project_repository: Option<Arc<dyn ProjectRepository>>,
+    workspace_repository: Option<Arc<dyn WorkspaceRepository>>,
+    document_repository: Option<Arc<dyn DocumentRepository>>,
 }
 
 impl ProjectTemplateService {
     /// Create a new template service
     pub fn new(template_repository: Arc<dyn ProjectTemplateRepository>) -> Self {
-        Self { template_repository }
+        Self {
+            template_repository,
+            project_repository: None,
+            workspace_repository: None,
+            document_repository: None,
+        }
+    }
+
+    /// Enable [`Self::instantiate`], which persists projects, their workspaces and default documents
+    pub fn with_instantiation(
+        mut self,
+        project_repository: Arc<dyn ProjectRepository>,
+        workspace_repository: Arc<dyn WorkspaceRepository>,
+        document_repository: Arc<dyn DocumentRepository>,
+    ) -> Self {
+        self.project_repository = Some(project_repository);
+        self.workspace_repository = Some(workspace_repository);
+        self.document_repository = Some(document_repository);
+        self
     }
     
     /// Create a new template
     pub async fn create_template(&self, template: ProjectTemplate) -> Result<()> {
+        template.validate_pane_layout()?;
+
         // Check if template already exists
         if self.template_repository.load_template(&template.name).await?.is_some() {
             return Err(WritemagicError::validation("Template already exists"));
@@ -266,6 +382,83 @@ impl ProjectTemplateService {
     pub async fn delete_template(&self, name: &str) -> Result<()> {
         self.template_repository.delete_template(name).await
     }
+
+    /// Create a project named `name` from a stored or built-in template, plus its
+    /// default documents and a workspace opened with the template's pane layout
+    ///
+    /// The first default document opens in the first editor pane. The project
+    /// is saved last; if any save fails, the documents and workspace already
+    /// saved are deleted again.
+    pub async fn instantiate(&self, template_id: &str, name: String) -> Result<(ProjectAggregate, Workspace)> {
+        let (project_repository, workspace_repository, document_repository) =
+            match (&self.project_repository, &self.workspace_repository, &self.document_repository) {
+                (Some(projects), Some(workspaces), Some(documents)) => (projects, workspaces, documents),
+                _ => return Err(WritemagicError::configuration("Template instantiation not configured")),
+            };
+
+        let template = match self.template_repository.load_template(template_id).await? {
+            Some(template) => template,
+            None => ProjectTemplate::built_in(template_id)
+                .ok_or_else(|| WritemagicError::not_found(format!("Template '{}' not found", template_id)))?,
+        };
+        template.validate_pane_layout()?;
+
+        let mut workspace_config = template.workspace_config;
+        if let Some(panes) = &template.pane_layout {
+            workspace_config.panes = panes.clone();
+        }
+
+        let mut aggregate = ProjectAggregate::new(name.clone(), Some(template.description), None)?;
+        aggregate.update_workspace_config(workspace_config.clone());
+        for tag in template.tags {
+            aggregate.add_tag(ProjectTag::new(tag)?)?;
+        }
+
+        let documents: Vec<Document> = template.default_documents
+            .into_iter()
+            .map(|title| Document::new(title, String::new(), ContentType::Markdown, None))
+            .collect();
+        for document in &documents {
+            aggregate.add_document(document.id, None)?;
+        }
+
+        let mut workspace = Workspace::new(name, WorkspaceLayout::Custom);
+        let mut unopened = documents.first().map(|document| document.id);
+        for config in &workspace_config.panes {
+            let document_id = if config.pane_type == PaneType::Editor { unopened.take() } else { None };
+            let mut pane = Pane::new(
+                document_id,
+                PanePosition { x: config.position.x / 100.0, y: config.position.y / 100.0 },
+                PaneSize { width: config.position.width / 100.0, height: config.position.height / 100.0 },
+            );
+            pane.pane_type = Some(config.pane_type.clone());
+            workspace.add_pane(pane);
+        }
+
+        let mut saved_documents = Vec::new();
+        let saved = async {
+            for document in &documents {
+                document_repository.save(document).await?;
+                saved_documents.push(document.id);
+            }
+            workspace_repository.save(&workspace).await?;
+            project_repository.save(&mut aggregate).await
+        }
+        .await;
+
+        if let Err(e) = saved {
+            if let Err(cleanup_error) = workspace_repository.delete(&workspace.id).await {
+                log::error!("Failed to delete workspace {} of unsaved project: {}", workspace.id, cleanup_error);
+            }
+            for document_id in saved_documents {
+                if let Err(cleanup_error) = document_repository.delete(&document_id).await {
+                    log::error!("Failed to delete document {} of unsaved project: {}", document_id, cleanup_error);
+                }
+            }
+            return Err(e);
+        }
+        Ok((aggregate, workspace))
+    }
 }
 
 /// Project analytics service - provides insights and statistics
@@ -458,7 +651,7 @@ pub struct ProductivityMetrics {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repositories::implementations::SqliteProjectRepository;
+    use crate::repositories::InMemoryProjectRepository;
     use std::sync::Arc;
 
     // Mock template repository for testing
@@ -489,7 +682,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_project() {
-        let project_repo = Arc::new(SqliteProjectRepository::new(":memory:".to_string()));
+        let project_repo = Arc::new(InMemoryProjectRepository::new());
         let template_repo = Arc::new(MockTemplateRepository);
         let service = ProjectManagementService::new(project_repo, template_repo);
         
@@ -515,7 +708,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_create_project_validation() {
-        let project_repo = Arc::new(SqliteProjectRepository::new(":memory:".to_string()));
+        let project_repo = Arc::new(InMemoryProjectRepository::new());
         let template_repo = Arc::new(MockTemplateRepository);
         let service = ProjectManagementService::new(project_repo, template_repo);
         
@@ -535,7 +728,7 @@ mod tests {
     
     #[tokio::test]
     async fn test_create_project_from_template() {
-        let project_repo = Arc::new(SqliteProjectRepository::new(":memory:".to_string()));
+        let project_repo = Arc::new(InMemoryProjectRepository::new());
         let template_repo = Arc::new(MockTemplateRepository);
         let service = ProjectManagementService::new(project_repo, template_repo);
         
@@ -569,4 +762,137 @@ mod tests {
         assert!(template.is_some());
         assert_eq!(template.unwrap().name, "Writing Project");
     }
+
+    #[tokio::test]
+    async fn test_instantiate_template_creates_project_and_workspace() {
+        use crate::entities::PaneType;
+        use crate::repositories::InMemoryWorkspaceRepository;
+        use writemagic_writing::InMemoryDocumentRepository;
+
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let workspaces = Arc::new(InMemoryWorkspaceRepository::new());
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let service = ProjectTemplateService::new(Arc::new(MockTemplateRepository))
+            .with_instantiation(projects.clone(), workspaces.clone(), documents.clone());
+
+        let (project, workspace) = service.instantiate("novel", "My Novel".to_string()).await.unwrap();
+        assert_eq!(project.project().name, "My Novel");
+        assert_eq!(workspace.name, "My Novel");
+        assert_eq!(workspace.panes.len(), 4);
+        assert_eq!(workspace.panes[1].pane_type, Some(PaneType::Editor));
+        assert_eq!(workspace.panes[1].position, PanePosition { x: 0.2, y: 0.0 });
+        assert_eq!(workspace.active_pane_id, Some(workspace.panes[0].id));
+
+        // The novel's default documents belong to the project; the first opens in the editor
+        let document_ids = &project.project().document_ids;
+        assert_eq!(document_ids.len(), 3);
+        let first = documents.find_by_id(&document_ids[0]).await.unwrap().unwrap();
+        assert_eq!(first.title, "Chapter 1");
+        assert_eq!(workspace.panes[1].document_id, Some(first.id));
+        assert_eq!(documents.count().await.unwrap(), 3);
+
+        assert!(projects.load(&project.project().id).await.unwrap().is_some());
+        assert!(workspaces.find_by_id(&workspace.id).await.unwrap().is_some());
+
+        let missing = service.instantiate("memoir", "Mine".to_string()).await;
+        assert!(matches!(missing, Err(WritemagicError::NotFound { .. })));
+
+        let unconfigured = ProjectTemplateService::new(Arc::new(MockTemplateRepository));
+        assert!(unconfigured.instantiate("blog", "Post".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_instantiation_leaves_no_workspace_or_documents() {
+        use crate::repositories::InMemoryWorkspaceRepository;
+        use writemagic_writing::InMemoryDocumentRepository;
+
+        /// Project repository refusing every save
+        struct RefusingProjectRepository(InMemoryProjectRepository);
+
+        #[async_trait::async_trait]
+        impl ProjectRepository for RefusingProjectRepository {
+            async fn save(&self, _aggregate: &mut ProjectAggregate) -> Result<()> {
+                Err(WritemagicError::database("disk full"))
+            }
+            async fn load(&self, project_id: &EntityId) -> Result<Option<ProjectAggregate>> {
+                self.0.load(project_id).await
+            }
+            async fn delete(&self, project_id: &EntityId) -> Result<()> {
+                self.0.delete(project_id).await
+            }
+            async fn list(&self, filter: ProjectFilter) -> Result<Vec<ProjectAggregate>> {
+                self.0.list(filter).await
+            }
+            async fn search(&self, criteria: ProjectSearchCriteria) -> Result<Vec<ProjectAggregate>> {
+                self.0.search(criteria).await
+            }
+            async fn get_statistics(&self, project_id: &EntityId) -> Result<crate::repositories::ProjectStatistics> {
+                self.0.get_statistics(project_id).await
+            }
+            async fn exists(&self, project_id: &EntityId) -> Result<bool> {
+                self.0.exists(project_id).await
+            }
+        }
+
+        let workspaces = Arc::new(InMemoryWorkspaceRepository::new());
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let service = ProjectTemplateService::new(Arc::new(MockTemplateRepository)).with_instantiation(
+            Arc::new(RefusingProjectRepository(InMemoryProjectRepository::new())),
+            workspaces.clone(),
+            documents.clone(),
+        );
+
+        assert!(service.instantiate("blog", "Post".to_string()).await.is_err());
+        assert!(workspaces.find_all().await.unwrap().is_empty());
+        assert_eq!(documents.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_template_rejects_overlapping_panes() {
+        let service = ProjectTemplateService::new(Arc::new(MockTemplateRepository));
+        let mut template = ProjectTemplate::screenplay_template();
+        template.pane_layout.as_mut().unwrap()[0].position.width = 30.0;
+
+        let result = service.create_template(template).await;
+        assert!(matches!(result, Err(WritemagicError::Validation { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_workspace_panes_require_existing_documents() {
+        use crate::repositories::InMemoryWorkspaceRepository;
+        use writemagic_shared::ContentType;
+        use writemagic_writing::{Document, InMemoryDocumentRepository};
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let document = Document::new("Chapter 1".to_string(), "It begins.".to_string(), ContentType::Markdown, None);
+        documents.save(&document).await.unwrap();
+
+        let service = ProjectManagementService::new(
+            Arc::new(InMemoryProjectRepository::new()),
+            Arc::new(MockTemplateRepository),
+        )
+        .with_workspaces(Arc::new(InMemoryWorkspaceRepository::new()), documents);
+
+        let workspace = service.create_workspace("Drafting".to_string(), WorkspaceLayout::Vertical).await.unwrap();
+        let full = |x| (PanePosition { x, y: 0.0 }, PaneSize { width: 0.5, height: 1.0 });
+
+        let (position, size) = full(0.0);
+        let unknown = service.add_pane(&workspace.id, Some(EntityId::new()), position, size).await;
+        assert!(matches!(unknown, Err(WritemagicError::Validation { .. })));
+
+        let (position, size) = full(0.0);
+        let workspace = service.add_pane(&workspace.id, Some(document.id), position, size).await.unwrap();
+        let (position, size) = full(0.5);
+        let workspace = service.add_pane(&workspace.id, None, position, size).await.unwrap();
+        let (first, second) = (workspace.panes[0].id, workspace.panes[1].id);
+        assert_eq!(workspace.active_pane_id, Some(first));
+
+        let workspace = service.set_active_pane(&workspace.id, &second).await.unwrap();
+        assert_eq!(workspace.active_pane_id, Some(second));
+        assert!(workspace.panes[1].is_active && !workspace.panes[0].is_active);
+
+        let workspace = service.remove_pane(&workspace.id, &second).await.unwrap();
+        assert_eq!(workspace.active_pane_id, Some(first));
+        assert_eq!(service.get_workspace(&workspace.id).await.unwrap().unwrap().panes.len(), 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::CString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use writemagic_shared::{with_pooled_buffer, EntityId};
+
+/// System allocator that counts allocations
+struct CountingAllocator {
+    allocations: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator { allocations: AtomicUsize::new(0) };
+
+/// Allocations and reallocations made while running `f`
+fn allocation_count<R>(f: impl FnOnce() -> R) -> usize {
+    let before = ALLOCATOR.allocations.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATOR.allocations.load(Ordering::Relaxed) - before
+}
+
+/// Shaped like the entries of a mobile document listing
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    id: EntityId,
+    title: String,
+    word_count: u32,
+    version: u64,
+    is_deleted: bool,
+}
+
+#[derive(Serialize)]
+struct Listing {
+    documents: Vec<Summary>,
+    count: usize,
+}
+
+fn listing() -> Listing {
+    let documents: Vec<Summary> = (0..100)
+        .map(|i| Summary {
+            id: EntityId::new(),
+            title: format!("Chapter {}", i),
+            word_count: 1200 + i,
+            version: 3,
+            is_deleted: false,
+        })
+        .collect();
+    Listing { count: documents.len(), documents }
+}
+
+fn allocating(listing: &Listing) -> CString {
+    CString::new(serde_json::to_string(listing).unwrap()).unwrap()
+}
+
+fn pooled(listing: &Listing) -> CString {
+    with_pooled_buffer(|buffer| {
+        serde_json::to_writer(&mut *buffer, listing).unwrap();
+        CString::new(buffer.as_slice()).unwrap()
+    })
+}
+
+/// Benchmark JSON responses built in a pooled buffer against fresh strings, reporting allocations of each
+fn bench_ffi_serialization(c: &mut Criterion) {
+    let listing = listing();
+
+    // Warm the pool so its first buffer isn't counted against the pooled path
+    pooled(&listing);
+    println!(
+        "allocations per listing: allocating {}, pooled {}",
+        allocation_count(|| allocating(&listing)),
+        allocation_count(|| pooled(&listing))
+    );
+
+    let mut group = c.benchmark_group("ffi_serialization");
+
+    group.bench_function("allocating", |b| {
+        b.iter(|| allocating(black_box(&listing)));
+    });
+
+    group.bench_function("pooled", |b| {
+        b.iter(|| pooled(black_box(&listing)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_ffi_serialization);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use writemagic_shared::text_processing::count_words_simd;
+
+/// Prose of roughly `len` bytes, with the odd accented word and non-breaking space
+fn prose(len: usize) -> String {
+    let paragraph = "The quick brown fox jumps over the lazy dog.\tCafé au lait,\u{a0}s'il vous plaît.\n\n";
+    paragraph.repeat(len / paragraph.len() + 1)
+}
+
+/// Benchmark SIMD word counting against `split_whitespace`
+fn bench_word_count(c: &mut Criterion) {
+    let mut group = c.benchmark_group("word_count");
+
+    for len in [1_024, 16_384, 262_144] {
+        let text = prose(len);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("simd", len), &text, |b, text| {
+            b.iter(|| count_words_simd(black_box(text)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("split_whitespace", len), &text, |b, text| {
+            b.iter(|| black_box(text).split_whitespace().count());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_word_count);
+criterion_main!(benches);
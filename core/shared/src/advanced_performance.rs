@@ -136,6 +136,51 @@ impl MappedFileMut {
         Ok(Self { mmap, len: size })
     }
     
+    /// Open an existing file for writing without truncating it, creating it if missing
+    ///
+    /// The file is grown to `min_size` bytes if it is shorter.
+    pub fn open<P: AsRef<Path>>(path: P, min_size: usize) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| WritemagicError::internal_with_source("Failed to open file", e))?;
+
+        let current = file.metadata()
+            .map_err(|e| WritemagicError::internal_with_source("Failed to read file size", e))?
+            .len() as usize;
+        let size = current.max(min_size);
+        if size > current {
+            file.set_len(size as u64)
+                .map_err(|e| WritemagicError::internal_with_source("Failed to set file size", e))?;
+        }
+
+        let mmap = unsafe {
+            MmapOptions::new()
+                .map_mut(&file)
+                .map_err(|e| WritemagicError::internal_with_source("Failed to memory-map file", e))?
+        };
+
+        Ok(Self { mmap, len: size })
+    }
+
+    /// Mapped length in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the mapping is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the entire file as a slice
+    pub fn as_slice(&self) -> &[u8] {
+        &self.mmap[..]
+    }
+
     /// Get the entire file as a mutable slice
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
         &mut self.mmap[..]
@@ -339,6 +384,22 @@ pub mod fast_serialization {
 pub mod batch_processing {
     // Remove unused import since parallel processing is not currently used
     // use rayon::prelude::*;
+    use futures::{Stream, StreamExt};
+    use std::future::Future;
+
+    /// Run `f` over `items` with at most `concurrency` futures in flight
+    ///
+    /// Results are yielded in input order, so a consumer that stops early knows
+    /// every item before the last result it saw has finished. Items are only
+    /// pulled as slots free up, which keeps pressure off pools behind `f`.
+    pub fn process_with_concurrency<I, F, Fut>(items: I, concurrency: usize, f: F) -> impl Stream<Item = Fut::Output>
+    where
+        I: IntoIterator,
+        F: FnMut(I::Item) -> Fut,
+        Fut: Future,
+    {
+        futures::stream::iter(items).map(f).buffered(concurrency.max(1))
+    }
     
     /// Process large datasets in parallel chunks with optimal memory usage
     pub struct BatchProcessor<T> {
@@ -632,6 +693,30 @@ mod tests {
         assert_eq!(results[999], 1998);
     }
     
+    #[tokio::test]
+    async fn test_process_with_concurrency_caps_in_flight() {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let results: Vec<u32> = batch_processing::process_with_concurrency(0..20u32, 3, |x| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(u64::from(20 - x))).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                x * 2
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(results, (0..20).map(|x| x * 2).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
     #[test]
     fn test_lock_free_queue() {
         let queue = lock_free::LockFreeQueue::new();
@@ -128,6 +128,21 @@ impl ArenaAllocator {
         })
     }
     
+    /// Copy up to `len` items into the arena, returning the ones copied as a slice
+    ///
+    /// The slice borrows the arena, so it can't outlive a `reset`.
+    pub fn alloc_slice<T: Copy>(&mut self, len: usize, items: impl IntoIterator<Item = T>) -> Option<&mut [T]> {
+        let ptr = self.alloc(Layout::array::<T>(len).ok()?)? as *mut T;
+        let mut written = 0;
+        for item in items.into_iter().take(len) {
+            // Safety: the arena handed out room for `len` values aligned for `T`
+            unsafe { ptr.add(written).write(item) };
+            written += 1;
+        }
+        // Safety: the first `written` values were initialized above
+        Some(unsafe { std::slice::from_raw_parts_mut(ptr, written) })
+    }
+
     /// Reset the arena, reclaiming all memory
     pub fn reset(&mut self) {
         self.position = 0;
@@ -161,19 +176,39 @@ thread_local! {
 }
 
 /// Allocate from thread-local arena
+///
+/// Returns `None` while `with_thread_arena` holds the arena.
 pub fn alloc_in_thread_arena(layout: Layout) -> Option<*mut u8> {
     THREAD_ARENA.with(|arena| {
-        arena.borrow_mut().alloc(layout)
+        arena.try_borrow_mut().ok()?.alloc(layout)
     })
 }
 
 /// Reset thread-local arena
+///
+/// Does nothing while `with_thread_arena` holds the arena, whose allocations
+/// are still in use.
 pub fn reset_thread_arena() {
     THREAD_ARENA.with(|arena| {
-        arena.borrow_mut().reset()
+        if let Ok(mut arena) = arena.try_borrow_mut() {
+            arena.reset();
+        }
     });
 }
 
+/// Run `f` with sole use of the thread-local arena, resetting it afterwards
+///
+/// Allocations borrow the arena, so none outlive the call. Returns `None`
+/// without calling `f` if the arena is already held further up the stack.
+pub fn with_thread_arena<R>(f: impl FnOnce(&mut ArenaAllocator) -> R) -> Option<R> {
+    THREAD_ARENA.with(|arena| {
+        let mut arena = arena.try_borrow_mut().ok()?;
+        let result = f(&mut arena);
+        arena.reset();
+        Some(result)
+    })
+}
+
 /// Get thread arena statistics
 pub fn thread_arena_stats() -> (usize, usize, f64) {
     THREAD_ARENA.with(|arena| {
@@ -461,4 +496,19 @@ mod tests {
         let (used_after, _, _) = thread_arena_stats();
         assert_eq!(used_after, 0);
     }
+
+    #[test]
+    fn test_scoped_thread_arena_is_held_until_the_scope_ends() {
+        let total = with_thread_arena(|arena| {
+            let values = arena.alloc_slice(4, [1u32, 2, 3, 4]).unwrap();
+            // Nobody else can allocate from or reset the arena meanwhile
+            assert!(alloc_in_thread_arena(Layout::new::<u64>()).is_none());
+            reset_thread_arena();
+            assert!(with_thread_arena(|_| ()).is_none());
+            values.iter().sum::<u32>()
+        });
+
+        assert_eq!(total, Some(10));
+        assert_eq!(thread_arena_stats().0, 0);
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 //! High-performance buffer pool for zero-allocation request processing
 
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 use smallvec::SmallVec;
 use arrayvec::ArrayVec;
 
@@ -168,4 +168,67 @@ where
         wm.reset(); // Reclaim memory for reuse
         result
     })
-}
\ No newline at end of file
+}
+
+/// Starting capacity of buffers in the shared pool
+const SHARED_BUFFER_SIZE: usize = 16 * 1024;
+/// Buffers grown past this are shrunk before going back to the shared pool
+const SHARED_BUFFER_MAX_RETAINED: usize = 1024 * 1024;
+
+static SHARED_BUFFER_POOL: OnceLock<BufferPool> = OnceLock::new();
+
+/// Process-wide pool used for building FFI responses
+pub fn shared_buffer_pool() -> &'static BufferPool {
+    SHARED_BUFFER_POOL.get_or_init(|| BufferPool::new(SHARED_BUFFER_SIZE, 4))
+}
+
+/// Execute function with an empty buffer from the shared pool
+///
+/// The buffer goes back to the pool when `f` returns, so repeated calls from
+/// any thread reuse its capacity instead of allocating.
+pub fn with_pooled_buffer<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut Vec<u8>) -> R,
+{
+    match shared_buffer_pool().acquire() {
+        Some(mut pooled) => {
+            let buffer = pooled.as_mut_vec();
+            let result = f(buffer);
+            buffer.clear();
+            if buffer.capacity() > SHARED_BUFFER_MAX_RETAINED {
+                buffer.shrink_to(SHARED_BUFFER_SIZE);
+            }
+            result
+        }
+        None => f(&mut Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled_buffers_are_reused_across_threads() {
+        let capacity = with_pooled_buffer(|buffer| {
+            assert!(buffer.is_empty());
+            buffer.extend_from_slice(b"{\"documents\":[]}");
+            buffer.capacity()
+        });
+        assert!(capacity >= SHARED_BUFFER_SIZE);
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    with_pooled_buffer(|buffer| {
+                        buffer.extend_from_slice(format!("thread {}", i).as_bytes());
+                        String::from_utf8(buffer.clone()).unwrap()
+                    })
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), format!("thread {}", i));
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Persisted checkpoints of AI session context
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use crate::{EntityId, Result, Timestamp, WritemagicError};
+
+/// Identifier of a stored context checkpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CheckpointId(pub EntityId);
+
+impl CheckpointId {
+    pub fn new() -> Self {
+        Self(EntityId::new())
+    }
+}
+
+impl Default for CheckpointId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CheckpointId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Snapshot of a session's context, serialized by the owning service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextCheckpoint {
+    pub id: CheckpointId,
+    pub session_id: EntityId,
+    pub context: String,
+    pub created_at: Timestamp,
+}
+
+/// How many checkpoints are kept, and for how long
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointRetention {
+    /// Newest checkpoints kept per session
+    pub max_per_session: usize,
+    /// Checkpoints older than this many seconds are pruned
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CheckpointRetention {
+    fn default() -> Self {
+        Self {
+            max_per_session: 10,
+            max_age_secs: None,
+        }
+    }
+}
+
+/// Storage for context checkpoints
+#[async_trait]
+pub trait ContextCheckpointStore: Send + Sync {
+    async fn save(&self, checkpoint: &ContextCheckpoint) -> Result<()>;
+
+    async fn find_by_id(&self, id: &CheckpointId) -> Result<Option<ContextCheckpoint>>;
+
+    /// Checkpoints for a session, newest first
+    async fn find_by_session(&self, session_id: &EntityId) -> Result<Vec<ContextCheckpoint>>;
+
+    async fn delete(&self, id: &CheckpointId) -> Result<bool>;
+
+    /// Delete a session's checkpoints created before `cutoff`, returning how many were removed
+    async fn delete_older_than(&self, session_id: &EntityId, cutoff: &Timestamp) -> Result<u64>;
+}
+
+/// In-memory checkpoint store for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryContextCheckpointStore {
+    checkpoints: Arc<RwLock<HashMap<CheckpointId, ContextCheckpoint>>>,
+}
+
+impl InMemoryContextCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ContextCheckpointStore for InMemoryContextCheckpointStore {
+    async fn save(&self, checkpoint: &ContextCheckpoint) -> Result<()> {
+        let mut checkpoints = self.checkpoints.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        checkpoints.insert(checkpoint.id, checkpoint.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &CheckpointId) -> Result<Option<ContextCheckpoint>> {
+        let checkpoints = self.checkpoints.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(checkpoints.get(id).cloned())
+    }
+
+    async fn find_by_session(&self, session_id: &EntityId) -> Result<Vec<ContextCheckpoint>> {
+        let checkpoints = self.checkpoints.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+
+        let mut matching: Vec<ContextCheckpoint> = checkpoints
+            .values()
+            .filter(|checkpoint| checkpoint.session_id == *session_id)
+            .cloned()
+            .collect();
+        matching.sort_by_key(|c| std::cmp::Reverse(c.created_at.as_datetime()));
+        Ok(matching)
+    }
+
+    async fn delete(&self, id: &CheckpointId) -> Result<bool> {
+        let mut checkpoints = self.checkpoints.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        Ok(checkpoints.remove(id).is_some())
+    }
+
+    async fn delete_older_than(&self, session_id: &EntityId, cutoff: &Timestamp) -> Result<u64> {
+        let mut checkpoints = self.checkpoints.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+
+        let before = checkpoints.len();
+        checkpoints.retain(|_, checkpoint| {
+            checkpoint.session_id != *session_id || checkpoint.created_at.as_datetime() >= cutoff.as_datetime()
+        });
+        Ok((before - checkpoints.len()) as u64)
+    }
+}
+
+/// SQLite-backed checkpoint store
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SqliteContextCheckpointStore {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SqliteContextCheckpointStore {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ContextCheckpoint> {
+        use sqlx::Row;
+
+        let id: String = row.get("id");
+        let session_id: String = row.get("session_id");
+        let created_at: String = row.get("created_at");
+
+        Ok(ContextCheckpoint {
+            id: CheckpointId(EntityId::from_string(&id)
+                .map_err(|e| WritemagicError::database(format!("Invalid checkpoint id: {}", e)))?),
+            session_id: EntityId::from_string(&session_id)
+                .map_err(|e| WritemagicError::database(format!("Invalid checkpoint session id: {}", e)))?,
+            context: row.get("context"),
+            created_at: Timestamp::from_string(&created_at)
+                .map_err(|e| WritemagicError::database(format!("Invalid checkpoint timestamp: {}", e)))?,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ContextCheckpointStore for SqliteContextCheckpointStore {
+    async fn save(&self, checkpoint: &ContextCheckpoint) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO context_checkpoints (id, session_id, context, created_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(checkpoint.id.to_string())
+        .bind(checkpoint.session_id.to_string())
+        .bind(&checkpoint.context)
+        .bind(checkpoint.created_at.to_sortable_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to save context checkpoint: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &CheckpointId) -> Result<Option<ContextCheckpoint>> {
+        let row = sqlx::query("SELECT * FROM context_checkpoints WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to find context checkpoint: {}", e)))?;
+
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_by_session(&self, session_id: &EntityId) -> Result<Vec<ContextCheckpoint>> {
+        let rows = sqlx::query("SELECT * FROM context_checkpoints WHERE session_id = ? ORDER BY created_at DESC")
+            .bind(session_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to list context checkpoints: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn delete(&self, id: &CheckpointId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM context_checkpoints WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to delete context checkpoint: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_older_than(&self, session_id: &EntityId, cutoff: &Timestamp) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM context_checkpoints WHERE session_id = ? AND created_at < ?")
+            .bind(session_id.to_string())
+            .bind(cutoff.to_sortable_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to prune context checkpoints: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::DatabaseManager;
+
+    fn checkpoint(session_id: EntityId, seconds_ago: i64) -> ContextCheckpoint {
+        ContextCheckpoint {
+            id: CheckpointId::new(),
+            session_id,
+            context: format!("context from {} seconds ago", seconds_ago),
+            created_at: Timestamp::from_datetime(
+                chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp() - seconds_ago, 0).unwrap(),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_round_trips_and_prunes_by_age() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let store = SqliteContextCheckpointStore::new(database.pool().clone());
+        let session_id = EntityId::new();
+
+        let old = checkpoint(session_id, 3600);
+        let recent = checkpoint(session_id, 5);
+        let other_session = checkpoint(EntityId::new(), 3600);
+        store.save(&old).await.unwrap();
+        store.save(&recent).await.unwrap();
+        store.save(&other_session).await.unwrap();
+
+        assert_eq!(store.find_by_id(&recent.id).await.unwrap(), Some(recent.clone()));
+        let listed: Vec<CheckpointId> = store.find_by_session(&session_id).await.unwrap().iter().map(|c| c.id).collect();
+        assert_eq!(listed, vec![recent.id, old.id]);
+
+        let cutoff = Timestamp::from_datetime(chrono::Utc::now() - chrono::Duration::seconds(60));
+        assert_eq!(store.delete_older_than(&session_id, &cutoff).await.unwrap(), 1);
+        assert!(store.find_by_id(&old.id).await.unwrap().is_none());
+        assert!(store.find_by_id(&other_session.id).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,520 @@
+//! Audit trail of AI completions
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+use crate::export::{write_csv_row, ExportFormat};
+use crate::{EntityId, Pagination, Result, Timestamp, WritemagicError};
+
+/// One completion served by an AI provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AICompletionRecord {
+    pub id: EntityId,
+    /// Salted SHA-256 of the prompt
+    pub prompt_hash: String,
+    /// Prompt text, only kept when plaintext storage is opted into
+    pub prompt: Option<String>,
+    pub model: String,
+    /// Provider that served the completion, after any fallback
+    pub provider: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub estimated_cost: f64,
+    /// Document the completion was made for, if any
+    pub document_id: Option<EntityId>,
+    pub created_at: Timestamp,
+}
+
+impl AICompletionRecord {
+    /// Salted SHA-256 of a prompt, hex encoded
+    pub fn hash_prompt(prompt: &str, salt: &str) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(salt.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(prompt.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn total_tokens(&self) -> u32 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Which completion records an export covers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    /// Inclusive lower bound on `created_at`
+    pub start: Option<Timestamp>,
+    /// Exclusive upper bound on `created_at`
+    pub end: Option<Timestamp>,
+    pub model: Option<String>,
+    pub document_id: Option<EntityId>,
+    /// Write prompt text for records that kept it; hashes are always written
+    #[serde(default)]
+    pub include_prompts: bool,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, record: &AICompletionRecord) -> bool {
+        let at = record.created_at.as_datetime();
+        self.start.as_ref().map_or(true, |start| at >= start.as_datetime())
+            && self.end.as_ref().map_or(true, |end| at < end.as_datetime())
+            && self.model.as_ref().map_or(true, |model| &record.model == model)
+            && self.document_id.map_or(true, |id| record.document_id == Some(id))
+    }
+}
+
+/// Records read from the repository per page while exporting
+const EXPORT_BATCH_SIZE: u32 = 500;
+
+const EXPORT_CSV_HEADER: &[&str] = &[
+    "id",
+    "created_at",
+    "model",
+    "provider",
+    "document_id",
+    "input_tokens",
+    "output_tokens",
+    "estimated_cost",
+    "prompt_hash",
+    "prompt",
+];
+
+/// A record as written by an export
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    id: EntityId,
+    created_at: &'a Timestamp,
+    model: &'a str,
+    provider: &'a str,
+    document_id: Option<EntityId>,
+    input_tokens: u32,
+    output_tokens: u32,
+    estimated_cost: f64,
+    prompt_hash: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt: Option<&'a str>,
+}
+
+impl<'a> ExportRow<'a> {
+    fn new(record: &'a AICompletionRecord, include_prompts: bool) -> Self {
+        Self {
+            id: record.id,
+            created_at: &record.created_at,
+            model: &record.model,
+            provider: &record.provider,
+            document_id: record.document_id,
+            input_tokens: record.input_tokens,
+            output_tokens: record.output_tokens,
+            estimated_cost: record.estimated_cost,
+            prompt_hash: &record.prompt_hash,
+            prompt: record.prompt.as_deref().filter(|_| include_prompts),
+        }
+    }
+
+    fn csv_fields(&self) -> [String; 10] {
+        [
+            self.id.to_string(),
+            self.created_at.to_string(),
+            self.model.to_string(),
+            self.provider.to_string(),
+            self.document_id.map(|id| id.to_string()).unwrap_or_default(),
+            self.input_tokens.to_string(),
+            self.output_tokens.to_string(),
+            self.estimated_cost.to_string(),
+            self.prompt_hash.to_string(),
+            self.prompt.unwrap_or_default().to_string(),
+        ]
+    }
+}
+
+/// Storage for completion records
+#[async_trait]
+pub trait CompletionHistoryRepository: Send + Sync {
+    async fn save(&self, record: &AICompletionRecord) -> Result<()>;
+
+    /// Records made for a document, newest first
+    async fn find_by_document(&self, document_id: &EntityId) -> Result<Vec<AICompletionRecord>>;
+
+    /// Records across all documents, newest first
+    async fn find_recent(&self, pagination: Pagination) -> Result<Vec<AICompletionRecord>>;
+
+    /// Records matching `filter`, oldest first
+    async fn find_filtered(&self, filter: &HistoryFilter, pagination: Pagination) -> Result<Vec<AICompletionRecord>>;
+
+    /// Export records matching `filter` for cost analysis or dataset extraction
+    async fn export(&self, filter: HistoryFilter, format: ExportFormat) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.write_export(&filter, format, &mut buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Stream records matching `filter` into `writer` a page at a time
+    ///
+    /// CSV output always starts with the header row and JSON output is an
+    /// array. Prompt text is written only when the filter asks for it and the
+    /// record kept it.
+    async fn write_export(
+        &self,
+        filter: &HistoryFilter,
+        format: ExportFormat,
+        writer: &mut (dyn Write + Send),
+    ) -> Result<()> {
+        match format {
+            ExportFormat::Csv => write_csv_row(writer, EXPORT_CSV_HEADER.iter().copied())?,
+            ExportFormat::Json => writer.write_all(b"[")?,
+            ExportFormat::Jsonl => {}
+        }
+
+        let mut offset = 0;
+        loop {
+            let page = self
+                .find_filtered(filter, Pagination { offset, limit: EXPORT_BATCH_SIZE })
+                .await?;
+            for (index, record) in page.iter().enumerate() {
+                let row = ExportRow::new(record, filter.include_prompts);
+                match format {
+                    ExportFormat::Csv => {
+                        write_csv_row(writer, row.csv_fields().iter().map(String::as_str))?;
+                    }
+                    ExportFormat::Json => {
+                        if offset > 0 || index > 0 {
+                            writer.write_all(b",")?;
+                        }
+                        serde_json::to_writer(&mut *writer, &row)?;
+                    }
+                    ExportFormat::Jsonl => {
+                        serde_json::to_writer(&mut *writer, &row)?;
+                        writer.write_all(b"\n")?;
+                    }
+                }
+            }
+            if (page.len() as u32) < EXPORT_BATCH_SIZE {
+                break;
+            }
+            offset += EXPORT_BATCH_SIZE;
+        }
+        if format == ExportFormat::Json {
+            writer.write_all(b"]")?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// In-memory completion history for testing and development
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryCompletionHistoryRepository {
+    records: Arc<RwLock<HashMap<EntityId, AICompletionRecord>>>,
+}
+
+impl InMemoryCompletionHistoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn newest_first(&self, filter: impl Fn(&AICompletionRecord) -> bool) -> Result<Vec<AICompletionRecord>> {
+        let records = self.records.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+
+        let mut matching: Vec<AICompletionRecord> = records.values().filter(|r| filter(r)).cloned().collect();
+        matching.sort_by_key(|r| std::cmp::Reverse(r.created_at.as_datetime()));
+        Ok(matching)
+    }
+}
+
+#[async_trait]
+impl CompletionHistoryRepository for InMemoryCompletionHistoryRepository {
+    async fn save(&self, record: &AICompletionRecord) -> Result<()> {
+        let mut records = self.records.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        records.insert(record.id, record.clone());
+        Ok(())
+    }
+
+    async fn find_by_document(&self, document_id: &EntityId) -> Result<Vec<AICompletionRecord>> {
+        self.newest_first(|record| record.document_id == Some(*document_id))
+    }
+
+    async fn find_recent(&self, pagination: Pagination) -> Result<Vec<AICompletionRecord>> {
+        Ok(self.newest_first(|_| true)?
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect())
+    }
+
+    async fn find_filtered(&self, filter: &HistoryFilter, pagination: Pagination) -> Result<Vec<AICompletionRecord>> {
+        let mut matching = self.newest_first(|record| filter.matches(record))?;
+        matching.reverse();
+        Ok(matching
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect())
+    }
+}
+
+/// SQLite-backed completion history
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SqliteCompletionHistoryRepository {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SqliteCompletionHistoryRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<AICompletionRecord> {
+        use sqlx::Row;
+
+        let id: String = row.get("id");
+        let document_id: Option<String> = row.get("document_id");
+        let created_at: String = row.get("created_at");
+        let input_tokens: i64 = row.get("input_tokens");
+        let output_tokens: i64 = row.get("output_tokens");
+
+        Ok(AICompletionRecord {
+            id: EntityId::from_string(&id)
+                .map_err(|e| WritemagicError::database(format!("Invalid completion record id: {}", e)))?,
+            prompt_hash: row.get("prompt_hash"),
+            prompt: row.get("prompt"),
+            model: row.get("model"),
+            provider: row.get("provider"),
+            input_tokens: input_tokens as u32,
+            output_tokens: output_tokens as u32,
+            estimated_cost: row.get("estimated_cost"),
+            document_id: document_id
+                .map(|id| EntityId::from_string(&id))
+                .transpose()
+                .map_err(|e| WritemagicError::database(format!("Invalid completion document id: {}", e)))?,
+            created_at: Timestamp::from_string(&created_at)
+                .map_err(|e| WritemagicError::database(format!("Invalid completion timestamp: {}", e)))?,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl CompletionHistoryRepository for SqliteCompletionHistoryRepository {
+    async fn save(&self, record: &AICompletionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO ai_completion_history
+                (id, prompt_hash, prompt, model, provider, input_tokens, output_tokens, estimated_cost, document_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(record.id.to_string())
+        .bind(&record.prompt_hash)
+        .bind(&record.prompt)
+        .bind(&record.model)
+        .bind(&record.provider)
+        .bind(record.input_tokens as i64)
+        .bind(record.output_tokens as i64)
+        .bind(record.estimated_cost)
+        .bind(record.document_id.map(|id| id.to_string()))
+        .bind(record.created_at.to_sortable_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to save completion record: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_by_document(&self, document_id: &EntityId) -> Result<Vec<AICompletionRecord>> {
+        let rows = sqlx::query("SELECT * FROM ai_completion_history WHERE document_id = ? ORDER BY created_at DESC")
+            .bind(document_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to find completion records: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn find_recent(&self, pagination: Pagination) -> Result<Vec<AICompletionRecord>> {
+        let rows = sqlx::query("SELECT * FROM ai_completion_history ORDER BY created_at DESC LIMIT ? OFFSET ?")
+            .bind(pagination.limit as i64)
+            .bind(pagination.offset as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to list completion records: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn find_filtered(&self, filter: &HistoryFilter, pagination: Pagination) -> Result<Vec<AICompletionRecord>> {
+        let start = filter.start.as_ref().map(Timestamp::to_sortable_string);
+        let end = filter.end.as_ref().map(Timestamp::to_sortable_string);
+        let document_id = filter.document_id.map(|id| id.to_string());
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM ai_completion_history
+            WHERE (?1 IS NULL OR created_at >= ?1)
+              AND (?2 IS NULL OR created_at < ?2)
+              AND (?3 IS NULL OR model = ?3)
+              AND (?4 IS NULL OR document_id = ?4)
+            ORDER BY created_at ASC, id ASC
+            LIMIT ?5 OFFSET ?6
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .bind(&filter.model)
+        .bind(document_id)
+        .bind(pagination.limit as i64)
+        .bind(pagination.offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to filter completion records: {}", e)))?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::DatabaseManager;
+
+    fn record(document_id: Option<EntityId>, seconds_ago: i64) -> AICompletionRecord {
+        AICompletionRecord {
+            id: EntityId::new(),
+            prompt_hash: AICompletionRecord::hash_prompt("Summarize this", "salt"),
+            prompt: None,
+            model: "claude-3-haiku-20240307".to_string(),
+            provider: "claude".to_string(),
+            input_tokens: 12,
+            output_tokens: 40,
+            estimated_cost: 0.0001,
+            document_id,
+            created_at: Timestamp::from_datetime(
+                chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp() - seconds_ago, 0).unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_prompt_hash_depends_on_salt() {
+        let hash = AICompletionRecord::hash_prompt("Summarize this", "salt");
+
+        assert_eq!(hash, AICompletionRecord::hash_prompt("Summarize this", "salt"));
+        assert_ne!(hash, AICompletionRecord::hash_prompt("Summarize this", "pepper"));
+        assert!(!hash.contains("Summarize"));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_history_lists_by_document_and_recency() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let history = SqliteCompletionHistoryRepository::new(database.pool().clone());
+        let document_id = EntityId::new();
+
+        let older = record(Some(document_id), 600);
+        let newer = record(Some(document_id), 5);
+        let unrelated = record(None, 60);
+        for r in [&older, &newer, &unrelated] {
+            history.save(r).await.unwrap();
+        }
+
+        let for_document = history.find_by_document(&document_id).await.unwrap();
+        assert_eq!(for_document, vec![newer.clone(), older.clone()]);
+
+        let recent: Vec<EntityId> = history.find_recent(Pagination::new(0, 2).unwrap()).await.unwrap()
+            .iter().map(|r| r.id).collect();
+        assert_eq!(recent, vec![newer.id, unrelated.id]);
+
+        let in_memory = InMemoryCompletionHistoryRepository::new();
+        for r in [&older, &newer, &unrelated] {
+            in_memory.save(r).await.unwrap();
+        }
+        assert_eq!(in_memory.find_by_document(&document_id).await.unwrap(), for_document);
+        let in_memory_recent: Vec<EntityId> = in_memory.find_recent(Pagination::new(0, 2).unwrap()).await.unwrap()
+            .iter().map(|r| r.id).collect();
+        assert_eq!(in_memory_recent, recent);
+    }
+
+    #[tokio::test]
+    async fn test_csv_export_escapes_prompt_text_and_respects_include_prompts() {
+        let history = InMemoryCompletionHistoryRepository::new();
+        let mut with_prompt = record(None, 30);
+        with_prompt.prompt = Some("Rewrite \"this\", please\nthen shorten it".to_string());
+        history.save(&with_prompt).await.unwrap();
+
+        let filter = HistoryFilter { include_prompts: true, ..HistoryFilter::default() };
+        let csv = String::from_utf8(history.export(filter, ExportFormat::Csv).await.unwrap()).unwrap();
+        let (header, row) = csv.split_once("\r\n").unwrap();
+        assert_eq!(header, EXPORT_CSV_HEADER.join(","));
+        assert!(row.ends_with(",\"Rewrite \"\"this\"\", please\nthen shorten it\"\r\n"));
+        assert!(row.starts_with(&format!("{},", with_prompt.id)));
+
+        let hashes_only = String::from_utf8(
+            history.export(HistoryFilter::default(), ExportFormat::Csv).await.unwrap(),
+        ).unwrap();
+        assert!(!hashes_only.contains("Rewrite"));
+        assert!(hashes_only.trim_end().ends_with(&format!("{},", with_prompt.prompt_hash)));
+    }
+
+    #[tokio::test]
+    async fn test_json_export_is_one_array_across_pages() {
+        let history = InMemoryCompletionHistoryRepository::new();
+        let count = EXPORT_BATCH_SIZE as usize + 2;
+        for seconds_ago in 0..count {
+            history.save(&record(None, seconds_ago as i64)).await.unwrap();
+        }
+
+        let json = history.export(HistoryFilter::default(), ExportFormat::Json).await.unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(rows.len(), count);
+
+        let empty = InMemoryCompletionHistoryRepository::new();
+        assert_eq!(empty.export(HistoryFilter::default(), ExportFormat::Json).await.unwrap(), b"[]");
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_export_filters_by_range_model_and_document() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let history = SqliteCompletionHistoryRepository::new(database.pool().clone());
+        let document_id = EntityId::new();
+
+        let old = record(Some(document_id), 3600);
+        let recent = record(Some(document_id), 60);
+        let other_model = AICompletionRecord { model: "gpt-4o-mini".to_string(), ..record(Some(document_id), 30) };
+        let other_document = record(None, 20);
+        for r in [&old, &recent, &other_model, &other_document] {
+            history.save(r).await.unwrap();
+        }
+
+        let filter = HistoryFilter {
+            start: Some(Timestamp::from_datetime(chrono::Utc::now() - chrono::Duration::minutes(10))),
+            end: Some(Timestamp::now()),
+            model: Some(recent.model.clone()),
+            document_id: Some(document_id),
+            include_prompts: false,
+        };
+        let jsonl = String::from_utf8(history.export(filter.clone(), ExportFormat::Jsonl).await.unwrap()).unwrap();
+        let ids: Vec<String> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec![recent.id.to_string()]);
+        assert!(!jsonl.contains("\"prompt\""));
+
+        let in_memory = InMemoryCompletionHistoryRepository::new();
+        for r in [&old, &recent, &other_model, &other_document] {
+            in_memory.save(r).await.unwrap();
+        }
+        assert_eq!(
+            in_memory.find_filtered(&filter, Pagination::default()).await.unwrap(),
+            history.find_filtered(&filter, Pagination::default()).await.unwrap(),
+        );
+    }
+}
@@ -1,17 +1,122 @@
 //! Database initialization and migration system
 
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{Row, SqliteConnection, SqlitePool};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 // Remove unused serde imports
-use crate::{Result, WritemagicError};
+use crate::{MetricsCollector, Result, WritemagicError};
+
+/// Default time a caller may wait for a pooled connection
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default time an idle connection above the minimum stays open when auto-scaling
+pub const DEFAULT_SCALE_DOWN_IDLE: Duration = Duration::from_secs(60);
+
+/// Default time a connection waits on another writer's lock before failing with "database is locked"
+pub const DEFAULT_BUSY_TIMEOUT_MS: u64 = 30_000;
+/// Default WAL size in pages at which SQLite checkpoints automatically, SQLite's own default
+pub const DEFAULT_WAL_AUTOCHECKPOINT_PAGES: u32 = 1000;
+
+/// How often the pool is checked for saturation
+const SATURATION_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// Minimum time between two pool saturation warnings
+const SATURATION_WARNING_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Database configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     pub database_url: String,
     pub max_connections: u32,
     pub min_connections: u32,
     pub enable_wal: bool,
     pub enable_foreign_keys: bool,
+    /// Close connections above `min_connections` once idle for `scale_down_idle`
+    ///
+    /// The pool always opens connections on demand up to `max_connections`;
+    /// this lets it shrink back. Ignored for in-memory databases, where each
+    /// connection is a separate database.
+    #[serde(default)]
+    pub auto_scale: bool,
+    /// How long a caller may wait for a connection before failing
+    #[serde(default = "default_acquire_timeout", with = "duration_as_millis")]
+    pub acquire_timeout: Duration,
+    /// How long an idle connection above the minimum stays open when auto-scaling
+    #[serde(default = "default_scale_down_idle", with = "duration_as_millis")]
+    pub scale_down_idle: Duration,
+    /// How long a connection retries while another connection holds a conflicting lock
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// WAL size in pages that triggers an automatic checkpoint; 0 disables them
+    #[serde(default = "default_wal_autocheckpoint_pages")]
+    pub wal_autocheckpoint_pages: u32,
+    #[serde(default)]
+    pub synchronous: SynchronousMode,
+    /// Apply pending migrations when the database is opened; when off, call
+    /// `DatabaseManager::run_migrations` before using the schema
+    #[serde(default = "default_auto_migrate")]
+    pub auto_migrate: bool,
+}
+
+/// How often SQLite waits for writes to reach the disk
+///
+/// With WAL, `Normal` syncs only at checkpoints: the database can never be
+/// corrupted, but transactions committed since the last checkpoint may be lost
+/// on power failure or an OS crash (not on an application crash). `Full` syncs
+/// every commit and makes them durable at the cost of write latency. `Off`
+/// hands everything to the OS and can corrupt the database on power loss.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SynchronousMode {
+    Off,
+    #[default]
+    Normal,
+    Full,
+}
+
+impl From<SynchronousMode> for sqlx::sqlite::SqliteSynchronous {
+    fn from(mode: SynchronousMode) -> Self {
+        match mode {
+            SynchronousMode::Off => Self::Off,
+            SynchronousMode::Normal => Self::Normal,
+            SynchronousMode::Full => Self::Full,
+        }
+    }
+}
+
+fn default_auto_migrate() -> bool {
+    true
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    DEFAULT_BUSY_TIMEOUT_MS
+}
+
+fn default_wal_autocheckpoint_pages() -> u32 {
+    DEFAULT_WAL_AUTOCHECKPOINT_PAGES
+}
+
+fn default_acquire_timeout() -> Duration {
+    DEFAULT_ACQUIRE_TIMEOUT
+}
+
+fn default_scale_down_idle() -> Duration {
+    DEFAULT_SCALE_DOWN_IDLE
+}
+
+mod duration_as_millis {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        (duration.as_millis() as u64).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
 }
 
 impl Default for DatabaseConfig {
@@ -22,42 +127,112 @@ impl Default for DatabaseConfig {
             min_connections: 1,
             enable_wal: true,
             enable_foreign_keys: true,
+            auto_scale: false,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            scale_down_idle: DEFAULT_SCALE_DOWN_IDLE,
+            busy_timeout_ms: DEFAULT_BUSY_TIMEOUT_MS,
+            wal_autocheckpoint_pages: DEFAULT_WAL_AUTOCHECKPOINT_PAGES,
+            synchronous: SynchronousMode::Normal,
+            auto_migrate: true,
         }
     }
 }
 
+/// Connection pool usage at one moment
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max_connections: u32,
+    /// `in_use` as a fraction of `max_connections`
+    pub utilization: f64,
+    /// Saturation checks that found every connection in use at `max_connections`,
+    /// so that further callers had to wait
+    pub saturated_checks: u64,
+}
+
+/// Watch `pool` for saturation, counting saturated checks in `saturated_checks`
+/// and warning at most once per `SATURATION_WARNING_INTERVAL`
+///
+/// Sampling the pool covers every caller, including repositories that query
+/// it directly.
+fn spawn_saturation_monitor(pool: SqlitePool, max_connections: u32, saturated_checks: Arc<AtomicU64>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SATURATION_CHECK_INTERVAL);
+        let mut last_warned: Option<Instant> = None;
+        while !pool.is_closed() {
+            interval.tick().await;
+            if pool.size() < max_connections || pool.num_idle() > 0 {
+                continue;
+            }
+
+            saturated_checks.fetch_add(1, Ordering::Relaxed);
+            if last_warned.map_or(true, |warned| warned.elapsed() >= SATURATION_WARNING_INTERVAL) {
+                last_warned = Some(Instant::now());
+                tracing::warn!(max_connections, "Database pool saturated, callers are waiting for connections");
+            }
+        }
+    })
+}
+
 /// Database manager for SQLite operations
 pub struct DatabaseManager {
     pool: SqlitePool,
-    _config: DatabaseConfig,
+    config: DatabaseConfig,
+    saturated_checks: Arc<AtomicU64>,
+    saturation_monitor: JoinHandle<()>,
 }
 
 impl DatabaseManager {
     /// Create a new database manager with configuration
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
-        let pool = if config.database_url == "sqlite::memory:" {
+        let in_memory = config.database_url == "sqlite::memory:";
+        let auto_scale = config.auto_scale && !in_memory;
+        if config.auto_scale && in_memory {
+            tracing::warn!("Pool auto-scaling is ignored for in-memory databases");
+        }
+
+        let max_connections = config.max_connections.max(1);
+        let mut options = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .min_connections(config.min_connections.min(max_connections))
+            .acquire_timeout(config.acquire_timeout);
+        if auto_scale {
+            // Idle connections above the minimum are closed as the pool shrinks
+            options = options.idle_timeout(config.scale_down_idle);
+        }
+
+        let connect_options = if in_memory {
             // Special handling for in-memory database
-            SqlitePool::connect("sqlite::memory:").await.map_err(|e| {
-                WritemagicError::database(format!("Failed to connect to database: {}", e))
+            "sqlite::memory:".parse::<SqliteConnectOptions>().map_err(|e| {
+                WritemagicError::database(format!("Invalid database URL: {}", e))
             })?
         } else {
-            SqlitePool::connect_with(
-                sqlx::sqlite::SqliteConnectOptions::new()
-                    .filename(config.database_url.replace("sqlite://", ""))
-                    .create_if_missing(true)
-                    .journal_mode(if config.enable_wal {
-                        sqlx::sqlite::SqliteJournalMode::Wal
-                    } else {
-                        sqlx::sqlite::SqliteJournalMode::Delete
-                    })
-                    .foreign_keys(config.enable_foreign_keys)
-                    .busy_timeout(std::time::Duration::from_secs(30))
-            ).await.map_err(|e| {
-                WritemagicError::database(format!("Failed to connect to database: {}", e))
-            })?
+            SqliteConnectOptions::new()
+                .filename(config.database_url.replace("sqlite://", ""))
+                .create_if_missing(true)
+                .journal_mode(if config.enable_wal {
+                    sqlx::sqlite::SqliteJournalMode::Wal
+                } else {
+                    sqlx::sqlite::SqliteJournalMode::Delete
+                })
         };
+        // Set on every pooled connection, since these PRAGMAs are per connection
+        let connect_options = connect_options
+            .foreign_keys(config.enable_foreign_keys)
+            .busy_timeout(Duration::from_millis(config.busy_timeout_ms))
+            .synchronous(config.synchronous.into())
+            .pragma("wal_autocheckpoint", config.wal_autocheckpoint_pages.to_string());
 
-        let manager = Self { pool, _config: config };
+        let pool = options.connect_with(connect_options).await.map_err(|e| {
+            WritemagicError::database(format!("Failed to connect to database: {}", e))
+        })?;
+
+        let saturated_checks = Arc::new(AtomicU64::new(0));
+        let saturation_monitor = spawn_saturation_monitor(pool.clone(), max_connections, saturated_checks.clone());
+        let manager = Self { pool, config, saturated_checks, saturation_monitor };
         
         // Run initial setup
         manager.setup().await?;
@@ -78,6 +253,7 @@ impl DatabaseManager {
             min_connections: 1,
             enable_wal: false,
             enable_foreign_keys: true,
+            ..DatabaseConfig::default()
         };
         Self::new(config).await
     }
@@ -87,6 +263,34 @@ impl DatabaseManager {
         &self.pool
     }
 
+    /// Current connection pool usage
+    pub fn pool_stats(&self) -> PoolStats {
+        let size = self.pool.size();
+        let idle = (self.pool.num_idle() as u32).min(size);
+        let in_use = size - idle;
+        let max_connections = self.config.max_connections.max(1);
+
+        PoolStats {
+            size,
+            idle,
+            in_use,
+            max_connections,
+            utilization: in_use as f64 / max_connections as f64,
+            saturated_checks: self.saturated_checks.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Publish `pool_stats` as `db_pool_*` gauges
+    pub async fn record_pool_metrics(&self, metrics: &MetricsCollector) {
+        let stats = self.pool_stats();
+        metrics.set_gauge("db_pool_connections", stats.size as f64).await;
+        metrics.set_gauge("db_pool_idle_connections", stats.idle as f64).await;
+        metrics.set_gauge("db_pool_in_use_connections", stats.in_use as f64).await;
+        metrics.set_gauge("db_pool_max_connections", stats.max_connections as f64).await;
+        metrics.set_gauge("db_pool_utilization", stats.utilization).await;
+        metrics.set_gauge("db_pool_saturated_checks_total", stats.saturated_checks as f64).await;
+    }
+
     /// Setup database with initial configuration
     async fn setup(&self) -> Result<()> {
         let mut conn = self.pool.acquire().await.map_err(|e| {
@@ -99,11 +303,6 @@ impl DatabaseManager {
             .await
             .map_err(|e| WritemagicError::database(format!("Failed to set journal mode: {}", e)))?;
 
-        sqlx::query("PRAGMA synchronous = NORMAL")
-            .execute(&mut *conn)
-            .await
-            .map_err(|e| WritemagicError::database(format!("Failed to set synchronous mode: {}", e)))?;
-
         sqlx::query("PRAGMA cache_size = 1000")
             .execute(&mut *conn)
             .await
@@ -114,15 +313,33 @@ impl DatabaseManager {
             .await
             .map_err(|e| WritemagicError::database(format!("Failed to enable foreign keys: {}", e)))?;
 
-        // Run migrations
-        self.run_migrations(&mut conn).await?;
+        self.ensure_migrations_table(&mut conn).await?;
+        if self.config.auto_migrate {
+            self.apply_migrations(&mut conn, MIGRATIONS).await?;
+        }
 
         Ok(())
     }
 
-    /// Run database migrations
-    async fn run_migrations(&self, conn: &mut SqliteConnection) -> Result<()> {
-        // Create migrations table if it doesn't exist
+    /// Apply pending migrations, returning the status of each one that ran
+    ///
+    /// Migrations already applied are skipped, so once the schema is current
+    /// this returns an empty list. Pending migrations run in one transaction:
+    /// if any fails, none are kept and the database stays at its prior schema.
+    pub async fn run_migrations(&self) -> Result<Vec<MigrationStatus>> {
+        let applied = {
+            let mut conn = self.pool.acquire().await.map_err(|e| {
+                WritemagicError::database(format!("Failed to acquire connection: {}", e))
+            })?;
+            self.ensure_migrations_table(&mut conn).await?;
+            self.apply_migrations(&mut conn, MIGRATIONS).await?
+        };
+
+        let status = self.get_migration_status().await?;
+        Ok(status.into_iter().filter(|migration| applied.contains(&migration.name.as_str())).collect())
+    }
+
+    async fn ensure_migrations_table(&self, conn: &mut SqliteConnection) -> Result<()> {
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS migrations (
@@ -135,30 +352,73 @@ impl DatabaseManager {
         .execute(&mut *conn)
         .await
         .map_err(|e| WritemagicError::database(format!("Failed to create migrations table: {}", e)))?;
+        Ok(())
+    }
 
-        // Run each migration
-        for migration in MIGRATIONS {
-            if !self.is_migration_applied(conn, migration.name).await? {
-                log::info!("Applying migration: {}", migration.name);
-                
-                // Execute migration
-                sqlx::query(migration.sql)
-                    .execute(&mut *conn)
-                    .await
-                    .map_err(|e| WritemagicError::database(format!("Failed to apply migration {}: {}", migration.name, e)))?;
-
-                // Record migration as applied
-                sqlx::query(
-                    "INSERT INTO migrations (name) VALUES (?)"
-                )
-                .bind(migration.name)
+    /// Apply the pending ones of `migrations`, returning their names
+    ///
+    /// Each migration is applied and recorded in a transaction of its own, as
+    /// SQLite cannot rebuild an FTS5 table in the transaction that created it.
+    /// A failing migration is rolled back alone, leaving the schema as the
+    /// migrations before it left it.
+    async fn apply_migrations(&self, conn: &mut SqliteConnection, migrations: &[Migration]) -> Result<Vec<&'static str>> {
+        let mut applied = Vec::new();
+        for migration in migrations {
+            if self.apply_migration(conn, migration).await? {
+                applied.push(migration.name);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Apply and record `migration` unless it already was, returning whether it ran
+    async fn apply_migration(&self, conn: &mut SqliteConnection, migration: &Migration) -> Result<bool> {
+        // IMMEDIATE takes the write lock before checking whether it is pending,
+        // so concurrent runners never apply the same migration twice
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to begin migration transaction: {}", e)))?;
+
+        let result = match self.apply_if_pending(conn, migration).await {
+            Ok(applied) => sqlx::query("COMMIT")
                 .execute(&mut *conn)
                 .await
-                .map_err(|e| WritemagicError::database(format!("Failed to record migration {}: {}", migration.name, e)))?;
+                .map(|_| applied)
+                .map_err(|e| WritemagicError::database(format!("Failed to commit migration {}: {}", migration.name, e))),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = &result {
+            if let Err(rollback_error) = sqlx::query("ROLLBACK").execute(&mut *conn).await {
+                log::error!("Failed to roll back migration {} after \"{}\": {}", migration.name, e, rollback_error);
+            } else {
+                log::warn!("Rolled back migration {}, schema left as the previous migration left it: {}", migration.name, e);
             }
         }
+        result
+    }
 
-        Ok(())
+    async fn apply_if_pending(&self, conn: &mut SqliteConnection, migration: &Migration) -> Result<bool> {
+        if self.is_migration_applied(conn, migration.name).await? {
+            return Ok(false);
+        }
+        log::info!("Applying migration: {}", migration.name);
+
+        sqlx::query(migration.sql)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to apply migration {}: {}", migration.name, e)))?;
+
+        sqlx::query(
+            "INSERT INTO migrations (name) VALUES (?)"
+        )
+        .bind(migration.name)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| WritemagicError::database(format!("Failed to record migration {}: {}", migration.name, e)))?;
+
+        Ok(true)
     }
 
     /// Check if migration has been applied
@@ -205,12 +465,36 @@ impl DatabaseManager {
         Ok(status)
     }
 
+    /// Names of migrations recorded in the database that this build does not know
+    ///
+    /// Non-empty when the database was last migrated by a newer version,
+    /// whose schema this one may read or write incorrectly.
+    pub async fn unknown_migrations(&self) -> Result<Vec<String>> {
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM migrations ORDER BY id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(format!("Failed to get migration status: {}", e)))?;
+
+        Ok(names
+            .into_iter()
+            .filter(|name| !MIGRATIONS.iter().any(|migration| migration.name == name))
+            .collect())
+    }
+
     /// Close the database connection pool
     pub async fn close(&self) {
+        self.saturation_monitor.abort();
         self.pool.close().await;
     }
 }
 
+impl Drop for DatabaseManager {
+    fn drop(&mut self) {
+        // The monitor holds a handle to the pool, so it would otherwise keep it open
+        self.saturation_monitor.abort();
+    }
+}
+
 /// Migration definition
 #[derive(Debug)]
 struct Migration {
@@ -334,4 +618,539 @@ const MIGRATIONS: &[Migration] = &[
             END;
         "#,
     },
-];
\ No newline at end of file
+    Migration {
+        name: "006_create_document_tags",
+        sql: r#"
+            CREATE TABLE document_tags (
+                document_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (document_id, tag),
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX idx_document_tags_tag ON document_tags(tag);
+        "#,
+    },
+    Migration {
+        name: "007_add_document_language",
+        sql: r#"
+            ALTER TABLE documents ADD COLUMN language TEXT NOT NULL DEFAULT 'en';
+            ALTER TABLE documents ADD COLUMN language_override TEXT;
+        "#,
+    },
+    Migration {
+        name: "008_create_context_checkpoints",
+        sql: r#"
+            CREATE TABLE context_checkpoints (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                context TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_context_checkpoints_session ON context_checkpoints(session_id, created_at);
+        "#,
+    },
+    Migration {
+        name: "009_create_document_links",
+        sql: r#"
+            CREATE TABLE document_links (
+                source_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                reference TEXT NOT NULL,
+                target_id TEXT,
+                PRIMARY KEY (source_id, position),
+                FOREIGN KEY (source_id) REFERENCES documents(id) ON DELETE CASCADE,
+                FOREIGN KEY (target_id) REFERENCES documents(id) ON DELETE SET NULL
+            );
+
+            CREATE INDEX idx_document_links_target ON document_links(target_id);
+        "#,
+    },
+    Migration {
+        name: "010_add_tenant_columns",
+        sql: r#"
+            ALTER TABLE documents ADD COLUMN tenant_id TEXT;
+            ALTER TABLE projects ADD COLUMN tenant_id TEXT;
+
+            CREATE INDEX idx_documents_tenant ON documents(tenant_id, updated_at);
+            CREATE INDEX idx_projects_tenant ON projects(tenant_id, updated_at);
+        "#,
+    },
+    Migration {
+        name: "011_add_document_pinned",
+        sql: r#"
+            ALTER TABLE documents ADD COLUMN is_pinned BOOLEAN NOT NULL DEFAULT FALSE;
+        "#,
+    },
+    Migration {
+        name: "012_add_document_generating",
+        sql: r#"
+            ALTER TABLE documents ADD COLUMN is_generating BOOLEAN NOT NULL DEFAULT FALSE;
+        "#,
+    },
+    Migration {
+        name: "013_rebuild_fts_documents",
+        sql: r#"
+            -- Contentless full-text index over title and content, keyed by document rowid
+            DROP TRIGGER IF EXISTS documents_fts_insert;
+            DROP TRIGGER IF EXISTS documents_fts_delete;
+            DROP TRIGGER IF EXISTS documents_fts_update;
+            DROP TABLE IF EXISTS documents_fts;
+
+            CREATE VIRTUAL TABLE documents_fts USING fts5(
+                title,
+                content,
+                content=''
+            );
+
+            INSERT INTO documents_fts(rowid, title, content)
+            SELECT rowid, title, content FROM documents;
+
+            CREATE TRIGGER documents_fts_insert AFTER INSERT ON documents BEGIN
+                INSERT INTO documents_fts(rowid, title, content)
+                VALUES (new.rowid, new.title, new.content);
+            END;
+
+            CREATE TRIGGER documents_fts_delete AFTER DELETE ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                VALUES ('delete', old.rowid, old.title, old.content);
+            END;
+
+            CREATE TRIGGER documents_fts_update AFTER UPDATE OF title, content ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                VALUES ('delete', old.rowid, old.title, old.content);
+                INSERT INTO documents_fts(rowid, title, content)
+                VALUES (new.rowid, new.title, new.content);
+            END;
+        "#,
+    },
+    Migration {
+        name: "014_create_ai_completion_history",
+        sql: r#"
+            CREATE TABLE ai_completion_history (
+                id TEXT PRIMARY KEY,
+                prompt_hash TEXT NOT NULL,
+                prompt TEXT,
+                model TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                estimated_cost REAL NOT NULL,
+                document_id TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_ai_completion_history_created ON ai_completion_history(created_at);
+            CREATE INDEX idx_ai_completion_history_document ON ai_completion_history(document_id, created_at);
+        "#,
+    },
+    Migration {
+        name: "015_create_workspaces",
+        sql: r#"
+            CREATE TABLE workspaces (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                layout TEXT NOT NULL,
+                panes TEXT NOT NULL DEFAULT '[]',
+                active_pane_id TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_workspaces_updated_at ON workspaces(updated_at);
+        "#,
+    },
+    Migration {
+        name: "016_add_document_encryption",
+        sql: r#"
+            ALTER TABLE documents ADD COLUMN title_nonce TEXT;
+            ALTER TABLE documents ADD COLUMN content_nonce TEXT;
+
+            CREATE TABLE encryption_settings (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                kdf_salt TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+        "#,
+    },
+    Migration {
+        name: "017_add_archiving",
+        sql: r#"
+            ALTER TABLE projects ADD COLUMN is_archived BOOLEAN NOT NULL DEFAULT FALSE;
+            ALTER TABLE projects ADD COLUMN archived_at TEXT;
+            ALTER TABLE documents ADD COLUMN is_archived BOOLEAN NOT NULL DEFAULT FALSE;
+        "#,
+    },
+    Migration {
+        name: "018_add_idempotency_keys",
+        sql: r#"
+            CREATE TABLE idempotency_keys (
+                key TEXT PRIMARY KEY,
+                document_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
+        "#,
+    },
+    Migration {
+        name: "019_create_document_versions",
+        sql: r#"
+            CREATE TABLE document_versions (
+                document_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                created_by TEXT,
+                content_nonce TEXT,
+                PRIMARY KEY (document_id, version),
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            );
+        "#,
+    },
+    Migration {
+        name: "020_create_document_locks",
+        sql: r#"
+            CREATE TABLE document_locks (
+                document_id TEXT PRIMARY KEY,
+                holder TEXT NOT NULL,
+                acquired_at TEXT NOT NULL,
+                expires_at INTEGER NOT NULL,
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            );
+        "#,
+    },
+    Migration {
+        name: "021_create_project_aggregates",
+        sql: r#"
+            CREATE TABLE project_aggregates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL,
+                priority INTEGER NOT NULL,
+                tags TEXT NOT NULL DEFAULT '[]',
+                created_by TEXT,
+                is_archived INTEGER NOT NULL DEFAULT 0,
+                document_count INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                last_activity TEXT NOT NULL,
+                aggregate TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_project_aggregates_status ON project_aggregates(status);
+            CREATE INDEX idx_project_aggregates_updated_at ON project_aggregates(updated_at);
+        "#,
+    },
+    Migration {
+        name: "022_create_search_index_rebuild",
+        sql: r#"
+            -- A full-text index rebuild in progress; at most one row
+            CREATE TABLE search_index_rebuild (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                max_rowid INTEGER NOT NULL,
+                started_at TEXT NOT NULL
+            );
+
+            -- Documents already re-indexed, so an interrupted rebuild can resume
+            CREATE TABLE search_index_rebuilt_documents (
+                document_rowid INTEGER PRIMARY KEY
+            );
+        "#,
+    },
+    Migration {
+        name: "023_create_document_templates",
+        sql: r#"
+            CREATE TABLE document_templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                body TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                required_variables TEXT NOT NULL DEFAULT '[]',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_document_templates_name ON document_templates(name);
+        "#,
+    },
+    Migration {
+        name: "024_add_project_system_prompt",
+        sql: r#"
+            ALTER TABLE projects ADD COLUMN system_prompt TEXT;
+        "#,
+    },
+    Migration {
+        name: "025_keep_encrypted_documents_out_of_fts",
+        sql: r#"
+            -- Encrypted rows carry a nonce; their ciphertext must not be indexed
+            INSERT INTO documents_fts(documents_fts, rowid, title, content)
+            SELECT 'delete', rowid, title, content FROM documents
+            WHERE title_nonce IS NOT NULL OR content_nonce IS NOT NULL;
+
+            DROP TRIGGER IF EXISTS documents_fts_insert;
+            DROP TRIGGER IF EXISTS documents_fts_update;
+            DROP TRIGGER IF EXISTS documents_fts_delete;
+
+            CREATE TRIGGER documents_fts_insert AFTER INSERT ON documents
+            WHEN new.title_nonce IS NULL AND new.content_nonce IS NULL BEGIN
+                INSERT INTO documents_fts(rowid, title, content)
+                VALUES (new.rowid, new.title, new.content);
+            END;
+
+            CREATE TRIGGER documents_fts_delete AFTER DELETE ON documents
+            WHEN old.title_nonce IS NULL AND old.content_nonce IS NULL BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                VALUES ('delete', old.rowid, old.title, old.content);
+            END;
+
+            -- A plaintext row that gets encrypted leaves the index, and one that gets decrypted joins it
+            CREATE TRIGGER documents_fts_update AFTER UPDATE OF title, content, title_nonce, content_nonce ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                SELECT 'delete', old.rowid, old.title, old.content
+                WHERE old.title_nonce IS NULL AND old.content_nonce IS NULL;
+                INSERT INTO documents_fts(rowid, title, content)
+                SELECT new.rowid, new.title, new.content
+                WHERE new.title_nonce IS NULL AND new.content_nonce IS NULL;
+            END;
+
+            -- A value sealed under the key, so a wrong passphrase is caught before any write
+            ALTER TABLE encryption_settings ADD COLUMN key_check TEXT;
+            ALTER TABLE encryption_settings ADD COLUMN key_check_nonce TEXT;
+        "#,
+    },
+    Migration {
+        name: "026_index_edits_during_search_index_rebuild",
+        sql: r#"
+            -- While a rebuild is in progress only documents it or an edit has
+            -- re-indexed are in the index, so only those may be deleted from
+            -- it, and an edit that indexes a document marks it re-indexed
+            DROP TRIGGER IF EXISTS documents_fts_insert;
+            DROP TRIGGER IF EXISTS documents_fts_update;
+            DROP TRIGGER IF EXISTS documents_fts_delete;
+
+            CREATE TRIGGER documents_fts_insert AFTER INSERT ON documents BEGIN
+                INSERT INTO documents_fts(rowid, title, content)
+                SELECT new.rowid, new.title, new.content
+                WHERE new.title_nonce IS NULL AND new.content_nonce IS NULL;
+                INSERT OR IGNORE INTO search_index_rebuilt_documents (document_rowid)
+                SELECT new.rowid WHERE EXISTS (SELECT 1 FROM search_index_rebuild);
+            END;
+
+            CREATE TRIGGER documents_fts_delete AFTER DELETE ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                SELECT 'delete', old.rowid, old.title, old.content
+                WHERE old.title_nonce IS NULL AND old.content_nonce IS NULL
+                  AND (NOT EXISTS (SELECT 1 FROM search_index_rebuild)
+                       OR old.rowid IN (SELECT document_rowid FROM search_index_rebuilt_documents));
+                DELETE FROM search_index_rebuilt_documents WHERE document_rowid = old.rowid;
+            END;
+
+            CREATE TRIGGER documents_fts_update AFTER UPDATE OF title, content, title_nonce, content_nonce ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content)
+                SELECT 'delete', old.rowid, old.title, old.content
+                WHERE old.title_nonce IS NULL AND old.content_nonce IS NULL
+                  AND (NOT EXISTS (SELECT 1 FROM search_index_rebuild)
+                       OR old.rowid IN (SELECT document_rowid FROM search_index_rebuilt_documents));
+                INSERT INTO documents_fts(rowid, title, content)
+                SELECT new.rowid, new.title, new.content
+                WHERE new.title_nonce IS NULL AND new.content_nonce IS NULL;
+                INSERT OR IGNORE INTO search_index_rebuilt_documents (document_rowid)
+                SELECT new.rowid WHERE EXISTS (SELECT 1 FROM search_index_rebuild);
+            END;
+        "#,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unmigrated_in_memory() -> DatabaseConfig {
+        DatabaseConfig {
+            database_url: "sqlite::memory:".to_string(),
+            max_connections: 1,
+            min_connections: 1,
+            enable_wal: false,
+            auto_migrate: false,
+            ..DatabaseConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_migrations_applies_pending_once() {
+        let manager = DatabaseManager::new(unmigrated_in_memory()).await.unwrap();
+        assert!(manager.get_migration_status().await.unwrap().iter().all(|m| !m.applied));
+
+        let applied = manager.run_migrations().await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(applied.iter().all(|m| m.applied && m.applied_at.is_some()));
+        assert_eq!(applied[0].name, "001_create_documents");
+
+        assert!(manager.run_migrations().await.unwrap().is_empty());
+        sqlx::query("SELECT COUNT(*) FROM documents").fetch_one(manager.pool()).await.unwrap();
+
+        let opened = DatabaseManager::new_in_memory().await.unwrap();
+        assert!(opened.run_migrations().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migrations_from_a_newer_version_are_reported() {
+        let manager = DatabaseManager::new_in_memory().await.unwrap();
+        assert!(manager.unknown_migrations().await.unwrap().is_empty());
+
+        sqlx::query("INSERT INTO migrations (name) VALUES ('999_from_the_future')")
+            .execute(manager.pool())
+            .await
+            .unwrap();
+        assert_eq!(manager.unknown_migrations().await.unwrap(), vec!["999_from_the_future"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back_alone() {
+        let manager = DatabaseManager::new(unmigrated_in_memory()).await.unwrap();
+        let broken = [
+            Migration { name: "900_create_drafts", sql: "CREATE TABLE drafts (id TEXT PRIMARY KEY)" },
+            Migration { name: "901_broken", sql: "CREATE TABLE notes (id TEXT PRIMARY KEY); ALTER TABLE missing_table ADD COLUMN body TEXT" },
+        ];
+
+        let mut conn = manager.pool().acquire().await.unwrap();
+        let error = manager.apply_migrations(&mut conn, &broken).await.unwrap_err();
+        assert!(error.to_string().contains("901_broken"));
+
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE name IN ('drafts', 'notes')")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        let recorded: Vec<String> = sqlx::query_scalar("SELECT name FROM migrations").fetch_all(&mut *conn).await.unwrap();
+        assert_eq!(tables, vec!["drafts"]);
+        assert_eq!(recorded, vec!["900_create_drafts"]);
+
+        // The connection is usable again once the failed migration is rolled back
+        let applied = manager.apply_migrations(&mut conn, &broken[..1]).await.unwrap();
+        assert!(applied.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pool_opens_connections_on_demand_up_to_the_maximum() {
+        let path = std::env::temp_dir().join(format!("writemagic-pool-{}.db", uuid::Uuid::new_v4()));
+        let manager = DatabaseManager::new(DatabaseConfig {
+            database_url: format!("sqlite://{}", path.display()),
+            max_connections: 2,
+            min_connections: 1,
+            auto_scale: true,
+            acquire_timeout: Duration::from_millis(50),
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+
+        let first = manager.pool().acquire().await.unwrap();
+        let second = manager.pool().acquire().await.unwrap();
+        let stats = manager.pool_stats();
+        assert_eq!((stats.size, stats.in_use, stats.max_connections), (2, 2, 2));
+        assert_eq!(stats.utilization, 1.0);
+
+        // At the maximum, a third caller waits out `acquire_timeout`
+        assert!(manager.pool().acquire().await.is_err());
+        tokio::time::sleep(SATURATION_CHECK_INTERVAL * 2).await;
+        assert!(manager.pool_stats().saturated_checks > 0);
+
+        drop(first);
+        drop(second);
+        // Dropped connections go back to the pool on a spawned task
+        for _ in 0..20 {
+            if manager.pool_stats().in_use == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(manager.pool_stats().in_use, 0);
+
+        manager.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_tuning_pragmas_apply_to_every_connection() {
+        let path = std::env::temp_dir().join(format!("writemagic-pragmas-{}.db", uuid::Uuid::new_v4()));
+        let manager = DatabaseManager::new(DatabaseConfig {
+            database_url: format!("sqlite://{}", path.display()),
+            max_connections: 2,
+            busy_timeout_ms: 1234,
+            wal_autocheckpoint_pages: 250,
+            synchronous: SynchronousMode::Full,
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+
+        let mut first = manager.pool().acquire().await.unwrap();
+        let mut second = manager.pool().acquire().await.unwrap();
+        for conn in [&mut first, &mut second] {
+            let busy: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&mut **conn).await.unwrap();
+            let checkpoint: i64 = sqlx::query_scalar("PRAGMA wal_autocheckpoint").fetch_one(&mut **conn).await.unwrap();
+            let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous").fetch_one(&mut **conn).await.unwrap();
+            // FULL is reported as 2
+            assert_eq!((busy, checkpoint, synchronous), (1234, 250, 2));
+        }
+
+        drop((first, second));
+        manager.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_busy_timeout_lets_parallel_writers_wait_for_the_lock() {
+        let path = std::env::temp_dir().join(format!("writemagic-busy-{}.db", uuid::Uuid::new_v4()));
+        let manager = DatabaseManager::new(DatabaseConfig {
+            database_url: format!("sqlite://{}", path.display()),
+            max_connections: 8,
+            busy_timeout_ms: 5_000,
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        sqlx::query("CREATE TABLE counters (writer INTEGER NOT NULL, n INTEGER NOT NULL)")
+            .execute(manager.pool())
+            .await
+            .unwrap();
+
+        let writers = (0..8).map(|writer| {
+            let pool = manager.pool().clone();
+            tokio::spawn(async move {
+                for n in 0..20 {
+                    // Each write takes the lock up front and holds it across a yield,
+                    // so the writers contend for it
+                    let mut conn = pool.acquire().await?;
+                    sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+                    sqlx::query("INSERT INTO counters (writer, n) VALUES (?, ?)")
+                        .bind(writer)
+                        .bind(n)
+                        .execute(&mut *conn)
+                        .await?;
+                    tokio::task::yield_now().await;
+                    sqlx::query("COMMIT").execute(&mut *conn).await?;
+                }
+                Ok::<_, sqlx::Error>(())
+            })
+        });
+        for writer in futures::future::join_all(writers).await {
+            writer.unwrap().expect("writer should wait for the lock instead of failing");
+        }
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM counters")
+            .fetch_one(manager.pool())
+            .await
+            .unwrap();
+        assert_eq!(rows, 8 * 20);
+
+        manager.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+}
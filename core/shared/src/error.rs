@@ -1,7 +1,7 @@
 //! Shared error types and handling
 
 use thiserror::Error;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::backtrace::Backtrace;
 
 /// Structured error response for APIs
@@ -48,6 +48,19 @@ impl ErrorCode {
     }
 }
 
+/// Structured details of an error returned by an AI provider API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderError {
+    /// HTTP status returned by the provider
+    pub status: u16,
+    /// Provider-specific error code or type, e.g. `content_policy_violation`
+    pub provider_code: Option<String>,
+    /// Human readable message from the provider
+    pub message: String,
+    /// Whether retrying or falling back to another provider may succeed
+    pub retryable: bool,
+}
+
 /// Main error type for WriteMagic operations
 #[derive(Error, Debug)]
 pub enum WritemagicError {
@@ -58,7 +71,10 @@ pub enum WritemagicError {
     Repository { message: String },
 
     #[error("AI provider error: {message}")]
-    AiProvider { message: String },
+    AiProvider {
+        message: String,
+        details: Option<Box<ProviderError>>,
+    },
 
     #[error("Git operation error: {message}")]
     Git { message: String },
@@ -113,8 +129,29 @@ pub enum WritemagicError {
     #[error("Version conflict: {message}")]
     VersionConflict { message: String },
 
+    #[error("Conflict: {message}")]
+    Conflict { message: String },
+
     #[error("Feature not implemented: {message}")]
     NotImplemented { message: String },
+
+    #[error("Feature disabled: {feature}")]
+    FeatureDisabled { feature: String },
+
+    #[error("Storage quota exceeded: {message}")]
+    StorageQuotaExceeded { message: String },
+
+    #[error("AI budget exceeded: ${spent_usd:.2} spent of ${budget_usd:.2} this month")]
+    BudgetExceeded { spent_usd: f64, budget_usd: f64 },
+
+    #[error("Circuit breaker open: {name}")]
+    CircuitOpen { name: String },
+
+    #[error("Workflow step '{step}' failed: {source}")]
+    WorkflowStepFailed {
+        step: String,
+        source: Box<WritemagicError>,
+    },
 }
 
 /// Result type alias for WriteMagic operations
@@ -136,6 +173,14 @@ impl WritemagicError {
     pub fn ai_provider(message: impl Into<String>) -> Self {
         Self::AiProvider {
             message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn ai_provider_with_details(message: impl Into<String>, details: ProviderError) -> Self {
+        Self::AiProvider {
+            message: message.into(),
+            details: Some(Box::new(details)),
         }
     }
 
@@ -218,18 +263,90 @@ impl WritemagicError {
         }
     }
 
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict {
+            message: message.into(),
+        }
+    }
+
     pub fn not_implemented(message: impl Into<String>) -> Self {
         Self::NotImplemented {
             message: message.into(),
         }
     }
 
+    pub fn feature_disabled(feature: impl Into<String>) -> Self {
+        Self::FeatureDisabled {
+            feature: feature.into(),
+        }
+    }
+
+    pub fn storage_quota_exceeded(message: impl Into<String>) -> Self {
+        Self::StorageQuotaExceeded {
+            message: message.into(),
+        }
+    }
+
+    pub fn budget_exceeded(spent_usd: f64, budget_usd: f64) -> Self {
+        Self::BudgetExceeded { spent_usd, budget_usd }
+    }
+
+    pub fn circuit_open(name: impl Into<String>) -> Self {
+        Self::CircuitOpen { name: name.into() }
+    }
+
+    pub fn workflow_step_failed(step: impl ToString, source: WritemagicError) -> Self {
+        Self::WorkflowStepFailed {
+            step: step.to_string(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Name of the workflow step that failed, for step failures
+    pub fn failed_step(&self) -> Option<&str> {
+        match self {
+            Self::WorkflowStepFailed { step, .. } => Some(step),
+            _ => None,
+        }
+    }
+
+    /// Structured provider details attached to an AI provider error
+    pub fn provider_error(&self) -> Option<&ProviderError> {
+        match self {
+            Self::AiProvider { details, .. } => details.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if tried again
+    ///
+    /// Provider errors follow the provider's own classification; provider
+    /// errors without details are assumed transient.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::AiProvider { details: Some(details), .. } => details.retryable,
+            Self::AiProvider { details: None, .. }
+            | Self::Network { .. }
+            | Self::Timeout { .. }
+            | Self::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the provider refused this particular request, e.g. for content policy
+    ///
+    /// Such a request fails the same way everywhere and says nothing about
+    /// the provider's health.
+    pub fn is_request_rejection(&self) -> bool {
+        self.provider_error().is_some_and(|details| !details.retryable)
+    }
+
     /// Get error message for debugging and testing
     pub fn message(&self) -> String {
         match self {
             Self::Validation { message } => message.clone(),
             Self::Repository { message } => message.clone(),
-            Self::AiProvider { message } => message.clone(),
+            Self::AiProvider { message, .. } => message.clone(),
             Self::Git { message } => message.clone(),
             Self::Database { message } => message.clone(),
             Self::Authentication { message } => message.clone(),
@@ -239,7 +356,13 @@ impl WritemagicError {
             Self::Internal { message, .. } => message.clone(),
             Self::NotFound { resource } => resource.clone(),
             Self::VersionConflict { message } => message.clone(),
+            Self::Conflict { message } => message.clone(),
             Self::NotImplemented { message } => message.clone(),
+            Self::FeatureDisabled { feature } => format!("Feature disabled: {}", feature),
+            Self::StorageQuotaExceeded { message } => message.clone(),
+            Self::BudgetExceeded { .. } => self.to_string(),
+            Self::CircuitOpen { .. } => self.to_string(),
+            Self::WorkflowStepFailed { step, source } => format!("{}: {}", step, source.message()),
             Self::Io { source } => source.to_string(),
             Self::Serialization { source } => source.to_string(),
             Self::Timeout { timeout_ms } => format!("Request timeout after {}ms", timeout_ms),
@@ -252,6 +375,13 @@ impl WritemagicError {
 
     /// Convert to structured error response
     pub fn to_error_response(&self, request_id: Option<String>) -> ErrorResponse {
+        if let Self::WorkflowStepFailed { step, source } = self {
+            let mut response = source.to_error_response(request_id);
+            response.message = self.to_string();
+            response.details = Some(serde_json::json!({ "step": step }));
+            return response;
+        }
+
         let (code, details) = match self {
             Self::Validation { .. } => (ErrorCode::ValidationFailed, None),
             Self::Authentication { .. } => (ErrorCode::Unauthorized, None),
@@ -267,12 +397,29 @@ impl WritemagicError {
                     "window_seconds": window_seconds
                 }))
             ),
+            Self::AiProvider { details: Some(details), .. } => (
+                if details.retryable { ErrorCode::ServiceUnavailable } else { ErrorCode::InvalidRequest },
+                Some(serde_json::json!({ "provider_error": details }))
+            ),
             Self::Network { .. } | Self::AiProvider { .. } => (
                 ErrorCode::ServiceUnavailable, 
                 None
             ),
-            Self::VersionConflict { .. } => (ErrorCode::Conflict, None),
+            Self::VersionConflict { .. } | Self::Conflict { .. } => (ErrorCode::Conflict, None),
             Self::NotImplemented { .. } => (ErrorCode::ServiceUnavailable, None),
+            Self::FeatureDisabled { feature } => (
+                ErrorCode::ServiceUnavailable,
+                Some(serde_json::json!({ "feature": feature }))
+            ),
+            Self::StorageQuotaExceeded { .. } => (ErrorCode::ServiceUnavailable, None),
+            Self::CircuitOpen { name } => (
+                ErrorCode::ServiceUnavailable,
+                Some(serde_json::json!({ "circuit_breaker": name }))
+            ),
+            Self::BudgetExceeded { spent_usd, budget_usd } => (
+                ErrorCode::Forbidden,
+                Some(serde_json::json!({ "spent_usd": spent_usd, "budget_usd": budget_usd }))
+            ),
             _ => (ErrorCode::InternalError, None),
         };
 
@@ -77,4 +77,26 @@ mod tests {
         let error = caller().await.unwrap_err();
         assert!(matches!(error, WritemagicError::Timeout { .. }));
     }
-}
\ No newline at end of file
+
+    /// Test provider error details survive into the structured response
+    #[test]
+    fn test_provider_error_details_in_response() {
+        let error = WritemagicError::ai_provider_with_details(
+            "OpenAI API error (400): Request rejected",
+            crate::ProviderError {
+                status: 400,
+                provider_code: Some("content_policy_violation".to_string()),
+                message: "Request rejected".to_string(),
+                retryable: false,
+            },
+        );
+
+        assert_eq!(error.provider_error().map(|d| d.status), Some(400));
+
+        let response = error.to_error_response(None);
+        assert_eq!(response.code, crate::ErrorCode::InvalidRequest);
+        let details = response.details.unwrap();
+        assert_eq!(details["provider_error"]["provider_code"], "content_policy_violation");
+        assert_eq!(details["provider_error"]["retryable"], false);
+    }
+}
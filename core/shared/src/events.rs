@@ -5,9 +5,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use crate::{EntityId, Result};
+use crate::{EntityId, Result, WritemagicError};
 
 /// Base trait for all domain events
 pub trait DomainEvent: Send + Sync + std::fmt::Debug + Any {
@@ -68,6 +69,106 @@ pub trait EventStore: Send + Sync {
     
     /// Get the current version of an aggregate
     async fn get_aggregate_version(&self, aggregate_id: EntityId) -> Result<u64>;
+
+    /// Load the events recorded after `version`, oldest first
+    ///
+    /// Versions start at 1, so `load_events_since(id, 0)` returns the whole stream.
+    async fn load_events_since(&self, aggregate_id: EntityId, version: u64) -> Result<Vec<Box<dyn DomainEvent>>> {
+        self.load_events(aggregate_id, version + 1).await
+    }
+}
+
+/// Builds a read model by applying events from an event stream
+#[async_trait]
+pub trait ReadModelProjector: Send + Sync {
+    /// Apply one event, ignoring events the read model does not track
+    async fn apply(&self, event: &dyn DomainEvent) -> Result<()>;
+
+    /// Apply `events` in order, returning how many were applied
+    async fn replay(&self, events: &[Box<dyn DomainEvent>]) -> Result<usize> {
+        for event in events {
+            self.apply(event.as_ref()).await?;
+        }
+        Ok(events.len())
+    }
+
+    /// Rebuild one aggregate's read model from its full stream in `store`
+    async fn rebuild(&self, store: &dyn EventStore, aggregate_id: EntityId) -> Result<usize> {
+        let events = store.load_events_since(aggregate_id, 0).await?;
+        self.replay(&events).await
+    }
+}
+
+/// In-memory event store for events of type `E`
+///
+/// Each aggregate's stream is versioned by position, starting at 1, and saves
+/// are rejected unless `expected_version` matches the stream's current version.
+pub struct InMemoryEventStore<E> {
+    streams: RwLock<HashMap<EntityId, Vec<E>>>,
+}
+
+impl<E: DomainEvent + Clone> InMemoryEventStore<E> {
+    pub fn new() -> Self {
+        Self {
+            streams: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Ids of every aggregate with recorded events
+    pub async fn aggregate_ids(&self) -> Vec<EntityId> {
+        self.streams.read().await.keys().copied().collect()
+    }
+}
+
+impl<E: DomainEvent + Clone> Default for InMemoryEventStore<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<E: DomainEvent + Clone> EventStore for InMemoryEventStore<E> {
+    async fn save_events(&self, aggregate_id: EntityId, events: Vec<Box<dyn DomainEvent>>, expected_version: u64) -> Result<()> {
+        let typed = events
+            .iter()
+            .map(|event| {
+                event.as_any().downcast_ref::<E>().cloned().ok_or_else(|| {
+                    WritemagicError::validation(format!("Unsupported event type for this store: {}", event.event_type()))
+                })
+            })
+            .collect::<Result<Vec<E>>>()?;
+
+        let mut streams = self.streams.write().await;
+        let stream = streams.entry(aggregate_id).or_default();
+        if stream.len() as u64 != expected_version {
+            return Err(WritemagicError::version_conflict(format!(
+                "Event stream version conflict: expected {}, found {}",
+                expected_version,
+                stream.len()
+            )));
+        }
+        stream.extend(typed);
+        Ok(())
+    }
+
+    async fn load_events(&self, aggregate_id: EntityId, from_version: u64) -> Result<Vec<Box<dyn DomainEvent>>> {
+        let streams = self.streams.read().await;
+        let skip = from_version.saturating_sub(1) as usize;
+        Ok(streams
+            .get(&aggregate_id)
+            .map(|stream| {
+                stream
+                    .iter()
+                    .skip(skip)
+                    .map(|event| Box::new(event.clone()) as Box<dyn DomainEvent>)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn get_aggregate_version(&self, aggregate_id: EntityId) -> Result<u64> {
+        Ok(self.streams.read().await.get(&aggregate_id).map_or(0, |stream| stream.len() as u64))
+    }
 }
 
 /// Base implementation for domain events
@@ -118,9 +219,17 @@ pub trait EventSourcedAggregate: Send + Sync {
     fn load_from_history(events: Vec<Self::Event>) -> Self;
 }
 
+/// One handler's subscription to an [`InMemoryEventBus`], for removing just that handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Subscribed handlers by the event type they handle
+type HandlerMap = HashMap<TypeId, Vec<(SubscriptionId, DynEventHandler)>>;
+
 /// In-memory event bus implementation
 pub struct InMemoryEventBus {
-    handlers: Arc<RwLock<HashMap<TypeId, Vec<DynEventHandler>>>>,
+    handlers: Arc<RwLock<HandlerMap>>,
+    next_subscription: AtomicU64,
 }
 
 impl InMemoryEventBus {
@@ -128,8 +237,32 @@ impl InMemoryEventBus {
     pub fn new() -> Self {
         Self {
             handlers: Arc::new(RwLock::new(HashMap::new())),
+            next_subscription: AtomicU64::new(1),
         }
     }
+
+    /// Subscribe a handler to events of a specific type, returning its subscription
+    pub async fn add_subscription(&self, event_type: TypeId, handler: DynEventHandler) -> SubscriptionId {
+        let id = SubscriptionId(self.next_subscription.fetch_add(1, Ordering::Relaxed));
+        let mut handlers = self.handlers.write().await;
+        handlers.entry(event_type).or_default().push((id, handler));
+        id
+    }
+
+    /// Remove one subscription, leaving other handlers of its event type in place
+    ///
+    /// Returns whether the subscription was still active.
+    pub async fn remove_subscription(&self, id: SubscriptionId) -> bool {
+        let mut handlers = self.handlers.write().await;
+        let mut removed = false;
+        handlers.retain(|_, event_handlers| {
+            let before = event_handlers.len();
+            event_handlers.retain(|(subscription, _)| *subscription != id);
+            removed |= event_handlers.len() != before;
+            !event_handlers.is_empty()
+        });
+        removed
+    }
     
     /// Type-safe helper for publishing events
     pub async fn publish_typed<T: DomainEvent + 'static>(&self, event: T) -> Result<()> {
@@ -141,15 +274,30 @@ impl InMemoryEventBus {
     where
         F: Fn(&T) -> Result<()> + Send + Sync + 'static,
     {
-        let handler = Arc::new(move |event: &dyn DomainEvent| {
-            if let Some(typed_event) = event.as_any().downcast_ref::<T>() {
-                handler(typed_event)
-            } else {
-                Ok(())
-            }
-        });
-        self.subscribe(TypeId::of::<T>(), handler).await
+        self.subscribe(TypeId::of::<T>(), typed_handler(handler)).await
     }
+
+    /// Type-safe [`InMemoryEventBus::add_subscription`]
+    pub async fn add_typed_subscription<T: DomainEvent + 'static, F>(&self, handler: F) -> SubscriptionId
+    where
+        F: Fn(&T) -> Result<()> + Send + Sync + 'static,
+    {
+        self.add_subscription(TypeId::of::<T>(), typed_handler(handler)).await
+    }
+}
+
+/// Handler calling `handler` with the events that are a `T`
+fn typed_handler<T: DomainEvent + 'static, F>(handler: F) -> DynEventHandler
+where
+    F: Fn(&T) -> Result<()> + Send + Sync + 'static,
+{
+    Arc::new(move |event: &dyn DomainEvent| {
+        if let Some(typed_event) = event.as_any().downcast_ref::<T>() {
+            handler(typed_event)
+        } else {
+            Ok(())
+        }
+    })
 }
 
 impl Default for InMemoryEventBus {
@@ -165,7 +313,7 @@ impl EventBus for InMemoryEventBus {
         let handlers = self.handlers.read().await;
         
         if let Some(event_handlers) = handlers.get(&event_type_id) {
-            for handler in event_handlers {
+            for (_, handler) in event_handlers {
                 if let Err(e) = handler(event.as_ref()) {
                     // Log error but continue with other handlers
                     tracing::error!("Error handling event {}: {}", event.event_type(), e);
@@ -177,8 +325,7 @@ impl EventBus for InMemoryEventBus {
     }
     
     async fn subscribe(&self, event_type: TypeId, handler: DynEventHandler) -> Result<()> {
-        let mut handlers = self.handlers.write().await;
-        handlers.entry(event_type).or_insert_with(Vec::new).push(handler);
+        self.add_subscription(event_type, handler).await;
         Ok(())
     }
     
@@ -413,6 +560,53 @@ mod tests {
         assert!(result.is_ok());
     }
     
+    #[tokio::test]
+    async fn test_removing_a_subscription_keeps_other_handlers() {
+        let event_bus = InMemoryEventBus::new();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let seen = seen.clone();
+            move |_: &CrossDomainEvent| -> Result<()> {
+                seen.lock().unwrap().push(name);
+                Ok(())
+            }
+        };
+        let first = event_bus.add_typed_subscription(record("first")).await;
+        event_bus.add_typed_subscription(record("second")).await;
+
+        let event = || CrossDomainEvent::DocumentDeleted {
+            base: BaseEvent::new(EntityId::new(), 1),
+            document_id: EntityId::new(),
+            deleted_by: EntityId::new(),
+        };
+        event_bus.publish_typed(event()).await.unwrap();
+        assert!(event_bus.remove_subscription(first).await);
+        assert!(!event_bus.remove_subscription(first).await);
+        event_bus.publish_typed(event()).await.unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec!["first", "second", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_event_store_versions_streams() {
+        let store = InMemoryEventStore::<CrossDomainEvent>::new();
+        let aggregate_id = EntityId::new();
+        let event = |version| CrossDomainEvent::DocumentDeleted {
+            base: BaseEvent::new(aggregate_id, version),
+            document_id: aggregate_id,
+            deleted_by: EntityId::new(),
+        };
+
+        store.save_events(aggregate_id, vec![Box::new(event(1)), Box::new(event(2))], 0).await.unwrap();
+        assert!(store.save_events(aggregate_id, vec![Box::new(event(3))], 1).await.is_err());
+        store.save_events(aggregate_id, vec![Box::new(event(3))], 2).await.unwrap();
+
+        assert_eq!(store.get_aggregate_version(aggregate_id).await.unwrap(), 3);
+        let since = store.load_events_since(aggregate_id, 1).await.unwrap();
+        let versions: Vec<u64> = since.iter().map(|event| event.aggregate_version()).collect();
+        assert_eq!(versions, vec![2, 3]);
+    }
+
     #[test]
     fn test_cross_domain_event_properties() {
         let base_event = BaseEvent::new(EntityId::new(), 1);
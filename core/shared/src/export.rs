@@ -0,0 +1,53 @@
+//! Formats and row writing shared by the record exports
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use crate::Result;
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+}
+
+/// Write one RFC 4180 row, quoting fields that need it
+pub fn write_csv_row<'a, W: Write + ?Sized>(writer: &mut W, fields: impl Iterator<Item = &'a str>) -> Result<()> {
+    for (index, field) in fields.enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut buffer = Vec::new();
+        write_csv_row(&mut buffer, ["a,b", "say \"hi\"", "plain"].into_iter()).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "\"a,b\",\"say \"\"hi\"\"\",plain\r\n");
+    }
+}
@@ -0,0 +1,129 @@
+//! Runtime feature toggles
+//!
+//! Flags are plain atomics so checking them on hot paths never takes a lock.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::{Result, WritemagicError};
+
+/// Engine features that can be switched on and off at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// AI completions and writing assistance
+    Ai,
+}
+
+impl Feature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ai => "ai",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Point-in-time view of every flag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureFlagsSnapshot {
+    pub ai: bool,
+}
+
+/// Partial update; flags left as `None` keep their current value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureFlagsUpdate {
+    #[serde(default)]
+    pub ai: Option<bool>,
+}
+
+/// Shared set of runtime feature flags, all enabled by default
+#[derive(Debug)]
+pub struct FeatureFlags {
+    ai: AtomicBool,
+}
+
+impl FeatureFlags {
+    pub fn new() -> Self {
+        Self {
+            ai: AtomicBool::new(true),
+        }
+    }
+
+    fn flag(&self, feature: Feature) -> &AtomicBool {
+        match feature {
+            Feature::Ai => &self.ai,
+        }
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.flag(feature).load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, feature: Feature, enabled: bool) {
+        self.flag(feature).store(enabled, Ordering::Relaxed);
+    }
+
+    /// Fail with a feature-disabled error when `feature` is switched off
+    pub fn ensure_enabled(&self, feature: Feature) -> Result<()> {
+        if self.is_enabled(feature) {
+            Ok(())
+        } else {
+            Err(WritemagicError::feature_disabled(feature.as_str()))
+        }
+    }
+
+    pub fn apply(&self, update: &FeatureFlagsUpdate) {
+        if let Some(enabled) = update.ai {
+            self.set(Feature::Ai, enabled);
+        }
+    }
+
+    pub fn snapshot(&self) -> FeatureFlagsSnapshot {
+        FeatureFlagsSnapshot {
+            ai: self.is_enabled(Feature::Ai),
+        }
+    }
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_default_to_enabled() {
+        let flags = FeatureFlags::new();
+        assert!(flags.is_enabled(Feature::Ai));
+        assert!(flags.ensure_enabled(Feature::Ai).is_ok());
+    }
+
+    #[test]
+    fn test_disabled_flag_returns_feature_disabled() {
+        let flags = FeatureFlags::new();
+        flags.set(Feature::Ai, false);
+
+        let err = flags.ensure_enabled(Feature::Ai).unwrap_err();
+        assert!(matches!(err, WritemagicError::FeatureDisabled { ref feature } if feature == "ai"));
+    }
+
+    #[test]
+    fn test_apply_only_touches_given_flags() {
+        let flags = FeatureFlags::new();
+        flags.apply(&FeatureFlagsUpdate { ai: None });
+        assert_eq!(flags.snapshot(), FeatureFlagsSnapshot { ai: true });
+
+        flags.apply(&FeatureFlagsUpdate { ai: Some(false) });
+        assert_eq!(flags.snapshot(), FeatureFlagsSnapshot { ai: false });
+    }
+}
@@ -0,0 +1,234 @@
+//! Delivery of domain events to foreign callbacks on a dedicated thread
+
+use crate::{DomainEvent, Result, WritemagicError};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+/// Events queued for a subscriber before newer ones are dropped
+pub const EVENT_QUEUE_CAPACITY: usize = 1024;
+
+/// Longest `stop` waits for an event being delivered to finish
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// JSON sent to a foreign subscriber: the event's type, aggregate and time next to the event itself
+pub fn event_json<E: DomainEvent + Serialize>(event: &E) -> Result<String> {
+    Ok(serde_json::to_string(&serde_json::json!({
+        "event_type": event.event_type(),
+        "aggregate_id": event.aggregate_id(),
+        "occurred_at": event.occurred_at(),
+        "event": event,
+    }))?)
+}
+
+/// JSON telling a subscriber that `count` events were dropped, so it should reload what it shows
+fn events_dropped_json(count: u64) -> String {
+    serde_json::json!({
+        "event_type": "EventsDropped",
+        "count": count,
+        "occurred_at": chrono::Utc::now(),
+    })
+    .to_string()
+}
+
+/// Queue of serialized events for one foreign subscriber, drained in order by its own thread
+///
+/// Event bus handlers run inside `publish` on the runtime, so they only
+/// queue; the foreign callback is always called on the dispatch thread. The
+/// queue holds at most [`EVENT_QUEUE_CAPACITY`] events. While it is full new
+/// events are dropped, and a single `EventsDropped` event with their count
+/// is queued once there is room again.
+pub struct EventDispatcher {
+    sender: Mutex<Option<SyncSender<String>>>,
+    dropped: AtomicU64,
+    stopped: Arc<AtomicBool>,
+    thread_id: ThreadId,
+    /// Disconnected once the dispatch thread has ended
+    finished: Mutex<Option<Receiver<()>>>,
+}
+
+impl EventDispatcher {
+    /// Start a dispatch thread named `name`
+    ///
+    /// `make_sink` runs on the new thread and returns the function each
+    /// event's JSON is delivered to, so thread-bound setup such as attaching
+    /// to a JVM happens there and is undone when the thread ends.
+    pub fn spawn<F, S>(name: &str, make_sink: F) -> Result<Self>
+    where
+        F: FnOnce() -> S + Send + 'static,
+        S: FnMut(&str),
+    {
+        let (sender, receiver) = mpsc::sync_channel::<String>(EVENT_QUEUE_CAPACITY);
+        let (finished_sender, finished) = mpsc::channel::<()>();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let _finished = finished_sender;
+                let mut sink = make_sink();
+                for json in receiver {
+                    if thread_stopped.load(Ordering::Acquire) {
+                        break;
+                    }
+                    sink(&json);
+                }
+            })
+            .map_err(|e| WritemagicError::internal(format!("Failed to start event dispatch thread: {}", e)))?;
+
+        Ok(Self {
+            sender: Mutex::new(Some(sender)),
+            dropped: AtomicU64::new(0),
+            stopped,
+            thread_id: thread.thread().id(),
+            finished: Mutex::new(Some(finished)),
+        })
+    }
+
+    /// Queue an event's JSON, dropping it if the queue is full; ignored once the dispatcher is stopped
+    pub fn send(&self, json: String) {
+        let sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(sender) = sender.as_ref() else {
+            return;
+        };
+
+        let dropped = self.dropped.swap(0, Ordering::AcqRel);
+        if dropped > 0 {
+            if let Err(TrySendError::Full(_)) = sender.try_send(events_dropped_json(dropped)) {
+                self.dropped.fetch_add(dropped + 1, Ordering::AcqRel);
+                return;
+            }
+        }
+        if let Err(TrySendError::Full(_)) = sender.try_send(json) {
+            self.dropped.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Stop delivering, dropping queued events
+    ///
+    /// Waits up to a short timeout for an event being delivered to finish,
+    /// so the sink is normally not called again once this returns. A sink
+    /// that blocks on the caller, or a call from the sink itself, is not
+    /// waited for; the thread then ends after the current event.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+
+        let finished = self.finished.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(finished) = finished {
+            if self.thread_id != thread::current().id()
+                && finished.recv_timeout(STOP_TIMEOUT) == Err(mpsc::RecvTimeoutError::Timeout)
+            {
+                log::warn!("Event dispatch thread still delivering an event after {:?}", STOP_TIMEOUT);
+            }
+        }
+    }
+}
+
+impl Drop for EventDispatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_delivered_in_order_on_the_dispatch_thread_until_stopped() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink_delivered = delivered.clone();
+        let dispatcher = EventDispatcher::spawn("test-events", move || {
+            move |json: &str| {
+                let name = thread::current().name().map(str::to_string);
+                sink_delivered.lock().unwrap().push((json.to_string(), name));
+            }
+        })
+        .unwrap();
+
+        dispatcher.send("first".to_string());
+        dispatcher.send("second".to_string());
+        // Wait for both to arrive before stopping, which would drop them
+        while delivered.lock().unwrap().len() < 2 {
+            thread::yield_now();
+        }
+        dispatcher.stop();
+        dispatcher.send("late".to_string());
+
+        let delivered = delivered.lock().unwrap();
+        let names: Vec<&str> = delivered.iter().map(|(json, _)| json.as_str()).collect();
+        assert_eq!(names, vec!["first", "second"]);
+        assert!(delivered.iter().all(|(_, thread)| thread.as_deref() == Some("test-events")));
+    }
+
+    #[test]
+    fn test_full_queue_drops_events_and_reports_how_many() {
+        let entered = Arc::new(AtomicBool::new(false));
+        let (release, released) = mpsc::channel::<()>();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let (sink_entered, sink_delivered) = (entered.clone(), delivered.clone());
+        let dispatcher = EventDispatcher::spawn("test-full-events", move || {
+            move |json: &str| {
+                // Hold the first event so the queue fills up behind it
+                if json == "blocker" {
+                    sink_entered.store(true, Ordering::Release);
+                    released.recv().unwrap();
+                }
+                sink_delivered.lock().unwrap().push(json.to_string());
+            }
+        })
+        .unwrap();
+
+        dispatcher.send("blocker".to_string());
+        while !entered.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+        for index in 0..EVENT_QUEUE_CAPACITY + 3 {
+            dispatcher.send(index.to_string());
+        }
+
+        release.send(()).unwrap();
+        while delivered.lock().unwrap().len() < EVENT_QUEUE_CAPACITY + 1 {
+            thread::yield_now();
+        }
+        dispatcher.send("after".to_string());
+        while delivered.lock().unwrap().len() < EVENT_QUEUE_CAPACITY + 3 {
+            thread::yield_now();
+        }
+        dispatcher.stop();
+
+        let delivered = delivered.lock().unwrap();
+        assert_eq!(delivered[EVENT_QUEUE_CAPACITY], (EVENT_QUEUE_CAPACITY - 1).to_string());
+        let notice: serde_json::Value = serde_json::from_str(&delivered[EVENT_QUEUE_CAPACITY + 1]).unwrap();
+        assert_eq!(notice["event_type"], "EventsDropped");
+        assert_eq!(notice["count"], 3);
+        assert_eq!(delivered[EVENT_QUEUE_CAPACITY + 2], "after");
+    }
+
+    #[test]
+    fn test_stop_gives_up_waiting_on_a_blocked_sink() {
+        let entered = Arc::new(AtomicBool::new(false));
+        let (_never_sent, blocked) = mpsc::channel::<()>();
+        let sink_entered = entered.clone();
+        let dispatcher = EventDispatcher::spawn("test-blocked-events", move || {
+            move |_: &str| {
+                sink_entered.store(true, Ordering::Release);
+                let _ = blocked.recv();
+            }
+        })
+        .unwrap();
+
+        dispatcher.send("stuck".to_string());
+        while !entered.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+        let started = std::time::Instant::now();
+        dispatcher.stop();
+        assert!(started.elapsed() >= STOP_TIMEOUT);
+        assert!(started.elapsed() < STOP_TIMEOUT * 3);
+    }
+}
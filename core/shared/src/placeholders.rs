@@ -0,0 +1,38 @@
+//! `{{name}}` placeholder substitution shared by document templates and agent workflows
+
+use std::borrow::Cow;
+
+/// Replace each `{{name}}` placeholder with `lookup(name)`, leaving placeholders it has no value for intact
+///
+/// Whitespace around the name is ignored, so `{{ name }}` and `{{name}}` are
+/// the same placeholder. An unclosed `{{` is kept as text.
+pub fn render_placeholders<'a>(template: &str, mut lookup: impl FnMut(&str) -> Option<Cow<'a, str>>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        rendered.push_str(&rest[..start]);
+        match lookup(rest[start + 2..end - 2].trim()) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_placeholders_are_replaced_and_others_kept() {
+        let lookup = |name: &str| (name == "team").then_some(Cow::Borrowed("Platform"));
+        assert_eq!(render_placeholders("{{ team }} sync {{unset}}", lookup), "Platform sync {{unset}}");
+        assert_eq!(render_placeholders("{{team}} {{open", lookup), "Platform {{open");
+    }
+}
@@ -17,6 +17,37 @@ impl<T> InMemoryRepository<T> {
             entities: Arc::new(RwLock::new(HashMap::new())),
         }
     }
+
+    /// Run `update` with exclusive access to every stored entity
+    ///
+    /// No other read or save happens while it runs, so several checks and
+    /// writes made inside it apply atomically.
+    pub fn update<R>(&self, update: impl FnOnce(&mut HashMap<EntityId, T>) -> Result<R>) -> Result<R> {
+        let mut entities = self.entities.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        update(&mut entities)
+    }
+}
+
+impl<T: Clone + crate::Entity<Id = EntityId>> InMemoryRepository<T> {
+    /// Store `entity` only if `check` accepts the currently stored value
+    ///
+    /// The check and the write happen under one lock, so no other save can
+    /// land between them.
+    pub fn save_if<E: From<WritemagicError>>(
+        &self,
+        entity: &T,
+        check: impl FnOnce(Option<&T>) -> std::result::Result<(), E>,
+    ) -> std::result::Result<T, E> {
+        let mut entities = self.entities.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+
+        check(entities.get(entity.id()))?;
+        entities.insert(*entity.id(), entity.clone());
+        Ok(entity.clone())
+    }
 }
 
 impl<T> Clone for InMemoryRepository<T> {
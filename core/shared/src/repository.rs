@@ -1,7 +1,9 @@
 //! Repository abstraction for data access
 
 use async_trait::async_trait;
+use crate::services::{ProjectDomainService, WritingDomainService};
 use crate::{EntityId, Pagination, Result};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -56,11 +58,24 @@ pub trait UnitOfWork: Send + Sync {
     /// Rollback the current transaction
     async fn rollback(&mut self) -> Result<()>;
 
+    /// Writing service whose writes belong to the current transaction
+    ///
+    /// `None` when there is no transaction or the writes can't be bound to it.
+    fn writing_service(&self) -> Option<Arc<dyn WritingDomainService>> {
+        None
+    }
+
+    /// Project service whose writes belong to the current transaction
+    fn project_service(&self) -> Option<Arc<dyn ProjectDomainService>> {
+        None
+    }
+
     /// Execute work within a transaction
     async fn with_transaction<F, R>(&mut self, work: F) -> Result<R>
     where
         F: FnOnce(&mut Self) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<R>> + Send>> + Send,
-        R: Send;
+        R: Send,
+        Self: Sized;
 }
 
 /// Query specification pattern
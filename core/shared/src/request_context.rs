@@ -0,0 +1,109 @@
+//! Correlation ids carried from the web and FFI boundaries into core services
+
+use std::future::Future;
+use tracing::{Instrument, Span};
+
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
+/// Identifies one user action as it crosses layers
+///
+/// Entered at the boundary with [`RequestContext::scope`]; services read it back
+/// with [`current_request_id`] so their spans and log lines share the id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    request_id: String,
+}
+
+impl RequestContext {
+    pub fn new(request_id: impl Into<String>) -> Self {
+        Self { request_id: request_id.into() }
+    }
+
+    /// A context with a fresh random id, for boundaries that receive none
+    pub fn generate() -> Self {
+        Self::new(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// The context of the request the current task is serving, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Root span for `operation`, tagged with this request's id
+    pub fn span(&self, operation: &str) -> Span {
+        tracing::info_span!("request", request_id = %self.request_id, operation = operation)
+    }
+
+    /// Run `future` as part of this request, inside its root span
+    pub async fn scope<F: Future>(self, operation: &str, future: F) -> F::Output {
+        let span = self.span(operation);
+        CURRENT.scope(self, future.instrument(span)).await
+    }
+}
+
+/// Correlation id of the request the current task is serving, if any
+pub fn current_request_id() -> Option<String> {
+    CURRENT.try_with(|context| context.request_id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    /// Name and `request_id` field of every span created while installed
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<(String, Option<String>)>>>);
+
+    struct RequestIdVisitor(Option<String>);
+
+    impl Visit for RequestIdVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "request_id" {
+                self.0 = Some(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "request_id" {
+                self.0 = Some(format!("{:?}", value));
+            }
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, _id: &tracing::span::Id, _ctx: Context<'_, S>) {
+            let mut visitor = RequestIdVisitor(None);
+            attrs.record(&mut visitor);
+            self.0.lock().unwrap().push((attrs.metadata().name().to_string(), visitor.0));
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    async fn service_call() -> Option<String> {
+        current_request_id()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_reaches_nested_spans() {
+        let recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+        assert_eq!(RequestContext::current(), None);
+        let seen = RequestContext::new("req-42").scope("test", service_call()).await;
+        assert_eq!(seen.as_deref(), Some("req-42"));
+        assert_eq!(current_request_id(), None);
+
+        let spans = recorder.0.lock().unwrap().clone();
+        assert!(spans.contains(&("request".to_string(), Some("req-42".to_string()))));
+        assert!(spans.contains(&("service_call".to_string(), Some("req-42".to_string()))));
+    }
+}
@@ -3,7 +3,7 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
-use crate::{EntityId, Result, WritemagicError};
+use crate::{EntityId, Result, UnitOfWork, WritemagicError};
 use crate::events::EventBus;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,7 @@ pub struct CrossDomainServiceRegistry {
     project_service: Option<Arc<dyn ProjectDomainService>>,
     version_control_service: Option<Arc<dyn VersionControlDomainService>>,
     agent_service: Option<Arc<dyn AgentDomainService>>,
+    unit_of_work: Option<Arc<tokio::sync::Mutex<dyn UnitOfWork>>>,
     event_bus: Arc<dyn EventBus>,
 }
 
@@ -27,6 +28,7 @@ impl CrossDomainServiceRegistry {
             project_service: None,
             version_control_service: None,
             agent_service: None,
+            unit_of_work: None,
             event_bus,
         }
     }
@@ -56,6 +58,11 @@ impl CrossDomainServiceRegistry {
         self.agent_service = Some(service);
     }
     
+    /// Register the unit of work shared by the writing and project services
+    pub fn register_unit_of_work(&mut self, unit_of_work: Arc<tokio::sync::Mutex<dyn UnitOfWork>>) {
+        self.unit_of_work = Some(unit_of_work);
+    }
+    
     /// Get writing service
     pub fn writing_service(&self) -> Result<&Arc<dyn WritingDomainService>> {
         self.writing_service.as_ref()
@@ -86,6 +93,12 @@ impl CrossDomainServiceRegistry {
             .ok_or_else(|| WritemagicError::not_found("Agent service not registered"))
     }
     
+    /// Get unit of work
+    pub fn unit_of_work(&self) -> Result<&Arc<tokio::sync::Mutex<dyn UnitOfWork>>> {
+        self.unit_of_work.as_ref()
+            .ok_or_else(|| WritemagicError::not_found("Unit of work not registered"))
+    }
+    
     /// Get event bus
     pub fn event_bus(&self) -> &Arc<dyn EventBus> {
         &self.event_bus
@@ -112,6 +125,11 @@ pub trait WritingDomainService: Send + Sync {
     
     /// Get document statistics
     async fn get_document_stats(&self, document_id: &EntityId) -> Result<DocumentStats>;
+    
+    /// Add tags to a document, keeping the ones it already has
+    async fn tag_document(&self, _document_id: &EntityId, _tags: Vec<String>) -> Result<()> {
+        Err(WritemagicError::not_implemented("Tagging documents is not supported by this writing service"))
+    }
 }
 
 /// AI domain service interface
@@ -275,6 +293,125 @@ impl CrossDomainCoordinator {
         
         version_control_service.create_commit(commit_request).await
     }
+    
+    /// Generate a document, then save, attach, tag and commit it as one unit
+    ///
+    /// Generation runs before the unit of work begins so a slow provider never
+    /// holds the transaction open. Every later step runs inside it and a
+    /// failure rolls all of them back, reported as
+    /// `WritemagicError::WorkflowStepFailed` naming the step. The commit is the
+    /// last step because version control is not covered by the rollback.
+    pub async fn create_document_workflow(&self, request: DocumentWorkflowRequest) -> Result<DocumentWorkflowResult> {
+        let ai_service = self.registry.ai_service()?;
+        let writing_service = self.registry.writing_service()?;
+        let unit_of_work = self.registry.unit_of_work()?;
+        let project_service = request.project_id
+            .map(|_| self.registry.project_service())
+            .transpose()?;
+        let version_control_service = request.commit_message
+            .as_ref()
+            .map(|_| self.registry.version_control_service())
+            .transpose()?;
+        
+        let generation = ai_service.generate_content(request.generation).await
+            .map_err(|e| WritemagicError::workflow_step_failed(WorkflowStep::Generate, e))?;
+        let tokens_used = generation.tokens_used;
+        
+        let mut unit_of_work = unit_of_work.lock().await;
+        unit_of_work.begin().await?;
+        // Writes go through the transaction's own services where it has them
+        let writing_service = unit_of_work.writing_service().unwrap_or_else(|| writing_service.clone());
+        let project_service = project_service.map(|service| unit_of_work.project_service().unwrap_or_else(|| service.clone()));
+        
+        let persisted = async {
+            // Attached below as its own step, so a failure there is reported as such
+            let document_request = CreateDocumentRequest {
+                title: request.title
+                    .or(generation.title)
+                    .unwrap_or_else(|| "AI Generated Document".to_string()),
+                content: generation.content,
+                project_id: None,
+                metadata: generation.metadata,
+            };
+            let mut document = writing_service.create_document(document_request).await
+                .map_err(|e| WritemagicError::workflow_step_failed(WorkflowStep::CreateDocument, e))?;
+            
+            if let (Some(project_service), Some(project_id)) = (project_service, &request.project_id) {
+                project_service.add_document_to_project(project_id, &document.id).await
+                    .map_err(|e| WritemagicError::workflow_step_failed(WorkflowStep::AttachToProject, e))?;
+                document.project_id = Some(*project_id);
+            }
+            
+            if !request.tags.is_empty() {
+                writing_service.tag_document(&document.id, request.tags.clone()).await
+                    .map_err(|e| WritemagicError::workflow_step_failed(WorkflowStep::Tag, e))?;
+            }
+            
+            let commit = match (version_control_service, request.commit_message) {
+                (Some(version_control_service), Some(message)) => {
+                    let commit_request = CreateCommitRequest {
+                        document_id: document.id,
+                        message,
+                        metadata: HashMap::new(),
+                    };
+                    let commit = version_control_service.create_commit(commit_request).await
+                        .map_err(|e| WritemagicError::workflow_step_failed(WorkflowStep::Commit, e))?;
+                    Some(commit)
+                }
+                _ => None,
+            };
+            
+            Ok(DocumentWorkflowResult {
+                document,
+                project_id: request.project_id,
+                tags: request.tags,
+                commit,
+                tokens_used,
+            })
+        }.await;
+        
+        let result = match persisted {
+            Ok(result) => unit_of_work.commit().await.map(|_| result),
+            Err(error) => Err(error),
+        };
+        
+        if result.is_err() {
+            if let Err(rollback_error) = unit_of_work.rollback().await {
+                tracing::error!("Failed to roll back document workflow: {}", rollback_error);
+            }
+        }
+        
+        result
+    }
+}
+
+/// Steps of the document workflow, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStep {
+    Generate,
+    CreateDocument,
+    AttachToProject,
+    Tag,
+    Commit,
+}
+
+impl WorkflowStep {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Generate => "generate",
+            Self::CreateDocument => "create_document",
+            Self::AttachToProject => "attach_to_project",
+            Self::Tag => "tag",
+            Self::Commit => "commit",
+        }
+    }
+}
+
+impl std::fmt::Display for WorkflowStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 // Data structures for cross-domain operations
@@ -301,6 +438,30 @@ pub struct DocumentStats {
     pub last_modified: DateTime<Utc>,
 }
 
+/// Generate, save, attach, tag and commit a document in one workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentWorkflowRequest {
+    pub generation: AIGenerationRequest,
+    /// Title of the document, or the generated title when `None`
+    pub title: Option<String>,
+    /// Project to attach the document to, if any
+    pub project_id: Option<EntityId>,
+    /// Tags to add; tagging is skipped when empty
+    pub tags: Vec<String>,
+    /// Message of the initial commit; no commit is made when `None`
+    pub commit_message: Option<String>,
+}
+
+/// Artifacts persisted by a document workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentWorkflowResult {
+    pub document: DocumentInfo,
+    pub project_id: Option<EntityId>,
+    pub tags: Vec<String>,
+    pub commit: Option<CommitInfo>,
+    pub tokens_used: u32,
+}
+
 /// Create document request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateDocumentRequest {
@@ -2,6 +2,7 @@
 
 use tokio::sync::{broadcast, mpsc};
 use tokio_util::sync::CancellationToken;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use tracing::{info, warn, error};
 
@@ -15,6 +16,8 @@ pub struct ShutdownCoordinator {
     completion_rx: mpsc::Receiver<ServiceShutdown>,
     /// Broadcast channel for shutdown notifications
     shutdown_tx: broadcast::Sender<ShutdownSignal>,
+    /// Subscribers handed out, each expected to report its completion
+    subscribers: AtomicUsize,
 }
 
 /// Signal sent to all services during shutdown
@@ -44,11 +47,15 @@ impl ShutdownCoordinator {
             completion_tx,
             completion_rx,
             shutdown_tx,
+            subscribers: AtomicUsize::new(0),
         }
     }
     
     /// Get a shutdown subscriber for a service
+    ///
+    /// `shutdown` waits for every subscriber to report its completion.
     pub fn subscriber(&self) -> ShutdownSubscriber {
+        self.subscribers.fetch_add(1, Ordering::SeqCst);
         ShutdownSubscriber {
             cancellation_token: self.cancellation_token.clone(),
             completion_tx: self.completion_tx.clone(),
@@ -57,6 +64,8 @@ impl ShutdownCoordinator {
     }
     
     /// Initiate graceful shutdown
+    ///
+    /// Returns `false` if some subscriber had not reported back within `timeout`.
     pub async fn shutdown(&mut self, timeout: Duration) -> bool {
         info!("Initiating graceful shutdown with timeout {:?}", timeout);
         
@@ -68,13 +77,13 @@ impl ShutdownCoordinator {
         // Cancel all operations
         self.cancellation_token.cancel();
         
-        let start = std::time::Instant::now();
-        let mut services_remaining: usize = 0;
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut services_remaining = self.subscribers.swap(0, Ordering::SeqCst);
         
         // Wait for services to complete or timeout
-        while start.elapsed() < timeout {
-            tokio::select! {
-                Some(completion) = self.completion_rx.recv() => {
+        while services_remaining > 0 {
+            match tokio::time::timeout_at(deadline, self.completion_rx.recv()).await {
+                Ok(Some(completion)) => {
                     if completion.success {
                         info!("Service '{}' shut down successfully in {:?}", 
                               completion.service_name, completion.duration);
@@ -82,16 +91,13 @@ impl ShutdownCoordinator {
                         warn!("Service '{}' failed to shut down gracefully in {:?}", 
                               completion.service_name, completion.duration);
                     }
-                    services_remaining = services_remaining.saturating_sub(1);
-                }
-                _ = tokio::time::sleep(Duration::from_millis(100)) => {
-                    // Continue waiting
+                    services_remaining -= 1;
                 }
-                else => break,
+                Ok(None) | Err(_) => break,
             }
         }
         
-        if start.elapsed() >= timeout && services_remaining > 0 {
+        if services_remaining > 0 {
             warn!("Shutdown timeout reached, forcing immediate shutdown");
             if let Err(e) = self.shutdown_tx.send(ShutdownSignal::Immediate) {
                 error!("Failed to send immediate shutdown signal: {}", e);
@@ -202,4 +208,44 @@ macro_rules! shutdown_service {
         let subscriber = $coordinator.subscriber();
         tokio::spawn($service.run_with_shutdown(subscriber))
     }};
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Service {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl GracefulShutdown for Service {
+        fn service_name(&self) -> &str {
+            "test"
+        }
+
+        async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            tokio::time::sleep(self.delay).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_returns_once_subscribers_report() {
+        let mut coordinator = ShutdownCoordinator::new();
+        shutdown_service!(Service { delay: Duration::from_millis(10) }, coordinator);
+        shutdown_service!(Service { delay: Duration::ZERO }, coordinator);
+
+        let start = std::time::Instant::now();
+        assert!(coordinator.shutdown(Duration::from_secs(5)).await);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_times_out_on_slow_subscriber() {
+        let mut coordinator = ShutdownCoordinator::new();
+        shutdown_service!(Service { delay: Duration::from_secs(60) }, coordinator);
+
+        assert!(!coordinator.shutdown(Duration::from_millis(50)).await);
+    }
+}
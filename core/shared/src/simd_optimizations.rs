@@ -145,6 +145,151 @@ pub mod text_processing {
 
     #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32")))]
     use case_conversion::*;
+
+    /// Number of whitespace-separated words, matching `str::split_whitespace().count()`
+    pub fn count_words_simd(text: &str) -> usize {
+        count_boundaries(text).words
+    }
+
+    /// Number of maximal runs of whitespace, as classified by `char::is_whitespace`
+    pub fn count_whitespace_runs_simd(text: &str) -> usize {
+        count_boundaries(text).whitespace_runs
+    }
+
+    fn count_boundaries(text: &str) -> BoundaryCounts {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32")))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                unsafe { count_boundaries_avx2(text) }
+            } else if is_x86_feature_detected!("sse2") {
+                unsafe { count_boundaries_sse2(text) }
+            } else {
+                count_boundaries_scalar(text)
+            }
+        }
+
+        #[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32"))))]
+        {
+            count_boundaries_scalar(text)
+        }
+    }
+
+    /// Word and whitespace-run starts seen so far in a left-to-right scan
+    #[derive(Debug, Default)]
+    struct BoundaryCounts {
+        words: usize,
+        whitespace_runs: usize,
+        /// Whether the last character scanned was whitespace, `None` before the first
+        prev_whitespace: Option<bool>,
+    }
+
+    impl BoundaryCounts {
+        /// Add `lanes` characters, bit `i` of `whitespace` set where character `i` is whitespace
+        #[inline(always)]
+        fn push_mask(&mut self, whitespace: u64, lanes: usize) {
+            let lane_bits = (1u64 << lanes) - 1;
+            let whitespace = whitespace & lane_bits;
+            // A word starts after whitespace or at the start of the text, a run only after a non-space
+            let after_space = (whitespace << 1) | self.prev_whitespace.unwrap_or(true) as u64;
+            let after_space_in_run = (whitespace << 1) | self.prev_whitespace.unwrap_or(false) as u64;
+
+            self.words += (!whitespace & after_space & lane_bits).count_ones() as usize;
+            self.whitespace_runs += (whitespace & !after_space_in_run).count_ones() as usize;
+            self.prev_whitespace = Some((whitespace >> (lanes - 1)) & 1 == 1);
+        }
+
+        /// Scan whole characters from byte `from` until reaching byte `until`, returning where it stopped
+        fn scan_chars(&mut self, text: &str, from: usize, until: usize) -> usize {
+            let mut offset = from;
+            for c in text[from..].chars() {
+                if offset >= until {
+                    break;
+                }
+                self.push_mask(c.is_whitespace() as u64, 1);
+                offset += c.len_utf8();
+            }
+            offset
+        }
+    }
+
+    fn count_boundaries_scalar(text: &str) -> BoundaryCounts {
+        let mut counts = BoundaryCounts::default();
+        counts.scan_chars(text, 0, text.len());
+        counts
+    }
+
+    // ASCII chunks are classified with SIMD; a chunk holding any non-ASCII byte is
+    // scanned character by character, since Unicode whitespace is multi-byte
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32")))]
+    mod word_boundaries {
+        use super::*;
+
+        #[target_feature(enable = "avx2")]
+        pub(super) unsafe fn count_boundaries_avx2(text: &str) -> BoundaryCounts {
+            const LANES: usize = 32;
+
+            let bytes = text.as_bytes();
+            let space = _mm256_set1_epi8(b' ' as i8);
+            let tab = _mm256_set1_epi8(b'\t' as i8);
+            let control_span = _mm256_set1_epi8(4);
+            let mut counts = BoundaryCounts::default();
+            let mut offset = 0;
+
+            while offset + LANES <= bytes.len() {
+                let data = _mm256_loadu_si256(bytes.as_ptr().add(offset) as *const _);
+                if _mm256_movemask_epi8(data) != 0 {
+                    offset = counts.scan_chars(text, offset, offset + LANES);
+                    continue;
+                }
+
+                // Whitespace is a space or one of \t, \n, \x0B, \x0C, \r
+                let from_tab = _mm256_sub_epi8(data, tab);
+                let is_control = _mm256_cmpeq_epi8(_mm256_min_epu8(from_tab, control_span), from_tab);
+                let is_space = _mm256_cmpeq_epi8(data, space);
+                let whitespace = _mm256_movemask_epi8(_mm256_or_si256(is_space, is_control)) as u32;
+
+                counts.push_mask(whitespace as u64, LANES);
+                offset += LANES;
+            }
+
+            counts.scan_chars(text, offset, bytes.len());
+            counts
+        }
+
+        #[target_feature(enable = "sse2")]
+        pub(super) unsafe fn count_boundaries_sse2(text: &str) -> BoundaryCounts {
+            const LANES: usize = 16;
+
+            let bytes = text.as_bytes();
+            let space = _mm_set1_epi8(b' ' as i8);
+            let tab = _mm_set1_epi8(b'\t' as i8);
+            let control_span = _mm_set1_epi8(4);
+            let mut counts = BoundaryCounts::default();
+            let mut offset = 0;
+
+            while offset + LANES <= bytes.len() {
+                let data = _mm_loadu_si128(bytes.as_ptr().add(offset) as *const _);
+                if _mm_movemask_epi8(data) != 0 {
+                    offset = counts.scan_chars(text, offset, offset + LANES);
+                    continue;
+                }
+
+                let from_tab = _mm_sub_epi8(data, tab);
+                let is_control = _mm_cmpeq_epi8(_mm_min_epu8(from_tab, control_span), from_tab);
+                let is_space = _mm_cmpeq_epi8(data, space);
+                let whitespace = _mm_movemask_epi8(_mm_or_si128(is_space, is_control)) as u32;
+
+                counts.push_mask(whitespace as u64, LANES);
+                offset += LANES;
+            }
+
+            counts.scan_chars(text, offset, bytes.len());
+            counts
+        }
+    }
+
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32")))]
+    use word_boundaries::*;
 }
 
 /// SIMD-optimized numerical operations
@@ -315,4 +460,56 @@ pub mod memory {
 
     #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(target_arch = "wasm32")))]
     use simd_memory::*;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::text_processing::{count_whitespace_runs_simd, count_words_simd};
+    use proptest::prelude::*;
+
+    /// Whitespace runs the way `count_whitespace_runs_simd` defines them
+    fn whitespace_runs_naive(text: &str) -> usize {
+        let mut runs = 0;
+        let mut prev_whitespace = false;
+        for c in text.chars() {
+            if c.is_whitespace() && !prev_whitespace {
+                runs += 1;
+            }
+            prev_whitespace = c.is_whitespace();
+        }
+        runs
+    }
+
+    /// Mostly ASCII text so whole SIMD chunks occur, salted with Unicode letters and whitespace
+    fn mixed_text() -> impl Strategy<Value = String> {
+        let c = prop_oneof![
+            6 => prop::char::range('a', 'z'),
+            3 => prop::sample::select(vec![' ', '\t', '\n', '\r', '\u{0B}', '\u{0C}']),
+            1 => prop::sample::select(vec!['\u{85}', '\u{a0}', '\u{1680}', '\u{2003}', '\u{2029}', '\u{3000}']),
+            1 => any::<char>(),
+        ];
+        prop::collection::vec(c, 0..400).prop_map(|chars| chars.into_iter().collect())
+    }
+
+    proptest! {
+        #[test]
+        fn word_count_matches_split_whitespace(text in mixed_text()) {
+            prop_assert_eq!(count_words_simd(&text), text.split_whitespace().count());
+            prop_assert_eq!(count_whitespace_runs_simd(&text), whitespace_runs_naive(&text));
+        }
+
+        #[test]
+        fn word_count_matches_on_arbitrary_unicode(text in "\\PC{0,300}") {
+            prop_assert_eq!(count_words_simd(&text), text.split_whitespace().count());
+        }
+    }
+
+    #[test]
+    fn test_words_spanning_chunk_boundaries() {
+        let text = format!("{}word  {}mid\u{3000}end ", "a".repeat(31), " ".repeat(40));
+        assert_eq!(count_words_simd(&text), 3);
+        assert_eq!(count_whitespace_runs_simd(&text), 3);
+        assert_eq!(count_words_simd(""), 0);
+        assert_eq!(count_whitespace_runs_simd("  lead"), 1);
+    }
+}
@@ -22,6 +22,39 @@ impl EntityId {
         Self(Uuid::new_v4())
     }
 
+    /// Deterministic id for `name` within `namespace` (UUID version 5)
+    ///
+    /// The same namespace and name always give the same id, so two entities
+    /// derived from identical content collide by design. Ids from different
+    /// namespaces or names only collide if SHA-1 does, and cannot collide with
+    /// random `new()` ids since the version bits differ.
+    pub fn new_v5(namespace: &Uuid, name: &str) -> Self {
+        Self(Uuid::new_v5(namespace, name.as_bytes()))
+    }
+
+    /// Reproducible id for tests: the same seed always gives the same id
+    ///
+    /// Consecutive seeds give a fixed sequence of distinct ids shaped like
+    /// `new()` ones. Seeded ids can collide with each other across test runs
+    /// that reuse seeds, so never mix them with ids in real data.
+    #[cfg(feature = "seeded-ids")]
+    pub fn seeded(seed: u64) -> Self {
+        /// SplitMix64 step, enough to spread nearby seeds across the id space
+        fn mix(mut z: u64) -> u64 {
+            z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        let high = mix(seed);
+        let low = mix(high);
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&high.to_be_bytes());
+        bytes[8..].copy_from_slice(&low.to_be_bytes());
+        Self(uuid::Builder::from_random_bytes(bytes).into_uuid())
+    }
+
     pub fn from_uuid(uuid: Uuid) -> Self {
         Self(uuid)
     }
@@ -83,6 +116,16 @@ impl Timestamp {
     pub fn as_datetime(&self) -> DateTime<Utc> {
         self.0
     }
+
+    /// Form used for stored timestamps, see [`sortable_datetime`]
+    pub fn to_sortable_string(&self) -> String {
+        sortable_datetime(&self.0)
+    }
+}
+
+/// Fixed-width RFC3339 at microsecond precision, so stored timestamps sort lexicographically
+pub fn sortable_datetime(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
 }
 
 impl Default for Timestamp {
@@ -159,6 +202,68 @@ impl fmt::Display for FilePath {
     }
 }
 
+/// Document tag value object
+///
+/// Tags are lowercase, contain no whitespace and are limited to ASCII
+/// letters, digits, `-` and `_`, with a maximum length of 32 characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DocumentTag(String);
+
+impl DocumentTag {
+    pub const MAX_LENGTH: usize = crate::validation::TAG_MAX_LENGTH;
+
+    pub fn new(tag: impl Into<String>) -> crate::Result<Self> {
+        let tag = tag.into();
+        crate::validation::validate_tag(&tag).map_err(|e| {
+            crate::WritemagicError::validation(format!("Invalid document tag: {}", e.message))
+        })?;
+        Ok(Self(tag))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Extract up to `max` tags from the most frequent keywords in `content`.
+    ///
+    /// This is the offline fallback used when no AI provider is available.
+    pub fn from_keywords(content: &str, max: usize) -> Vec<Self> {
+        const STOP_WORDS: &[&str] = &[
+            "about", "after", "again", "also", "been", "before", "being", "between", "both",
+            "could", "does", "doing", "down", "each", "from", "further", "have", "having",
+            "here", "into", "just", "more", "most", "only", "other", "over", "same", "should",
+            "some", "such", "than", "that", "their", "them", "then", "there", "these", "they",
+            "this", "those", "through", "under", "until", "very", "were", "what", "when",
+            "where", "which", "while", "will", "with", "would", "your",
+        ];
+
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for word in content
+            .split(|c: char| !c.is_alphanumeric())
+            .map(|w| w.to_lowercase())
+            .filter(|w| w.len() >= 4 && !STOP_WORDS.contains(&w.as_str()))
+            .filter(|w| !w.chars().all(|c| c.is_ascii_digit()))
+        {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        ranked
+            .into_iter()
+            .filter_map(|(word, _)| Self::new(word).ok())
+            .take(max)
+            .collect()
+    }
+}
+
+impl fmt::Display for DocumentTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Content type enumeration
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContentType {
@@ -270,4 +375,31 @@ impl Default for Pagination {
             limit: 50,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v5_ids_are_stable_and_round_trip() {
+        let first = EntityId::new_v5(&Uuid::NAMESPACE_URL, "writemagic://documents/intro");
+        let second = EntityId::new_v5(&Uuid::NAMESPACE_URL, "writemagic://documents/intro");
+        assert_eq!(first, second);
+        assert_eq!(first.as_uuid().get_version_num(), 5);
+        assert_ne!(first, EntityId::new_v5(&Uuid::NAMESPACE_OID, "writemagic://documents/intro"));
+
+        let text = first.to_string();
+        assert_eq!(EntityId::from_string(&text).unwrap(), first);
+        assert_eq!(EntityId::from_string(&text).unwrap().to_string(), text);
+    }
+
+    #[cfg(feature = "seeded-ids")]
+    #[test]
+    fn test_seeded_ids_repeat_per_seed() {
+        let sequence: Vec<EntityId> = (0..4).map(EntityId::seeded).collect();
+        assert_eq!(sequence, (0..4).map(EntityId::seeded).collect::<Vec<_>>());
+        assert_eq!(sequence.iter().collect::<std::collections::HashSet<_>>().len(), 4);
+        assert_eq!(sequence[0].as_uuid().get_version_num(), 4);
+    }
+}
@@ -3,7 +3,8 @@
 use crate::{Result, WritemagicError};
 use regex::Regex;
 use std::collections::HashMap;
-use validator::{Validate, ValidationError, ValidationErrors};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError as ValidatorError, ValidationErrors};
 
 /// Validation context for domain-specific validation
 pub struct ValidationContext {
@@ -54,24 +55,24 @@ pub struct ContentValidator;
 
 impl ContentValidator {
     /// Validate content is not empty or just whitespace
-    pub fn validate_not_empty(content: &str) -> std::result::Result<(), ValidationError> {
+    pub fn validate_not_empty(content: &str) -> std::result::Result<(), ValidatorError> {
         if content.trim().is_empty() {
-            return Err(ValidationError::new("content_empty"));
+            return Err(ValidatorError::new("content_empty"));
         }
         Ok(())
     }
     
     /// Validate content length
-    pub fn validate_length(content: &str, min: usize, max: usize) -> std::result::Result<(), ValidationError> {
+    pub fn validate_length(content: &str, min: usize, max: usize) -> std::result::Result<(), ValidatorError> {
         let len = content.len();
         if len < min {
-            let mut error = ValidationError::new("content_too_short");
+            let mut error = ValidatorError::new("content_too_short");
             error.add_param(std::borrow::Cow::from("min"), &min);
             error.add_param(std::borrow::Cow::from("actual"), &len);
             return Err(error);
         }
         if len > max {
-            let mut error = ValidationError::new("content_too_long");
+            let mut error = ValidatorError::new("content_too_long");
             error.add_param(std::borrow::Cow::from("max"), &max);
             error.add_param(std::borrow::Cow::from("actual"), &len);
             return Err(error);
@@ -80,7 +81,7 @@ impl ContentValidator {
     }
     
     /// Validate no prohibited content
-    pub fn validate_no_prohibited_content(content: &str) -> std::result::Result<(), ValidationError> {
+    pub fn validate_no_prohibited_content(content: &str) -> std::result::Result<(), ValidatorError> {
         // Check for common patterns that should be filtered
         let prohibited_patterns = [
             r"<script\b[^<]*(?:(?!<\/script>)<[^<]*)*<\/script>", // Script tags
@@ -89,9 +90,9 @@ impl ContentValidator {
         ];
         
         for pattern in &prohibited_patterns {
-            let regex = Regex::new(pattern).map_err(|_| ValidationError::new("regex_error"))?;
+            let regex = Regex::new(pattern).map_err(|_| ValidatorError::new("regex_error"))?;
             if regex.is_match(content) {
-                return Err(ValidationError::new("prohibited_content"));
+                return Err(ValidatorError::new("prohibited_content"));
             }
         }
         
@@ -104,26 +105,26 @@ pub struct FilePathValidator;
 
 impl FilePathValidator {
     /// Validate file path is safe (no directory traversal)
-    pub fn validate_safe_path(path: &str) -> std::result::Result<(), ValidationError> {
+    pub fn validate_safe_path(path: &str) -> std::result::Result<(), ValidatorError> {
         if path.contains("..") || path.contains("~") {
-            return Err(ValidationError::new("unsafe_path"));
+            return Err(ValidatorError::new("unsafe_path"));
         }
         
         // Check for absolute paths on Unix systems
         if path.starts_with('/') {
-            return Err(ValidationError::new("absolute_path_not_allowed"));
+            return Err(ValidatorError::new("absolute_path_not_allowed"));
         }
         
         // Check for Windows drive letters
         if path.len() >= 2 && path.chars().nth(1) == Some(':') {
-            return Err(ValidationError::new("windows_drive_not_allowed"));
+            return Err(ValidatorError::new("windows_drive_not_allowed"));
         }
         
         Ok(())
     }
     
     /// Validate file extension is allowed
-    pub fn validate_allowed_extension(path: &str, allowed: &[&str]) -> std::result::Result<(), ValidationError> {
+    pub fn validate_allowed_extension(path: &str, allowed: &[&str]) -> std::result::Result<(), ValidatorError> {
         if let Some(extension) = std::path::Path::new(path).extension() {
             if let Some(ext_str) = extension.to_str() {
                 if allowed.contains(&ext_str.to_lowercase().as_str()) {
@@ -132,12 +133,125 @@ impl FilePathValidator {
             }
         }
         
-        let mut error = ValidationError::new("invalid_file_extension");
+        let mut error = ValidatorError::new("invalid_file_extension");
         error.add_param(std::borrow::Cow::from("allowed"), &allowed.join(", "));
         Err(error)
     }
 }
 
+/// A single failed validation rule, serializable for field-level API errors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub field: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: impl Into<String>, rule: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            rule: rule.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<ValidationError> for WritemagicError {
+    fn from(error: ValidationError) -> Self {
+        WritemagicError::validation(error.to_string())
+    }
+}
+
+pub const DOCUMENT_TITLE_MAX_LENGTH: usize = 255;
+pub const DOCUMENT_CONTENT_MAX_LENGTH: usize = 10_485_760;
+pub const PROJECT_NAME_MAX_LENGTH: usize = 100;
+pub const TAG_MAX_LENGTH: usize = 32;
+pub const SYSTEM_PROMPT_MAX_LENGTH: usize = 16_000;
+
+/// Check a length in characters against `1..=max` (or `0..=max` when empty values are allowed)
+fn validate_char_length(
+    field: &str,
+    value: &str,
+    allow_empty: bool,
+    max: usize,
+) -> std::result::Result<(), ValidationError> {
+    if !allow_empty && value.is_empty() {
+        return Err(ValidationError::new(field, "required", "must not be empty"));
+    }
+    if value.chars().count() > max {
+        return Err(ValidationError::new(
+            field,
+            "max_length",
+            format!("must be at most {} characters", max),
+        ));
+    }
+    Ok(())
+}
+
+/// Rules for a document title; surrounding whitespace is ignored
+pub fn validate_document_title(title: &str) -> std::result::Result<(), ValidationError> {
+    validate_char_length("title", title.trim(), false, DOCUMENT_TITLE_MAX_LENGTH)
+}
+
+/// Rules for document content, which may be empty
+pub fn validate_document_content(content: &str) -> std::result::Result<(), ValidationError> {
+    validate_char_length("content", content, true, DOCUMENT_CONTENT_MAX_LENGTH)
+}
+
+/// Rules for a project name; surrounding whitespace is ignored
+pub fn validate_project_name(name: &str) -> std::result::Result<(), ValidationError> {
+    validate_char_length("name", name.trim(), false, PROJECT_NAME_MAX_LENGTH)
+}
+
+/// Rules for a project's AI system prompt; surrounding whitespace is ignored
+pub fn validate_system_prompt(prompt: &str) -> std::result::Result<(), ValidationError> {
+    validate_char_length("system_prompt", prompt.trim(), false, SYSTEM_PROMPT_MAX_LENGTH)
+}
+
+/// Rules for a single document tag
+pub fn validate_tag(tag: &str) -> std::result::Result<(), ValidationError> {
+    if tag.is_empty() || tag.len() > TAG_MAX_LENGTH {
+        return Err(ValidationError::new(
+            "tags",
+            "length",
+            format!("tag '{}' must be between 1 and {} characters", tag, TAG_MAX_LENGTH),
+        ));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err(ValidationError::new(
+            "tags",
+            "charset",
+            format!("tag '{}' may only contain lowercase letters, digits, '-' and '_'", tag),
+        ));
+    }
+    Ok(())
+}
+
+/// Rules for a list of tags, reporting every invalid entry
+pub fn validate_tags<S: AsRef<str>>(tags: &[S]) -> std::result::Result<(), Vec<ValidationError>> {
+    let errors: Vec<ValidationError> = tags
+        .iter()
+        .filter_map(|tag| validate_tag(tag.as_ref()).err())
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
 /// Convert validation errors to WriteMagic errors
 pub fn validation_errors_to_writemagic_error(errors: ValidationErrors) -> WritemagicError {
     let mut messages = Vec::new();
@@ -170,4 +284,49 @@ pub fn validation_errors_to_writemagic_error(errors: ValidationErrors) -> Writem
 /// Validate with context
 pub fn validate_with_context<T: Validate>(value: &T) -> Result<()> {
     value.validate().map_err(validation_errors_to_writemagic_error)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_title_rules() {
+        assert!(validate_document_title("  Draft  ").is_ok());
+        assert_eq!(validate_document_title("   ").unwrap_err().rule, "required");
+
+        let long = "é".repeat(DOCUMENT_TITLE_MAX_LENGTH + 1);
+        let error = validate_document_title(&long).unwrap_err();
+        assert_eq!(error.field, "title");
+        assert_eq!(error.rule, "max_length");
+        assert!(validate_document_title(&"é".repeat(DOCUMENT_TITLE_MAX_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn test_content_and_project_name_rules() {
+        assert!(validate_document_content("").is_ok());
+        assert_eq!(validate_project_name("").unwrap_err().field, "name");
+        assert!(validate_project_name(&"p".repeat(PROJECT_NAME_MAX_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_tag_rules_report_every_invalid_tag() {
+        assert!(validate_tag("rust_2024").is_ok());
+        assert_eq!(validate_tag("Rust").unwrap_err().rule, "charset");
+
+        let errors = validate_tags(&["ok", "", "Bad Tag"]).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].rule, "length");
+    }
+
+    #[test]
+    fn test_validation_error_serializes_field_rule_and_message() {
+        let error = ValidationError::new("title", "required", "must not be empty");
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["field"], "title");
+        assert_eq!(json["rule"], "required");
+        assert_eq!(json["message"], "must not be empty");
+
+        let converted: WritemagicError = error.into();
+        assert!(matches!(converted, WritemagicError::Validation { .. }));
+    }
+}
@@ -133,17 +133,19 @@ pub struct DiffStats {
 }
 
 /// A hunk is a contiguous block of changes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiffHunk {
     pub start_line_old: usize,
     pub start_line_new: usize,
     pub lines_old: usize,
     pub lines_new: usize,
+    /// Overall kind of change the hunk makes
+    pub change_type: ChangeType,
     pub lines: Vec<DiffLine>,
 }
 
 /// A single line in a diff
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiffLine {
     pub line_type: DiffLineType,
     pub content: String,
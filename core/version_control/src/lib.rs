@@ -1,8 +1,10 @@
 //! Version control domain - Git integration with timeline visualization
 
 pub mod entities;
+pub mod services;
 
 pub use entities::*;
+pub use services::*;
 
 /// Git repository abstraction
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -0,0 +1,452 @@
+//! Version control domain services
+
+use crate::entities::{ChangeType, DiffHunk, DiffLine, DiffLineType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Line-matching strategy used when diffing text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DiffAlgorithm {
+    /// Shortest edit script (Myers, 1986)
+    #[default]
+    Myers,
+    /// Anchors on lines that occur exactly once on both sides, which keeps
+    /// reordered paragraphs readable; falls back to Myers between anchors
+    Patience,
+}
+
+/// A single step of an edit script, indexing into the old and new lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Line-based diffing of document text
+#[derive(Debug, Clone)]
+pub struct DiffService {
+    context_lines: usize,
+}
+
+impl Default for DiffService {
+    fn default() -> Self {
+        Self { context_lines: 3 }
+    }
+}
+
+impl DiffService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of unchanged lines kept around each change
+    pub fn with_context_lines(mut self, context_lines: usize) -> Self {
+        self.context_lines = context_lines;
+        self
+    }
+
+    /// Diff two versions of a text line by line
+    ///
+    /// Line numbers are 1-based. A hunk with no lines on one side starts at
+    /// the line the change would occupy on that side. The result only depends
+    /// on the inputs, so the same pair of texts always yields the same hunks.
+    pub fn diff_text(&self, old: &str, new: &str, algorithm: DiffAlgorithm) -> Vec<DiffHunk> {
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+
+        let mut edits = Vec::new();
+        match algorithm {
+            DiffAlgorithm::Myers => myers(&old_lines, &new_lines, 0, 0, &mut edits),
+            DiffAlgorithm::Patience => patience(&old_lines, &new_lines, 0, 0, &mut edits),
+        }
+
+        let mut hunks = self.group_hunks(&edits, &old_lines, &new_lines);
+        mark_moves(&mut hunks);
+        hunks
+    }
+
+    /// Split an edit script into hunks, merging changes whose context would overlap
+    fn group_hunks(&self, edits: &[Edit], old: &[&str], new: &[&str]) -> Vec<DiffHunk> {
+        let changed: Vec<usize> = edits
+            .iter()
+            .enumerate()
+            .filter(|(_, edit)| !matches!(edit, Edit::Equal(..)))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &i in &changed {
+            let start = i.saturating_sub(self.context_lines);
+            let end = (i + self.context_lines + 1).min(edits.len());
+            match ranges.last_mut() {
+                Some(last) if start <= last.1 => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        // Position in each text before every edit
+        let mut positions = Vec::with_capacity(edits.len());
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for edit in edits {
+            positions.push((old_pos, new_pos));
+            match edit {
+                Edit::Equal(..) => {
+                    old_pos += 1;
+                    new_pos += 1;
+                }
+                Edit::Delete(_) => old_pos += 1,
+                Edit::Insert(_) => new_pos += 1,
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start, end)| {
+                let lines: Vec<DiffLine> = edits[start..end]
+                    .iter()
+                    .map(|edit| match *edit {
+                        Edit::Equal(o, n) => DiffLine {
+                            line_type: DiffLineType::Context,
+                            content: old[o].to_string(),
+                            line_number_old: Some(o + 1),
+                            line_number_new: Some(n + 1),
+                        },
+                        Edit::Delete(o) => DiffLine {
+                            line_type: DiffLineType::Deletion,
+                            content: old[o].to_string(),
+                            line_number_old: Some(o + 1),
+                            line_number_new: None,
+                        },
+                        Edit::Insert(n) => DiffLine {
+                            line_type: DiffLineType::Addition,
+                            content: new[n].to_string(),
+                            line_number_old: None,
+                            line_number_new: Some(n + 1),
+                        },
+                    })
+                    .collect();
+
+                let lines_old = lines.iter().filter(|l| l.line_type != DiffLineType::Addition).count();
+                let lines_new = lines.iter().filter(|l| l.line_type != DiffLineType::Deletion).count();
+                let (start_old, start_new) = positions[start];
+
+                DiffHunk {
+                    start_line_old: start_old + 1,
+                    start_line_new: start_new + 1,
+                    lines_old,
+                    lines_new,
+                    change_type: classify(&lines),
+                    lines,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Changed lines of one kind, in order
+fn changed_lines(lines: &[DiffLine], line_type: DiffLineType) -> Vec<&str> {
+    lines
+        .iter()
+        .filter(|line| line.line_type == line_type)
+        .map(|line| line.content.as_str())
+        .collect()
+}
+
+/// Change type of a single hunk, treating lines reordered within it as a move
+fn classify(lines: &[DiffLine]) -> ChangeType {
+    let mut deleted = changed_lines(lines, DiffLineType::Deletion);
+    let mut added = changed_lines(lines, DiffLineType::Addition);
+
+    match (deleted.is_empty(), added.is_empty()) {
+        (true, _) => ChangeType::Addition,
+        (_, true) => ChangeType::Deletion,
+        _ => {
+            deleted.sort_unstable();
+            added.sort_unstable();
+            if deleted == added {
+                ChangeType::Move
+            } else {
+                ChangeType::Modification
+            }
+        }
+    }
+}
+
+/// Pair each pure deletion with a later or earlier pure addition of the same lines
+fn mark_moves(hunks: &mut [DiffHunk]) {
+    for deletion in 0..hunks.len() {
+        if hunks[deletion].change_type != ChangeType::Deletion {
+            continue;
+        }
+        let removed = changed_lines(&hunks[deletion].lines, DiffLineType::Deletion);
+        let addition = (0..hunks.len()).find(|&i| {
+            hunks[i].change_type == ChangeType::Addition
+                && changed_lines(&hunks[i].lines, DiffLineType::Addition) == removed
+        });
+        if let Some(addition) = addition {
+            hunks[deletion].change_type = ChangeType::Move;
+            hunks[addition].change_type = ChangeType::Move;
+        }
+    }
+}
+
+/// Myers' O(ND) shortest edit script, appended to `out` with indices shifted by the offsets
+fn myers(old: &[&str], new: &[&str], old_offset: usize, new_offset: usize, out: &mut Vec<Edit>) {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (n + m) as usize;
+    if max == 0 {
+        return;
+    }
+
+    let offset = max as isize;
+    let mut v = vec![0isize; 2 * max + 2];
+    // Furthest x per diagonal k in -d..=d before each round d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'rounds: for d in 0..=max as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'rounds;
+            }
+            k += 2;
+        }
+    }
+
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, snapshot) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| snapshot[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            script.push(Edit::Equal(x as usize + old_offset, y as usize + new_offset));
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                script.push(Edit::Insert(y as usize + new_offset));
+            } else {
+                x -= 1;
+                script.push(Edit::Delete(x as usize + old_offset));
+            }
+        }
+    }
+
+    script.reverse();
+    out.extend(script);
+}
+
+/// Patience diff, appended to `out` with indices shifted by the offsets
+fn patience(old: &[&str], new: &[&str], old_offset: usize, new_offset: usize, out: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    out.extend((0..prefix).map(|i| Edit::Equal(i + old_offset, i + new_offset)));
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (middle_old_offset, middle_new_offset) = (old_offset + prefix, new_offset + prefix);
+
+    if old_middle.is_empty() || new_middle.is_empty() {
+        out.extend((0..old_middle.len()).map(|i| Edit::Delete(i + middle_old_offset)));
+        out.extend((0..new_middle.len()).map(|i| Edit::Insert(i + middle_new_offset)));
+    } else {
+        let anchors = unique_common_lines(old_middle, new_middle);
+        if anchors.is_empty() {
+            myers(old_middle, new_middle, middle_old_offset, middle_new_offset, out);
+        } else {
+            let (mut old_start, mut new_start) = (0, 0);
+            for (old_index, new_index) in anchors {
+                patience(
+                    &old_middle[old_start..old_index],
+                    &new_middle[new_start..new_index],
+                    middle_old_offset + old_start,
+                    middle_new_offset + new_start,
+                    out,
+                );
+                out.push(Edit::Equal(old_index + middle_old_offset, new_index + middle_new_offset));
+                old_start = old_index + 1;
+                new_start = new_index + 1;
+            }
+            patience(
+                &old_middle[old_start..],
+                &new_middle[new_start..],
+                middle_old_offset + old_start,
+                middle_new_offset + new_start,
+                out,
+            );
+        }
+    }
+
+    let old_suffix_start = old.len() - suffix;
+    let new_suffix_start = new.len() - suffix;
+    out.extend((0..suffix).map(|i| Edit::Equal(old_suffix_start + i + old_offset, new_suffix_start + i + new_offset)));
+}
+
+/// Longest increasing run of lines that appear exactly once in both texts
+fn unique_common_lines(old: &[&str], new: &[&str]) -> Vec<(usize, usize)> {
+    let mut counts: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        let entry = counts.entry(*line).or_insert((0, 0, i));
+        entry.0 += 1;
+    }
+    let mut new_index: HashMap<&str, usize> = HashMap::new();
+    for (j, line) in new.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(line) {
+            entry.1 += 1;
+            new_index.insert(*line, j);
+        }
+    }
+
+    // Candidate pairs in old order, so the result doesn't depend on map iteration
+    let pairs: Vec<(usize, usize)> = old
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| matches!(counts.get(*line), Some((1, 1, _))))
+        .map(|(i, line)| (i, new_index[line]))
+        .collect();
+
+    // Patience sorting: piles hold the index of the pair on top, with a back-link
+    let mut piles: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (p, &(_, j)) in pairs.iter().enumerate() {
+        let pile = piles.partition_point(|&top| pairs[top].1 < j);
+        previous[p] = pile.checked_sub(1).map(|below| piles[below]);
+        if pile == piles.len() {
+            piles.push(p);
+        } else {
+            piles[pile] = p;
+        }
+    }
+
+    let mut anchors = Vec::with_capacity(piles.len());
+    let mut current = piles.last().copied();
+    while let Some(p) = current {
+        anchors.push(pairs[p]);
+        current = previous[p];
+    }
+    anchors.reverse();
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [DiffAlgorithm; 2] = [DiffAlgorithm::Myers, DiffAlgorithm::Patience];
+
+    /// Hunk contents in unified-diff notation
+    fn render(hunk: &DiffHunk) -> Vec<String> {
+        hunk.lines
+            .iter()
+            .map(|line| {
+                let marker = match line.line_type {
+                    DiffLineType::Context => ' ',
+                    DiffLineType::Addition => '+',
+                    DiffLineType::Deletion => '-',
+                };
+                format!("{}{}", marker, line.content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_insertion_and_deletion_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni";
+        let new = "a\nb\nc\nd\nnew\ne\nf\ng\ni";
+
+        for algorithm in ALGORITHMS {
+            let hunks = DiffService::new().with_context_lines(1).diff_text(old, new, algorithm);
+
+            assert_eq!(hunks.len(), 2, "{:?}", algorithm);
+            assert_eq!(hunks[0].change_type, ChangeType::Addition);
+            assert_eq!(render(&hunks[0]), vec![" d", "+new", " e"]);
+            assert_eq!((hunks[0].start_line_old, hunks[0].lines_old), (4, 2));
+            assert_eq!((hunks[0].start_line_new, hunks[0].lines_new), (4, 3));
+
+            assert_eq!(hunks[1].change_type, ChangeType::Deletion);
+            assert_eq!(render(&hunks[1]), vec![" g", "-h", " i"]);
+            assert_eq!((hunks[1].start_line_old, hunks[1].start_line_new), (7, 8));
+        }
+    }
+
+    #[test]
+    fn test_overlapping_context_merges_hunks() {
+        let old = "a\nb\nc\nd\ne";
+        let new = "a\nB\nc\nD\ne";
+
+        let hunks = DiffService::new().with_context_lines(1).diff_text(old, new, DiffAlgorithm::Myers);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].change_type, ChangeType::Modification);
+
+        let hunks = DiffService::new().with_context_lines(0).diff_text(old, new, DiffAlgorithm::Myers);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(render(&hunks[1]), vec!["-d", "+D"]);
+    }
+
+    #[test]
+    fn test_moved_paragraph_is_reported_as_move() {
+        let old = "Intro\nMoved line\nOne\nTwo\nThree\nFour\nFive\nSix\nSeven\nOutro";
+        let new = "Intro\nOne\nTwo\nThree\nFour\nFive\nSix\nSeven\nMoved line\nOutro";
+
+        for algorithm in ALGORITHMS {
+            let hunks = DiffService::new().with_context_lines(1).diff_text(old, new, algorithm);
+
+            assert_eq!(hunks.len(), 2, "{:?}", algorithm);
+            assert!(hunks.iter().all(|hunk| hunk.change_type == ChangeType::Move));
+        }
+    }
+
+    #[test]
+    fn test_whitespace_only_change_is_a_modification() {
+        let hunks = DiffService::new().diff_text("Title\nBody text\n", "Title\nBody  text \n", DiffAlgorithm::Myers);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].change_type, ChangeType::Modification);
+        assert_eq!(render(&hunks[0]), vec![" Title", "-Body text", "+Body  text "]);
+    }
+
+    #[test]
+    fn test_patience_anchors_on_unique_lines() {
+        let old = "fn a() {\n}\nfn b() {\n}";
+        let new = "fn b() {\n}\nfn c() {\n}";
+
+        let hunks = DiffService::new().with_context_lines(0).diff_text(old, new, DiffAlgorithm::Patience);
+        let lines: Vec<String> = hunks.iter().flat_map(render).collect();
+        assert_eq!(lines, vec!["-fn a() {", "-}", "+}", "+fn c() {"]);
+
+        let service = DiffService::new();
+        assert_eq!(
+            service.diff_text(old, new, DiffAlgorithm::Patience),
+            service.diff_text(old, new, DiffAlgorithm::Patience)
+        );
+        assert!(service.diff_text(old, old, DiffAlgorithm::Myers).is_empty());
+    }
+}
@@ -4,7 +4,6 @@
 
 import init, { 
     WriteMagicEngine, 
-    WasmCompletionRequest,
     init_logging,
     init_panic_hook 
 } from './pkg/writemagic_wasm.js';
@@ -20,19 +19,18 @@ async function main() {
     // Create and configure the engine
     const engine = new WriteMagicEngine();
     
-    // Configuration with AI providers (optional)
+    // Engine configuration, in the shape of ApplicationConfig; omitted sections keep their defaults.
+    // The WASM build has no AI providers of its own, so it takes no API keys:
+    // completions go through a backend proxy that holds them.
     const config = {
-        claude_api_key: process.env.CLAUDE_API_KEY, // Optional
-        openai_api_key: process.env.OPENAI_API_KEY, // Optional
-        default_model: "claude-3-haiku-20240307",
-        log_level: "info",
-        enable_content_filtering: true,
-        database_type: "indexeddb"
+        logging: { level: "info" },
+        timestamp_format: "rfc3339"
     };
+    const aiProxyEndpoint = process.env.WRITEMAGIC_AI_PROXY; // Optional
     
     try {
         // Initialize the engine
-        await engine.initialize(JSON.stringify(config));
+        await engine.initialize(config);
         console.log('Engine initialized:', engine.is_initialized());
         
         // Create a new document
@@ -72,29 +70,22 @@ async function main() {
         const projectDocuments = await engine.list_project_documents(project.id);
         console.log('Documents in project:', projectDocuments.length);
         
-        // Test AI completion (if API keys are configured)
-        if (config.claude_api_key || config.openai_api_key) {
+        // Test AI completion (if an AI proxy is configured)
+        if (aiProxyEndpoint) {
             try {
-                const completionRequest = new WasmCompletionRequest(
-                    "Write a creative opening paragraph for a science fiction story.",
-                    "claude-3-haiku-20240307"
-                );
-                completionRequest.set_max_tokens(150);
-                completionRequest.set_temperature(0.7);
-                
-                const aiResponse = await engine.complete_text(completionRequest);
-                console.log('AI Response:', aiResponse.content);
-                console.log('Tokens used:', aiResponse.tokens_used);
-                
-                // Check AI provider health
-                const health = await engine.get_ai_provider_health();
-                console.log('AI Provider Health:', health);
-                
+                engine.set_ai_proxy_endpoint(aiProxyEndpoint);
+                const aiResponse = await engine.ai_completion(JSON.stringify({
+                    prompt: "Write a creative opening paragraph for a science fiction story.",
+                    model: "claude-3-haiku-20240307",
+                    max_tokens: 150,
+                    temperature: 0.7
+                }));
+                console.log('AI Response:', aiResponse);
             } catch (aiError) {
-                console.log('AI completion failed (API keys may not be configured):', aiError.message);
+                console.log('AI completion failed (the proxy may be unreachable):', aiError.message);
             }
         } else {
-            console.log('AI features disabled - no API keys configured');
+            console.log('AI features disabled - no AI proxy configured');
         }
         
         // Retrieve document by ID
@@ -7,21 +7,24 @@
 
 use wasm_bindgen::prelude::*;
 use js_sys::Promise;
-use web_sys::console;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{console, Request, RequestInit, Response};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 // Import core WriteMagic types and services
 use writemagic_writing::ProjectName;
 use writemagic_shared::{
-    WritemagicError, EntityId,
+    WritemagicError, EntityId, ProviderError,
 };
 
 use writemagic_writing::{
     CoreEngine, ApplicationConfig,
     Document, 
     DocumentTitle, DocumentContent,
+    TimestampFormat, WireTimestamp,
+    IndexedDbManager, SyncEntry, SyncOperation, SyncQueue, UploadOutcome,
 };
 
 // Note: AI, version-control, and agent domains not available in WASM build
@@ -46,6 +49,8 @@ pub fn initialize() {
 pub struct WasmError {
     message: String,
     code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider_error: Option<ProviderError>,
 }
 
 impl WasmError {
@@ -58,6 +63,11 @@ impl WasmError {
     pub fn code(&self) -> String {
         self.code.clone()
     }
+
+    /// Get structured details for AI provider errors, if any
+    pub fn provider_error(&self) -> Option<ProviderError> {
+        self.provider_error.clone()
+    }
 }
 
 impl From<WritemagicError> for WasmError {
@@ -65,13 +75,19 @@ impl From<WritemagicError> for WasmError {
         let (message, code) = match &error {
             WritemagicError::Validation { message } => (message.clone(), "VALIDATION_ERROR".to_string()),
             WritemagicError::Repository { message } => (message.clone(), "REPOSITORY_ERROR".to_string()),
-            WritemagicError::AiProvider { message } => (message.clone(), "AI_PROVIDER_ERROR".to_string()),
+            WritemagicError::AiProvider { message, .. } => (message.clone(), "AI_PROVIDER_ERROR".to_string()),
             WritemagicError::Configuration { message } => (message.clone(), "CONFIGURATION_ERROR".to_string()),
             WritemagicError::Internal { message, .. } => (message.clone(), "INTERNAL_ERROR".to_string()),
+            WritemagicError::StorageQuotaExceeded { message } => (message.clone(), "STORAGE_QUOTA_EXCEEDED".to_string()),
+            WritemagicError::BudgetExceeded { .. } => (error.to_string(), "BUDGET_EXCEEDED".to_string()),
             _ => (error.to_string(), "UNKNOWN_ERROR".to_string()),
         };
         
-        WasmError { message, code }
+        WasmError {
+            message,
+            code,
+            provider_error: error.provider_error().cloned(),
+        }
     }
 }
 
@@ -80,15 +96,33 @@ impl From<uuid::Error> for WasmError {
         WasmError {
             message: format!("UUID error: {}", error),
             code: "UUID_ERROR".to_string(),
+            provider_error: None,
         }
     }
 }
 
+/// Serialize a value into a plain JavaScript object
+fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| {
+        WasmError {
+            message: format!("Serialization error: {}", e),
+            code: "SERIALIZATION_ERROR".to_string(),
+            provider_error: None,
+        }
+        .into()
+    })
+}
+
 impl From<WasmError> for JsValue {
     fn from(error: WasmError) -> Self {
         let obj = js_sys::Object::new();
         js_sys::Reflect::set(&obj, &"message".into(), &error.message.into()).unwrap();
         js_sys::Reflect::set(&obj, &"code".into(), &error.code.into()).unwrap();
+        if let Some(details) = &error.provider_error {
+            if let Ok(details) = serde_wasm_bindgen::to_value(details) {
+                js_sys::Reflect::set(&obj, &"providerError".into(), &details).unwrap();
+            }
+        }
         obj.into()
     }
 }
@@ -104,8 +138,10 @@ pub struct WasmDocument {
     content_type: String,
     word_count: u32,
     character_count: u32,
-    created_at: String,
-    updated_at: String,
+    language: String,
+    tags: Vec<String>,
+    created_at: WireTimestamp,
+    updated_at: WireTimestamp,
     created_by: Option<String>,
     is_deleted: bool,
 }
@@ -153,16 +189,28 @@ impl WasmDocument {
         self.character_count
     }
 
+    /// Get the document language as an ISO 639 code
+    #[wasm_bindgen(getter)]
+    pub fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    /// Get the document tags as a JavaScript array
+    #[wasm_bindgen(getter)]
+    pub fn tags(&self) -> js_sys::Array {
+        self.tags.iter().map(|tag| JsValue::from_str(tag)).collect()
+    }
+
     /// Get the creation timestamp
     #[wasm_bindgen(getter)]
-    pub fn created_at(&self) -> String {
-        self.created_at.clone()
+    pub fn created_at(&self) -> JsValue {
+        wire_timestamp_to_js(&self.created_at)
     }
 
     /// Get the last update timestamp
     #[wasm_bindgen(getter)]
-    pub fn updated_at(&self) -> String {
-        self.updated_at.clone()
+    pub fn updated_at(&self) -> JsValue {
+        wire_timestamp_to_js(&self.updated_at)
     }
 
     /// Get the creator ID if available
@@ -178,9 +226,9 @@ impl WasmDocument {
     }
 }
 
-/// Convert from Document to WasmDocument
-impl From<&Document> for WasmDocument {
-    fn from(doc: &Document) -> Self {
+impl WasmDocument {
+    /// Convert from Document, emitting timestamps in `timestamp_format`
+    pub fn from_document(doc: &Document, timestamp_format: TimestampFormat) -> Self {
         Self {
             id: doc.id.to_string(),
             title: doc.title.clone(),
@@ -189,31 +237,35 @@ impl From<&Document> for WasmDocument {
             content_type: format!("{:?}", doc.content_type),
             word_count: doc.word_count,
             character_count: doc.character_count,
-            created_at: doc.created_at.as_datetime().to_rfc3339(),
-            updated_at: doc.updated_at.as_datetime().to_rfc3339(),
+            language: doc.effective_language().to_string(),
+            tags: doc.tags.iter().map(|tag| tag.to_string()).collect(),
+            created_at: timestamp_format.encode(&doc.created_at),
+            updated_at: timestamp_format.encode(&doc.updated_at),
             created_by: doc.created_by.as_ref().map(|id| id.to_string()),
             is_deleted: doc.is_deleted,
         }
     }
 }
 
+/// Convert from Document to WasmDocument
+impl From<&Document> for WasmDocument {
+    fn from(doc: &Document) -> Self {
+        Self::from_document(doc, TimestampFormat::default())
+    }
+}
+
 /// Convert from DocumentAggregate to WasmDocument  
 impl From<&writemagic_writing::DocumentAggregate> for WasmDocument {
     fn from(aggregate: &writemagic_writing::DocumentAggregate) -> Self {
-        let doc = aggregate.document();
-        Self {
-            id: doc.id.to_string(),
-            title: doc.title.clone(),
-            content: doc.content.clone(),
-            project_id: None, // TODO: Add project association when needed
-            content_type: format!("{:?}", doc.content_type),
-            word_count: doc.word_count,
-            character_count: doc.character_count,
-            created_at: doc.created_at.as_datetime().to_rfc3339(),
-            updated_at: doc.updated_at.as_datetime().to_rfc3339(),
-            created_by: doc.created_by.as_ref().map(|id| id.to_string()),
-            is_deleted: doc.is_deleted,
-        }
+        Self::from(aggregate.document())
+    }
+}
+
+/// Timestamps are numbers in epoch-millis format and strings otherwise
+fn wire_timestamp_to_js(timestamp: &WireTimestamp) -> JsValue {
+    match timestamp {
+        WireTimestamp::EpochMillis(millis) => JsValue::from_f64(*millis as f64),
+        WireTimestamp::Rfc3339(value) => JsValue::from_str(value),
     }
 }
 
@@ -225,8 +277,8 @@ pub struct WasmProject {
     name: String,
     description: Option<String>,
     document_ids: Vec<String>,
-    created_at: String,
-    updated_at: String,
+    created_at: WireTimestamp,
+    updated_at: WireTimestamp,
     created_by: Option<String>,
     is_archived: bool,
 }
@@ -262,14 +314,14 @@ impl WasmProject {
 
     /// Get the project creation timestamp
     #[wasm_bindgen(getter)]
-    pub fn created_at(&self) -> String {
-        self.created_at.clone()
+    pub fn created_at(&self) -> JsValue {
+        wire_timestamp_to_js(&self.created_at)
     }
 
     /// Get the project last update timestamp  
     #[wasm_bindgen(getter)]
-    pub fn updated_at(&self) -> String {
-        self.updated_at.clone()
+    pub fn updated_at(&self) -> JsValue {
+        wire_timestamp_to_js(&self.updated_at)
     }
 
     /// Get the project creator ID if available
@@ -285,6 +337,32 @@ impl WasmProject {
     }
 }
 
+/// Readability statistics for WASM bindings
+#[cfg(feature = "analysis")]
+#[derive(Debug, Clone, Serialize)]
+struct WasmReadability {
+    flesch_reading_ease: f64,
+    flesch_kincaid_grade_level: f64,
+    reading_level: &'static str,
+    sentences: u32,
+    words: u32,
+    syllables: u32,
+}
+
+#[cfg(feature = "analysis")]
+impl From<&writemagic_writing::ReadabilityAnalysis> for WasmReadability {
+    fn from(analysis: &writemagic_writing::ReadabilityAnalysis) -> Self {
+        Self {
+            flesch_reading_ease: analysis.flesch_reading_ease,
+            flesch_kincaid_grade_level: analysis.flesch_kincaid_grade_level,
+            reading_level: analysis.reading_level_description(),
+            sentences: analysis.sentences,
+            words: analysis.words,
+            syllables: analysis.syllables,
+        }
+    }
+}
+
 // Note: AI completion structs removed - not available in WASM build due to native networking dependencies
 
 /// Main WriteMagic engine for WASM
@@ -292,6 +370,11 @@ impl WasmProject {
 pub struct WriteMagicEngine {
     #[allow(dead_code)]
     inner: Rc<RefCell<Option<CoreEngine>>>,
+    timestamp_format: Rc<Cell<TimestampFormat>>,
+    /// Backend that `ai_completion` forwards requests to
+    ai_proxy_endpoint: Rc<RefCell<Option<String>>>,
+    /// Local mutations awaiting upload; absent when IndexedDB is unavailable
+    sync_queue: Rc<RefCell<Option<SyncQueue>>>,
 }
 
 #[wasm_bindgen]
@@ -301,30 +384,54 @@ impl WriteMagicEngine {
     pub fn new() -> Self {
         Self {
             inner: Rc::new(RefCell::new(None)),
+            timestamp_format: Rc::new(Cell::new(TimestampFormat::default())),
+            ai_proxy_endpoint: Rc::new(RefCell::new(None)),
+            sync_queue: Rc::new(RefCell::new(None)),
         }
     }
 
-    /// Initialize the engine with configuration
-    pub fn initialize(&mut self, config_json: Option<String>) -> Promise {
+    /// Initialize the engine with a configuration object
+    ///
+    /// Pass `undefined` or `null` to use the default configuration.
+    pub fn initialize(&mut self, config: JsValue) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.clone();
+        let sync_queue = self.sync_queue.clone();
         
         wasm_bindgen_futures::future_to_promise(async move {
-            let _config = if let Some(json) = config_json {
-                serde_json::from_str::<ApplicationConfig>(&json)
+            let config = if !config.is_undefined() && !config.is_null() {
+                serde_wasm_bindgen::from_value::<ApplicationConfig>(config)
                     .map_err(|e| WasmError {
                         message: format!("Invalid configuration: {}", e),
                         code: "CONFIG_ERROR".to_string(),
+                        provider_error: None,
                     })?
             } else {
                 ApplicationConfig::default()
             };
+            timestamp_format.set(config.timestamp_format);
 
             // For WASM, use in-memory storage for now
             let engine = CoreEngine::new_in_memory()
                 .await
                 .map_err(WasmError::from)?;
+            // Share the engine's IndexedDB connection when it has one
+            let engine_manager = engine.indexeddb_manager();
             
             *inner.borrow_mut() = Some(engine);
+
+            // Offline sync is optional; editing works without it
+            let manager = match engine_manager {
+                Some(manager) => Ok(manager),
+                None => {
+                    let mut manager = IndexedDbManager::with_defaults();
+                    manager.initialize().await.map(|()| std::sync::Arc::new(tokio::sync::Mutex::new(manager)))
+                }
+            };
+            match manager {
+                Ok(manager) => *sync_queue.borrow_mut() = Some(SyncQueue::new(manager)),
+                Err(e) => console::warn_1(&format!("Offline sync unavailable: {}", e).into()),
+            }
             
             Ok(JsValue::from("Engine initialized successfully"))
         })
@@ -333,12 +440,15 @@ impl WriteMagicEngine {
     /// Create a new document
     pub fn create_document(&self, title: String, content: String, project_id: Option<String>) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.get();
+        let sync_queue = self.sync_queue.clone();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let doc_title = DocumentTitle::new(title).map_err(WasmError::from)?;
@@ -349,30 +459,27 @@ impl WriteMagicEngine {
                 .map_err(WasmError::from)?;
 
             let document = engine.document_management_service()
-                .create_document(doc_title, doc_content, writemagic_shared::ContentType::Markdown, None)
+                .create_document(doc_title, doc_content, writemagic_shared::ContentType::Markdown, None, None)
                 .await
                 .map_err(WasmError::from)?;
+            record_sync(&sync_queue, document.document(), SyncOperation::Create).await;
 
-            let wasm_doc = WasmDocument::from(&document);
-            let serialized = serde_wasm_bindgen::to_value(&wasm_doc)
-                .map_err(|e| WasmError {
-                    message: format!("Serialization error: {}", e),
-                    code: "SERIALIZATION_ERROR".to_string(),
-                })?;
-
-            Ok(serialized)
+            let wasm_doc = WasmDocument::from_document(document.document(), timestamp_format);
+            to_js(&wasm_doc)
         })
     }
 
     /// Get a document by ID
     pub fn get_document(&self, id: String) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.get();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
@@ -384,65 +491,58 @@ impl WriteMagicEngine {
                 .ok_or_else(|| WasmError {
                     message: "Document not found".to_string(),
                     code: "DOCUMENT_NOT_FOUND".to_string(),
+                    provider_error: None,
                 })?;
 
-            let wasm_doc = WasmDocument::from(&document);
-            let serialized = serde_wasm_bindgen::to_value(&wasm_doc)
-                .map_err(|e| WasmError {
-                    message: format!("Serialization error: {}", e),
-                    code: "SERIALIZATION_ERROR".to_string(),
-                })?;
-
-            Ok(serialized)
+            let wasm_doc = WasmDocument::from_document(&document, timestamp_format);
+            to_js(&wasm_doc)
         })
     }
 
     /// Update a document
-    pub fn update_document(&self, id: String, _title: Option<String>, content: Option<String>) -> Promise {
+    pub fn update_document(&self, id: String, title: Option<String>, content: Option<String>) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.get();
+        let sync_queue = self.sync_queue.clone();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
             
-            // For now, we'll only support content updates since that's what's available in the service
-            let updated_document = if let Some(new_content) = content {
-                let doc_content = DocumentContent::new(new_content).map_err(WasmError::from)?;
-                engine.document_management_service()
-                    .update_document_content(entity_id, doc_content, None, None)
-                    .await
-                    .map_err(WasmError::from)?
-            } else {
-                // If only title update or no updates, just fetch the document
-                // TODO: Add title update support when available in service
-                return Ok(JsValue::from("Title updates not yet supported"));
-            };
-
-            let wasm_doc = WasmDocument::from(&updated_document);
-            let serialized = serde_wasm_bindgen::to_value(&wasm_doc)
-                .map_err(|e| WasmError {
-                    message: format!("Serialization error: {}", e),
-                    code: "SERIALIZATION_ERROR".to_string(),
-                })?;
-
-            Ok(serialized)
+            let doc_title = title.map(DocumentTitle::new).transpose().map_err(WasmError::from)?;
+            let doc_content = content.map(DocumentContent::new).transpose().map_err(WasmError::from)?;
+
+            let service = engine.document_management_service();
+            let updated_document = match (doc_title, doc_content) {
+                (Some(doc_title), None) => service.update_document_title(entity_id, doc_title, None).await,
+                (None, Some(doc_content)) => service.update_document_content(entity_id, doc_content, None, None).await,
+                (doc_title, doc_content) => service.update_document(entity_id, doc_title, doc_content, None).await,
+            }
+            .map_err(WasmError::from)?;
+            record_sync(&sync_queue, updated_document.document(), SyncOperation::Update).await;
+
+            let wasm_doc = WasmDocument::from_document(updated_document.document(), timestamp_format);
+            to_js(&wasm_doc)
         })
     }
 
     /// Delete a document
     pub fn delete_document(&self, id: String) -> Promise {
         let inner = self.inner.clone();
+        let sync_queue = self.sync_queue.clone();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
@@ -452,6 +552,11 @@ impl WriteMagicEngine {
                 .await
                 .map_err(WasmError::from)?;
 
+            // Deletion is soft, so the document still carries the version to upload
+            if let Some(document) = engine.document_repository().find_by_id(&entity_id).await.map_err(WasmError::from)? {
+                record_sync(&sync_queue, &document, SyncOperation::Delete).await;
+            }
+
             Ok(JsValue::from("Document deleted successfully"))
         })
     }
@@ -459,40 +564,41 @@ impl WriteMagicEngine {
     /// List all documents
     pub fn list_documents(&self) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.get();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let pagination = writemagic_shared::Pagination::new(0, 100).map_err(WasmError::from)?; // Default pagination
-            let documents = engine.document_repository()
-                .find_all(pagination)
+            let documents = engine.document_management_service()
+                .list_documents(pagination)
                 .await
                 .map_err(WasmError::from)?;
 
-            let wasm_docs: Vec<WasmDocument> = documents.iter().map(WasmDocument::from).collect();
-            let serialized = serde_wasm_bindgen::to_value(&wasm_docs)
-                .map_err(|e| WasmError {
-                    message: format!("Serialization error: {}", e),
-                    code: "SERIALIZATION_ERROR".to_string(),
-                })?;
-
-            Ok(serialized)
+            let wasm_docs: Vec<WasmDocument> = documents
+                .iter()
+                .map(|aggregate| WasmDocument::from_document(aggregate.document(), timestamp_format))
+                .collect();
+            to_js(&wasm_docs)
         })
     }
 
     /// Create a new project
     pub fn create_project(&self, name: String, description: Option<String>) -> Promise {
         let inner = self.inner.clone();
+        let timestamp_format = self.timestamp_format.get();
         
         wasm_bindgen_futures::future_to_promise(async move {
             let engine = inner.borrow();
             let engine = engine.as_ref().ok_or_else(|| WasmError {
                 message: "Engine not initialized".to_string(),
                 code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
             })?;
 
             let project_name = ProjectName::new(name).map_err(WasmError::from)?;
@@ -507,33 +613,259 @@ impl WriteMagicEngine {
                 name: project.project().name.clone(),
                 description: project.project().description.clone(),
                 document_ids: project.project().document_ids.iter().map(|id| id.to_string()).collect(),
-                created_at: project.project().created_at.as_datetime().to_rfc3339(),
-                updated_at: project.project().updated_at.as_datetime().to_rfc3339(),
+                created_at: timestamp_format.encode(&project.project().created_at),
+                updated_at: timestamp_format.encode(&project.project().updated_at),
                 created_by: project.project().created_by.as_ref().map(|id| id.to_string()),
-                is_archived: project.project().is_deleted, // Map is_deleted to is_archived for WASM
+                is_archived: project.project().is_archived,
             };
 
-            let serialized = serde_wasm_bindgen::to_value(&wasm_project)
-                .map_err(|e| WasmError {
-                    message: format!("Serialization error: {}", e),
-                    code: "SERIALIZATION_ERROR".to_string(),
+            to_js(&wasm_project)
+        })
+    }
+
+    /// Render a document's content in another format without changing the stored document
+    ///
+    /// `target` is a content type name such as `"markdown"`, `"html"` or `"plain_text"`.
+    #[cfg(feature = "export")]
+    pub fn export_document(&self, id: String, target: String) -> Promise {
+        let inner = self.inner.clone();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let engine = inner.borrow();
+            let engine = engine.as_ref().ok_or_else(|| WasmError {
+                message: "Engine not initialized".to_string(),
+                code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
+            })?;
+
+            let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
+            let target = writemagic_shared::ContentType::from_string(&target).map_err(|message| WasmError {
+                message,
+                code: "VALIDATION_ERROR".to_string(),
+                provider_error: None,
+            })?;
+
+            let document = engine.document_repository()
+                .find_by_id(&entity_id)
+                .await
+                .map_err(WasmError::from)?
+                .ok_or_else(|| WasmError {
+                    message: "Document not found".to_string(),
+                    code: "DOCUMENT_NOT_FOUND".to_string(),
+                    provider_error: None,
                 })?;
 
-            Ok(serialized)
+            let rendered = writemagic_writing::convert_content(&document.content, &document.content_type, &target)
+                .map_err(WasmError::from)?;
+
+            Ok(JsValue::from(rendered))
         })
     }
 
-    /// Request AI completion (Not available in WASM - requires native networking)
-    pub fn ai_completion(&self, _request_json: String) -> Promise {
+    /// Compute readability statistics for a document
+    #[cfg(feature = "analysis")]
+    pub fn analyze_document(&self, id: String) -> Promise {
+        let inner = self.inner.clone();
+
         wasm_bindgen_futures::future_to_promise(async move {
-            Err(WasmError {
-                message: "AI completion not available in WASM build. Use native or server-side AI integration.".to_string(),
+            let engine = inner.borrow();
+            let engine = engine.as_ref().ok_or_else(|| WasmError {
+                message: "Engine not initialized".to_string(),
+                code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
+            })?;
+
+            let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
+            let document = engine.document_repository()
+                .find_by_id(&entity_id)
+                .await
+                .map_err(WasmError::from)?
+                .ok_or_else(|| WasmError {
+                    message: "Document not found".to_string(),
+                    code: "DOCUMENT_NOT_FOUND".to_string(),
+                    provider_error: None,
+                })?;
+
+            let content = DocumentContent::new(document.content).map_err(WasmError::from)?;
+            let analysis = engine.content_analysis_service().analyze_readability(&content);
+
+            to_js(&WasmReadability::from(&analysis))
+        })
+    }
+
+    /// Count words, sentences and paragraphs and estimate reading time for a document
+    #[cfg(feature = "analysis")]
+    pub fn get_document_statistics(&self, id: String) -> Promise {
+        let inner = self.inner.clone();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let engine = inner.borrow();
+            let engine = engine.as_ref().ok_or_else(|| WasmError {
+                message: "Engine not initialized".to_string(),
+                code: "ENGINE_NOT_INITIALIZED".to_string(),
+                provider_error: None,
+            })?;
+
+            let entity_id = EntityId::from_string(&id).map_err(WasmError::from)?;
+            let statistics = engine.analyze_document(entity_id).await.map_err(WasmError::from)?;
+
+            to_js(&statistics)
+        })
+    }
+
+    /// Route `ai_completion` through a backend endpoint
+    ///
+    /// Accepts an absolute `http(s)` URL or a same-origin path such as
+    /// `/api/ai/complete`. An empty string removes the proxy.
+    pub fn set_ai_proxy_endpoint(&self, url: String) -> Result<(), JsValue> {
+        let url = url.trim();
+        if url.is_empty() {
+            *self.ai_proxy_endpoint.borrow_mut() = None;
+            return Ok(());
+        }
+        if !(url.starts_with("https://") || url.starts_with("http://") || url.starts_with('/')) {
+            return Err(WasmError {
+                message: format!("Invalid AI proxy endpoint: {}", url),
+                code: "CONFIG_ERROR".to_string(),
+                provider_error: None,
+            }
+            .into());
+        }
+        *self.ai_proxy_endpoint.borrow_mut() = Some(url.to_string());
+        Ok(())
+    }
+
+    /// Request AI completion through the configured proxy endpoint
+    ///
+    /// The request JSON is POSTed unchanged and the proxy's JSON response is
+    /// returned. Without a proxy this fails with `FEATURE_NOT_AVAILABLE`, since
+    /// the WASM build has no provider clients of its own.
+    pub fn ai_completion(&self, request_json: String) -> Promise {
+        let endpoint = self.ai_proxy_endpoint.borrow().clone();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let endpoint = endpoint.ok_or_else(|| WasmError {
+                message: "AI completion not available in WASM build. Configure a proxy with set_ai_proxy_endpoint or use native AI integration.".to_string(),
+                code: "FEATURE_NOT_AVAILABLE".to_string(),
+                provider_error: None,
+            })?;
+
+            proxy_ai_completion(&endpoint, &request_json).await
+        })
+    }
+
+    /// Number of documents with local changes not yet uploaded, for sync indicators
+    ///
+    /// Resolves to 0 when offline sync is unavailable.
+    pub fn pending_sync_count(&self) -> Promise {
+        let sync_queue = self.sync_queue.borrow().clone();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let count = match sync_queue {
+                Some(queue) => queue.pending_count().await
+                    .map_err(|e| WasmError::from(WritemagicError::from(e)))?,
+                None => 0,
+            };
+            Ok(JsValue::from(count))
+        })
+    }
+
+    /// Upload queued local changes through `uploader`, e.g. once the browser is back online
+    ///
+    /// `uploader` is called with each queued entry and must resolve to
+    /// `{ status: "applied" }` or `{ status: "conflict", remote_version }`.
+    /// Resolves to a report of uploaded, superseded and failed entries;
+    /// failed entries stay queued.
+    pub fn sync_pending_changes(&self, uploader: js_sys::Function) -> Promise {
+        let sync_queue = self.sync_queue.borrow().clone();
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let queue = sync_queue.ok_or_else(|| WasmError {
+                message: "Offline sync requires IndexedDB".to_string(),
                 code: "FEATURE_NOT_AVAILABLE".to_string(),
-            }.into())
+                provider_error: None,
+            })?;
+
+            let report = queue
+                .drain(|entry| upload_sync_entry(uploader.clone(), entry.clone()))
+                .await
+                .map_err(|e| WasmError::from(WritemagicError::from(e)))?;
+            to_js(&report)
         })
     }
 }
 
+/// Queue a document mutation for upload; a sync failure never fails the edit itself
+async fn record_sync(sync_queue: &Rc<RefCell<Option<SyncQueue>>>, document: &Document, operation: SyncOperation) {
+    let queue = sync_queue.borrow().clone();
+    if let Some(queue) = queue {
+        if let Err(e) = queue.record(SyncEntry::for_document(document, operation)).await {
+            console::warn_1(&format!("Failed to queue change to document {} for sync: {}", document.id, e).into());
+        }
+    }
+}
+
+/// Hand one queued entry to the JavaScript uploader and read its outcome
+async fn upload_sync_entry(uploader: js_sys::Function, entry: SyncEntry) -> writemagic_shared::Result<UploadOutcome> {
+    let js_entry = serde_wasm_bindgen::to_value(&entry)
+        .map_err(|e| WritemagicError::internal(format!("Failed to serialize sync entry: {}", e)))?;
+    let returned = uploader
+        .call1(&JsValue::NULL, &js_entry)
+        .map_err(|e| WritemagicError::network(format!("Sync uploader threw: {:?}", e)))?;
+    let outcome = JsFuture::from(Promise::resolve(&returned))
+        .await
+        .map_err(|e| WritemagicError::network(format!("Sync upload failed: {:?}", e)))?;
+
+    serde_wasm_bindgen::from_value(outcome)
+        .map_err(|e| WritemagicError::validation(format!("Invalid sync upload outcome: {}", e)))
+}
+
+/// POST a completion request to `endpoint` and parse the JSON response
+async fn proxy_ai_completion(endpoint: &str, request_json: &str) -> Result<JsValue, JsValue> {
+    let proxy_error = |message: String| -> JsValue {
+        WasmError {
+            message,
+            code: "AI_PROXY_ERROR".to_string(),
+            provider_error: None,
+        }
+        .into()
+    };
+
+    js_sys::JSON::parse(request_json).map_err(|_| -> JsValue {
+        WasmError {
+            message: "AI completion request is not valid JSON".to_string(),
+            code: "VALIDATION_ERROR".to_string(),
+            provider_error: None,
+        }
+        .into()
+    })?;
+
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_body(&JsValue::from_str(request_json));
+    let request = Request::new_with_str_and_init(endpoint, &init)?;
+    request.headers().set("Content-Type", "application/json")?;
+    request.headers().set("Accept", "application/json")?;
+
+    let window = web_sys::window().ok_or_else(|| proxy_error("No window available for fetch".to_string()))?;
+    let response: Response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|e| proxy_error(format!("AI proxy request failed: {:?}", e)))?
+        .dyn_into()?;
+
+    if !response.ok() {
+        let body = match response.text() {
+            Ok(text) => JsFuture::from(text).await.ok().and_then(|text| text.as_string()).unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+        return Err(proxy_error(format!("AI proxy returned {}: {}", response.status(), body)));
+    }
+
+    JsFuture::from(response.json()?)
+        .await
+        .map_err(|_| proxy_error("AI proxy returned invalid JSON".to_string()))
+}
+
 /// Version information
 #[wasm_bindgen]
 pub fn version() -> String {
@@ -547,6 +879,54 @@ pub fn get_ai_providers() -> Promise {
         Err(WasmError {
             message: "AI providers not available in WASM build. Use server-side integration.".to_string(),
             code: "FEATURE_NOT_AVAILABLE".to_string(),
+            provider_error: None,
         }.into())
     })
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_error_reaches_wasm_error() {
+        let error = WritemagicError::ai_provider_with_details(
+            "Claude API error: Output blocked by content filtering policy",
+            ProviderError {
+                status: 400,
+                provider_code: Some("invalid_request_error".to_string()),
+                message: "Output blocked by content filtering policy".to_string(),
+                retryable: false,
+            },
+        );
+
+        let wasm_error = WasmError::from(error);
+        assert_eq!(wasm_error.code(), "AI_PROVIDER_ERROR");
+
+        let details = wasm_error.provider_error().unwrap();
+        assert_eq!(details.status, 400);
+        assert!(!details.retryable);
+    }
+    #[test]
+    fn test_document_timestamps_follow_format() {
+        let doc = Document::new("Title".to_string(), "Body".to_string(), writemagic_shared::ContentType::Markdown, None);
+
+        let millis = WasmDocument::from_document(&doc, TimestampFormat::EpochMillis);
+        assert_eq!(millis.created_at, WireTimestamp::EpochMillis(doc.created_at.as_datetime().timestamp_millis()));
+        assert_eq!(millis.updated_at.to_timestamp().unwrap().as_datetime().timestamp_millis(), doc.updated_at.as_datetime().timestamp_millis());
+
+        let rfc3339 = WasmDocument::from(&doc);
+        assert_eq!(rfc3339.created_at.to_timestamp().unwrap(), doc.created_at);
+    }
+
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn test_readability_includes_reading_level() {
+        let content = DocumentContent::new("The cat sat. The dog ran.".to_string()).unwrap();
+        let analysis = writemagic_writing::ContentAnalysisService::new().analyze_readability(&content);
+
+        let readability = WasmReadability::from(&analysis);
+        assert_eq!(readability.sentences, 2);
+        assert_eq!(readability.words, 6);
+        assert_eq!(readability.reading_level, analysis.reading_level_description());
+    }
+}
@@ -0,0 +1,77 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use writemagic_writing::{ContentAnalysisService, DocumentContent};
+
+/// System allocator that remembers the most memory held at once
+struct PeakAllocator {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            self.peak.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator { current: AtomicUsize::new(0), peak: AtomicUsize::new(0) };
+
+/// Bytes allocated at the high-water mark of `f`, above what was held before it ran
+fn peak_allocation<R>(f: impl FnOnce() -> R) -> usize {
+    let baseline = ALLOCATOR.current.load(Ordering::Relaxed);
+    ALLOCATOR.peak.store(baseline, Ordering::Relaxed);
+    black_box(f());
+    ALLOCATOR.peak.load(Ordering::Relaxed) - baseline
+}
+
+/// A Markdown manuscript of roughly 1MB
+fn manuscript() -> String {
+    let chapter = "## Chapter\n\nThe *garden* was quiet. Rain fell on the [beds](beds.md) all night!\n\n\
+        - Sow peas\n- Thin carrots\n\nBy morning the **soil** was dark and soft, and the seedlings stood up again.\n\n";
+    chapter.repeat(1024 * 1024 / chapter.len())
+}
+
+/// Benchmark arena-backed analysis against the allocating path, reporting peak memory of each
+fn bench_content_analysis(c: &mut Criterion) {
+    let service = ContentAnalysisService::new();
+    let allocating = ContentAnalysisService::new().with_arena_threshold(usize::MAX);
+    let text = manuscript();
+    let content = DocumentContent::new(text.clone()).unwrap();
+
+    // Warm the thread arena so its one-off buffer isn't counted against the arena path
+    service.analyze_arena(&text);
+    println!(
+        "peak allocation: allocating {} bytes, arena {} bytes",
+        peak_allocation(|| allocating.analyze(&content)),
+        peak_allocation(|| service.analyze_arena(&text))
+    );
+
+    let mut group = c.benchmark_group("content_analysis");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("allocating", |b| {
+        b.iter(|| allocating.analyze(black_box(&content)));
+    });
+
+    group.bench_function("arena", |b| {
+        b.iter(|| service.analyze_arena(black_box(&text)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_content_analysis);
+criterion_main!(benches);
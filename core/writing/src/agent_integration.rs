@@ -0,0 +1,132 @@
+//! Completion and document adapters running agent `AiComplete` steps through the engine
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use writemagic_agent::{AgentCompletion, AgentCompletionProvider, AgentDocumentWriter};
+use writemagic_ai::AiPriority;
+use writemagic_shared::{ContentType, EntityId, Result, WritemagicError};
+
+use crate::core_engine::{CoreEngine, TextCompletionParams};
+use crate::services::{DocumentManagementService, ProjectManagementService};
+use crate::value_objects::{DocumentContent, DocumentTitle};
+
+/// Text put between a document's content and agent output appended to it
+const APPEND_SEPARATOR: &str = "\n\n";
+
+/// Agent completions run through the engine, so they are filtered, billed
+/// and recorded like any other completion
+pub struct EngineAgentCompletions {
+    engine: Arc<CoreEngine>,
+}
+
+impl EngineAgentCompletions {
+    pub fn new(engine: Arc<CoreEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+#[async_trait]
+impl AgentCompletionProvider for EngineAgentCompletions {
+    async fn complete(&self, prompt: &str, model: &str, max_tokens: Option<u64>) -> Result<AgentCompletion> {
+        let mut params = TextCompletionParams {
+            model: Some(model.to_string()),
+            priority: AiPriority::Background,
+            ..TextCompletionParams::default()
+        };
+        if let Some(max_tokens) = max_tokens {
+            params.max_tokens = params.max_tokens.min(u32::try_from(max_tokens).unwrap_or(u32::MAX));
+        }
+
+        let outcome = self.engine.complete_text_detailed(prompt.to_string(), params).await?;
+        Ok(AgentCompletion {
+            text: outcome.text,
+            tokens_used: u64::from(outcome.usage.total_tokens),
+        })
+    }
+}
+
+/// Agent output saved as Markdown documents through the document and project services
+pub struct AgentDocumentService {
+    documents: Arc<DocumentManagementService>,
+    projects: Arc<ProjectManagementService>,
+}
+
+impl AgentDocumentService {
+    pub fn new(documents: Arc<DocumentManagementService>, projects: Arc<ProjectManagementService>) -> Self {
+        Self { documents, projects }
+    }
+}
+
+#[async_trait]
+impl AgentDocumentWriter for AgentDocumentService {
+    /// Add `text` after the document's content as a new paragraph
+    ///
+    /// The append is made to the version read, so an edit made meanwhile
+    /// fails it with a conflict rather than being overwritten.
+    async fn append(&self, document_id: &EntityId, text: &str) -> Result<()> {
+        let document = self.documents
+            .get_document(document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Document {}", document_id)))?
+            .document()
+            .clone();
+        let appended = if document.content.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}{}", APPEND_SEPARATOR, text)
+        };
+
+        self.documents
+            .apply_content_delta(*document_id, document.version, document.content.len(), 0, &appended, None)
+            .await?;
+        self.documents.refresh_document_links(*document_id).await
+    }
+
+    async fn create(&self, title: &str, content: &str, project_id: Option<EntityId>) -> Result<EntityId> {
+        let document = self.documents
+            .create_document(
+                DocumentTitle::new(title)?,
+                DocumentContent::new(content)?,
+                ContentType::Markdown,
+                None,
+                None,
+            )
+            .await?;
+        let document_id = document.document().id;
+
+        if let Some(project_id) = project_id {
+            self.projects.add_document_to_project(project_id, document_id, None).await?;
+        }
+        Ok(document_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{InMemoryDocumentRepository, InMemoryProjectRepository};
+    use crate::value_objects::ProjectName;
+    use writemagic_shared::Repository;
+
+    #[tokio::test]
+    async fn test_agent_output_is_appended_or_saved_into_the_project() {
+        let document_repository = Arc::new(InMemoryDocumentRepository::new());
+        let documents = Arc::new(DocumentManagementService::new(document_repository.clone()));
+        let project_repository = Arc::new(InMemoryProjectRepository::new());
+        let projects = Arc::new(ProjectManagementService::new(project_repository.clone(), document_repository));
+        let writer = AgentDocumentService::new(documents.clone(), projects.clone());
+        let project_id = projects.create_project(ProjectName::new("Owls").unwrap(), None, None).await.unwrap().project().id;
+
+        let document_id = writer.create("Owl draft", "Owls hunt at night.", Some(project_id)).await.unwrap();
+        writer.append(&document_id, "They fly silently.").await.unwrap();
+
+        let document = documents.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(document.document().content, "Owls hunt at night.\n\nThey fly silently.");
+        assert_eq!(document.document().content_type, ContentType::Markdown);
+        let project = project_repository.find_by_id(&project_id).await.unwrap().unwrap();
+        assert_eq!(project.document_ids, vec![document_id]);
+
+        let missing = writer.append(&EntityId::new(), "Lost").await;
+        assert!(matches!(missing, Err(WritemagicError::NotFound { .. })));
+    }
+}
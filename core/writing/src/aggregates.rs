@@ -2,8 +2,10 @@
 
 use crate::entities::{Document, Project};
 use crate::events::{DocumentEvent, ProjectEvent};
+use crate::language::{normalize_language_code, LanguageConfig};
 use crate::value_objects::{DocumentTitle, DocumentContent, ProjectName, TextSelection};
-use writemagic_shared::{EntityId, Timestamp, ContentType, FilePath, Result, WritemagicError};
+use writemagic_shared::{sanitize_html, EntityId, Timestamp, ContentType, DocumentTag, FilePath, Result, WritemagicError};
+use writemagic_shared::validation::validate_system_prompt;
 use std::collections::HashMap;
 
 /// Document aggregate with business logic and invariants
@@ -46,10 +48,12 @@ impl DocumentAggregate {
     }
 
     pub fn new(title: DocumentTitle, content: DocumentContent, content_type: ContentType, created_by: Option<EntityId>) -> Self {
-        let document = Document::new(title.value.clone(), content.value.clone(), content_type.clone(), created_by);
+        let content = stored_content(&content_type, content.value);
+        let document = Document::new(title.value.clone(), content.clone(), content_type.clone(), created_by);
         let event = DocumentEvent::DocumentCreated {
             document_id: document.id,
             title: title.value.clone(),
+            content,
             content_type: content_type.clone(),
             created_by,
             created_at: document.created_at.clone(),
@@ -101,10 +105,11 @@ impl DocumentAggregate {
             return Err(WritemagicError::validation("Cannot update deleted document"));
         }
 
+        let content = stored_content(&self.document.content_type, content.value);
         let old_content = self.document.content.clone();
         let old_word_count = self.document.word_count;
         
-        self.document.update_content(content.value.clone(), updated_by);
+        self.document.update_content(content.clone(), updated_by);
 
         // Record edit operation
         let edit_op = EditOperation {
@@ -113,7 +118,7 @@ impl DocumentAggregate {
             operation_type: EditOperationType::ContentUpdate,
             selection,
             old_text: old_content.clone(),
-            new_text: content.value.clone(),
+            new_text: content.clone(),
             timestamp: Timestamp::now(),
             user_id: updated_by,
         };
@@ -122,7 +127,7 @@ impl DocumentAggregate {
         let event = DocumentEvent::DocumentContentUpdated {
             document_id: self.document.id,
             old_content,
-            new_content: content.value,
+            new_content: content,
             old_word_count,
             new_word_count: self.document.word_count,
             updated_by,
@@ -133,6 +138,87 @@ impl DocumentAggregate {
         Ok(())
     }
 
+    /// Apply a small edit, replacing `old_len` bytes at `start` with `new_text`
+    ///
+    /// Statistics are updated incrementally and reported with `DocumentStatsChanged`.
+    /// An edit that leaves HTML content with markup the sanitizer removes is
+    /// applied as a full content update of the sanitized result instead.
+    pub fn apply_content_delta(&mut self, start: usize, old_len: usize, new_text: &str, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot update deleted document"));
+        }
+        if self.document.content_type == ContentType::Html {
+            let mut edited = self.document.clone();
+            edited.apply_content_delta(start, old_len, new_text, updated_by)?;
+            let sanitized = sanitize_html(&edited.content);
+            if sanitized != edited.content {
+                return self.update_content(DocumentContent::new(sanitized)?, None, updated_by);
+            }
+        }
+
+        let old_text = self.document.content
+            .get(start..start.saturating_add(old_len))
+            .unwrap_or_default()
+            .to_string();
+        if !self.document.apply_content_delta(start, old_len, new_text, updated_by)? {
+            return Ok(());
+        }
+
+        let operation_type = match (old_len, new_text.is_empty()) {
+            (0, _) => EditOperationType::Insert,
+            (_, true) => EditOperationType::Delete,
+            _ => EditOperationType::Replace,
+        };
+        self.edit_history.push(EditOperation {
+            id: EntityId::new(),
+            document_id: self.document.id,
+            operation_type,
+            selection: Some(TextSelection::new(start, start + old_len)?),
+            old_text,
+            new_text: new_text.to_string(),
+            timestamp: Timestamp::now(),
+            user_id: updated_by,
+        });
+
+        self.uncommitted_events.push(DocumentEvent::DocumentStatsChanged {
+            document_id: self.document.id,
+            start,
+            removed_len: old_len,
+            inserted: new_text.to_string(),
+            word_count: self.document.word_count,
+            character_count: self.document.character_count,
+            updated_by,
+            updated_at: self.document.updated_at.clone(),
+        });
+        Ok(())
+    }
+
+    /// Replace the content with `content`, already converted to `content_type`
+    pub fn convert_format(&mut self, content: DocumentContent, content_type: ContentType, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot convert deleted document"));
+        }
+        if self.document.content_type == content_type {
+            return Ok(());
+        }
+
+        let content = stored_content(&content_type, content.value);
+        let old_content_type = self.document.content_type.clone();
+        self.document.convert_content(content.clone(), content_type.clone(), updated_by);
+
+        let event = DocumentEvent::DocumentFormatConverted {
+            document_id: self.document.id,
+            old_content_type,
+            new_content_type: content_type,
+            new_content: content,
+            updated_by,
+            updated_at: self.document.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
     pub fn set_file_path(&mut self, file_path: FilePath, updated_by: Option<EntityId>) -> Result<()> {
         if self.document.is_deleted {
             return Err(WritemagicError::validation("Cannot update deleted document"));
@@ -186,6 +272,100 @@ impl DocumentAggregate {
         Ok(())
     }
 
+    /// Archive the document; already archived documents are left untouched
+    pub fn archive(&mut self, archived_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot archive deleted document"));
+        }
+
+        if !self.document.archive(archived_by) {
+            return Ok(());
+        }
+
+        let event = DocumentEvent::DocumentArchived {
+            document_id: self.document.id,
+            archived_by,
+            archived_at: self.document.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
+    pub fn add_tags(&mut self, tags: Vec<DocumentTag>, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot tag deleted document"));
+        }
+
+        let old_tags: Vec<String> = self.document.tags.iter().map(|t| t.to_string()).collect();
+        if self.document.add_tags(tags, updated_by) {
+            self.record_tags_updated(old_tags, updated_by);
+        }
+        Ok(())
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<DocumentTag>, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot tag deleted document"));
+        }
+
+        let old_tags: Vec<String> = self.document.tags.iter().map(|t| t.to_string()).collect();
+        if self.document.set_tags(tags, updated_by) {
+            self.record_tags_updated(old_tags, updated_by);
+        }
+        Ok(())
+    }
+
+    pub fn remove_tag(&mut self, tag: &DocumentTag, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot untag deleted document"));
+        }
+
+        let old_tags: Vec<String> = self.document.tags.iter().map(|t| t.to_string()).collect();
+        if self.document.remove_tag(tag, updated_by) {
+            self.record_tags_updated(old_tags, updated_by);
+        }
+        Ok(())
+    }
+
+    fn record_tags_updated(&mut self, old_tags: Vec<String>, updated_by: Option<EntityId>) {
+        let event = DocumentEvent::DocumentTagsUpdated {
+            document_id: self.document.id,
+            old_tags,
+            new_tags: self.document.tags.iter().map(|t| t.to_string()).collect(),
+            updated_by,
+            updated_at: self.document.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+    }
+
+    /// Re-detect the document language from its content
+    pub fn detect_language(&mut self, config: &LanguageConfig) -> bool {
+        self.document.detect_language(config)
+    }
+
+    pub fn set_language_override(&mut self, language: Option<String>, updated_by: Option<EntityId>) -> Result<()> {
+        if self.document.is_deleted {
+            return Err(WritemagicError::validation("Cannot update deleted document"));
+        }
+
+        let language = language.as_deref().map(normalize_language_code).transpose()?;
+        if !self.document.set_language_override(language.clone(), updated_by) {
+            return Ok(());
+        }
+
+        let event = DocumentEvent::DocumentLanguageOverridden {
+            document_id: self.document.id,
+            language,
+            updated_by,
+            updated_at: self.document.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
     pub fn add_collaborator(&mut self, user_id: EntityId, display_name: String) {
         self.collaborators.insert(user_id, display_name);
     }
@@ -211,6 +391,14 @@ impl DocumentAggregate {
     }
 }
 
+/// Content as stored for `content_type`: HTML is sanitized so it is safe to display as is
+fn stored_content(content_type: &ContentType, content: String) -> String {
+    match content_type {
+        ContentType::Html => sanitize_html(&content),
+        _ => content,
+    }
+}
+
 /// Project aggregate with business logic and invariants
 #[derive(Debug, Clone)]
 pub struct ProjectAggregate {
@@ -334,6 +522,59 @@ impl ProjectAggregate {
         Ok(())
     }
 
+    /// Take in a batch of documents moved out of `from_project_ids`, raising one `DocumentsMoved` event
+    pub fn receive_documents(
+        &mut self,
+        document_ids: Vec<EntityId>,
+        from_project_ids: Vec<EntityId>,
+        moved_by: Option<EntityId>,
+    ) -> Result<()> {
+        if self.project.is_deleted {
+            return Err(WritemagicError::validation("Cannot move documents into deleted project"));
+        }
+
+        if self.project.is_archived {
+            return Err(WritemagicError::validation("Cannot move documents into archived project"));
+        }
+
+        let new_documents = document_ids.iter().filter(|id| !self.project.document_ids.contains(id)).count();
+        if self.project.document_ids.len() + new_documents > 1000 {
+            return Err(WritemagicError::validation("Project cannot have more than 1000 documents"));
+        }
+
+        self.project.add_documents(&document_ids, moved_by);
+
+        let event = ProjectEvent::DocumentsMoved {
+            project_id: self.project.id,
+            from_project_ids,
+            document_ids,
+            moved_by,
+            moved_at: self.project.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
+    /// Give up documents moved to another project; the receiving project raises the event
+    pub fn release_documents(&mut self, document_ids: &[EntityId], moved_by: Option<EntityId>) -> Result<()> {
+        if self.project.is_deleted {
+            return Err(WritemagicError::validation("Cannot move documents out of deleted project"));
+        }
+
+        if let Some(missing) = document_ids.iter().find(|id| !self.project.document_ids.contains(id)) {
+            return Err(WritemagicError::validation(format!(
+                "Document {} is not part of project {}", missing, self.project.id
+            )));
+        }
+
+        self.project.remove_documents(document_ids, moved_by);
+        for document_id in document_ids {
+            self.document_metadata.remove(document_id);
+        }
+        Ok(())
+    }
+
     pub fn update_name(&mut self, name: ProjectName, updated_by: Option<EntityId>) -> Result<()> {
         if self.project.is_deleted {
             return Err(WritemagicError::validation("Cannot update deleted project"));
@@ -374,6 +615,70 @@ impl ProjectAggregate {
         Ok(())
     }
 
+    /// Set the AI system prompt; blank prompts clear it
+    pub fn update_system_prompt(&mut self, system_prompt: Option<String>, updated_by: Option<EntityId>) -> Result<()> {
+        if self.project.is_deleted {
+            return Err(WritemagicError::validation("Cannot update deleted project"));
+        }
+
+        let system_prompt = system_prompt
+            .map(|prompt| prompt.trim().to_string())
+            .filter(|prompt| !prompt.is_empty());
+        if let Some(prompt) = &system_prompt {
+            validate_system_prompt(prompt)?;
+        }
+        if system_prompt == self.project.system_prompt {
+            return Ok(());
+        }
+
+        self.project.update_system_prompt(system_prompt.clone(), updated_by);
+
+        let event = ProjectEvent::ProjectSystemPromptUpdated {
+            project_id: self.project.id,
+            system_prompt,
+            updated_by,
+            updated_at: self.project.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
+    pub fn archive(&mut self, archived_by: Option<EntityId>, cascade: bool) -> Result<()> {
+        if self.project.is_deleted {
+            return Err(WritemagicError::validation("Cannot archive deleted project"));
+        }
+
+        if !self.project.archive(archived_by) {
+            return Err(WritemagicError::validation("Project is already archived"));
+        }
+
+        let event = ProjectEvent::ProjectArchived {
+            project_id: self.project.id,
+            cascade,
+            archived_by,
+            archived_at: self.project.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
+    pub fn unarchive(&mut self, unarchived_by: Option<EntityId>) -> Result<()> {
+        if !self.project.unarchive(unarchived_by) {
+            return Err(WritemagicError::validation("Project is not archived"));
+        }
+
+        let event = ProjectEvent::ProjectUnarchived {
+            project_id: self.project.id,
+            unarchived_by,
+            unarchived_at: self.project.updated_at.clone(),
+        };
+
+        self.uncommitted_events.push(event);
+        Ok(())
+    }
+
     pub fn document_metadata(&self) -> &HashMap<EntityId, DocumentMetadata> {
         &self.document_metadata
     }
@@ -3,7 +3,7 @@
 // Remove duplicated attribute - already defined in lib.rs
 
 use std::sync::Arc;
-use writemagic_shared::{EntityId, Result, WritemagicError};
+use writemagic_shared::{EntityId, Pagination, Result, WritemagicError};
 
 use crate::entities::Document;
 use crate::repositories::{DocumentRepository, ProjectRepository};
@@ -229,7 +229,7 @@ impl IntegratedWritingService {
             let summary_content = DocumentContent::new(response.content.clone())?;
             
             let summary_doc = self.document_service
-                .create_document(summary_title, summary_content, document.content_type.clone(), updated_by)
+                .create_document(summary_title, summary_content, document.content_type.clone(), updated_by, None)
                 .await?;
 
             response.applied_to_document = true;
@@ -401,6 +401,7 @@ impl IntegratedWritingService {
             user_input,
             model_config: None,
             stream_response: false,
+            example_set: None,
         };
 
         // Get assistance
@@ -501,6 +502,7 @@ impl IntegratedWritingService {
                 project_id: project.id,
                 project_name: project.name,
                 project_description: project.description,
+                system_prompt: project.system_prompt,
                 related_documents,
             }))
         } else {
@@ -508,11 +510,15 @@ impl IntegratedWritingService {
         }
     }
 
-    /// Find project containing a document
-    async fn find_project_for_document(&self, _document_id: EntityId) -> Result<Option<ProjectContext>> {
-        // This would require a more sophisticated query or index
-        // For now, we'll skip this optimization
-        Ok(None)
+    /// Context of the first project containing a document, if any
+    async fn find_project_for_document(&self, document_id: EntityId) -> Result<Option<ProjectContext>> {
+        let projects = self.project_repository
+            .find_containing_document(&document_id, Pagination { offset: 0, limit: 1 })
+            .await?;
+        match projects.into_iter().next() {
+            Some(project) => self.build_project_context(project.id).await,
+            None => Ok(None),
+        }
     }
 
     /// Determine if content should be applied to document for a given assistance type
@@ -0,0 +1,528 @@
+//! Document autosave buffering and conflict resolution
+
+use std::collections::HashMap;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+use tokio::sync::Mutex;
+use writemagic_shared::{EntityId, Result, Timestamp, WritemagicError};
+use crate::aggregates::DocumentAggregate;
+use crate::services::DocumentManagementService;
+use crate::value_objects::DocumentContent;
+
+/// Policy applied when a document changed between queueing and flushing an autosave
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum ConflictResolution {
+    /// Whichever write happened most recently is kept
+    #[default]
+    LastWriteWins,
+    /// Combine the stored and autosaved changes with a three-way text merge
+    Merge,
+    /// Discard the autosave and surface a version conflict
+    RejectOnConflict,
+}
+
+/// Default quiet period before a document's queued content is flushed
+pub const DEFAULT_AUTOSAVE_DEBOUNCE_MS: u64 = 500;
+
+/// Autosave configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AutosaveConfig {
+    pub conflict_resolution: ConflictResolution,
+    /// Milliseconds without new content before a document is flushed
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            conflict_resolution: ConflictResolution::default(),
+            debounce_ms: DEFAULT_AUTOSAVE_DEBOUNCE_MS,
+        }
+    }
+}
+
+fn default_debounce_ms() -> u64 {
+    DEFAULT_AUTOSAVE_DEBOUNCE_MS
+}
+
+/// Outcome of flushing a pending autosave
+#[derive(Debug, Clone)]
+pub enum AutosaveFlush {
+    /// No autosave was pending for the document
+    NothingPending,
+    /// The autosaved content was written without conflict
+    Saved(DocumentAggregate),
+    /// A newer write was already stored, so the autosave was dropped
+    Superseded(DocumentAggregate),
+    /// The autosaved content was merged with a concurrent write
+    Merged(DocumentAggregate),
+}
+
+/// Content waiting to be flushed for a single document
+#[derive(Debug, Clone)]
+struct PendingAutosave {
+    content: DocumentContent,
+    base_version: u64,
+    base_content: String,
+    queued_at: Timestamp,
+}
+
+/// Buffers autosaved content per document and flushes it deterministically
+pub struct AutosaveBuffer {
+    document_service: Arc<DocumentManagementService>,
+    conflict_resolution: ConflictResolution,
+    pending: Mutex<HashMap<EntityId, PendingAutosave>>,
+}
+
+impl AutosaveBuffer {
+    pub fn new(document_service: Arc<DocumentManagementService>, conflict_resolution: ConflictResolution) -> Self {
+        Self {
+            document_service,
+            conflict_resolution,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn conflict_resolution(&self) -> ConflictResolution {
+        self.conflict_resolution
+    }
+
+    /// Queue content for a document, replacing any content already pending
+    ///
+    /// The version the first queued update was based on is kept so a flush can
+    /// detect writes that landed in between.
+    pub async fn queue(&self, document_id: EntityId, content: DocumentContent) -> Result<()> {
+        let mut pending = self.pending.lock().await;
+
+        if let Some(entry) = pending.get_mut(&document_id) {
+            entry.content = content;
+            entry.queued_at = Timestamp::now();
+            return Ok(());
+        }
+
+        let aggregate = self.document_service
+            .get_document(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let document = aggregate.document();
+
+        pending.insert(document_id, PendingAutosave {
+            content,
+            base_version: document.version,
+            base_content: document.content.clone(),
+            queued_at: Timestamp::now(),
+        });
+        Ok(())
+    }
+
+    /// Whether content is pending for a document
+    pub async fn has_pending(&self, document_id: &EntityId) -> bool {
+        self.pending.lock().await.contains_key(document_id)
+    }
+
+    /// Documents with content pending
+    pub async fn pending_documents(&self) -> Vec<EntityId> {
+        self.pending.lock().await.keys().copied().collect()
+    }
+
+    /// Flush pending content for a document, resolving conflicts by policy
+    pub async fn flush(&self, document_id: EntityId) -> Result<AutosaveFlush> {
+        let Some(entry) = self.pending.lock().await.remove(&document_id) else {
+            return Ok(AutosaveFlush::NothingPending);
+        };
+
+        let current = self.document_service
+            .get_document(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let stored = current.document();
+
+        if stored.version == entry.base_version {
+            let saved = self.save(document_id, entry.content).await?;
+            return Ok(AutosaveFlush::Saved(saved));
+        }
+
+        match self.conflict_resolution {
+            ConflictResolution::LastWriteWins => {
+                if stored.updated_at.as_datetime() > entry.queued_at.as_datetime() {
+                    log::debug!("Autosave for {} superseded by newer write", document_id);
+                    Ok(AutosaveFlush::Superseded(current))
+                } else {
+                    let saved = self.save(document_id, entry.content).await?;
+                    Ok(AutosaveFlush::Saved(saved))
+                }
+            }
+            ConflictResolution::Merge => {
+                let merged = three_way_merge(&entry.base_content, entry.content.as_str(), &stored.content);
+                let saved = self.save(document_id, DocumentContent::new(merged)?).await?;
+                Ok(AutosaveFlush::Merged(saved))
+            }
+            ConflictResolution::RejectOnConflict => Err(WritemagicError::version_conflict(format!(
+                "Autosave for document {} was based on version {} but version {} is stored",
+                document_id, entry.base_version, stored.version
+            ))),
+        }
+    }
+
+    /// Flush every pending document, returning each outcome
+    pub async fn flush_all(&self) -> Vec<(EntityId, Result<AutosaveFlush>)> {
+        let document_ids = self.pending_documents().await;
+
+        let mut results = Vec::with_capacity(document_ids.len());
+        for document_id in document_ids {
+            results.push((document_id, self.flush(document_id).await));
+        }
+        results
+    }
+
+    async fn save(&self, document_id: EntityId, content: DocumentContent) -> Result<DocumentAggregate> {
+        self.document_service
+            .update_document_content(document_id, content, None, None)
+            .await
+    }
+}
+
+/// Debounce bookkeeping for one document, dropped once its timer has fired
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct DocumentSlot {
+    /// Generation of the latest queued update; a timer only flushes if it is
+    /// still current. Zero until the first timer is started.
+    generation: u64,
+    /// Held while queueing or flushing, so a flush never races a newer update
+    write_lock: Arc<Mutex<()>>,
+}
+
+/// Debounces autosaves per document and flushes them through an `AutosaveBuffer`
+///
+/// Each update restarts the document's timer; once no update has arrived for
+/// the debounce window the latest content is flushed. Cloning shares the
+/// pending state, so a clone can be handed to a `ShutdownCoordinator`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct AutosaveCoordinator {
+    buffer: Arc<AutosaveBuffer>,
+    debounce: Duration,
+    slots: Arc<std::sync::Mutex<HashMap<EntityId, DocumentSlot>>>,
+    /// Source of timer generations, never reused so a stale timer cannot match a new slot
+    next_generation: Arc<std::sync::atomic::AtomicU64>,
+    /// Number of documents with a slot, for `wait_idle`
+    slot_count: Arc<tokio::sync::watch::Sender<usize>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AutosaveCoordinator {
+    pub fn new(buffer: Arc<AutosaveBuffer>) -> Self {
+        Self {
+            buffer,
+            debounce: Duration::from_millis(DEFAULT_AUTOSAVE_DEBOUNCE_MS),
+            slots: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            next_generation: Arc::new(std::sync::atomic::AtomicU64::new(1)),
+            slot_count: Arc::new(tokio::sync::watch::channel(0).0),
+        }
+    }
+
+    /// Quiet period before queued content is flushed
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    pub fn buffer(&self) -> Arc<AutosaveBuffer> {
+        self.buffer.clone()
+    }
+
+    /// Queue content for a document and restart its debounce timer
+    ///
+    /// Waits for an in-flight flush of the same document, so the update is
+    /// based on what that flush stored and always lands after it.
+    pub async fn queue_update(&self, document_id: EntityId, content: DocumentContent) -> Result<()> {
+        let write_lock = self.write_lock(document_id)?;
+        let queued = {
+            let _guard = write_lock.lock().await;
+            self.buffer.queue(document_id, content).await
+        };
+
+        if let Err(e) = queued {
+            drop(write_lock);
+            // Only drops a slot this call created, which has no timer yet
+            self.prune(document_id, 0)?;
+            return Err(e);
+        }
+        let generation = self.next_generation.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.slots()?.entry(document_id).or_default().generation = generation;
+        drop(write_lock);
+
+        let coordinator = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(coordinator.debounce).await;
+            if coordinator.is_current(document_id, generation) {
+                if let Err(e) = coordinator.flush(document_id).await {
+                    log::warn!("Debounced autosave for {} failed: {}", document_id, e);
+                }
+                if let Err(e) = coordinator.prune(document_id, generation) {
+                    log::warn!("Failed to release autosave state for {}: {}", document_id, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Flush a document now instead of waiting for its timer
+    pub async fn flush(&self, document_id: EntityId) -> Result<AutosaveFlush> {
+        let write_lock = self.write_lock(document_id)?;
+        let flushed = {
+            let _guard = write_lock.lock().await;
+            self.buffer.flush(document_id).await
+        };
+        drop(write_lock);
+        // A slot without a timer was created by this call; a timer prunes its own
+        self.prune(document_id, 0)?;
+        flushed
+    }
+
+    /// Flush every pending document, e.g. on shutdown
+    pub async fn flush_all(&self) -> Vec<(EntityId, Result<AutosaveFlush>)> {
+        let document_ids = self.buffer.pending_documents().await;
+
+        let mut results = Vec::with_capacity(document_ids.len());
+        for document_id in document_ids {
+            results.push((document_id, self.flush(document_id).await));
+        }
+        results
+    }
+
+    /// Wait until every started debounce timer has fired and released its document
+    pub async fn wait_idle(&self) {
+        let mut slot_count = self.slot_count.subscribe();
+        // The sender lives in `self`, so the channel cannot close while waiting
+        let _ = slot_count.wait_for(|count| *count == 0).await;
+    }
+
+    /// Flush pending autosaves when `coordinator` starts a graceful shutdown
+    pub fn register_shutdown(&self, coordinator: &writemagic_shared::ShutdownCoordinator) -> tokio::task::JoinHandle<()> {
+        use writemagic_shared::GracefulShutdown;
+        tokio::spawn(self.clone().run_with_shutdown(coordinator.subscriber()))
+    }
+
+    fn is_current(&self, document_id: EntityId, generation: u64) -> bool {
+        self.slots()
+            .map(|slots| slots.get(&document_id).map(|slot| slot.generation) == Some(generation))
+            .unwrap_or(false)
+    }
+
+    fn write_lock(&self, document_id: EntityId) -> Result<Arc<Mutex<()>>> {
+        let mut slots = self.slots()?;
+        let write_lock = slots.entry(document_id).or_default().write_lock.clone();
+        self.slot_count.send_replace(slots.len());
+        Ok(write_lock)
+    }
+
+    /// Drop a document's slot if `generation` is still its latest and nobody holds its lock
+    fn prune(&self, document_id: EntityId, generation: u64) -> Result<()> {
+        let mut slots = self.slots()?;
+        let idle = slots
+            .get(&document_id)
+            .is_some_and(|slot| slot.generation == generation && Arc::strong_count(&slot.write_lock) == 1);
+        if idle {
+            slots.remove(&document_id);
+            self.slot_count.send_replace(slots.len());
+        }
+        Ok(())
+    }
+
+    fn slots(&self) -> Result<std::sync::MutexGuard<'_, HashMap<EntityId, DocumentSlot>>> {
+        self.slots.lock().map_err(|_| WritemagicError::internal("Failed to acquire autosave lock"))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait::async_trait]
+impl writemagic_shared::GracefulShutdown for AutosaveCoordinator {
+    fn service_name(&self) -> &str {
+        "autosave"
+    }
+
+    async fn shutdown(&mut self) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let failed: Vec<String> = self
+            .flush_all()
+            .await
+            .into_iter()
+            .filter_map(|(document_id, result)| result.err().map(|e| format!("{}: {}", document_id, e)))
+            .collect();
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Failed to flush autosaves for {}", failed.join(", ")).into())
+        }
+    }
+}
+
+/// Merge two edits of `base` into one text
+///
+/// Non-overlapping edits are both applied. Overlapping edits keep `theirs`
+/// and append the region changed by `ours`.
+fn three_way_merge(base: &str, ours: &str, theirs: &str) -> String {
+    if ours == base || ours == theirs {
+        return theirs.to_string();
+    }
+    if theirs == base {
+        return ours.to_string();
+    }
+
+    let base: Vec<char> = base.chars().collect();
+    let ours: Vec<char> = ours.chars().collect();
+    let theirs: Vec<char> = theirs.chars().collect();
+
+    let (ours_start, ours_end, ours_text) = changed_region(&base, &ours);
+    let (theirs_start, theirs_end, theirs_text) = changed_region(&base, &theirs);
+
+    let (first, second) = if theirs_end <= ours_start {
+        ((theirs_start, theirs_end, theirs_text), (ours_start, ours_end, ours_text))
+    } else if ours_end <= theirs_start {
+        ((ours_start, ours_end, ours_text), (theirs_start, theirs_end, theirs_text))
+    } else {
+        let theirs: String = theirs.iter().collect();
+        let ours_text: String = ours_text.iter().collect();
+        return format!("{}\n{}", theirs, ours_text);
+    };
+
+    let mut merged: String = base[..first.0].iter().collect();
+    merged.extend(first.2);
+    merged.extend(&base[first.1..second.0]);
+    merged.extend(second.2);
+    merged.extend(&base[second.1..]);
+    merged
+}
+
+/// Region of `base` replaced in `edited`, as (start, end, replacement)
+fn changed_region<'a>(base: &[char], edited: &'a [char]) -> (usize, usize, &'a [char]) {
+    let prefix = base.iter().zip(edited).take_while(|(a, b)| a == b).count();
+    let max_suffix = base.len().min(edited.len()) - prefix;
+    let suffix = base.iter().rev().zip(edited.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    (prefix, base.len() - suffix, &edited[prefix..edited.len() - suffix])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::InMemoryDocumentRepository;
+    use crate::value_objects::DocumentTitle;
+    use writemagic_shared::ContentType;
+
+    async fn setup(policy: ConflictResolution) -> (Arc<DocumentManagementService>, AutosaveBuffer, EntityId) {
+        let service = Arc::new(DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new())));
+        let aggregate = service
+            .create_document(
+                DocumentTitle::new("Draft").unwrap(),
+                DocumentContent::new("Line one").unwrap(),
+                ContentType::Markdown,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let buffer = AutosaveBuffer::new(service.clone(), policy);
+        (service, buffer, aggregate.document().id)
+    }
+
+    /// Autosave flush, then an autosave queued before an explicit save, then a second flush
+    async fn race(policy: ConflictResolution) -> (Arc<DocumentManagementService>, EntityId, Result<AutosaveFlush>) {
+        let (service, buffer, document_id) = setup(policy).await;
+
+        buffer.queue(document_id, DocumentContent::new("Line one\nLine two").unwrap()).await.unwrap();
+        assert!(matches!(buffer.flush(document_id).await.unwrap(), AutosaveFlush::Saved(_)));
+
+        buffer.queue(document_id, DocumentContent::new("Line one\nLine two\nAutosaved line").unwrap()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        service
+            .update_document_content(document_id, DocumentContent::new("Intro\nLine one\nLine two").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        let outcome = buffer.flush(document_id).await;
+        (service, document_id, outcome)
+    }
+
+    async fn stored_content(service: &DocumentManagementService, document_id: &EntityId) -> String {
+        service.get_document(document_id).await.unwrap().unwrap().document().content.clone()
+    }
+
+    #[tokio::test]
+    async fn test_last_write_wins_keeps_newest() {
+        let (service, document_id, outcome) = race(ConflictResolution::LastWriteWins).await;
+
+        assert!(matches!(outcome.unwrap(), AutosaveFlush::Superseded(_)));
+        assert_eq!(stored_content(&service, &document_id).await, "Intro\nLine one\nLine two");
+    }
+
+    #[tokio::test]
+    async fn test_last_write_wins_applies_autosave_queued_after_explicit_save() {
+        let (service, buffer, document_id) = setup(ConflictResolution::LastWriteWins).await;
+
+        buffer.queue(document_id, DocumentContent::new("Autosaved").unwrap()).await.unwrap();
+        service
+            .update_document_content(document_id, DocumentContent::new("Explicit").unwrap(), None, None)
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        buffer.queue(document_id, DocumentContent::new("Autosaved again").unwrap()).await.unwrap();
+
+        assert!(matches!(buffer.flush(document_id).await.unwrap(), AutosaveFlush::Saved(_)));
+        assert_eq!(stored_content(&service, &document_id).await, "Autosaved again");
+    }
+
+    #[tokio::test]
+    async fn test_reject_on_conflict_surfaces_conflict() {
+        let (service, document_id, outcome) = race(ConflictResolution::RejectOnConflict).await;
+
+        assert!(matches!(outcome, Err(WritemagicError::VersionConflict { .. })));
+        assert_eq!(stored_content(&service, &document_id).await, "Intro\nLine one\nLine two");
+    }
+
+    #[tokio::test]
+    async fn test_merge_combines_content() {
+        let (service, document_id, outcome) = race(ConflictResolution::Merge).await;
+
+        assert!(matches!(outcome.unwrap(), AutosaveFlush::Merged(_)));
+        assert_eq!(
+            stored_content(&service, &document_id).await,
+            "Intro\nLine one\nLine two\nAutosaved line"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_debounces_to_one_save_of_latest_content() {
+        let (service, buffer, document_id) = setup(ConflictResolution::LastWriteWins).await;
+        let created_version = service.get_document(&document_id).await.unwrap().unwrap().document().version;
+        let coordinator = AutosaveCoordinator::new(Arc::new(buffer)).with_debounce(std::time::Duration::from_millis(30));
+
+        for content in ["L", "Li", "Line"] {
+            coordinator.queue_update(document_id, DocumentContent::new(content).unwrap()).await.unwrap();
+        }
+        coordinator.wait_idle().await;
+
+        let stored = service.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.document().content, "Line");
+        assert_eq!(stored.document().version, created_version + 1);
+        assert!(coordinator.flush_all().await.is_empty());
+        assert!(coordinator.slots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_three_way_merge_overlapping_edits_keep_both() {
+        let merged = three_way_merge("abc", "aXc", "aYc");
+        assert_eq!(merged, "aYc\nX");
+    }
+}
@@ -0,0 +1,356 @@
+//! Running one AI action across every document in a project
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use writemagic_shared::{EntityId, Result, WritemagicError};
+use crate::entities::Document;
+
+/// AI action applied to each document of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiBatchAction {
+    Summarize,
+    Tag,
+    Rephrase,
+}
+
+impl AiBatchAction {
+    /// Completion prompt for `document`
+    pub fn prompt(self, document: &Document) -> String {
+        let instruction = match self {
+            Self::Summarize => "Summarize the following document in a few sentences.",
+            Self::Tag => "Suggest a short comma-separated list of topic tags for the following document.",
+            Self::Rephrase => "Rephrase the following document for clarity while preserving its meaning.",
+        };
+        format!("{}\n\nTitle: {}\n\n{}", instruction, document.title, document.content)
+    }
+}
+
+/// Where an interrupted batch left off
+///
+/// Passing the token back in `AiBatchParams::resume_token` re-runs only the
+/// documents that did not complete.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchResumeToken {
+    pub project_id: EntityId,
+    pub action: AiBatchAction,
+    pub completed: Vec<EntityId>,
+}
+
+/// Parameters for a project-wide AI batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiBatchParams {
+    /// Model to use, or the engine default when `None`
+    pub model: Option<String>,
+    /// Documents processed at the same time
+    pub max_concurrency: usize,
+    /// Report what would be processed without calling the AI provider
+    pub dry_run: bool,
+    pub resume_token: Option<BatchResumeToken>,
+    /// Dispatch priority of the batch's requests, background unless a user is waiting
+    #[cfg(feature = "ai")]
+    pub priority: writemagic_ai::AiPriority,
+}
+
+impl Default for AiBatchParams {
+    fn default() -> Self {
+        Self {
+            model: None,
+            max_concurrency: 4,
+            dry_run: false,
+            resume_token: None,
+            #[cfg(feature = "ai")]
+            priority: writemagic_ai::AiPriority::Background,
+        }
+    }
+}
+
+/// Output of the action for one document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchDocumentResult {
+    pub document_id: EntityId,
+    pub output: String,
+}
+
+/// Error the action hit for one document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchDocumentFailure {
+    pub document_id: EntityId,
+    pub error: String,
+}
+
+/// Outcome of a project-wide AI batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchAiSummary {
+    pub project_id: EntityId,
+    pub action: AiBatchAction,
+    pub dry_run: bool,
+    /// Documents the action completed for, in project order
+    pub results: Vec<BatchDocumentResult>,
+    pub failures: Vec<BatchDocumentFailure>,
+    /// Documents already completed by the run named in the resume token
+    pub skipped: Vec<EntityId>,
+    /// Documents not attempted, either planned by a dry run or left after the
+    /// batch was halted by rate limiting or an open circuit breaker
+    pub pending: Vec<EntityId>,
+    /// Set when a real run did not complete every document
+    pub resume_token: Option<BatchResumeToken>,
+}
+
+/// Runs the batch action for a single document
+#[async_trait]
+pub trait AiBatchExecutor: Send + Sync {
+    async fn execute(&self, action: AiBatchAction, document: &Document, params: &AiBatchParams) -> Result<String>;
+}
+
+/// Whether `error` means further requests in the batch will fail too
+fn halts_batch(error: &WritemagicError) -> bool {
+    match error {
+        WritemagicError::RateLimited { .. }
+        | WritemagicError::FeatureDisabled { .. }
+        | WritemagicError::BudgetExceeded { .. }
+        | WritemagicError::CircuitOpen { .. } => true,
+        _ => error.provider_error().is_some_and(|details| details.status == 429),
+    }
+}
+
+enum Outcome {
+    Completed(String),
+    Failed(String),
+    NotAttempted,
+}
+
+/// Run `action` over `documents` with at most `params.max_concurrency` in flight
+///
+/// A failing document does not stop the batch. Rate limiting or an open
+/// circuit breaker stops new documents from being started; those are
+/// reported as pending and covered by the resume token.
+pub async fn run_ai_batch(
+    executor: &dyn AiBatchExecutor,
+    project_id: EntityId,
+    documents: Vec<Document>,
+    action: AiBatchAction,
+    params: &AiBatchParams,
+) -> Result<BatchAiSummary> {
+    let completed_before: HashSet<EntityId> = match &params.resume_token {
+        Some(token) if token.project_id != project_id || token.action != action => {
+            return Err(WritemagicError::validation(format!(
+                "Resume token is for {:?} on project {}, not {:?} on project {}",
+                token.action, token.project_id, action, project_id
+            )));
+        }
+        Some(token) => token.completed.iter().copied().collect(),
+        None => HashSet::new(),
+    };
+
+    let (skipped, remaining): (Vec<Document>, Vec<Document>) = documents
+        .into_iter()
+        .partition(|document| completed_before.contains(&document.id));
+    let skipped: Vec<EntityId> = skipped.iter().map(|document| document.id).collect();
+
+    if params.dry_run {
+        return Ok(BatchAiSummary {
+            project_id,
+            action,
+            dry_run: true,
+            results: Vec::new(),
+            failures: Vec::new(),
+            skipped,
+            pending: remaining.iter().map(|document| document.id).collect(),
+            resume_token: None,
+        });
+    }
+
+    let halted = AtomicBool::new(false);
+    let mut outcomes: Vec<(usize, EntityId, Outcome)> = stream::iter(remaining.iter().enumerate())
+        .map(|(index, document)| {
+            let halted = &halted;
+            async move {
+                if halted.load(Ordering::SeqCst) {
+                    return (index, document.id, Outcome::NotAttempted);
+                }
+                match executor.execute(action, document, params).await {
+                    Ok(output) => (index, document.id, Outcome::Completed(output)),
+                    Err(error) => {
+                        if halts_batch(&error) {
+                            log::warn!("Halting AI batch for project {}: {}", project_id, error);
+                            halted.store(true, Ordering::SeqCst);
+                        }
+                        (index, document.id, Outcome::Failed(error.to_string()))
+                    }
+                }
+            }
+        })
+        .buffer_unordered(params.max_concurrency.max(1))
+        .collect()
+        .await;
+    outcomes.sort_by_key(|(index, _, _)| *index);
+
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+    let mut pending = Vec::new();
+    for (_, document_id, outcome) in outcomes {
+        match outcome {
+            Outcome::Completed(output) => results.push(BatchDocumentResult { document_id, output }),
+            Outcome::Failed(error) => failures.push(BatchDocumentFailure { document_id, error }),
+            Outcome::NotAttempted => pending.push(document_id),
+        }
+    }
+
+    let resume_token = (!failures.is_empty() || !pending.is_empty()).then(|| BatchResumeToken {
+        project_id,
+        action,
+        completed: skipped
+            .iter()
+            .copied()
+            .chain(results.iter().map(|result| result.document_id))
+            .collect(),
+    });
+
+    log::info!(
+        "AI batch {:?} on project {}: {} completed, {} failed, {} pending",
+        action,
+        project_id,
+        results.len(),
+        failures.len(),
+        pending.len()
+    );
+
+    Ok(BatchAiSummary {
+        project_id,
+        action,
+        dry_run: false,
+        results,
+        failures,
+        skipped,
+        pending,
+        resume_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use writemagic_shared::ContentType;
+
+    /// Fails documents titled "broken", rate limits "throttled" ones and finds
+    /// every provider's circuit open for "tripped" ones
+    #[derive(Default)]
+    struct RecordingExecutor {
+        calls: Mutex<HashMap<EntityId, u32>>,
+        healthy: AtomicBool,
+    }
+
+    #[async_trait]
+    impl AiBatchExecutor for RecordingExecutor {
+        async fn execute(&self, action: AiBatchAction, document: &Document, _params: &AiBatchParams) -> Result<String> {
+            *self.calls.lock().unwrap().entry(document.id).or_default() += 1;
+            match document.title.as_str() {
+                "broken" if !self.healthy.load(Ordering::SeqCst) => Err(WritemagicError::ai_provider("provider exploded")),
+                "throttled" if !self.healthy.load(Ordering::SeqCst) => Err(WritemagicError::rate_limited(10, 60)),
+                "tripped" if !self.healthy.load(Ordering::SeqCst) => Err(WritemagicError::circuit_open("claude")),
+                _ => Ok(format!("{:?} of {}", action, document.title)),
+            }
+        }
+    }
+
+    impl RecordingExecutor {
+        fn calls(&self, document: &Document) -> u32 {
+            self.calls.lock().unwrap().get(&document.id).copied().unwrap_or_default()
+        }
+    }
+
+    fn project(titles: &[&str]) -> Vec<Document> {
+        titles
+            .iter()
+            .map(|title| Document::new(title.to_string(), format!("Content of {}", title), ContentType::Markdown, None))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_each_document_processed_once_and_failures_isolated() {
+        let executor = RecordingExecutor::default();
+        let documents = project(&["one", "broken", "two", "three"]);
+        let project_id = EntityId::new();
+
+        let summary = run_ai_batch(&executor, project_id, documents.clone(), AiBatchAction::Summarize, &AiBatchParams::default())
+            .await
+            .unwrap();
+
+        assert!(documents.iter().all(|document| executor.calls(document) == 1));
+        let completed: Vec<EntityId> = summary.results.iter().map(|result| result.document_id).collect();
+        assert_eq!(completed, vec![documents[0].id, documents[2].id, documents[3].id]);
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].document_id, documents[1].id);
+        assert!(summary.failures[0].error.contains("provider exploded"));
+        assert_eq!(summary.resume_token.unwrap().completed, completed);
+    }
+
+    #[tokio::test]
+    async fn test_resume_token_reruns_only_unprocessed_documents() {
+        let executor = RecordingExecutor::default();
+        let documents = project(&["one", "throttled", "two", "three"]);
+        let project_id = EntityId::new();
+        let sequential = AiBatchParams { max_concurrency: 1, ..AiBatchParams::default() };
+
+        let first = run_ai_batch(&executor, project_id, documents.clone(), AiBatchAction::Tag, &sequential)
+            .await
+            .unwrap();
+        assert_eq!(first.results.len(), 1);
+        assert_eq!(first.pending, vec![documents[2].id, documents[3].id]);
+        assert_eq!(executor.calls(&documents[2]), 0);
+
+        executor.healthy.store(true, Ordering::SeqCst);
+        let resumed = AiBatchParams { resume_token: first.resume_token, ..sequential };
+        let second = run_ai_batch(&executor, project_id, documents.clone(), AiBatchAction::Tag, &resumed)
+            .await
+            .unwrap();
+
+        assert_eq!(second.skipped, vec![documents[0].id]);
+        assert_eq!(second.results.len(), 3);
+        assert!(second.resume_token.is_none());
+        assert_eq!(executor.calls(&documents[0]), 1);
+        assert_eq!(executor.calls(&documents[1]), 2);
+        assert_eq!(executor.calls(&documents[2]), 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_halts_but_provider_errors_do_not() {
+        let executor = RecordingExecutor::default();
+        let documents = project(&["broken", "tripped", "one"]);
+        let sequential = AiBatchParams { max_concurrency: 1, ..AiBatchParams::default() };
+
+        let summary = run_ai_batch(&executor, EntityId::new(), documents.clone(), AiBatchAction::Summarize, &sequential)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.failures.len(), 2);
+        assert_eq!(summary.pending, vec![documents[2].id]);
+        assert_eq!(executor.calls(&documents[2]), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_makes_no_calls_and_rejects_foreign_token() {
+        let executor = RecordingExecutor::default();
+        let documents = project(&["one", "two"]);
+        let project_id = EntityId::new();
+
+        let params = AiBatchParams { dry_run: true, ..AiBatchParams::default() };
+        let summary = run_ai_batch(&executor, project_id, documents.clone(), AiBatchAction::Rephrase, &params)
+            .await
+            .unwrap();
+        assert_eq!(summary.pending, vec![documents[0].id, documents[1].id]);
+        assert!(executor.calls.lock().unwrap().is_empty());
+
+        let foreign = AiBatchParams {
+            resume_token: Some(BatchResumeToken { project_id: EntityId::new(), action: AiBatchAction::Rephrase, completed: Vec::new() }),
+            ..AiBatchParams::default()
+        };
+        assert!(run_ai_batch(&executor, project_id, documents, AiBatchAction::Rephrase, &foreign).await.is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Assembling related documents into AI completion context
+
+use std::collections::HashSet;
+use writemagic_shared::{EntityId, Timestamp};
+use crate::entities::Document;
+
+/// Rough characters-per-token ratio used for context budgeting
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimate the token count of a piece of text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Parameters for a completion that draws on related documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextCompletionParams {
+    /// Model to use, or the engine default when `None`
+    pub model: Option<String>,
+    /// Token budget for all document context combined
+    pub context_token_budget: usize,
+    /// Token cap for a single document's snapshot
+    pub max_tokens_per_document: usize,
+}
+
+impl Default for ContextCompletionParams {
+    fn default() -> Self {
+        Self {
+            model: None,
+            context_token_budget: 4000,
+            max_tokens_per_document: 1500,
+        }
+    }
+}
+
+/// Token-budgeted view of a document for use as AI context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSnapshot {
+    pub document_id: EntityId,
+    pub title: String,
+    pub content: String,
+    pub updated_at: Timestamp,
+    pub estimated_tokens: usize,
+    pub truncated: bool,
+}
+
+impl Document {
+    /// Snapshot of this document trimmed to at most `max_tokens`
+    pub fn snapshot_for_context(&self, max_tokens: usize) -> DocumentSnapshot {
+        let max_chars = max_tokens * CHARS_PER_TOKEN;
+        let truncated = self.content.chars().count() > max_chars;
+        let content: String = if truncated {
+            self.content.chars().take(max_chars).collect()
+        } else {
+            self.content.clone()
+        };
+
+        DocumentSnapshot {
+            document_id: self.id,
+            title: self.title.clone(),
+            estimated_tokens: estimate_tokens(&self.title) + estimate_tokens(&content),
+            content,
+            updated_at: self.updated_at.clone(),
+            truncated,
+        }
+    }
+}
+
+/// Prompt with document context, and which documents made it in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextAssembly {
+    pub prompt: String,
+    /// Documents included, most relevant first
+    pub included: Vec<EntityId>,
+    /// Documents left out because the budget was exhausted
+    pub omitted: Vec<EntityId>,
+    /// Requested ids that did not resolve to a live document
+    pub missing: Vec<EntityId>,
+    pub context_tokens: usize,
+}
+
+/// Result of a completion made with document context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextualCompletion {
+    pub completion: String,
+    pub context: ContextAssembly,
+}
+
+/// Build the prompt from `documents`, most relevant to `prompt` first
+///
+/// Documents are ranked by how many prompt keywords they share, with more
+/// recently updated documents winning ties. Documents that don't fit in the
+/// remaining budget are omitted.
+pub fn assemble_context(
+    prompt: &str,
+    documents: &[Document],
+    missing: Vec<EntityId>,
+    params: &ContextCompletionParams,
+) -> ContextAssembly {
+    let keywords = keywords(prompt);
+
+    let mut ranked: Vec<(usize, DocumentSnapshot)> = documents
+        .iter()
+        .map(|document| {
+            let snapshot = document.snapshot_for_context(params.max_tokens_per_document);
+            (relevance(&keywords, &snapshot), snapshot)
+        })
+        .collect();
+    ranked.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| b.updated_at.as_datetime().cmp(&a.updated_at.as_datetime()))
+    });
+
+    let mut included = Vec::new();
+    let mut omitted = Vec::new();
+    let mut sections = Vec::new();
+    let mut context_tokens = 0;
+
+    for (_, snapshot) in ranked {
+        if context_tokens + snapshot.estimated_tokens > params.context_token_budget {
+            omitted.push(snapshot.document_id);
+            continue;
+        }
+        context_tokens += snapshot.estimated_tokens;
+        included.push(snapshot.document_id);
+        sections.push(format!("## {}\n{}", snapshot.title, snapshot.content));
+    }
+
+    let prompt = if sections.is_empty() {
+        prompt.to_string()
+    } else {
+        format!(
+            "Context from related documents:\n\n{}\n\n---\n\n{}",
+            sections.join("\n\n"),
+            prompt
+        )
+    };
+
+    ContextAssembly {
+        prompt,
+        included,
+        omitted,
+        missing,
+        context_tokens,
+    }
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4)
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+fn relevance(keywords: &HashSet<String>, snapshot: &DocumentSnapshot) -> usize {
+    let document_words = self::keywords(&format!("{} {}", snapshot.title, snapshot.content));
+    keywords.intersection(&document_words).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use writemagic_shared::ContentType;
+
+    fn document(title: &str, content: &str) -> Document {
+        Document::new(title.to_string(), content.to_string(), ContentType::Markdown, None)
+    }
+
+    #[test]
+    fn test_snapshot_truncates_to_token_cap() {
+        let doc = document("Long", &"word ".repeat(100));
+
+        let snapshot = doc.snapshot_for_context(10);
+
+        assert!(snapshot.truncated);
+        assert_eq!(snapshot.content.chars().count(), 40);
+    }
+
+    #[test]
+    fn test_relevant_documents_are_ranked_first() {
+        let garden = document("Garden", "Tomatoes need sunlight and water.");
+        let finance = document("Budget", "Quarterly spending report.");
+        let params = ContextCompletionParams::default();
+
+        let assembly = assemble_context("How much sunlight do tomatoes need?", &[finance.clone(), garden.clone()], Vec::new(), &params);
+
+        assert_eq!(assembly.included, vec![garden.id, finance.id]);
+        assert!(assembly.prompt.contains("Tomatoes need sunlight"));
+        assert!(assembly.prompt.ends_with("How much sunlight do tomatoes need?"));
+    }
+}
@@ -3,9 +3,13 @@
 use crate::value_objects::{DocumentTitle, DocumentContent, ProjectName};
 use crate::entities::{Document, Project};
 use crate::aggregates::{DocumentAggregate, ProjectAggregate};
-use writemagic_shared::{EntityId, Result, WritemagicError, ContentType};
+use crate::links::DocumentLink;
+use crate::reading_time::ReadingTimeConfig;
+use writemagic_shared::{EntityId, Result, Timestamp, WritemagicError, ContentType};
 use serde::{Serialize, Deserialize};
 
+pub use crate::markup::{html_to_markdown, markdown_to_html};
+
 /// Document DTO for web API responses
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentDto {
@@ -15,12 +19,22 @@ pub struct DocumentDto {
     pub content_type: String,
     pub word_count: u32,
     pub character_count: u32,
+    /// Effective language, honouring any manual override
+    pub language: String,
+    pub detected_language: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub created_by: Option<String>,
     pub updated_by: Option<String>,
     pub version: u64,
     pub is_deleted: bool,
+    #[serde(default)]
+    pub is_pinned: bool,
+    #[serde(default)]
+    pub is_generating: bool,
+    /// Estimated reading time in seconds
+    #[serde(default)]
+    pub reading_time_seconds: u32,
 }
 
 /// Project DTO for web API responses
@@ -38,6 +52,15 @@ pub struct ProjectDto {
     pub is_deleted: bool,
 }
 
+/// Document link DTO for web API responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentLinkDto {
+    pub source_id: String,
+    pub reference: String,
+    pub target_id: Option<String>,
+    pub dangling: bool,
+}
+
 /// Create document request DTO
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateDocumentDto {
@@ -62,8 +85,13 @@ pub struct CreateProjectDto {
 
 /// Conversion functions for Document types
 impl DocumentDto {
-    /// Convert from Document entity
+    /// Convert from Document entity, estimating reading time at the default speeds
     pub fn from_document(document: &Document) -> Self {
+        Self::from_document_with_rates(document, &ReadingTimeConfig::default())
+    }
+
+    /// Convert from Document entity, estimating reading time at the given speeds
+    pub fn from_document_with_rates(document: &Document, rates: &ReadingTimeConfig) -> Self {
         Self {
             id: document.id.to_string(),
             title: document.title.clone(),
@@ -71,12 +99,17 @@ impl DocumentDto {
             content_type: document.content_type.to_string(),
             word_count: document.word_count,
             character_count: document.character_count,
+            language: document.effective_language().to_string(),
+            detected_language: document.language.clone(),
             created_at: document.created_at.as_datetime(),
             updated_at: document.updated_at.as_datetime(),
             created_by: document.created_by.map(|id| id.to_string()),
             updated_by: document.updated_by.map(|id| id.to_string()),
             version: document.version,
             is_deleted: document.is_deleted,
+            is_pinned: document.is_pinned,
+            is_generating: document.is_generating,
+            reading_time_seconds: document.reading_time(rates),
         }
     }
 
@@ -84,6 +117,22 @@ impl DocumentDto {
     pub fn from_aggregate(aggregate: &DocumentAggregate) -> Self {
         Self::from_document(aggregate.document())
     }
+
+    /// Convert from DocumentAggregate, estimating reading time at the given speeds
+    pub fn from_aggregate_with_rates(aggregate: &DocumentAggregate, rates: &ReadingTimeConfig) -> Self {
+        Self::from_document_with_rates(aggregate.document(), rates)
+    }
+}
+
+impl From<&DocumentLink> for DocumentLinkDto {
+    fn from(link: &DocumentLink) -> Self {
+        Self {
+            source_id: link.source_id.to_string(),
+            reference: link.reference.clone(),
+            target_id: link.target_id.map(|id| id.to_string()),
+            dangling: link.is_dangling(),
+        }
+    }
 }
 
 /// Conversion functions for Project types
@@ -110,6 +159,49 @@ impl ProjectDto {
     }
 }
 
+/// Wire format for timestamps in FFI and WASM DTOs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// RFC3339 string, e.g. `2024-01-01T12:00:00+00:00`
+    #[default]
+    Rfc3339,
+    /// Signed milliseconds since the Unix epoch
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    /// Encode a timestamp in this format
+    pub fn encode(self, timestamp: &Timestamp) -> WireTimestamp {
+        match self {
+            Self::Rfc3339 => WireTimestamp::Rfc3339(timestamp.as_datetime().to_rfc3339()),
+            Self::EpochMillis => WireTimestamp::EpochMillis(timestamp.as_datetime().timestamp_millis()),
+        }
+    }
+}
+
+/// Timestamp as emitted to clients, in either supported format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WireTimestamp {
+    EpochMillis(i64),
+    Rfc3339(String),
+}
+
+impl WireTimestamp {
+    /// Decode back into a domain timestamp
+    pub fn to_timestamp(&self) -> Result<Timestamp> {
+        match self {
+            Self::EpochMillis(millis) => chrono::DateTime::from_timestamp_millis(*millis)
+                .map(Timestamp::from_datetime)
+                .ok_or_else(|| WritemagicError::validation(format!("Epoch milliseconds out of range: {}", millis))),
+            Self::Rfc3339(value) => chrono::DateTime::parse_from_rfc3339(value)
+                .map(|dt| Timestamp::from_datetime(dt.with_timezone(&chrono::Utc)))
+                .map_err(|e| WritemagicError::validation(format!("Invalid RFC3339 timestamp '{}': {}", value, e))),
+        }
+    }
+}
+
 /// Type conversion utilities
 pub struct TypeConverter;
 
@@ -296,6 +388,36 @@ mod tests {
         assert_eq!(pagination.limit, 10);
     }
 
+    #[test]
+    fn test_timestamp_formats_round_trip() {
+        let timestamp = Timestamp::from_datetime(
+            chrono::DateTime::from_timestamp_millis(1_700_000_000_123).unwrap(),
+        );
+
+        for format in [TimestampFormat::Rfc3339, TimestampFormat::EpochMillis] {
+            assert_eq!(format.encode(&timestamp).to_timestamp().unwrap(), timestamp);
+        }
+    }
+
+    #[test]
+    fn test_epoch_millis_format_selected_by_flag() {
+        let timestamp = Timestamp::from_datetime(chrono::DateTime::from_timestamp_millis(1_500).unwrap());
+
+        assert_eq!(TimestampFormat::EpochMillis.encode(&timestamp), WireTimestamp::EpochMillis(1_500));
+        assert!(matches!(TimestampFormat::default().encode(&timestamp), WireTimestamp::Rfc3339(_)));
+        assert_eq!(serde_json::to_value(TimestampFormat::EpochMillis.encode(&timestamp)).unwrap(), serde_json::json!(1_500));
+    }
+
+    #[test]
+    fn test_pre_epoch_timestamps() {
+        let timestamp = Timestamp::from_string("1969-07-20T20:17:40.250Z").unwrap();
+
+        let encoded = TimestampFormat::EpochMillis.encode(&timestamp);
+        assert_eq!(encoded, WireTimestamp::EpochMillis(-14_182_939_750));
+        assert_eq!(encoded.to_timestamp().unwrap(), timestamp);
+        assert_eq!(TimestampFormat::Rfc3339.encode(&timestamp).to_timestamp().unwrap(), timestamp);
+    }
+
     #[test]
     fn test_pagination_metadata() {
         let metadata = PaginationConverter::calculate_metadata(100, 2, 20);
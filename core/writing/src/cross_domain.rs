@@ -0,0 +1,480 @@
+//! Adapters exposing the writing domain to cross-domain coordination
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use writemagic_shared::services::{
+    CreateDocumentRequest, CreateProjectRequest, DocumentInfo, DocumentStats, ProjectInfo,
+    UpdateDocumentRequest, UpdateProjectRequest,
+};
+use writemagic_shared::{
+    ContentType, DocumentTag, EntityId, Pagination, ProjectDomainService, Result, WritemagicError,
+    WritingDomainService,
+};
+use crate::entities::{Document, Project};
+use crate::reading_time::ReadingTimeConfig;
+use crate::repositories::{DocumentRepository, ProjectRepository};
+use crate::services::{DocumentManagementService, ProjectManagementService};
+use crate::value_objects::{DocumentContent, DocumentTitle, ProjectName};
+
+/// Default number of results for a cross-domain document search
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// Metadata key of a created document's content type, as accepted by `ContentType::from_string`
+pub const CONTENT_TYPE_METADATA_KEY: &str = "content_type";
+
+fn document_info(document: &Document, project_id: Option<EntityId>) -> DocumentInfo {
+    DocumentInfo {
+        id: document.id,
+        title: document.title.clone(),
+        content: document.content.clone(),
+        project_id,
+        created_at: document.created_at.as_datetime(),
+        updated_at: document.updated_at.as_datetime(),
+        metadata: HashMap::new(),
+    }
+}
+
+fn project_info(project: &Project) -> ProjectInfo {
+    ProjectInfo {
+        id: project.id,
+        name: project.name.clone(),
+        description: project.description.clone(),
+        created_at: project.created_at.as_datetime(),
+        updated_at: project.updated_at.as_datetime(),
+        document_count: project.document_ids.len() as u32,
+    }
+}
+
+/// `WritingDomainService` backed by the document management service
+pub struct WritingServiceAdapter {
+    service: Arc<DocumentManagementService>,
+    document_repository: Arc<dyn DocumentRepository>,
+    project_service: Arc<ProjectManagementService>,
+    default_content_type: ContentType,
+}
+
+impl WritingServiceAdapter {
+    pub fn new(
+        service: Arc<DocumentManagementService>,
+        document_repository: Arc<dyn DocumentRepository>,
+        project_service: Arc<ProjectManagementService>,
+    ) -> Self {
+        Self { service, document_repository, project_service, default_content_type: ContentType::Markdown }
+    }
+
+    /// Content type of created documents whose request doesn't name one
+    pub fn with_default_content_type(mut self, content_type: ContentType) -> Self {
+        self.default_content_type = content_type;
+        self
+    }
+
+    async fn load(&self, document_id: &EntityId) -> Result<Document> {
+        self.document_repository
+            .find_by_id(document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Document {}", document_id)))
+    }
+}
+
+#[async_trait]
+impl WritingDomainService for WritingServiceAdapter {
+    /// Create a document, adding it to the request's project if it names one
+    async fn create_document(&self, request: CreateDocumentRequest) -> Result<DocumentInfo> {
+        let content_type = match request.metadata.get(CONTENT_TYPE_METADATA_KEY) {
+            Some(content_type) => ContentType::from_string(content_type).map_err(WritemagicError::validation)?,
+            None => self.default_content_type.clone(),
+        };
+        let aggregate = self.service
+            .create_document(
+                DocumentTitle::new(request.title)?,
+                DocumentContent::new(request.content)?,
+                content_type,
+                None,
+                None,
+            )
+            .await?;
+        let document = aggregate.document();
+
+        if let Some(project_id) = request.project_id {
+            self.project_service.add_document_to_project(project_id, document.id, None).await?;
+        }
+
+        Ok(document_info(document, request.project_id))
+    }
+
+    async fn get_document(&self, document_id: &EntityId) -> Result<Option<DocumentInfo>> {
+        Ok(self.service
+            .get_document(document_id)
+            .await?
+            .map(|aggregate| document_info(aggregate.document(), None)))
+    }
+
+    async fn update_document(&self, request: UpdateDocumentRequest) -> Result<()> {
+        let title = request.title.map(DocumentTitle::new).transpose()?;
+        let content = request.content.map(DocumentContent::new).transpose()?;
+        self.service.update_document(request.document_id, title, content, None).await?;
+        Ok(())
+    }
+
+    async fn delete_document(&self, document_id: &EntityId) -> Result<()> {
+        self.service.delete_document(*document_id, None).await
+    }
+
+    async fn search_documents(&self, query: &str, limit: Option<u32>) -> Result<Vec<DocumentInfo>> {
+        let pagination = Pagination::new(0, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))?;
+        let documents = self.document_repository.search_by_content(query, pagination).await?;
+        Ok(documents.iter().map(|document| document_info(document, None)).collect())
+    }
+
+    async fn get_document_stats(&self, document_id: &EntityId) -> Result<DocumentStats> {
+        let document = self.load(document_id).await?;
+        let paragraph_count = document.content
+            .split("\n\n")
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .count() as u32;
+
+        Ok(DocumentStats {
+            word_count: document.word_count,
+            character_count: document.character_count,
+            paragraph_count,
+            reading_time_minutes: (document.reading_time(&ReadingTimeConfig::default()) + 59) / 60,
+            last_modified: document.updated_at.as_datetime(),
+        })
+    }
+
+    async fn tag_document(&self, document_id: &EntityId, tags: Vec<String>) -> Result<()> {
+        let tags = tags.into_iter().map(DocumentTag::new).collect::<Result<Vec<_>>>()?;
+        self.service.add_document_tags(*document_id, tags, None).await?;
+        Ok(())
+    }
+}
+
+/// `ProjectDomainService` backed by the project management service
+pub struct ProjectServiceAdapter {
+    service: Arc<ProjectManagementService>,
+    project_repository: Arc<dyn ProjectRepository>,
+    document_repository: Arc<dyn DocumentRepository>,
+}
+
+impl ProjectServiceAdapter {
+    pub fn new(
+        service: Arc<ProjectManagementService>,
+        project_repository: Arc<dyn ProjectRepository>,
+        document_repository: Arc<dyn DocumentRepository>,
+    ) -> Self {
+        Self { service, project_repository, document_repository }
+    }
+
+    async fn load(&self, project_id: &EntityId) -> Result<Project> {
+        self.project_repository
+            .find_by_id(project_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Project {}", project_id)))
+    }
+}
+
+#[async_trait]
+impl ProjectDomainService for ProjectServiceAdapter {
+    async fn create_project(&self, request: CreateProjectRequest) -> Result<ProjectInfo> {
+        let aggregate = self.service
+            .create_project(ProjectName::new(request.name)?, request.description, None)
+            .await?;
+
+        Ok(project_info(aggregate.project()))
+    }
+
+    async fn get_project(&self, project_id: &EntityId) -> Result<Option<ProjectInfo>> {
+        Ok(self.project_repository.find_by_id(project_id).await?.as_ref().map(project_info))
+    }
+
+    async fn add_document_to_project(&self, project_id: &EntityId, document_id: &EntityId) -> Result<()> {
+        self.service.add_document_to_project(*project_id, *document_id, None).await?;
+        Ok(())
+    }
+
+    async fn remove_document_from_project(&self, project_id: &EntityId, document_id: &EntityId) -> Result<()> {
+        self.service.remove_document_from_project(*project_id, *document_id, None).await?;
+        Ok(())
+    }
+
+    async fn get_project_documents(&self, project_id: &EntityId) -> Result<Vec<DocumentInfo>> {
+        let project = self.load(project_id).await?;
+
+        let mut documents = Vec::with_capacity(project.document_ids.len());
+        for document_id in &project.document_ids {
+            if let Some(document) = self.document_repository.find_by_id(document_id).await? {
+                if !document.is_archived {
+                    documents.push(document_info(&document, Some(project.id)));
+                }
+            }
+        }
+        Ok(documents)
+    }
+
+    async fn update_project(&self, request: UpdateProjectRequest) -> Result<()> {
+        if let Some(name) = request.name {
+            self.service.update_project_name(request.project_id, ProjectName::new(name)?, None).await?;
+        }
+
+        if request.description.is_some() {
+            let mut project = self.load(&request.project_id).await?;
+            project.update_description(request.description, None);
+            self.project_repository.save(&project).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use writemagic_shared::{Repository, UnitOfWork};
+    use writemagic_shared::services::{
+        AIGenerationRequest, AIGenerationResponse, AIWorkflowRequest, AIWorkflowResult, AnalysisType,
+        BranchInfo, CommitInfo, CreateBranchRequest, CreateCommitRequest, DocumentAnalysis, DocumentDiff,
+        MergeBranchesRequest, MergeResult, WritingSuggestion, WritingSuggestionsRequest,
+    };
+    use crate::repositories::{InMemoryDocumentRepository, InMemoryProjectRepository};
+    use crate::unit_of_work::{InMemoryWriteStore, StagedUnitOfWork};
+    use writemagic_shared::{
+        AIDomainService, CrossDomainCoordinator, CrossDomainServiceRegistry, DocumentWorkflowRequest,
+        EventBus, InMemoryEventBus, VersionControlDomainService,
+    };
+
+    struct MockAiService;
+
+    #[async_trait]
+    impl AIDomainService for MockAiService {
+        async fn generate_content(&self, _request: AIGenerationRequest) -> Result<AIGenerationResponse> {
+            Ok(AIGenerationResponse {
+                content: "The keeper climbed the lighthouse stairs at dusk.".to_string(),
+                title: Some("Lighthouse".to_string()),
+                metadata: HashMap::new(),
+                tokens_used: 42,
+                processing_time_ms: 5,
+            })
+        }
+
+        async fn analyze_document(&self, _document_id: &EntityId, _analysis_type: AnalysisType) -> Result<DocumentAnalysis> {
+            Err(WritemagicError::not_implemented("analyze_document"))
+        }
+
+        async fn get_writing_suggestions(&self, _request: WritingSuggestionsRequest) -> Result<Vec<WritingSuggestion>> {
+            Err(WritemagicError::not_implemented("get_writing_suggestions"))
+        }
+
+        async fn process_document_workflow(&self, _request: AIWorkflowRequest) -> Result<AIWorkflowResult> {
+            Err(WritemagicError::not_implemented("process_document_workflow"))
+        }
+    }
+
+    /// Version control recording commit requests, or failing every commit
+    struct MockVersionControl {
+        fail: bool,
+        commits: Mutex<Vec<CreateCommitRequest>>,
+    }
+
+    #[async_trait]
+    impl VersionControlDomainService for MockVersionControl {
+        async fn create_commit(&self, request: CreateCommitRequest) -> Result<CommitInfo> {
+            if self.fail {
+                return Err(WritemagicError::git("repository is locked"));
+            }
+
+            self.commits.lock().unwrap().push(request.clone());
+            Ok(CommitInfo {
+                id: EntityId::new(),
+                document_id: request.document_id,
+                message: request.message,
+                created_at: chrono::Utc::now(),
+                metadata: request.metadata,
+            })
+        }
+
+        async fn get_commit_history(&self, _document_id: &EntityId, _limit: Option<u32>) -> Result<Vec<CommitInfo>> {
+            Ok(Vec::new())
+        }
+
+        async fn create_branch(&self, _request: CreateBranchRequest) -> Result<BranchInfo> {
+            Err(WritemagicError::not_implemented("create_branch"))
+        }
+
+        async fn merge_branches(&self, _request: MergeBranchesRequest) -> Result<MergeResult> {
+            Err(WritemagicError::not_implemented("merge_branches"))
+        }
+
+        async fn get_document_diff(&self, _from_commit: &EntityId, _to_commit: &EntityId) -> Result<DocumentDiff> {
+            Err(WritemagicError::not_implemented("get_document_diff"))
+        }
+    }
+
+    struct Fixture {
+        coordinator: CrossDomainCoordinator,
+        unit_of_work: Arc<tokio::sync::Mutex<StagedUnitOfWork>>,
+        documents: InMemoryDocumentRepository,
+        projects: InMemoryProjectRepository,
+        version_control: Arc<MockVersionControl>,
+        project_id: EntityId,
+    }
+
+    async fn setup(fail_commit: bool) -> Fixture {
+        let documents = InMemoryDocumentRepository::new();
+        let projects = InMemoryProjectRepository::new();
+        let document_repository = Arc::new(documents.clone()) as Arc<dyn DocumentRepository>;
+        let project_repository = Arc::new(projects.clone()) as Arc<dyn ProjectRepository>;
+
+        let document_service = Arc::new(DocumentManagementService::new(document_repository.clone()));
+        let project_service = Arc::new(ProjectManagementService::new(project_repository.clone(), document_repository.clone()));
+        let project = project_service
+            .create_project(ProjectName::new("Stories").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        let version_control = Arc::new(MockVersionControl { fail: fail_commit, commits: Mutex::new(Vec::new()) });
+        let unit_of_work = Arc::new(tokio::sync::Mutex::new(StagedUnitOfWork::new(
+            Arc::new(InMemoryWriteStore::new(documents.clone(), projects.clone())),
+            document_service.clone(),
+            project_service.clone(),
+            document_repository.clone(),
+            project_repository.clone(),
+        )));
+
+        let mut registry = CrossDomainServiceRegistry::new(Arc::new(InMemoryEventBus::new()) as Arc<dyn EventBus>);
+        registry.register_ai_service(Arc::new(MockAiService));
+        registry.register_writing_service(Arc::new(WritingServiceAdapter::new(
+            document_service,
+            document_repository.clone(),
+            project_service.clone(),
+        )));
+        registry.register_project_service(Arc::new(ProjectServiceAdapter::new(project_service, project_repository, document_repository)));
+        registry.register_version_control_service(version_control.clone());
+        registry.register_unit_of_work(unit_of_work.clone());
+
+        Fixture {
+            coordinator: CrossDomainCoordinator::new(Arc::new(registry)),
+            unit_of_work,
+            documents,
+            projects,
+            version_control,
+            project_id: project.project().id,
+        }
+    }
+
+    fn workflow_request(project_id: EntityId) -> DocumentWorkflowRequest {
+        DocumentWorkflowRequest {
+            generation: AIGenerationRequest {
+                prompt: "Write about a lighthouse keeper".to_string(),
+                max_tokens: None,
+                temperature: None,
+                context: None,
+                style: None,
+            },
+            title: None,
+            project_id: Some(project_id),
+            tags: vec!["draft".to_string(), "lighthouse".to_string()],
+            commit_message: Some("Initial draft".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_persists_every_artifact() {
+        let fixture = setup(false).await;
+
+        let result = fixture.coordinator
+            .create_document_workflow(workflow_request(fixture.project_id))
+            .await
+            .unwrap();
+
+        let document = fixture.documents.find_by_id(&result.document.id).await.unwrap().unwrap();
+        assert_eq!(document.title, "Lighthouse");
+        assert_eq!(document.content, "The keeper climbed the lighthouse stairs at dusk.");
+        let tags: Vec<String> = document.tags.iter().map(|tag| tag.to_string()).collect();
+        assert_eq!(tags, vec!["draft", "lighthouse"]);
+
+        let project = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        assert_eq!(project.document_ids, vec![document.id]);
+
+        let commits = fixture.version_control.commits.lock().unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].document_id, document.id);
+        assert_eq!(result.commit.unwrap().message, "Initial draft");
+        assert_eq!(result.tokens_used, 42);
+    }
+
+    #[tokio::test]
+    async fn test_commit_failure_rolls_back_document_and_project() {
+        let fixture = setup(true).await;
+        let project_before = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+
+        let error = fixture.coordinator
+            .create_document_workflow(workflow_request(fixture.project_id))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.failed_step(), Some("commit"));
+        assert_eq!(fixture.documents.count().await.unwrap(), 0);
+
+        let project_after = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        assert!(project_after.document_ids.is_empty());
+        assert_eq!(project_after.version, project_before.version);
+        assert!(fixture.version_control.commits.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unit_of_work_writes_stay_invisible_until_commit() {
+        let fixture = setup(false).await;
+        let mut unit_of_work = fixture.unit_of_work.lock().await;
+        unit_of_work.begin().await.unwrap();
+
+        let request = CreateDocumentRequest {
+            title: "Keeper's log".to_string(),
+            content: "Lamp lit at six.".to_string(),
+            project_id: Some(fixture.project_id),
+            metadata: HashMap::from([(CONTENT_TYPE_METADATA_KEY.to_string(), "plain_text".to_string())]),
+        };
+        let created = unit_of_work.writing_service().unwrap().create_document(request).await.unwrap();
+        assert_eq!(created.project_id, Some(fixture.project_id));
+        assert!(fixture.documents.find_by_id(&created.id).await.unwrap().is_none());
+        let project = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        assert!(project.document_ids.is_empty());
+
+        unit_of_work.commit().await.unwrap();
+
+        let document = fixture.documents.find_by_id(&created.id).await.unwrap().unwrap();
+        assert_eq!(document.content_type, ContentType::PlainText);
+        let project = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        assert_eq!(project.document_ids, vec![created.id]);
+    }
+
+    #[tokio::test]
+    async fn test_commit_refuses_to_overwrite_changes_made_since_the_read() {
+        let fixture = setup(false).await;
+        let mut unit_of_work = fixture.unit_of_work.lock().await;
+        unit_of_work.begin().await.unwrap();
+
+        let request = CreateDocumentRequest {
+            title: "Keeper's log".to_string(),
+            content: "Lamp lit at six.".to_string(),
+            project_id: Some(fixture.project_id),
+            metadata: HashMap::new(),
+        };
+        let created = unit_of_work.writing_service().unwrap().create_document(request).await.unwrap();
+
+        // Another writer changes the project after the unit of work read it
+        let mut project = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        project.update_description(Some("Coastal stories".to_string()), None);
+        fixture.projects.save(&project).await.unwrap();
+
+        let error = unit_of_work.commit().await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Conflict { .. }), "{:?}", error);
+        assert!(!unit_of_work.in_transaction());
+
+        assert!(fixture.documents.find_by_id(&created.id).await.unwrap().is_none());
+        let stored = fixture.projects.find_by_id(&fixture.project_id).await.unwrap().unwrap();
+        assert_eq!(stored.description.as_deref(), Some("Coastal stories"));
+        assert!(stored.document_ids.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Startup self-check of an engine's dependencies
+//!
+//! Each check reports whether it passed and, when it didn't, a hint an
+//! operator can act on before the engine starts serving traffic.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Outcome of one self-check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+    /// How to fix the failure; absent when the check passed
+    pub remediation: Option<String>,
+}
+
+impl DiagnosticCheck {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+            remediation: None,
+        }
+    }
+
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: detail.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+/// Results of all self-checks, in the order they ran
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+impl DiagnosticsReport {
+    pub const DATABASE: &'static str = "database";
+    pub const MIGRATIONS: &'static str = "migrations";
+    pub const AI_PROVIDERS: &'static str = "ai_providers";
+    pub const ENCRYPTION_KEY: &'static str = "encryption_key";
+    pub const STORAGE: &'static str = "storage";
+
+    pub fn push(&mut self, check: DiagnosticCheck) {
+        self.checks.push(check);
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &DiagnosticCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    pub fn check(&self, name: &str) -> Option<&DiagnosticCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+}
+
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = if check.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+            if let Some(remediation) = &check.remediation {
+                writeln!(f, "       hint: {}", remediation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_is_healthy_only_when_every_check_passed() {
+        let mut report = DiagnosticsReport::default();
+        report.push(DiagnosticCheck::pass(DiagnosticsReport::DATABASE, "Connected"));
+        assert!(report.is_healthy());
+
+        report.push(DiagnosticCheck::fail(DiagnosticsReport::STORAGE, "Read-only", "Fix permissions"));
+        assert!(!report.is_healthy());
+        assert_eq!(report.failures().map(|check| check.name.as_str()).collect::<Vec<_>>(), vec!["storage"]);
+
+        let rendered = report.to_string();
+        assert!(rendered.contains("[PASS] database: Connected"));
+        assert!(rendered.contains("[FAIL] storage: Read-only\n       hint: Fix permissions"));
+    }
+}
@@ -0,0 +1,388 @@
+//! Export of documents as Markdown, HTML, plain text or PDF
+//!
+//! Embedded images are not carried over yet: HTML, plain text and PDF output
+//! show an `[Image: alt]` placeholder where each image was.
+
+use crate::entities::Document;
+use crate::markup::{escape_html, html_to_markdown, html_to_plain_text, replace_images_with_placeholders};
+use pulldown_cmark::{html::push_html, Options, Parser};
+use serde::{Deserialize, Serialize};
+use writemagic_shared::{sanitize_html, ContentType, Result, WritemagicError};
+
+/// A4 page size, in points
+const PDF_PAGE_WIDTH: f32 = 595.0;
+const PDF_PAGE_HEIGHT: f32 = 842.0;
+const PDF_MARGIN: f32 = 56.0;
+const PDF_FONT_SIZE: f32 = 11.0;
+const PDF_LEADING: f32 = 15.0;
+/// Characters per line, from Helvetica's average advance of about half the type size
+const PDF_LINE_CHARS: usize = 86;
+
+/// Output format of a document export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentExportFormat {
+    Markdown,
+    Html,
+    PlainText,
+    Pdf,
+}
+
+impl DocumentExportFormat {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s {
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            "plain_text" => Ok(Self::PlainText),
+            "pdf" => Ok(Self::Pdf),
+            _ => Err(WritemagicError::validation(format!("Unknown export format: {}", s))),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+            Self::PlainText => "text/plain; charset=utf-8",
+            Self::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::PlainText => "txt",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// Renders documents into downloadable files
+#[derive(Debug, Clone, Default)]
+pub struct ExportService;
+
+impl ExportService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Render `document` in `format`
+    ///
+    /// Text formats are UTF-8. PDF output is a plain single-column layout in
+    /// Helvetica, which only covers Windows-1252; documents with other
+    /// characters are refused rather than exported with them replaced.
+    pub fn export(&self, document: &Document, format: DocumentExportFormat) -> Result<Vec<u8>> {
+        match format {
+            DocumentExportFormat::Markdown => Ok(self.markdown(document)?.into_bytes()),
+            DocumentExportFormat::Html => Ok(self.html(document)?.into_bytes()),
+            DocumentExportFormat::PlainText => Ok(self.plain_text(document)?.into_bytes()),
+            DocumentExportFormat::Pdf => render_pdf(&document.title, &self.plain_text(document)?),
+        }
+    }
+
+    fn markdown(&self, document: &Document) -> Result<String> {
+        match document.content_type {
+            ContentType::Html => html_to_markdown(&document.content),
+            _ => Ok(document.content.clone()),
+        }
+    }
+
+    /// Document body as a sanitized HTML fragment, with images replaced by placeholders
+    fn body_html(&self, document: &Document) -> Result<String> {
+        let html = match document.content_type {
+            ContentType::Markdown => render_markdown(&document.content),
+            ContentType::Html => document.content.clone(),
+            _ => format!("<pre>{}</pre>", escape_html(&document.content)),
+        };
+        Ok(sanitize_html(&replace_images_with_placeholders(&html)))
+    }
+
+    fn html(&self, document: &Document) -> Result<String> {
+        Ok(format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+            escape_html(&document.title),
+            self.body_html(document)?
+        ))
+    }
+
+    fn plain_text(&self, document: &Document) -> Result<String> {
+        match document.content_type {
+            ContentType::Markdown | ContentType::Html => {
+                Ok(collapse_blank_lines(&html_to_plain_text(&self.body_html(document)?)))
+            }
+            _ => Ok(document.content.clone()),
+        }
+    }
+}
+
+/// CommonMark with tables and strikethrough, rendered to unsanitized HTML
+fn render_markdown(markdown: &str) -> String {
+    let mut html = String::new();
+    push_html(&mut html, Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH));
+    html
+}
+
+/// Trim trailing whitespace and keep at most one blank line between blocks
+fn collapse_blank_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().map_or(true, |last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    if lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Greedy word wrap at `width` characters, splitting words that are longer than a line
+fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    let mut wrapped = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > width {
+                if !current.is_empty() {
+                    wrapped.push(std::mem::take(&mut current));
+                }
+                wrapped.push(word.drain(..width).collect());
+            }
+            let word: String = word.into_iter().collect();
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+                wrapped.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&word);
+        }
+        wrapped.push(current);
+    }
+    wrapped
+}
+
+/// Windows-1252 byte for `c`, the encoding of the standard fonts
+fn win_ansi_byte(c: char) -> Option<u8> {
+    match c {
+        '\t' => Some(b' '),
+        '€' => Some(0x80),
+        '…' => Some(0x85),
+        '‘' => Some(0x91),
+        '’' => Some(0x92),
+        '“' => Some(0x93),
+        '”' => Some(0x94),
+        '•' => Some(0x95),
+        '–' => Some(0x96),
+        '—' => Some(0x97),
+        ' '..='~' | '\u{a0}'..='\u{ff}' => Some(c as u8),
+        _ => None,
+    }
+}
+
+/// Refuse text the standard fonts cannot show, naming a few of the offending characters
+fn check_pdf_text(text: &str) -> Result<()> {
+    let mut unsupported: Vec<char> = text.chars().filter(|c| *c != '\n' && win_ansi_byte(*c).is_none()).collect();
+    if unsupported.is_empty() {
+        return Ok(());
+    }
+
+    unsupported.sort_unstable();
+    unsupported.dedup();
+    let sample: String = unsupported.iter().take(5).collect();
+    Err(WritemagicError::validation(format!(
+        "PDF export only supports Latin text; the document contains unsupported characters such as '{}'. Export as HTML instead",
+        sample
+    )))
+}
+
+/// PDF literal string in Windows-1252, for text that passed `check_pdf_text`
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for c in text.chars() {
+        if matches!(c, '(' | ')' | '\\') {
+            bytes.push(b'\\');
+        }
+        bytes.push(win_ansi_byte(c).unwrap_or(b'?'));
+    }
+    bytes.push(b')');
+    bytes
+}
+
+/// A minimal PDF 1.4 file showing `text` in Helvetica, one page per screenful of lines
+fn render_pdf(title: &str, text: &str) -> Result<Vec<u8>> {
+    check_pdf_text(title)?;
+    check_pdf_text(text)?;
+
+    let lines = wrap_lines(text, PDF_LINE_CHARS);
+    let lines_per_page = ((PDF_PAGE_HEIGHT - 2.0 * PDF_MARGIN) / PDF_LEADING) as usize;
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&[] as &[String]]
+    } else {
+        lines.chunks(lines_per_page).collect()
+    };
+
+    // Objects 1-4 are the catalog, page tree, font and info; each page then
+    // takes two, the page itself and its content stream
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + 2 * i).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        [b"<< /Title ".to_vec(), pdf_string(title), b" /Producer (WriteMagic) >>".to_vec()].concat(),
+    ];
+
+    for (page, content_id) in pages.iter().zip(page_ids.iter().map(|id| id + 1)) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                PDF_PAGE_WIDTH, PDF_PAGE_HEIGHT, content_id
+            )
+            .into_bytes(),
+        );
+
+        let mut stream = format!(
+            "BT\n/F1 {} Tf\n{} TL\n{} {} Td\n",
+            PDF_FONT_SIZE,
+            PDF_LEADING,
+            PDF_MARGIN,
+            PDF_PAGE_HEIGHT - PDF_MARGIN - PDF_FONT_SIZE
+        )
+        .into_bytes();
+        for line in page.iter() {
+            stream.extend(pdf_string(line));
+            stream.extend_from_slice(b" Tj T*\n");
+        }
+        stream.extend_from_slice(b"ET");
+
+        let mut object = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        object.extend(stream);
+        object.extend_from_slice(b"\nendstream");
+        objects.push(object);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+    Ok(pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> Document {
+        Document::new(
+            "Field <Notes>".to_string(),
+            "# Heading\n\nSome **bold** and *italic* text.\n\n![Seed tray](images/tray.png)".to_string(),
+            ContentType::Markdown,
+            None,
+        )
+    }
+
+    fn export_text(format: DocumentExportFormat) -> String {
+        String::from_utf8(ExportService::new().export(&document(), format).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_text_formats_render_markdown_and_replace_images() {
+        assert_eq!(export_text(DocumentExportFormat::Markdown), document().content);
+
+        let html = export_text(DocumentExportFormat::Html);
+        assert!(html.contains("<title>Field &lt;Notes&gt;</title>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains("[Image: Seed tray]"));
+        assert!(!html.contains("<img"));
+
+        assert_eq!(
+            export_text(DocumentExportFormat::PlainText),
+            "Heading\n\nSome bold and italic text.\n\n[Image: Seed tray]"
+        );
+    }
+
+    #[test]
+    fn test_html_export_strips_scripts_and_unsafe_links() {
+        let document = Document::new(
+            "Notes".to_string(),
+            "<p onclick=\"steal()\">Hi<script>steal()</script></p>".to_string(),
+            ContentType::Html,
+            None,
+        );
+        let html = String::from_utf8(ExportService::new().export(&document, DocumentExportFormat::Html).unwrap()).unwrap();
+        assert!(html.contains("<p>Hi</p>"));
+        assert!(!html.contains("steal"));
+
+        let mut markdown = document.clone();
+        markdown.content = "[Click](javascript:steal)".to_string();
+        markdown.content_type = ContentType::Markdown;
+        let html = String::from_utf8(ExportService::new().export(&markdown, DocumentExportFormat::Html).unwrap()).unwrap();
+        assert!(html.contains("<a>Click</a>"));
+
+        // Raw HTML inside Markdown is rendered and then sanitized too
+        markdown.content = "Hi <img src=x onerror=steal()> <script>steal()</script>".to_string();
+        let html = String::from_utf8(ExportService::new().export(&markdown, DocumentExportFormat::Html).unwrap()).unwrap();
+        assert!(!html.contains("steal"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn test_pdf_export_refuses_text_outside_the_standard_fonts() {
+        let mut document = document();
+        document.content = "Café – “naïve” résumé".to_string();
+        assert!(ExportService::new().export(&document, DocumentExportFormat::Pdf).is_ok());
+
+        document.content = "Привет, 世界".to_string();
+        let error = ExportService::new().export(&document, DocumentExportFormat::Pdf).unwrap_err();
+        assert!(matches!(error, WritemagicError::Validation { .. }), "{:?}", error);
+        assert!(error.to_string().contains('世'));
+    }
+
+    #[test]
+    fn test_pdf_export_is_well_formed() {
+        let pdf = ExportService::new().export(&document(), DocumentExportFormat::Pdf).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(Some bold and italic text.) Tj"));
+        assert!(text.contains("/Title (Field <Notes>)"));
+
+        let xref: usize = text.rsplit("startxref\n").next().unwrap().trim_end_matches("\n%%EOF\n").parse().unwrap();
+        assert!(text[xref..].starts_with("xref\n0 7\n"));
+    }
+
+    #[test]
+    fn test_long_words_wrap_and_formats_parse() {
+        let long = "x".repeat(200);
+        let wrapped = wrap_lines(&format!("short {}", long), PDF_LINE_CHARS);
+        assert_eq!(wrapped.len(), 4);
+        assert!(wrapped.iter().all(|line| line.chars().count() <= PDF_LINE_CHARS));
+
+        assert_eq!(DocumentExportFormat::from_string("plain_text").unwrap(), DocumentExportFormat::PlainText);
+        assert!(DocumentExportFormat::from_string("docx").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Field encryption for documents stored in SQLite
+//!
+//! Document titles and content are sealed with XChaCha20-Poly1305 under a key
+//! derived from the configured passphrase with Argon2. Each field gets its own
+//! random nonce, stored next to the ciphertext; rows without a nonce are
+//! legacy plaintext and are read as-is.
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sqlx::{Row, SqlitePool};
+use writemagic_shared::{Result, Timestamp, WritemagicError};
+
+/// Length of the per-database key derivation salt
+const SALT_LEN: usize = 16;
+/// XChaCha20 nonce length
+const NONCE_LEN: usize = 24;
+/// Known text sealed under the key to recognise it on later runs
+const KEY_CHECK_PLAINTEXT: &str = "writemagic-key-check";
+
+/// Encrypts and decrypts individual text fields
+pub struct FieldCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl std::fmt::Debug for FieldCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FieldCipher").finish_non_exhaustive()
+    }
+}
+
+impl FieldCipher {
+    /// Derive the field key from `passphrase` and `salt` with Argon2id
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| WritemagicError::configuration(format!("Failed to derive encryption key: {}", e)))?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        key.fill(0);
+        Ok(Self { cipher })
+    }
+
+    /// Cipher for the database behind `pool`, creating its salt on first use
+    pub async fn for_database(pool: &SqlitePool, passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        // Keeps the salt of an earlier run, or of a concurrent first run
+        sqlx::query("INSERT OR IGNORE INTO encryption_settings (id, kdf_salt, created_at) VALUES (1, ?, ?)")
+            .bind(STANDARD.encode(salt))
+            .bind(Timestamp::now().to_string())
+            .execute(pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to store encryption salt: {}", e)))?;
+
+        let row = sqlx::query("SELECT kdf_salt FROM encryption_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load encryption salt: {}", e)))?;
+        let salt = STANDARD
+            .decode(row.get::<String, _>("kdf_salt"))
+            .map_err(|e| WritemagicError::database(&format!("Corrupt encryption salt: {}", e)))?;
+
+        let cipher = Self::from_passphrase(passphrase, &salt)?;
+        cipher.verify_key(pool).await?;
+        Ok(cipher)
+    }
+
+    /// Check the key against the database's key check value, storing one on first use
+    ///
+    /// A wrong passphrase fails here instead of writing documents nobody can read back.
+    async fn verify_key(&self, pool: &SqlitePool) -> Result<()> {
+        let (key_check, key_check_nonce) = self.encrypt(KEY_CHECK_PLAINTEXT)?;
+        // Keeps the value of an earlier run, or of a concurrent first run
+        sqlx::query("UPDATE encryption_settings SET key_check = ?, key_check_nonce = ? WHERE id = 1 AND key_check IS NULL")
+            .bind(key_check)
+            .bind(key_check_nonce)
+            .execute(pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to store encryption key check: {}", e)))?;
+
+        let row = sqlx::query("SELECT key_check, key_check_nonce FROM encryption_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load encryption key check: {}", e)))?;
+        let verified = self
+            .decrypt(&row.get::<String, _>("key_check"), &row.get::<String, _>("key_check_nonce"))
+            .map_or(false, |plaintext| plaintext == KEY_CHECK_PLAINTEXT);
+        if !verified {
+            return Err(WritemagicError::configuration(
+                "The encryption key does not match the one this database was encrypted with",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encrypt `plaintext`, returning the base64 ciphertext and nonce
+    pub fn encrypt(&self, plaintext: &str) -> Result<(String, String)> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| WritemagicError::internal("Failed to encrypt document field"))?;
+        Ok((STANDARD.encode(ciphertext), STANDARD.encode(nonce)))
+    }
+
+    /// Decrypt a field written by `encrypt`
+    pub fn decrypt(&self, ciphertext: &str, nonce: &str) -> Result<String> {
+        let nonce = STANDARD
+            .decode(nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_LEN)
+            .ok_or_else(|| WritemagicError::database("Corrupt encryption nonce"))?;
+        let ciphertext = STANDARD
+            .decode(ciphertext)
+            .map_err(|e| WritemagicError::database(&format!("Corrupt encrypted field: {}", e)))?;
+
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| WritemagicError::configuration("Failed to decrypt document field, the encryption key may be wrong"))?;
+        String::from_utf8(plaintext).map_err(|_| WritemagicError::database("Decrypted document field is not UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_round_trip_with_fresh_nonces() {
+        let cipher = FieldCipher::from_passphrase("correct horse", b"0123456789abcdef").unwrap();
+
+        let (first, first_nonce) = cipher.encrypt("Chapter one").unwrap();
+        let (second, second_nonce) = cipher.encrypt("Chapter one").unwrap();
+        assert_ne!((&first, &first_nonce), (&second, &second_nonce));
+        assert_eq!(cipher.decrypt(&first, &first_nonce).unwrap(), "Chapter one");
+
+        let wrong_key = FieldCipher::from_passphrase("battery staple", b"0123456789abcdef").unwrap();
+        assert!(wrong_key.decrypt(&first, &first_nonce).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_a_wrong_passphrase_is_refused_by_the_database() {
+        let database = writemagic_shared::DatabaseManager::new_in_memory().await.unwrap();
+
+        FieldCipher::for_database(database.pool(), "correct horse").await.unwrap();
+        FieldCipher::for_database(database.pool(), "correct horse").await.unwrap();
+
+        let error = FieldCipher::for_database(database.pool(), "battery staple").await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Configuration { .. }), "{:?}", error);
+    }
+}
@@ -2,7 +2,9 @@
 
 // Remove unused chrono imports
 use serde::{Deserialize, Serialize};
-use writemagic_shared::{EntityId, Timestamp, ContentHash, FilePath, ContentType, Entity, AggregateRoot, Auditable, Versioned};
+use writemagic_shared::{EntityId, Timestamp, ContentHash, FilePath, ContentType, DocumentTag, Entity, AggregateRoot, Auditable, Versioned, Result, WritemagicError};
+use crate::language::{detect_language, LanguageConfig, WordCountPolicy, DEFAULT_LANGUAGE};
+use crate::reading_time::{estimate_reading_time, ReadingTimeConfig};
 
 /// Document entity representing a single document
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +17,26 @@ pub struct Document {
     pub file_path: Option<FilePath>,
     pub word_count: u32,
     pub character_count: u32,
+    #[serde(default)]
+    pub tags: Vec<DocumentTag>,
+    /// Detected language as an ISO 639 code
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Language chosen by the user, taking precedence over detection
+    #[serde(default)]
+    pub language_override: Option<String>,
+    /// Tenant owning the document, or `None` for single-tenant deployments
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Pinned documents are kept at hand by clients, e.g. listed first
+    #[serde(default)]
+    pub is_pinned: bool,
+    /// AI output is being streamed into the content
+    #[serde(default)]
+    pub is_generating: bool,
+    /// Archived documents are kept but put out of the way, e.g. with their project
+    #[serde(default)]
+    pub is_archived: bool,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
     pub created_by: Option<EntityId>,
@@ -28,7 +50,7 @@ impl Document {
     pub fn new(title: String, content: String, content_type: ContentType, created_by: Option<EntityId>) -> Self {
         let now = Timestamp::now();
         let content_hash = ContentHash::new(&content);
-        let word_count = Self::count_words(&content);
+        let word_count = WordCountPolicy::for_language(DEFAULT_LANGUAGE).count(&content);
         let character_count = content.len() as u32;
 
         Self {
@@ -40,6 +62,13 @@ impl Document {
             file_path: None,
             word_count,
             character_count,
+            tags: Vec::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            language_override: None,
+            tenant_id: None,
+            is_pinned: false,
+            is_generating: false,
+            is_archived: false,
             created_at: now.clone(),
             updated_at: now,
             created_by,
@@ -54,7 +83,7 @@ impl Document {
         if self.content != content {
             // Calculate metrics before moving content
             let content_hash = ContentHash::new(&content);
-            let word_count = Self::count_words(&content);
+            let word_count = self.word_count_policy().count(&content);
             let character_count = content.len() as u32;
             
             // Move content to avoid clone
@@ -68,6 +97,44 @@ impl Document {
         }
     }
 
+    /// Replace `old_len` bytes at `start` with `new_text`
+    ///
+    /// Word and character counts are adjusted from the edited range instead of
+    /// rescanning the content. Returns whether the content changed.
+    pub fn apply_content_delta(&mut self, start: usize, old_len: usize, new_text: &str, updated_by: Option<EntityId>) -> Result<bool> {
+        let end = start
+            .checked_add(old_len)
+            .filter(|&end| end <= self.content.len())
+            .ok_or_else(|| WritemagicError::validation("Edit range is out of bounds"))?;
+        if !self.content.is_char_boundary(start) || !self.content.is_char_boundary(end) {
+            return Err(WritemagicError::validation("Edit range must fall on character boundaries"));
+        }
+        if &self.content[start..end] == new_text {
+            return Ok(false);
+        }
+
+        self.word_count = self.word_count_policy().count_after_edit(&self.content, self.word_count, start, end, new_text);
+        self.content.replace_range(start..end, new_text);
+        self.character_count = self.content.len() as u32;
+        self.content_hash = ContentHash::new(&self.content);
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+        Ok(true)
+    }
+
+    /// Replace the content with `content` in another format, as a single new version
+    pub fn convert_content(&mut self, content: String, content_type: ContentType, updated_by: Option<EntityId>) {
+        self.content_hash = ContentHash::new(&content);
+        self.word_count = self.word_count_policy().count(&content);
+        self.character_count = content.len() as u32;
+        self.content = content;
+        self.content_type = content_type;
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+    }
+
     pub fn update_title(&mut self, title: String, updated_by: Option<EntityId>) {
         if self.title != title {
             self.title = title;
@@ -84,6 +151,57 @@ impl Document {
         self.increment_version();
     }
 
+    /// Add tags that are not already present, returning true if any were added
+    pub fn add_tags(&mut self, tags: Vec<DocumentTag>, updated_by: Option<EntityId>) -> bool {
+        let before = self.tags.len();
+        for tag in tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+
+        let changed = self.tags.len() != before;
+        if changed {
+            self.updated_at = Timestamp::now();
+            self.updated_by = updated_by;
+            self.increment_version();
+        }
+        changed
+    }
+
+    /// Replace all tags, dropping duplicates, returning true if they changed
+    pub fn set_tags(&mut self, tags: Vec<DocumentTag>, updated_by: Option<EntityId>) -> bool {
+        let mut deduplicated: Vec<DocumentTag> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            if !deduplicated.contains(&tag) {
+                deduplicated.push(tag);
+            }
+        }
+        if deduplicated == self.tags {
+            return false;
+        }
+
+        self.tags = deduplicated;
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+        true
+    }
+
+    /// Remove a tag, returning true if the document had it
+    pub fn remove_tag(&mut self, tag: &DocumentTag, updated_by: Option<EntityId>) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+
+        let changed = self.tags.len() != before;
+        if changed {
+            self.updated_at = Timestamp::now();
+            self.updated_by = updated_by;
+            self.increment_version();
+        }
+        changed
+    }
+
     pub fn mark_deleted(&mut self, deleted_by: Option<EntityId>) {
         if !self.is_deleted {
             self.is_deleted = true;
@@ -93,6 +211,57 @@ impl Document {
         }
     }
 
+    /// Pin or unpin the document, returning true if it changed
+    pub fn set_pinned(&mut self, pinned: bool, updated_by: Option<EntityId>) -> bool {
+        if self.is_pinned == pinned {
+            return false;
+        }
+
+        self.is_pinned = pinned;
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+        true
+    }
+
+    /// Mark whether AI output is being streamed in, returning true if it changed
+    ///
+    /// The flag describes transient state, so it does not bump the version.
+    pub fn set_generating(&mut self, generating: bool) -> bool {
+        if self.is_generating == generating {
+            return false;
+        }
+
+        self.is_generating = generating;
+        true
+    }
+
+    /// Archive the document, returning true if it changed
+    pub fn archive(&mut self, archived_by: Option<EntityId>) -> bool {
+        if self.is_archived {
+            return false;
+        }
+
+        self.is_archived = true;
+        self.updated_at = Timestamp::now();
+        self.updated_by = archived_by;
+        self.increment_version();
+        true
+    }
+
+    /// Take the document out of the archive, returning true if it changed
+    pub fn unarchive(&mut self, unarchived_by: Option<EntityId>) -> bool {
+        if !self.is_archived {
+            return false;
+        }
+
+        self.is_archived = false;
+        self.updated_at = Timestamp::now();
+        self.updated_by = unarchived_by;
+        self.increment_version();
+        true
+    }
+
     pub fn restore(&mut self, restored_by: Option<EntityId>) {
         if self.is_deleted {
             self.is_deleted = false;
@@ -103,14 +272,73 @@ impl Document {
         }
     }
 
-    fn count_words(content: &str) -> u32 {
-        content
-            .split_whitespace()
-            .filter(|word| !word.is_empty())
-            .count() as u32
+    /// Language used for counting and display, preferring a manual override
+    pub fn effective_language(&self) -> &str {
+        self.language_override.as_deref().unwrap_or(&self.language)
+    }
+
+    /// Re-detect the language from the content, recounting words if it changed
+    ///
+    /// Detection is derived from the content, so it does not bump the version.
+    pub fn detect_language(&mut self, config: &LanguageConfig) -> bool {
+        let language = detect_language(&self.content, config);
+        if self.language == language {
+            return false;
+        }
+
+        self.language = language;
+        self.word_count = self.word_count_policy().count(&self.content);
+        true
+    }
+
+    /// Set or clear the manual language override, returning true if it changed
+    pub fn set_language_override(&mut self, language: Option<String>, updated_by: Option<EntityId>) -> bool {
+        if self.language_override == language {
+            return false;
+        }
+
+        self.language_override = language;
+        self.word_count = self.word_count_policy().count(&self.content);
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+        true
+    }
+
+    /// Estimated time to read the document in seconds
+    pub fn reading_time(&self, config: &ReadingTimeConfig) -> u32 {
+        estimate_reading_time(&self.content, &self.content_type, self.effective_language(), config)
+    }
+
+    /// Whether the stored word and character counts no longer match the content
+    pub fn has_stale_statistics(&self) -> bool {
+        self.word_count != self.word_count_policy().count(&self.content)
+            || self.character_count != self.content.len() as u32
+    }
+
+    /// Recompute word and character counts from the content
+    pub fn recalculate_statistics(&mut self, updated_by: Option<EntityId>) -> bool {
+        if !self.has_stale_statistics() {
+            return false;
+        }
+
+        self.word_count = self.word_count_policy().count(&self.content);
+        self.character_count = self.content.len() as u32;
+        self.updated_at = Timestamp::now();
+        self.updated_by = updated_by;
+        self.increment_version();
+        true
+    }
+
+    fn word_count_policy(&self) -> WordCountPolicy {
+        WordCountPolicy::for_language(self.effective_language())
     }
 }
 
+fn default_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
+}
+
 impl Entity for Document {
     type Id = EntityId;
 
@@ -169,7 +397,13 @@ pub struct Project {
     pub id: EntityId,
     pub name: String,
     pub description: Option<String>,
+    /// Instructions sent ahead of every AI request made for the project
+    #[serde(default)]
+    pub system_prompt: Option<String>,
     pub document_ids: Vec<EntityId>,
+    /// Tenant owning the project, or `None` for single-tenant deployments
+    #[serde(default)]
+    pub tenant_id: Option<String>,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
     pub created_by: Option<EntityId>,
@@ -177,6 +411,10 @@ pub struct Project {
     pub version: u64,
     pub is_deleted: bool,
     pub deleted_at: Option<Timestamp>,
+    #[serde(default)]
+    pub is_archived: bool,
+    #[serde(default)]
+    pub archived_at: Option<Timestamp>,
 }
 
 impl Project {
@@ -187,7 +425,9 @@ impl Project {
             id: EntityId::new(),
             name,
             description,
+            system_prompt: None,
             document_ids: Vec::new(),
+            tenant_id: None,
             created_at: now.clone(),
             updated_at: now,
             created_by,
@@ -195,6 +435,8 @@ impl Project {
             version: 1,
             is_deleted: false,
             deleted_at: None,
+            is_archived: false,
+            archived_at: None,
         }
     }
 
@@ -216,6 +458,32 @@ impl Project {
         }
     }
 
+    /// Add the documents not already in the project, bumping the version once
+    pub fn add_documents(&mut self, document_ids: &[EntityId], updated_by: Option<EntityId>) {
+        let before = self.document_ids.len();
+        for document_id in document_ids {
+            if !self.document_ids.contains(document_id) {
+                self.document_ids.push(*document_id);
+            }
+        }
+        if self.document_ids.len() != before {
+            self.updated_at = Timestamp::now();
+            self.updated_by = updated_by;
+            self.increment_version();
+        }
+    }
+
+    /// Remove the given documents, bumping the version once
+    pub fn remove_documents(&mut self, document_ids: &[EntityId], updated_by: Option<EntityId>) {
+        let before = self.document_ids.len();
+        self.document_ids.retain(|id| !document_ids.contains(id));
+        if self.document_ids.len() != before {
+            self.updated_at = Timestamp::now();
+            self.updated_by = updated_by;
+            self.increment_version();
+        }
+    }
+
     pub fn update_name(&mut self, name: String, updated_by: Option<EntityId>) {
         if self.name != name {
             self.name = name;
@@ -225,6 +493,35 @@ impl Project {
         }
     }
 
+    /// Archive the project, returning true if it changed
+    pub fn archive(&mut self, archived_by: Option<EntityId>) -> bool {
+        if self.is_archived {
+            return false;
+        }
+
+        let now = Timestamp::now();
+        self.is_archived = true;
+        self.archived_at = Some(now.clone());
+        self.updated_at = now;
+        self.updated_by = archived_by;
+        self.increment_version();
+        true
+    }
+
+    /// Take the project out of the archive, returning true if it changed
+    pub fn unarchive(&mut self, unarchived_by: Option<EntityId>) -> bool {
+        if !self.is_archived {
+            return false;
+        }
+
+        self.is_archived = false;
+        self.archived_at = None;
+        self.updated_at = Timestamp::now();
+        self.updated_by = unarchived_by;
+        self.increment_version();
+        true
+    }
+
     pub fn update_description(&mut self, description: Option<String>, updated_by: Option<EntityId>) {
         if self.description != description {
             self.description = description;
@@ -233,6 +530,15 @@ impl Project {
             self.increment_version();
         }
     }
+
+    pub fn update_system_prompt(&mut self, system_prompt: Option<String>, updated_by: Option<EntityId>) {
+        if self.system_prompt != system_prompt {
+            self.system_prompt = system_prompt;
+            self.updated_at = Timestamp::now();
+            self.updated_by = updated_by;
+            self.increment_version();
+        }
+    }
 }
 
 impl Entity for Project {
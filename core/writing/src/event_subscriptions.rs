@@ -0,0 +1,133 @@
+//! Forwarding of writing domain events to app listeners, shared by the FFI bindings
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use writemagic_shared::{event_json, EventDispatcher, InMemoryEventBus, SubscriptionId};
+
+use crate::events::{DocumentEvent, ProjectEvent};
+
+/// Event subscriptions from an app, by the handle it was given
+pub struct EventSubscriptions {
+    event_bus: Arc<InMemoryEventBus>,
+    subscriptions: Mutex<HashMap<u64, (Vec<SubscriptionId>, Arc<EventDispatcher>)>>,
+    next_handle: AtomicU64,
+}
+
+impl EventSubscriptions {
+    pub fn new(event_bus: Arc<InMemoryEventBus>) -> Self {
+        Self {
+            event_bus,
+            subscriptions: Mutex::new(HashMap::new()),
+            next_handle: AtomicU64::new(1),
+        }
+    }
+
+    /// Send document and project events to `dispatcher` until unsubscribed, returning the handle
+    pub async fn subscribe(&self, dispatcher: EventDispatcher) -> u64 {
+        let dispatcher = Arc::new(dispatcher);
+        let document_dispatcher = dispatcher.clone();
+        let document_subscription = self.event_bus
+            .add_typed_subscription(move |event: &DocumentEvent| {
+                document_dispatcher.send(event_json(event)?);
+                Ok(())
+            })
+            .await;
+        let project_dispatcher = dispatcher.clone();
+        let project_subscription = self.event_bus
+            .add_typed_subscription(move |event: &ProjectEvent| {
+                project_dispatcher.send(event_json(event)?);
+                Ok(())
+            })
+            .await;
+
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed);
+        self.subscriptions.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(handle, (vec![document_subscription, project_subscription], dispatcher));
+        handle
+    }
+
+    /// End a subscription, returning whether it was active
+    ///
+    /// Once this returns its dispatcher delivers nothing more.
+    pub async fn unsubscribe(&self, handle: u64) -> bool {
+        let removed = self.subscriptions.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&handle);
+        let Some((subscriptions, dispatcher)) = removed else {
+            return false;
+        };
+        for subscription in subscriptions {
+            self.event_bus.remove_subscription(subscription).await;
+        }
+        dispatcher.stop();
+        true
+    }
+
+    /// End every subscription, as on shutdown
+    pub async fn unsubscribe_all(&self) {
+        let handles: Vec<u64> = self.subscriptions.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .copied()
+            .collect();
+        for handle in handles {
+            self.unsubscribe(handle).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use writemagic_shared::{EntityId, EventBus, Timestamp};
+
+    #[tokio::test]
+    async fn test_forwards_document_and_project_events_until_unsubscribed() {
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let subscriptions = EventSubscriptions::new(event_bus.clone());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let dispatcher = EventDispatcher::spawn("test-events", move || {
+            move |json: &str| sender.send(json.to_string()).unwrap()
+        })
+        .unwrap();
+        let handle = subscriptions.subscribe(dispatcher).await;
+
+        let project_id = EntityId::new();
+        event_bus
+            .publish(Box::new(ProjectEvent::ProjectNameUpdated {
+                project_id,
+                old_name: "Drafts".to_string(),
+                new_name: "Book".to_string(),
+                updated_by: None,
+                updated_at: Timestamp::now(),
+            }))
+            .await
+            .unwrap();
+        event_bus
+            .publish(Box::new(DocumentEvent::DocumentDeleted {
+                document_id: EntityId::new(),
+                deleted_by: None,
+                deleted_at: Timestamp::now(),
+            }))
+            .await
+            .unwrap();
+
+        let timeout = std::time::Duration::from_secs(2);
+        assert!(receiver.recv_timeout(timeout).unwrap().contains("\"ProjectNameUpdated\""));
+        assert!(receiver.recv_timeout(timeout).unwrap().contains("\"DocumentDeleted\""));
+
+        assert!(subscriptions.unsubscribe(handle).await);
+        assert!(!subscriptions.unsubscribe(handle).await);
+        event_bus
+            .publish(Box::new(DocumentEvent::DocumentDeleted {
+                document_id: EntityId::new(),
+                deleted_by: None,
+                deleted_at: Timestamp::now(),
+            }))
+            .await
+            .unwrap();
+        assert!(receiver.recv_timeout(std::time::Duration::from_millis(100)).is_err());
+    }
+}
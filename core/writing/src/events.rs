@@ -11,6 +11,8 @@ pub enum DocumentEvent {
     DocumentCreated {
         document_id: EntityId,
         title: String,
+        #[serde(default)]
+        content: String,
         content_type: writemagic_shared::ContentType,
         created_by: Option<EntityId>,
         created_at: Timestamp,
@@ -47,6 +49,45 @@ pub enum DocumentEvent {
         restored_by: Option<EntityId>,
         restored_at: Timestamp,
     },
+    DocumentTagsUpdated {
+        document_id: EntityId,
+        old_tags: Vec<String>,
+        new_tags: Vec<String>,
+        updated_by: Option<EntityId>,
+        updated_at: Timestamp,
+    },
+    DocumentLanguageOverridden {
+        document_id: EntityId,
+        language: Option<String>,
+        updated_by: Option<EntityId>,
+        updated_at: Timestamp,
+    },
+    DocumentFormatConverted {
+        document_id: EntityId,
+        old_content_type: writemagic_shared::ContentType,
+        new_content_type: writemagic_shared::ContentType,
+        #[serde(default)]
+        new_content: String,
+        updated_by: Option<EntityId>,
+        updated_at: Timestamp,
+    },
+    DocumentArchived {
+        document_id: EntityId,
+        archived_by: Option<EntityId>,
+        archived_at: Timestamp,
+    },
+    /// A small edit replaced `removed_len` bytes at `start` with `inserted`,
+    /// leaving the document with the given counts
+    DocumentStatsChanged {
+        document_id: EntityId,
+        start: usize,
+        removed_len: usize,
+        inserted: String,
+        word_count: u32,
+        character_count: u32,
+        updated_by: Option<EntityId>,
+        updated_at: Timestamp,
+    },
 }
 
 impl DomainEvent for DocumentEvent {
@@ -63,6 +104,11 @@ impl DomainEvent for DocumentEvent {
             DocumentEvent::DocumentFilePathSet { updated_at, .. } => updated_at.as_datetime(),
             DocumentEvent::DocumentDeleted { deleted_at, .. } => deleted_at.as_datetime(),
             DocumentEvent::DocumentRestored { restored_at, .. } => restored_at.as_datetime(),
+            DocumentEvent::DocumentTagsUpdated { updated_at, .. } => updated_at.as_datetime(),
+            DocumentEvent::DocumentLanguageOverridden { updated_at, .. } => updated_at.as_datetime(),
+            DocumentEvent::DocumentFormatConverted { updated_at, .. } => updated_at.as_datetime(),
+            DocumentEvent::DocumentArchived { archived_at, .. } => archived_at.as_datetime(),
+            DocumentEvent::DocumentStatsChanged { updated_at, .. } => updated_at.as_datetime(),
         }
     }
 
@@ -74,6 +120,11 @@ impl DomainEvent for DocumentEvent {
             DocumentEvent::DocumentFilePathSet { .. } => "DocumentFilePathSet",
             DocumentEvent::DocumentDeleted { .. } => "DocumentDeleted",
             DocumentEvent::DocumentRestored { .. } => "DocumentRestored",
+            DocumentEvent::DocumentTagsUpdated { .. } => "DocumentTagsUpdated",
+            DocumentEvent::DocumentLanguageOverridden { .. } => "DocumentLanguageOverridden",
+            DocumentEvent::DocumentFormatConverted { .. } => "DocumentFormatConverted",
+            DocumentEvent::DocumentArchived { .. } => "DocumentArchived",
+            DocumentEvent::DocumentStatsChanged { .. } => "DocumentStatsChanged",
         }
     }
 
@@ -85,6 +136,11 @@ impl DomainEvent for DocumentEvent {
             DocumentEvent::DocumentFilePathSet { document_id, .. } => *document_id,
             DocumentEvent::DocumentDeleted { document_id, .. } => *document_id,
             DocumentEvent::DocumentRestored { document_id, .. } => *document_id,
+            DocumentEvent::DocumentTagsUpdated { document_id, .. } => *document_id,
+            DocumentEvent::DocumentLanguageOverridden { document_id, .. } => *document_id,
+            DocumentEvent::DocumentFormatConverted { document_id, .. } => *document_id,
+            DocumentEvent::DocumentArchived { document_id, .. } => *document_id,
+            DocumentEvent::DocumentStatsChanged { document_id, .. } => *document_id,
         }
     }
 
@@ -129,6 +185,12 @@ pub enum ProjectEvent {
         updated_by: Option<EntityId>,
         updated_at: Timestamp,
     },
+    ProjectSystemPromptUpdated {
+        project_id: EntityId,
+        system_prompt: Option<String>,
+        updated_by: Option<EntityId>,
+        updated_at: Timestamp,
+    },
     DocumentAdded {
         project_id: EntityId,
         document_id: EntityId,
@@ -143,6 +205,27 @@ pub enum ProjectEvent {
         removed_by: Option<EntityId>,
         removed_at: Timestamp,
     },
+    ProjectArchived {
+        project_id: EntityId,
+        /// Whether the project's documents were archived with it
+        cascade: bool,
+        archived_by: Option<EntityId>,
+        archived_at: Timestamp,
+    },
+    ProjectUnarchived {
+        project_id: EntityId,
+        unarchived_by: Option<EntityId>,
+        unarchived_at: Timestamp,
+    },
+    /// A batch of documents moved into `project_id`
+    DocumentsMoved {
+        project_id: EntityId,
+        /// Projects the documents were taken out of
+        from_project_ids: Vec<EntityId>,
+        document_ids: Vec<EntityId>,
+        moved_by: Option<EntityId>,
+        moved_at: Timestamp,
+    },
 }
 
 impl DomainEvent for ProjectEvent {
@@ -156,8 +239,12 @@ impl DomainEvent for ProjectEvent {
             ProjectEvent::ProjectCreated { created_at, .. } => created_at.as_datetime(),
             ProjectEvent::ProjectNameUpdated { updated_at, .. } => updated_at.as_datetime(),
             ProjectEvent::ProjectDescriptionUpdated { updated_at, .. } => updated_at.as_datetime(),
+            ProjectEvent::ProjectSystemPromptUpdated { updated_at, .. } => updated_at.as_datetime(),
             ProjectEvent::DocumentAdded { added_at, .. } => added_at.as_datetime(),
             ProjectEvent::DocumentRemoved { removed_at, .. } => removed_at.as_datetime(),
+            ProjectEvent::ProjectArchived { archived_at, .. } => archived_at.as_datetime(),
+            ProjectEvent::ProjectUnarchived { unarchived_at, .. } => unarchived_at.as_datetime(),
+            ProjectEvent::DocumentsMoved { moved_at, .. } => moved_at.as_datetime(),
         }
     }
 
@@ -166,8 +253,12 @@ impl DomainEvent for ProjectEvent {
             ProjectEvent::ProjectCreated { .. } => "ProjectCreated",
             ProjectEvent::ProjectNameUpdated { .. } => "ProjectNameUpdated",
             ProjectEvent::ProjectDescriptionUpdated { .. } => "ProjectDescriptionUpdated",
+            ProjectEvent::ProjectSystemPromptUpdated { .. } => "ProjectSystemPromptUpdated",
             ProjectEvent::DocumentAdded { .. } => "DocumentAdded",
             ProjectEvent::DocumentRemoved { .. } => "DocumentRemoved",
+            ProjectEvent::ProjectArchived { .. } => "ProjectArchived",
+            ProjectEvent::ProjectUnarchived { .. } => "ProjectUnarchived",
+            ProjectEvent::DocumentsMoved { .. } => "DocumentsMoved",
         }
     }
 
@@ -176,8 +267,12 @@ impl DomainEvent for ProjectEvent {
             ProjectEvent::ProjectCreated { project_id, .. } => *project_id,
             ProjectEvent::ProjectNameUpdated { project_id, .. } => *project_id,
             ProjectEvent::ProjectDescriptionUpdated { project_id, .. } => *project_id,
+            ProjectEvent::ProjectSystemPromptUpdated { project_id, .. } => *project_id,
             ProjectEvent::DocumentAdded { project_id, .. } => *project_id,
             ProjectEvent::DocumentRemoved { project_id, .. } => *project_id,
+            ProjectEvent::ProjectArchived { project_id, .. } => *project_id,
+            ProjectEvent::ProjectUnarchived { project_id, .. } => *project_id,
+            ProjectEvent::DocumentsMoved { project_id, .. } => *project_id,
         }
     }
 
@@ -0,0 +1,274 @@
+//! Export of audit and AI usage records for compliance and billing
+//!
+//! Exports carry metadata only. Prompts, completions and document content are
+//! never written, and neither are the free-text details of audit events,
+//! which may quote the content that triggered them.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::SystemTime;
+use writemagic_shared::export::write_csv_row;
+use writemagic_shared::Result;
+
+pub use writemagic_shared::export::ExportFormat;
+
+/// Time window `[start, end)` of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl ExportRange {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        at >= self.start && at < self.end
+    }
+
+    pub(crate) fn system_times(&self) -> (SystemTime, SystemTime) {
+        (self.start.into(), self.end.into())
+    }
+}
+
+/// A record that can be written as a CSV row or a JSON object
+pub trait ExportRecord: Serialize {
+    /// Column names, in the order of `csv_fields`
+    const CSV_HEADER: &'static [&'static str];
+
+    fn csv_fields(&self) -> Vec<String>;
+}
+
+/// Metadata of one security audit event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditExportRecord {
+    pub timestamp: DateTime<Utc>,
+    pub event_type: String,
+    pub severity: String,
+}
+
+impl ExportRecord for AuditExportRecord {
+    const CSV_HEADER: &'static [&'static str] = &["timestamp", "event_type", "severity"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.timestamp.to_rfc3339(),
+            self.event_type.clone(),
+            self.severity.clone(),
+        ]
+    }
+}
+
+/// Usage of one AI request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageExportRecord {
+    pub started_at: DateTime<Utc>,
+    pub request_id: String,
+    pub provider: String,
+    pub model: String,
+    pub priority: String,
+    pub success: bool,
+    pub cache_hit: bool,
+    pub error_type: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    pub cost: f64,
+}
+
+impl ExportRecord for UsageExportRecord {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "started_at",
+        "request_id",
+        "provider",
+        "model",
+        "priority",
+        "success",
+        "cache_hit",
+        "error_type",
+        "duration_ms",
+        "input_tokens",
+        "output_tokens",
+        "total_tokens",
+        "cost",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.started_at.to_rfc3339(),
+            self.request_id.clone(),
+            self.provider.clone(),
+            self.model.clone(),
+            self.priority.clone(),
+            self.success.to_string(),
+            self.cache_hit.to_string(),
+            self.error_type.clone().unwrap_or_default(),
+            self.duration_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            self.input_tokens.to_string(),
+            self.output_tokens.to_string(),
+            self.total_tokens.to_string(),
+            self.cost.to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "ai")]
+impl From<&writemagic_ai::security::SecurityEvent> for AuditExportRecord {
+    fn from(event: &writemagic_ai::security::SecurityEvent) -> Self {
+        use writemagic_ai::security::{PIISeverity, SecurityEventType};
+
+        let event_type = match event.event_type {
+            SecurityEventType::PIIDetected => "pii_detected",
+            SecurityEventType::KeyRotationNeeded => "key_rotation_needed",
+            SecurityEventType::KeyRotated => "key_rotated",
+            SecurityEventType::SecurityViolation => "security_violation",
+            SecurityEventType::SuspiciousActivity => "suspicious_activity",
+            SecurityEventType::CircuitBreakerTripped => "circuit_breaker_tripped",
+        };
+        let severity = match event.severity {
+            PIISeverity::Critical => "critical",
+            PIISeverity::High => "high",
+            PIISeverity::Medium => "medium",
+            PIISeverity::Low => "low",
+        };
+
+        Self {
+            timestamp: event.timestamp.into(),
+            event_type: event_type.to_string(),
+            severity: severity.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "ai")]
+impl From<&writemagic_ai::performance_monitor::AIPerformanceMetrics> for UsageExportRecord {
+    fn from(metric: &writemagic_ai::performance_monitor::AIPerformanceMetrics) -> Self {
+        use writemagic_ai::RequestPriority;
+
+        let priority = match metric.priority {
+            RequestPriority::Low => "low",
+            RequestPriority::Normal => "normal",
+            RequestPriority::High => "high",
+            RequestPriority::Critical => "critical",
+        };
+
+        Self {
+            started_at: metric.recorded_at.into(),
+            request_id: metric.request_id.clone(),
+            provider: metric.provider_name.clone(),
+            model: metric.model_name.clone(),
+            priority: priority.to_string(),
+            success: metric.success,
+            cache_hit: metric.cache_hit,
+            error_type: metric.error_type.clone(),
+            duration_ms: metric.duration.map(|duration| duration.as_millis() as u64),
+            input_tokens: metric.input_tokens,
+            output_tokens: metric.output_tokens,
+            total_tokens: metric.total_tokens,
+            cost: metric.cost,
+        }
+    }
+}
+
+/// Write `records` to `writer` one at a time, so large exports are never held
+/// in memory as a whole
+///
+/// CSV output always starts with the header row; JSON output is an array
+/// and JSON Lines output one object per line.
+pub fn write_export<R, W>(records: impl IntoIterator<Item = R>, format: ExportFormat, mut writer: W) -> Result<()>
+where
+    R: ExportRecord,
+    W: Write,
+{
+    match format {
+        ExportFormat::Csv => {
+            write_csv_row(&mut writer, R::CSV_HEADER.iter().copied())?;
+            for record in records {
+                write_csv_row(&mut writer, record.csv_fields().iter().map(String::as_str))?;
+            }
+        }
+        ExportFormat::Json => {
+            writer.write_all(b"[")?;
+            for (index, record) in records.into_iter().enumerate() {
+                if index > 0 {
+                    writer.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut writer, &record)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        ExportFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn audit_records() -> Vec<AuditExportRecord> {
+        (0..3)
+            .map(|minute| AuditExportRecord {
+                timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 12, minute, 0).unwrap(),
+                event_type: "pii_detected".to_string(),
+                severity: "high".to_string(),
+            })
+            .collect()
+    }
+
+    fn export(records: &[AuditExportRecord], format: ExportFormat) -> String {
+        let mut buffer = Vec::new();
+        write_export(records.iter().cloned(), format, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_csv_has_stable_header_and_one_row_per_record_in_range() {
+        let range = ExportRange::new(
+            Utc.with_ymd_and_hms(2025, 3, 1, 12, 1, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 3, 1, 13, 0, 0).unwrap(),
+        );
+        let in_range: Vec<_> = audit_records().into_iter().filter(|record| range.contains(record.timestamp)).collect();
+
+        let csv = export(&in_range, ExportFormat::Csv);
+        let lines: Vec<_> = csv.split_terminator("\r\n").collect();
+
+        assert_eq!(lines[0], "timestamp,event_type,severity");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "2025-03-01T12:01:00+00:00,pii_detected,high");
+    }
+
+    #[test]
+    fn test_json_parses_back_to_records() {
+        let records = audit_records();
+        let parsed: Vec<AuditExportRecord> = serde_json::from_str(&export(&records, ExportFormat::Json)).unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn test_empty_export_is_header_or_empty_array() {
+        assert_eq!(export(&[], ExportFormat::Csv), "timestamp,event_type,severity\r\n");
+        assert_eq!(export(&[], ExportFormat::Json), "[]");
+    }
+
+    #[test]
+    fn test_jsonl_writes_one_record_per_line() {
+        let records = audit_records();
+        let jsonl = export(&records, ExportFormat::Jsonl);
+        let parsed: Vec<AuditExportRecord> = jsonl.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(parsed, records);
+        assert_eq!(export(&[], ExportFormat::Jsonl), "");
+    }
+}
@@ -0,0 +1,156 @@
+//! Fuzzy matching of short text such as document titles, for quick-open
+
+/// Score of every query character matched
+const MATCH_SCORE: u32 = 1;
+/// Extra score for a match at the start of a word
+const WORD_START_BONUS: u32 = 2;
+/// Extra score for a match directly after the previous one
+const CONSECUTIVE_BONUS: u32 = 2;
+
+/// How well a query matched a piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// In `(0, 1]`, higher is better
+    pub score: f32,
+    /// Character indices of the matched characters, for highlighting
+    pub positions: Vec<usize>,
+}
+
+/// Match the non-whitespace characters of `query`, in order and ignoring
+/// case, against `text`
+///
+/// Matches at word starts and runs of adjacent characters score higher, and
+/// shorter texts beat longer ones with the same matches. Returns `None` when
+/// `query` is not a subsequence of `text` or has nothing to match.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).map(fold_case).collect();
+    let chars: Vec<char> = text.chars().collect();
+    if query.is_empty() || query.len() > chars.len() {
+        return None;
+    }
+    let folded: Vec<char> = chars.iter().copied().map(fold_case).collect();
+
+    // best[i][j]: best score of matching query[..=i] with query[i] at text[j]
+    let mut best: Vec<Vec<Option<u32>>> = vec![vec![None; chars.len()]; query.len()];
+    let mut previous: Vec<Vec<usize>> = vec![vec![0; chars.len()]; query.len()];
+    for (i, &wanted) in query.iter().enumerate() {
+        // Best score[i - 1][k] over k < j - 1, which can't run into position j
+        let mut gapped: Option<(u32, usize)> = None;
+        for j in 0..chars.len() {
+            if i > 0 && j >= 2 {
+                if let Some(score) = best[i - 1][j - 2] {
+                    if gapped.map_or(true, |(best_score, _)| score >= best_score) {
+                        gapped = Some((score, j - 2));
+                    }
+                }
+            }
+            if folded[j] != wanted {
+                continue;
+            }
+
+            let base = MATCH_SCORE + if is_word_start(&chars, j) { WORD_START_BONUS } else { 0 };
+            if i == 0 {
+                best[i][j] = Some(base);
+                continue;
+            }
+            let adjacent = (j >= 1)
+                .then(|| best[i - 1][j - 1])
+                .flatten()
+                .map(|score| (score + CONSECUTIVE_BONUS, j - 1));
+            let chosen = match (adjacent, gapped) {
+                (Some(a), Some(g)) => Some(if a.0 >= g.0 { a } else { g }),
+                (a, g) => a.or(g),
+            };
+            if let Some((score, from)) = chosen {
+                best[i][j] = Some(score + base);
+                previous[i][j] = from;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (raw, end) = (0..chars.len())
+        .filter_map(|j| best[last][j].map(|score| (score, j)))
+        .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))?;
+
+    let mut positions = vec![end; query.len()];
+    for i in (1..query.len()).rev() {
+        positions[i - 1] = previous[i][positions[i]];
+    }
+
+    let ideal = (query.len() as u32 * (MATCH_SCORE + WORD_START_BONUS)) as f32;
+    let quality = (raw as f32 / ideal).min(1.0);
+    let coverage = query.len() as f32 / chars.len() as f32;
+    Some(FuzzyMatch {
+        score: quality * (0.8 + 0.2 * coverage),
+        positions,
+    })
+}
+
+/// Keep the `limit` candidates whose text best matches `query`, best first, with their scores
+///
+/// Candidates that score the same keep the order they were given in.
+pub fn rank_fuzzy<T>(
+    query: &str,
+    candidates: impl IntoIterator<Item = T>,
+    text: impl Fn(&T) -> &str,
+    limit: u32,
+) -> Vec<(T, f32)> {
+    let mut ranked: Vec<(T, f32)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let score = fuzzy_match(query, text(&candidate))?.score;
+            Some((candidate, score))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(limit as usize);
+    ranked
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    match index.checked_sub(1).map(|before| chars[before]) {
+        None => true,
+        Some(before) => !before.is_alphanumeric() || (before.is_lowercase() && chars[index].is_uppercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_matches_rank_word_starts_and_shorter_titles_first() {
+        let titles = [
+            "Improper object planner",
+            "Project planning notes",
+            "Plan for the project",
+            "Project plan",
+        ];
+
+        let ranked: Vec<&str> = rank_fuzzy("proj plan", titles, |title| *title, 10)
+            .into_iter()
+            .map(|(title, _)| title)
+            .collect();
+        assert_eq!(ranked, vec!["Project plan", "Project planning notes", "Improper object planner"]);
+
+        let top_two = rank_fuzzy("proj plan", titles, |title| *title, 2);
+        assert_eq!(top_two.len(), 2);
+        assert!(top_two[0].1 > top_two[1].1);
+    }
+
+    #[test]
+    fn test_match_reports_positions_for_highlighting() {
+        let matched = fuzzy_match("wm", "Writing with WriteMagic").unwrap();
+        assert_eq!(matched.positions, vec![13, 18]);
+        assert!(matched.score > 0.0 && matched.score <= 1.0);
+
+        assert_eq!(fuzzy_match("PLAN", "plan").unwrap().score, 1.0);
+        assert_eq!(fuzzy_match("xyz", "Project plan"), None);
+        assert_eq!(fuzzy_match("  ", "Project plan"), None);
+    }
+}
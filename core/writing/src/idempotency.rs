@@ -0,0 +1,88 @@
+//! Idempotency keys for document creation
+//!
+//! A client that retries a create with the same key gets back the document
+//! the first attempt created instead of a duplicate, for as long as the key
+//! lives.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use writemagic_shared::{Result, WritemagicError};
+
+/// Longest accepted idempotency key, in bytes
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Idempotency key configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    /// How long a key keeps returning the document it created
+    pub ttl_seconds: u64,
+    /// How often expired keys are purged
+    pub cleanup_interval_seconds: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: 24 * 60 * 60,
+            cleanup_interval_seconds: 60 * 60,
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    pub fn ttl(&self) -> chrono::Duration {
+        i64::try_from(self.ttl_seconds)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .unwrap_or(chrono::Duration::MAX)
+    }
+
+    pub fn cleanup_interval(&self) -> Duration {
+        Duration::from_secs(self.cleanup_interval_seconds.max(1))
+    }
+}
+
+/// Check that `key` is non-blank, printable and at most `MAX_IDEMPOTENCY_KEY_LEN` bytes
+pub fn validate_idempotency_key(key: &str) -> Result<()> {
+    if key.trim().is_empty() {
+        return Err(WritemagicError::validation("Idempotency key cannot be empty"));
+    }
+    if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(WritemagicError::validation(format!(
+            "Idempotency key cannot be longer than {} bytes",
+            MAX_IDEMPOTENCY_KEY_LEN
+        )));
+    }
+    if key.chars().any(char::is_control) {
+        return Err(WritemagicError::validation("Idempotency key cannot contain control characters"));
+    }
+    Ok(())
+}
+
+/// Background task purging expired idempotency keys every `cleanup_interval`
+///
+/// Runs until `shutdown` completes.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn idempotency_cleanup_task(
+    documents: std::sync::Arc<crate::services::DocumentManagementService>,
+    cleanup_interval: Duration,
+    shutdown: impl std::future::Future<Output = ()>,
+) {
+    let mut interval = tokio::time::interval(cleanup_interval);
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = interval.tick() => {}
+        }
+
+        match documents.purge_expired_idempotency_keys().await {
+            Ok(purged) => tracing::debug!("Idempotency key cleanup completed. Purged: {}", purged),
+            Err(e) => tracing::warn!("Idempotency key cleanup failed: {}", e),
+        }
+    }
+
+    tracing::info!("Idempotency key cleanup task stopped");
+}
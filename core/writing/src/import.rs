@@ -0,0 +1,332 @@
+//! Splitting pasted plain text into separate documents, and bulk import of files
+
+use std::iter::Peekable;
+use std::str::Lines;
+use writemagic_shared::{ContentType, EntityId, Result, WritemagicError};
+
+/// Longest title derived from chunk content, in characters
+const MAX_DERIVED_TITLE_CHARS: usize = 80;
+
+/// How raw text is divided into documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SplitStrategy {
+    /// One document per paragraph separated by blank lines
+    ByBlankLines,
+    /// One document per `#` heading, titled by the heading
+    ByHeadingMarker,
+    /// Documents of at most this many words, never splitting a word
+    MaxWords(usize),
+}
+
+/// A single chunk of imported text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    pub title: String,
+    pub content: String,
+}
+
+/// Lazily splits text line by line so large inputs are never copied whole
+pub struct TextChunks<'a> {
+    lines: Peekable<Lines<'a>>,
+    strategy: SplitStrategy,
+    pending_heading: Option<String>,
+    line_rest: Option<&'a str>,
+    produced: usize,
+}
+
+impl<'a> TextChunks<'a> {
+    pub fn new(raw: &'a str, strategy: SplitStrategy) -> Result<Self> {
+        if strategy == SplitStrategy::MaxWords(0) {
+            return Err(WritemagicError::validation("MaxWords split requires a limit of at least one word"));
+        }
+
+        Ok(Self {
+            lines: raw.lines().peekable(),
+            strategy,
+            pending_heading: None,
+            line_rest: None,
+            produced: 0,
+        })
+    }
+
+    fn next_paragraph(&mut self) -> Option<(Option<String>, String)> {
+        while self.lines.next_if(|line| line.trim().is_empty()).is_some() {}
+
+        let mut content = String::new();
+        while let Some(line) = self.lines.next_if(|line| !line.trim().is_empty()) {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(line);
+        }
+
+        (!content.is_empty()).then_some((None, content))
+    }
+
+    fn next_section(&mut self) -> Option<(Option<String>, String)> {
+        let heading = self.pending_heading.take();
+        let mut content = String::new();
+        let mut exhausted = true;
+
+        for line in self.lines.by_ref() {
+            if let Some(text) = heading_text(line) {
+                self.pending_heading = Some(text.to_string());
+                exhausted = false;
+                break;
+            }
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(line);
+        }
+
+        if exhausted && heading.is_none() && content.trim().is_empty() {
+            return None;
+        }
+        Some((heading, content))
+    }
+
+    fn next_word_window(&mut self, max_words: usize) -> Option<(Option<String>, String)> {
+        let mut content = String::new();
+        let mut count = 0;
+
+        while count < max_words {
+            let Some(line) = self.line_rest.take().or_else(|| self.lines.next()) else {
+                break;
+            };
+
+            let mut words = Vec::new();
+            let mut rest = line.trim_start();
+            while !rest.is_empty() {
+                if count == max_words {
+                    self.line_rest = Some(rest);
+                    break;
+                }
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                words.push(&rest[..end]);
+                rest = rest[end..].trim_start();
+                count += 1;
+            }
+
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&words.join(" "));
+        }
+
+        (count > 0).then_some((None, content))
+    }
+}
+
+impl Iterator for TextChunks<'_> {
+    type Item = TextChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (heading, content) = match self.strategy {
+                SplitStrategy::ByBlankLines => self.next_paragraph(),
+                SplitStrategy::ByHeadingMarker => self.next_section(),
+                SplitStrategy::MaxWords(max_words) => self.next_word_window(max_words),
+            }?;
+
+            let content = content.trim().to_string();
+            if heading.is_none() && content.is_empty() {
+                continue;
+            }
+
+            self.produced += 1;
+            let title = heading
+                .filter(|heading| !heading.is_empty())
+                .map(|heading| truncate_title(&heading))
+                .unwrap_or_else(|| derive_title(&content, self.produced));
+
+            return Some(TextChunk { title, content });
+        }
+    }
+}
+
+/// Text of a markdown ATX heading line, if the line is one
+fn heading_text(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let text = trimmed.trim_start_matches('#');
+    let level = trimmed.len() - text.len();
+
+    if (1..=6).contains(&level) && (text.is_empty() || text.starts_with(char::is_whitespace)) {
+        Some(text.trim().trim_end_matches('#').trim_end())
+    } else {
+        None
+    }
+}
+
+/// Title taken from the first non-empty line of a chunk
+fn derive_title(content: &str, index: usize) -> String {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(truncate_title)
+        .unwrap_or_else(|| format!("Imported document {}", index))
+}
+
+/// Shorten a title to the last whole word within the length limit
+fn truncate_title(title: &str) -> String {
+    if title.chars().count() <= MAX_DERIVED_TITLE_CHARS {
+        return title.to_string();
+    }
+
+    let cut: String = title.chars().take(MAX_DERIVED_TITLE_CHARS).collect();
+    match cut.rfind(char::is_whitespace) {
+        Some(end) if end > 0 => cut[..end].trim_end().to_string(),
+        _ => cut,
+    }
+}
+
+/// One document of a bulk import
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImportEntry {
+    pub title: String,
+    pub content: String,
+    pub content_type: ContentType,
+}
+
+impl ImportEntry {
+    /// Entry for a file, titled by its name without the extension
+    pub fn from_file(file_name: &str, content: String) -> Self {
+        let base_name = file_name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or(file_name);
+        let title = match base_name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem,
+            _ => base_name,
+        };
+        Self {
+            title: title.to_string(),
+            content,
+            content_type: content_type_for_file(file_name),
+        }
+    }
+}
+
+/// Content type implied by a file name's extension; unknown extensions are plain text
+pub fn content_type_for_file(file_name: &str) -> ContentType {
+    let extension = match file_name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_ascii_lowercase(),
+        None => return ContentType::PlainText,
+    };
+    let code = |language: &str| ContentType::Code { language: language.to_string() };
+
+    match extension.as_str() {
+        "md" | "markdown" | "mdown" | "mkd" => ContentType::Markdown,
+        "html" | "htm" | "xhtml" => ContentType::Html,
+        "json" => ContentType::Json,
+        "yaml" | "yml" => ContentType::Yaml,
+        "rs" => code("rust"),
+        "py" => code("python"),
+        "js" => code("javascript"),
+        "ts" => code("typescript"),
+        "swift" => code("swift"),
+        "kt" => code("kotlin"),
+        _ => ContentType::PlainText,
+    }
+}
+
+/// An import entry that could not be created
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ImportFailure {
+    /// Position of the entry in the import
+    pub index: usize,
+    pub title: String,
+    pub error: String,
+}
+
+/// Outcome of a bulk import
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub failed: usize,
+    /// Created documents, in entry order
+    pub document_ids: Vec<EntityId>,
+    pub failures: Vec<ImportFailure>,
+}
+
+impl ImportSummary {
+    pub fn record_created(&mut self, document_id: EntityId) {
+        self.created += 1;
+        self.document_ids.push(document_id);
+    }
+
+    pub fn record_failure(&mut self, index: usize, title: impl Into<String>, error: impl ToString) {
+        self.failed += 1;
+        self.failures.push(ImportFailure { index, title: title.into(), error: error.to_string() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(raw: &str, strategy: SplitStrategy) -> Vec<TextChunk> {
+        TextChunks::new(raw, strategy).unwrap().collect()
+    }
+
+    #[test]
+    fn test_blank_lines_split_paragraphs() {
+        let chunks = split("First line\nstill first\n\n\n  \nSecond\n\nThird\n", SplitStrategy::ByBlankLines);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].content, "First line\nstill first");
+        assert_eq!(chunks[0].title, "First line");
+        assert_eq!(chunks[1].content, "Second");
+        assert_eq!(chunks[2].content, "Third");
+    }
+
+    #[test]
+    fn test_heading_marker_uses_headings_as_titles() {
+        let raw = "Preamble text\n# Introduction\nHello there\n\n## Details ##\nMore text\n#hashtag stays\n";
+        let chunks = split(raw, SplitStrategy::ByHeadingMarker);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].title, "Preamble text");
+        assert_eq!(chunks[1].title, "Introduction");
+        assert_eq!(chunks[1].content, "Hello there");
+        assert_eq!(chunks[2].title, "Details");
+        assert_eq!(chunks[2].content, "More text\n#hashtag stays");
+    }
+
+    #[test]
+    fn test_max_words_respects_limit_without_splitting_words() {
+        let raw = "alpha beta gamma delta\nepsilon zeta\n\neta theta iota";
+        let chunks = split(raw, SplitStrategy::MaxWords(3));
+
+        let source_words: Vec<&str> = raw.split_whitespace().collect();
+        let chunk_words: Vec<&str> = chunks.iter().flat_map(|c| c.content.split_whitespace()).collect();
+        assert_eq!(chunk_words, source_words);
+        assert!(chunks.iter().all(|c| c.content.split_whitespace().count() <= 3));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].content, "delta\nepsilon zeta");
+    }
+
+    #[test]
+    fn test_max_words_zero_is_rejected() {
+        assert!(TextChunks::new("text", SplitStrategy::MaxWords(0)).is_err());
+    }
+
+    #[test]
+    fn test_import_entries_infer_title_and_content_type_from_file_name() {
+        let entry = ImportEntry::from_file("notes/Chapter One.MD", "# One".to_string());
+        assert_eq!(entry.title, "Chapter One");
+        assert_eq!(entry.content_type, ContentType::Markdown);
+
+        assert_eq!(content_type_for_file("index.htm"), ContentType::Html);
+        assert_eq!(content_type_for_file("main.rs"), ContentType::Code { language: "rust".to_string() });
+        assert_eq!(content_type_for_file("README"), ContentType::PlainText);
+        assert_eq!(ImportEntry::from_file(".env", String::new()).title, ".env");
+    }
+
+    #[test]
+    fn test_long_titles_are_truncated_at_word_boundary() {
+        let line = "word ".repeat(40);
+        let chunks = split(&line, SplitStrategy::ByBlankLines);
+
+        assert!(chunks[0].title.chars().count() <= MAX_DERIVED_TITLE_CHARS);
+        assert!(chunks[0].title.ends_with("word"));
+    }
+}
@@ -0,0 +1,261 @@
+//! Document language detection and script-aware word counting
+
+use serde::{Deserialize, Serialize};
+use writemagic_shared::{Result, WritemagicError};
+
+/// Language assumed when detection is disabled or inconclusive
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Language detection configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LanguageConfig {
+    /// Detect the language whenever document content changes
+    pub auto_detect: bool,
+    /// Language used for short or ambiguous content
+    pub default_language: String,
+    /// Content shorter than this many characters is not detected
+    pub min_detection_chars: usize,
+    /// Detections below this confidence fall back to the default
+    pub min_confidence: f64,
+}
+
+impl Default for LanguageConfig {
+    fn default() -> Self {
+        Self {
+            auto_detect: true,
+            default_language: DEFAULT_LANGUAGE.to_string(),
+            min_detection_chars: 20,
+            min_confidence: 0.5,
+        }
+    }
+}
+
+/// Detect the language of `content` as an ISO 639 code
+///
+/// Two-letter ISO 639-1 codes are returned for common languages, and the
+/// three-letter ISO 639-3 code otherwise.
+pub fn detect_language(content: &str, config: &LanguageConfig) -> String {
+    if !config.auto_detect || content.trim().chars().count() < config.min_detection_chars {
+        return config.default_language.clone();
+    }
+
+    match whatlang::detect(content) {
+        Some(info) if info.confidence() >= config.min_confidence => iso_639_1(info.lang())
+            .unwrap_or_else(|| info.lang().code())
+            .to_string(),
+        _ => config.default_language.clone(),
+    }
+}
+
+/// Normalize a manually chosen language code
+pub fn normalize_language_code(code: &str) -> Result<String> {
+    let code = code.trim().to_ascii_lowercase();
+    if !(2..=3).contains(&code.len()) || !code.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err(WritemagicError::validation(format!(
+            "Invalid language code '{}': expected a two or three letter ISO 639 code",
+            code
+        )));
+    }
+    Ok(code)
+}
+
+fn iso_639_1(lang: whatlang::Lang) -> Option<&'static str> {
+    use whatlang::Lang;
+
+    Some(match lang {
+        Lang::Eng => "en",
+        Lang::Cmn => "zh",
+        Lang::Jpn => "ja",
+        Lang::Kor => "ko",
+        Lang::Spa => "es",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ita => "it",
+        Lang::Por => "pt",
+        Lang::Nld => "nl",
+        Lang::Swe => "sv",
+        Lang::Pol => "pl",
+        Lang::Rus => "ru",
+        Lang::Ukr => "uk",
+        Lang::Tur => "tr",
+        Lang::Ara => "ar",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Vie => "vi",
+        Lang::Tha => "th",
+        Lang::Ell => "el",
+        _ => return None,
+    })
+}
+
+/// Content length in bytes from which whitespace word counts use the SIMD scanner
+const SIMD_WORD_COUNT_THRESHOLD: usize = 4096;
+
+/// How words are counted for a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordCountPolicy {
+    /// Words are separated by whitespace
+    Whitespace,
+    /// Each ideographic or kana character is a word; runs of ASCII letters
+    /// and digits still count as one word
+    PerCharacter,
+}
+
+impl WordCountPolicy {
+    pub fn for_language(language: &str) -> Self {
+        match language {
+            "zh" | "ja" => Self::PerCharacter,
+            _ => Self::Whitespace,
+        }
+    }
+
+    pub fn count(self, content: &str) -> u32 {
+        match self {
+            Self::Whitespace if content.len() >= SIMD_WORD_COUNT_THRESHOLD => {
+                writemagic_shared::text_processing::count_words_simd(content) as u32
+            }
+            Self::Whitespace => content.split_whitespace().count() as u32,
+            Self::PerCharacter => {
+                let mut count = 0;
+                let mut in_ascii_word = false;
+                for c in content.chars() {
+                    if c.is_ascii_alphanumeric() {
+                        if !in_ascii_word {
+                            count += 1;
+                        }
+                        in_ascii_word = true;
+                    } else {
+                        in_ascii_word = false;
+                        if c.is_alphanumeric() {
+                            count += 1;
+                        }
+                    }
+                }
+                count
+            }
+        }
+    }
+
+    /// Word count of `content` after replacing `start..end` with `replacement`,
+    /// given the count `current` of `content` as it is
+    ///
+    /// Only the words touching the edited range are recounted, so an edit that
+    /// splits or joins words is still accounted for exactly.
+    pub fn count_after_edit(self, content: &str, current: u32, start: usize, end: usize, replacement: &str) -> u32 {
+        let window_start = content[..start]
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| !self.joins_words(c))
+            .map(|(index, c)| index + c.len_utf8())
+            .unwrap_or(0);
+        let window_end = content[end..]
+            .char_indices()
+            .find(|&(_, c)| !self.joins_words(c))
+            .map(|(index, _)| end + index)
+            .unwrap_or(content.len());
+
+        let mut edited = String::with_capacity(window_end - window_start - (end - start) + replacement.len());
+        edited.push_str(&content[window_start..start]);
+        edited.push_str(replacement);
+        edited.push_str(&content[end..window_end]);
+
+        let before = self.count(&content[window_start..window_end]);
+        (current + self.count(&edited)).saturating_sub(before)
+    }
+
+    /// Whether `c` continues the word next to it rather than separating words
+    fn joins_words(self, c: char) -> bool {
+        match self {
+            Self::Whitespace => !c.is_whitespace(),
+            Self::PerCharacter => c.is_ascii_alphanumeric(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_short_content_falls_back_to_configured_default() {
+        let config = LanguageConfig {
+            default_language: "fr".to_string(),
+            ..LanguageConfig::default()
+        };
+
+        assert_eq!(detect_language("ok", &config), "fr");
+        assert_eq!(detect_language("", &LanguageConfig::default()), DEFAULT_LANGUAGE);
+    }
+
+    #[test]
+    fn test_per_character_counts_ascii_runs_as_words() {
+        assert_eq!(WordCountPolicy::PerCharacter.count("我用 Rust 写作。"), 4);
+        assert_eq!(WordCountPolicy::Whitespace.count("我用 Rust 写作。"), 3);
+
+        let long = "one two\u{a0}three\n".repeat(SIMD_WORD_COUNT_THRESHOLD);
+        assert_eq!(WordCountPolicy::Whitespace.count(&long), long.split_whitespace().count() as u32);
+    }
+
+    #[test]
+    fn test_count_after_edit_handles_word_boundaries() {
+        let policy = WordCountPolicy::Whitespace;
+        let content = "hello world";
+        let current = policy.count(content);
+
+        // Splitting, joining and extending words
+        assert_eq!(policy.count_after_edit(content, current, 2, 2, " "), 3);
+        assert_eq!(policy.count_after_edit(content, current, 5, 6, ""), 1);
+        assert_eq!(policy.count_after_edit(content, current, 11, 11, "s"), 2);
+        assert_eq!(policy.count_after_edit(content, current, 0, 11, ""), 0);
+    }
+
+    /// Text mixing words, whitespace, CJK characters and punctuation
+    fn edit_text() -> impl Strategy<Value = String> {
+        let c = prop_oneof![
+            5 => prop::char::range('a', 'e'),
+            3 => prop::sample::select(vec![' ', '\n', '\u{a0}', '\u{3000}']),
+            2 => prop::sample::select(vec!['我', '写', '。', '-', '1']),
+        ];
+        prop::collection::vec(c, 0..12).prop_map(|chars| chars.into_iter().collect())
+    }
+
+    /// Nearest char boundary at or below `index`
+    fn floor_boundary(text: &str, index: usize) -> usize {
+        let mut index = index.min(text.len());
+        while !text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+
+    proptest! {
+        #[test]
+        fn incremental_count_matches_full_recount(
+            initial in edit_text(),
+            edits in prop::collection::vec((any::<usize>(), any::<usize>(), edit_text()), 1..20),
+            per_character in any::<bool>(),
+        ) {
+            let policy = if per_character { WordCountPolicy::PerCharacter } else { WordCountPolicy::Whitespace };
+            let mut content = initial;
+            let mut count = policy.count(&content);
+
+            for (a, b, replacement) in edits {
+                let start = floor_boundary(&content, a % (content.len() + 1));
+                let end = floor_boundary(&content, start + b % (content.len() - start + 1));
+
+                count = policy.count_after_edit(&content, count, start, end, &replacement);
+                content.replace_range(start..end, &replacement);
+                prop_assert_eq!(count, policy.count(&content), "after editing into {:?}", content);
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_code_normalization() {
+        assert_eq!(normalize_language_code(" ZH ").unwrap(), "zh");
+        assert!(normalize_language_code("english").is_err());
+        assert!(normalize_language_code("e1").is_err());
+    }
+}
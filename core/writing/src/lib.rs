@@ -8,10 +8,41 @@ pub mod services;
 pub mod repositories;
 #[cfg(feature = "database")]
 pub mod sqlite_repositories;
+#[cfg(feature = "database")]
+pub mod encryption;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mapped_repository;
 pub mod events;
+pub mod event_subscriptions;
+pub mod projections;
 pub mod conversions;
+pub mod autosave;
+pub mod import;
+pub mod context_assembly;
+pub mod language;
+pub mod reading_time;
+pub mod links;
+pub mod batch_ai;
+pub mod undo;
+pub mod tenancy;
+pub mod export;
+pub mod document_export;
+pub mod markup;
+pub mod diagnostics;
+pub mod query;
+pub mod cross_domain;
+pub mod unit_of_work;
+pub mod idempotency;
+pub mod versions;
+pub mod locking;
+pub mod templates;
+pub mod fuzzy;
 #[cfg(feature = "ai")]
 pub mod ai_writing_integration;
+#[cfg(feature = "ai")]
+pub mod agent_integration;
+#[cfg(feature = "ai")]
+pub mod streaming;
 
 // Web persistence layer for IndexedDB
 #[cfg(target_arch = "wasm32")]
@@ -26,10 +57,41 @@ pub use services::*;
 pub use repositories::*;
 #[cfg(feature = "database")]
 pub use sqlite_repositories::*;
+#[cfg(feature = "database")]
+pub use encryption::FieldCipher;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mapped_repository::MappedFileDocumentRepository;
 pub use events::*;
+pub use event_subscriptions::EventSubscriptions;
+pub use projections::*;
 pub use conversions::*;
+pub use autosave::*;
+pub use import::*;
+pub use context_assembly::*;
+pub use language::*;
+pub use reading_time::*;
+pub use links::*;
+pub use batch_ai::*;
+pub use undo::*;
+pub use tenancy::*;
+pub use export::*;
+pub use document_export::*;
+pub use markup::*;
+pub use diagnostics::*;
+pub use query::*;
+pub use cross_domain::*;
+pub use unit_of_work::*;
+pub use idempotency::*;
+pub use versions::*;
+pub use locking::*;
+pub use templates::*;
+pub use fuzzy::*;
 #[cfg(feature = "ai")]
 pub use ai_writing_integration::*;
+#[cfg(feature = "ai")]
+pub use agent_integration::*;
+#[cfg(feature = "ai")]
+pub use streaming::*;
 
 // Re-export web persistence types for WASM builds
 #[cfg(target_arch = "wasm32")]
@@ -40,7 +102,8 @@ pub use web_persistence::*;
 pub use writemagic_ai::{
     AIProvider, AIOrchestrationService, AIProviderRegistry,
     CompletionRequest, CompletionResponse, Message, MessageRole,
-    ContextManagementService, ContentFilteringService
+    ContextManagementService, ContentFilteringService,
+    ContentCategory, ContentFlag, ContentSeverity, FilterMode, FilterResult,
 };
 
 #[cfg(test)]
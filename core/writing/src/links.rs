@@ -0,0 +1,98 @@
+//! Wiki-style `[[links]]` between documents
+
+use serde::{Deserialize, Serialize};
+use writemagic_shared::EntityId;
+
+/// Document linking configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    /// Extract links whenever document content changes
+    pub enabled: bool,
+    /// Match link references against titles case-sensitively
+    pub case_sensitive_titles: bool,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            case_sensitive_titles: false,
+        }
+    }
+}
+
+impl LinkConfig {
+    /// Whether `reference` names a document titled `title`
+    pub fn title_matches(&self, reference: &str, title: &str) -> bool {
+        if self.case_sensitive_titles {
+            reference == title.trim()
+        } else {
+            reference.to_lowercase() == title.trim().to_lowercase()
+        }
+    }
+}
+
+/// A link from one document to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentLink {
+    pub source_id: EntityId,
+    /// Reference as written between the brackets
+    pub reference: String,
+    /// Resolved target, or `None` when no document matches the reference
+    pub target_id: Option<EntityId>,
+}
+
+impl DocumentLink {
+    pub fn is_dangling(&self) -> bool {
+        self.target_id.is_none()
+    }
+}
+
+/// Extract `[[title-or-id]]` references from content
+///
+/// References are trimmed and deduplicated in order of first appearance. A
+/// `[[target|label]]` link refers to `target`.
+pub fn extract_link_references(content: &str) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("[[") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("]]") else {
+            break;
+        };
+
+        let inner = &after_open[..end];
+        // A nested opener means the earlier brackets were never closed
+        if let Some(nested) = inner.rfind("[[") {
+            rest = &after_open[nested..];
+            continue;
+        }
+
+        let target = inner.split('|').next().unwrap_or_default().trim();
+        if !target.is_empty() && !target.contains('\n') && !references.iter().any(|r| r == target) {
+            references.push(target.to_string());
+        }
+        rest = &after_open[end + 2..];
+    }
+
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_unique_references_with_labels() {
+        let content = "See [[Garden Notes]] and [[ Seeds | the seed list]], then [[Garden Notes]] again.";
+        assert_eq!(extract_link_references(content), vec!["Garden Notes", "Seeds"]);
+    }
+
+    #[test]
+    fn test_ignores_empty_and_unclosed_links() {
+        assert!(extract_link_references("[[]] and [[  ]] and [[never closed").is_empty());
+        assert_eq!(extract_link_references("[[broken [[Fixed]]"), vec!["Fixed"]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Advisory document locks against lost updates between concurrent editors
+
+use crate::repositories::{DocumentLockRepository, InMemoryDocumentLockRepository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+use writemagic_shared::{EntityId, Result, Timestamp, WritemagicError};
+
+/// Longest a lock may be held without being renewed
+pub const MAX_LOCK_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Exclusive right of `holder` to edit a document until `expires_at`
+///
+/// Locks expire on their own so a crashed client can't block a document;
+/// holders renew a lock by acquiring it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentLock {
+    pub document_id: EntityId,
+    pub holder: EntityId,
+    pub acquired_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+impl DocumentLock {
+    pub fn is_active_at(&self, now: &Timestamp) -> bool {
+        self.expires_at.0 > now.0
+    }
+}
+
+/// Advisory locks together with the per-document guard that makes checking
+/// them atomic with a write
+///
+/// Taking a lock and a guarded write on the same document wait for each
+/// other, so no lock can be granted between a writer's check and its save.
+#[derive(Clone)]
+pub struct DocumentLocks {
+    repository: Arc<dyn DocumentLockRepository>,
+    writes: KeyLocks<EntityId>,
+}
+
+impl DocumentLocks {
+    pub fn new(repository: Arc<dyn DocumentLockRepository>) -> Self {
+        Self {
+            repository,
+            writes: KeyLocks::default(),
+        }
+    }
+
+    pub fn repository(&self) -> &Arc<dyn DocumentLockRepository> {
+        &self.repository
+    }
+
+    /// Hold off lock changes and other guarded writes to `document_id` until the guard is dropped
+    pub(crate) async fn guard(&self, document_id: &EntityId) -> KeyGuard<EntityId> {
+        self.writes.lock(document_id).await
+    }
+
+    /// Guards for several documents, taken in a fixed order so two callers can't deadlock
+    pub(crate) async fn guard_all(&self, document_ids: &[EntityId]) -> Vec<KeyGuard<EntityId>> {
+        let mut ids = document_ids.to_vec();
+        ids.sort_by_key(EntityId::as_uuid);
+        ids.dedup();
+        let mut guards = Vec::with_capacity(ids.len());
+        for id in &ids {
+            guards.push(self.writes.lock(id).await);
+        }
+        guards
+    }
+
+    /// Fail with a conflict when someone other than `editor` holds a lock on the document
+    ///
+    /// Only meaningful while the document's guard is held.
+    pub(crate) async fn ensure_not_locked(&self, document_id: &EntityId, editor: Option<&EntityId>) -> Result<()> {
+        match self.repository.find_active(document_id, &Timestamp::now()).await? {
+            Some(lock) if editor != Some(&lock.holder) => Err(WritemagicError::conflict(format!(
+                "Document {} is locked by {} until {}",
+                document_id, lock.holder, lock.expires_at
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for DocumentLocks {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryDocumentLockRepository::new()))
+    }
+}
+
+/// Async locks keyed by value, serializing work on the same key
+///
+/// Work on different keys runs concurrently. A key's entry lives only while
+/// someone holds or waits for its lock.
+#[derive(Debug)]
+pub(crate) struct KeyLocks<K> {
+    locks: Arc<Mutex<HashMap<K, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl<K> Clone for KeyLocks<K> {
+    fn clone(&self) -> Self {
+        Self { locks: self.locks.clone() }
+    }
+}
+
+impl<K> Default for KeyLocks<K> {
+    fn default() -> Self {
+        Self { locks: Arc::new(Mutex::new(HashMap::new())) }
+    }
+}
+
+impl<K: Eq + Hash + Clone> KeyLocks<K> {
+    /// Wait for the lock on `key`, held until the guard is dropped
+    pub(crate) async fn lock(&self, key: &K) -> KeyGuard<K> {
+        let lock = self.locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let guard = lock.lock_owned().await;
+        KeyGuard {
+            locks: self.locks.clone(),
+            key: key.clone(),
+            guard: Some(guard),
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+}
+
+/// Held lock on one key of a [`KeyLocks`]
+pub(crate) struct KeyGuard<K: Eq + Hash> {
+    locks: Arc<Mutex<HashMap<K, Arc<tokio::sync::Mutex<()>>>>>,
+    key: K,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl<K: Eq + Hash> Drop for KeyGuard<K> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        // The map and this guard are the only owners when nobody is waiting
+        if locks.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 2) {
+            locks.remove(&self.key);
+        }
+        self.guard.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_key_locks_serialize_one_key_and_forget_it_once_released() {
+        let locks = KeyLocks::default();
+
+        let first = locks.lock(&"retry".to_string()).await;
+        // Another key is not held up by the first
+        let other = locks.lock(&"other".to_string()).await;
+        drop(other);
+
+        let waiter_locks = locks.clone();
+        let waiter = tokio::spawn(async move {
+            let _guard = waiter_locks.lock(&"retry".to_string()).await;
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        assert_eq!(locks.len(), 1);
+
+        drop(first);
+        waiter.await.unwrap();
+        assert_eq!(locks.len(), 0);
+    }
+}
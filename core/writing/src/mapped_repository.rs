@@ -0,0 +1,400 @@
+//! Document repository backed by a single append-only memory-mapped file
+//!
+//! The file starts with a 16 byte header: an 8 byte magic and the little-endian
+//! end offset of the last record. Each record is a status byte, a little-endian
+//! `u32` payload length and the document as JSON. Saving appends a new record
+//! and marks the previous one stale; deleting only marks it stale. The id to
+//! offset index lives in memory and is rebuilt from the live records on open.
+
+use crate::entities::Document;
+use crate::repositories::{ConcurrencyError, DocumentRepository, DocumentStatistics};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use writemagic_shared::{DocumentTag, EntityId, MappedFileMut, Pagination, Repository, Result, WritemagicError};
+
+const MAGIC: &[u8; 8] = b"WMDOCS\x00\x01";
+const HEADER_LEN: usize = 16;
+const RECORD_HEADER_LEN: usize = 5;
+const STATUS_LIVE: u8 = 1;
+const STATUS_STALE: u8 = 0;
+/// Size a new store file is created with, and the least it grows by
+const INITIAL_CAPACITY: usize = 1024 * 1024;
+
+struct MappedStore {
+    file: MappedFileMut,
+    path: PathBuf,
+    /// Offset just past the last record
+    end: usize,
+    /// Offset of each document's live record
+    index: HashMap<EntityId, usize>,
+    /// Bytes taken up by stale records, reclaimable by `compact`
+    stale_bytes: u64,
+}
+
+impl MappedStore {
+    fn open(path: PathBuf) -> Result<Self> {
+        let mut file = MappedFileMut::open(&path, INITIAL_CAPACITY)?;
+        let data = file.as_slice();
+        if data[..MAGIC.len()].iter().all(|&b| b == 0) {
+            file.write_at(0, MAGIC)?;
+            file.write_at(MAGIC.len(), &(HEADER_LEN as u64).to_le_bytes())?;
+            file.flush_range(0, HEADER_LEN)?;
+        } else if &data[..MAGIC.len()] != MAGIC {
+            return Err(WritemagicError::validation(format!("{} is not a document store", path.display())));
+        }
+
+        let end = u64::from_le_bytes(file.as_slice()[MAGIC.len()..HEADER_LEN].try_into().unwrap()) as usize;
+        if end < HEADER_LEN || end > file.len() {
+            return Err(WritemagicError::database(format!("Corrupt document store header in {}", path.display())));
+        }
+
+        let mut store = Self { file, path, end, index: HashMap::new(), stale_bytes: 0 };
+        store.rebuild_index()?;
+        Ok(store)
+    }
+
+    /// Index every live record, keeping the latest if an interrupted save left two
+    fn rebuild_index(&mut self) -> Result<()> {
+        let mut offset = HEADER_LEN;
+        while offset < self.end {
+            let (status, payload) = self.record(offset)?;
+            let record_len = RECORD_HEADER_LEN + payload.len();
+            if status == STATUS_LIVE {
+                let document = decode(payload)?;
+                if let Some(previous) = self.index.insert(document.id, offset) {
+                    self.mark_stale(previous)?;
+                }
+            } else {
+                self.stale_bytes += record_len as u64;
+            }
+            offset += record_len;
+        }
+        Ok(())
+    }
+
+    /// Status byte and payload of the record at `offset`
+    fn record(&self, offset: usize) -> Result<(u8, &[u8])> {
+        let data = &self.file.as_slice()[..self.end];
+        let corrupt = || WritemagicError::database(format!("Corrupt document record at offset {}", offset));
+
+        let header = data.get(offset..offset + RECORD_HEADER_LEN).ok_or_else(corrupt)?;
+        let len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+        let payload = data.get(offset + RECORD_HEADER_LEN..offset + RECORD_HEADER_LEN + len).ok_or_else(corrupt)?;
+        Ok((header[0], payload))
+    }
+
+    fn document_at(&self, offset: usize) -> Result<Document> {
+        decode(self.record(offset)?.1)
+    }
+
+    /// Live documents in the order their records were written
+    fn documents(&self) -> Result<Vec<Document>> {
+        let mut offsets: Vec<usize> = self.index.values().copied().collect();
+        offsets.sort_unstable();
+        offsets.into_iter().map(|offset| self.document_at(offset)).collect()
+    }
+
+    /// Append a live record for `payload`, returning its offset
+    fn append(&mut self, payload: &[u8]) -> Result<usize> {
+        let len = u32::try_from(payload.len())
+            .map_err(|_| WritemagicError::validation("Document is too large for the document store"))?;
+        let offset = self.end;
+        let end = offset + RECORD_HEADER_LEN + payload.len();
+        if end > self.file.len() {
+            self.file.flush()?;
+            let capacity = end.max(self.file.len() * 2).max(self.file.len() + INITIAL_CAPACITY);
+            self.file = MappedFileMut::open(&self.path, capacity)?;
+        }
+
+        self.file.write_at(offset, &[STATUS_LIVE])?;
+        self.file.write_at(offset + 1, &len.to_le_bytes())?;
+        self.file.write_at(offset + RECORD_HEADER_LEN, payload)?;
+        self.file.flush_range(offset, end - offset)?;
+
+        // Only move the end past the record once it is on disk
+        self.end = end;
+        self.file.write_at(MAGIC.len(), &(end as u64).to_le_bytes())?;
+        self.file.flush_range(0, HEADER_LEN)?;
+        Ok(offset)
+    }
+
+    fn mark_stale(&mut self, offset: usize) -> Result<()> {
+        let record_len = RECORD_HEADER_LEN + self.record(offset)?.1.len();
+        self.file.write_at(offset, &[STATUS_STALE])?;
+        self.file.flush_range(offset, 1)?;
+        self.stale_bytes += record_len as u64;
+        Ok(())
+    }
+}
+
+fn decode(payload: &[u8]) -> Result<Document> {
+    serde_json::from_slice(payload)
+        .map_err(|e| WritemagicError::database(format!("Failed to decode stored document: {}", e)))
+}
+
+fn page(documents: impl Iterator<Item = Document>, pagination: Pagination) -> Vec<Document> {
+    documents
+        .skip(pagination.offset as usize)
+        .take(pagination.limit as usize)
+        .collect()
+}
+
+/// Document repository storing every document in one memory-mapped file
+///
+/// Suited to read-mostly desktop use without SQLite: reads decode straight
+/// from the mapping, and space left by updates and deletes is reclaimed by
+/// `compact`. Like the in-memory repository, it has no project membership,
+/// so `find_by_project_id` returns every document.
+pub struct MappedFileDocumentRepository {
+    store: RwLock<MappedStore>,
+}
+
+impl MappedFileDocumentRepository {
+    /// Open the store at `path`, creating it if missing, and rebuild its index
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            store: RwLock::new(MappedStore::open(path.as_ref().to_path_buf())?),
+        })
+    }
+
+    /// Bytes held by superseded or deleted records
+    pub fn stale_bytes(&self) -> Result<u64> {
+        Ok(self.read()?.stale_bytes)
+    }
+
+    /// Rewrite the store with only its live records, returning the bytes reclaimed
+    ///
+    /// The compacted copy is written beside the store and renamed over it, so an
+    /// interrupted compaction leaves the original intact.
+    pub fn compact(&self) -> Result<u64> {
+        let mut store = self.write()?;
+        let mut offsets: Vec<usize> = store.index.values().copied().collect();
+        offsets.sort_unstable();
+
+        let mut records = Vec::with_capacity(offsets.len());
+        let mut live_bytes = 0;
+        for offset in offsets {
+            let payload = store.record(offset)?.1;
+            live_bytes += RECORD_HEADER_LEN + payload.len();
+            records.push((decode(payload)?.id, offset));
+        }
+
+        let compacted_path = store.path.with_extension("compacting");
+        let mut compacted = MappedFileMut::create(&compacted_path, (HEADER_LEN + live_bytes).max(INITIAL_CAPACITY))?;
+        let mut index = HashMap::with_capacity(records.len());
+        let mut end = HEADER_LEN;
+        for (id, offset) in records {
+            let record_len = RECORD_HEADER_LEN + store.record(offset)?.1.len();
+            compacted.write_at(end, &store.file.as_slice()[offset..offset + record_len])?;
+            index.insert(id, end);
+            end += record_len;
+        }
+        compacted.write_at(0, MAGIC)?;
+        compacted.write_at(MAGIC.len(), &(end as u64).to_le_bytes())?;
+        compacted.flush()?;
+
+        store.file = compacted;
+        std::fs::rename(&compacted_path, &store.path)
+            .map_err(|e| WritemagicError::internal_with_source("Failed to replace document store", e))?;
+
+        let reclaimed = (store.end - end) as u64;
+        store.end = end;
+        store.index = index;
+        store.stale_bytes = 0;
+        Ok(reclaimed)
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, MappedStore>> {
+        self.store.read().map_err(|_| WritemagicError::internal("Failed to acquire read lock"))
+    }
+
+    fn write(&self) -> Result<std::sync::RwLockWriteGuard<'_, MappedStore>> {
+        self.store.write().map_err(|_| WritemagicError::internal("Failed to acquire write lock"))
+    }
+}
+
+#[async_trait]
+impl Repository<Document, EntityId> for MappedFileDocumentRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Document>> {
+        let store = self.read()?;
+        store.index.get(id).map(|&offset| store.document_at(offset)).transpose()
+    }
+
+    async fn find_all(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        Ok(page(self.read()?.documents()?.into_iter(), pagination))
+    }
+
+    async fn save(&self, entity: &Document) -> Result<Document> {
+        let payload = serde_json::to_vec(entity)?;
+        let mut store = self.write()?;
+        let offset = store.append(&payload)?;
+        if let Some(previous) = store.index.insert(entity.id, offset) {
+            store.mark_stale(previous)?;
+        }
+        Ok(entity.clone())
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        let mut store = self.write()?;
+        match store.index.remove(id) {
+            Some(offset) => {
+                store.mark_stale(offset)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn exists(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.read()?.index.contains_key(id))
+    }
+
+    async fn count(&self) -> Result<u64> {
+        Ok(self.read()?.index.len() as u64)
+    }
+}
+
+#[async_trait]
+impl DocumentRepository for MappedFileDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        let payload = serde_json::to_vec(document).map_err(WritemagicError::from)?;
+        let mut store = self.write()?;
+        let actual = match store.index.get(&document.id) {
+            Some(&offset) => Some(store.document_at(offset)?.version),
+            None => None,
+        };
+        if actual != Some(expected_version) {
+            return Err(ConcurrencyError::VersionMismatch {
+                document_id: document.id,
+                expected: expected_version,
+                actual,
+            });
+        }
+
+        let offset = store.append(&payload)?;
+        if let Some(previous) = store.index.insert(document.id, offset) {
+            store.mark_stale(previous)?;
+        }
+        Ok(document.clone())
+    }
+
+    async fn find_by_ids(&self, ids: &[EntityId]) -> Result<Vec<Document>> {
+        let store = self.read()?;
+        ids.iter()
+            .filter_map(|id| store.index.get(id))
+            .map(|&offset| store.document_at(offset))
+            .collect()
+    }
+
+    async fn find_by_project_id(&self, _project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        self.find_all(pagination).await
+    }
+
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>> {
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| !doc.is_deleted && doc.tags.contains(tag)), pagination))
+    }
+
+    async fn find_by_content_type(&self, content_type: &writemagic_shared::ContentType, pagination: Pagination) -> Result<Vec<Document>> {
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| &doc.content_type == content_type), pagination))
+    }
+
+    async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        let query_lower = query.to_lowercase();
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| doc.title.to_lowercase().contains(&query_lower)), pagination))
+    }
+
+    async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        let query_lower = query.to_lowercase();
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| doc.content.to_lowercase().contains(&query_lower)), pagination))
+    }
+
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+        let query_lower = query.trim().to_lowercase();
+        if query_lower.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut matches: Vec<Document> = self.read()?.documents()?
+            .into_iter()
+            .filter(|doc| include_deleted || !doc.is_deleted)
+            .filter(|doc| {
+                doc.title.to_lowercase().contains(&query_lower)
+                    || doc.content.to_lowercase().contains(&query_lower)
+            })
+            .collect();
+        matches.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
+        Ok(page(matches.into_iter(), pagination))
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| doc.created_by.as_ref() == Some(user_id)), pagination))
+    }
+
+    async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        let mut documents = self.read()?.documents()?;
+        documents.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
+        Ok(page(documents.into_iter(), pagination))
+    }
+
+    async fn find_deleted(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        let documents = self.read()?.documents()?;
+        Ok(page(documents.into_iter().filter(|doc| doc.is_deleted), pagination))
+    }
+
+    async fn get_statistics(&self) -> Result<DocumentStatistics> {
+        Ok(DocumentStatistics::from_documents(&self.read()?.documents()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use writemagic_shared::ContentType;
+
+    fn document(title: &str) -> Document {
+        Document::new(title.to_string(), format!("{} content", title), ContentType::Markdown, None)
+    }
+
+    #[tokio::test]
+    async fn test_updates_survive_reopen_and_compaction() {
+        let path = std::env::temp_dir().join(format!("writemagic-docs-{}.wmd", EntityId::new()));
+        let mut kept = document("Kept");
+        let removed = document("Removed");
+        {
+            let repository = MappedFileDocumentRepository::open(&path).unwrap();
+            repository.save(&kept).await.unwrap();
+            repository.save(&removed).await.unwrap();
+            kept.title = "Kept, revised".to_string();
+            repository.save(&kept).await.unwrap();
+            assert!(repository.delete(&removed.id).await.unwrap());
+        }
+
+        let repository = MappedFileDocumentRepository::open(&path).unwrap();
+        assert_eq!(repository.count().await.unwrap(), 1);
+        assert_eq!(repository.find_by_id(&kept.id).await.unwrap().unwrap().title, "Kept, revised");
+        assert!(repository.find_by_id(&removed.id).await.unwrap().is_none());
+
+        let stale = repository.stale_bytes().unwrap();
+        assert!(stale > 0);
+        assert_eq!(repository.compact().unwrap(), stale);
+        assert_eq!(repository.stale_bytes().unwrap(), 0);
+        assert_eq!(repository.find_by_id(&kept.id).await.unwrap().unwrap().title, "Kept, revised");
+        drop(repository);
+
+        let reopened = MappedFileDocumentRepository::open(&path).unwrap();
+        assert_eq!(reopened.find_all(Pagination::new(0, 10).unwrap()).await.unwrap().len(), 1);
+        drop(reopened);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,1058 @@
+//! Conversion between HTML and Markdown document content
+//!
+//! Both directions are hand-rolled and forgiving: unknown tags are dropped
+//! while their text is kept, and malformed markup is converted as far as it
+//! can be rather than rejected.
+
+use writemagic_shared::html::{HtmlToken, HtmlTokens};
+use writemagic_shared::{is_safe_url, sanitize_html, ContentType, Result, WritemagicError};
+
+pub(crate) use writemagic_shared::html::escape_html;
+
+/// Elements whose contents are discarded along with the tags
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "head", "title", "noscript", "template", "iframe", "object"];
+
+/// Elements that start a new paragraph-like block
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p", "div", "section", "article", "header", "footer", "main", "nav", "aside", "figure",
+    "figcaption", "table", "thead", "tbody", "tfoot", "dl", "dt", "dd", "address", "details", "summary",
+];
+
+/// Convert HTML to Markdown
+///
+/// Headings, paragraphs, lists, links, images, emphasis, blockquotes, rules and
+/// code are translated. Other tags are stripped and their text kept, except
+/// for scripts, styles and similar elements, which are dropped entirely.
+pub fn html_to_markdown(html: &str) -> Result<String> {
+    let mut writer = MarkdownWriter::default();
+
+    for token in HtmlTokens::new(html) {
+        match token {
+            HtmlToken::Text(text) => writer.text(&text),
+            HtmlToken::Open { name, attributes, self_closing } => {
+                writer.open(&name, &attributes);
+                if self_closing {
+                    writer.close(&name);
+                }
+            }
+            HtmlToken::Close(name) => writer.close(&name),
+        }
+    }
+
+    Ok(writer.finish())
+}
+
+/// Convert Markdown to HTML
+///
+/// Covers the subset produced by [`html_to_markdown`]: ATX headings,
+/// paragraphs, nested lists, blockquotes, fenced code, rules, emphasis,
+/// strikethrough, code spans, links and images. The result is sanitized, so
+/// raw HTML and links with unsafe schemes never reach the output.
+pub fn markdown_to_html(markdown: &str) -> Result<String> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut html = String::new();
+    render_blocks(&lines, false, &mut html);
+    Ok(sanitize_html(&html))
+}
+
+/// Visible text of Markdown, with blocks separated by blank lines
+///
+/// Markup such as heading markers, emphasis and link destinations is removed
+/// and only the text a reader would see is kept.
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut html = String::new();
+    render_blocks(&lines, false, &mut html);
+    html_to_plain_text(&html)
+}
+
+/// Visible text of HTML, with blocks separated by blank lines
+///
+/// Scripts, styles and similar elements are dropped along with their text.
+pub fn html_to_plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut skipped_depth = 0usize;
+    for token in HtmlTokens::new(html) {
+        match token {
+            HtmlToken::Open { name, self_closing: false, .. } if SKIPPED_ELEMENTS.contains(&name.as_str()) => {
+                skipped_depth += 1;
+            }
+            HtmlToken::Close(name) if SKIPPED_ELEMENTS.contains(&name.as_str()) => {
+                skipped_depth = skipped_depth.saturating_sub(1);
+            }
+            _ if skipped_depth > 0 => {}
+            HtmlToken::Text(run) => text.push_str(&run),
+            HtmlToken::Open { name, .. } | HtmlToken::Close(name) if is_text_block(&name) => text.push_str("\n\n"),
+            HtmlToken::Open { name, .. } if name == "br" => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Replace each `<img>` in HTML with a text placeholder naming its alt text
+///
+/// The rest of the markup is passed through untouched.
+pub fn replace_images_with_placeholders(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut tokens = HtmlTokens::new(html);
+    let mut start = 0;
+
+    while let Some(token) = tokens.next() {
+        let end = tokens.position();
+        match token {
+            HtmlToken::Open { name, attributes, .. } if name == "img" => {
+                let placeholder = match attribute(&attributes, "alt").map(str::trim).filter(|alt| !alt.is_empty()) {
+                    Some(alt) => format!("[Image: {}]", alt),
+                    None => "[Image]".to_string(),
+                };
+                output.push_str(&format!("<span class=\"image-placeholder\">{}</span>", escape_html(&placeholder)));
+            }
+            _ => output.push_str(&html[start..end]),
+        }
+        start = end;
+    }
+    output
+}
+
+/// Elements whose text stands apart from the text around it
+fn is_text_block(name: &str) -> bool {
+    BLOCK_ELEMENTS.contains(&name)
+        || matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "li" | "blockquote" | "pre" | "hr")
+}
+
+/// Convert `content` from one content type to another
+///
+/// Only HTML and Markdown can be converted into each other; converting to the
+/// same type returns the content unchanged.
+pub fn convert_content(content: &str, from: &ContentType, to: &ContentType) -> Result<String> {
+    match (from, to) {
+        _ if from == to => Ok(content.to_string()),
+        (ContentType::Html, ContentType::Markdown) => html_to_markdown(content),
+        (ContentType::Markdown, ContentType::Html) => markdown_to_html(content),
+        _ => Err(WritemagicError::validation(format!("Cannot convert {} content to {}", from, to))),
+    }
+}
+
+struct ListLevel {
+    ordered: bool,
+    next_number: u64,
+    /// Width of the item marker, which continuation lines are indented by
+    indent: usize,
+}
+
+/// An inline element awaiting its closing tag
+struct InlineFrame {
+    tag: String,
+    close: String,
+}
+
+/// Markdown output with lazy line breaks, so block boundaries collapse
+/// instead of piling up blank lines
+#[derive(Default)]
+struct MarkdownWriter {
+    out: String,
+    /// Line breaks owed before the next output: 1 ends a line, 2 ends a block
+    pending_breaks: usize,
+    pending_hard_break: bool,
+    pending_space: bool,
+    /// Quote depth of the last written line, which a blank line continues
+    written_quote_depth: usize,
+    /// Block marker such as `## ` or `- ` to write at the start of the next line
+    pending_marker: Option<String>,
+    /// Opening inline markers, written only once their content arrives
+    pending_open: String,
+    quote_depth: usize,
+    lists: Vec<ListLevel>,
+    inline: Vec<InlineFrame>,
+    /// Raw text of the `<pre>` block being collected, with its language
+    pre: Option<(String, String)>,
+    skipping: Option<String>,
+}
+
+impl MarkdownWriter {
+    fn open(&mut self, name: &str, attributes: &[(String, String)]) {
+        if self.skipping.is_some() {
+            return;
+        }
+        if SKIPPED_ELEMENTS.contains(&name) {
+            self.skipping = Some(name.to_string());
+            return;
+        }
+        if let Some((_, language)) = &mut self.pre {
+            if name == "code" && language.is_empty() {
+                *language = code_language(attributes);
+            }
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.start_block();
+                let level = name[1..].parse().unwrap_or(1);
+                self.pending_marker = Some(format!("{} ", "#".repeat(level)));
+            }
+            "ul" | "ol" => {
+                self.close_inline();
+                if self.lists.is_empty() {
+                    self.request_breaks(2);
+                } else {
+                    self.request_breaks(1);
+                }
+                let start = attribute(attributes, "start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                self.lists.push(ListLevel { ordered: name == "ol", next_number: start, indent: 2 });
+            }
+            "li" => {
+                self.close_inline();
+                self.request_breaks(1);
+                if self.lists.is_empty() {
+                    self.lists.push(ListLevel { ordered: false, next_number: 1, indent: 2 });
+                }
+                let level = self.lists.last_mut().expect("list level pushed above");
+                let marker = if level.ordered {
+                    level.next_number += 1;
+                    format!("{}. ", level.next_number - 1)
+                } else {
+                    "- ".to_string()
+                };
+                level.indent = marker.len();
+                self.pending_marker = Some(marker);
+            }
+            "blockquote" => {
+                self.start_block();
+                self.quote_depth += 1;
+            }
+            "pre" => {
+                self.start_block();
+                self.pre = Some((String::new(), String::new()));
+            }
+            "hr" => {
+                self.start_block();
+                self.write("---");
+                self.request_breaks(2);
+            }
+            "br" => {
+                self.request_breaks(1);
+                self.pending_hard_break = true;
+            }
+            "img" => {
+                let alt = attribute(attributes, "alt").unwrap_or_default();
+                if let Some(src) = attribute(attributes, "src").filter(|src| is_safe_url(src)) {
+                    self.flush_space();
+                    self.write(&format!("![{}]({})", escape_markdown(alt), link_destination(src)));
+                }
+            }
+            "a" => match attribute(attributes, "href").filter(|href| is_safe_url(href)) {
+                Some(href) => self.open_inline(name, "[", format!("]({})", link_destination(href))),
+                None => self.open_inline(name, "", String::new()),
+            },
+            "strong" | "b" => self.open_inline(name, "**", "**".to_string()),
+            "em" | "i" => self.open_inline(name, "*", "*".to_string()),
+            "del" | "s" | "strike" => self.open_inline(name, "~~", "~~".to_string()),
+            "code" | "kbd" | "samp" | "tt" => self.open_inline(name, "`", "`".to_string()),
+            "tr" => {
+                self.close_inline();
+                self.request_breaks(1);
+            }
+            "td" | "th" => self.pending_space = true,
+            _ if BLOCK_ELEMENTS.contains(&name) => self.start_block(),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if let Some(skipped) = &self.skipping {
+            if skipped == name {
+                self.skipping = None;
+            }
+            return;
+        }
+        if self.pre.is_some() {
+            if name == "pre" {
+                self.finish_pre();
+            }
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.start_block(),
+            "ul" | "ol" => {
+                self.close_inline();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.request_breaks(2);
+                } else {
+                    self.request_breaks(1);
+                }
+            }
+            "li" => {
+                self.close_inline();
+                self.pending_marker = None;
+                self.request_breaks(1);
+            }
+            "blockquote" => {
+                self.start_block();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            "a" | "strong" | "b" | "em" | "i" | "del" | "s" | "strike" | "code" | "kbd" | "samp" | "tt" => {
+                self.close_inline_tag(name)
+            }
+            "td" | "th" => self.pending_space = true,
+            "tr" => {
+                self.close_inline();
+                self.request_breaks(1);
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => self.start_block(),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skipping.is_some() {
+            return;
+        }
+        if let Some((raw, _)) = &mut self.pre {
+            raw.push_str(text);
+            return;
+        }
+
+        let in_code = self.inline.iter().any(|frame| frame.close == "`");
+        for (index, word) in text.split(|c: char| c.is_whitespace() && c != '\u{a0}').enumerate() {
+            if index > 0 {
+                self.pending_space = true;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            self.flush_space();
+            let word = if in_code {
+                word.replace('`', "'")
+            } else if self.starts_line() {
+                escape_line_start(&escape_markdown(word))
+            } else {
+                escape_markdown(word)
+            };
+            self.write(&word);
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.pre.is_some() {
+            self.finish_pre();
+        }
+        self.close_inline();
+
+        let trimmed = self.out.trim_end();
+        if trimmed.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n", trimmed)
+        }
+    }
+
+    fn start_block(&mut self) {
+        self.close_inline();
+        self.pending_marker = None;
+        self.request_breaks(2);
+    }
+
+    fn request_breaks(&mut self, breaks: usize) {
+        self.pending_breaks = self.pending_breaks.max(breaks);
+        self.pending_space = false;
+    }
+
+    fn open_inline(&mut self, tag: &str, open: &str, close: String) {
+        self.pending_open.push_str(open);
+        self.inline.push(InlineFrame { tag: tag.to_string(), close });
+    }
+
+    fn close_inline_tag(&mut self, tag: &str) {
+        if let Some(position) = self.inline.iter().rposition(|frame| frame.tag == tag) {
+            while self.inline.len() > position {
+                self.close_innermost();
+            }
+        }
+    }
+
+    fn close_inline(&mut self) {
+        while !self.inline.is_empty() {
+            self.close_innermost();
+        }
+    }
+
+    fn close_innermost(&mut self) {
+        let Some(frame) = self.inline.pop() else {
+            return;
+        };
+        if frame.close.is_empty() {
+            return;
+        }
+        // An element that never received content is dropped rather than
+        // written as an empty marker pair
+        let open = match frame.tag.as_str() {
+            "a" => "[",
+            "strong" | "b" => "**",
+            "em" | "i" => "*",
+            "del" | "s" | "strike" => "~~",
+            _ => "`",
+        };
+        if self.pending_open.ends_with(open) {
+            self.pending_open.truncate(self.pending_open.len() - open.len());
+        } else {
+            self.write(&frame.close);
+        }
+    }
+
+    /// Whether the next write begins a line, where Markdown reads block syntax
+    fn starts_line(&self) -> bool {
+        (self.out.is_empty() || self.pending_breaks > 0) && self.pending_open.is_empty()
+    }
+
+    fn flush_space(&mut self) {
+        if self.pending_space && !self.out.is_empty() && self.pending_breaks == 0 {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+    }
+
+    /// Write inline output, emitting owed line breaks, prefixes and markers first
+    fn write(&mut self, text: &str) {
+        let mut at_line_start = self.out.is_empty();
+        if self.pending_breaks > 0 {
+            if !self.out.is_empty() {
+                if self.pending_hard_break && self.pending_breaks == 1 {
+                    self.out.push('\\');
+                }
+                for _ in 0..self.pending_breaks {
+                    self.out.push('\n');
+                }
+                let blank_depth = self.quote_depth.min(self.written_quote_depth);
+                if self.pending_breaks > 1 && blank_depth > 0 {
+                    // Keep the blank line inside the quote it separates
+                    let blank_at = self.out.len() - 1;
+                    self.out.insert_str(blank_at, "> ".repeat(blank_depth).trim_end());
+                }
+            }
+            self.pending_breaks = 0;
+            at_line_start = true;
+        }
+        self.pending_hard_break = false;
+
+        if at_line_start {
+            let prefix = self.line_prefix();
+            self.out.push_str(&prefix);
+            if let Some(marker) = self.pending_marker.take() {
+                self.out.push_str(&marker);
+            }
+            self.written_quote_depth = self.quote_depth;
+        }
+
+        let open = std::mem::take(&mut self.pending_open);
+        self.out.push_str(&open);
+        self.out.push_str(text);
+    }
+
+    fn line_prefix(&self) -> String {
+        // A marker line is indented to its parent item's content; other lines
+        // continue the innermost item
+        let levels = if self.pending_marker.is_some() && !self.lists.is_empty() {
+            &self.lists[..self.lists.len() - 1]
+        } else {
+            &self.lists[..]
+        };
+        let indent: usize = levels.iter().map(|level| level.indent).sum();
+        format!("{}{}", "> ".repeat(self.quote_depth), " ".repeat(indent))
+    }
+
+    fn finish_pre(&mut self) {
+        let Some((raw, language)) = self.pre.take() else {
+            return;
+        };
+        let raw = raw.strip_prefix('\n').unwrap_or(&raw);
+        let raw = raw.trim_end_matches(['\n', '\r']);
+
+        let fence = if raw.contains("```") { "~~~~" } else { "```" };
+        self.write(&format!("{}{}", fence, language));
+        for line in raw.lines() {
+            self.request_breaks(1);
+            // Blank code lines still need their prefix
+            self.write("");
+            self.out.push_str(line);
+        }
+        self.request_breaks(1);
+        self.write(fence);
+        self.request_breaks(2);
+    }
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| attribute == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Language of a `<code class="language-rust">` element, if any
+fn code_language(attributes: &[(String, String)]) -> String {
+    attribute(attributes, "class")
+        .unwrap_or_default()
+        .split_whitespace()
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn link_destination(url: &str) -> String {
+    let url = url.trim();
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Escape characters that Markdown would read as inline syntax
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~') {
+            escaped.push('\\');
+        }
+        escaped.push(if c == '\u{a0}' { ' ' } else { c });
+    }
+    escaped
+}
+
+/// Escape a word that would otherwise start a heading, quote or list item
+fn escape_line_start(word: &str) -> String {
+    if word.starts_with(['#', '>', '-', '+', '=']) {
+        return format!("\\{}", word);
+    }
+    let digits = word.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && word[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &word[..digits], &word[digits..]);
+    }
+    word.to_string()
+}
+
+fn heading_level(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        return Some((level, ""));
+    }
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    // A closing run of `#` is dropped only when set off by a space
+    let text = rest.trim();
+    let unclosed = text.trim_end_matches('#');
+    if unclosed.is_empty() || unclosed.ends_with(' ') {
+        Some((level, unclosed.trim_end()))
+    } else {
+        Some((level, text))
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_'].iter().any(|&marker| compact.chars().all(|c| c == marker))
+}
+
+fn is_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    ["```", "~~~"].into_iter().find(|fence| trimmed.starts_with(fence)).map(|fence| {
+        let run = trimmed.chars().take_while(|&c| c == fence.as_bytes()[0] as char).count();
+        &trimmed[..run]
+    })
+}
+
+/// A list item marker: whether it is ordered, its number, and the content width
+fn list_marker(line: &str) -> Option<(bool, u64, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+
+    if let Some(after) = rest.strip_prefix(['-', '*', '+']) {
+        if after.is_empty() || after.starts_with(' ') {
+            return (!is_rule(line)).then_some((false, 1, indent + 2));
+        }
+        return None;
+    }
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let after = &rest[digits..];
+    let after = after.strip_prefix(['.', ')'])?;
+    if !after.is_empty() && !after.starts_with(' ') {
+        return None;
+    }
+    let number = rest[..digits].parse().ok()?;
+    Some((true, number, indent + digits + 2))
+}
+
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    heading_level(trimmed).is_some()
+        || is_fence(line).is_some()
+        || trimmed.starts_with('>')
+        || is_rule(line)
+        || list_marker(line).is_some()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn render_blocks(lines: &[&str], tight: bool, html: &mut String) {
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            index += 1;
+        } else if let Some(fence) = is_fence(line) {
+            let language = trimmed[fence.len()..].trim();
+            let mut code = Vec::new();
+            index += 1;
+            while index < lines.len() && !lines[index].trim_start().starts_with(fence) {
+                code.push(lines[index]);
+                index += 1;
+            }
+            index += 1;
+
+            if language.is_empty() {
+                html.push_str("<pre><code>");
+            } else {
+                html.push_str(&format!("<pre><code class=\"language-{}\">", escape_html(language)));
+            }
+            html.push_str(&escape_html(&code.join("\n")));
+            html.push_str("</code></pre>\n");
+        } else if let Some((level, text)) = heading_level(trimmed) {
+            html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, render_inline(text)));
+            index += 1;
+        } else if is_rule(line) {
+            html.push_str("<hr>\n");
+            index += 1;
+        } else if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while index < lines.len() && lines[index].trim_start().starts_with('>') {
+                let content = &lines[index].trim_start()[1..];
+                quoted.push(content.strip_prefix(' ').unwrap_or(content));
+                index += 1;
+            }
+            html.push_str("<blockquote>\n");
+            render_blocks(&quoted, false, html);
+            html.push_str("</blockquote>\n");
+        } else if let Some((ordered, start, _)) = list_marker(line) {
+            index = render_list(lines, index, ordered, start, html);
+        } else {
+            let mut paragraph = vec![trimmed];
+            index += 1;
+            while index < lines.len() && !lines[index].trim().is_empty() && !starts_block(lines[index]) {
+                paragraph.push(lines[index].trim_start());
+                index += 1;
+            }
+
+            let text = render_inline(&paragraph.join("\n"));
+            if tight {
+                html.push_str(&text);
+                html.push('\n');
+            } else {
+                html.push_str(&format!("<p>{}</p>\n", text));
+            }
+        }
+    }
+}
+
+/// Render the list starting at `start_index`, returning the index after it
+fn render_list(lines: &[&str], start_index: usize, ordered: bool, start: u64, html: &mut String) -> usize {
+    let list_indent = indentation(lines[start_index]);
+    let mut items: Vec<Vec<&str>> = Vec::new();
+    let mut loose = false;
+    let mut content_indent = list_indent + 2;
+    let mut index = start_index;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let marker = list_marker(line).filter(|&(item_ordered, _, _)| item_ordered == ordered);
+
+        match marker {
+            Some((_, _, width)) if indentation(line) == list_indent => {
+                content_indent = width;
+                let content = line.get(width..).unwrap_or("");
+                items.push(vec![content]);
+                index += 1;
+            }
+            _ if line.trim().is_empty() => {
+                // A blank line continues the list only if more of it follows
+                let next = lines[index + 1..].iter().find(|next| !next.trim().is_empty());
+                match next {
+                    Some(next) if indentation(next) > list_indent
+                        || (indentation(next) == list_indent && list_marker(next).is_some_and(|(o, _, _)| o == ordered)) =>
+                    {
+                        loose = true;
+                        if let Some(item) = items.last_mut() {
+                            item.push("");
+                        }
+                        index += 1;
+                    }
+                    _ => break,
+                }
+            }
+            _ if indentation(line) > list_indent => {
+                let content = if indentation(line) >= content_indent {
+                    &line[content_indent.min(indentation(line))..]
+                } else {
+                    line.trim_start()
+                };
+                if let Some(item) = items.last_mut() {
+                    item.push(content);
+                }
+                index += 1;
+            }
+            _ if !starts_block(line) && items.last().is_some_and(|item| item.last().is_some_and(|l| !l.is_empty())) => {
+                // Lazy paragraph continuation
+                if let Some(item) = items.last_mut() {
+                    item.push(line.trim_start());
+                }
+                index += 1;
+            }
+            _ => break,
+        }
+    }
+
+    if ordered && start != 1 {
+        html.push_str(&format!("<ol start=\"{}\">\n", start));
+    } else {
+        html.push_str(if ordered { "<ol>\n" } else { "<ul>\n" });
+    }
+    for item in items {
+        html.push_str("<li>");
+        let mut content = String::new();
+        render_blocks(&item, !loose, &mut content);
+        html.push_str(content.trim_end());
+        html.push_str("</li>\n");
+    }
+    html.push_str(if ordered { "</ol>\n" } else { "</ul>\n" });
+
+    index
+}
+
+/// Find `delimiter` in `text` from `from`, skipping escaped characters
+fn find_delimiter(text: &str, from: usize, delimiter: &str) -> Option<usize> {
+    let mut index = from;
+    while index < text.len() {
+        let rest = &text[index..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            index += 1 + escaped.chars().next().map_or(0, char::len_utf8);
+        } else if rest.starts_with(delimiter) {
+            return Some(index);
+        } else {
+            index += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Parse `[label](destination)` at `start`, returning label, destination and end
+fn parse_link(text: &str, start: usize) -> Option<(&str, &str, usize)> {
+    let mut depth = 0;
+    let mut label_end = None;
+    let mut index = start;
+    while index < text.len() {
+        let c = text[index..].chars().next()?;
+        match c {
+            '\\' => {
+                index += 1 + text[index + 1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    label_end = Some(index);
+                    break;
+                }
+            }
+            _ => {}
+        }
+        index += c.len_utf8();
+    }
+
+    let label_end = label_end?;
+    let after = &text[label_end + 1..];
+    let destination_start = label_end + 2;
+    if !after.starts_with('(') {
+        return None;
+    }
+
+    if after[1..].starts_with('<') {
+        let close = after.find('>')?;
+        if !after[close + 1..].starts_with(')') {
+            return None;
+        }
+        Some((&text[start + 1..label_end], &after[2..close], label_end + 1 + close + 2))
+    } else {
+        let close = after.find(')')?;
+        Some((&text[start + 1..label_end], text[destination_start..label_end + 1 + close].trim(), label_end + 1 + close + 1))
+    }
+}
+
+fn render_inline(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+        let c = rest.chars().next().expect("index is within text");
+
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            match escaped.chars().next() {
+                Some('\n') => {
+                    html.push_str("<br>\n");
+                    index += 2;
+                }
+                Some(next) if next.is_ascii_punctuation() => {
+                    html.push_str(&escape_html(&next.to_string()));
+                    index += 1 + next.len_utf8();
+                }
+                _ => {
+                    html.push('\\');
+                    index += 1;
+                }
+            }
+            continue;
+        }
+
+        if rest.starts_with("  \n") {
+            html.push_str("<br>\n");
+            index += 3;
+            continue;
+        }
+
+        if c == '`' {
+            let run = rest.chars().take_while(|&c| c == '`').count();
+            let delimiter = &rest[..run];
+            if let Some(close) = rest[run..].find(delimiter).map(|i| i + run) {
+                html.push_str(&format!("<code>{}</code>", escape_html(rest[run..close].trim())));
+                index += close + run;
+            } else {
+                html.push_str(delimiter);
+                index += run;
+            }
+            continue;
+        }
+
+        if rest.starts_with("![") {
+            if let Some((alt, src, end)) = parse_link(text, index + 1) {
+                if is_safe_url(src) {
+                    html.push_str(&format!("<img src=\"{}\" alt=\"{}\">", escape_html(src), escape_html(&unescape(alt))));
+                } else {
+                    html.push_str(&escape_html(&unescape(alt)));
+                }
+                index = end;
+                continue;
+            }
+        }
+
+        if c == '[' {
+            if let Some((label, href, end)) = parse_link(text, index) {
+                if is_safe_url(href) {
+                    html.push_str(&format!("<a href=\"{}\">{}</a>", escape_html(href), render_inline(label)));
+                } else {
+                    html.push_str(&render_inline(label));
+                }
+                index = end;
+                continue;
+            }
+        }
+
+        let emphasis = [("**", "strong"), ("__", "strong"), ("~~", "del"), ("*", "em"), ("_", "em")]
+            .into_iter()
+            .find(|(delimiter, _)| rest.starts_with(delimiter));
+        if let Some((delimiter, tag)) = emphasis {
+            let inner_start = index + delimiter.len();
+            let word_bound = !delimiter.starts_with('_')
+                || !text[..index].chars().next_back().is_some_and(char::is_alphanumeric);
+            let opens = word_bound && text[inner_start..].chars().next().is_some_and(|c| !c.is_whitespace());
+
+            if let Some(close) = find_delimiter(text, inner_start, delimiter).filter(|_| opens) {
+                let inner = &text[inner_start..close];
+                if !inner.is_empty() && !inner.ends_with(char::is_whitespace) {
+                    html.push_str(&format!("<{0}>{1}</{0}>", tag, render_inline(inner)));
+                    index = close + delimiter.len();
+                    continue;
+                }
+            }
+            html.push_str(delimiter);
+            index += delimiter.len();
+            continue;
+        }
+
+        html.push_str(&escape_html(&c.to_string()));
+        index += c.len_utf8();
+    }
+
+    html
+}
+
+/// Drop backslash escapes, for text that is not rendered as inline Markdown
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(char::is_ascii_punctuation) {
+            continue;
+        }
+        unescaped.push(c);
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Element names in document order, ignoring attributes and text
+    fn outline(html: &str) -> Vec<String> {
+        HtmlTokens::new(html)
+            .filter_map(|token| match token {
+                HtmlToken::Open { name, .. } => Some(name),
+                HtmlToken::Close(name) => Some(format!("/{}", name)),
+                HtmlToken::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// Visible text with whitespace collapsed
+    fn text_content(html: &str) -> String {
+        let text: String = HtmlTokens::new(html)
+            .filter_map(|token| match token {
+                HtmlToken::Text(text) => Some(text),
+                _ => Some(" ".to_string()),
+            })
+            .collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_representative_html_converts_to_markdown() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Ignored</title><style>p { color: red; }</style></head>
+<body>
+  <h1>Release notes</h1>
+  <p>Some <em>emphasis</em>, <strong>bold</strong> and <a href="https://example.com/docs">a link</a>.<br>Next line &amp; more.</p>
+  <ul>
+    <li>First</li>
+    <li>Second
+      <ol><li>Nested one</li><li>Nested two</li></ol>
+    </li>
+  </ul>
+  <blockquote><p>Quoted text</p></blockquote>
+  <pre><code class="language-rust">fn main() {
+    println!("hi");
+}
+</code></pre>
+  <p>Inline <code>let x = 1;</code> and 2 * 3.</p>
+  <script>alert("x")</script>
+</body></html>"#;
+
+        let expected = [
+            "# Release notes",
+            "",
+            "Some *emphasis*, **bold** and [a link](https://example.com/docs).\\",
+            "Next line & more.",
+            "",
+            "- First",
+            "- Second",
+            "  1. Nested one",
+            "  2. Nested two",
+            "",
+            "> Quoted text",
+            "",
+            "```rust",
+            "fn main() {",
+            "    println!(\"hi\");",
+            "}",
+            "```",
+            "",
+            "Inline `let x = 1;` and 2 \\* 3.",
+            "",
+        ]
+        .join("\n");
+
+        assert_eq!(html_to_markdown(html).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_round_trip_preserves_structure() {
+        let html = "<h2>Title</h2>\
+<p>Intro with <em>em</em>, <strong>strong</strong>, <del>gone</del> and <a href=\"https://example.com\">link</a>.</p>\
+<ul><li>one</li><li>two<ul><li>nested</li></ul></li></ul>\
+<ol><li>first</li><li>second</li></ol>\
+<blockquote><p>quote</p></blockquote>\
+<hr>\
+<pre><code>let x = 1 &lt; 2;</code></pre>\
+<p>snake_case *literal* [brackets]</p>\
+<p>1. not a list, # not a heading</p>";
+
+        let markdown = html_to_markdown(html).unwrap();
+        let round_tripped = markdown_to_html(&markdown).unwrap();
+
+        assert_eq!(outline(&round_tripped), outline(html), "markdown was:\n{}", markdown);
+        assert_eq!(text_content(&round_tripped), text_content(html));
+    }
+
+    #[test]
+    fn test_malformed_html_converts_without_panicking() {
+        let inputs = [
+            "<p>Unclosed <b>bold <i>italic",
+            "</div></p>stray closers<li>orphan item",
+            "<a href='x'>link <p>split</a> tail",
+            "< not a tag > 3 < 4 && 5 > 2",
+            "<img src=\"unterminated",
+            "<!-- unterminated comment <p>hidden</p>",
+            "&#xFFFFFFFF; &bogus; &amp",
+            "<pre>unclosed\ncode",
+            "<blockquote><blockquote>deep</blockquote>",
+            "<ul><li><ul><li><ol><li>very nested",
+            "<",
+            "",
+            "<😀>emoji tag</😀>",
+        ];
+
+        for input in inputs {
+            let markdown = html_to_markdown(input).unwrap();
+            markdown_to_html(&markdown).unwrap();
+        }
+
+        assert_eq!(html_to_markdown("<p>Unclosed <b>bold <i>italic").unwrap(), "Unclosed **bold *italic***\n");
+        assert_eq!(html_to_markdown("3 < 4 && 5 > 2").unwrap(), "3 < 4 && 5 > 2\n");
+    }
+
+    #[test]
+    fn test_links_with_unsafe_schemes_keep_only_their_text() {
+        let markdown = html_to_markdown("<p><a href=\"javascript:alert(1)\">click</a> <a href=\"https://example.com\">safe</a></p>").unwrap();
+        assert_eq!(markdown, "click [safe](https://example.com)\n");
+
+        let html = markdown_to_html("[click](javascript:alert(1)) ![pic](data:text/html,x) <script>alert(1)</script>").unwrap();
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("data:"));
+        assert!(!html.contains("<script"));
+        assert!(html.contains("click"));
+    }
+
+    #[test]
+    fn test_unsupported_tags_are_stripped_but_text_kept() {
+        let markdown = html_to_markdown("<div><span class=\"x\">kept</span> <font>text</font><iframe>dropped</iframe></div>").unwrap();
+        assert_eq!(markdown, "kept text\n");
+    }
+}
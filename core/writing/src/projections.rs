@@ -0,0 +1,280 @@
+//! Read models rebuilt from writing domain events
+//!
+//! Replaying a document's or project's event stream into an empty repository
+//! should reproduce what the services saved; comparing the two is a quick way
+//! to spot divergence between the event log and repository state.
+
+use crate::entities::{Document, Project};
+use crate::events::{DocumentEvent, ProjectEvent};
+use crate::repositories::{DocumentRepository, ProjectRepository};
+use async_trait::async_trait;
+use std::sync::Arc;
+use writemagic_shared::{DocumentTag, DomainEvent, EntityId, FilePath, ReadModelProjector, Result, WritemagicError};
+
+/// Projects `DocumentEvent`s into a document repository
+pub struct DocumentProjector {
+    documents: Arc<dyn DocumentRepository>,
+}
+
+impl DocumentProjector {
+    pub fn new(documents: Arc<dyn DocumentRepository>) -> Self {
+        Self { documents }
+    }
+
+    async fn load(&self, document_id: &EntityId) -> Result<Document> {
+        self.documents
+            .find_by_id(document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Document {} has events but was never created", document_id)))
+    }
+}
+
+#[async_trait]
+impl ReadModelProjector for DocumentProjector {
+    async fn apply(&self, event: &dyn DomainEvent) -> Result<()> {
+        let Some(event) = event.as_any().downcast_ref::<DocumentEvent>() else {
+            return Ok(());
+        };
+
+        let document = match event {
+            DocumentEvent::DocumentCreated { document_id, title, content, content_type, created_by, created_at } => {
+                let mut document = Document::new(title.clone(), content.clone(), content_type.clone(), *created_by);
+                document.id = *document_id;
+                document.created_at = created_at.clone();
+                document.updated_at = created_at.clone();
+                document
+            }
+            DocumentEvent::DocumentTitleUpdated { document_id, new_title, updated_by, updated_at, .. } => {
+                let mut document = self.load(document_id).await?;
+                document.update_title(new_title.clone(), *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentContentUpdated { document_id, new_content, updated_by, updated_at, .. } => {
+                let mut document = self.load(document_id).await?;
+                document.update_content(new_content.clone(), *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentFilePathSet { document_id, file_path, updated_by, updated_at } => {
+                let mut document = self.load(document_id).await?;
+                document.set_file_path(FilePath::new(file_path.clone())?, *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentDeleted { document_id, deleted_by, deleted_at } => {
+                let mut document = self.load(document_id).await?;
+                document.mark_deleted(*deleted_by);
+                document.deleted_at = Some(deleted_at.clone());
+                document
+            }
+            DocumentEvent::DocumentRestored { document_id, restored_by, restored_at } => {
+                let mut document = self.load(document_id).await?;
+                document.restore(*restored_by);
+                document.updated_at = restored_at.clone();
+                document
+            }
+            DocumentEvent::DocumentTagsUpdated { document_id, new_tags, updated_by, updated_at, .. } => {
+                let mut document = self.load(document_id).await?;
+                let tags = new_tags.iter().map(DocumentTag::new).collect::<Result<Vec<_>>>()?;
+                document.set_tags(tags, *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentLanguageOverridden { document_id, language, updated_by, updated_at } => {
+                let mut document = self.load(document_id).await?;
+                document.set_language_override(language.clone(), *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentFormatConverted { document_id, new_content_type, new_content, updated_by, updated_at, .. } => {
+                let mut document = self.load(document_id).await?;
+                document.convert_content(new_content.clone(), new_content_type.clone(), *updated_by);
+                document.updated_at = updated_at.clone();
+                document
+            }
+            DocumentEvent::DocumentArchived { document_id, archived_by, archived_at } => {
+                let mut document = self.load(document_id).await?;
+                document.archive(*archived_by);
+                document.updated_at = archived_at.clone();
+                document
+            }
+            DocumentEvent::DocumentStatsChanged { document_id, start, removed_len, inserted, updated_by, updated_at, .. } => {
+                let mut document = self.load(document_id).await?;
+                document.apply_content_delta(*start, *removed_len, inserted, *updated_by)?;
+                document.updated_at = updated_at.clone();
+                document
+            }
+        };
+
+        self.documents.save(&document).await?;
+        Ok(())
+    }
+}
+
+/// Projects `ProjectEvent`s into a project repository
+pub struct ProjectProjector {
+    projects: Arc<dyn ProjectRepository>,
+}
+
+impl ProjectProjector {
+    pub fn new(projects: Arc<dyn ProjectRepository>) -> Self {
+        Self { projects }
+    }
+
+    async fn load(&self, project_id: &EntityId) -> Result<Project> {
+        self.projects
+            .find_by_id(project_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Project {} has events but was never created", project_id)))
+    }
+}
+
+#[async_trait]
+impl ReadModelProjector for ProjectProjector {
+    async fn apply(&self, event: &dyn DomainEvent) -> Result<()> {
+        let Some(event) = event.as_any().downcast_ref::<ProjectEvent>() else {
+            return Ok(());
+        };
+
+        let project = match event {
+            ProjectEvent::ProjectCreated { project_id, name, description, created_by, created_at } => {
+                let mut project = Project::new(name.clone(), description.clone(), *created_by);
+                project.id = *project_id;
+                project.created_at = created_at.clone();
+                project.updated_at = created_at.clone();
+                project
+            }
+            ProjectEvent::ProjectNameUpdated { project_id, new_name, updated_by, updated_at, .. } => {
+                let mut project = self.load(project_id).await?;
+                project.update_name(new_name.clone(), *updated_by);
+                project.updated_at = updated_at.clone();
+                project
+            }
+            ProjectEvent::ProjectDescriptionUpdated { project_id, new_description, updated_by, updated_at, .. } => {
+                let mut project = self.load(project_id).await?;
+                project.update_description(new_description.clone(), *updated_by);
+                project.updated_at = updated_at.clone();
+                project
+            }
+            ProjectEvent::ProjectSystemPromptUpdated { project_id, system_prompt, updated_by, updated_at } => {
+                let mut project = self.load(project_id).await?;
+                project.update_system_prompt(system_prompt.clone(), *updated_by);
+                project.updated_at = updated_at.clone();
+                project
+            }
+            ProjectEvent::DocumentAdded { project_id, document_id, added_by, added_at, .. } => {
+                let mut project = self.load(project_id).await?;
+                project.add_document(*document_id, *added_by);
+                project.updated_at = added_at.clone();
+                project
+            }
+            ProjectEvent::DocumentRemoved { project_id, document_id, removed_by, removed_at, .. } => {
+                let mut project = self.load(project_id).await?;
+                project.remove_document(document_id, *removed_by);
+                project.updated_at = removed_at.clone();
+                project
+            }
+            ProjectEvent::ProjectArchived { project_id, archived_by, archived_at, .. } => {
+                let mut project = self.load(project_id).await?;
+                project.archive(*archived_by);
+                project.archived_at = Some(archived_at.clone());
+                project.updated_at = archived_at.clone();
+                project
+            }
+            ProjectEvent::ProjectUnarchived { project_id, unarchived_by, unarchived_at } => {
+                let mut project = self.load(project_id).await?;
+                project.unarchive(*unarchived_by);
+                project.updated_at = unarchived_at.clone();
+                project
+            }
+            ProjectEvent::DocumentsMoved { project_id, from_project_ids, document_ids, moved_by, moved_at } => {
+                for source_id in from_project_ids {
+                    let mut source = self.load(source_id).await?;
+                    source.remove_documents(document_ids, *moved_by);
+                    source.updated_at = moved_at.clone();
+                    self.projects.save(&source).await?;
+                }
+                let mut project = self.load(project_id).await?;
+                project.add_documents(document_ids, *moved_by);
+                project.updated_at = moved_at.clone();
+                project
+            }
+        };
+
+        self.projects.save(&project).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregates::DocumentAggregate;
+    use crate::repositories::InMemoryDocumentRepository;
+    use crate::value_objects::{DocumentContent, DocumentTitle};
+    use writemagic_shared::{ContentType, EventStore, InMemoryEventStore, Repository};
+
+    #[tokio::test]
+    async fn test_document_rebuilt_from_event_stream() {
+        let mut aggregate = DocumentAggregate::new(
+            DocumentTitle::new("Draft").unwrap(),
+            DocumentContent::new("First pass").unwrap(),
+            ContentType::Markdown,
+            None,
+        );
+        aggregate.update_title(DocumentTitle::new("Final").unwrap(), None).unwrap();
+        aggregate.update_content(DocumentContent::new("Second pass, much better").unwrap(), None, None).unwrap();
+        let expected = aggregate.document().clone();
+
+        let store = InMemoryEventStore::<DocumentEvent>::new();
+        let events = aggregate
+            .uncommitted_events()
+            .iter()
+            .map(|event| Box::new(event.clone()) as Box<dyn DomainEvent>)
+            .collect();
+        store.save_events(expected.id, events, 0).await.unwrap();
+        aggregate.mark_events_as_committed();
+
+        // A fresh repository stands in for one that was wiped
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let projector = DocumentProjector::new(repository.clone());
+        assert_eq!(projector.rebuild(&store, expected.id).await.unwrap(), 3);
+
+        let rebuilt = repository.find_by_id(&expected.id).await.unwrap().unwrap();
+        assert_eq!(rebuilt.title, expected.title);
+        assert_eq!(rebuilt.content, expected.content);
+        assert_eq!(rebuilt.word_count, expected.word_count);
+        assert_eq!(rebuilt.version, expected.version);
+        assert_eq!(rebuilt.updated_at, expected.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_tag_updates_replace_the_tags() {
+        let tags = |names: &[&str]| names.iter().map(|name| DocumentTag::new(*name).unwrap()).collect::<Vec<_>>();
+        let mut aggregate = DocumentAggregate::new(
+            DocumentTitle::new("Garden").unwrap(),
+            DocumentContent::new("Peas and beans").unwrap(),
+            ContentType::Markdown,
+            None,
+        );
+        aggregate.add_tags(tags(&["garden", "draft"]), None).unwrap();
+        aggregate.set_tags(tags(&["garden"]), None).unwrap();
+        let expected = aggregate.document().clone();
+
+        let store = InMemoryEventStore::<DocumentEvent>::new();
+        let events = aggregate
+            .uncommitted_events()
+            .iter()
+            .map(|event| Box::new(event.clone()) as Box<dyn DomainEvent>)
+            .collect();
+        store.save_events(expected.id, events, 0).await.unwrap();
+
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        DocumentProjector::new(repository.clone()).rebuild(&store, expected.id).await.unwrap();
+
+        // The removed tag stays removed rather than being merged back in
+        let rebuilt = repository.find_by_id(&expected.id).await.unwrap().unwrap();
+        assert_eq!(rebuilt.tags, tags(&["garden"]));
+    }
+}
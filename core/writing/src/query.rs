@@ -0,0 +1,568 @@
+//! Combined document queries with filtering, sorting and cursor pagination
+//!
+//! A [`DocumentQuery`] is evaluated either in memory over repository contents
+//! or compiled into a single SQL statement by the SQLite repository. Both
+//! paths share the matching and ordering rules defined here, so they return
+//! the same pages for the same data.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use writemagic_shared::{ContentType, DocumentTag, EntityId, Pagination, Result, WritemagicError};
+use crate::entities::Document;
+use crate::repositories::{DocumentRepository, ProjectRepository};
+use crate::tenancy::TenantScope;
+
+/// Page size used when reading documents for in-memory evaluation
+const SCAN_PAGE_SIZE: u32 = 200;
+
+/// How a tag filter with several tags is matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// Documents carrying at least one of the tags
+    #[default]
+    Any,
+    /// Documents carrying every tag
+    All,
+}
+
+/// Field documents are ordered by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSortKey {
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+    WordCount,
+}
+
+impl DocumentSortKey {
+    /// Column holding the sort value in the `documents` table
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::UpdatedAt => "updated_at",
+            Self::CreatedAt => "created_at",
+            Self::Title => "title",
+            Self::WordCount => "word_count",
+        }
+    }
+
+    fn value(&self, document: &Document) -> SortValue {
+        // Timestamps compare in their stored text form, as SQLite does
+        match self {
+            Self::UpdatedAt => SortValue::Text(document.updated_at.to_string()),
+            Self::CreatedAt => SortValue::Text(document.created_at.to_string()),
+            Self::Title => SortValue::Text(document.title.clone()),
+            Self::WordCount => SortValue::Integer(document.word_count as i64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Ascending,
+    #[default]
+    Descending,
+}
+
+impl SortOrder {
+    fn apply(&self, ordering: Ordering) -> Ordering {
+        match self {
+            Self::Ascending => ordering,
+            Self::Descending => ordering.reverse(),
+        }
+    }
+}
+
+/// Sort value of a document, as stored in a cursor
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SortValue {
+    Integer(i64),
+    Text(String),
+}
+
+/// Position after the last document of a page
+///
+/// Cursors are keyset positions: the sort value and id of the last document
+/// returned, so pages stay stable while documents are added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentCursor {
+    pub value: SortValue,
+    pub id: String,
+}
+
+impl DocumentCursor {
+    fn after(document: &Document, sort_key: DocumentSortKey) -> Self {
+        Self {
+            value: sort_key.value(document),
+            id: document.id.to_string(),
+        }
+    }
+
+    /// Opaque, URL-safe form of the cursor
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        json.bytes().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let invalid = || WritemagicError::validation(format!("Invalid document cursor '{}'", encoded));
+
+        if encoded.len() % 2 != 0 || !encoded.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..encoded.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+
+        serde_json::from_slice(&bytes).map_err(|_| invalid())
+    }
+}
+
+/// One page of query results
+#[derive(Debug, Clone)]
+pub struct DocumentPage {
+    pub documents: Vec<Document>,
+    /// Cursor for the following page, or `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Filters, ordering and page window for listing documents
+///
+/// Filters combine with AND. By default only documents that are neither
+/// deleted nor archived are returned, newest update first.
+#[derive(Debug, Clone)]
+pub struct DocumentQuery {
+    pub project_id: Option<EntityId>,
+    pub tags: Vec<DocumentTag>,
+    pub tag_match: TagMatch,
+    pub content_types: Vec<ContentType>,
+    /// Match on the deleted flag, or `None` for both
+    pub deleted: Option<bool>,
+    /// Match on the pinned flag, or `None` for both
+    pub pinned: Option<bool>,
+    /// Match on the archived flag, or `None` for both
+    pub archived: Option<bool>,
+    /// Case-insensitive term searched in titles and content
+    pub text: Option<String>,
+    pub tenant: Option<TenantScope>,
+    pub sort_key: DocumentSortKey,
+    pub sort_order: SortOrder,
+    pub cursor: Option<DocumentCursor>,
+    pub limit: u32,
+}
+
+impl Default for DocumentQuery {
+    fn default() -> Self {
+        Self {
+            project_id: None,
+            tags: Vec::new(),
+            tag_match: TagMatch::Any,
+            content_types: Vec::new(),
+            deleted: Some(false),
+            pinned: None,
+            archived: Some(false),
+            text: None,
+            tenant: None,
+            sort_key: DocumentSortKey::UpdatedAt,
+            sort_order: SortOrder::Descending,
+            cursor: None,
+            limit: Self::DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl DocumentQuery {
+    pub const DEFAULT_LIMIT: u32 = 50;
+    /// Largest page, matching the bound of `Pagination::limit`
+    pub const MAX_LIMIT: u32 = 1000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_project(mut self, project_id: EntityId) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<DocumentTag>, tag_match: TagMatch) -> Self {
+        self.tags = tags;
+        self.tag_match = tag_match;
+        self
+    }
+
+    pub fn with_content_types(mut self, content_types: Vec<ContentType>) -> Self {
+        self.content_types = content_types;
+        self
+    }
+
+    pub fn with_deleted(mut self, deleted: Option<bool>) -> Self {
+        self.deleted = deleted;
+        self
+    }
+
+    pub fn with_pinned(mut self, pinned: Option<bool>) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    pub fn with_archived(mut self, archived: Option<bool>) -> Self {
+        self.archived = archived;
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        let text = text.into();
+        self.text = (!text.trim().is_empty()).then_some(text);
+        self
+    }
+
+    pub fn with_tenant_scope(mut self, scope: TenantScope) -> Self {
+        self.tenant = Some(scope);
+        self
+    }
+
+    pub fn with_sort(mut self, sort_key: DocumentSortKey, sort_order: SortOrder) -> Self {
+        self.sort_key = sort_key;
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Continue after `cursor`, as returned in [`DocumentPage::next_cursor`]
+    pub fn with_cursor(mut self, cursor: &str) -> Result<Self> {
+        self.cursor = Some(DocumentCursor::decode(cursor)?);
+        Ok(self)
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.limit == 0 || self.limit > Self::MAX_LIMIT {
+            return Err(WritemagicError::validation(format!(
+                "Query limit must be between 1 and {}",
+                Self::MAX_LIMIT
+            )));
+        }
+        let cursor_is_integer = matches!(self.cursor, Some(DocumentCursor { value: SortValue::Integer(_), .. }));
+        if self.cursor.is_some() && cursor_is_integer != (self.sort_key == DocumentSortKey::WordCount) {
+            return Err(WritemagicError::validation("Cursor does not belong to the requested sort key"));
+        }
+        Ok(())
+    }
+
+    /// Distinct tags of the tag filter
+    pub fn distinct_tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.tags.iter().map(|tag| tag.as_str()).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// Whether `document` passes every filter except the project filter,
+    /// which needs the project's membership and is checked by the caller
+    pub fn matches(&self, document: &Document) -> bool {
+        let tags = self.distinct_tags();
+        let has_tag = |tag: &&str| document.tags.iter().any(|t| t.as_str() == *tag);
+        let tags_match = tags.is_empty() || match self.tag_match {
+            TagMatch::Any => tags.iter().any(has_tag),
+            TagMatch::All => tags.iter().all(has_tag),
+        };
+
+        tags_match
+            && (self.content_types.is_empty() || self.content_types.contains(&document.content_type))
+            && self.deleted.map_or(true, |deleted| document.is_deleted == deleted)
+            && self.pinned.map_or(true, |pinned| document.is_pinned == pinned)
+            && self.archived.map_or(true, |archived| document.is_archived == archived)
+            && self.tenant.as_ref().map_or(true, |scope| scope.contains(document.tenant_id.as_deref()))
+            && self.text.as_deref().map_or(true, |text| {
+                // Mirrors SQLite LIKE, which ignores case for ASCII only
+                let term = text.to_ascii_lowercase();
+                document.title.to_ascii_lowercase().contains(&term)
+                    || document.content.to_ascii_lowercase().contains(&term)
+            })
+    }
+
+    /// Order of two documents under the query's sort, ties broken by id
+    pub fn compare(&self, a: &Document, b: &Document) -> Ordering {
+        let ordering = self.sort_key.value(a).cmp(&self.sort_key.value(b))
+            .then_with(|| a.id.to_string().cmp(&b.id.to_string()));
+        self.sort_order.apply(ordering)
+    }
+
+    fn is_after_cursor(&self, document: &Document) -> bool {
+        match &self.cursor {
+            Some(cursor) => {
+                let ordering = self.sort_key.value(document).cmp(&cursor.value)
+                    .then_with(|| document.id.to_string().cmp(&cursor.id));
+                self.sort_order.apply(ordering) == Ordering::Greater
+            }
+            None => true,
+        }
+    }
+
+    /// Filter, sort and page `documents`, restricted to `project_documents`
+    /// when the query has a project filter
+    pub fn apply(&self, documents: Vec<Document>, project_documents: Option<&HashSet<EntityId>>) -> DocumentPage {
+        let mut matching: Vec<Document> = documents
+            .into_iter()
+            .filter(|document| match (&self.project_id, project_documents) {
+                (Some(_), Some(members)) => members.contains(&document.id),
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .filter(|document| self.matches(document) && self.is_after_cursor(document))
+            .collect();
+        matching.sort_by(|a, b| self.compare(a, b));
+
+        self.page(matching)
+    }
+
+    /// Cut up to `limit + 1` ordered documents down to a page
+    pub fn page(&self, mut documents: Vec<Document>) -> DocumentPage {
+        let has_more = documents.len() > self.limit as usize;
+        documents.truncate(self.limit as usize);
+
+        let next_cursor = match documents.last() {
+            Some(last) if has_more => Some(DocumentCursor::after(last, self.sort_key).encode()),
+            _ => None,
+        };
+        DocumentPage { documents, next_cursor }
+    }
+
+    /// Evaluate the query over the contents of generic repositories
+    pub async fn execute_in_memory(
+        &self,
+        documents: &dyn DocumentRepository,
+        projects: &dyn ProjectRepository,
+    ) -> Result<DocumentPage> {
+        self.validate()?;
+
+        let project_documents = match &self.project_id {
+            Some(project_id) => Some(
+                projects
+                    .find_by_id(project_id)
+                    .await?
+                    .map(|project| project.document_ids.into_iter().collect::<HashSet<_>>())
+                    .unwrap_or_default(),
+            ),
+            None => None,
+        };
+
+        Ok(self.apply(load_every_document(documents).await?, project_documents.as_ref()))
+    }
+}
+
+/// Read every document, deleted or not, from a repository
+async fn load_every_document(repository: &dyn DocumentRepository) -> Result<Vec<Document>> {
+    let mut documents = HashMap::new();
+
+    // Repositories differ on whether `find_all` includes deleted documents
+    for deleted in [false, true] {
+        let mut offset = 0;
+        loop {
+            let pagination = Pagination { offset, limit: SCAN_PAGE_SIZE };
+            let page = if deleted {
+                repository.find_deleted(pagination).await?
+            } else {
+                repository.find_all(pagination).await?
+            };
+            let exhausted = page.len() < SCAN_PAGE_SIZE as usize;
+
+            for document in page {
+                documents.insert(document.id, document);
+            }
+            if exhausted {
+                break;
+            }
+            offset += SCAN_PAGE_SIZE;
+        }
+    }
+
+    Ok(documents.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Project;
+    use crate::repositories::{InMemoryDocumentRepository, InMemoryProjectRepository};
+    use writemagic_shared::Repository;
+
+    fn tags(names: &[&str]) -> Vec<DocumentTag> {
+        names.iter().map(|name| DocumentTag::new(*name).unwrap()).collect()
+    }
+
+    /// Title, content, content type, tags, pinned and deleted flag of a fixture document
+    type Spec = (&'static str, &'static str, ContentType, &'static [&'static str], bool, bool);
+
+    /// Documents and a project holding the first four of them
+    fn fixture() -> (Vec<Document>, Project) {
+        let specs: [Spec; 8] = [
+            ("Chapter One", "The storm began at dusk", ContentType::Markdown, &["draft", "fiction"], true, false),
+            ("Chapter Two", "A quiet morning after the STORM", ContentType::Markdown, &["fiction"], false, false),
+            ("Research notes", "Storm patterns and tides", ContentType::PlainText, &["research"], true, false),
+            ("Outline", "Acts and scenes", ContentType::Markdown, &["draft"], false, false),
+            ("Letters", "Dear reader, more to come", ContentType::PlainText, &["fiction", "draft"], false, false),
+            ("Old draft", "A storm long forgotten", ContentType::Markdown, &["draft"], true, true),
+            ("Glossary", "Terms", ContentType::Html, &[], false, false),
+            ("Appendix", "Tables and figures to follow", ContentType::Markdown, &["research", "draft"], false, false),
+        ];
+
+        let documents: Vec<Document> = specs
+            .iter()
+            .map(|(title, content, content_type, tag_names, pinned, deleted)| {
+                let mut document = Document::new(title.to_string(), content.to_string(), content_type.clone(), None);
+                document.add_tags(tags(tag_names), None);
+                document.set_pinned(*pinned, None);
+                if *deleted {
+                    document.mark_deleted(None);
+                }
+                document
+            })
+            .collect();
+
+        let mut project = Project::new("Novel".to_string(), None, None);
+        for document in &documents[..4] {
+            project.add_document(document.id, None);
+        }
+        (documents, project)
+    }
+
+    async fn in_memory_repositories() -> (InMemoryDocumentRepository, InMemoryProjectRepository, Vec<Document>, Project) {
+        let (documents, project) = fixture();
+        let document_repository = InMemoryDocumentRepository::new();
+        let project_repository = InMemoryProjectRepository::new();
+        for document in &documents {
+            document_repository.save(document).await.unwrap();
+        }
+        project_repository.save(&project).await.unwrap();
+        (document_repository, project_repository, documents, project)
+    }
+
+    fn titles(page: &DocumentPage) -> Vec<&str> {
+        page.documents.iter().map(|document| document.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_filters_combine_across_dimensions() {
+        let (documents, projects, _, project) = in_memory_repositories().await;
+        let by_title = |query: DocumentQuery| query.with_sort(DocumentSortKey::Title, SortOrder::Ascending);
+
+        let query = by_title(DocumentQuery::new().with_project(project.id).with_tags(tags(&["draft", "fiction"]), TagMatch::Any));
+        let page = query.execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(titles(&page), vec!["Chapter One", "Chapter Two", "Outline"]);
+
+        let query = by_title(DocumentQuery::new().with_tags(tags(&["draft", "fiction"]), TagMatch::All));
+        let page = query.execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(titles(&page), vec!["Chapter One", "Letters"]);
+
+        let query = by_title(DocumentQuery::new().with_text("storm").with_pinned(Some(true)));
+        let page = query.execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(titles(&page), vec!["Chapter One", "Research notes"]);
+
+        let query = by_title(DocumentQuery::new()
+            .with_content_types(vec![ContentType::PlainText, ContentType::Html])
+            .with_text("e"));
+        let page = query.execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(titles(&page), vec!["Glossary", "Letters", "Research notes"]);
+
+        let query = by_title(DocumentQuery::new().with_deleted(Some(true)).with_text("STORM"));
+        let page = query.execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(titles(&page), vec!["Old draft"]);
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pages_cover_results_once_in_order() {
+        let (documents, projects, _, _) = in_memory_repositories().await;
+        let query = DocumentQuery::new()
+            .with_sort(DocumentSortKey::WordCount, SortOrder::Descending)
+            .with_limit(3);
+
+        let everything = query.clone().with_limit(DocumentQuery::MAX_LIMIT)
+            .execute_in_memory(&documents, &projects).await.unwrap();
+        assert_eq!(everything.documents.len(), 7);
+        assert!(everything.next_cursor.is_none());
+
+        let mut paged = Vec::new();
+        let mut next = query.clone();
+        loop {
+            let page = next.execute_in_memory(&documents, &projects).await.unwrap();
+            paged.extend(page.documents.iter().map(|document| document.id));
+            match page.next_cursor {
+                Some(cursor) => next = query.clone().with_cursor(&cursor).unwrap(),
+                None => break,
+            }
+        }
+
+        let expected: Vec<EntityId> = everything.documents.iter().map(|document| document.id).collect();
+        assert_eq!(paged, expected);
+    }
+
+    #[test]
+    fn test_invalid_cursor_and_limit_are_rejected() {
+        assert!(DocumentQuery::new().with_cursor("zz").is_err());
+        assert!(DocumentQuery::new().with_limit(0).validate().is_err());
+
+        let title_cursor = DocumentCursor { value: SortValue::Text("Outline".to_string()), id: EntityId::new().to_string() };
+        let query = DocumentQuery::new()
+            .with_sort(DocumentSortKey::WordCount, SortOrder::Ascending)
+            .with_cursor(&title_cursor.encode())
+            .unwrap();
+        assert!(query.validate().is_err());
+    }
+
+    #[cfg(feature = "database")]
+    #[tokio::test]
+    async fn test_sqlite_and_in_memory_queries_return_identical_pages() {
+        use crate::sqlite_repositories::{SqliteDocumentRepository, SqliteProjectRepository};
+        use writemagic_shared::DatabaseManager;
+
+        let (documents, projects, fixture_documents, project) = in_memory_repositories().await;
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let sqlite_documents = SqliteDocumentRepository::new(database.pool().clone());
+        let sqlite_projects = SqliteProjectRepository::new(database.pool().clone());
+        for document in &fixture_documents {
+            sqlite_documents.save(document).await.unwrap();
+        }
+        sqlite_projects.save(&project).await.unwrap();
+
+        let queries = [
+            DocumentQuery::new(),
+            DocumentQuery::new().with_project(project.id).with_sort(DocumentSortKey::Title, SortOrder::Descending),
+            DocumentQuery::new().with_tags(tags(&["draft", "research"]), TagMatch::All),
+            DocumentQuery::new().with_tags(tags(&["fiction", "research"]), TagMatch::Any).with_pinned(Some(false)),
+            DocumentQuery::new().with_text("storm").with_deleted(None).with_sort(DocumentSortKey::CreatedAt, SortOrder::Ascending),
+            DocumentQuery::new().with_content_types(vec![ContentType::Markdown]).with_sort(DocumentSortKey::WordCount, SortOrder::Ascending),
+            DocumentQuery::new().with_text("100%_").with_deleted(None),
+        ];
+
+        for base in queries {
+            for limit in [2, DocumentQuery::MAX_LIMIT] {
+                let mut query = base.clone().with_limit(limit);
+                loop {
+                    let expected = query.execute_in_memory(&documents, &projects).await.unwrap();
+                    let actual = sqlite_documents.query(&query).await.unwrap();
+
+                    let ids = |page: &DocumentPage| page.documents.iter().map(|d| d.id).collect::<Vec<_>>();
+                    assert_eq!(ids(&actual), ids(&expected), "results differ for {:?}", query);
+                    assert_eq!(actual.next_cursor, expected.next_cursor);
+
+                    match expected.next_cursor {
+                        Some(cursor) => query = base.clone().with_limit(limit).with_cursor(&cursor).unwrap(),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Reading time estimates that account for script and code
+
+use serde::{Deserialize, Serialize};
+use writemagic_shared::ContentType;
+use crate::language::WordCountPolicy;
+
+/// Reading speeds used to estimate how long a document takes to read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadingTimeConfig {
+    /// Prose in languages separated by whitespace
+    pub words_per_minute: u32,
+    /// Prose in languages read per character, such as Chinese and Japanese
+    pub characters_per_minute: u32,
+    /// Code, counted in whitespace separated tokens
+    pub code_words_per_minute: u32,
+}
+
+impl Default for ReadingTimeConfig {
+    fn default() -> Self {
+        Self {
+            words_per_minute: 230,
+            characters_per_minute: 400,
+            code_words_per_minute: 100,
+        }
+    }
+}
+
+/// Estimate the reading time of `content` in seconds, rounded up
+///
+/// Code documents are read entirely at the code rate. In Markdown, fenced
+/// code blocks use the code rate and the rest is read as prose.
+pub fn estimate_reading_time(
+    content: &str,
+    content_type: &ContentType,
+    language: &str,
+    config: &ReadingTimeConfig,
+) -> u32 {
+    let (prose, code) = split_code(content, content_type);
+
+    let prose_minutes = match WordCountPolicy::for_language(language) {
+        WordCountPolicy::PerCharacter => {
+            WordCountPolicy::PerCharacter.count(&prose) as f64 / config.characters_per_minute.max(1) as f64
+        }
+        WordCountPolicy::Whitespace => {
+            WordCountPolicy::Whitespace.count(&prose) as f64 / config.words_per_minute.max(1) as f64
+        }
+    };
+    let code_minutes = WordCountPolicy::Whitespace.count(&code) as f64 / config.code_words_per_minute.max(1) as f64;
+
+    ((prose_minutes + code_minutes) * 60.0).ceil() as u32
+}
+
+/// Separate content into prose and code
+fn split_code(content: &str, content_type: &ContentType) -> (String, String) {
+    match content_type {
+        ContentType::Code { .. } | ContentType::Json | ContentType::Yaml => (String::new(), content.to_string()),
+        ContentType::Markdown => {
+            let mut prose = String::new();
+            let mut code = String::new();
+            let mut in_fence = false;
+
+            for line in content.lines() {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    in_fence = !in_fence;
+                    continue;
+                }
+
+                let target = if in_fence { &mut code } else { &mut prose };
+                target.push_str(line);
+                target.push('\n');
+            }
+
+            (prose, code)
+        }
+        ContentType::PlainText | ContentType::Html => (content.to_string(), String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Document;
+    use crate::language::LanguageConfig;
+
+    fn document(content: String, content_type: ContentType) -> Document {
+        let mut document = Document::new("Reading".to_string(), content, content_type, None);
+        document.detect_language(&LanguageConfig::default());
+        document
+    }
+
+    #[test]
+    fn test_english_prose_uses_words_per_minute() {
+        let document = document("the quick brown fox jumps over the lazy dog ".repeat(46), ContentType::PlainText);
+
+        assert_eq!(document.effective_language(), "en");
+        assert_eq!(document.word_count, 414);
+        assert_eq!(document.reading_time(&ReadingTimeConfig::default()), 108);
+    }
+
+    #[test]
+    fn test_chinese_prose_uses_characters_per_minute() {
+        let document = document("我们今天一起在花园里写作。".repeat(40), ContentType::PlainText);
+
+        assert_eq!(document.effective_language(), "zh");
+        assert_eq!(document.reading_time(&ReadingTimeConfig::default()), 72);
+
+        // Counted as whitespace separated words the whole text would be a single word
+        let as_words = estimate_reading_time(&document.content, &document.content_type, "en", &ReadingTimeConfig::default());
+        assert_eq!(as_words, 1);
+    }
+
+    #[test]
+    fn test_code_blocks_use_code_rate() {
+        let prose = "the quick brown fox jumps over the lazy dog ".repeat(23);
+        let code = "total = first + second;\n".repeat(20);
+        let content = format!("{}\n```rust\n{}```\n", prose, code);
+        let config = ReadingTimeConfig::default();
+
+        // 207 prose words at 230 per minute plus 100 code tokens at 100 per minute
+        let markdown = document(content.clone(), ContentType::Markdown);
+        assert_eq!(markdown.reading_time(&config), 114);
+
+        // The same text read entirely as prose is faster
+        let plain = document(content, ContentType::PlainText);
+        assert_eq!(plain.reading_time(&config), 81);
+
+        let source = document(code, ContentType::Code { language: "rust".to_string() });
+        assert_eq!(source.reading_time(&config), 60);
+    }
+
+    #[test]
+    fn test_estimates_are_deterministic_for_a_config() {
+        let document = document("the quick brown fox jumps over the lazy dog ".repeat(46), ContentType::PlainText);
+        let fast = ReadingTimeConfig { words_per_minute: 414, ..ReadingTimeConfig::default() };
+
+        assert_eq!(document.reading_time(&fast), 60);
+        assert_eq!(document.reading_time(&fast), document.reading_time(&fast.clone()));
+        assert_eq!(crate::conversions::DocumentDto::from_document_with_rates(&document, &fast).reading_time_seconds, 60);
+    }
+
+    #[test]
+    fn test_markdown_fences_are_read_as_code() {
+        let (prose, code) = split_code("Intro text\n```rust\nfn main() {}\n```\nOutro", &ContentType::Markdown);
+
+        assert_eq!(prose, "Intro text\nOutro\n");
+        assert_eq!(code, "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_zero_rates_do_not_divide_by_zero() {
+        let config = ReadingTimeConfig { words_per_minute: 0, characters_per_minute: 0, code_words_per_minute: 0 };
+
+        assert_eq!(estimate_reading_time("one two", &ContentType::PlainText, "en", &config), 120);
+    }
+}
@@ -1,24 +1,161 @@
 //! Writing domain repositories
 
 use async_trait::async_trait;
-use writemagic_shared::{EntityId, Pagination, Repository, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use writemagic_shared::{DocumentTag, EntityId, Pagination, Repository, Result, Timestamp, WritemagicError};
 use crate::entities::{Document, Project};
+use crate::fuzzy::rank_fuzzy;
+use crate::links::DocumentLink;
+use crate::locking::DocumentLock;
+use crate::templates::DocumentTemplate;
+use crate::tenancy::TenantScope;
+use crate::versions::DocumentVersion;
+
+/// Why a version-checked save was refused
+#[derive(Debug, thiserror::Error)]
+pub enum ConcurrencyError {
+    /// The stored document is not at the version the caller loaded
+    #[error("Document {document_id} was modified concurrently: expected version {expected}, found {}", .actual.map_or_else(|| "none".to_string(), |v| v.to_string()))]
+    VersionMismatch {
+        document_id: EntityId,
+        expected: u64,
+        actual: Option<u64>,
+    },
+    #[error(transparent)]
+    Repository(#[from] WritemagicError),
+}
+
+impl From<ConcurrencyError> for WritemagicError {
+    fn from(error: ConcurrencyError) -> Self {
+        match error {
+            ConcurrencyError::VersionMismatch { .. } => WritemagicError::conflict(error.to_string()),
+            ConcurrencyError::Repository(error) => error,
+        }
+    }
+}
+
+/// Conflict refusing a version-checked save of project `project_id`
+pub(crate) fn project_version_mismatch(project_id: EntityId, expected: u64, actual: Option<u64>) -> WritemagicError {
+    WritemagicError::conflict(format!(
+        "Project {} was modified concurrently: expected version {}, found {}",
+        project_id,
+        expected,
+        actual.map_or_else(|| "none".to_string(), |v| v.to_string())
+    ))
+}
+
+/// Documents read per page when gathering fuzzy title candidates
+const FUZZY_CANDIDATE_PAGE_SIZE: u32 = 500;
+
+/// Page size used by [`scan_where`] when reading through a repository
+const SCAN_PAGE_SIZE: u32 = 200;
+
+/// Read pages from `fetch` until `window` of the entities passing `keep` is
+/// filled, or every such entity when `window` is `None`
+///
+/// For filters the backend cannot apply in storage, so pages stay full.
+pub(crate) async fn scan_where<T, P, F, Fut>(window: Option<Pagination>, keep: P, mut fetch: F) -> Result<Vec<T>>
+where
+    P: Fn(&T) -> bool,
+    F: FnMut(Pagination) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let (mut skip, take) = match window {
+        Some(pagination) => (pagination.offset as usize, pagination.limit as usize),
+        None => (0, usize::MAX),
+    };
+
+    let mut kept = Vec::new();
+    let mut offset = 0u32;
+    loop {
+        let page = fetch(Pagination { offset, limit: SCAN_PAGE_SIZE }).await?;
+        let exhausted = page.len() < SCAN_PAGE_SIZE as usize;
+
+        for entity in page.into_iter().filter(|entity| keep(entity)) {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            kept.push(entity);
+            if kept.len() >= take {
+                return Ok(kept);
+            }
+        }
+
+        if exhausted {
+            return Ok(kept);
+        }
+        offset = offset.saturating_add(SCAN_PAGE_SIZE);
+    }
+}
 
 /// Document repository interface
 #[async_trait]
 pub trait DocumentRepository: Repository<Document, EntityId> + Send + Sync {
+    /// Save `document` only if the stored copy is still at `expected_version`
+    ///
+    /// The comparison and the write are atomic, so of two writers that loaded
+    /// the same version exactly one succeeds.
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError>;
+
+    /// Find several documents by ID, in the order the IDs are given
+    ///
+    /// IDs without a stored document are skipped rather than reported.
+    async fn find_by_ids(&self, ids: &[EntityId]) -> Result<Vec<Document>> {
+        let mut documents = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(document) = self.find_by_id(id).await? {
+                documents.push(document);
+            }
+        }
+        Ok(documents)
+    }
+
     /// Find documents by project ID
     async fn find_by_project_id(&self, project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>>;
 
+    /// Find live documents carrying `tag`
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>>;
+
     /// Find documents by content type
     async fn find_by_content_type(&self, content_type: &writemagic_shared::ContentType, pagination: Pagination) -> Result<Vec<Document>>;
 
     /// Search documents by title
     async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>>;
 
+    /// Live documents whose title fuzzily matches `query`, best match first, with their scores
+    ///
+    /// For quick-open, where typing a few letters of each word should find
+    /// the document. Scores come from [`crate::fuzzy::fuzzy_match`].
+    async fn search_titles_fuzzy(&self, query: &str, limit: u32) -> Result<Vec<(Document, f32)>> {
+        let mut candidates = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self.find_all(Pagination { offset, limit: FUZZY_CANDIDATE_PAGE_SIZE }).await?;
+            let exhausted = page.len() < FUZZY_CANDIDATE_PAGE_SIZE as usize;
+            candidates.extend(page.into_iter().filter(|document| !document.is_deleted));
+            if exhausted {
+                break;
+            }
+            offset += FUZZY_CANDIDATE_PAGE_SIZE;
+        }
+        Ok(rank_fuzzy(query, candidates, |document| document.title.as_str(), limit))
+    }
+
     /// Search documents by content
     async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>>;
 
+    /// Search titles and content for `query`, most relevant first where the backend can rank
+    ///
+    /// Soft-deleted documents are only included when `include_deleted` is set.
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>>;
+
     /// Find documents created by user
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>>;
 
@@ -30,11 +167,34 @@ pub trait DocumentRepository: Repository<Document, EntityId> + Send + Sync {
 
     /// Get document statistics
     async fn get_statistics(&self) -> Result<DocumentStatistics>;
+
+    /// This repository restricted to `scope` by the backend itself, if it can filter in storage
+    ///
+    /// Otherwise [`crate::tenancy::scope_documents`] filters in memory.
+    fn scoped_to(&self, _scope: &TenantScope) -> Option<Arc<dyn DocumentRepository>> {
+        None
+    }
 }
 
 /// Project repository interface
 #[async_trait]
 pub trait ProjectRepository: Repository<Project, EntityId> + Send + Sync {
+    /// Save `project` only if the stored copy is still at `expected_version`
+    ///
+    /// Like [`DocumentRepository::save_if_version`] the comparison and the
+    /// write are atomic; a refused save is a conflict.
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> Result<Project>;
+
+    /// Find projects, leaving out archived ones unless `include_archived` is set
+    ///
+    /// Pages through `find_all`; backends that can filter in storage override it.
+    async fn find_all_projects(&self, pagination: Pagination, include_archived: bool) -> Result<Vec<Project>> {
+        if include_archived {
+            return self.find_all(pagination).await;
+        }
+        scan_where(Some(pagination), |project: &Project| !project.is_archived, |page| self.find_all(page)).await
+    }
+
     /// Find projects by creator
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>>;
 
@@ -49,6 +209,80 @@ pub trait ProjectRepository: Repository<Project, EntityId> + Send + Sync {
 
     /// Get project statistics
     async fn get_statistics(&self) -> Result<ProjectStatistics>;
+
+    /// This repository restricted to `scope` by the backend itself, if it can filter in storage
+    fn scoped_to(&self, _scope: &TenantScope) -> Option<Arc<dyn ProjectRepository>> {
+        None
+    }
+}
+
+/// Storage for links between documents
+#[async_trait]
+pub trait DocumentLinkRepository: Send + Sync {
+    /// Replace every outgoing link of `source_id` with `links`
+    async fn replace_links(&self, source_id: &EntityId, links: &[DocumentLink]) -> Result<()>;
+
+    /// Links written in `source_id`, in the order they appear
+    async fn find_outgoing(&self, source_id: &EntityId) -> Result<Vec<DocumentLink>>;
+
+    /// Links from other documents that resolve to `target_id`
+    async fn find_backlinks(&self, target_id: &EntityId) -> Result<Vec<DocumentLink>>;
+}
+
+/// Idempotency keys of document creations, each mapped to the document it created
+#[async_trait]
+pub trait IdempotencyKeyRepository: Send + Sync {
+    /// Document created under `key`, unless the key is unknown or expired at `now`
+    async fn find_document(&self, key: &str, now: &Timestamp) -> Result<Option<EntityId>>;
+
+    /// Remember that `key` created `document_id` until `expires_at`, replacing any earlier entry
+    async fn save_key(&self, key: &str, document_id: &EntityId, expires_at: &Timestamp) -> Result<()>;
+
+    /// Forget the keys expired at `now`, returning how many were removed
+    async fn delete_expired(&self, now: &Timestamp) -> Result<u64>;
+}
+
+/// Prior content versions of documents
+#[async_trait]
+pub trait DocumentVersionRepository: Send + Sync {
+    /// Store a snapshot, replacing an earlier one of the same document version
+    async fn save_version(&self, version: &DocumentVersion) -> Result<()>;
+
+    /// Snapshots of a document, newest first
+    async fn find_versions(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<DocumentVersion>>;
+
+    async fn find_version(&self, document_id: &EntityId, version: u64) -> Result<Option<DocumentVersion>>;
+
+    /// Delete all but the newest `keep` snapshots of a document, returning how many were removed
+    async fn prune(&self, document_id: &EntityId, keep: usize) -> Result<u64>;
+}
+
+/// Advisory locks on documents
+#[async_trait]
+pub trait DocumentLockRepository: Send + Sync {
+    /// Store `lock` unless another holder's lock is active at `now`, returning the lock in force
+    async fn acquire(&self, lock: &DocumentLock, now: &Timestamp) -> Result<DocumentLock>;
+
+    /// Lock on a document that is active at `now`
+    async fn find_active(&self, document_id: &EntityId, now: &Timestamp) -> Result<Option<DocumentLock>>;
+
+    /// Remove the lock `holder` has on a document, returning whether there was one
+    async fn release(&self, document_id: &EntityId, holder: &EntityId) -> Result<bool>;
+}
+
+/// Templates new documents are created from
+#[async_trait]
+pub trait DocumentTemplateRepository: Send + Sync {
+    /// Insert or replace a template
+    async fn save(&self, template: &DocumentTemplate) -> Result<DocumentTemplate>;
+
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<DocumentTemplate>>;
+
+    /// All templates, ordered by name
+    async fn list(&self) -> Result<Vec<DocumentTemplate>>;
+
+    /// Delete a template, returning whether there was one
+    async fn delete(&self, id: &EntityId) -> Result<bool>;
 }
 
 /// Document repository statistics
@@ -63,6 +297,44 @@ pub struct DocumentStatistics {
     pub deleted_documents: u64,
 }
 
+impl DocumentStatistics {
+    /// Statistics over `documents`, deleted ones included
+    pub fn from_documents(documents: &[Document]) -> Self {
+        let total_documents = documents.len() as u64;
+        let total_word_count: u64 = documents.iter().map(|doc| doc.word_count as u64).sum();
+        let total_character_count: u64 = documents.iter().map(|doc| doc.character_count as u64).sum();
+        let deleted_documents = documents.iter().filter(|doc| doc.is_deleted).count() as u64;
+
+        let mut documents_by_type = std::collections::HashMap::new();
+        for doc in documents {
+            let type_str = doc.content_type.to_string();
+            *documents_by_type.entry(type_str).or_insert(0) += 1;
+        }
+
+        let average_word_count = if total_documents > 0 {
+            total_word_count as f64 / total_documents as f64
+        } else {
+            0.0
+        };
+
+        let average_character_count = if total_documents > 0 {
+            total_character_count as f64 / total_documents as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            total_documents,
+            total_word_count,
+            total_character_count,
+            documents_by_type,
+            average_word_count,
+            average_character_count,
+            deleted_documents,
+        }
+    }
+}
+
 /// Project repository statistics
 #[derive(Debug, Clone)]
 pub struct ProjectStatistics {
@@ -73,6 +345,43 @@ pub struct ProjectStatistics {
     pub smallest_project_size: u64,
 }
 
+impl ProjectStatistics {
+    /// Statistics over `projects`
+    pub fn from_projects(projects: &[Project]) -> Self {
+        let total_projects = projects.len() as u64;
+        let total_documents_in_projects: u64 = projects
+            .iter()
+            .map(|project| project.document_ids.len() as u64)
+            .sum();
+
+        let average_documents_per_project = if total_projects > 0 {
+            total_documents_in_projects as f64 / total_projects as f64
+        } else {
+            0.0
+        };
+
+        let largest_project_size = projects
+            .iter()
+            .map(|project| project.document_ids.len() as u64)
+            .max()
+            .unwrap_or(0);
+
+        let smallest_project_size = projects
+            .iter()
+            .map(|project| project.document_ids.len() as u64)
+            .min()
+            .unwrap_or(0);
+
+        Self {
+            total_projects,
+            total_documents_in_projects,
+            average_documents_per_project,
+            largest_project_size,
+            smallest_project_size,
+        }
+    }
+}
+
 /// Document search criteria
 #[derive(Debug, Clone)]
 pub struct DocumentSearchCriteria {
@@ -192,6 +501,16 @@ impl InMemoryDocumentRepository {
             base: writemagic_shared::InMemoryRepository::new(),
         }
     }
+
+    /// Every stored document, bypassing the page size limit of `Pagination`
+    async fn find_every(&self) -> Result<Vec<Document>> {
+        self.base.find_all(Pagination { offset: 0, limit: u32::MAX }).await
+    }
+
+    /// Run `update` with exclusive access to every stored document, including deleted ones
+    pub fn update<R>(&self, update: impl FnOnce(&mut HashMap<EntityId, Document>) -> Result<R>) -> Result<R> {
+        self.base.update(update)
+    }
 }
 
 impl Default for InMemoryDocumentRepository {
@@ -229,14 +548,44 @@ impl Repository<Document, EntityId> for InMemoryDocumentRepository {
 
 #[async_trait]
 impl DocumentRepository for InMemoryDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        self.base.save_if(document, |stored| {
+            let actual = stored.map(|stored| stored.version);
+            if actual == Some(expected_version) {
+                Ok(())
+            } else {
+                Err(ConcurrencyError::VersionMismatch {
+                    document_id: document.id,
+                    expected: expected_version,
+                    actual,
+                })
+            }
+        })
+    }
+
     async fn find_by_project_id(&self, _project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
         // For in-memory implementation, return all for now
         // In a real implementation, this would filter by project_id
         self.find_all(pagination).await
     }
 
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>> {
+        let all_docs = self.find_every().await?;
+        let filtered: Vec<Document> = all_docs
+            .into_iter()
+            .filter(|doc| !doc.is_deleted && doc.tags.contains(tag))
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect();
+        Ok(filtered)
+    }
+
     async fn find_by_content_type(&self, content_type: &writemagic_shared::ContentType, pagination: Pagination) -> Result<Vec<Document>> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_docs = self.find_every().await?;
         let filtered: Vec<Document> = all_docs
             .into_iter()
             .filter(|doc| &doc.content_type == content_type)
@@ -247,7 +596,7 @@ impl DocumentRepository for InMemoryDocumentRepository {
     }
 
     async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_docs = self.find_every().await?;
         let query_lower = query.to_lowercase();
         let filtered: Vec<Document> = all_docs
             .into_iter()
@@ -258,8 +607,17 @@ impl DocumentRepository for InMemoryDocumentRepository {
         Ok(filtered)
     }
 
+    async fn search_titles_fuzzy(&self, query: &str, limit: u32) -> Result<Vec<(Document, f32)>> {
+        let mut candidates: Vec<Document> = self.find_every().await?
+            .into_iter()
+            .filter(|doc| !doc.is_deleted)
+            .collect();
+        candidates.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
+        Ok(rank_fuzzy(query, candidates, |doc| doc.title.as_str(), limit))
+    }
+
     async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_docs = self.find_every().await?;
         let query_lower = query.to_lowercase();
         let filtered: Vec<Document> = all_docs
             .into_iter()
@@ -270,8 +628,31 @@ impl DocumentRepository for InMemoryDocumentRepository {
         Ok(filtered)
     }
 
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+        let query_lower = query.trim().to_lowercase();
+        if query_lower.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut matches: Vec<Document> = self.find_every().await?
+            .into_iter()
+            .filter(|doc| include_deleted || !doc.is_deleted)
+            .filter(|doc| {
+                doc.title.to_lowercase().contains(&query_lower)
+                    || doc.content.to_lowercase().contains(&query_lower)
+            })
+            .collect();
+        matches.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
+
+        Ok(matches
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect())
+    }
+
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_docs = self.find_every().await?;
         let filtered: Vec<Document> = all_docs
             .into_iter()
             .filter(|doc| doc.created_by.as_ref() == Some(user_id))
@@ -282,7 +663,7 @@ impl DocumentRepository for InMemoryDocumentRepository {
     }
 
     async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Document>> {
-        let mut all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let mut all_docs = self.find_every().await?;
         all_docs.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
         let filtered: Vec<Document> = all_docs
             .into_iter()
@@ -293,7 +674,7 @@ impl DocumentRepository for InMemoryDocumentRepository {
     }
 
     async fn find_deleted(&self, pagination: Pagination) -> Result<Vec<Document>> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_docs = self.find_every().await?;
         let filtered: Vec<Document> = all_docs
             .into_iter()
             .filter(|doc| doc.is_deleted)
@@ -304,39 +685,7 @@ impl DocumentRepository for InMemoryDocumentRepository {
     }
 
     async fn get_statistics(&self) -> Result<DocumentStatistics> {
-        let all_docs = self.find_all(Pagination::new(0, 10000)?).await?;
-        let total_documents = all_docs.len() as u64;
-        let total_word_count: u64 = all_docs.iter().map(|doc| doc.word_count as u64).sum();
-        let total_character_count: u64 = all_docs.iter().map(|doc| doc.character_count as u64).sum();
-        let deleted_documents = all_docs.iter().filter(|doc| doc.is_deleted).count() as u64;
-
-        let mut documents_by_type = std::collections::HashMap::new();
-        for doc in &all_docs {
-            let type_str = doc.content_type.to_string();
-            *documents_by_type.entry(type_str).or_insert(0) += 1;
-        }
-
-        let average_word_count = if total_documents > 0 {
-            total_word_count as f64 / total_documents as f64
-        } else {
-            0.0
-        };
-
-        let average_character_count = if total_documents > 0 {
-            total_character_count as f64 / total_documents as f64
-        } else {
-            0.0
-        };
-
-        Ok(DocumentStatistics {
-            total_documents,
-            total_word_count,
-            total_character_count,
-            documents_by_type,
-            average_word_count,
-            average_character_count,
-            deleted_documents,
-        })
+        Ok(DocumentStatistics::from_documents(&self.find_every().await?))
     }
 }
 
@@ -352,6 +701,16 @@ impl InMemoryProjectRepository {
             base: writemagic_shared::InMemoryRepository::new(),
         }
     }
+
+    /// Every stored project, bypassing the page size limit of `Pagination`
+    async fn find_every(&self) -> Result<Vec<Project>> {
+        self.base.find_all(Pagination { offset: 0, limit: u32::MAX }).await
+    }
+
+    /// Run `update` with exclusive access to every stored project, including deleted ones
+    pub fn update<R>(&self, update: impl FnOnce(&mut HashMap<EntityId, Project>) -> Result<R>) -> Result<R> {
+        self.base.update(update)
+    }
 }
 
 impl Default for InMemoryProjectRepository {
@@ -389,8 +748,19 @@ impl Repository<Project, EntityId> for InMemoryProjectRepository {
 
 #[async_trait]
 impl ProjectRepository for InMemoryProjectRepository {
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> Result<Project> {
+        self.base.save_if(project, |stored| {
+            let actual = stored.map(|stored| stored.version);
+            if actual == Some(expected_version) {
+                Ok(())
+            } else {
+                Err(project_version_mismatch(project.id, expected_version, actual))
+            }
+        })
+    }
+
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
-        let all_projects = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_projects = self.find_every().await?;
         let filtered: Vec<Project> = all_projects
             .into_iter()
             .filter(|project| project.created_by.as_ref() == Some(user_id))
@@ -401,7 +771,7 @@ impl ProjectRepository for InMemoryProjectRepository {
     }
 
     async fn search_by_name(&self, query: &str, pagination: Pagination) -> Result<Vec<Project>> {
-        let all_projects = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_projects = self.find_every().await?;
         let query_lower = query.to_lowercase();
         let filtered: Vec<Project> = all_projects
             .into_iter()
@@ -413,7 +783,7 @@ impl ProjectRepository for InMemoryProjectRepository {
     }
 
     async fn find_containing_document(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
-        let all_projects = self.find_all(Pagination::new(0, 10000)?).await?;
+        let all_projects = self.find_every().await?;
         let filtered: Vec<Project> = all_projects
             .into_iter()
             .filter(|project| project.document_ids.contains(document_id))
@@ -424,7 +794,7 @@ impl ProjectRepository for InMemoryProjectRepository {
     }
 
     async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Project>> {
-        let mut all_projects = self.find_all(Pagination::new(0, 10000)?).await?;
+        let mut all_projects = self.find_every().await?;
         all_projects.sort_by(|a, b| b.updated_at.0.cmp(&a.updated_at.0));
         let filtered: Vec<Project> = all_projects
             .into_iter()
@@ -435,37 +805,253 @@ impl ProjectRepository for InMemoryProjectRepository {
     }
 
     async fn get_statistics(&self) -> Result<ProjectStatistics> {
-        let all_projects = self.find_all(Pagination::new(0, 10000)?).await?;
-        let total_projects = all_projects.len() as u64;
-        let total_documents_in_projects: u64 = all_projects
-            .iter()
-            .map(|project| project.document_ids.len() as u64)
-            .sum();
+        Ok(ProjectStatistics::from_projects(&self.find_every().await?))
+    }
+}
 
-        let average_documents_per_project = if total_projects > 0 {
-            total_documents_in_projects as f64 / total_projects as f64
+/// In-memory document link repository implementation
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDocumentLinkRepository {
+    links: Arc<RwLock<HashMap<EntityId, Vec<DocumentLink>>>>,
+}
+
+impl InMemoryDocumentLinkRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DocumentLinkRepository for InMemoryDocumentLinkRepository {
+    async fn replace_links(&self, source_id: &EntityId, links: &[DocumentLink]) -> Result<()> {
+        let mut stored = self.links.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        if links.is_empty() {
+            stored.remove(source_id);
         } else {
-            0.0
+            stored.insert(*source_id, links.to_vec());
+        }
+        Ok(())
+    }
+
+    async fn find_outgoing(&self, source_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        let stored = self.links.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(stored.get(source_id).cloned().unwrap_or_default())
+    }
+
+    async fn find_backlinks(&self, target_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        let stored = self.links.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(stored
+            .values()
+            .flatten()
+            .filter(|link| link.target_id.as_ref() == Some(target_id))
+            .cloned()
+            .collect())
+    }
+}
+
+/// In-memory idempotency key repository implementation
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryIdempotencyKeyRepository {
+    keys: Arc<RwLock<HashMap<String, (EntityId, Timestamp)>>>,
+}
+
+impl InMemoryIdempotencyKeyRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyRepository for InMemoryIdempotencyKeyRepository {
+    async fn find_document(&self, key: &str, now: &Timestamp) -> Result<Option<EntityId>> {
+        let keys = self.keys.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(keys
+            .get(key)
+            .filter(|(_, expires_at)| expires_at.0 > now.0)
+            .map(|(document_id, _)| *document_id))
+    }
+
+    async fn save_key(&self, key: &str, document_id: &EntityId, expires_at: &Timestamp) -> Result<()> {
+        let mut keys = self.keys.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        keys.insert(key.to_string(), (*document_id, expires_at.clone()));
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: &Timestamp) -> Result<u64> {
+        let mut keys = self.keys.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        let before = keys.len();
+        keys.retain(|_, (_, expires_at)| expires_at.0 > now.0);
+        Ok((before - keys.len()) as u64)
+    }
+}
+
+/// In-memory document version repository implementation
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDocumentVersionRepository {
+    /// Snapshots per document, oldest first
+    versions: Arc<RwLock<HashMap<EntityId, Vec<DocumentVersion>>>>,
+}
+
+impl InMemoryDocumentVersionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DocumentVersionRepository for InMemoryDocumentVersionRepository {
+    async fn save_version(&self, version: &DocumentVersion) -> Result<()> {
+        let mut versions = self.versions.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        let history = versions.entry(version.document_id).or_default();
+        history.retain(|existing| existing.version != version.version);
+        let position = history.partition_point(|existing| existing.version < version.version);
+        history.insert(position, version.clone());
+        Ok(())
+    }
+
+    async fn find_versions(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<DocumentVersion>> {
+        let versions = self.versions.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(versions
+            .get(document_id)
+            .map(|history| {
+                history
+                    .iter()
+                    .rev()
+                    .skip(pagination.offset as usize)
+                    .take(pagination.limit as usize)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn find_version(&self, document_id: &EntityId, version: u64) -> Result<Option<DocumentVersion>> {
+        let versions = self.versions.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(versions
+            .get(document_id)
+            .and_then(|history| history.iter().find(|existing| existing.version == version))
+            .cloned())
+    }
+
+    async fn prune(&self, document_id: &EntityId, keep: usize) -> Result<u64> {
+        let mut versions = self.versions.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        let Some(history) = versions.get_mut(document_id) else {
+            return Ok(0);
         };
+        let excess = history.len().saturating_sub(keep);
+        history.drain(..excess);
+        Ok(excess as u64)
+    }
+}
 
-        let largest_project_size = all_projects
-            .iter()
-            .map(|project| project.document_ids.len() as u64)
-            .max()
-            .unwrap_or(0);
+/// In-memory document template repository implementation
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDocumentTemplateRepository {
+    templates: Arc<RwLock<HashMap<EntityId, DocumentTemplate>>>,
+}
 
-        let smallest_project_size = all_projects
-            .iter()
-            .map(|project| project.document_ids.len() as u64)
-            .min()
-            .unwrap_or(0);
+impl InMemoryDocumentTemplateRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-        Ok(ProjectStatistics {
-            total_projects,
-            total_documents_in_projects,
-            average_documents_per_project,
-            largest_project_size,
-            smallest_project_size,
-        })
+#[async_trait]
+impl DocumentTemplateRepository for InMemoryDocumentTemplateRepository {
+    async fn save(&self, template: &DocumentTemplate) -> Result<DocumentTemplate> {
+        let mut templates = self.templates.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        templates.insert(template.id, template.clone());
+        Ok(template.clone())
+    }
+
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<DocumentTemplate>> {
+        let templates = self.templates.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(templates.get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<DocumentTemplate>> {
+        let templates = self.templates.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        let mut templates: Vec<DocumentTemplate> = templates.values().cloned().collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        let mut templates = self.templates.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        Ok(templates.remove(id).is_some())
     }
-}
\ No newline at end of file
+}
+
+/// In-memory document lock repository implementation
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDocumentLockRepository {
+    locks: Arc<RwLock<HashMap<EntityId, DocumentLock>>>,
+}
+
+impl InMemoryDocumentLockRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DocumentLockRepository for InMemoryDocumentLockRepository {
+    async fn acquire(&self, lock: &DocumentLock, now: &Timestamp) -> Result<DocumentLock> {
+        let mut locks = self.locks.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        match locks.get(&lock.document_id) {
+            Some(existing) if existing.holder != lock.holder && existing.is_active_at(now) => Ok(existing.clone()),
+            _ => {
+                locks.insert(lock.document_id, lock.clone());
+                Ok(lock.clone())
+            }
+        }
+    }
+
+    async fn find_active(&self, document_id: &EntityId, now: &Timestamp) -> Result<Option<DocumentLock>> {
+        let locks = self.locks.read().map_err(|_| {
+            WritemagicError::internal("Failed to acquire read lock")
+        })?;
+        Ok(locks.get(document_id).filter(|lock| lock.is_active_at(now)).cloned())
+    }
+
+    async fn release(&self, document_id: &EntityId, holder: &EntityId) -> Result<bool> {
+        let mut locks = self.locks.write().map_err(|_| {
+            WritemagicError::internal("Failed to acquire write lock")
+        })?;
+        if locks.get(document_id).is_some_and(|lock| &lock.holder == holder) {
+            locks.remove(document_id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
@@ -1,46 +1,335 @@
 //! Writing domain services
 
 // Remove unused async_trait import
-use writemagic_shared::{EntityId, Result, WritemagicError};
+use writemagic_shared::{current_request_id, ContentType, DocumentTag, DomainEvent, EntityId, EventBus, Result, Timestamp, WritemagicError};
 use crate::aggregates::{DocumentAggregate, ProjectAggregate};
-// Remove unused entity imports
+use crate::entities::{Document, Project};
+use crate::events::{DocumentEvent, ProjectEvent};
+use crate::idempotency::{validate_idempotency_key, IdempotencyConfig};
+use crate::import::{ImportEntry, ImportSummary};
+use crate::language::{detect_language, LanguageConfig};
+use crate::links::{extract_link_references, DocumentLink, LinkConfig};
+use crate::markup::{convert_content, markdown_to_plain_text};
+use crate::reading_time::{estimate_reading_time, ReadingTimeConfig};
+use crate::undo::{UndoConfig, UndoHistory, UndoOutcome};
 use crate::value_objects::{DocumentTitle, DocumentContent, ProjectName, TextSelection};
-use crate::repositories::{DocumentRepository, ProjectRepository};
+use crate::repositories::{
+    ConcurrencyError, DocumentLinkRepository, DocumentLockRepository, DocumentRepository, DocumentTemplateRepository, DocumentVersionRepository,
+    IdempotencyKeyRepository, InMemoryDocumentLinkRepository,
+    InMemoryDocumentTemplateRepository, InMemoryDocumentVersionRepository, InMemoryIdempotencyKeyRepository, ProjectRepository,
+    scan_where,
+};
+use crate::locking::{DocumentLock, DocumentLocks, KeyLocks, MAX_LOCK_TTL};
+use crate::templates::{DocumentTemplate, TemplateError};
+use crate::versions::{DocumentVersion, VersionHistoryConfig};
+use crate::tenancy::{scope_documents, scope_projects, TenantOwned, TenantScope};
+use crate::unit_of_work::{StagedWrite, StagedWriteStore, StagedWrites};
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use writemagic_shared::with_thread_arena;
+#[cfg(not(target_arch = "wasm32"))]
+use writemagic_shared::MetricsCollector;
+
+/// Maximum number of tags suggested for a single document
+const MAX_SUGGESTED_TAGS: usize = 8;
+
+/// Page size used when scanning the repository for bulk operations
+const BULK_SCAN_PAGE_SIZE: u32 = 500;
+
+/// Outcome of a bulk destructive operation
+///
+/// For a dry run, `affected_ids` lists exactly what a real run would change
+/// and nothing has been mutated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkOperationReport {
+    pub dry_run: bool,
+    pub affected_ids: Vec<EntityId>,
+}
+
+impl BulkOperationReport {
+    pub fn count(&self) -> usize {
+        self.affected_ids.len()
+    }
+}
+
+/// Document receiving the content of a merge
+#[derive(Debug, Clone)]
+pub enum MergeTarget {
+    /// Create a new document from the merged content
+    New { title: DocumentTitle, content_type: ContentType },
+    /// Append the merged content to an existing document
+    Existing(EntityId),
+}
 
 /// Document management service
 pub struct DocumentManagementService {
     document_repository: Arc<dyn DocumentRepository>,
+    language_config: LanguageConfig,
+    link_repository: Arc<dyn DocumentLinkRepository>,
+    link_config: LinkConfig,
+    undo_history: Arc<UndoHistory>,
+    project_repository: Option<Arc<dyn ProjectRepository>>,
+    idempotency_keys: Arc<dyn IdempotencyKeyRepository>,
+    idempotency_ttl: chrono::Duration,
+    /// Held per key from lookup to save so concurrent retries can't both create
+    idempotency_locks: KeyLocks<String>,
+    version_repository: Arc<dyn DocumentVersionRepository>,
+    version_history: VersionHistoryConfig,
+    locks: DocumentLocks,
+    /// Holder lock checks are made against in place of each change's editor
+    lock_holder: Option<EntityId>,
+    template_repository: Arc<dyn DocumentTemplateRepository>,
+    event_bus: Option<Arc<dyn EventBus>>,
+    /// Applies batched writes such as imports in one go
+    write_store: Option<Arc<dyn StagedWriteStore>>,
+    /// Tenant stamped on documents written through the write store
+    tenant_id: Option<String>,
+    #[cfg(feature = "ai")]
+    ai_writing_service: Option<Arc<writemagic_ai::AIWritingService>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    metrics: Option<Arc<MetricsCollector>>,
 }
 
 impl DocumentManagementService {
     pub fn new(document_repository: Arc<dyn DocumentRepository>) -> Self {
         Self {
             document_repository,
+            language_config: LanguageConfig::default(),
+            link_repository: Arc::new(InMemoryDocumentLinkRepository::new()),
+            link_config: LinkConfig::default(),
+            undo_history: Arc::new(UndoHistory::default()),
+            project_repository: None,
+            idempotency_keys: Arc::new(InMemoryIdempotencyKeyRepository::new()),
+            idempotency_ttl: IdempotencyConfig::default().ttl(),
+            idempotency_locks: KeyLocks::default(),
+            version_repository: Arc::new(InMemoryDocumentVersionRepository::new()),
+            version_history: VersionHistoryConfig::default(),
+            locks: DocumentLocks::default(),
+            lock_holder: None,
+            template_repository: Arc::new(InMemoryDocumentTemplateRepository::new()),
+            event_bus: None,
+            write_store: None,
+            tenant_id: None,
+            #[cfg(feature = "ai")]
+            ai_writing_service: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            metrics: None,
+        }
+    }
+
+    /// Use `language_config` when detecting document languages
+    pub fn with_language_config(mut self, language_config: LanguageConfig) -> Self {
+        self.language_config = language_config;
+        self
+    }
+
+    /// Store `[[links]]` between documents in `link_repository`
+    pub fn with_link_repository(mut self, link_repository: Arc<dyn DocumentLinkRepository>, link_config: LinkConfig) -> Self {
+        self.link_repository = link_repository;
+        self.link_config = link_config;
+        self
+    }
+
+    /// Keep prior content versions in `version_repository`, bounded by `version_history`
+    pub fn with_version_repository(
+        mut self,
+        version_repository: Arc<dyn DocumentVersionRepository>,
+        version_history: VersionHistoryConfig,
+    ) -> Self {
+        self.version_repository = version_repository;
+        self.version_history = version_history;
+        self
+    }
+
+    /// Keep advisory document locks in `lock_repository`
+    pub fn with_lock_repository(mut self, lock_repository: Arc<dyn DocumentLockRepository>) -> Self {
+        self.locks = DocumentLocks::new(lock_repository);
+        self
+    }
+
+    /// Advisory locks this service checks writes against, for other services writing the same documents
+    pub fn document_locks(&self) -> DocumentLocks {
+        self.locks.clone()
+    }
+
+    /// The same service taking locks as `holder` and letting only `holder`'s locks through
+    ///
+    /// For callers such as a websocket connection whose locks belong to the
+    /// session rather than to the user recorded as each change's editor.
+    pub fn as_lock_holder(&self, holder: EntityId) -> Self {
+        Self {
+            lock_holder: Some(holder),
+            ..self.with_repositories(self.document_repository.clone(), self.project_repository.clone())
+        }
+    }
+
+    /// Keep the templates documents are created from in `template_repository`
+    pub fn with_template_repository(mut self, template_repository: Arc<dyn DocumentTemplateRepository>) -> Self {
+        self.template_repository = template_repository;
+        self
+    }
+
+    /// Bound the undo history kept for each document
+    pub fn with_undo_config(mut self, undo_config: &UndoConfig) -> Self {
+        self.undo_history = Arc::new(UndoHistory::new(undo_config));
+        self
+    }
+
+    /// Projects that imported documents can be added to
+    pub fn with_project_repository(mut self, project_repository: Arc<dyn ProjectRepository>) -> Self {
+        self.project_repository = Some(project_repository);
+        self
+    }
+
+    /// Apply bulk imports through `write_store`, all at once
+    pub fn with_write_store(mut self, write_store: Arc<dyn StagedWriteStore>) -> Self {
+        self.write_store = Some(write_store);
+        self
+    }
+
+    /// Store the idempotency keys of document creations in `idempotency_keys`
+    pub fn with_idempotency_keys(mut self, idempotency_keys: Arc<dyn IdempotencyKeyRepository>, config: &IdempotencyConfig) -> Self {
+        self.idempotency_keys = idempotency_keys;
+        self.idempotency_ttl = config.ttl();
+        self
+    }
+
+    /// Attach an AI writing service used for tag suggestions
+    #[cfg(feature = "ai")]
+    pub fn with_ai_writing_service(mut self, ai_writing_service: Arc<writemagic_ai::AIWritingService>) -> Self {
+        self.ai_writing_service = Some(ai_writing_service);
+        self
+    }
+
+    /// Record create, update and lookup latencies in `metrics`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Publish the events of every saved document change on `event_bus`
+    pub fn with_event_bus(mut self, event_bus: Arc<dyn EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Publish events of a change that has been saved, logging rather than failing on errors
+    async fn publish_events(&self, events: Vec<DocumentEvent>) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+        for event in events {
+            if let Err(e) = event_bus.publish(Box::new(event)).await {
+                log::warn!("Failed to publish document event: {}", e);
+            }
+        }
+    }
+
+    /// Await `future`, timing it as `operation` when metrics are attached
+    async fn timed<T>(&self, operation: &str, future: impl Future<Output = T>) -> T {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(metrics) = &self.metrics {
+            return metrics.measure(operation, future).await;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = operation;
+        future.await
+    }
+
+    /// The same service restricted to the documents of one tenant
+    ///
+    /// Documents of other tenants behave as if they did not exist.
+    pub fn scoped(&self, scope: TenantScope) -> Self {
+        Self {
+            tenant_id: scope.tenant_id().map(str::to_string),
+            ..self.with_repositories(
+                scope_documents(&self.document_repository, scope.clone()),
+                self.project_repository.as_ref().map(|project_repository| scope_projects(project_repository, scope)),
+            )
+        }
+    }
+
+    /// The same service reading and writing through a unit of work's staging repositories
+    ///
+    /// Events are not published, since the writes stay invisible until the
+    /// unit of work commits.
+    pub fn with_staged_repositories(
+        &self,
+        document_repository: Arc<dyn DocumentRepository>,
+        project_repository: Arc<dyn ProjectRepository>,
+    ) -> Self {
+        Self {
+            event_bus: None,
+            ..self.with_repositories(document_repository, Some(project_repository))
+        }
+    }
+
+    fn with_repositories(
+        &self,
+        document_repository: Arc<dyn DocumentRepository>,
+        project_repository: Option<Arc<dyn ProjectRepository>>,
+    ) -> Self {
+        Self {
+            document_repository,
+            project_repository,
+            language_config: self.language_config.clone(),
+            link_repository: self.link_repository.clone(),
+            link_config: self.link_config.clone(),
+            undo_history: self.undo_history.clone(),
+            idempotency_keys: self.idempotency_keys.clone(),
+            idempotency_ttl: self.idempotency_ttl,
+            idempotency_locks: self.idempotency_locks.clone(),
+            version_repository: self.version_repository.clone(),
+            version_history: self.version_history.clone(),
+            locks: self.locks.clone(),
+            lock_holder: self.lock_holder,
+            template_repository: self.template_repository.clone(),
+            event_bus: self.event_bus.clone(),
+            write_store: self.write_store.clone(),
+            tenant_id: self.tenant_id.clone(),
+            #[cfg(feature = "ai")]
+            ai_writing_service: self.ai_writing_service.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            metrics: self.metrics.clone(),
         }
     }
 
     /// Get a document by ID - web handler compatibility method
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn get_document(&self, document_id: &EntityId) -> Result<Option<DocumentAggregate>> {
-        match self.document_repository.find_by_id(document_id).await? {
-            Some(document) => Ok(Some(DocumentAggregate::load_from_document(document))),
-            None => Ok(None),
-        }
+        self.timed("document.find", async {
+            match self.document_repository.find_by_id(document_id).await? {
+                Some(document) => Ok(Some(DocumentAggregate::load_from_document(document))),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
-    /// List documents with pagination - web handler compatibility method
+    /// List documents with pagination, leaving out archived ones - web handler compatibility method
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn list_documents(&self, pagination: writemagic_shared::Pagination) -> Result<Vec<DocumentAggregate>> {
-        let documents = self.document_repository.find_all(pagination).await?;
+        let documents = scan_where(Some(pagination), |document: &Document| !document.is_archived, |page| {
+            self.document_repository.find_all(page)
+        })
+        .await?;
         Ok(documents.into_iter().map(DocumentAggregate::load_from_document).collect())
     }
 
-    /// List documents by creator with pagination
+    /// List documents by creator with pagination, leaving out archived ones
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn list_documents_by_creator(&self, creator_id: &EntityId, pagination: writemagic_shared::Pagination) -> Result<Vec<DocumentAggregate>> {
-        let documents = self.document_repository.find_by_creator(creator_id, pagination).await?;
+        let documents = scan_where(Some(pagination), |document: &Document| !document.is_archived, |page| {
+            self.document_repository.find_by_creator(creator_id, page)
+        })
+        .await?;
         Ok(documents.into_iter().map(DocumentAggregate::load_from_document).collect())
     }
 
     /// Update a full document - web handler compatibility method
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn update_document(
         &self,
         document_id: EntityId,
@@ -53,8 +342,10 @@ impl DocumentManagementService {
             .find_by_id(&document_id)
             .await?
             .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
         // Create aggregate
+        let previous_version = DocumentVersion::snapshot(&document);
         let mut aggregate = DocumentAggregate::load_from_document(document);
 
         // Update title if provided
@@ -63,42 +354,148 @@ impl DocumentManagementService {
         }
 
         // Update content if provided
+        let content_changed = content.is_some();
         if let Some(new_content) = content {
             aggregate.update_content(new_content, None, updated_by)?;
+            aggregate.detect_language(&self.language_config);
         }
 
         // Save changes
-        let updated_document = self.document_repository.save(aggregate.document()).await?;
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+        if content_changed {
+            self.refresh_links(&updated_document).await?;
+            if updated_document.content != previous_version.content {
+                self.record_version(&previous_version).await?;
+                self.undo_history.record_edit(document_id, previous_version.content);
+            }
+        }
         
         // Reload aggregate to ensure version consistency
         let reloaded_aggregate = DocumentAggregate::load_from_document(updated_document);
         let mut final_aggregate = reloaded_aggregate;
         final_aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
 
         Ok(final_aggregate)
     }
 
+    /// Create a document
+    ///
+    /// With an `idempotency_key` seen before and not yet expired, the document
+    /// created under that key is returned instead of a new one.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn create_document(
         &self,
         title: DocumentTitle,
         content: DocumentContent,
         content_type: writemagic_shared::ContentType,
         created_by: Option<EntityId>,
+        idempotency_key: Option<String>,
+    ) -> Result<DocumentAggregate> {
+        self.timed("document.create", async move {
+            let Some(key) = idempotency_key else {
+                return self.insert_document(title, content, content_type, created_by, Vec::new()).await;
+            };
+            validate_idempotency_key(&key)?;
+            // Keys are per creator so one client can't replay another's document
+            let key = match &created_by {
+                Some(creator) => format!("{}:{}", creator, key),
+                None => key,
+            };
+
+            let _guard = self.idempotency_locks.lock(&key).await;
+            let now = Timestamp::now();
+            if let Some(document_id) = self.idempotency_keys.find_document(&key, &now).await? {
+                // A key whose document is gone no longer protects anything
+                if let Some(document) = self.document_repository.find_by_id(&document_id).await? {
+                    return Ok(DocumentAggregate::load_from_document(document));
+                }
+            }
+
+            let aggregate = self.insert_document(title, content, content_type, created_by, Vec::new()).await?;
+            let expires_at = now
+                .0
+                .checked_add_signed(self.idempotency_ttl)
+                .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
+            let expires_at = Timestamp::from_datetime(expires_at);
+            self.idempotency_keys.save_key(&key, &aggregate.document().id, &expires_at).await?;
+            Ok(aggregate)
+        })
+        .await
+    }
+
+    async fn insert_document(
+        &self,
+        title: DocumentTitle,
+        content: DocumentContent,
+        content_type: writemagic_shared::ContentType,
+        created_by: Option<EntityId>,
+        tags: Vec<DocumentTag>,
     ) -> Result<DocumentAggregate> {
         // Create new document aggregate
         let mut aggregate = DocumentAggregate::new(title, content, content_type, created_by);
+        aggregate.detect_language(&self.language_config);
+        if !tags.is_empty() {
+            aggregate.add_tags(tags, created_by)?;
+        }
 
         // Save to repository
+        let events = aggregate.uncommitted_events().to_vec();
         let document = self.document_repository.save(aggregate.document()).await?;
-        
+        self.refresh_links(&document).await?;
+
         // Reload aggregate with updated document to ensure consistency
         let updated_aggregate = DocumentAggregate::load_from_document(document);
         aggregate = updated_aggregate;
         aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
+    }
+
+    /// Store a template that documents can be created from
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn save_template(&self, template: DocumentTemplate) -> Result<DocumentTemplate> {
+        if template.name.trim().is_empty() {
+            return Err(WritemagicError::validation("Template name must not be empty"));
+        }
+        self.template_repository.save(&template).await
+    }
+
+    /// Create a document from a stored template rendered with `variables`
+    ///
+    /// Title and tags come from the template's front-matter, the title falling
+    /// back to the template name, and the content type from the template.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn create_from_template(
+        &self,
+        template_id: EntityId,
+        variables: &HashMap<String, String>,
+        created_by: Option<EntityId>,
+    ) -> std::result::Result<DocumentAggregate, TemplateError> {
+        let template = self
+            .template_repository
+            .find_by_id(&template_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Template {}", template_id)))?;
+        let rendered = template.render(variables).map_err(TemplateError::Invalid)?;
 
+        let title = DocumentTitle::new(rendered.title.unwrap_or_else(|| template.name.clone()))?;
+        let content = DocumentContent::new(rendered.content)?;
+        let aggregate = self
+            .timed("document.create", self.insert_document(title, content, template.content_type, created_by, rendered.tags))
+            .await?;
         Ok(aggregate)
     }
 
+    /// Forget idempotency keys past their TTL, returning how many were removed
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn purge_expired_idempotency_keys(&self) -> Result<u64> {
+        self.idempotency_keys.delete_expired(&Timestamp::now()).await
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
     pub async fn update_document_content(
         &self,
         document_id: EntityId,
@@ -106,307 +503,3223 @@ impl DocumentManagementService {
         selection: Option<TextSelection>,
         updated_by: Option<EntityId>,
     ) -> Result<DocumentAggregate> {
-        // Load existing document
+        self.timed("document.update_content", async move {
+            // Load existing document
+            let document = self.document_repository
+                .find_by_id(&document_id)
+                .await?
+                .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+            let expected_version = document.version;
+
+            // Create aggregate and update content
+            let previous_version = DocumentVersion::snapshot(&document);
+            let mut aggregate = DocumentAggregate::load_from_document(document);
+            aggregate.update_content(content, selection, updated_by)?;
+            aggregate.detect_language(&self.language_config);
+
+            // Save changes
+            let events = aggregate.uncommitted_events().to_vec();
+            let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+            self.refresh_links(&updated_document).await?;
+            if updated_document.content != previous_version.content {
+                self.record_version(&previous_version).await?;
+                self.undo_history.record_edit(document_id, previous_version.content);
+            }
+
+            // Reload aggregate to ensure version consistency and prevent conflicts
+            let reloaded_aggregate = DocumentAggregate::load_from_document(updated_document);
+            aggregate = reloaded_aggregate;
+            aggregate.mark_events_as_committed();
+            self.publish_events(events).await;
+
+            Ok(aggregate)
+        })
+        .await
+    }
+
+    /// Apply a live edit made to version `base_version`, replacing `old_len`
+    /// bytes at `start` with `new_text`
+    ///
+    /// Statistics are adjusted incrementally. Returns the `DocumentStatsChanged`
+    /// event for clients to be told the new counts, or `None` if nothing changed.
+    /// An edit to any other version is refused with a conflict. Keystroke-sized
+    /// edits are not recorded in version or undo history, and links are left
+    /// for [`refresh_document_links`](Self::refresh_document_links) once edits pause.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn apply_content_delta(
+        &self,
+        document_id: EntityId,
+        base_version: u64,
+        start: usize,
+        old_len: usize,
+        new_text: &str,
+        updated_by: Option<EntityId>,
+    ) -> Result<Option<DocumentEvent>> {
+        self.timed("document.apply_content_delta", async move {
+            let document = self.document_repository
+                .find_by_id(&document_id)
+                .await?
+                .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+            if document.version != base_version {
+                return Err(ConcurrencyError::VersionMismatch {
+                    document_id,
+                    expected: base_version,
+                    actual: Some(document.version),
+                }
+                .into());
+            }
+
+            let mut aggregate = DocumentAggregate::load_from_document(document);
+            aggregate.apply_content_delta(start, old_len, new_text, updated_by)?;
+            let Some(event) = aggregate.uncommitted_events().last().cloned() else {
+                return Ok(None);
+            };
+
+            let events = aggregate.uncommitted_events().to_vec();
+            self.save_unlocked(aggregate.document(), base_version, updated_by.as_ref()).await?;
+            aggregate.mark_events_as_committed();
+            self.publish_events(events).await;
+
+            Ok(Some(event))
+        })
+        .await
+    }
+
+    /// Re-read the `[[links]]` in a document's stored content
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn refresh_document_links(&self, document_id: EntityId) -> Result<()> {
         let document = self.document_repository
             .find_by_id(&document_id)
             .await?
             .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        self.refresh_links(&document).await
+    }
 
-        // Create aggregate and update content
-        let mut aggregate = DocumentAggregate::load_from_document(document);
-        aggregate.update_content(content, selection, updated_by)?;
+    /// Lock a document for `holder` for `ttl`, or renew the lock `holder` already has
+    ///
+    /// Fails with a conflict while someone else holds an unexpired lock.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn acquire_lock(
+        &self,
+        document_id: EntityId,
+        holder: EntityId,
+        ttl: std::time::Duration,
+    ) -> Result<DocumentLock> {
+        if ttl.is_zero() || ttl > MAX_LOCK_TTL {
+            return Err(WritemagicError::validation(format!(
+                "Lock TTL must be between 1ms and {}s",
+                MAX_LOCK_TTL.as_secs()
+            )));
+        }
+        self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
 
-        // Save changes
-        let updated_document = self.document_repository.save(aggregate.document()).await?;
-        
-        // Reload aggregate to ensure version consistency and prevent conflicts
-        let reloaded_aggregate = DocumentAggregate::load_from_document(updated_document);
-        aggregate = reloaded_aggregate;
-        aggregate.mark_events_as_committed();
+        let now = Timestamp::now();
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|e| WritemagicError::validation(format!("Invalid lock TTL: {}", e)))?;
+        let lock = DocumentLock {
+            document_id,
+            holder,
+            acquired_at: now.clone(),
+            expires_at: Timestamp::from_datetime(now.0 + ttl),
+        };
 
-        Ok(aggregate)
+        let _guard = self.locks.guard(&document_id).await;
+        let granted = self.locks.repository().acquire(&lock, &now).await?;
+        if granted.holder != holder {
+            return Err(WritemagicError::conflict(format!(
+                "Document {} is locked by {} until {}",
+                document_id, granted.holder, granted.expires_at
+            )));
+        }
+        Ok(granted)
     }
 
-    pub async fn delete_document(
+    /// Release the lock `holder` has on a document, returning whether there was one
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn release_lock(&self, document_id: EntityId, holder: EntityId) -> Result<bool> {
+        self.locks.repository().release(&document_id, &holder).await
+    }
+
+    /// Unexpired lock on a document, if any
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn active_lock(&self, document_id: &EntityId) -> Result<Option<DocumentLock>> {
+        self.locks.repository().find_active(document_id, &Timestamp::now()).await
+    }
+
+    /// Save `document` unless it changed since `loaded_version` or someone other than `editor` holds a lock on it
+    ///
+    /// The lock check and the write happen under the document's guard, so a
+    /// lock can't be granted in between.
+    async fn save_unlocked(&self, document: &Document, loaded_version: u64, editor: Option<&EntityId>) -> Result<Document> {
+        let _guard = self.locks.guard(&document.id).await;
+        self.locks.ensure_not_locked(&document.id, self.lock_holder.as_ref().or(editor)).await?;
+        Ok(self.document_repository.save_if_version(document, loaded_version).await?)
+    }
+
+    /// Keep `version` in the history, dropping the oldest beyond the retention cap
+    async fn record_version(&self, version: &DocumentVersion) -> Result<()> {
+        let keep = self.version_history.max_versions_per_document;
+        if keep == 0 {
+            return Ok(());
+        }
+        self.version_repository.save_version(version).await?;
+        self.version_repository.prune(&version.document_id, keep).await?;
+        Ok(())
+    }
+
+    /// Prior content versions of a document, newest first
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn get_version_history(&self, document_id: EntityId, pagination: writemagic_shared::Pagination) -> Result<Vec<DocumentVersion>> {
+        self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+
+        self.version_repository.find_versions(&document_id, pagination).await
+    }
+
+    /// Set a document's content back to a prior version, as a new version
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn restore_version(
         &self,
         document_id: EntityId,
-        deleted_by: Option<EntityId>,
-    ) -> Result<()> {
-        // Load existing document
+        version: u64,
+        restored_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
         let document = self.document_repository
             .find_by_id(&document_id)
             .await?
             .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-        // Create aggregate and delete
+        let snapshot = self.version_repository
+            .find_version(&document_id, version)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Version {} of document {} not found", version, document_id)))?;
+
+        let previous_version = DocumentVersion::snapshot(&document);
+        let format_changed = document.content_type != snapshot.content_type;
         let mut aggregate = DocumentAggregate::load_from_document(document);
-        aggregate.delete(deleted_by)?;
+        let content = DocumentContent::new(snapshot.content)?;
+        // The snapshot's content is only meaningful in the format it was written in
+        if format_changed {
+            aggregate.convert_format(content, snapshot.content_type, restored_by)?;
+        } else {
+            aggregate.update_content(content, None, restored_by)?;
+        }
+        aggregate.detect_language(&self.language_config);
 
-        // Save changes
-        self.document_repository.save(aggregate.document()).await?;
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, restored_by.as_ref()).await?;
+        self.refresh_links(&updated_document).await?;
+        if format_changed {
+            self.record_version(&previous_version).await?;
+            self.undo_history.clear(&document_id);
+        } else if updated_document.content != previous_version.content {
+            self.record_version(&previous_version).await?;
+            self.undo_history.record_edit(document_id, previous_version.content);
+        }
 
-        Ok(())
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
+        aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
     }
 
-    pub async fn restore_document(
+    /// Rename a document, leaving its content and derived statistics untouched
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn update_document_title(
         &self,
         document_id: EntityId,
-        restored_by: Option<EntityId>,
+        title: DocumentTitle,
+        updated_by: Option<EntityId>,
     ) -> Result<DocumentAggregate> {
         // Load existing document
         let document = self.document_repository
             .find_by_id(&document_id)
             .await?
             .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-        // Create aggregate and restore
+        // Create aggregate and update title
         let mut aggregate = DocumentAggregate::load_from_document(document);
-        aggregate.restore(restored_by)?;
+        aggregate.update_title(title, updated_by)?;
 
         // Save changes
-        let updated_document = self.document_repository.save(aggregate.document()).await?;
-        
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+
         // Reload aggregate to ensure version consistency and prevent conflicts
         let reloaded_aggregate = DocumentAggregate::load_from_document(updated_document);
         aggregate = reloaded_aggregate;
         aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
 
         Ok(aggregate)
     }
-}
 
-/// Project management service
-pub struct ProjectManagementService {
-    project_repository: Arc<dyn ProjectRepository>,
-    document_repository: Arc<dyn DocumentRepository>,
-}
+    /// Mark whether AI output is being streamed into a document
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn set_document_generating(&self, document_id: EntityId, generating: bool) -> Result<DocumentAggregate> {
+        let mut document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-impl ProjectManagementService {
-    pub fn new(
-        project_repository: Arc<dyn ProjectRepository>,
-        document_repository: Arc<dyn DocumentRepository>,
-    ) -> Self {
-        Self {
-            project_repository,
-            document_repository,
+        if document.set_generating(generating) {
+            document = self.document_repository.save_if_version(&document, expected_version).await?;
         }
+
+        Ok(DocumentAggregate::load_from_document(document))
     }
 
-    pub async fn create_project(
-        &self,
-        name: ProjectName,
-        description: Option<String>,
-        created_by: Option<EntityId>,
-    ) -> Result<ProjectAggregate> {
-        // Create new project aggregate
-        let mut aggregate = ProjectAggregate::new(name, description, created_by);
+    /// Revert the most recent content edit of a document
+    ///
+    /// Returns `UndoOutcome::NothingToUndo` once the recorded history is exhausted.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn undo(&self, document_id: EntityId) -> Result<UndoOutcome> {
+        let Some(previous) = self.undo_history.peek_undo(&document_id) else {
+            return Ok(UndoOutcome::NothingToUndo);
+        };
 
-        // Save to repository
-        let project = self.project_repository.save(aggregate.project()).await?;
-        
-        // Reload aggregate with updated project to ensure consistency
-        let updated_aggregate = ProjectAggregate::load_from_project(project);
-        aggregate = updated_aggregate;
-        aggregate.mark_events_as_committed();
+        let (replaced, aggregate) = self.replace_content(document_id, previous).await?;
+        self.undo_history.commit_undo(document_id, replaced);
 
-        Ok(aggregate)
+        Ok(UndoOutcome::Applied {
+            content: aggregate.document().content.clone(),
+            version: aggregate.document().version,
+        })
     }
 
-    pub async fn add_document_to_project(
-        &self,
-        project_id: EntityId,
-        document_id: EntityId,
-        updated_by: Option<EntityId>,
-    ) -> Result<ProjectAggregate> {
-        // Load existing project
-        let project = self.project_repository
-            .find_by_id(&project_id)
-            .await?
-            .ok_or_else(|| WritemagicError::repository("Project not found"))?;
+    /// Reapply the most recently undone content edit of a document
+    ///
+    /// Any new edit after an undo discards what could be redone.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn redo(&self, document_id: EntityId) -> Result<UndoOutcome> {
+        let Some(next) = self.undo_history.peek_redo(&document_id) else {
+            return Ok(UndoOutcome::NothingToRedo);
+        };
 
-        // Verify document exists
+        let (replaced, aggregate) = self.replace_content(document_id, next).await?;
+        self.undo_history.commit_redo(document_id, replaced);
+
+        Ok(UndoOutcome::Applied {
+            content: aggregate.document().content.clone(),
+            version: aggregate.document().version,
+        })
+    }
+
+    /// Swap in `content` without touching the undo history, returning the content it replaced
+    async fn replace_content(&self, document_id: EntityId, content: String) -> Result<(String, DocumentAggregate)> {
         let document = self.document_repository
             .find_by_id(&document_id)
             .await?
             .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-        // Create aggregate and add document
-        let mut aggregate = ProjectAggregate::load_from_project(project);
-        aggregate.add_document(document_id, document.title, updated_by)?;
+        let replaced = document.content.clone();
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.update_content(DocumentContent::new(content)?, None, None)?;
+        aggregate.detect_language(&self.language_config);
 
-        // Save changes
-        let updated_project = self.project_repository.save(aggregate.project()).await?;
-        
-        // Reload aggregate to ensure version consistency and prevent conflicts
-        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
-        aggregate = reloaded_aggregate;
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, None).await?;
+        self.refresh_links(&updated_document).await?;
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
         aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
 
-        Ok(aggregate)
+        Ok((replaced, aggregate))
     }
 
-    pub async fn remove_document_from_project(
+    /// Set or clear a manual language override for a document
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn set_document_language(
         &self,
-        project_id: EntityId,
         document_id: EntityId,
+        language: Option<String>,
         updated_by: Option<EntityId>,
-    ) -> Result<ProjectAggregate> {
-        // Load existing project
-        let project = self.project_repository
-            .find_by_id(&project_id)
+    ) -> Result<DocumentAggregate> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
             .await?
-            .ok_or_else(|| WritemagicError::repository("Project not found"))?;
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-        // Create aggregate and remove document
-        let mut aggregate = ProjectAggregate::load_from_project(project);
-        aggregate.remove_document(&document_id, updated_by)?;
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.set_language_override(language, updated_by)?;
 
-        // Save changes
-        let updated_project = self.project_repository.save(aggregate.project()).await?;
-        
-        // Reload aggregate to ensure version consistency and prevent conflicts
-        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
-        aggregate = reloaded_aggregate;
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
         aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
 
         Ok(aggregate)
     }
 
-    pub async fn update_project_name(
+    /// Add tags to a document, keeping the ones it already has
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn add_document_tags(
         &self,
-        project_id: EntityId,
-        name: ProjectName,
+        document_id: EntityId,
+        tags: Vec<DocumentTag>,
         updated_by: Option<EntityId>,
-    ) -> Result<ProjectAggregate> {
-        // Load existing project
-        let project = self.project_repository
-            .find_by_id(&project_id)
+    ) -> Result<DocumentAggregate> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
             .await?
-            .ok_or_else(|| WritemagicError::repository("Project not found"))?;
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
 
-        // Create aggregate and update name
-        let mut aggregate = ProjectAggregate::load_from_project(project);
-        aggregate.update_name(name, updated_by)?;
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.add_tags(tags, updated_by)?;
+
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
+        aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
+    }
+
+    /// Add a single tag to a document
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn add_tag(
+        &self,
+        document_id: EntityId,
+        tag: DocumentTag,
+        updated_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
+        self.add_document_tags(document_id, vec![tag], updated_by).await
+    }
+
+    /// Replace a document's tags with `tags`
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn set_tags(
+        &self,
+        document_id: EntityId,
+        tags: Vec<DocumentTag>,
+        updated_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.set_tags(tags, updated_by)?;
+
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
+        aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
+    }
+
+    /// Remove a tag from a document; removing a tag it lacks is a no-op
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn remove_tag(
+        &self,
+        document_id: EntityId,
+        tag: &DocumentTag,
+        updated_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.remove_tag(tag, updated_by)?;
+
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
+        aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
+    }
+
+    /// Convert a document's content to `target`, storing it as a new version
+    ///
+    /// Undo history is dropped, since earlier edits are in the old format.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn convert_document_format(
+        &self,
+        document_id: EntityId,
+        target: ContentType,
+        updated_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        if document.content_type == target {
+            return Ok(DocumentAggregate::load_from_document(document));
+        }
+
+        let converted = convert_content(&document.content, &document.content_type, &target)?;
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.convert_format(DocumentContent::new(converted)?, target, updated_by)?;
+        aggregate.detect_language(&self.language_config);
+
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, updated_by.as_ref()).await?;
+        self.refresh_links(&updated_document).await?;
+        self.undo_history.clear(&document_id);
+
+        let mut aggregate = DocumentAggregate::load_from_document(updated_document);
+        aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
+
+        Ok(aggregate)
+    }
+
+    /// Links written in a document, in the order they appear
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn outgoing_links(&self, document_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        self.document_repository
+            .find_by_id(document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+
+        self.link_repository.find_outgoing(document_id).await
+    }
+
+    /// Links from other documents that resolve to this one
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn backlinks(&self, document_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        self.document_repository
+            .find_by_id(document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+
+        self.link_repository.find_backlinks(document_id).await
+    }
+
+    /// Re-extract and resolve the links written in `document`
+    async fn refresh_links(&self, document: &Document) -> Result<()> {
+        if !self.link_config.enabled {
+            return Ok(());
+        }
+
+        let mut links = Vec::new();
+        for reference in extract_link_references(&document.content) {
+            let target_id = self.resolve_link(&reference).await?;
+            links.push(DocumentLink {
+                source_id: document.id,
+                reference,
+                target_id,
+            });
+        }
+
+        self.link_repository.replace_links(&document.id, &links).await
+    }
+
+    /// Resolve a link reference by document id, then by exact title
+    async fn resolve_link(&self, reference: &str) -> Result<Option<EntityId>> {
+        if let Ok(id) = EntityId::from_string(reference) {
+            if let Some(document) = self.document_repository.find_by_id(&id).await? {
+                if !document.is_deleted {
+                    return Ok(Some(document.id));
+                }
+            }
+        }
+
+        let candidates = self.document_repository
+            .search_by_title(reference, writemagic_shared::Pagination { offset: 0, limit: BULK_SCAN_PAGE_SIZE })
+            .await?;
+
+        Ok(candidates
+            .into_iter()
+            .find(|document| !document.is_deleted && self.link_config.title_matches(reference, &document.title))
+            .map(|document| document.id))
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn delete_document(
+        &self,
+        document_id: EntityId,
+        deleted_by: Option<EntityId>,
+    ) -> Result<()> {
+        // Load existing document
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        // Create aggregate and delete
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.delete(deleted_by)?;
+
+        // Save changes
+        let events = aggregate.uncommitted_events().to_vec();
+        self.save_unlocked(aggregate.document(), expected_version, deleted_by.as_ref()).await?;
+        self.publish_events(events).await;
+
+        Ok(())
+    }
+
+    /// Bring back a soft-deleted document, failing validation if it isn't deleted
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn restore_document(
+        &self,
+        document_id: EntityId,
+        restored_by: Option<EntityId>,
+    ) -> Result<DocumentAggregate> {
+        // Load existing document
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        // Create aggregate and restore
+        let mut aggregate = DocumentAggregate::load_from_document(document);
+        aggregate.restore(restored_by)?;
 
         // Save changes
-        let updated_project = self.project_repository.save(aggregate.project()).await?;
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_document = self.save_unlocked(aggregate.document(), expected_version, restored_by.as_ref()).await?;
         
         // Reload aggregate to ensure version consistency and prevent conflicts
-        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
+        let reloaded_aggregate = DocumentAggregate::load_from_document(updated_document);
         aggregate = reloaded_aggregate;
         aggregate.mark_events_as_committed();
+        self.publish_events(events).await;
 
         Ok(aggregate)
     }
-}
 
-/// Content analysis service
-pub struct ContentAnalysisService;
+    /// Permanently remove soft-deleted documents
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn purge_deleted(&self, dry_run: bool) -> Result<BulkOperationReport> {
+        let affected_ids = self.plan_purge_deleted().await?;
 
-impl ContentAnalysisService {
-    pub fn new() -> Self {
-        Self
+        if !dry_run {
+            for document_id in &affected_ids {
+                self.document_repository.delete(document_id).await?;
+                self.undo_history.clear(document_id);
+            }
+            log::info!("Purged {} deleted documents", affected_ids.len());
+        }
+
+        Ok(BulkOperationReport { dry_run, affected_ids })
     }
 
-    pub fn analyze_readability(&self, content: &DocumentContent) -> ReadabilityAnalysis {
-        let text = content.as_str();
-        let sentences = self.count_sentences(text);
-        let words = content.word_count().value();
-        let syllables = self.count_syllables(text);
+    /// Recompute word and character counts for documents whose stored counts are stale
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn reprocess_documents(&self, dry_run: bool) -> Result<BulkOperationReport> {
+        let stale = self.plan_reprocess_documents().await?;
+        let affected_ids = stale.iter().map(|document| document.id).collect();
 
-        // Flesch Reading Ease Score
-        let flesch_score = if sentences > 0 && words > 0 {
-            206.835 - (1.015 * (words as f64 / sentences as f64)) - (84.6 * (syllables as f64 / words as f64))
-        } else {
-            0.0
-        };
+        if !dry_run {
+            for mut document in stale {
+                let loaded_version = document.version;
+                document.recalculate_statistics(None);
+                let saved = self.save_unlocked(&document, loaded_version, None).await?;
+                self.publish_events(vec![DocumentEvent::DocumentStatsChanged {
+                    document_id: saved.id,
+                    start: 0,
+                    removed_len: 0,
+                    inserted: String::new(),
+                    word_count: saved.word_count,
+                    character_count: saved.character_count,
+                    updated_by: None,
+                    updated_at: saved.updated_at.clone(),
+                }]).await;
+            }
+        }
 
-        // Flesch-Kincaid Grade Level
-        let grade_level = if sentences > 0 && words > 0 {
-            (0.39 * (words as f64 / sentences as f64)) + (11.8 * (syllables as f64 / words as f64)) - 15.59
-        } else {
-            0.0
-        };
+        Ok(BulkOperationReport { dry_run, affected_ids })
+    }
 
-        ReadabilityAnalysis {
-            flesch_reading_ease: flesch_score,
-            flesch_kincaid_grade_level: grade_level,
-            sentences,
-            words,
-            syllables,
-            average_words_per_sentence: if sentences > 0 { words as f64 / sentences as f64 } else { 0.0 },
-            average_syllables_per_word: if words > 0 { syllables as f64 / words as f64 } else { 0.0 },
+    /// Soft-delete several documents at once
+    ///
+    /// Ids that don't exist or are already deleted are skipped.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn batch_delete_documents(
+        &self,
+        document_ids: &[EntityId],
+        deleted_by: Option<EntityId>,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport> {
+        let affected_ids = self.plan_batch_delete(document_ids).await?;
+
+        if !dry_run {
+            for document_id in &affected_ids {
+                self.delete_document(*document_id, deleted_by).await?;
+            }
         }
+
+        Ok(BulkOperationReport { dry_run, affected_ids })
     }
 
-    fn count_sentences(&self, text: &str) -> u32 {
-        text.chars()
-            .filter(|&c| c == '.' || c == '!' || c == '?')
-            .count() as u32
+    /// Create a document for each entry, optionally adding them all to a project
+    ///
+    /// An entry that fails validation is reported in the summary and the
+    /// remaining entries are still imported. The valid entries and the project
+    /// update are written as one batch through the write store, so either all
+    /// of them are stored or none are. The project, if given, must exist.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn import_documents(
+        &self,
+        entries: Vec<ImportEntry>,
+        project_id: Option<EntityId>,
+        created_by: Option<EntityId>,
+    ) -> Result<ImportSummary> {
+        let write_store = self.write_store
+            .as_ref()
+            .ok_or_else(|| WritemagicError::configuration("Write store not configured for imports"))?;
+        let project = match project_id {
+            Some(project_id) => {
+                let project_repository = self.project_repository
+                    .as_ref()
+                    .ok_or_else(|| WritemagicError::configuration("Project repository not configured"))?;
+                let project = project_repository
+                    .find_by_id(&project_id)
+                    .await?
+                    .ok_or_else(|| WritemagicError::not_found(format!("Project {}", project_id)))?;
+                Some(project)
+            }
+            None => None,
+        };
+
+        let mut summary = ImportSummary::default();
+        let mut imported: Vec<DocumentAggregate> = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.into_iter().enumerate() {
+            let title = entry.title.clone();
+            match self.import_entry(entry, created_by) {
+                Ok(aggregate) => imported.push(aggregate),
+                Err(error) => summary.record_failure(index, title, error),
+            }
+        }
+        if imported.is_empty() {
+            return Ok(summary);
+        }
+
+        let mut writes = StagedWrites::default();
+        if let Some(project) = project {
+            let read_version = project.version;
+            let mut aggregate = ProjectAggregate::load_from_project(project);
+            for document in &imported {
+                aggregate.add_document(document.document().id, document.document().title.clone(), created_by)?;
+            }
+            writes.projects.push(StagedWrite { entity: aggregate.project().clone(), read_version: Some(read_version) });
+        }
+        // Written past any tenant-scoped repository, so stamp the tenant here
+        writes.documents = imported
+            .iter()
+            .map(|aggregate| {
+                let mut entity = aggregate.document().clone();
+                entity.set_tenant_id(self.tenant_id.clone());
+                StagedWrite { entity, read_version: None }
+            })
+            .collect();
+        write_store.apply(writes).await?;
+
+        for mut aggregate in imported {
+            let events = aggregate.uncommitted_events().to_vec();
+            aggregate.mark_events_as_committed();
+            self.refresh_links(aggregate.document()).await?;
+            summary.record_created(aggregate.document().id);
+            self.publish_events(events).await;
+        }
+
+        Ok(summary)
     }
 
-    fn count_syllables(&self, text: &str) -> u32 {
-        text.split_whitespace()
-            .map(|word| self.count_syllables_in_word(word))
-            .sum()
+    /// New, unsaved document for an import entry
+    fn import_entry(&self, entry: ImportEntry, created_by: Option<EntityId>) -> Result<DocumentAggregate> {
+        let title = DocumentTitle::new(entry.title)?;
+        let content = DocumentContent::new(entry.content)?;
+        let mut aggregate = DocumentAggregate::new(title, content, entry.content_type, created_by);
+        aggregate.detect_language(&self.language_config);
+        Ok(aggregate)
     }
 
-    fn count_syllables_in_word(&self, word: &str) -> u32 {
-        let word = word.to_lowercase();
-        let vowels = ['a', 'e', 'i', 'o', 'u'];
-        let mut syllable_count = 0;
-        let mut prev_was_vowel = false;
+    /// Combine several documents into one
+    ///
+    /// Source contents are concatenated in the given order, joined by
+    /// `separator`, and written to `target` together with the union of all
+    /// tags. Sources are soft-deleted when `delete_sources` is set. Either every
+    /// write succeeds or the documents written so far are restored.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn merge_documents(
+        &self,
+        source_ids: Vec<EntityId>,
+        target: MergeTarget,
+        separator: Option<String>,
+        delete_sources: bool,
+    ) -> Result<DocumentAggregate> {
+        if source_ids.is_empty() {
+            return Err(WritemagicError::validation("At least one source document is required to merge"));
+        }
+        if let MergeTarget::Existing(target_id) = &target {
+            if source_ids.contains(target_id) {
+                return Err(WritemagicError::validation("The merge target cannot also be a source"));
+            }
+        }
 
-        for ch in word.chars() {
-            let is_vowel = vowels.contains(&ch);
-            if is_vowel && !prev_was_vowel {
-                syllable_count += 1;
+        let mut sources: Vec<Document> = Vec::with_capacity(source_ids.len());
+        for source_id in &source_ids {
+            if sources.iter().any(|source| source.id == *source_id) {
+                return Err(WritemagicError::validation(format!("Document {} is listed twice in the merge", source_id)));
             }
-            prev_was_vowel = is_vowel;
+            let source = self.document_repository
+                .find_by_id(source_id)
+                .await?
+                .filter(|document| !document.is_deleted)
+                .ok_or_else(|| WritemagicError::repository(format!("Document {} not found", source_id)))?;
+            sources.push(source);
         }
 
-        // Adjust for silent 'e'
-        if word.ends_with('e') && syllable_count > 1 {
-            syllable_count -= 1;
+        let existing = match &target {
+            MergeTarget::Existing(target_id) => Some(
+                self.document_repository
+                    .find_by_id(target_id)
+                    .await?
+                    .filter(|document| !document.is_deleted)
+                    .ok_or_else(|| WritemagicError::repository("Document not found"))?,
+            ),
+            MergeTarget::New { .. } => None,
+        };
+
+        let mut parts: Vec<&str> = Vec::with_capacity(sources.len() + 1);
+        let mut tags: Vec<DocumentTag> = Vec::new();
+        if let Some(document) = existing.as_ref().filter(|document| !document.content.is_empty()) {
+            parts.push(&document.content);
+        }
+        for document in existing.iter().chain(sources.iter()) {
+            for tag in &document.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
         }
+        parts.extend(sources.iter().map(|source| source.content.as_str()));
+        let content = DocumentContent::new(parts.join(separator.as_deref().unwrap_or("")))?;
 
-        // Every word has at least one syllable
-        std::cmp::max(1, syllable_count)
-    }
-}
+        let mut merged = match &target {
+            MergeTarget::New { title, content_type } => {
+                Document::new(title.as_str().to_string(), content.as_str().to_string(), content_type.clone(), None)
+            }
+            MergeTarget::Existing(_) => {
+                let mut document = existing.clone().ok_or_else(|| WritemagicError::repository("Document not found"))?;
+                document.update_content(content.as_str().to_string(), None);
+                document
+            }
+        };
+        merged.tags = tags;
+        merged.detect_language(&self.language_config);
+        let previous_content = existing.as_ref().map(|document| document.content.clone());
 
-impl Default for ContentAnalysisService {
-    fn default() -> Self {
-        Self::new()
+        // Each document written so far with its copy from before the merge, `None` if it was created
+        let mut written: Vec<(Option<Document>, Document)> = Vec::new();
+        match self.write_merge(&merged, existing, &sources, delete_sources, &mut written).await {
+            Ok(saved) => {
+                self.refresh_links(&saved).await?;
+                if let Some(previous_content) = previous_content {
+                    self.undo_history.record_edit(saved.id, previous_content);
+                }
+                self.publish_events(Self::merge_events(&written)).await;
+                let mut aggregate = DocumentAggregate::load_from_document(saved);
+                aggregate.mark_events_as_committed();
+                Ok(aggregate)
+            }
+            Err(error) => {
+                for (original, write) in written.into_iter().rev() {
+                    if let Err(rollback_error) = self.undo_merge_write(original, &write).await {
+                        log::error!("Failed to roll back merge write to document {}: {}", write.id, rollback_error);
+                    }
+                }
+                Err(error)
+            }
+        }
     }
-}
 
-/// Readability analysis result
-#[derive(Debug, Clone)]
-pub struct ReadabilityAnalysis {
-    pub flesch_reading_ease: f64,
-    pub flesch_kincaid_grade_level: f64,
-    pub sentences: u32,
-    pub words: u32,
-    pub syllables: u32,
-    pub average_words_per_sentence: f64,
-    pub average_syllables_per_word: f64,
-}
+    /// Persist a merge, recording each write in `written` once it is made
+    ///
+    /// Every write is conditional on the version the merge read, so a
+    /// document changed or locked meanwhile fails the merge instead of being
+    /// overwritten.
+    async fn write_merge(
+        &self,
+        merged: &Document,
+        existing: Option<Document>,
+        sources: &[Document],
+        delete_sources: bool,
+        written: &mut Vec<(Option<Document>, Document)>,
+    ) -> Result<Document> {
+        let saved = match &existing {
+            Some(existing) => self.save_unlocked(merged, existing.version, None).await?,
+            None => self.document_repository.save(merged).await?,
+        };
+        written.push((existing, saved.clone()));
 
-impl ReadabilityAnalysis {
-    pub fn reading_level_description(&self) -> &'static str {
-        match self.flesch_reading_ease {
-            score if score >= 90.0 => "Very Easy",
-            score if score >= 80.0 => "Easy",
-            score if score >= 70.0 => "Fairly Easy",
-            score if score >= 60.0 => "Standard",
-            score if score >= 50.0 => "Fairly Difficult",
-            score if score >= 30.0 => "Difficult",
-            _ => "Very Difficult",
+        if delete_sources {
+            for source in sources {
+                let mut deleted = source.clone();
+                deleted.mark_deleted(None);
+                let deleted = self.save_unlocked(&deleted, source.version, None).await?;
+                written.push((Some(source.clone()), deleted));
+            }
+        }
+
+        Ok(saved)
+    }
+
+    /// Undo one merge write, unless the document has changed since it was made
+    ///
+    /// A restored document gets a new version rather than its old one, so
+    /// copies read while the merge was stored are still detected as stale.
+    async fn undo_merge_write(&self, original: Option<Document>, write: &Document) -> Result<()> {
+        let Some(mut restored) = original else {
+            // Nobody was handed the new document, but keep anything written to it since
+            if self.document_repository.find_by_id(&write.id).await?.is_some_and(|stored| stored.version == write.version) {
+                self.document_repository.delete(&write.id).await?;
+            }
+            return Ok(());
+        };
+
+        restored.version = write.version + 1;
+        restored.updated_at = Timestamp::now();
+        match self.document_repository.save_if_version(&restored, write.version).await {
+            Ok(_) => Ok(()),
+            Err(ConcurrencyError::VersionMismatch { .. }) => {
+                log::warn!("Document {} changed after the failed merge wrote it, keeping the newer copy", write.id);
+                Ok(())
+            }
+            Err(ConcurrencyError::Repository(error)) => Err(error),
+        }
+    }
+
+    /// Events of the writes a merge made, which bypass the aggregate
+    fn merge_events(written: &[(Option<Document>, Document)]) -> Vec<DocumentEvent> {
+        written
+            .iter()
+            .map(|(original, write)| match original {
+                None => DocumentEvent::DocumentCreated {
+                    document_id: write.id,
+                    title: write.title.clone(),
+                    content: write.content.clone(),
+                    content_type: write.content_type.clone(),
+                    created_by: write.created_by,
+                    created_at: write.created_at.clone(),
+                },
+                Some(original) if write.is_deleted && !original.is_deleted => DocumentEvent::DocumentDeleted {
+                    document_id: write.id,
+                    deleted_by: None,
+                    deleted_at: write.updated_at.clone(),
+                },
+                Some(original) => DocumentEvent::DocumentContentUpdated {
+                    document_id: write.id,
+                    old_content: original.content.clone(),
+                    new_content: write.content.clone(),
+                    old_word_count: original.word_count,
+                    new_word_count: write.word_count,
+                    updated_by: None,
+                    updated_at: write.updated_at.clone(),
+                },
+            })
+            .collect()
+    }
+
+    async fn plan_purge_deleted(&self) -> Result<Vec<EntityId>> {
+        let mut affected_ids = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page = self.document_repository
+                .find_deleted(writemagic_shared::Pagination { offset, limit: BULK_SCAN_PAGE_SIZE })
+                .await?;
+            let page_len = page.len() as u32;
+            affected_ids.extend(page.into_iter().filter(|document| document.is_deleted).map(|document| document.id));
+
+            if page_len < BULK_SCAN_PAGE_SIZE {
+                return Ok(affected_ids);
+            }
+            offset += page_len;
+        }
+    }
+
+    async fn plan_reprocess_documents(&self) -> Result<Vec<Document>> {
+        let mut stale = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page = self.document_repository
+                .find_all(writemagic_shared::Pagination { offset, limit: BULK_SCAN_PAGE_SIZE })
+                .await?;
+            let page_len = page.len() as u32;
+            stale.extend(page.into_iter().filter(|document| !document.is_deleted && document.has_stale_statistics()));
+
+            if page_len < BULK_SCAN_PAGE_SIZE {
+                return Ok(stale);
+            }
+            offset += page_len;
+        }
+    }
+
+    async fn plan_batch_delete(&self, document_ids: &[EntityId]) -> Result<Vec<EntityId>> {
+        let mut affected_ids = Vec::new();
+
+        for document_id in document_ids {
+            if affected_ids.contains(document_id) {
+                continue;
+            }
+            if let Some(document) = self.document_repository.find_by_id(document_id).await? {
+                if !document.is_deleted {
+                    affected_ids.push(*document_id);
+                }
+            }
+        }
+
+        Ok(affected_ids)
+    }
+
+    /// Suggest tags for a document, optionally applying them
+    ///
+    /// Uses the AI writing service when configured and falls back to
+    /// keyword-frequency extraction otherwise. When `auto_apply` is false the
+    /// document is left untouched.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn suggest_and_apply_tags(
+        &self,
+        document_id: EntityId,
+        auto_apply: bool,
+    ) -> Result<Vec<DocumentTag>> {
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+        let expected_version = document.version;
+
+        let suggestions = self.suggest_tags(&document.content).await?;
+
+        if auto_apply && !suggestions.is_empty() {
+            let mut aggregate = DocumentAggregate::load_from_document(document);
+            aggregate.add_tags(suggestions.clone(), None)?;
+            let events = aggregate.uncommitted_events().to_vec();
+            self.save_unlocked(aggregate.document(), expected_version, None).await?;
+            self.publish_events(events).await;
+        }
+
+        Ok(suggestions)
+    }
+
+    async fn suggest_tags(&self, content: &str) -> Result<Vec<DocumentTag>> {
+        #[cfg(feature = "ai")]
+        if let Some(ai_writing_service) = &self.ai_writing_service {
+            return ai_writing_service.suggest_tags(content, MAX_SUGGESTED_TAGS).await;
+        }
+
+        Ok(DocumentTag::from_keywords(content, MAX_SUGGESTED_TAGS))
+    }
+}
+
+/// Project management service
+pub struct ProjectManagementService {
+    project_repository: Arc<dyn ProjectRepository>,
+    document_repository: Arc<dyn DocumentRepository>,
+    /// Serializes archive changes and document moves so their version checks and saves don't interleave
+    archive_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Advisory document locks moved and archived documents are checked against
+    document_locks: DocumentLocks,
+    /// Applies the project writes of a document move all at once
+    write_store: Option<Arc<dyn StagedWriteStore>>,
+    event_bus: Option<Arc<dyn EventBus>>,
+}
+
+/// Project and documents changed by archiving or unarchiving, with the events raised
+#[derive(Debug, Clone)]
+pub struct ProjectArchiveResult {
+    pub project: ProjectAggregate,
+    pub archived_documents: Vec<EntityId>,
+    pub project_events: Vec<ProjectEvent>,
+    pub document_events: Vec<DocumentEvent>,
+}
+
+/// Projects changed by moving a batch of documents, with the event raised
+#[derive(Debug, Clone)]
+pub struct DocumentsMoveResult {
+    pub project: ProjectAggregate,
+    /// Projects the documents were taken out of
+    pub source_projects: Vec<ProjectAggregate>,
+    pub event: ProjectEvent,
+}
+
+impl ProjectManagementService {
+    pub fn new(
+        project_repository: Arc<dyn ProjectRepository>,
+        document_repository: Arc<dyn DocumentRepository>,
+    ) -> Self {
+        Self {
+            project_repository,
+            document_repository,
+            archive_lock: Arc::new(tokio::sync::Mutex::new(())),
+            document_locks: DocumentLocks::default(),
+            write_store: None,
+            event_bus: None,
+        }
+    }
+
+    /// Respect the advisory locks `document_locks`, shared with the document service
+    pub fn with_document_locks(mut self, document_locks: DocumentLocks) -> Self {
+        self.document_locks = document_locks;
+        self
+    }
+
+    /// Apply the project writes of document moves through `write_store`, all at once
+    pub fn with_write_store(mut self, write_store: Arc<dyn StagedWriteStore>) -> Self {
+        self.write_store = Some(write_store);
+        self
+    }
+
+    /// Publish the events of every saved project change on `event_bus`, and
+    /// of documents archived with their project
+    pub fn with_event_bus(mut self, event_bus: Arc<dyn EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Publish events of a change that has been saved, logging rather than failing on errors
+    async fn publish_events<E: DomainEvent>(&self, events: Vec<E>) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+        for event in events {
+            if let Err(e) = event_bus.publish(Box::new(event)).await {
+                log::warn!("Failed to publish event: {}", e);
+            }
+        }
+    }
+
+    /// The same service restricted to the projects and documents of one tenant
+    pub fn scoped(&self, scope: TenantScope) -> Self {
+        Self {
+            project_repository: scope_projects(&self.project_repository, scope.clone()),
+            document_repository: scope_documents(&self.document_repository, scope),
+            archive_lock: self.archive_lock.clone(),
+            document_locks: self.document_locks.clone(),
+            write_store: self.write_store.clone(),
+            event_bus: self.event_bus.clone(),
+        }
+    }
+
+    /// The same service reading and writing through a unit of work's staging repositories
+    ///
+    /// Writes go to the staging repositories rather than the write store, and
+    /// events are not published, since the writes stay invisible until the
+    /// unit of work commits.
+    pub fn with_staged_repositories(
+        &self,
+        project_repository: Arc<dyn ProjectRepository>,
+        document_repository: Arc<dyn DocumentRepository>,
+    ) -> Self {
+        Self {
+            project_repository,
+            document_repository,
+            archive_lock: self.archive_lock.clone(),
+            document_locks: self.document_locks.clone(),
+            write_store: None,
+            event_bus: None,
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn create_project(
+        &self,
+        name: ProjectName,
+        description: Option<String>,
+        created_by: Option<EntityId>,
+    ) -> Result<ProjectAggregate> {
+        // Create new project aggregate
+        let mut aggregate = ProjectAggregate::new(name, description, created_by);
+        let events = aggregate.uncommitted_events().to_vec();
+
+        // Save to repository
+        let project = self.project_repository.save(aggregate.project()).await?;
+        self.publish_events(events).await;
+        
+        // Reload aggregate with updated project to ensure consistency
+        let updated_aggregate = ProjectAggregate::load_from_project(project);
+        aggregate = updated_aggregate;
+        aggregate.mark_events_as_committed();
+
+        Ok(aggregate)
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn add_document_to_project(
+        &self,
+        project_id: EntityId,
+        document_id: EntityId,
+        updated_by: Option<EntityId>,
+    ) -> Result<ProjectAggregate> {
+        // Load existing project
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        // Verify document exists
+        let document = self.document_repository
+            .find_by_id(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+
+        // Create aggregate and add document
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.add_document(document_id, document.title, updated_by)?;
+
+        // Save changes
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(events).await;
+        
+        // Reload aggregate to ensure version consistency and prevent conflicts
+        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
+        aggregate = reloaded_aggregate;
+        aggregate.mark_events_as_committed();
+
+        Ok(aggregate)
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn remove_document_from_project(
+        &self,
+        project_id: EntityId,
+        document_id: EntityId,
+        updated_by: Option<EntityId>,
+    ) -> Result<ProjectAggregate> {
+        // Load existing project
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        // Create aggregate and remove document
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.remove_document(&document_id, updated_by)?;
+
+        // Save changes
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(events).await;
+        
+        // Reload aggregate to ensure version consistency and prevent conflicts
+        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
+        aggregate = reloaded_aggregate;
+        aggregate.mark_events_as_committed();
+
+        Ok(aggregate)
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn update_project_name(
+        &self,
+        project_id: EntityId,
+        name: ProjectName,
+        updated_by: Option<EntityId>,
+    ) -> Result<ProjectAggregate> {
+        // Load existing project
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        // Create aggregate and update name
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.update_name(name, updated_by)?;
+
+        // Save changes
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(events).await;
+        
+        // Reload aggregate to ensure version consistency and prevent conflicts
+        let reloaded_aggregate = ProjectAggregate::load_from_project(updated_project);
+        aggregate = reloaded_aggregate;
+        aggregate.mark_events_as_committed();
+
+        Ok(aggregate)
+    }
+
+    /// Set or clear the system prompt sent ahead of AI requests made for a project
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn set_project_system_prompt(
+        &self,
+        project_id: EntityId,
+        system_prompt: Option<String>,
+        updated_by: Option<EntityId>,
+    ) -> Result<ProjectAggregate> {
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.update_system_prompt(system_prompt, updated_by)?;
+        if aggregate.uncommitted_events().is_empty() {
+            return Ok(aggregate);
+        }
+
+        let events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(events).await;
+        let mut aggregate = ProjectAggregate::load_from_project(updated_project);
+        aggregate.mark_events_as_committed();
+
+        Ok(aggregate)
+    }
+
+    /// The system prompt of a project, if it has one
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn project_system_prompt(&self, project_id: EntityId) -> Result<Option<String>> {
+        Ok(self.load_project(&project_id).await?.system_prompt)
+    }
+
+    /// Archive a project, and with `cascade` every live document in it
+    ///
+    /// Documents are archived before the project, so a run cut short by a
+    /// version conflict can simply be retried.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn archive_project(
+        &self,
+        project_id: EntityId,
+        archived_by: Option<EntityId>,
+        cascade: bool,
+    ) -> Result<ProjectArchiveResult> {
+        let _guard = self.archive_lock.lock().await;
+
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.archive(archived_by, cascade)?;
+
+        let mut archived_documents = Vec::new();
+        let mut document_events = Vec::new();
+        if cascade {
+            let documents = self.document_repository.find_by_ids(&aggregate.project().document_ids).await?;
+            for document in documents.into_iter().filter(|document| !document.is_deleted) {
+                let document_version = document.version;
+                let mut document_aggregate = DocumentAggregate::load_from_document(document);
+                document_aggregate.archive(archived_by)?;
+                if document_aggregate.uncommitted_events().is_empty() {
+                    continue;
+                }
+
+                self.save_document_at_version(document_aggregate.document(), document_version, archived_by.as_ref()).await?;
+                archived_documents.push(document_aggregate.document().id);
+                document_events.extend_from_slice(document_aggregate.uncommitted_events());
+            }
+        }
+
+        let project_events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(document_events.clone()).await;
+        self.publish_events(project_events.clone()).await;
+
+        Ok(ProjectArchiveResult {
+            project: ProjectAggregate::load_from_project(updated_project),
+            archived_documents,
+            project_events,
+            document_events,
+        })
+    }
+
+    /// Take a project out of the archive, leaving its documents archived
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn unarchive_project(
+        &self,
+        project_id: EntityId,
+        unarchived_by: Option<EntityId>,
+    ) -> Result<ProjectArchiveResult> {
+        let _guard = self.archive_lock.lock().await;
+
+        let project = self.load_project(&project_id).await?;
+        let loaded_version = project.version;
+
+        let mut aggregate = ProjectAggregate::load_from_project(project);
+        aggregate.unarchive(unarchived_by)?;
+
+        let project_events = aggregate.uncommitted_events().to_vec();
+        let updated_project = self.save_project_at_version(aggregate.project(), loaded_version).await?;
+        self.publish_events(project_events.clone()).await;
+
+        Ok(ProjectArchiveResult {
+            project: ProjectAggregate::load_from_project(updated_project),
+            archived_documents: Vec::new(),
+            project_events,
+            document_events: Vec::new(),
+        })
+    }
+
+    /// Move documents into project `to` as one batch
+    ///
+    /// With `from` the documents must all be in that project; without it they
+    /// are taken out of every project holding them. The projects are written
+    /// in one version-checked batch through the write store, so either all of
+    /// them change or none do. Without a write store, as inside a unit of
+    /// work, each project is saved with its own version check.
+    #[tracing::instrument(skip_all, fields(request_id = current_request_id().as_deref()))]
+    pub async fn move_documents(
+        &self,
+        document_ids: Vec<EntityId>,
+        from: Option<EntityId>,
+        to: EntityId,
+        moved_by: Option<EntityId>,
+    ) -> Result<DocumentsMoveResult> {
+        let mut unique_ids: Vec<EntityId> = Vec::with_capacity(document_ids.len());
+        for document_id in document_ids {
+            if !unique_ids.contains(&document_id) {
+                unique_ids.push(document_id);
+            }
+        }
+        if unique_ids.is_empty() {
+            return Err(WritemagicError::validation("No documents to move"));
+        }
+        if from == Some(to) {
+            return Err(WritemagicError::validation("Documents are already in the target project"));
+        }
+
+        let _guard = self.archive_lock.lock().await;
+
+        let target = self.project_repository
+            .find_by_id(&to)
+            .await?
+            .filter(|project| !project.is_deleted)
+            .ok_or_else(|| WritemagicError::not_found(format!("Project {}", to)))?;
+
+        let documents = self.document_repository.find_by_ids(&unique_ids).await?;
+        if let Some(missing) = unique_ids.iter().find(|id| {
+            !documents.iter().any(|document| document.id == **id && !document.is_deleted)
+        }) {
+            return Err(WritemagicError::not_found(format!("Document {}", missing)));
+        }
+
+        let sources = match from {
+            Some(from) => vec![self.project_repository
+                .find_by_id(&from)
+                .await?
+                .filter(|project| !project.is_deleted)
+                .ok_or_else(|| WritemagicError::not_found(format!("Project {}", from)))?],
+            None => {
+                let mut sources: Vec<Project> = Vec::new();
+                for document_id in &unique_ids {
+                    let pagination = writemagic_shared::Pagination { offset: 0, limit: BULK_SCAN_PAGE_SIZE };
+                    for project in self.project_repository.find_containing_document(document_id, pagination).await? {
+                        if project.id != to && !sources.iter().any(|source| source.id == project.id) {
+                            sources.push(project);
+                        }
+                    }
+                }
+                sources
+            }
+        };
+
+        let mut source_aggregates = Vec::with_capacity(sources.len());
+        for source in &sources {
+            let moved: Vec<EntityId> = match from {
+                Some(_) => unique_ids.clone(),
+                None => unique_ids.iter().filter(|id| source.document_ids.contains(id)).copied().collect(),
+            };
+            let mut aggregate = ProjectAggregate::load_from_project(source.clone());
+            aggregate.release_documents(&moved, moved_by)?;
+            source_aggregates.push(aggregate);
+        }
+
+        let mut target_aggregate = ProjectAggregate::load_from_project(target.clone());
+        let from_project_ids = sources.iter().map(|source| source.id).collect();
+        target_aggregate.receive_documents(unique_ids.clone(), from_project_ids, moved_by)?;
+        let event = target_aggregate.uncommitted_events()[0].clone();
+
+        let mut changes: Vec<(&Project, u64)> = source_aggregates
+            .iter()
+            .map(ProjectAggregate::project)
+            .zip(sources.iter().map(|source| source.version))
+            .collect();
+        changes.push((target_aggregate.project(), target.version));
+
+        // Held through the saves so no document can be locked after its check
+        let _document_guards = self.document_locks.guard_all(&unique_ids).await;
+        for document_id in &unique_ids {
+            self.document_locks.ensure_not_locked(document_id, moved_by.as_ref()).await?;
+        }
+        let mut saved = self.save_projects_at_versions(&changes).await?;
+        let project = ProjectAggregate::load_from_project(saved.remove(sources.len()));
+
+        self.publish_events(vec![event.clone()]).await;
+
+        Ok(DocumentsMoveResult {
+            project,
+            source_projects: saved.into_iter().map(ProjectAggregate::load_from_project).collect(),
+            event,
+        })
+    }
+
+    /// Save each `(project, loaded_version)` pair unless another writer changed it since it was loaded
+    async fn save_projects_at_versions(&self, changes: &[(&Project, u64)]) -> Result<Vec<Project>> {
+        let Some(write_store) = &self.write_store else {
+            let mut saved = Vec::with_capacity(changes.len());
+            for (project, loaded_version) in changes {
+                saved.push(self.save_project_at_version(project, *loaded_version).await?);
+            }
+            return Ok(saved);
+        };
+
+        let writes = StagedWrites {
+            documents: Vec::new(),
+            projects: changes
+                .iter()
+                .map(|(project, loaded_version)| StagedWrite {
+                    entity: (*project).clone(),
+                    read_version: Some(*loaded_version),
+                })
+                .collect(),
+        };
+        write_store.apply(writes).await?;
+        Ok(changes.iter().map(|(project, _)| (*project).clone()).collect())
+    }
+
+    /// Stored project `project_id`, or a not-found error
+    async fn load_project(&self, project_id: &EntityId) -> Result<Project> {
+        self.project_repository
+            .find_by_id(project_id)
+            .await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Project {}", project_id)))
+    }
+
+    /// Save `project` unless another writer changed it since it was loaded at `loaded_version`
+    async fn save_project_at_version(&self, project: &Project, loaded_version: u64) -> Result<Project> {
+        self.project_repository.save_if_version(project, loaded_version).await
+    }
+
+    /// Save `document` unless another writer changed it since it was loaded at
+    /// `loaded_version` or someone other than `editor` holds a lock on it
+    async fn save_document_at_version(&self, document: &Document, loaded_version: u64, editor: Option<&EntityId>) -> Result<Document> {
+        let _guard = self.document_locks.guard(&document.id).await;
+        self.document_locks.ensure_not_locked(&document.id, editor).await?;
+        Ok(self.document_repository.save_if_version(document, loaded_version).await?)
+    }
+}
+
+/// Default content length in bytes above which `ContentAnalysisService::analyze` uses the arena
+pub const ARENA_ANALYSIS_THRESHOLD: usize = 256 * 1024;
+
+/// Byte range of a paragraph within the text being analyzed
+#[derive(Debug, Clone, Copy)]
+struct TextSpan {
+    start: usize,
+    end: usize,
+}
+
+/// Trimmed, non-empty blank-line separated paragraphs of `text`
+fn paragraph_spans(text: &str) -> impl Iterator<Item = TextSpan> + '_ {
+    text.split("\n\n").filter_map(move |paragraph| {
+        let trimmed = paragraph.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let start = trimmed.as_ptr() as usize - text.as_ptr() as usize;
+        Some(TextSpan { start, end: start + trimmed.len() })
+    })
+}
+
+/// Content analysis service
+pub struct ContentAnalysisService {
+    reading_time: ReadingTimeConfig,
+    language: LanguageConfig,
+    arena_threshold: usize,
+}
+
+impl ContentAnalysisService {
+    pub fn new() -> Self {
+        Self {
+            reading_time: ReadingTimeConfig::default(),
+            language: LanguageConfig::default(),
+            arena_threshold: ARENA_ANALYSIS_THRESHOLD,
+        }
+    }
+
+    /// Reading speeds used for reading time estimates
+    pub fn with_reading_time_config(mut self, reading_time: ReadingTimeConfig) -> Self {
+        self.reading_time = reading_time;
+        self
+    }
+
+    /// How the language of bare content is detected for reading time estimates
+    pub fn with_language_config(mut self, language: LanguageConfig) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Content length in bytes above which `analyze` switches to `analyze_arena`
+    pub fn with_arena_threshold(mut self, bytes: usize) -> Self {
+        self.arena_threshold = bytes;
+        self
+    }
+
+    /// Count words, characters, sentences and paragraphs, and estimate reading time and grade level
+    ///
+    /// Content is read as Markdown in its detected language. Markdown syntax is
+    /// stripped first so only the text a reader sees is counted. Content over
+    /// the arena threshold is counted as in `analyze_arena`.
+    pub fn analyze(&self, content: &DocumentContent) -> ContentStatistics {
+        let language = detect_language(content.as_str(), &self.language);
+        self.analyze_as(content.as_str(), &ContentType::Markdown, &language)
+    }
+
+    /// Same as `analyze`, with reading time estimated for the document's own
+    /// content type and language
+    pub fn analyze_document(&self, document: &Document) -> ContentStatistics {
+        self.analyze_as(&document.content, &document.content_type, document.effective_language())
+    }
+
+    /// Same statistics as `analyze`, without allocating per word or per paragraph
+    pub fn analyze_arena(&self, content: &str) -> ContentStatistics {
+        let language = detect_language(content, &self.language);
+        ContentStatistics {
+            reading_time_seconds: estimate_reading_time(content, &ContentType::Markdown, &language, &self.reading_time),
+            ..self.count_arena(content)
+        }
+    }
+
+    fn analyze_as(&self, content: &str, content_type: &ContentType, language: &str) -> ContentStatistics {
+        let statistics = if content.len() > self.arena_threshold {
+            self.count_arena(content)
+        } else {
+            self.count(content)
+        };
+
+        ContentStatistics {
+            reading_time_seconds: estimate_reading_time(content, content_type, language, &self.reading_time),
+            ..statistics
+        }
+    }
+
+    /// Statistics of `content` with reading time left at zero
+    fn count(&self, content: &str) -> ContentStatistics {
+        let text = markdown_to_plain_text(content);
+        let paragraphs: Vec<&str> = text
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+            .collect();
+
+        let words: Vec<&str> = paragraphs
+            .iter()
+            .flat_map(|paragraph| paragraph.split_whitespace())
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .collect();
+        let word_count = words.len() as u32;
+        let sentence_count: u32 = paragraphs.iter().map(|paragraph| Self::count_paragraph_sentences(paragraph)).sum();
+        let syllables: u32 = words
+            .iter()
+            .map(|word| self.count_syllables_in_word(&word.chars().filter(|c| c.is_alphabetic()).collect::<String>()))
+            .sum();
+
+        let visible = paragraphs.join("\n\n");
+        let flesch_kincaid_grade_level = if sentence_count > 0 && word_count > 0 {
+            (0.39 * (word_count as f64 / sentence_count as f64)) + (11.8 * (syllables as f64 / word_count as f64)) - 15.59
+        } else {
+            0.0
+        };
+
+        ContentStatistics {
+            word_count,
+            character_count: visible.chars().count() as u32,
+            character_count_excluding_whitespace: visible.chars().filter(|c| !c.is_whitespace()).count() as u32,
+            sentence_count,
+            paragraph_count: paragraphs.len() as u32,
+            reading_time_seconds: 0,
+            flesch_kincaid_grade_level,
+        }
+    }
+
+    /// Same as `count`, without allocating per word or per paragraph
+    ///
+    /// Paragraph spans index into the stripped text and live in the thread
+    /// arena for the duration of the count; when the arena is too small or
+    /// already in use further up the stack they are collected instead.
+    fn count_arena(&self, content: &str) -> ContentStatistics {
+        let text = markdown_to_plain_text(content);
+        let paragraph_count = paragraph_spans(&text).count();
+
+        let in_arena = with_thread_arena(|arena| {
+            let spans = arena.alloc_slice(paragraph_count, paragraph_spans(&text))?;
+            Some(Self::count_spans(&text, spans))
+        });
+        in_arena.flatten().unwrap_or_else(|| {
+            let spans: Vec<TextSpan> = paragraph_spans(&text).collect();
+            Self::count_spans(&text, &spans)
+        })
+    }
+
+    /// Statistics of the paragraphs `spans` of `text`, with reading time left at zero
+    fn count_spans(text: &str, spans: &[TextSpan]) -> ContentStatistics {
+        let mut word_count = 0u32;
+        let mut sentence_count = 0u32;
+        let mut syllables = 0u32;
+        let mut character_count = 2 * spans.len().saturating_sub(1) as u32;
+        let mut character_count_excluding_whitespace = 0u32;
+        for span in spans {
+            let paragraph = &text[span.start..span.end];
+            for word in paragraph.split_whitespace().filter(|word| word.chars().any(char::is_alphanumeric)) {
+                word_count += 1;
+                syllables += Self::count_syllables_in_chars(word.chars().filter(|c| c.is_alphabetic()));
+            }
+            sentence_count += Self::count_paragraph_sentences(paragraph);
+            for c in paragraph.chars() {
+                character_count += 1;
+                character_count_excluding_whitespace += !c.is_whitespace() as u32;
+            }
+        }
+
+        let flesch_kincaid_grade_level = if sentence_count > 0 && word_count > 0 {
+            (0.39 * (word_count as f64 / sentence_count as f64)) + (11.8 * (syllables as f64 / word_count as f64)) - 15.59
+        } else {
+            0.0
+        };
+
+        ContentStatistics {
+            word_count,
+            character_count,
+            character_count_excluding_whitespace,
+            sentence_count,
+            paragraph_count: spans.len() as u32,
+            reading_time_seconds: 0,
+            flesch_kincaid_grade_level,
+        }
+    }
+
+    /// Runs of terminal punctuation, plus a trailing sentence left unpunctuated (e.g. a heading)
+    fn count_paragraph_sentences(paragraph: &str) -> u32 {
+        let is_terminal = |c: char| matches!(c, '.' | '!' | '?');
+        let mut chars = paragraph.chars().peekable();
+        let mut terminated = 0;
+        while let Some(c) = chars.next() {
+            if is_terminal(c) && chars.peek().map_or(true, |next| next.is_whitespace()) {
+                terminated += 1;
+            }
+        }
+        let unterminated = paragraph.chars().next_back().is_some_and(|c| !is_terminal(c)) as u32;
+        terminated + unterminated
+    }
+
+    pub fn analyze_readability(&self, content: &DocumentContent) -> ReadabilityAnalysis {
+        let text = content.as_str();
+        let sentences = self.count_sentences(text);
+        let words = content.word_count().value();
+        let syllables = self.count_syllables(text);
+
+        // Flesch Reading Ease Score
+        let flesch_score = if sentences > 0 && words > 0 {
+            206.835 - (1.015 * (words as f64 / sentences as f64)) - (84.6 * (syllables as f64 / words as f64))
+        } else {
+            0.0
+        };
+
+        // Flesch-Kincaid Grade Level
+        let grade_level = if sentences > 0 && words > 0 {
+            (0.39 * (words as f64 / sentences as f64)) + (11.8 * (syllables as f64 / words as f64)) - 15.59
+        } else {
+            0.0
+        };
+
+        ReadabilityAnalysis {
+            flesch_reading_ease: flesch_score,
+            flesch_kincaid_grade_level: grade_level,
+            sentences,
+            words,
+            syllables,
+            average_words_per_sentence: if sentences > 0 { words as f64 / sentences as f64 } else { 0.0 },
+            average_syllables_per_word: if words > 0 { syllables as f64 / words as f64 } else { 0.0 },
+        }
+    }
+
+    fn count_sentences(&self, text: &str) -> u32 {
+        text.chars()
+            .filter(|&c| c == '.' || c == '!' || c == '?')
+            .count() as u32
+    }
+
+    fn count_syllables(&self, text: &str) -> u32 {
+        text.split_whitespace()
+            .map(|word| self.count_syllables_in_word(word))
+            .sum()
+    }
+
+    fn count_syllables_in_word(&self, word: &str) -> u32 {
+        Self::count_syllables_in_chars(word.chars())
+    }
+
+    fn count_syllables_in_chars(chars: impl Iterator<Item = char>) -> u32 {
+        let vowels = ['a', 'e', 'i', 'o', 'u'];
+        let mut syllable_count = 0;
+        let mut prev_was_vowel = false;
+        let mut last = None;
+
+        for ch in chars.flat_map(char::to_lowercase) {
+            let is_vowel = vowels.contains(&ch);
+            if is_vowel && !prev_was_vowel {
+                syllable_count += 1;
+            }
+            prev_was_vowel = is_vowel;
+            last = Some(ch);
+        }
+
+        // Adjust for silent 'e'
+        if last == Some('e') && syllable_count > 1 {
+            syllable_count -= 1;
+        }
+
+        // Every word has at least one syllable
+        std::cmp::max(1, syllable_count)
+    }
+}
+
+impl Default for ContentAnalysisService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts and estimates describing a document's visible text
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ContentStatistics {
+    pub word_count: u32,
+    pub character_count: u32,
+    pub character_count_excluding_whitespace: u32,
+    pub sentence_count: u32,
+    pub paragraph_count: u32,
+    /// Estimated with the service's reading time config, rounded up
+    pub reading_time_seconds: u32,
+    pub flesch_kincaid_grade_level: f64,
+}
+
+/// Readability analysis result
+#[derive(Debug, Clone)]
+pub struct ReadabilityAnalysis {
+    pub flesch_reading_ease: f64,
+    pub flesch_kincaid_grade_level: f64,
+    pub sentences: u32,
+    pub words: u32,
+    pub syllables: u32,
+    pub average_words_per_sentence: f64,
+    pub average_syllables_per_word: f64,
+}
+
+impl ReadabilityAnalysis {
+    pub fn reading_level_description(&self) -> &'static str {
+        match self.flesch_reading_ease {
+            score if score >= 90.0 => "Very Easy",
+            score if score >= 80.0 => "Easy",
+            score if score >= 70.0 => "Fairly Easy",
+            score if score >= 60.0 => "Standard",
+            score if score >= 50.0 => "Fairly Difficult",
+            score if score >= 30.0 => "Difficult",
+            _ => "Very Difficult",
         }
     }
 
     pub fn grade_level_description(&self) -> String {
         format!("Grade {:.1}", self.flesch_kincaid_grade_level)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{ConcurrencyError, InMemoryDocumentRepository};
+    use crate::unit_of_work::InMemoryWriteStore;
+    use writemagic_shared::{ContentType, Repository};
+
+    async fn create_document(service: &DocumentManagementService, content: &str) -> EntityId {
+        let aggregate = service
+            .create_document(
+                DocumentTitle::new("Garden Notes").unwrap(),
+                DocumentContent::new(content).unwrap(),
+                ContentType::Markdown,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        aggregate.document().id
+    }
+
+    #[tokio::test]
+    async fn test_saved_changes_publish_their_events() {
+        use writemagic_shared::{DomainEvent, InMemoryEventBus};
+
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_seen = seen.clone();
+        event_bus
+            .add_typed_subscription(move |event: &DocumentEvent| -> Result<()> {
+                handler_seen.lock().unwrap().push(event.event_type());
+                Ok(())
+            })
+            .await;
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()))
+            .with_event_bus(event_bus.clone());
+
+        let document_id = create_document(&service, "Tomatoes").await;
+        service.update_document_title(document_id, DocumentTitle::new("Vegetables").unwrap(), None).await.unwrap();
+        assert!(service.update_document_title(EntityId::new(), DocumentTitle::new("Missing").unwrap(), None).await.is_err());
+
+        assert_eq!(*seen.lock().unwrap(), vec!["DocumentCreated", "DocumentTitleUpdated"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_continues_past_bad_entries_and_fills_project() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let project = ProjectManagementService::new(projects.clone(), documents.clone())
+            .create_project(ProjectName::new("Migration").unwrap(), None, None)
+            .await
+            .unwrap();
+        let service = DocumentManagementService::new(documents.clone())
+            .with_project_repository(projects.clone())
+            .with_write_store(Arc::new(InMemoryWriteStore::new((*documents).clone(), (*projects).clone())));
+
+        let entries = vec![
+            ImportEntry::from_file("intro.md", "# Intro".to_string()),
+            ImportEntry { title: "   ".to_string(), content: "Untitled".to_string(), content_type: ContentType::PlainText },
+            ImportEntry::from_file("page.html", "<p>Body</p>".to_string()),
+        ];
+        let summary = service.import_documents(entries, Some(project.project().id), None).await.unwrap();
+
+        assert_eq!((summary.created, summary.failed), (2, 1));
+        assert_eq!(summary.failures[0].index, 1);
+        let stored = documents.find_by_id(&summary.document_ids[1]).await.unwrap().unwrap();
+        assert_eq!(stored.content_type, ContentType::Html);
+
+        let project = projects.find_by_id(&project.project().id).await.unwrap().unwrap();
+        assert_eq!(project.document_ids, summary.document_ids);
+
+        let missing = service.import_documents(Vec::new(), Some(EntityId::new()), None).await;
+        assert!(matches!(missing, Err(WritemagicError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_import_writes_nothing_when_the_project_changed_meanwhile() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let project = ProjectManagementService::new(projects.clone(), documents.clone())
+            .create_project(ProjectName::new("Migration").unwrap(), None, None)
+            .await
+            .unwrap();
+        let store = ConflictOnApply { inner: InMemoryWriteStore::new((*documents).clone(), (*projects).clone()), projects: projects.clone() };
+        let service = DocumentManagementService::new(documents.clone())
+            .with_project_repository(projects.clone())
+            .with_write_store(Arc::new(store));
+
+        let entries = vec![
+            ImportEntry::from_file("intro.md", "# Intro".to_string()),
+            ImportEntry::from_file("notes.txt", "Notes".to_string()),
+        ];
+        let result = service.import_documents(entries, Some(project.project().id), None).await;
+
+        assert!(matches!(result, Err(WritemagicError::Conflict { .. })), "{:?}", result);
+        assert_eq!(documents.count().await.unwrap(), 0);
+    }
+
+    /// Store that lets another writer update every project just before applying
+    struct ConflictOnApply {
+        inner: InMemoryWriteStore,
+        projects: Arc<crate::repositories::InMemoryProjectRepository>,
+    }
+
+    #[async_trait::async_trait]
+    impl StagedWriteStore for ConflictOnApply {
+        async fn apply(&self, writes: StagedWrites) -> Result<()> {
+            for write in &writes.projects {
+                let mut project = self.projects.find_by_id(&write.entity.id).await?.unwrap();
+                project.version += 1;
+                self.projects.save(&project).await?;
+            }
+            self.inner.apply(writes).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_system_prompt_is_trimmed_cleared_and_length_checked() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let service = ProjectManagementService::new(projects.clone(), Arc::new(InMemoryDocumentRepository::new()));
+        let project_id = service.create_project(ProjectName::new("Novel").unwrap(), None, None).await.unwrap().project().id;
+
+        let updated = service
+            .set_project_system_prompt(project_id, Some("  Write in British English.\n".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(updated.project().version, 2);
+        assert_eq!(service.project_system_prompt(project_id).await.unwrap().as_deref(), Some("Write in British English."));
+
+        let too_long = "a".repeat(writemagic_shared::validation::SYSTEM_PROMPT_MAX_LENGTH + 1);
+        assert!(service.set_project_system_prompt(project_id, Some(too_long), None).await.is_err());
+
+        service.set_project_system_prompt(project_id, Some("   ".to_string()), None).await.unwrap();
+        assert_eq!(service.project_system_prompt(project_id).await.unwrap(), None);
+        assert_eq!(projects.find_by_id(&project_id).await.unwrap().unwrap().version, 3);
+
+        let missing = EntityId::new();
+        assert!(matches!(service.set_project_system_prompt(missing, None, None).await, Err(WritemagicError::NotFound { .. })));
+        assert!(matches!(service.project_system_prompt(missing).await, Err(WritemagicError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_archive_cascades_to_documents_but_unarchive_does_not() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let document_service = DocumentManagementService::new(documents.clone());
+        let service = ProjectManagementService::new(projects.clone(), documents.clone());
+
+        let project = service.create_project(ProjectName::new("Season").unwrap(), None, None).await.unwrap();
+        let project_id = project.project().id;
+        let first = create_document(&document_service, "Beds").await;
+        let second = create_document(&document_service, "Paths").await;
+        service.add_document_to_project(project_id, first, None).await.unwrap();
+        service.add_document_to_project(project_id, second, None).await.unwrap();
+        let version_before = projects.find_by_id(&project_id).await.unwrap().unwrap().version;
+
+        let archived = service.archive_project(project_id, None, true).await.unwrap();
+        assert!(archived.project.project().is_archived);
+        assert_eq!(archived.project.project().version, version_before + 1);
+        assert_eq!(archived.archived_documents, vec![first, second]);
+        assert_eq!(archived.document_events.len(), 2);
+        assert!(matches!(archived.project_events[..], [ProjectEvent::ProjectArchived { cascade: true, .. }]));
+        assert!(documents.find_by_id(&first).await.unwrap().unwrap().is_archived);
+
+        let pagination = writemagic_shared::Pagination::new(0, 10).unwrap();
+        assert!(projects.find_all_projects(pagination.clone(), false).await.unwrap().is_empty());
+        assert_eq!(projects.find_all_projects(pagination.clone(), true).await.unwrap().len(), 1);
+        assert!(document_service.list_documents(pagination).await.unwrap().is_empty());
+        assert!(service.archive_project(project_id, None, true).await.is_err());
+        let missing = service.archive_project(EntityId::new(), None, false).await.unwrap_err();
+        assert!(matches!(missing, WritemagicError::NotFound { .. }));
+
+        let unarchived = service.unarchive_project(project_id, None).await.unwrap();
+        assert!(!unarchived.project.project().is_archived);
+        assert!(unarchived.project.project().archived_at.is_none());
+        assert!(documents.find_by_id(&second).await.unwrap().unwrap().is_archived);
+    }
+
+    #[tokio::test]
+    async fn test_project_saves_are_refused_at_a_stale_version() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let projects = InMemoryProjectRepository::new();
+        let loaded = projects.save(&Project::new("Atlas".to_string(), None, None)).await.unwrap();
+
+        let mut first = loaded.clone();
+        first.archive(None);
+        projects.save_if_version(&first, loaded.version).await.unwrap();
+
+        let mut second = loaded.clone();
+        second.update_name("Almanac".to_string(), None);
+        let error = projects.save_if_version(&second, loaded.version).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Conflict { .. }));
+        assert!(projects.find_by_id(&loaded.id).await.unwrap().unwrap().is_archived);
+    }
+
+    #[tokio::test]
+    async fn test_project_edits_report_a_missing_project_as_not_found() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let service = ProjectManagementService::new(Arc::new(InMemoryProjectRepository::new()), documents.clone());
+        let document_id = create_document(&DocumentManagementService::new(documents), "Outline").await;
+        let missing = EntityId::new();
+
+        let renamed = service.update_project_name(missing, ProjectName::new("Atlas").unwrap(), None).await;
+        assert!(matches!(renamed, Err(WritemagicError::NotFound { .. })), "{:?}", renamed);
+        let added = service.add_document_to_project(missing, document_id, None).await;
+        assert!(matches!(added, Err(WritemagicError::NotFound { .. })), "{:?}", added);
+        let removed = service.remove_document_from_project(missing, document_id, None).await;
+        assert!(matches!(removed, Err(WritemagicError::NotFound { .. })), "{:?}", removed);
+    }
+
+    /// Write store that refuses every batch
+    struct FailingWriteStore;
+
+    #[async_trait::async_trait]
+    impl StagedWriteStore for FailingWriteStore {
+        async fn apply(&self, _writes: StagedWrites) -> Result<()> {
+            Err(WritemagicError::database("disk full"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_move_documents_updates_both_projects_with_one_event() {
+        use crate::repositories::InMemoryProjectRepository;
+        use writemagic_shared::{DomainEvent, InMemoryEventBus};
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let published = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_published = published.clone();
+        event_bus
+            .add_typed_subscription(move |event: &ProjectEvent| -> Result<()> {
+                handler_published.lock().unwrap().push(event.aggregate_id());
+                Ok(())
+            })
+            .await;
+        let document_service = DocumentManagementService::new(documents.clone());
+        let service = ProjectManagementService::new(projects.clone(), documents.clone())
+            .with_write_store(Arc::new(InMemoryWriteStore::new((*documents).clone(), (*projects).clone())))
+            .with_event_bus(event_bus);
+
+        let drafts = service.create_project(ProjectName::new("Drafts").unwrap(), None, None).await.unwrap().project().id;
+        let book = service.create_project(ProjectName::new("Book").unwrap(), None, None).await.unwrap().project().id;
+        let first = create_document(&document_service, "Chapter one").await;
+        let second = create_document(&document_service, "Chapter two").await;
+        let stays = create_document(&document_service, "Notes").await;
+        for document_id in [first, second, stays] {
+            service.add_document_to_project(drafts, document_id, None).await.unwrap();
+        }
+        published.lock().unwrap().clear();
+
+        let moved = service.move_documents(vec![first, second, first], Some(drafts), book, None).await.unwrap();
+        assert_eq!(moved.project.project().document_ids, vec![first, second]);
+        assert_eq!(moved.source_projects[0].project().document_ids, vec![stays]);
+        assert!(matches!(
+            &moved.event,
+            ProjectEvent::DocumentsMoved { project_id, from_project_ids, document_ids, .. }
+                if *project_id == book && *from_project_ids == vec![drafts] && *document_ids == vec![first, second]
+        ));
+        assert_eq!(projects.find_by_id(&drafts).await.unwrap().unwrap().document_ids, vec![stays]);
+
+        // Without a source the documents leave every project holding them
+        let moved = service.move_documents(vec![stays], None, book, None).await.unwrap();
+        assert_eq!(moved.project.project().document_ids, vec![first, second, stays]);
+        assert!(projects.find_by_id(&drafts).await.unwrap().unwrap().document_ids.is_empty());
+
+        assert_eq!(*published.lock().unwrap(), vec![book, book]);
+    }
+
+    #[tokio::test]
+    async fn test_project_changes_and_archives_publish_their_events() {
+        use crate::repositories::InMemoryProjectRepository;
+        use writemagic_shared::{DomainEvent, InMemoryEventBus};
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let project_seen = seen.clone();
+        event_bus
+            .add_typed_subscription(move |event: &ProjectEvent| -> Result<()> {
+                project_seen.lock().unwrap().push(event.event_type());
+                Ok(())
+            })
+            .await;
+        let document_seen = seen.clone();
+        event_bus
+            .add_typed_subscription(move |event: &DocumentEvent| -> Result<()> {
+                document_seen.lock().unwrap().push(event.event_type());
+                Ok(())
+            })
+            .await;
+        let document_service = DocumentManagementService::new(documents.clone());
+        let service = ProjectManagementService::new(Arc::new(InMemoryProjectRepository::new()), documents)
+            .with_event_bus(event_bus);
+
+        let project_id = service.create_project(ProjectName::new("Drafts").unwrap(), None, None).await.unwrap().project().id;
+        let document_id = create_document(&document_service, "Chapter one").await;
+        service.add_document_to_project(project_id, document_id, None).await.unwrap();
+        service.update_project_name(project_id, ProjectName::new("Book").unwrap(), None).await.unwrap();
+        service.set_project_system_prompt(project_id, Some("Be brief".to_string()), None).await.unwrap();
+        service.archive_project(project_id, None, true).await.unwrap();
+        service.unarchive_project(project_id, None).await.unwrap();
+        service.remove_document_from_project(project_id, document_id, None).await.unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "ProjectCreated",
+                "DocumentAdded",
+                "ProjectNameUpdated",
+                "ProjectSystemPromptUpdated",
+                "DocumentArchived",
+                "ProjectArchived",
+                "ProjectUnarchived",
+                "DocumentRemoved",
+            ]
+        );
+
+        assert!(service.move_documents(vec![first], Some(drafts), book, None).await.is_err());
+        let missing = service.move_documents(vec![EntityId::new()], None, drafts, None).await;
+        assert!(matches!(missing, Err(WritemagicError::NotFound { .. })), "{:?}", missing);
+        let missing = service.move_documents(vec![first], None, EntityId::new(), None).await;
+        assert!(matches!(missing, Err(WritemagicError::NotFound { .. })), "{:?}", missing);
+        service.archive_project(drafts, None, false).await.unwrap();
+        assert!(service.move_documents(vec![first], Some(book), drafts, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_move_documents_changes_no_project_when_the_batch_fails() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let document_service = DocumentManagementService::new(documents.clone());
+        let service = ProjectManagementService::new(projects.clone(), documents.clone())
+            .with_write_store(Arc::new(FailingWriteStore));
+
+        let source = service.create_project(ProjectName::new("Source").unwrap(), None, None).await.unwrap().project().id;
+        let target = service.create_project(ProjectName::new("Target").unwrap(), None, None).await.unwrap().project().id;
+        let document_id = create_document(&document_service, "Outline").await;
+        service.add_document_to_project(source, document_id, None).await.unwrap();
+        let before = projects.find_by_id(&source).await.unwrap().unwrap();
+
+        assert!(service.move_documents(vec![document_id], Some(source), target, None).await.is_err());
+
+        let after = projects.find_by_id(&source).await.unwrap().unwrap();
+        assert_eq!(after.document_ids, vec![document_id]);
+        assert_eq!(after.version, before.version);
+        assert!(projects.find_by_id(&target).await.unwrap().unwrap().document_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_move_documents_does_not_overwrite_a_concurrent_project_change() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let document_service = DocumentManagementService::new(documents.clone());
+        let store = ConflictOnApply {
+            inner: InMemoryWriteStore::new((*documents).clone(), (*projects).clone()),
+            projects: projects.clone(),
+        };
+        let service = ProjectManagementService::new(projects.clone(), documents.clone())
+            .with_write_store(Arc::new(store));
+
+        let source = service.create_project(ProjectName::new("Source").unwrap(), None, None).await.unwrap().project().id;
+        let target = service.create_project(ProjectName::new("Target").unwrap(), None, None).await.unwrap().project().id;
+        let document_id = create_document(&document_service, "Outline").await;
+        service.add_document_to_project(source, document_id, None).await.unwrap();
+
+        let result = service.move_documents(vec![document_id], Some(source), target, None).await;
+        assert!(matches!(result, Err(WritemagicError::Conflict { .. })), "{:?}", result);
+        assert_eq!(projects.find_by_id(&source).await.unwrap().unwrap().document_ids, vec![document_id]);
+    }
+
+    #[tokio::test]
+    async fn test_tag_changes_bump_version_and_drive_find_by_tag() {
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(documents.clone());
+        let document_id = create_document(&service, "Compost notes").await;
+        let tag = |name: &str| DocumentTag::new(name).unwrap();
+        let pagination = writemagic_shared::Pagination::new(0, 10).unwrap();
+
+        let tagged = service.set_tags(document_id, vec![tag("soil"), tag("soil"), tag("spring")], None).await.unwrap();
+        assert_eq!(tagged.document().tags, vec![tag("soil"), tag("spring")]);
+        let version = tagged.document().version;
+
+        let added = service.add_tag(document_id, tag("compost"), None).await.unwrap();
+        assert_eq!(added.document().version, version + 1);
+        let removed = service.remove_tag(document_id, &tag("soil"), None).await.unwrap();
+        assert_eq!(removed.document().version, version + 2);
+        assert_eq!(removed.document().tags, vec![tag("spring"), tag("compost")]);
+
+        // Removing a tag the document lacks changes nothing
+        let unchanged = service.remove_tag(document_id, &tag("soil"), None).await.unwrap();
+        assert_eq!(unchanged.document().version, version + 2);
+
+        assert_eq!(documents.find_by_tag(&tag("compost"), pagination.clone()).await.unwrap().len(), 1);
+        assert!(documents.find_by_tag(&tag("soil"), pagination).await.unwrap().is_empty());
+        assert!(DocumentTag::new("Has Space").is_err());
+    }
+
+    #[test]
+    fn test_content_statistics_ignore_markdown_syntax() {
+        let rates = ReadingTimeConfig { words_per_minute: 60, ..ReadingTimeConfig::default() };
+        let service = ContentAnalysisService::new().with_reading_time_config(rates.clone());
+        let content = DocumentContent::new(
+            "# Spring Planting\n\nSow *peas* early. Read the [guide](https://example.com/guide) first!\n\nWater **daily**.",
+        )
+        .unwrap();
+
+        let statistics = service.analyze(&content);
+
+        assert_eq!(statistics.word_count, 11);
+        assert_eq!(statistics.sentence_count, 4);
+        assert_eq!(statistics.paragraph_count, 3);
+        // Reading time comes from the shared estimator, which reads the raw Markdown
+        assert_eq!(
+            statistics.reading_time_seconds,
+            estimate_reading_time(content.as_str(), &ContentType::Markdown, "en", &rates)
+        );
+        assert_eq!(
+            statistics.character_count_excluding_whitespace,
+            "SpringPlantingSowpeasearly.Readtheguidefirst!Waterdaily.".len() as u32
+        );
+        assert_eq!(service.analyze_arena(content.as_str()), statistics);
+    }
+
+    #[test]
+    fn test_document_statistics_use_the_documents_reading_time() {
+        let service = ContentAnalysisService::new();
+        let mut document = Document::new(
+            "Garden".to_string(),
+            "春天在花园里种豌豆和番茄，每天早上浇水，等待它们慢慢长大。".repeat(10),
+            ContentType::PlainText,
+            None,
+        );
+        document.detect_language(&LanguageConfig::default());
+
+        let statistics = service.analyze_document(&document);
+
+        assert_eq!(statistics.reading_time_seconds, document.reading_time(&ReadingTimeConfig::default()));
+        assert!(statistics.reading_time_seconds > 0);
+    }
+
+    #[test]
+    fn test_large_content_is_analyzed_in_the_arena() {
+        let service = ContentAnalysisService::new();
+        let paragraph = "Tomatoes ripen *slowly* in cool weather. Pick them early!\n\n";
+        let content = paragraph.repeat(ARENA_ANALYSIS_THRESHOLD / paragraph.len() + 1);
+
+        let statistics = service.analyze(&DocumentContent::new(&content).unwrap());
+
+        let paragraphs = (ARENA_ANALYSIS_THRESHOLD / paragraph.len() + 1) as u32;
+        assert_eq!(statistics.paragraph_count, paragraphs);
+        assert_eq!(statistics.word_count, 9 * paragraphs);
+        assert_eq!(statistics.sentence_count, 2 * paragraphs);
+        assert_eq!(writemagic_shared::allocators::thread_arena_stats().0, 0);
+
+        // A caller already holding the arena keeps its allocations, and the count falls back to the heap
+        let nested = writemagic_shared::with_thread_arena(|arena| {
+            let held = arena.alloc_slice(3, [7u64, 8, 9]).unwrap();
+            let nested = service.analyze_arena(&content);
+            assert_eq!(held, &[7, 8, 9]);
+            nested
+        });
+        assert_eq!(nested.map(|nested| nested.word_count), Some(statistics.word_count));
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_without_auto_apply_does_not_persist() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Tomatoes and basil. Tomatoes need sunlight.").await;
+
+        let suggestions = service.suggest_and_apply_tags(document_id, false).await.unwrap();
+        assert_eq!(suggestions.first().map(|t| t.as_str()), Some("tomatoes"));
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert!(stored.tags.is_empty());
+        assert_eq!(stored.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_with_auto_apply_persists() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Tomatoes and basil. Tomatoes need sunlight.").await;
+
+        let suggestions = service.suggest_and_apply_tags(document_id, true).await.unwrap();
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.tags, suggestions);
+        assert_eq!(stored.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_purge_matches_real_purge() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let kept = create_document(&service, "Keep me").await;
+        let first = create_document(&service, "Delete me").await;
+        let second = create_document(&service, "Delete me too").await;
+        service.delete_document(first, None).await.unwrap();
+        service.delete_document(second, None).await.unwrap();
+
+        let preview = service.purge_deleted(true).await.unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.count(), 2);
+        assert_eq!(repository.count().await.unwrap(), 3);
+
+        let purged = service.purge_deleted(false).await.unwrap();
+        let mut expected = preview.affected_ids.clone();
+        let mut actual = purged.affected_ids.clone();
+        expected.sort_by_key(|id| id.to_string());
+        actual.sort_by_key(|id| id.to_string());
+        assert_eq!(actual, expected);
+
+        assert_eq!(repository.count().await.unwrap(), 1);
+        assert!(repository.find_by_id(&kept).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_batch_delete_and_reprocess_leave_data_intact() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Two words").await;
+        let missing = EntityId::new();
+
+        let preview = service.batch_delete_documents(&[document_id, missing, document_id], None, true).await.unwrap();
+        assert_eq!(preview.affected_ids, vec![document_id]);
+        assert!(!repository.find_by_id(&document_id).await.unwrap().unwrap().is_deleted);
+
+        let mut stale = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        stale.word_count = 0;
+        repository.save(&stale).await.unwrap();
+
+        let preview = service.reprocess_documents(true).await.unwrap();
+        assert_eq!(preview.affected_ids, vec![document_id]);
+        assert_eq!(repository.find_by_id(&document_id).await.unwrap().unwrap().word_count, 0);
+
+        let applied = service.reprocess_documents(false).await.unwrap();
+        assert_eq!(applied.affected_ids, vec![document_id]);
+        assert_eq!(repository.find_by_id(&document_id).await.unwrap().unwrap().word_count, 2);
+
+        let deleted = service.batch_delete_documents(&[document_id], None, false).await.unwrap();
+        assert_eq!(deleted.count(), 1);
+        assert!(repository.find_by_id(&document_id).await.unwrap().unwrap().is_deleted);
+    }
+    #[tokio::test]
+    async fn test_interleaved_saves_of_one_version_let_exactly_one_win() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Opening").await;
+
+        let loaded = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        let mut first = loaded.clone();
+        let mut second = loaded.clone();
+        first.update_content("First writer".to_string(), None);
+        second.update_content("Second writer".to_string(), None);
+
+        let (first_result, second_result) = tokio::join!(
+            repository.save_if_version(&first, loaded.version),
+            repository.save_if_version(&second, loaded.version),
+        );
+        assert_eq!([first_result.is_ok(), second_result.is_ok()].iter().filter(|ok| **ok).count(), 1);
+        let loser = if first_result.is_ok() { second_result } else { first_result };
+        assert!(matches!(loser, Err(ConcurrencyError::VersionMismatch { .. })));
+
+        // Converted for services, a stale save surfaces as a conflict
+        let mut edited = loaded.clone();
+        edited.update_title("Stale rename".to_string(), None);
+        let error: WritemagicError = repository.save_if_version(&edited, loaded.version).await.unwrap_err().into();
+        assert!(matches!(error, WritemagicError::Conflict { .. }));
+
+        let updated = service
+            .update_document_content(document_id, DocumentContent::new("Third writer").unwrap(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(updated.document().version, loaded.version + 2);
+    }
+
+    #[tokio::test]
+    async fn test_english_content_detects_en_and_counts_by_whitespace() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "The quick brown fox jumps over the lazy dog near the river bank.").await;
+
+        let document = service.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(document.document().language, "en");
+        assert_eq!(document.document().word_count, 13);
+    }
+
+    #[tokio::test]
+    async fn test_chinese_content_detects_zh_and_counts_by_character() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "Draft").await;
+
+        let updated = service
+            .update_document_content(document_id, DocumentContent::new("我今天在图书馆写了一篇关于春天的文章。明天我还要继续修改。").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(updated.document().language, "zh");
+        assert_eq!(updated.document().word_count, 27);
+    }
+
+    #[tokio::test]
+    async fn test_language_override_takes_precedence_over_detection() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "我今天在图书馆写了一篇关于春天的文章。明天我还要继续修改。").await;
+
+        let overridden = service.set_document_language(document_id, Some("EN".to_string()), None).await.unwrap();
+        assert_eq!(overridden.document().effective_language(), "en");
+        assert_eq!(overridden.document().word_count, 1);
+
+        let updated = service
+            .update_document_content(document_id, DocumentContent::new("明天我还要继续修改这篇关于春天的文章。我会写得更好。").unwrap(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(updated.document().language, "zh");
+        assert_eq!(updated.document().effective_language(), "en");
+        assert_eq!(updated.document().word_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_content_uses_configured_default_language() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()))
+            .with_language_config(LanguageConfig {
+                default_language: "de".to_string(),
+                ..LanguageConfig::default()
+            });
+        let document_id = create_document(&service, "ok 42").await;
+
+        let document = service.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(document.document().language, "de");
+    }
+
+    #[tokio::test]
+    async fn test_convert_html_document_to_markdown_bumps_version() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let created = service
+            .create_document(
+                DocumentTitle::new("Imported").unwrap(),
+                DocumentContent::new("<h1>Notes</h1><p>Some <em>text</em></p>").unwrap(),
+                ContentType::Html,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let document_id = created.document().id;
+
+        let converted = service.convert_document_format(document_id, ContentType::Markdown, None).await.unwrap();
+        assert_eq!(converted.document().content, "# Notes\n\nSome *text*\n");
+        assert_eq!(converted.document().version, created.document().version + 1);
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.content_type, ContentType::Markdown);
+        assert!(service.convert_document_format(document_id, ContentType::Json, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_html_content_is_sanitized_when_stored() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let created = service
+            .create_document(
+                DocumentTitle::new("Imported").unwrap(),
+                DocumentContent::new("<p onclick=\"steal()\">Hi<script>steal()</script></p>").unwrap(),
+                ContentType::Html,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let document_id = created.document().id;
+        assert_eq!(created.document().content, "<p>Hi</p>");
+
+        let updated = service
+            .update_document_content(document_id, DocumentContent::new("<a href=\"javascript:steal()\">link</a>").unwrap(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(updated.document().content, "<a>link</a>");
+
+        let version = updated.document().version;
+        service.apply_content_delta(document_id, version, 0, 0, "<img src=x onerror=steal()>", None).await.unwrap();
+        let edited = service.get_document(&document_id).await.unwrap().unwrap();
+        assert!(!edited.document().content.contains("onerror"));
+    }
+
+    #[tokio::test]
+    async fn test_create_document_with_idempotency_key_is_deduplicated() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = &DocumentManagementService::new(repository.clone());
+        let create = move |key: &str| {
+            service.create_document(
+                DocumentTitle::new("Retried").unwrap(),
+                DocumentContent::new("Once only").unwrap(),
+                ContentType::Markdown,
+                None,
+                Some(key.to_string()),
+            )
+        };
+
+        let first = create("request-1").await.unwrap();
+        let retried = create("request-1").await.unwrap();
+        let other = create("request-2").await.unwrap();
+
+        assert_eq!(retried.document().id, first.document().id);
+        assert_ne!(other.document().id, first.document().id);
+        assert_eq!(repository.find_all(writemagic_shared::Pagination::default()).await.unwrap().len(), 2);
+        assert!(create("  ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_version_history_keeps_prior_content_and_restores_it() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone()).with_version_repository(
+            Arc::new(InMemoryDocumentVersionRepository::new()),
+            VersionHistoryConfig { max_versions_per_document: 2 },
+        );
+        let document_id = create_document(&service, "First draft").await;
+        for content in ["Second draft", "Third draft", "Final draft"] {
+            service
+                .update_document_content(document_id, DocumentContent::new(content).unwrap(), None, None)
+                .await
+                .unwrap();
+        }
+
+        // Only the two most recent prior versions are retained, newest first
+        let history = service.get_version_history(document_id, writemagic_shared::Pagination::default()).await.unwrap();
+        let contents: Vec<&str> = history.iter().map(|version| version.content.as_str()).collect();
+        assert_eq!(contents, vec!["Third draft", "Second draft"]);
+
+        let restored = service.restore_version(document_id, history[1].version, None).await.unwrap();
+        assert_eq!(restored.document().content, "Second draft");
+        assert_eq!(restored.document().version, history[0].version + 2);
+        assert!(service.restore_version(document_id, 1, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restoring_a_version_brings_back_its_content_type() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "# Draft").await;
+        service
+            .update_document_content(document_id, DocumentContent::new("# Second").unwrap(), None, None)
+            .await
+            .unwrap();
+        service.convert_document_format(document_id, ContentType::Html, None).await.unwrap();
+
+        let history = service.get_version_history(document_id, writemagic_shared::Pagination::default()).await.unwrap();
+        let draft = history.iter().find(|version| version.content == "# Draft").unwrap();
+        let restored = service.restore_version(document_id, draft.version, None).await.unwrap();
+        assert_eq!(restored.document().content, "# Draft");
+        assert_eq!(restored.document().content_type, ContentType::Markdown);
+
+        // The HTML it replaced is kept as a version of its own
+        let history = service.get_version_history(document_id, writemagic_shared::Pagination::default()).await.unwrap();
+        assert_eq!(history[0].content_type, ContentType::Html);
+    }
+
+    #[tokio::test]
+    async fn test_create_from_template_renders_front_matter_into_the_document() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let template = service
+            .save_template(
+                DocumentTemplate::new(
+                    "Chapter",
+                    "---\ntitle: \"Chapter {{number}}\"\ntags: [draft, fiction]\n---\n# Chapter {{number}}\n\n{{opening}}",
+                    ContentType::Html,
+                )
+                .with_required_variables(["number"]),
+            )
+            .await
+            .unwrap();
+
+        let variables = HashMap::from([
+            ("number".to_string(), "3".to_string()),
+            ("opening".to_string(), "It was raining.".to_string()),
+        ]);
+        let aggregate = service.create_from_template(template.id, &variables, None).await.unwrap();
+        let document = aggregate.document();
+        assert_eq!(document.title, "Chapter 3");
+        assert_eq!(document.content, "# Chapter 3\n\nIt was raining.");
+        assert_eq!(document.content_type, ContentType::Html);
+        assert_eq!(document.tags, vec![DocumentTag::new("draft").unwrap(), DocumentTag::new("fiction").unwrap()]);
+
+        let error = service.create_from_template(template.id, &HashMap::new(), None).await.unwrap_err();
+        let TemplateError::Invalid(errors) = error else {
+            panic!("expected field errors, got {:?}", error);
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].field.as_str(), errors[0].rule.as_str()), ("variables.number", "required"));
+
+        let error = service.create_from_template(EntityId::new(), &variables, None).await.unwrap_err();
+        assert!(matches!(WritemagicError::from(error), WritemagicError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_document_lock_blocks_other_editors_until_released_or_expired() {
+        let service = &DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(service, "Shared draft").await;
+        let (alice, bob) = (EntityId::new(), EntityId::new());
+        let edit = move |editor: Option<EntityId>| {
+            service.update_document_content(document_id, DocumentContent::new("Edited").unwrap(), None, editor)
+        };
+
+        service.acquire_lock(document_id, alice, std::time::Duration::from_secs(30)).await.unwrap();
+        assert!(matches!(edit(Some(bob)).await, Err(WritemagicError::Conflict { .. })));
+        assert!(matches!(edit(None).await, Err(WritemagicError::Conflict { .. })));
+        assert!(matches!(
+            service.acquire_lock(document_id, bob, std::time::Duration::from_secs(30)).await,
+            Err(WritemagicError::Conflict { .. })
+        ));
+        edit(Some(alice)).await.unwrap();
+
+        assert!(service.release_lock(document_id, alice).await.unwrap());
+        edit(Some(bob)).await.unwrap();
+
+        // An abandoned lock stops blocking once it expires
+        service.acquire_lock(document_id, bob, std::time::Duration::from_millis(1)).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        service.acquire_lock(document_id, alice, std::time::Duration::from_secs(30)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_document_lock_blocks_merges_and_moves_by_anyone_but_its_holder() {
+        use crate::repositories::InMemoryProjectRepository;
+
+        let documents = Arc::new(InMemoryDocumentRepository::new());
+        let projects = Arc::new(InMemoryProjectRepository::new());
+        let service = DocumentManagementService::new(documents.clone());
+        let projects_service = ProjectManagementService::new(projects, documents)
+            .with_document_locks(service.document_locks());
+        let locked = create_document(&service, "Locked draft").await;
+        let source = create_document(&service, "Appendix").await;
+        let session = EntityId::new();
+        service.acquire_lock(locked, session, std::time::Duration::from_secs(30)).await.unwrap();
+
+        let merged = service.merge_documents(vec![source], MergeTarget::Existing(locked), None, false).await;
+        assert!(matches!(merged, Err(WritemagicError::Conflict { .. })));
+        // The session holding the lock writes through it, whoever the editor is
+        service
+            .as_lock_holder(session)
+            .merge_documents(vec![source], MergeTarget::Existing(locked), None, false)
+            .await
+            .unwrap();
+
+        let drafts = projects_service.create_project(ProjectName::new("Drafts").unwrap(), None, None).await.unwrap().project().id;
+        let moved = projects_service.move_documents(vec![locked], None, drafts, None).await;
+        assert!(matches!(moved, Err(WritemagicError::Conflict { .. })));
+        projects_service.move_documents(vec![locked], None, drafts, Some(session)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_title_bumps_version_and_keeps_content() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Tomatoes and basil.").await;
+
+        let renamed = service
+            .update_document_title(document_id, DocumentTitle::new("Kitchen Garden").unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(renamed.document().title, "Kitchen Garden");
+        assert_eq!(renamed.document().version, 2);
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Kitchen Garden");
+        assert_eq!(stored.content, "Tomatoes and basil.");
+        assert_eq!(stored.word_count, 3);
+
+        service.delete_document(document_id, None).await.unwrap();
+        let error = service
+            .update_document_title(document_id, DocumentTitle::new("Too Late").unwrap(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, WritemagicError::Validation { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_content_delta_updates_stats_and_emits_event() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Tomatoes and basil.").await;
+
+        let version = repository.find_by_id(&document_id).await.unwrap().unwrap().version;
+
+        // Splitting "Tomatoes" into two words
+        let event = service.apply_content_delta(document_id, version, 4, 0, " ", None).await.unwrap();
+        match event {
+            Some(DocumentEvent::DocumentStatsChanged { word_count, character_count, .. }) => {
+                assert_eq!(word_count, 4);
+                assert_eq!(character_count, 20);
+            }
+            other => panic!("expected DocumentStatsChanged, got {:?}", other),
+        }
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Toma toes and basil.");
+        assert_eq!(stored.word_count, 4);
+        assert!(!stored.has_stale_statistics());
+        assert_eq!(stored.version, version + 1);
+
+        let unchanged = service.apply_content_delta(document_id, stored.version, 0, 4, "Toma", None).await.unwrap();
+        assert!(unchanged.is_none());
+
+        let error = service.apply_content_delta(document_id, stored.version, 18, 10, "", None).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Validation { .. }));
+
+        // An edit made to the version before the last one is refused
+        let stale = service.apply_content_delta(document_id, version, 0, 0, "Ripe ", None).await.unwrap_err();
+        assert!(matches!(stale, WritemagicError::Conflict { .. }));
+        assert_eq!(repository.find_by_id(&document_id).await.unwrap().unwrap().content, "Toma toes and basil.");
+    }
+
+    #[tokio::test]
+    async fn test_restore_undeletes_and_rejects_live_or_missing_documents() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let document_id = create_document(&service, "Tomatoes and basil.").await;
+
+        let error = service.restore_document(document_id, None).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Validation { .. }));
+
+        service.delete_document(document_id, None).await.unwrap();
+        let restored = service.restore_document(document_id, None).await.unwrap();
+        assert!(!restored.document().is_deleted);
+        assert_eq!(restored.document().version, 3);
+
+        let stored = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert!(!stored.is_deleted);
+        assert!(stored.deleted_at.is_none());
+
+        let error = service.restore_document(EntityId::new(), None).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Repository { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_title_or_content_and_skips_deleted() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let by_title = create_titled(&service, "Garden Plan", "Rows and beds").await;
+        let by_content = create_titled(&service, "Notes", "Water the garden daily").await;
+        let deleted = create_titled(&service, "Old garden", "Gone").await;
+        create_titled(&service, "Recipes", "Soup").await;
+        service.delete_document(deleted, None).await.unwrap();
+
+        let page = writemagic_shared::Pagination::default();
+        let found: std::collections::HashSet<EntityId> = repository.search("GARDEN", page.clone(), false).await.unwrap()
+            .into_iter().map(|d| d.id).collect();
+        assert_eq!(found, [by_title, by_content].into_iter().collect());
+
+        assert_eq!(repository.search("garden", page.clone(), true).await.unwrap().len(), 3);
+        assert!(repository.search("  ", page, true).await.unwrap().is_empty());
+    }
+
+    async fn create_titled(service: &DocumentManagementService, title: &str, content: &str) -> EntityId {
+        let aggregate = service
+            .create_document(
+                DocumentTitle::new(title).unwrap(),
+                DocumentContent::new(content).unwrap(),
+                ContentType::Markdown,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        aggregate.document().id
+    }
+
+    #[tokio::test]
+    async fn test_link_between_documents_records_forward_link_and_backlink() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let target_id = create_titled(&service, "Seed Catalogue", "Varieties to order this spring.").await;
+        let source_id = create_titled(&service, "Planting Plan", "Order from the [[seed catalogue]] first.").await;
+
+        let outgoing = service.outgoing_links(&source_id).await.unwrap();
+        assert_eq!(outgoing, vec![DocumentLink {
+            source_id,
+            reference: "seed catalogue".to_string(),
+            target_id: Some(target_id),
+        }]);
+
+        let backlinks = service.backlinks(&target_id).await.unwrap();
+        assert_eq!(backlinks, outgoing);
+    }
+
+    #[tokio::test]
+    async fn test_removing_link_updates_both_sides() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let target_id = create_titled(&service, "Seed Catalogue", "Varieties to order this spring.").await;
+        let source_id = create_titled(&service, "Planting Plan", &format!("See [[{}]].", target_id)).await;
+        assert_eq!(service.backlinks(&target_id).await.unwrap().len(), 1);
+
+        service
+            .update_document_content(source_id, DocumentContent::new("No references any more.").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        assert!(service.outgoing_links(&source_id).await.unwrap().is_empty());
+        assert!(service.backlinks(&target_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_link_to_missing_title_is_dangling() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let source_id = create_titled(&service, "Planting Plan", "Follow [[Watering Schedule]] daily.").await;
+
+        let outgoing = service.outgoing_links(&source_id).await.unwrap();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].reference, "Watering Schedule");
+        assert!(outgoing[0].is_dangling());
+    }
+
+    #[tokio::test]
+    async fn test_undo_restores_prior_content_and_redo_reapplies_it() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "First draft").await;
+        service
+            .update_document_content(document_id, DocumentContent::new("Second draft").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        match service.undo(document_id).await.unwrap() {
+            UndoOutcome::Applied { content, version } => {
+                assert_eq!(content, "First draft");
+                assert_eq!(version, 3);
+            }
+            other => panic!("expected undo to apply, got {:?}", other),
+        }
+
+        match service.redo(document_id).await.unwrap() {
+            UndoOutcome::Applied { content, .. } => assert_eq!(content, "Second draft"),
+            other => panic!("expected redo to apply, got {:?}", other),
+        }
+        assert_eq!(service.redo(document_id).await.unwrap(), UndoOutcome::NothingToRedo);
+    }
+
+    #[tokio::test]
+    async fn test_new_edit_after_undo_truncates_redo() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let document_id = create_document(&service, "One").await;
+        for content in ["Two", "Three"] {
+            service
+                .update_document_content(document_id, DocumentContent::new(content).unwrap(), None, None)
+                .await
+                .unwrap();
+        }
+
+        service.undo(document_id).await.unwrap();
+        service
+            .update_document_content(document_id, DocumentContent::new("Branch").unwrap(), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(service.redo(document_id).await.unwrap(), UndoOutcome::NothingToRedo);
+        match service.undo(document_id).await.unwrap() {
+            UndoOutcome::Applied { content, .. } => assert_eq!(content, "Two"),
+            other => panic!("expected undo to apply, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undo_past_bottom_reports_nothing_to_undo() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()))
+            .with_undo_config(&UndoConfig { max_depth: 1 });
+        let document_id = create_document(&service, "One").await;
+        assert_eq!(service.undo(document_id).await.unwrap(), UndoOutcome::NothingToUndo);
+
+        for content in ["Two", "Three"] {
+            service
+                .update_document_content(document_id, DocumentContent::new(content).unwrap(), None, None)
+                .await
+                .unwrap();
+        }
+
+        match service.undo(document_id).await.unwrap() {
+            UndoOutcome::Applied { content, .. } => assert_eq!(content, "Two"),
+            other => panic!("expected undo to apply, got {:?}", other),
+        }
+        assert_eq!(service.undo(document_id).await.unwrap(), UndoOutcome::NothingToUndo);
+
+        let stored = service.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.document().content, "Two");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_documents_invisible_to_other_tenants() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let tenant_a = service.scoped(TenantScope::tenant("tenant-a"));
+        let tenant_b = service.scoped(TenantScope::tenant("tenant-b"));
+        let document_id = create_document(&tenant_a, "Quarterly roadmap").await;
+
+        let pagination = writemagic_shared::Pagination::default();
+        assert_eq!(tenant_a.list_documents(pagination.clone()).await.unwrap().len(), 1);
+        assert!(tenant_b.list_documents(pagination.clone()).await.unwrap().is_empty());
+        assert!(service.scoped(TenantScope::default()).list_documents(pagination).await.unwrap().is_empty());
+
+        let stored = tenant_a.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.document().tenant_id.as_deref(), Some("tenant-a"));
+
+        let search = writemagic_shared::Pagination::default();
+        assert!(tenant_b.document_repository.search_by_title("Garden", search.clone()).await.unwrap().is_empty());
+        assert!(tenant_b.document_repository.search_by_content("roadmap", search.clone()).await.unwrap().is_empty());
+        assert_eq!(tenant_a.document_repository.search_by_content("roadmap", search).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_tenant_document_id_is_not_found() {
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let tenant_a = service.scoped(TenantScope::tenant("tenant-a"));
+        let tenant_b = service.scoped(TenantScope::tenant("tenant-b"));
+        let document_id = create_document(&tenant_a, "Private notes").await;
+
+        assert!(tenant_b.get_document(&document_id).await.unwrap().is_none());
+
+        let update = tenant_b
+            .update_document_content(document_id, DocumentContent::new("Overwritten").unwrap(), None, None)
+            .await;
+        assert!(update.is_err());
+        assert!(tenant_b.delete_document(document_id, None).await.is_err());
+
+        let stored = tenant_a.get_document(&document_id).await.unwrap().unwrap();
+        assert_eq!(stored.document().content, "Private notes");
+        assert!(!stored.document().is_deleted);
+    }
+
+    /// Repository that refuses to soft-delete one document
+    struct FailingDeleteRepository {
+        inner: InMemoryDocumentRepository,
+        fail_on: EntityId,
+        /// Document another writer edits just before the refused delete
+        concurrent_edit: Option<EntityId>,
+    }
+
+    impl FailingDeleteRepository {
+        async fn refuse(&self) -> Result<()> {
+            if let Some(document_id) = self.concurrent_edit {
+                let mut document = self.inner.find_by_id(&document_id).await?.unwrap();
+                let expected = document.version;
+                document.update_content(format!("{} Edited meanwhile.", document.content), None);
+                self.inner.save_if_version(&document, expected).await?;
+            }
+            Err(WritemagicError::database("disk full"))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Repository<Document, EntityId> for FailingDeleteRepository {
+        async fn find_by_id(&self, id: &EntityId) -> Result<Option<Document>> {
+            self.inner.find_by_id(id).await
+        }
+
+        async fn find_all(&self, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_all(pagination).await
+        }
+
+        async fn save(&self, entity: &Document) -> Result<Document> {
+            if entity.id == self.fail_on && entity.is_deleted {
+                return self.refuse().await.map(|_| entity.clone());
+            }
+            self.inner.save(entity).await
+        }
+
+        async fn delete(&self, id: &EntityId) -> Result<bool> {
+            self.inner.delete(id).await
+        }
+
+        async fn exists(&self, id: &EntityId) -> Result<bool> {
+            self.inner.exists(id).await
+        }
+
+        async fn count(&self) -> Result<u64> {
+            self.inner.count().await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl DocumentRepository for FailingDeleteRepository {
+        async fn save_if_version(
+            &self,
+            document: &Document,
+            expected_version: u64,
+        ) -> std::result::Result<Document, ConcurrencyError> {
+            if document.id == self.fail_on && document.is_deleted {
+                self.refuse().await?;
+            }
+            self.inner.save_if_version(document, expected_version).await
+        }
+
+        async fn find_by_project_id(&self, project_id: &EntityId, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_by_project_id(project_id, pagination).await
+        }
+
+        async fn find_by_tag(&self, tag: &DocumentTag, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_by_tag(tag, pagination).await
+        }
+
+        async fn find_by_content_type(&self, content_type: &ContentType, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_by_content_type(content_type, pagination).await
+        }
+
+        async fn search_by_title(&self, query: &str, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.search_by_title(query, pagination).await
+        }
+
+        async fn search_by_content(&self, query: &str, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.search_by_content(query, pagination).await
+        }
+
+        async fn search(&self, query: &str, pagination: writemagic_shared::Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+            self.inner.search(query, pagination, include_deleted).await
+        }
+
+        async fn find_by_creator(&self, user_id: &EntityId, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_by_creator(user_id, pagination).await
+        }
+
+        async fn find_recently_updated(&self, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_recently_updated(pagination).await
+        }
+
+        async fn find_deleted(&self, pagination: writemagic_shared::Pagination) -> Result<Vec<Document>> {
+            self.inner.find_deleted(pagination).await
+        }
+
+        async fn get_statistics(&self) -> Result<crate::repositories::DocumentStatistics> {
+            self.inner.get_statistics().await
+        }
+    }
+
+    async fn create_tagged(service: &DocumentManagementService, content: &str, tags: &[&str]) -> EntityId {
+        let document_id = create_document(service, content).await;
+        let mut document = service.document_repository.find_by_id(&document_id).await.unwrap().unwrap();
+        document.add_tags(tags.iter().map(|tag| DocumentTag::new(*tag).unwrap()).collect(), None);
+        service.document_repository.save(&document).await.unwrap();
+        document_id
+    }
+
+    fn new_target() -> MergeTarget {
+        MergeTarget::New { title: DocumentTitle::new("Combined").unwrap(), content_type: ContentType::Markdown }
+    }
+
+    #[tokio::test]
+    async fn test_merge_into_new_document_concatenates_in_order() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let first = create_tagged(&service, "Plant tomatoes in May.", &["garden", "spring"]).await;
+        let second = create_tagged(&service, "Water basil daily.", &["garden"]).await;
+        let third = create_tagged(&service, "Harvest in August.", &["summer", "spring"]).await;
+
+        let merged = service
+            .merge_documents(vec![third, first, second], new_target(), Some("\n\n".to_string()), false)
+            .await
+            .unwrap();
+
+        let document = merged.document();
+        assert_eq!(document.title, "Combined");
+        assert_eq!(document.content, "Harvest in August.\n\nPlant tomatoes in May.\n\nWater basil daily.");
+        let tags: Vec<&str> = document.tags.iter().map(|tag| tag.as_str()).collect();
+        assert_eq!(tags, vec!["summer", "spring", "garden"]);
+        assert_eq!(document.word_count, 10);
+        assert_eq!(document.character_count, document.content.len() as u32);
+
+        for source in [first, second, third] {
+            assert!(!repository.find_by_id(&source).await.unwrap().unwrap().is_deleted);
+        }
+        assert_eq!(repository.find_by_id(&document.id).await.unwrap().unwrap().content, document.content);
+    }
+
+    #[tokio::test]
+    async fn test_merge_publishes_an_event_per_write() {
+        use writemagic_shared::InMemoryEventBus;
+
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_seen = seen.clone();
+        let service = DocumentManagementService::new(Arc::new(InMemoryDocumentRepository::new()));
+        let target = create_document(&service, "Intro.").await;
+        let source = create_document(&service, "Body.").await;
+        event_bus
+            .add_typed_subscription(move |event: &DocumentEvent| -> Result<()> {
+                handler_seen.lock().unwrap().push(event.clone());
+                Ok(())
+            })
+            .await;
+        let service = service.with_event_bus(event_bus);
+
+        service.merge_documents(vec![source], MergeTarget::Existing(target), Some(" ".to_string()), true).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert!(matches!(
+            &seen[..],
+            [
+                DocumentEvent::DocumentContentUpdated { document_id, old_content, new_content, .. },
+                DocumentEvent::DocumentDeleted { document_id: deleted_id, .. },
+            ] if *document_id == target && old_content == "Intro." && new_content == "Intro. Body." && *deleted_id == source
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_merge_into_existing_document_deletes_sources_when_requested() {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let service = DocumentManagementService::new(repository.clone());
+        let target = create_tagged(&service, "Garden log.", &["garden"]).await;
+        let first = create_tagged(&service, "Sowed peas.", &["peas"]).await;
+        let second = create_document(&service, "Pruned roses.").await;
+
+        let merged = service
+            .merge_documents(vec![first, second], MergeTarget::Existing(target), Some(" ".to_string()), true)
+            .await
+            .unwrap();
+
+        assert_eq!(merged.document().id, target);
+        assert_eq!(merged.document().content, "Garden log. Sowed peas. Pruned roses.");
+        assert_eq!(merged.document().tags.len(), 2);
+        assert_eq!(merged.document().version, 3);
+        assert!(repository.find_by_id(&first).await.unwrap().unwrap().is_deleted);
+        assert!(repository.find_by_id(&second).await.unwrap().unwrap().is_deleted);
+        assert!(!repository.find_by_id(&target).await.unwrap().unwrap().is_deleted);
+    }
+
+    #[tokio::test]
+    async fn test_failed_merge_rolls_back_every_write() {
+        let inner = InMemoryDocumentRepository::new();
+        let seeding = DocumentManagementService::new(Arc::new(inner.clone()));
+        let target = create_document(&seeding, "Garden log.").await;
+        let first = create_document(&seeding, "Sowed peas.").await;
+        let second = create_document(&seeding, "Pruned roses.").await;
+
+        let repository = Arc::new(FailingDeleteRepository { inner: inner.clone(), fail_on: second, concurrent_edit: None });
+        let service = DocumentManagementService::new(repository);
+
+        let result = service.merge_documents(vec![first, second], MergeTarget::Existing(target), None, true).await;
+        assert!(result.is_err());
+        let stored_target = inner.find_by_id(&target).await.unwrap().unwrap();
+        assert_eq!(stored_target.content, "Garden log.");
+        assert!(!inner.find_by_id(&first).await.unwrap().unwrap().is_deleted);
+
+        let before = inner.count().await.unwrap();
+        let result = service.merge_documents(vec![first, second], new_target(), None, true).await;
+        assert!(result.is_err());
+        assert_eq!(inner.count().await.unwrap(), before);
+        assert!(!inner.find_by_id(&first).await.unwrap().unwrap().is_deleted);
+    }
+
+    #[tokio::test]
+    async fn test_failed_merge_keeps_edits_made_after_its_writes() {
+        let inner = InMemoryDocumentRepository::new();
+        let seeding = DocumentManagementService::new(Arc::new(inner.clone()));
+        let target = create_document(&seeding, "Garden log.").await;
+        let first = create_document(&seeding, "Sowed peas.").await;
+        let second = create_document(&seeding, "Pruned roses.").await;
+
+        let repository = FailingDeleteRepository { inner: inner.clone(), fail_on: second, concurrent_edit: Some(target) };
+        let service = DocumentManagementService::new(Arc::new(repository));
+
+        let result = service.merge_documents(vec![first, second], MergeTarget::Existing(target), None, true).await;
+        assert!(result.is_err());
+
+        // The target's edit landed on the merged text, so rolling back would lose it
+        let stored_target = inner.find_by_id(&target).await.unwrap().unwrap();
+        assert_eq!(stored_target.content, "Garden log.Sowed peas.Pruned roses. Edited meanwhile.");
+
+        // The first source's delete is undone, at a version newer than the deleted copy
+        let stored_first = inner.find_by_id(&first).await.unwrap().unwrap();
+        assert!(!stored_first.is_deleted);
+        assert_eq!(stored_first.version, 3);
+    }
+}
@@ -3,24 +3,559 @@
 // Remove duplicated attribute - already defined in lib.rs
 
 use async_trait::async_trait;
-use sqlx::{Row, SqlitePool};
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool};
 use std::collections::HashMap;
-use writemagic_shared::{EntityId, Pagination, Repository, Result, WritemagicError, Timestamp, ContentType, ContentHash, FilePath};
+use std::sync::Arc;
+use futures::StreamExt;
+use writemagic_shared::{batch_processing, EntityId, Pagination, Repository, Result, WritemagicError, Timestamp, ContentType, ContentHash, DocumentTag, FilePath};
+use crate::encryption::FieldCipher;
 use crate::entities::{Document, Project};
-use crate::repositories::{DocumentRepository, ProjectRepository, DocumentStatistics, ProjectStatistics};
+use crate::fuzzy::rank_fuzzy;
+use crate::links::DocumentLink;
+use crate::query::{DocumentPage, DocumentQuery, DocumentSortKey, SortOrder, SortValue, TagMatch};
+use crate::repositories::{
+    ConcurrencyError, DocumentRepository, DocumentLinkRepository, DocumentLockRepository, DocumentTemplateRepository,
+    DocumentVersionRepository, IdempotencyKeyRepository, ProjectRepository, DocumentStatistics, ProjectStatistics,
+    project_version_mismatch,
+};
+use crate::locking::DocumentLock;
+use crate::templates::DocumentTemplate;
+use crate::tenancy::TenantScope;
+use crate::unit_of_work::{StagedWrites, StagedWriteStore};
+use crate::versions::DocumentVersion;
+
+/// Most parameters bound in one statement, safely under SQLite's default limit of 999
+const MAX_BOUND_PARAMETERS: usize = 900;
+
+/// Turn free text into an FTS5 expression of quoted prefix terms, so user input can't inject query syntax
+fn fts_match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
 
 /// SQLite document repository implementation
 #[derive(Debug, Clone)]
 pub struct SqliteDocumentRepository {
     pool: SqlitePool,
+    /// Encrypts titles and content when encryption at rest is enabled
+    cipher: Option<Arc<FieldCipher>>,
+    /// Tenant every statement is restricted to, or every tenant when unset
+    tenant: Option<TenantScope>,
 }
 
 impl SqliteDocumentRepository {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool, cipher: None, tenant: None }
+    }
+
+    /// The same repository restricted to one tenant's documents in SQL
+    ///
+    /// Documents of other tenants are never returned, overwritten or deleted,
+    /// and saved documents are stamped with the scope's tenant.
+    pub fn scoped(&self, scope: TenantScope) -> Self {
+        Self { tenant: Some(scope), ..self.clone() }
+    }
+
+    /// First parameter of a `(? OR tenant_id IS ?)` condition: true when unscoped
+    fn all_tenants(&self) -> bool {
+        self.tenant.is_none()
+    }
+
+    /// Second parameter of a `(? OR tenant_id IS ?)` condition
+    fn tenant_id(&self) -> Option<String> {
+        self.tenant.as_ref().and_then(|scope| scope.tenant_id().map(str::to_string))
+    }
+
+    /// Store titles and content encrypted with `cipher`
+    ///
+    /// Rows written before encryption was enabled are still read as plaintext.
+    /// Encrypted rows are kept out of the full-text index, so `search` and
+    /// `search_by_content` are refused; title search and text filters in
+    /// `query` decrypt titles and content and match them here instead.
+    pub fn with_encryption(mut self, cipher: Arc<FieldCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Convert a row into a document, decrypting the fields stored with a nonce
+    fn decode(&self, mut row: SqliteDocument) -> Result<Document> {
+        if row.title_nonce.is_some() || row.content_nonce.is_some() {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                WritemagicError::configuration(format!("Document {} is encrypted but no encryption key is configured", row.id))
+            })?;
+            if let Some(nonce) = row.title_nonce.take() {
+                row.title = cipher.decrypt(&row.title, &nonce)?;
+            }
+            if let Some(nonce) = row.content_nonce.take() {
+                row.content = cipher.decrypt(&row.content, &nonce)?;
+                // The stored hash is of the ciphertext
+                row.content_hash = ContentHash::new(&row.content).to_string();
+            }
+        }
+        Ok(Document::from(row))
+    }
+
+    /// Convert a document into a row, encrypting its fields if a cipher is set
+    fn encode(&self, document: &Document) -> Result<SqliteDocument> {
+        let mut row = SqliteDocument::from(document);
+        if let Some(cipher) = &self.cipher {
+            let (title, title_nonce) = cipher.encrypt(&row.title)?;
+            let (content, content_nonce) = cipher.encrypt(&row.content)?;
+            row.title = title;
+            row.title_nonce = Some(title_nonce);
+            // A plaintext hash would let anyone with the file confirm a guessed document
+            row.content_hash = ContentHash::new(&content).to_string();
+            row.content = content;
+            row.content_nonce = Some(content_nonce);
+        }
+        Ok(row)
+    }
+
+    /// Id and decrypted title of every live document, most recently updated first
+    async fn live_titles(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query("SELECT id, title, title_nonce FROM documents WHERE is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC")
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document titles: {}", e)))?;
+
+        let mut titles = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get("id");
+            let mut title: String = row.get("title");
+            if let Some(nonce) = row.get::<Option<String>, _>("title_nonce") {
+                let cipher = self.cipher.as_ref().ok_or_else(|| {
+                    WritemagicError::configuration(format!("Document {} is encrypted but no encryption key is configured", id))
+                })?;
+                title = cipher.decrypt(&title, &nonce)?;
+            }
+            titles.push((id, title));
+        }
+        Ok(titles)
+    }
+
+    /// Refuse full-text search when encrypted documents are kept out of the index
+    fn ensure_full_text_search(&self) -> Result<()> {
+        match self.cipher {
+            Some(_) => Err(WritemagicError::feature_disabled("full-text search of encrypted documents")),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `query` filters or sorts on titles or content, which SQL can't read when encrypted
+    fn needs_decrypted_filter(&self, query: &DocumentQuery) -> bool {
+        self.cipher.is_some() && (query.text.is_some() || query.sort_key == DocumentSortKey::Title)
+    }
+
+    /// Convert rows into documents, loading tags for each
+    async fn with_tags(&self, rows: Vec<SqliteDocument>) -> Result<Vec<Document>> {
+        let mut documents = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut document = self.decode(row)?;
+
+            let tag_rows = sqlx::query(
+                "SELECT tag FROM document_tags WHERE document_id = ? ORDER BY tag"
+            )
+            .bind(document.id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document tags: {}", e)))?;
+
+            document.tags = tag_rows.into_iter()
+                .filter_map(|row| {
+                    let tag: String = row.get("tag");
+                    DocumentTag::new(tag).ok()
+                })
+                .collect();
+
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    /// Replace the stored tags of `document` with its current ones
+    async fn replace_tags(conn: &mut SqliteConnection, document: &Document) -> Result<()> {
+        let document_id = document.id.to_string();
+        sqlx::query("DELETE FROM document_tags WHERE document_id = ?")
+            .bind(&document_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to clear document tags: {}", e)))?;
+
+        for tag in &document.tags {
+            sqlx::query("INSERT INTO document_tags (document_id, tag) VALUES (?, ?)")
+                .bind(&document_id)
+                .bind(tag.as_str())
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| WritemagicError::database(&format!("Failed to save document tag: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Insert or update `entity` and its tags on `conn`, inside the caller's transaction
+    pub(crate) async fn write(&self, conn: &mut SqliteConnection, entity: &Document) -> Result<Document> {
+        let mut sqlite_doc = self.encode(entity)?;
+        if !self.all_tenants() {
+            sqlite_doc.tenant_id = self.tenant_id();
+        }
+
+        // A scoped repository must not overwrite another tenant's document
+        let saved = sqlx::query(
+            r#"
+            INSERT INTO documents (
+                id, title, content, content_type, content_hash, file_path,
+                word_count, character_count, created_at, updated_at,
+                created_by, updated_by, version, is_deleted, deleted_at,
+                language, language_override, tenant_id, is_pinned, is_generating,
+                is_archived, title_nonce, content_nonce
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                content_type = excluded.content_type,
+                content_hash = excluded.content_hash,
+                file_path = excluded.file_path,
+                word_count = excluded.word_count,
+                character_count = excluded.character_count,
+                updated_at = excluded.updated_at,
+                updated_by = excluded.updated_by,
+                version = excluded.version,
+                is_deleted = excluded.is_deleted,
+                deleted_at = excluded.deleted_at,
+                language = excluded.language,
+                language_override = excluded.language_override,
+                is_pinned = excluded.is_pinned,
+                is_generating = excluded.is_generating,
+                is_archived = excluded.is_archived,
+                title_nonce = excluded.title_nonce,
+                content_nonce = excluded.content_nonce
+            WHERE ? OR documents.tenant_id IS excluded.tenant_id
+            "#
+        )
+        .bind(&sqlite_doc.id)
+        .bind(&sqlite_doc.title)
+        .bind(&sqlite_doc.content)
+        .bind(&sqlite_doc.content_type)
+        .bind(&sqlite_doc.content_hash)
+        .bind(&sqlite_doc.file_path)
+        .bind(sqlite_doc.word_count)
+        .bind(sqlite_doc.character_count)
+        .bind(&sqlite_doc.created_at)
+        .bind(&sqlite_doc.updated_at)
+        .bind(&sqlite_doc.created_by)
+        .bind(&sqlite_doc.updated_by)
+        .bind(sqlite_doc.version)
+        .bind(sqlite_doc.is_deleted)
+        .bind(&sqlite_doc.deleted_at)
+        .bind(&sqlite_doc.language)
+        .bind(&sqlite_doc.language_override)
+        .bind(&sqlite_doc.tenant_id)
+        .bind(sqlite_doc.is_pinned)
+        .bind(sqlite_doc.is_generating)
+        .bind(sqlite_doc.is_archived)
+        .bind(&sqlite_doc.title_nonce)
+        .bind(&sqlite_doc.content_nonce)
+        .bind(self.all_tenants())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save document: {}", e)))?;
+        if saved.rows_affected() == 0 {
+            return Err(WritemagicError::not_found(format!("Document {}", entity.id)));
+        }
+
+        Self::replace_tags(conn, entity).await?;
+
+        let mut saved = entity.clone();
+        if !self.all_tenants() {
+            saved.tenant_id = self.tenant_id();
+        }
+        Ok(saved)
+    }
+
+    /// Stored version of document `id` on `conn`, or `None` if there is no such document
+    pub(crate) async fn stored_version(&self, conn: &mut SqliteConnection, id: &EntityId) -> Result<Option<u64>> {
+        let version: Option<i64> = sqlx::query_scalar("SELECT version FROM documents WHERE id = ? AND (? OR tenant_id IS ?)")
+            .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to read document version: {}", e)))?;
+        Ok(version.map(|version| version as u64))
+    }
+
+    /// Run a combined document query as a single SQL statement
+    pub async fn query(&self, query: &DocumentQuery) -> Result<DocumentPage> {
+        query.validate()?;
+
+        let mut sql = QueryBuilder::<Sqlite>::new("SELECT d.* FROM documents d WHERE 1 = 1");
+
+        if let Some(project_id) = &query.project_id {
+            sql.push(" AND d.id IN (SELECT document_id FROM project_documents WHERE project_id = ")
+                .push_bind(project_id.to_string())
+                .push(")");
+        }
+
+        let tags = query.distinct_tags();
+        if !tags.is_empty() {
+            match query.tag_match {
+                TagMatch::Any => sql.push(" AND d.id IN (SELECT document_id FROM document_tags WHERE tag IN ("),
+                TagMatch::All => sql.push(" AND (SELECT COUNT(*) FROM document_tags t WHERE t.document_id = d.id AND t.tag IN ("),
+            };
+            let mut list = sql.separated(", ");
+            for tag in &tags {
+                list.push_bind(tag.to_string());
+            }
+            match query.tag_match {
+                TagMatch::Any => sql.push("))"),
+                TagMatch::All => sql.push(")) = ").push_bind(tags.len() as i64),
+            };
+        }
+
+        if !query.content_types.is_empty() {
+            sql.push(" AND d.content_type IN (");
+            let mut list = sql.separated(", ");
+            for content_type in &query.content_types {
+                list.push_bind(content_type.to_string());
+            }
+            sql.push(")");
+        }
+
+        if let Some(deleted) = query.deleted {
+            sql.push(" AND d.is_deleted = ").push_bind(deleted);
+        }
+        if let Some(pinned) = query.pinned {
+            sql.push(" AND d.is_pinned = ").push_bind(pinned);
+        }
+        if let Some(archived) = query.archived {
+            sql.push(" AND d.is_archived = ").push_bind(archived);
+        }
+        if let Some(scope) = &query.tenant {
+            sql.push(" AND d.tenant_id IS ").push_bind(scope.tenant_id().map(str::to_string));
+        }
+        if !self.all_tenants() {
+            sql.push(" AND d.tenant_id IS ").push_bind(self.tenant_id());
+        }
+
+        if self.needs_decrypted_filter(query) {
+            // Filtered, sorted and paged here once decrypted; the project and tenant filters above still apply
+            let rows = sql.build_query_as::<SqliteDocument>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| WritemagicError::database(&format!("Failed to query documents: {}", e)))?;
+            let decrypted = DocumentQuery { project_id: None, ..query.clone() };
+            return Ok(decrypted.apply(self.with_tags(rows).await?, None));
+        }
+
+        if let Some(text) = &query.text {
+            let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            let pattern = format!("%{}%", escaped);
+            sql.push(" AND (d.title LIKE ").push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR d.content LIKE ").push_bind(pattern)
+                .push(" ESCAPE '\\')");
+        }
+
+        let column = query.sort_key.column();
+        let (comparison, direction) = match query.sort_order {
+            SortOrder::Ascending => (">", "ASC"),
+            SortOrder::Descending => ("<", "DESC"),
+        };
+
+        if let Some(cursor) = &query.cursor {
+            let push_value = |sql: &mut QueryBuilder<Sqlite>| match &cursor.value {
+                SortValue::Integer(value) => { sql.push_bind(*value); }
+                SortValue::Text(value) => { sql.push_bind(value.clone()); }
+            };
+            sql.push(format!(" AND (d.{} {} ", column, comparison));
+            push_value(&mut sql);
+            sql.push(format!(" OR (d.{} = ", column));
+            push_value(&mut sql);
+            sql.push(format!(" AND d.id {} ", comparison)).push_bind(cursor.id.clone()).push("))");
+        }
+
+        // One extra row tells whether another page follows
+        sql.push(format!(" ORDER BY d.{} {}, d.id {} LIMIT ", column, direction, direction))
+            .push_bind(query.limit as i64 + 1);
+
+        let rows = sql.build_query_as::<SqliteDocument>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to query documents: {}", e)))?;
+
+        Ok(query.page(self.with_tags(rows).await?))
+    }
+
+    /// Rebuild the full-text search index from the documents table
+    ///
+    /// At most `concurrency` documents are indexed at once, so the rebuild
+    /// leaves pool connections for other work. Each document is indexed and
+    /// checkpointed in one transaction; calling this again after an interruption
+    /// resumes with the documents not yet indexed. While a rebuild is in
+    /// progress the index triggers only remove documents that are already
+    /// re-indexed, and mark the ones an edit indexes, so documents may be
+    /// edited meanwhile. Encrypted documents are never indexed.
+    pub async fn rebuild_search_index(
+        &self,
+        concurrency: usize,
+        mut on_progress: impl FnMut(SearchIndexProgress),
+    ) -> Result<SearchIndexProgress> {
+        let database_error = |e: sqlx::Error| WritemagicError::database(format!("Failed to rebuild search index: {}", e));
+
+        let resumed: Option<i64> = sqlx::query_scalar("SELECT max_rowid FROM search_index_rebuild WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let max_rowid = match resumed {
+            Some(max_rowid) => max_rowid,
+            None => self.start_search_index_rebuild().await.map_err(database_error)?,
+        };
+
+        let (total, indexed): (i64, i64) = sqlx::query_as(
+            "SELECT (SELECT COUNT(*) FROM documents WHERE rowid <= ? AND title_nonce IS NULL AND content_nonce IS NULL), \
+             (SELECT COUNT(*) FROM search_index_rebuilt_documents WHERE document_rowid <= ?)"
+        )
+        .bind(max_rowid)
+        .bind(max_rowid)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(database_error)?;
+        let mut progress = SearchIndexProgress {
+            indexed: (indexed as u64).min(total as u64),
+            total: total as u64,
+            resumed: resumed.is_some(),
+        };
+        on_progress(progress);
+
+        let mut cursor = 0;
+        loop {
+            let rowids: Vec<i64> = sqlx::query_scalar(
+                r#"
+                SELECT rowid FROM documents
+                WHERE rowid > ? AND rowid <= ?
+                  AND title_nonce IS NULL AND content_nonce IS NULL
+                  AND rowid NOT IN (SELECT document_rowid FROM search_index_rebuilt_documents)
+                ORDER BY rowid
+                LIMIT ?
+                "#
+            )
+            .bind(cursor)
+            .bind(max_rowid)
+            .bind(SEARCH_INDEX_PAGE_SIZE)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+            let Some(&last_rowid) = rowids.last() else {
+                break;
+            };
+            cursor = last_rowid;
+            let page_len = rowids.len() as u64;
+
+            let mut inserts = std::pin::pin!(batch_processing::process_with_concurrency(
+                rowids,
+                concurrency,
+                |rowid| self.index_document(rowid),
+            ));
+            while let Some(result) = inserts.next().await {
+                result.map_err(database_error)?;
+            }
+
+            progress.indexed = (progress.indexed + page_len).min(progress.total);
+            on_progress(progress);
+        }
+        // Documents deleted or encrypted meanwhile were counted but need no indexing
+        if progress.indexed < progress.total {
+            progress.indexed = progress.total;
+            on_progress(progress);
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query("DELETE FROM search_index_rebuild")
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        sqlx::query("DELETE FROM search_index_rebuilt_documents")
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        Ok(progress)
+    }
+
+    /// Clear the index and record the rebuild, returning the last document rowid it covers
+    ///
+    /// Done in one transaction, so the index triggers see an empty index
+    /// exactly when they see a rebuild in progress.
+    async fn start_search_index_rebuild(&self) -> std::result::Result<i64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // A contentless index can only be cleared wholesale
+        sqlx::query("INSERT INTO documents_fts(documents_fts) VALUES ('delete-all')")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM search_index_rebuilt_documents")
+            .execute(&mut *tx)
+            .await?;
+        let max_rowid: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(rowid), 0) FROM documents")
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO search_index_rebuild (id, max_rowid, started_at) VALUES (1, ?, ?)")
+            .bind(max_rowid)
+            .bind(Timestamp::now().to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(max_rowid)
+    }
+
+    /// Add one document to the search index and mark it re-indexed, atomically
+    ///
+    /// The document is read in the same transaction, so an edit made since it
+    /// was listed is what gets indexed. A document an edit has already indexed
+    /// is skipped.
+    async fn index_document(&self, rowid: i64) -> std::result::Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let marked = sqlx::query("INSERT OR IGNORE INTO search_index_rebuilt_documents (document_rowid) VALUES (?)")
+            .bind(rowid)
+            .execute(&mut *tx)
+            .await?;
+        if marked.rows_affected() > 0 {
+            sqlx::query(
+                r#"
+                INSERT INTO documents_fts(rowid, title, content)
+                SELECT rowid, title, content FROM documents
+                WHERE rowid = ? AND title_nonce IS NULL AND content_nonce IS NULL
+                "#
+            )
+            .bind(rowid)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
     }
 }
 
+/// Documents read per page while rebuilding the search index
+const SEARCH_INDEX_PAGE_SIZE: i64 = 500;
+
+/// Documents indexed at once by a search index rebuild unless the caller chooses
+pub const DEFAULT_SEARCH_INDEX_CONCURRENCY: usize = 4;
+
+/// How far a search index rebuild has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SearchIndexProgress {
+    /// Documents indexed so far, including any from an interrupted earlier run
+    pub indexed: u64,
+    /// Plaintext documents that existed when the rebuild started
+    pub total: u64,
+    /// Whether this run continued an interrupted rebuild
+    pub resumed: bool,
+}
+
 /// Document struct for SQLite serialization
 #[derive(Debug, Clone, sqlx::FromRow)]
 struct SqliteDocument {
@@ -39,6 +574,16 @@ struct SqliteDocument {
     pub version: i64,
     pub is_deleted: bool,
     pub deleted_at: Option<String>,
+    pub language: String,
+    pub language_override: Option<String>,
+    pub tenant_id: Option<String>,
+    pub is_pinned: bool,
+    pub is_generating: bool,
+    pub is_archived: bool,
+    /// Set when `title` holds ciphertext
+    pub title_nonce: Option<String>,
+    /// Set when `content` holds ciphertext
+    pub content_nonce: Option<String>,
 }
 
 impl From<SqliteDocument> for Document {
@@ -52,6 +597,13 @@ impl From<SqliteDocument> for Document {
             file_path: doc.file_path.map(|p| FilePath::new(&p).unwrap_or_default()),
             word_count: doc.word_count as u32,
             character_count: doc.character_count as u32,
+            tags: Vec::new(), // Will be loaded separately
+            language: doc.language,
+            language_override: doc.language_override,
+            tenant_id: doc.tenant_id,
+            is_pinned: doc.is_pinned,
+            is_generating: doc.is_generating,
+            is_archived: doc.is_archived,
             created_at: Timestamp::from_string(&doc.created_at).unwrap_or_else(|_| Timestamp::now()),
             updated_at: Timestamp::from_string(&doc.updated_at).unwrap_or_else(|_| Timestamp::now()),
             created_by: doc.created_by.and_then(|s| EntityId::from_string(&s).ok()),
@@ -81,6 +633,14 @@ impl From<&Document> for SqliteDocument {
             version: doc.version as i64,
             is_deleted: doc.is_deleted,
             deleted_at: doc.deleted_at.as_ref().map(|t| t.to_string()),
+            language: doc.language.clone(),
+            language_override: doc.language_override.clone(),
+            tenant_id: doc.tenant_id.clone(),
+            is_pinned: doc.is_pinned,
+            is_generating: doc.is_generating,
+            is_archived: doc.is_archived,
+            title_nonce: None,
+            content_nonce: None,
         }
     }
 }
@@ -89,79 +649,52 @@ impl From<&Document> for SqliteDocument {
 impl Repository<Document, EntityId> for SqliteDocumentRepository {
     async fn find_by_id(&self, id: &EntityId) -> Result<Option<Document>> {
         let row = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE id = ?"
+            "SELECT * FROM documents WHERE id = ? AND (? OR tenant_id IS ?)"
         )
         .bind(id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find document by id: {}", e)))?;
 
-        Ok(row.map(|doc| doc.into()))
+        match row {
+            Some(doc) => Ok(self.with_tags(vec![doc]).await?.pop()),
+            None => Ok(None),
+        }
     }
 
     async fn find_all(&self, pagination: Pagination) -> Result<Vec<Document>> {
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find all documents: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
     async fn save(&self, entity: &Document) -> Result<Document> {
-        let sqlite_doc = SqliteDocument::from(entity);
-        
-        sqlx::query(
-            r#"
-            INSERT INTO documents (
-                id, title, content, content_type, content_hash, file_path,
-                word_count, character_count, created_at, updated_at,
-                created_by, updated_by, version, is_deleted, deleted_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO UPDATE SET
-                title = excluded.title,
-                content = excluded.content,
-                content_type = excluded.content_type,
-                content_hash = excluded.content_hash,
-                file_path = excluded.file_path,
-                word_count = excluded.word_count,
-                character_count = excluded.character_count,
-                updated_at = excluded.updated_at,
-                updated_by = excluded.updated_by,
-                version = excluded.version,
-                is_deleted = excluded.is_deleted,
-                deleted_at = excluded.deleted_at
-            "#
-        )
-        .bind(&sqlite_doc.id)
-        .bind(&sqlite_doc.title)
-        .bind(&sqlite_doc.content)
-        .bind(&sqlite_doc.content_type)
-        .bind(&sqlite_doc.content_hash)
-        .bind(&sqlite_doc.file_path)
-        .bind(sqlite_doc.word_count)
-        .bind(sqlite_doc.character_count)
-        .bind(&sqlite_doc.created_at)
-        .bind(&sqlite_doc.updated_at)
-        .bind(&sqlite_doc.created_by)
-        .bind(&sqlite_doc.updated_by)
-        .bind(sqlite_doc.version)
-        .bind(sqlite_doc.is_deleted)
-        .bind(&sqlite_doc.deleted_at)
-        .execute(&self.pool)
-        .await
-        .map_err(|e| WritemagicError::database(&format!("Failed to save document: {}", e)))?;
+        let mut tx = self.pool.begin().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
 
-        Ok(entity.clone())
+        let saved = self.write(&mut tx, entity).await?;
+
+        tx.commit().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
+        Ok(saved)
     }
 
     async fn delete(&self, id: &EntityId) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM documents WHERE id = ?")
+        let result = sqlx::query("DELETE FROM documents WHERE id = ? AND (? OR tenant_id IS ?)")
             .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .execute(&self.pool)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to delete document: {}", e)))?;
@@ -170,8 +703,10 @@ impl Repository<Document, EntityId> for SqliteDocumentRepository {
     }
 
     async fn exists(&self, id: &EntityId) -> Result<bool> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM documents WHERE id = ?")
+        let row = sqlx::query("SELECT COUNT(*) as count FROM documents WHERE id = ? AND (? OR tenant_id IS ?)")
             .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .fetch_one(&self.pool)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to check document existence: {}", e)))?;
@@ -181,7 +716,9 @@ impl Repository<Document, EntityId> for SqliteDocumentRepository {
     }
 
     async fn count(&self) -> Result<u64> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM documents WHERE is_deleted = FALSE")
+        let row = sqlx::query("SELECT COUNT(*) as count FROM documents WHERE is_deleted = FALSE AND (? OR tenant_id IS ?)")
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .fetch_one(&self.pool)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to count documents: {}", e)))?;
@@ -193,130 +730,346 @@ impl Repository<Document, EntityId> for SqliteDocumentRepository {
 
 #[async_trait]
 impl DocumentRepository for SqliteDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
+
+        let sqlite_doc = self.encode(document)?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE documents SET
+                title = ?, content = ?, content_type = ?, content_hash = ?, file_path = ?,
+                word_count = ?, character_count = ?, updated_at = ?, updated_by = ?, version = ?,
+                is_deleted = ?, deleted_at = ?, language = ?, language_override = ?,
+                is_pinned = ?, is_generating = ?, is_archived = ?, title_nonce = ?, content_nonce = ?
+            WHERE id = ? AND version = ? AND (? OR tenant_id IS ?)
+            "#
+        )
+        .bind(&sqlite_doc.title)
+        .bind(&sqlite_doc.content)
+        .bind(&sqlite_doc.content_type)
+        .bind(&sqlite_doc.content_hash)
+        .bind(&sqlite_doc.file_path)
+        .bind(sqlite_doc.word_count)
+        .bind(sqlite_doc.character_count)
+        .bind(&sqlite_doc.updated_at)
+        .bind(&sqlite_doc.updated_by)
+        .bind(sqlite_doc.version)
+        .bind(sqlite_doc.is_deleted)
+        .bind(&sqlite_doc.deleted_at)
+        .bind(&sqlite_doc.language)
+        .bind(&sqlite_doc.language_override)
+        .bind(sqlite_doc.is_pinned)
+        .bind(sqlite_doc.is_generating)
+        .bind(sqlite_doc.is_archived)
+        .bind(&sqlite_doc.title_nonce)
+        .bind(&sqlite_doc.content_nonce)
+        .bind(&sqlite_doc.id)
+        .bind(expected_version as i64)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save document: {}", e)))?;
+
+        if updated.rows_affected() == 0 {
+            return Err(ConcurrencyError::VersionMismatch {
+                document_id: document.id,
+                expected: expected_version,
+                actual: self.stored_version(&mut tx, &document.id).await?,
+            });
+        }
+
+        Self::replace_tags(&mut tx, document).await?;
+
+        tx.commit().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(document.clone())
+    }
+
+    async fn find_by_ids(&self, ids: &[EntityId]) -> Result<Vec<Document>> {
+        let mut found = HashMap::with_capacity(ids.len());
+
+        for chunk in ids.chunks(MAX_BOUND_PARAMETERS) {
+            let mut sql = QueryBuilder::<Sqlite>::new("SELECT * FROM documents WHERE id IN (");
+            let mut list = sql.separated(", ");
+            for id in chunk {
+                list.push_bind(id.to_string());
+            }
+            sql.push(")");
+            if !self.all_tenants() {
+                sql.push(" AND tenant_id IS ").push_bind(self.tenant_id());
+            }
+
+            let rows = sql.build_query_as::<SqliteDocument>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| WritemagicError::database(&format!("Failed to find documents by ids: {}", e)))?;
+
+            // Load the tags of the whole chunk in one query too
+            let mut tags: HashMap<String, Vec<DocumentTag>> = HashMap::new();
+            if !rows.is_empty() {
+                let mut sql = QueryBuilder::<Sqlite>::new("SELECT document_id, tag FROM document_tags WHERE document_id IN (");
+                let mut list = sql.separated(", ");
+                for row in &rows {
+                    list.push_bind(row.id.clone());
+                }
+                sql.push(") ORDER BY tag");
+
+                let tag_rows = sql.build()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| WritemagicError::database(&format!("Failed to load document tags: {}", e)))?;
+                for row in tag_rows {
+                    if let Ok(tag) = DocumentTag::new(row.get::<String, _>("tag")) {
+                        tags.entry(row.get("document_id")).or_default().push(tag);
+                    }
+                }
+            }
+
+            for row in rows {
+                let document_tags = tags.remove(&row.id).unwrap_or_default();
+                let mut document = self.decode(row)?;
+                document.tags = document_tags;
+                found.insert(document.id, document);
+            }
+        }
+
+        Ok(ids.iter().filter_map(|id| found.get(id).cloned()).collect())
+    }
+
     async fn find_by_project_id(&self, project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
         let rows = sqlx::query_as::<_, SqliteDocument>(
             r#"
             SELECT d.* FROM documents d
             INNER JOIN project_documents pd ON d.id = pd.document_id
-            WHERE pd.project_id = ? AND d.is_deleted = FALSE
+            WHERE pd.project_id = ? AND d.is_deleted = FALSE AND (? OR d.tenant_id IS ?)
             ORDER BY d.updated_at DESC
             LIMIT ? OFFSET ?
             "#
         )
         .bind(project_id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find documents by project id: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
+    }
+
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>> {
+        let rows = sqlx::query_as::<_, SqliteDocument>(
+            r#"
+            SELECT d.* FROM documents d
+            INNER JOIN document_tags dt ON d.id = dt.document_id
+            WHERE dt.tag = ? AND d.is_deleted = FALSE AND (? OR d.tenant_id IS ?)
+            ORDER BY d.updated_at DESC
+            LIMIT ? OFFSET ?
+            "#
+        )
+        .bind(tag.as_str())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
+        .bind(pagination.limit as i64)
+        .bind(pagination.offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to find documents by tag: {}", e)))?;
+
+        self.with_tags(rows).await
     }
 
     async fn find_by_content_type(&self, content_type: &ContentType, pagination: Pagination) -> Result<Vec<Document>> {
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE content_type = ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE content_type = ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
         .bind(content_type.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find documents by content type: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
     async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        if self.cipher.is_some() {
+            // Matches as LIKE does, ignoring case for ASCII only
+            let term = query.to_ascii_lowercase();
+            let ids: Vec<EntityId> = self.live_titles().await?
+                .into_iter()
+                .filter(|(_, title)| title.to_ascii_lowercase().contains(&term))
+                .skip(pagination.offset as usize)
+                .take(pagination.limit as usize)
+                .filter_map(|(id, _)| EntityId::from_string(&id).ok())
+                .collect();
+            return self.find_by_ids(&ids).await;
+        }
+
         let search_query = format!("%{}%", query);
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE title LIKE ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE title LIKE ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
         .bind(&search_query)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to search documents by title: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
+    }
+
+    async fn search_titles_fuzzy(&self, query: &str, limit: u32) -> Result<Vec<(Document, f32)>> {
+        // Titles may be encrypted, so every live title is ranked here rather than filtered in SQL
+        let titles = self.live_titles().await?;
+
+        let ranked: Vec<(EntityId, f32)> = rank_fuzzy(query, titles, |(_, title)| title.as_str(), limit)
+            .into_iter()
+            .filter_map(|((id, _), score)| Some((EntityId::from_string(&id).ok()?, score)))
+            .collect();
+        let ids: Vec<EntityId> = ranked.iter().map(|(id, _)| *id).collect();
+        let mut documents: HashMap<EntityId, Document> = self.find_by_ids(&ids).await?
+            .into_iter()
+            .map(|document| (document.id, document))
+            .collect();
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(id, score)| Some((documents.remove(&id)?, score)))
+            .collect())
     }
 
     async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        self.ensure_full_text_search()?;
+
         // Try FTS first for better performance
         let fts_result = sqlx::query_as::<_, SqliteDocument>(
             r#"
             SELECT d.* FROM documents d
-            INNER JOIN documents_fts fts ON d.id = fts.id
-            WHERE documents_fts MATCH ? AND d.is_deleted = FALSE
+            INNER JOIN documents_fts ON documents_fts.rowid = d.rowid
+            WHERE documents_fts MATCH ? AND d.is_deleted = FALSE AND (? OR d.tenant_id IS ?)
             ORDER BY bm25(documents_fts), d.updated_at DESC
             LIMIT ? OFFSET ?
             "#
         )
         .bind(query)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await;
 
         if let Ok(rows) = fts_result {
-            return Ok(rows.into_iter().map(|doc| doc.into()).collect());
+            return self.with_tags(rows).await;
         }
 
         // Fallback to LIKE search if FTS fails
         log::warn!("FTS search failed, falling back to LIKE search for query: {}", query);
         let search_query = format!("%{}%", query);
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE content LIKE ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE content LIKE ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
         .bind(&search_query)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to search documents by content: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
-    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+        self.ensure_full_text_search()?;
+        let Some(match_expression) = fts_match_expression(query) else {
+            return Ok(Vec::new());
+        };
+
+        // Title hits weigh ten times content hits in the bm25 score
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE created_by = ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
-        )
-        .bind(user_id.to_string())
+            r#"
+            SELECT d.* FROM documents d
+            INNER JOIN documents_fts ON documents_fts.rowid = d.rowid
+            WHERE documents_fts MATCH ? AND (? OR d.is_deleted = FALSE) AND (? OR d.tenant_id IS ?)
+            ORDER BY bm25(documents_fts, 10.0, 1.0), d.updated_at DESC
+            LIMIT ? OFFSET ?
+            "#
+        )
+        .bind(&match_expression)
+        .bind(include_deleted)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
+        .bind(pagination.limit as i64)
+        .bind(pagination.offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to search documents: {}", e)))?;
+
+        self.with_tags(rows).await
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        let rows = sqlx::query_as::<_, SqliteDocument>(
+            "SELECT * FROM documents WHERE created_by = ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+        )
+        .bind(user_id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find documents by creator: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
     async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Document>> {
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find recently updated documents: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
     async fn find_deleted(&self, pagination: Pagination) -> Result<Vec<Document>> {
         let rows = sqlx::query_as::<_, SqliteDocument>(
-            "SELECT * FROM documents WHERE is_deleted = TRUE ORDER BY deleted_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM documents WHERE is_deleted = TRUE AND (? OR tenant_id IS ?) ORDER BY deleted_at DESC LIMIT ? OFFSET ?"
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find deleted documents: {}", e)))?;
 
-        Ok(rows.into_iter().map(|doc| doc.into()).collect())
+        self.with_tags(rows).await
     }
 
     async fn get_statistics(&self) -> Result<DocumentStatistics> {
@@ -330,8 +1083,11 @@ impl DocumentRepository for SqliteDocumentRepository {
                 COALESCE(AVG(CAST(word_count AS REAL)), 0) as avg_word_count,
                 COALESCE(AVG(CAST(character_count AS REAL)), 0) as avg_character_count
             FROM documents
+            WHERE ? OR tenant_id IS ?
             "#
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_one(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to get document statistics: {}", e)))?;
@@ -345,8 +1101,10 @@ impl DocumentRepository for SqliteDocumentRepository {
 
         // Get documents by type
         let type_rows = sqlx::query(
-            "SELECT content_type, COUNT(*) as count FROM documents GROUP BY content_type"
+            "SELECT content_type, COUNT(*) as count FROM documents WHERE ? OR tenant_id IS ? GROUP BY content_type"
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_all(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to get documents by type: {}", e)))?;
@@ -368,17 +1126,125 @@ impl DocumentRepository for SqliteDocumentRepository {
             deleted_documents: deleted_documents as u64,
         })
     }
+
+    fn scoped_to(&self, scope: &TenantScope) -> Option<Arc<dyn DocumentRepository>> {
+        Some(Arc::new(self.scoped(scope.clone())))
+    }
 }
 
 /// SQLite project repository implementation
 #[derive(Debug, Clone)]
 pub struct SqliteProjectRepository {
     pool: SqlitePool,
+    /// Tenant every statement is restricted to, or every tenant when unset
+    tenant: Option<TenantScope>,
 }
 
 impl SqliteProjectRepository {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool, tenant: None }
+    }
+
+    /// The same repository restricted to one tenant's projects in SQL
+    pub fn scoped(&self, scope: TenantScope) -> Self {
+        Self { tenant: Some(scope), ..self.clone() }
+    }
+
+    /// First parameter of a `(? OR tenant_id IS ?)` condition: true when unscoped
+    fn all_tenants(&self) -> bool {
+        self.tenant.is_none()
+    }
+
+    /// Second parameter of a `(? OR tenant_id IS ?)` condition
+    fn tenant_id(&self) -> Option<String> {
+        self.tenant.as_ref().and_then(|scope| scope.tenant_id().map(str::to_string))
+    }
+
+    /// Insert or update `entity` and its document list on `conn`, inside the caller's transaction
+    pub(crate) async fn write(&self, conn: &mut SqliteConnection, entity: &Project) -> Result<Project> {
+        let mut sqlite_proj = SqliteProject::from(entity);
+        if !self.all_tenants() {
+            sqlite_proj.tenant_id = self.tenant_id();
+        }
+
+        // Save project, never overwriting another tenant's from a scoped repository
+        let saved = sqlx::query(
+            r#"
+            INSERT INTO projects (
+                id, name, description, created_at, updated_at,
+                created_by, updated_by, version, is_deleted, deleted_at, tenant_id,
+                is_archived, archived_at, system_prompt
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                description = excluded.description,
+                updated_at = excluded.updated_at,
+                updated_by = excluded.updated_by,
+                version = excluded.version,
+                is_deleted = excluded.is_deleted,
+                deleted_at = excluded.deleted_at,
+                is_archived = excluded.is_archived,
+                archived_at = excluded.archived_at,
+                system_prompt = excluded.system_prompt
+            WHERE ? OR projects.tenant_id IS excluded.tenant_id
+            "#
+        )
+        .bind(&sqlite_proj.id)
+        .bind(&sqlite_proj.name)
+        .bind(&sqlite_proj.description)
+        .bind(&sqlite_proj.created_at)
+        .bind(&sqlite_proj.updated_at)
+        .bind(&sqlite_proj.created_by)
+        .bind(&sqlite_proj.updated_by)
+        .bind(sqlite_proj.version)
+        .bind(sqlite_proj.is_deleted)
+        .bind(&sqlite_proj.deleted_at)
+        .bind(&sqlite_proj.tenant_id)
+        .bind(sqlite_proj.is_archived)
+        .bind(&sqlite_proj.archived_at)
+        .bind(&sqlite_proj.system_prompt)
+        .bind(self.all_tenants())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save project: {}", e)))?;
+        if saved.rows_affected() == 0 {
+            return Err(WritemagicError::not_found(format!("Project {}", entity.id)));
+        }
+
+        // Clear existing document relationships
+        sqlx::query("DELETE FROM project_documents WHERE project_id = ?")
+            .bind(&sqlite_proj.id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to clear project documents: {}", e)))?;
+
+        // Insert new document relationships
+        for doc_id in &entity.document_ids {
+            sqlx::query(
+                "INSERT INTO project_documents (project_id, document_id) VALUES (?, ?)"
+            )
+            .bind(&sqlite_proj.id)
+            .bind(doc_id.to_string())
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to save project document relationship: {}", e)))?;
+        }
+
+        let mut saved = entity.clone();
+        saved.tenant_id = sqlite_proj.tenant_id;
+        Ok(saved)
+    }
+
+    /// Stored version of project `id` on `conn`, or `None` if there is no such project
+    pub(crate) async fn stored_version(&self, conn: &mut SqliteConnection, id: &EntityId) -> Result<Option<u64>> {
+        let version: Option<i64> = sqlx::query_scalar("SELECT version FROM projects WHERE id = ? AND (? OR tenant_id IS ?)")
+            .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to read project version: {}", e)))?;
+        Ok(version.map(|version| version as u64))
     }
 }
 
@@ -395,6 +1261,10 @@ struct SqliteProject {
     pub version: i64,
     pub is_deleted: bool,
     pub deleted_at: Option<String>,
+    pub tenant_id: Option<String>,
+    pub is_archived: bool,
+    pub archived_at: Option<String>,
+    pub system_prompt: Option<String>,
 }
 
 impl From<SqliteProject> for Project {
@@ -403,7 +1273,9 @@ impl From<SqliteProject> for Project {
             id: EntityId::from_string(&proj.id).unwrap_or_else(|_| EntityId::new()),
             name: proj.name,
             description: proj.description,
+            system_prompt: proj.system_prompt,
             document_ids: Vec::new(), // Will be loaded separately
+            tenant_id: proj.tenant_id,
             created_at: Timestamp::from_string(&proj.created_at).unwrap_or_else(|_| Timestamp::now()),
             updated_at: Timestamp::from_string(&proj.updated_at).unwrap_or_else(|_| Timestamp::now()),
             created_by: proj.created_by.and_then(|s| EntityId::from_string(&s).ok()),
@@ -411,6 +1283,8 @@ impl From<SqliteProject> for Project {
             version: proj.version as u64,
             is_deleted: proj.is_deleted,
             deleted_at: proj.deleted_at.and_then(|s| Timestamp::from_string(&s).ok()),
+            is_archived: proj.is_archived,
+            archived_at: proj.archived_at.and_then(|s| Timestamp::from_string(&s).ok()),
         }
     }
 }
@@ -428,6 +1302,10 @@ impl From<&Project> for SqliteProject {
             version: proj.version as i64,
             is_deleted: proj.is_deleted,
             deleted_at: proj.deleted_at.as_ref().map(|t| t.to_string()),
+            tenant_id: proj.tenant_id.clone(),
+            is_archived: proj.is_archived,
+            archived_at: proj.archived_at.as_ref().map(|t| t.to_string()),
+            system_prompt: proj.system_prompt.clone(),
         }
     }
 }
@@ -436,9 +1314,11 @@ impl From<&Project> for SqliteProject {
 impl Repository<Project, EntityId> for SqliteProjectRepository {
     async fn find_by_id(&self, id: &EntityId) -> Result<Option<Project>> {
         let row = sqlx::query_as::<_, SqliteProject>(
-            "SELECT * FROM projects WHERE id = ?"
+            "SELECT * FROM projects WHERE id = ? AND (? OR tenant_id IS ?)"
         )
         .bind(id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to find project by id: {}", e)))?;
@@ -469,121 +1349,42 @@ impl Repository<Project, EntityId> for SqliteProjectRepository {
     }
 
     async fn find_all(&self, pagination: Pagination) -> Result<Vec<Project>> {
-        let rows = sqlx::query_as::<_, SqliteProject>(
-            "SELECT * FROM projects WHERE is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
-        )
-        .bind(pagination.limit as i64)
-        .bind(pagination.offset as i64)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| WritemagicError::database(&format!("Failed to find all projects: {}", e)))?;
-
-        let mut projects = Vec::new();
-        for proj in rows {
-            let mut project = Project::from(proj);
-            
-            // Load document IDs for each project
-            let doc_rows = sqlx::query(
-                "SELECT document_id FROM project_documents WHERE project_id = ?"
-            )
-            .bind(project.id.to_string())
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| WritemagicError::database(&format!("Failed to load project documents: {}", e)))?;
-
-            project.document_ids = doc_rows.into_iter()
-                .filter_map(|row| {
-                    let doc_id: String = row.get("document_id");
-                    EntityId::from_string(&doc_id).ok()
-                })
-                .collect();
-
-            projects.push(project);
-        }
-
-        Ok(projects)
+        self.find_all_projects(pagination, true).await
     }
 
     async fn save(&self, entity: &Project) -> Result<Project> {
         let mut tx = self.pool.begin().await
             .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
 
-        let sqlite_proj = SqliteProject::from(entity);
-        
-        // Save project
-        sqlx::query(
-            r#"
-            INSERT INTO projects (
-                id, name, description, created_at, updated_at,
-                created_by, updated_by, version, is_deleted, deleted_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO UPDATE SET
-                name = excluded.name,
-                description = excluded.description,
-                updated_at = excluded.updated_at,
-                updated_by = excluded.updated_by,
-                version = excluded.version,
-                is_deleted = excluded.is_deleted,
-                deleted_at = excluded.deleted_at
-            "#
-        )
-        .bind(&sqlite_proj.id)
-        .bind(&sqlite_proj.name)
-        .bind(&sqlite_proj.description)
-        .bind(&sqlite_proj.created_at)
-        .bind(&sqlite_proj.updated_at)
-        .bind(&sqlite_proj.created_by)
-        .bind(&sqlite_proj.updated_by)
-        .bind(sqlite_proj.version)
-        .bind(sqlite_proj.is_deleted)
-        .bind(&sqlite_proj.deleted_at)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| WritemagicError::database(&format!("Failed to save project: {}", e)))?;
-
-        // Clear existing document relationships
-        sqlx::query("DELETE FROM project_documents WHERE project_id = ?")
-            .bind(&sqlite_proj.id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| WritemagicError::database(&format!("Failed to clear project documents: {}", e)))?;
-
-        // Insert new document relationships
-        for doc_id in &entity.document_ids {
-            sqlx::query(
-                "INSERT INTO project_documents (project_id, document_id) VALUES (?, ?)"
-            )
-            .bind(&sqlite_proj.id)
-            .bind(doc_id.to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| WritemagicError::database(&format!("Failed to save project document relationship: {}", e)))?;
-        }
+        let saved = self.write(&mut tx, entity).await?;
 
         tx.commit().await
             .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
-
-        Ok(entity.clone())
+        Ok(saved)
     }
 
     async fn delete(&self, id: &EntityId) -> Result<bool> {
         let mut tx = self.pool.begin().await
             .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
 
-        // Delete project documents relationships
-        sqlx::query("DELETE FROM project_documents WHERE project_id = ?")
-            .bind(id.to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| WritemagicError::database(&format!("Failed to delete project documents: {}", e)))?;
-
         // Delete project
-        let result = sqlx::query("DELETE FROM projects WHERE id = ?")
+        let result = sqlx::query("DELETE FROM projects WHERE id = ? AND (? OR tenant_id IS ?)")
             .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .execute(&mut *tx)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to delete project: {}", e)))?;
 
+        // Delete project documents relationships of a project that was ours to delete
+        if result.rows_affected() > 0 {
+            sqlx::query("DELETE FROM project_documents WHERE project_id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| WritemagicError::database(&format!("Failed to delete project documents: {}", e)))?;
+        }
+
         tx.commit().await
             .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
 
@@ -591,8 +1392,10 @@ impl Repository<Project, EntityId> for SqliteProjectRepository {
     }
 
     async fn exists(&self, id: &EntityId) -> Result<bool> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM projects WHERE id = ?")
+        let row = sqlx::query("SELECT COUNT(*) as count FROM projects WHERE id = ? AND (? OR tenant_id IS ?)")
             .bind(id.to_string())
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .fetch_one(&self.pool)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to check project existence: {}", e)))?;
@@ -602,7 +1405,9 @@ impl Repository<Project, EntityId> for SqliteProjectRepository {
     }
 
     async fn count(&self) -> Result<u64> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM projects WHERE is_deleted = FALSE")
+        let row = sqlx::query("SELECT COUNT(*) as count FROM projects WHERE is_deleted = FALSE AND (? OR tenant_id IS ?)")
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
             .fetch_one(&self.pool)
             .await
             .map_err(|e| WritemagicError::database(&format!("Failed to count projects: {}", e)))?;
@@ -614,11 +1419,80 @@ impl Repository<Project, EntityId> for SqliteProjectRepository {
 
 #[async_trait]
 impl ProjectRepository for SqliteProjectRepository {
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> Result<Project> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
+
+        // Claiming the row takes the write lock, so no other save lands before ours
+        let claimed = sqlx::query("UPDATE projects SET version = version WHERE id = ? AND version = ? AND (? OR tenant_id IS ?)")
+            .bind(project.id.to_string())
+            .bind(expected_version as i64)
+            .bind(self.all_tenants())
+            .bind(self.tenant_id())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to save project: {}", e)))?;
+
+        if claimed.rows_affected() == 0 {
+            let actual = self.stored_version(&mut tx, &project.id).await?;
+            return Err(project_version_mismatch(project.id, expected_version, actual));
+        }
+
+        let saved = self.write(&mut tx, project).await?;
+
+        tx.commit().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
+        Ok(saved)
+    }
+
+    async fn find_all_projects(&self, pagination: Pagination, include_archived: bool) -> Result<Vec<Project>> {
+        let sql = if include_archived {
+            "SELECT * FROM projects WHERE is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+        } else {
+            "SELECT * FROM projects WHERE is_deleted = FALSE AND is_archived = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+        };
+        let rows = sqlx::query_as::<_, SqliteProject>(sql)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
+        .bind(pagination.limit as i64)
+        .bind(pagination.offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to find all projects: {}", e)))?;
+
+        let mut projects = Vec::new();
+        for proj in rows {
+            let mut project = Project::from(proj);
+            
+            // Load document IDs for each project
+            let doc_rows = sqlx::query(
+                "SELECT document_id FROM project_documents WHERE project_id = ?"
+            )
+            .bind(project.id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load project documents: {}", e)))?;
+
+            project.document_ids = doc_rows.into_iter()
+                .filter_map(|row| {
+                    let doc_id: String = row.get("document_id");
+                    EntityId::from_string(&doc_id).ok()
+                })
+                .collect();
+
+            projects.push(project);
+        }
+
+        Ok(projects)
+    }
+
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
         let rows = sqlx::query_as::<_, SqliteProject>(
-            "SELECT * FROM projects WHERE created_by = ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM projects WHERE created_by = ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
         .bind(user_id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
@@ -654,9 +1528,11 @@ impl ProjectRepository for SqliteProjectRepository {
     async fn search_by_name(&self, query: &str, pagination: Pagination) -> Result<Vec<Project>> {
         let search_query = format!("%{}%", query);
         let rows = sqlx::query_as::<_, SqliteProject>(
-            "SELECT * FROM projects WHERE name LIKE ? AND is_deleted = FALSE ORDER BY updated_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM projects WHERE name LIKE ? AND is_deleted = FALSE AND (? OR tenant_id IS ?) ORDER BY updated_at DESC LIMIT ? OFFSET ?"
         )
         .bind(&search_query)
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
@@ -694,12 +1570,14 @@ impl ProjectRepository for SqliteProjectRepository {
             r#"
             SELECT p.* FROM projects p
             INNER JOIN project_documents pd ON p.id = pd.project_id
-            WHERE pd.document_id = ? AND p.is_deleted = FALSE
+            WHERE pd.document_id = ? AND p.is_deleted = FALSE AND (? OR p.tenant_id IS ?)
             ORDER BY p.updated_at DESC
             LIMIT ? OFFSET ?
             "#
         )
         .bind(document_id.to_string())
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .bind(pagination.limit as i64)
         .bind(pagination.offset as i64)
         .fetch_all(&self.pool)
@@ -741,11 +1619,15 @@ impl ProjectRepository for SqliteProjectRepository {
             r#"
             SELECT 
                 COUNT(*) as total_projects,
-                (SELECT COUNT(*) FROM project_documents) as total_documents_in_projects
+                (SELECT COUNT(*) FROM project_documents pd
+                 INNER JOIN projects p ON pd.project_id = p.id
+                 WHERE p.is_deleted = FALSE AND (?1 OR p.tenant_id IS ?2)) as total_documents_in_projects
             FROM projects
-            WHERE is_deleted = FALSE
+            WHERE is_deleted = FALSE AND (?1 OR tenant_id IS ?2)
             "#
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_one(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to get project statistics: {}", e)))?;
@@ -769,11 +1651,13 @@ impl ProjectRepository for SqliteProjectRepository {
                 SELECT COUNT(*) as doc_count 
                 FROM project_documents pd
                 INNER JOIN projects p ON pd.project_id = p.id
-                WHERE p.is_deleted = FALSE
+                WHERE p.is_deleted = FALSE AND (? OR p.tenant_id IS ?)
                 GROUP BY pd.project_id
             )
             "#
         )
+        .bind(self.all_tenants())
+        .bind(self.tenant_id())
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| WritemagicError::database(&format!("Failed to get project size statistics: {}", e)))?;
@@ -794,4 +1678,900 @@ impl ProjectRepository for SqliteProjectRepository {
             smallest_project_size,
         })
     }
-}
\ No newline at end of file
+
+    fn scoped_to(&self, scope: &TenantScope) -> Option<Arc<dyn ProjectRepository>> {
+        Some(Arc::new(self.scoped(scope.clone())))
+    }
+}
+
+/// SQLite document link repository implementation
+#[derive(Debug, Clone)]
+pub struct SqliteDocumentLinkRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteDocumentLinkRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn link_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<DocumentLink> {
+        let source_id: String = row.get("source_id");
+        let target_id: Option<String> = row.get("target_id");
+
+        Ok(DocumentLink {
+            source_id: EntityId::from_string(&source_id)
+                .map_err(|e| WritemagicError::database(&format!("Invalid link source id: {}", e)))?,
+            reference: row.get("reference"),
+            target_id: target_id
+                .map(|id| EntityId::from_string(&id))
+                .transpose()
+                .map_err(|e| WritemagicError::database(&format!("Invalid link target id: {}", e)))?,
+        })
+    }
+}
+
+#[async_trait]
+impl DocumentLinkRepository for SqliteDocumentLinkRepository {
+    async fn replace_links(&self, source_id: &EntityId, links: &[DocumentLink]) -> Result<()> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
+
+        sqlx::query("DELETE FROM document_links WHERE source_id = ?")
+            .bind(source_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to clear document links: {}", e)))?;
+
+        for (position, link) in links.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO document_links (source_id, position, reference, target_id) VALUES (?, ?, ?, ?)"
+            )
+            .bind(source_id.to_string())
+            .bind(position as i64)
+            .bind(&link.reference)
+            .bind(link.target_id.map(|id| id.to_string()))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to insert document link: {}", e)))?;
+        }
+
+        tx.commit().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_outgoing(&self, source_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        let rows = sqlx::query("SELECT * FROM document_links WHERE source_id = ? ORDER BY position")
+            .bind(source_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document links: {}", e)))?;
+
+        rows.iter().map(Self::link_from_row).collect()
+    }
+
+    async fn find_backlinks(&self, target_id: &EntityId) -> Result<Vec<DocumentLink>> {
+        let rows = sqlx::query("SELECT * FROM document_links WHERE target_id = ? ORDER BY source_id, position")
+            .bind(target_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document backlinks: {}", e)))?;
+
+        rows.iter().map(Self::link_from_row).collect()
+    }
+}
+
+/// SQLite document version repository implementation
+#[derive(Debug, Clone)]
+pub struct SqliteDocumentVersionRepository {
+    pool: SqlitePool,
+    /// Encrypts snapshot content when encryption at rest is enabled
+    cipher: Option<Arc<FieldCipher>>,
+}
+
+impl SqliteDocumentVersionRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, cipher: None }
+    }
+
+    /// Store snapshot content encrypted with `cipher`, as documents are
+    pub fn with_encryption(mut self, cipher: Arc<FieldCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    fn version_from_row(&self, row: &sqlx::sqlite::SqliteRow) -> Result<DocumentVersion> {
+        let document_id: String = row.get("document_id");
+        let version: i64 = row.get("version");
+        let mut content: String = row.get("content");
+        let content_nonce: Option<String> = row.get("content_nonce");
+        let content_type: String = row.get("content_type");
+        let mut content_hash: String = row.get("content_hash");
+        let created_at: String = row.get("created_at");
+        let created_by: Option<String> = row.get("created_by");
+
+        if let Some(nonce) = content_nonce {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                WritemagicError::configuration(format!(
+                    "Version {} of document {} is encrypted but no encryption key is configured",
+                    version, document_id
+                ))
+            })?;
+            content = cipher.decrypt(&content, &nonce)?;
+            content_hash = ContentHash::new(&content).to_string();
+        }
+
+        Ok(DocumentVersion {
+            document_id: EntityId::from_string(&document_id)
+                .map_err(|e| WritemagicError::database(&format!("Invalid version document id: {}", e)))?,
+            version: version as u64,
+            content,
+            content_type: ContentType::from_string(&content_type).unwrap_or(ContentType::Markdown),
+            content_hash: ContentHash::from_string(&content_hash),
+            created_at: Timestamp::from_string(&created_at).unwrap_or_else(|_| Timestamp::now()),
+            created_by: created_by.and_then(|s| EntityId::from_string(&s).ok()),
+        })
+    }
+}
+
+#[async_trait]
+impl DocumentVersionRepository for SqliteDocumentVersionRepository {
+    async fn save_version(&self, version: &DocumentVersion) -> Result<()> {
+        // Encrypted snapshots store the hash of their ciphertext, as documents do
+        let (content, content_hash, content_nonce) = match &self.cipher {
+            Some(cipher) => {
+                let (content, nonce) = cipher.encrypt(&version.content)?;
+                let content_hash = ContentHash::new(&content).to_string();
+                (content, content_hash, Some(nonce))
+            }
+            None => (version.content.clone(), version.content_hash.to_string(), None),
+        };
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO document_versions \
+             (document_id, version, content, content_type, content_hash, created_at, created_by, content_nonce) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(version.document_id.to_string())
+        .bind(version.version as i64)
+        .bind(content)
+        .bind(version.content_type.to_string())
+        .bind(content_hash)
+        .bind(version.created_at.to_string())
+        .bind(version.created_by.map(|id| id.to_string()))
+        .bind(content_nonce)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save document version: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_versions(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<DocumentVersion>> {
+        let rows = sqlx::query(
+            "SELECT * FROM document_versions WHERE document_id = ? ORDER BY version DESC LIMIT ? OFFSET ?"
+        )
+        .bind(document_id.to_string())
+        .bind(pagination.limit as i64)
+        .bind(pagination.offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to load document versions: {}", e)))?;
+
+        rows.iter().map(|row| self.version_from_row(row)).collect()
+    }
+
+    async fn find_version(&self, document_id: &EntityId, version: u64) -> Result<Option<DocumentVersion>> {
+        let row = sqlx::query("SELECT * FROM document_versions WHERE document_id = ? AND version = ?")
+            .bind(document_id.to_string())
+            .bind(version as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document version: {}", e)))?;
+
+        row.map(|row| self.version_from_row(&row)).transpose()
+    }
+
+    async fn prune(&self, document_id: &EntityId, keep: usize) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM document_versions WHERE document_id = ? AND version NOT IN \
+             (SELECT version FROM document_versions WHERE document_id = ? ORDER BY version DESC LIMIT ?)"
+        )
+        .bind(document_id.to_string())
+        .bind(document_id.to_string())
+        .bind(keep as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to prune document versions: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// SQLite document lock repository implementation
+///
+/// Expiry is stored as Unix milliseconds so it can be compared in SQL.
+#[derive(Debug, Clone)]
+pub struct SqliteDocumentLockRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteDocumentLockRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn lock_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<DocumentLock> {
+        let document_id: String = row.get("document_id");
+        let holder: String = row.get("holder");
+        let acquired_at: String = row.get("acquired_at");
+        let expires_at: i64 = row.get("expires_at");
+
+        Ok(DocumentLock {
+            document_id: EntityId::from_string(&document_id)
+                .map_err(|e| WritemagicError::database(&format!("Invalid lock document id: {}", e)))?,
+            holder: EntityId::from_string(&holder)
+                .map_err(|e| WritemagicError::database(&format!("Invalid lock holder: {}", e)))?,
+            acquired_at: Timestamp::from_string(&acquired_at).unwrap_or_else(|_| Timestamp::now()),
+            expires_at: chrono::DateTime::from_timestamp_millis(expires_at)
+                .map(Timestamp::from_datetime)
+                .ok_or_else(|| WritemagicError::database(&format!("Invalid lock expiry: {}", expires_at)))?,
+        })
+    }
+}
+
+#[async_trait]
+impl DocumentLockRepository for SqliteDocumentLockRepository {
+    async fn acquire(&self, lock: &DocumentLock, now: &Timestamp) -> Result<DocumentLock> {
+        // The upsert only takes over a row that is ours already or has expired
+        sqlx::query(
+            "INSERT INTO document_locks (document_id, holder, acquired_at, expires_at) VALUES (?, ?, ?, ?) \
+             ON CONFLICT(document_id) DO UPDATE SET \
+             holder = excluded.holder, acquired_at = excluded.acquired_at, expires_at = excluded.expires_at \
+             WHERE document_locks.holder = excluded.holder OR document_locks.expires_at <= ?"
+        )
+        .bind(lock.document_id.to_string())
+        .bind(lock.holder.to_string())
+        .bind(lock.acquired_at.to_string())
+        .bind(lock.expires_at.0.timestamp_millis())
+        .bind(now.0.timestamp_millis())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to acquire document lock: {}", e)))?;
+
+        let row = sqlx::query("SELECT * FROM document_locks WHERE document_id = ?")
+            .bind(lock.document_id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document lock: {}", e)))?;
+
+        Self::lock_from_row(&row)
+    }
+
+    async fn find_active(&self, document_id: &EntityId, now: &Timestamp) -> Result<Option<DocumentLock>> {
+        let row = sqlx::query("SELECT * FROM document_locks WHERE document_id = ? AND expires_at > ?")
+            .bind(document_id.to_string())
+            .bind(now.0.timestamp_millis())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document lock: {}", e)))?;
+
+        row.as_ref().map(Self::lock_from_row).transpose()
+    }
+
+    async fn release(&self, document_id: &EntityId, holder: &EntityId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM document_locks WHERE document_id = ? AND holder = ?")
+            .bind(document_id.to_string())
+            .bind(holder.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to release document lock: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// SQLite document template repository implementation
+#[derive(Debug, Clone)]
+pub struct SqliteDocumentTemplateRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteDocumentTemplateRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<DocumentTemplate> {
+        let id: String = row.get("id");
+        let content_type: String = row.get("content_type");
+        let required_variables: String = row.get("required_variables");
+        let created_at: String = row.get("created_at");
+        let updated_at: String = row.get("updated_at");
+
+        Ok(DocumentTemplate {
+            id: EntityId::from_string(&id)
+                .map_err(|e| WritemagicError::database(&format!("Invalid template id: {}", e)))?,
+            name: row.get("name"),
+            body: row.get("body"),
+            content_type: ContentType::from_string(&content_type).unwrap_or(ContentType::Markdown),
+            required_variables: serde_json::from_str(&required_variables)
+                .map_err(|e| WritemagicError::database(&format!("Invalid template variables: {}", e)))?,
+            created_at: Timestamp::from_string(&created_at).unwrap_or_else(|_| Timestamp::now()),
+            updated_at: Timestamp::from_string(&updated_at).unwrap_or_else(|_| Timestamp::now()),
+        })
+    }
+}
+
+#[async_trait]
+impl DocumentTemplateRepository for SqliteDocumentTemplateRepository {
+    async fn save(&self, template: &DocumentTemplate) -> Result<DocumentTemplate> {
+        let required_variables = serde_json::to_string(&template.required_variables)
+            .map_err(|e| WritemagicError::internal(format!("Failed to serialize template variables: {}", e)))?;
+
+        sqlx::query(
+            "INSERT INTO document_templates (id, name, body, content_type, required_variables, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET \
+             name = excluded.name, body = excluded.body, content_type = excluded.content_type, \
+             required_variables = excluded.required_variables, updated_at = excluded.updated_at"
+        )
+        .bind(template.id.to_string())
+        .bind(&template.name)
+        .bind(&template.body)
+        .bind(template.content_type.to_string())
+        .bind(required_variables)
+        .bind(template.created_at.to_string())
+        .bind(template.updated_at.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save document template: {}", e)))?;
+
+        Ok(template.clone())
+    }
+
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<DocumentTemplate>> {
+        let row = sqlx::query("SELECT * FROM document_templates WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load document template: {}", e)))?;
+
+        row.as_ref().map(Self::template_from_row).transpose()
+    }
+
+    async fn list(&self) -> Result<Vec<DocumentTemplate>> {
+        let rows = sqlx::query("SELECT * FROM document_templates ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to list document templates: {}", e)))?;
+
+        rows.iter().map(Self::template_from_row).collect()
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM document_templates WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to delete document template: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// SQLite idempotency key repository implementation
+///
+/// Expiry is stored as Unix seconds so it can be compared in SQL.
+#[derive(Debug, Clone)]
+pub struct SqliteIdempotencyKeyRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteIdempotencyKeyRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyRepository for SqliteIdempotencyKeyRepository {
+    async fn find_document(&self, key: &str, now: &Timestamp) -> Result<Option<EntityId>> {
+        let row = sqlx::query("SELECT document_id FROM idempotency_keys WHERE key = ? AND expires_at > ?")
+            .bind(key)
+            .bind(now.0.timestamp())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to load idempotency key: {}", e)))?;
+
+        row.map(|row| {
+            let document_id: String = row.get("document_id");
+            EntityId::from_string(&document_id)
+                .map_err(|e| WritemagicError::database(&format!("Invalid idempotency key document id: {}", e)))
+        })
+        .transpose()
+    }
+
+    async fn save_key(&self, key: &str, document_id: &EntityId, expires_at: &Timestamp) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO idempotency_keys (key, document_id, created_at, expires_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(key)
+        .bind(document_id.to_string())
+        .bind(Timestamp::now().to_string())
+        .bind(expires_at.0.timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WritemagicError::database(&format!("Failed to save idempotency key: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: &Timestamp) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= ?")
+            .bind(now.0.timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to delete expired idempotency keys: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Applies a unit of work's writes to SQLite in one transaction
+#[derive(Debug, Clone)]
+pub struct SqliteWriteStore {
+    documents: SqliteDocumentRepository,
+    projects: SqliteProjectRepository,
+}
+
+impl SqliteWriteStore {
+    /// Store writing through `documents` and `projects`, which must share a database
+    pub fn new(documents: SqliteDocumentRepository, projects: SqliteProjectRepository) -> Self {
+        Self { documents, projects }
+    }
+
+    async fn apply_in(&self, conn: &mut SqliteConnection, writes: &StagedWrites) -> Result<()> {
+        for write in &writes.documents {
+            if self.documents.stored_version(conn, &write.entity.id).await? != write.read_version {
+                return Err(WritemagicError::conflict(format!("Document {} changed since the unit of work read it", write.entity.id)));
+            }
+        }
+        for write in &writes.projects {
+            if self.projects.stored_version(conn, &write.entity.id).await? != write.read_version {
+                return Err(WritemagicError::conflict(format!("Project {} changed since the unit of work read it", write.entity.id)));
+            }
+        }
+
+        // Documents first, so projects list documents that exist
+        for write in &writes.documents {
+            self.documents.write(conn, &write.entity).await?;
+        }
+        for write in &writes.projects {
+            self.projects.write(conn, &write.entity).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StagedWriteStore for SqliteWriteStore {
+    async fn apply(&self, writes: StagedWrites) -> Result<()> {
+        let mut conn = self.documents.pool.acquire().await
+            .map_err(|e| WritemagicError::database(&format!("Failed to acquire connection: {}", e)))?;
+
+        // IMMEDIATE takes the write lock before the version checks, so nothing
+        // can change between them and the writes
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| WritemagicError::database(&format!("Failed to begin transaction: {}", e)))?;
+
+        let result = match self.apply_in(&mut conn, &writes).await {
+            Ok(()) => sqlx::query("COMMIT")
+                .execute(&mut *conn)
+                .await
+                .map(|_| ())
+                .map_err(|e| WritemagicError::database(&format!("Failed to commit transaction: {}", e))),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = &result {
+            if let Err(rollback_error) = sqlx::query("ROLLBACK").execute(&mut *conn).await {
+                log::error!("Failed to roll back unit of work after \"{}\": {}", e, rollback_error);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::InMemoryDocumentRepository;
+    use writemagic_shared::DatabaseManager;
+
+    #[tokio::test]
+    async fn test_find_by_ids_keeps_input_order_across_chunks() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let sqlite_documents = SqliteDocumentRepository::new(database.pool().clone());
+        let in_memory_documents = InMemoryDocumentRepository::new();
+
+        let mut ids = Vec::new();
+        for i in 0..MAX_BOUND_PARAMETERS + 50 {
+            let mut document = Document::new(format!("Note {}", i), "Body".to_string(), ContentType::Markdown, None);
+            if i % 100 == 0 {
+                document.add_tags(vec![DocumentTag::new("milestone").unwrap()], None);
+            }
+            sqlite_documents.save(&document).await.unwrap();
+            in_memory_documents.save(&document).await.unwrap();
+            ids.push(document.id);
+        }
+
+        // Reverse so the order differs from insertion, with a missing id in between
+        ids.reverse();
+        ids.insert(10, EntityId::new());
+
+        let found = sqlite_documents.find_by_ids(&ids).await.unwrap();
+        assert_eq!(found.len(), MAX_BOUND_PARAMETERS + 50);
+        let expected: Vec<EntityId> = ids.iter().copied().filter(|id| *id != ids[10]).collect();
+        assert_eq!(found.iter().map(|d| d.id).collect::<Vec<_>>(), expected);
+        assert_eq!(found.iter().filter(|d| !d.tags.is_empty()).count(), 10);
+        let milestone = DocumentTag::new("milestone").unwrap();
+        let tagged = sqlite_documents.find_by_tag(&milestone, Pagination { offset: 0, limit: 100 }).await.unwrap();
+        assert_eq!(tagged.len(), 10);
+        assert!(tagged.iter().all(|d| d.tags == vec![milestone.clone()]));
+
+        let in_memory = in_memory_documents.find_by_ids(&ids).await.unwrap();
+        assert_eq!(in_memory.iter().map(|d| d.id).collect::<Vec<_>>(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_document_templates_round_trip() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let templates = SqliteDocumentTemplateRepository::new(database.pool().clone());
+        let mut standup = DocumentTemplate::new("Standup", "---\ntags: [standup]\n---\n{{date}}", ContentType::Markdown)
+            .with_required_variables(["date"]);
+        let outline = DocumentTemplate::new("Outline", "1. {{topic}}", ContentType::PlainText);
+        templates.save(&standup).await.unwrap();
+        templates.save(&outline).await.unwrap();
+
+        standup.body = "{{date}} standup".to_string();
+        templates.save(&standup).await.unwrap();
+        let loaded = templates.find_by_id(&standup.id).await.unwrap().unwrap();
+        assert_eq!(loaded.body, "{{date}} standup");
+        assert_eq!(loaded.required_variables, vec!["date"]);
+        assert_eq!(loaded.content_type, ContentType::Markdown);
+
+        let names: Vec<String> = templates.list().await.unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Outline", "Standup"]);
+
+        assert!(templates.delete(&outline.id).await.unwrap());
+        assert!(!templates.delete(&outline.id).await.unwrap());
+        assert!(templates.find_by_id(&outline.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_save_if_version_lets_exactly_one_interleaved_update_win() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        let original = Document::new("Draft".to_string(), "Opening".to_string(), ContentType::Markdown, None);
+        documents.save(&original).await.unwrap();
+
+        // Both writers loaded the same version before either saved
+        let mut first = documents.find_by_id(&original.id).await.unwrap().unwrap();
+        let mut second = first.clone();
+        first.update_content("Opening, revised by the first writer".to_string(), None);
+        second.update_title("Renamed by the second writer".to_string(), None);
+        second.add_tags(vec![DocumentTag::new("lost").unwrap()], None);
+
+        let (first_result, second_result) = tokio::join!(
+            documents.save_if_version(&first, original.version),
+            documents.save_if_version(&second, original.version),
+        );
+        assert_eq!([first_result.is_ok(), second_result.is_ok()].iter().filter(|ok| **ok).count(), 1);
+
+        let (winner, loser) = if first_result.is_ok() { (&first, second_result) } else { (&second, first_result) };
+        match loser.unwrap_err() {
+            ConcurrencyError::VersionMismatch { expected, actual, .. } => {
+                assert_eq!(expected, original.version);
+                assert_eq!(actual, Some(winner.version));
+            }
+            other => panic!("expected a version mismatch, got {:?}", other),
+        }
+
+        let stored = documents.find_by_id(&original.id).await.unwrap().unwrap();
+        assert_eq!(stored.title, winner.title);
+        assert_eq!(stored.content, winner.content);
+        assert_eq!(stored.tags, winner.tags);
+    }
+
+    #[tokio::test]
+    async fn test_save_if_version_refuses_missing_documents() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        let unsaved = Document::new("Draft".to_string(), String::new(), ContentType::Markdown, None);
+
+        let error = documents.save_if_version(&unsaved, unsaved.version).await.unwrap_err();
+        assert!(matches!(error, ConcurrencyError::VersionMismatch { actual: None, .. }));
+        assert!(matches!(WritemagicError::from(error), WritemagicError::Conflict { .. }));
+        assert!(!documents.exists(&unsaved.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_scope_is_applied_in_sql() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        let acme = documents.scoped(TenantScope::tenant("acme"));
+        let globex = documents.scoped(TenantScope::tenant("globex"));
+
+        for i in 0..5 {
+            acme.save(&Document::new(format!("Acme {}", i), String::new(), ContentType::Markdown, None)).await.unwrap();
+            globex.save(&Document::new(format!("Globex {}", i), String::new(), ContentType::Markdown, None)).await.unwrap();
+        }
+
+        let page = acme.find_all(Pagination { offset: 1, limit: 3 }).await.unwrap();
+        assert_eq!(page.len(), 3);
+        assert!(page.iter().all(|document| document.tenant_id.as_deref() == Some("acme")));
+        assert_eq!(acme.count().await.unwrap(), 5);
+        assert_eq!(acme.get_statistics().await.unwrap().total_documents, 5);
+        assert_eq!(documents.count().await.unwrap(), 10);
+
+        // Another tenant's document can be neither read, overwritten nor deleted
+        let theirs = globex.find_all(Pagination { offset: 0, limit: 1 }).await.unwrap().remove(0);
+        assert!(acme.find_by_id(&theirs.id).await.unwrap().is_none());
+        assert!(matches!(acme.save(&theirs).await, Err(WritemagicError::NotFound { .. })));
+        assert!(!acme.delete(&theirs.id).await.unwrap());
+        assert_eq!(globex.find_by_id(&theirs.id).await.unwrap().unwrap().title, theirs.title);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_fields_round_trip_alongside_legacy_rows() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let plaintext = SqliteDocumentRepository::new(database.pool().clone());
+        let cipher = Arc::new(FieldCipher::for_database(database.pool(), "passphrase").await.unwrap());
+        let encrypted = SqliteDocumentRepository::new(database.pool().clone()).with_encryption(cipher);
+
+        let legacy = Document::new("Legacy".to_string(), "Written before encryption".to_string(), ContentType::Markdown, None);
+        plaintext.save(&legacy).await.unwrap();
+        let secret = Document::new("Diary".to_string(), "Private thoughts".to_string(), ContentType::Markdown, None);
+        encrypted.save(&secret).await.unwrap();
+
+        let row = sqlx::query("SELECT title, content, content_nonce FROM documents WHERE id = ?")
+            .bind(secret.id.to_string())
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_ne!(row.get::<String, _>("content"), "Private thoughts");
+        assert_ne!(row.get::<String, _>("title"), "Diary");
+        assert!(row.get::<Option<String>, _>("content_nonce").is_some());
+
+        let found = encrypted.find_by_ids(&[legacy.id, secret.id]).await.unwrap();
+        assert_eq!(found[0].content, "Written before encryption");
+        assert_eq!((found[1].title.as_str(), found[1].content.as_str()), ("Diary", "Private thoughts"));
+
+        // Without the key, encrypted rows are refused rather than returned as ciphertext
+        assert!(plaintext.find_by_id(&secret.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_documents_stay_out_of_the_search_index() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let plaintext = SqliteDocumentRepository::new(database.pool().clone());
+        let cipher = Arc::new(FieldCipher::for_database(database.pool(), "passphrase").await.unwrap());
+        let encrypted = SqliteDocumentRepository::new(database.pool().clone()).with_encryption(cipher);
+        let page = Pagination::new(0, 10).unwrap();
+
+        let legacy = Document::new("Lighthouse log".to_string(), "Lamp lit at six".to_string(), ContentType::Markdown, None);
+        plaintext.save(&legacy).await.unwrap();
+        assert_eq!(plaintext.search("lighthouse", page.clone(), false).await.unwrap().len(), 1);
+
+        // Re-saved under the key, the row leaves the index rather than leaking its plaintext there
+        let secret = encrypted.find_by_id(&legacy.id).await.unwrap().unwrap();
+        encrypted.save(&secret).await.unwrap();
+        assert!(plaintext.search("lighthouse", page.clone(), false).await.unwrap().is_empty());
+        let error = encrypted.search("lighthouse", page.clone(), false).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::FeatureDisabled { .. }), "{:?}", error);
+
+        // The stored hash is of the ciphertext, while the document's is of its plaintext
+        let stored_hash: String = sqlx::query_scalar("SELECT content_hash FROM documents WHERE id = ?")
+            .bind(legacy.id.to_string())
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_ne!(stored_hash, ContentHash::new("Lamp lit at six").to_string());
+        assert_eq!(encrypted.find_by_id(&legacy.id).await.unwrap().unwrap().content_hash, ContentHash::new("Lamp lit at six"));
+
+        // Title search, text filters and title order work on the decrypted fields
+        let other = Document::new("Harbour notes".to_string(), "Boats at dawn".to_string(), ContentType::Markdown, None);
+        encrypted.save(&other).await.unwrap();
+        let by_title = encrypted.search_by_title("LIGHT", page).await.unwrap();
+        assert_eq!(by_title.iter().map(|d| d.id).collect::<Vec<_>>(), vec![legacy.id]);
+        let by_text = encrypted.query(&DocumentQuery::new().with_text("boats")).await.unwrap();
+        assert_eq!(by_text.documents.iter().map(|d| d.id).collect::<Vec<_>>(), vec![other.id]);
+        let by_name = encrypted.query(&DocumentQuery::new().with_sort(DocumentSortKey::Title, SortOrder::Ascending)).await.unwrap();
+        assert_eq!(by_name.documents.iter().map(|d| d.id).collect::<Vec<_>>(), vec![other.id, legacy.id]);
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_title_matches_and_hides_deleted() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+
+        let in_content = Document::new("Meeting notes".to_string(), "We discussed the roadmap".to_string(), ContentType::Markdown, None);
+        let in_title = Document::new("Roadmap 2025".to_string(), "Quarterly goals".to_string(), ContentType::Markdown, None);
+        let mut deleted = Document::new("Old roadmap".to_string(), "Superseded".to_string(), ContentType::Markdown, None);
+        deleted.mark_deleted(None);
+        for document in [&in_content, &in_title, &deleted] {
+            documents.save(document).await.unwrap();
+        }
+
+        let page = Pagination::new(0, 10).unwrap();
+        let found = documents.search("roadmap", page.clone(), false).await.unwrap();
+        assert_eq!(found.iter().map(|d| d.id).collect::<Vec<_>>(), vec![in_title.id, in_content.id]);
+
+        let with_deleted = documents.search("roadmap", page.clone(), true).await.unwrap();
+        assert_eq!(with_deleted.len(), 3);
+
+        // Prefix terms and FTS syntax characters in user input are both handled
+        assert_eq!(documents.search("quarter", page.clone(), false).await.unwrap().len(), 1);
+        assert!(documents.search("\"roadmap OR (", page.clone(), false).await.is_ok());
+        assert!(documents.search("   ", page, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_title_search_ranks_encrypted_titles_and_hides_deleted() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let cipher = Arc::new(FieldCipher::for_database(database.pool(), "passphrase").await.unwrap());
+        let documents = SqliteDocumentRepository::new(database.pool().clone()).with_encryption(cipher);
+        let in_memory = crate::repositories::InMemoryDocumentRepository::new();
+
+        let exact = Document::new("Project plan".to_string(), String::new(), ContentType::Markdown, None);
+        let longer = Document::new("Project planning notes".to_string(), String::new(), ContentType::Markdown, None);
+        let unrelated = Document::new("Grocery list".to_string(), String::new(), ContentType::Markdown, None);
+        let mut deleted = Document::new("Project plan (old)".to_string(), String::new(), ContentType::Markdown, None);
+        deleted.mark_deleted(None);
+        for document in [&exact, &longer, &unrelated, &deleted] {
+            documents.save(document).await.unwrap();
+            in_memory.save(document).await.unwrap();
+        }
+
+        let found = documents.search_titles_fuzzy("proj plan", 10).await.unwrap();
+        assert_eq!(found.iter().map(|(d, _)| d.id).collect::<Vec<_>>(), vec![exact.id, longer.id]);
+        assert!(found[0].1 > found[1].1);
+        assert_eq!(found[0].0.title, "Project plan");
+
+        let from_memory = in_memory.search_titles_fuzzy("proj plan", 10).await.unwrap();
+        assert_eq!(from_memory.iter().map(|(d, score)| (d.id, *score)).collect::<Vec<_>>(),
+            found.iter().map(|(d, score)| (d.id, *score)).collect::<Vec<_>>());
+        assert_eq!(documents.search_titles_fuzzy("proj plan", 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_search_index_restores_the_index_without_duplicates() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        for i in 0..30 {
+            let document = Document::new(format!("Chapter {}", i), "The lighthouse keeper".to_string(), ContentType::Markdown, None);
+            documents.save(&document).await.unwrap();
+        }
+        let page = Pagination::new(0, 100).unwrap();
+
+        // A lost index comes back in full
+        sqlx::query("INSERT INTO documents_fts(documents_fts) VALUES ('delete-all')").execute(database.pool()).await.unwrap();
+        assert!(documents.search("lighthouse", page.clone(), false).await.unwrap().is_empty());
+
+        let mut reports = Vec::new();
+        let done = documents.rebuild_search_index(4, |progress| reports.push(progress)).await.unwrap();
+        assert_eq!(done, SearchIndexProgress { indexed: 30, total: 30, resumed: false });
+        assert_eq!(reports.first().map(|p| p.indexed), Some(0));
+        assert_eq!(documents.search("lighthouse", page.clone(), false).await.unwrap().len(), 30);
+
+        // Rebuilding an intact index leaves one entry per document
+        documents.rebuild_search_index(1, |_| {}).await.unwrap();
+        let entries: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM documents_fts WHERE documents_fts MATCH 'lighthouse'")
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_eq!(entries, 30);
+        assert_eq!(documents.search("lighthouse", page, false).await.unwrap().len(), 30);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_search_index_rebuild_resumes_and_keeps_edits() {
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        let mut saved = Vec::new();
+        for i in 0..20 {
+            let document = Document::new(format!("Chapter {}", i), "The lighthouse keeper".to_string(), ContentType::Markdown, None);
+            saved.push(documents.save(&document).await.unwrap());
+        }
+        let rowids: Vec<i64> = sqlx::query_scalar("SELECT rowid FROM documents ORDER BY rowid")
+            .fetch_all(database.pool())
+            .await
+            .unwrap();
+
+        // Interrupted after indexing the first half
+        documents.start_search_index_rebuild().await.unwrap();
+        for &rowid in &rowids[..10] {
+            documents.index_document(rowid).await.unwrap();
+        }
+
+        // Edits while the rebuild is pending: to an indexed document, one not yet indexed, and a deletion
+        let mut indexed = saved[0].clone();
+        indexed.update_content("The harbour pilot".to_string(), None);
+        documents.save(&indexed).await.unwrap();
+        let mut pending = saved[15].clone();
+        pending.update_content("The harbour pilot".to_string(), None);
+        documents.save(&pending).await.unwrap();
+        documents.delete(&saved[19].id).await.unwrap();
+
+        let mut reports = Vec::new();
+        let done = documents.rebuild_search_index(4, |progress| reports.push(progress)).await.unwrap();
+        assert!(done.resumed);
+        assert_eq!(done.indexed, done.total);
+        assert!(reports.first().unwrap().indexed >= 10);
+
+        let count = |term: &'static str| {
+            let pool = database.pool().clone();
+            async move {
+                sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM documents_fts WHERE documents_fts MATCH ?")
+                    .bind(term)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(count("lighthouse").await, 17);
+        assert_eq!(count("harbour").await, 2);
+        let state: i64 = sqlx::query_scalar("SELECT (SELECT COUNT(*) FROM search_index_rebuild) + (SELECT COUNT(*) FROM search_index_rebuilt_documents)")
+            .fetch_one(database.pool())
+            .await
+            .unwrap();
+        assert_eq!(state, 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_store_applies_all_writes_or_none() {
+        use crate::unit_of_work::StagedWrite;
+
+        let database = DatabaseManager::new_in_memory().await.unwrap();
+        let documents = SqliteDocumentRepository::new(database.pool().clone());
+        let projects = SqliteProjectRepository::new(database.pool().clone());
+        let store = SqliteWriteStore::new(documents.clone(), projects.clone());
+
+        let project = Project::new("Stories".to_string(), None, None);
+        projects.save(&project).await.unwrap();
+        let document = Document::new("Log".to_string(), "Lamp lit at six.".to_string(), ContentType::Markdown, None);
+        let writes = |project: &Project| {
+            let read_version = Some(project.version);
+            let mut project = project.clone();
+            project.add_document(document.id, None);
+            StagedWrites {
+                documents: vec![StagedWrite { entity: document.clone(), read_version: None }],
+                projects: vec![StagedWrite { entity: project, read_version }],
+            }
+        };
+        let stale = writes(&project);
+
+        // Changed by another writer after the unit of work read it
+        let mut changed = projects.find_by_id(&project.id).await.unwrap().unwrap();
+        changed.update_description(Some("Coastal stories".to_string()), None);
+        projects.save(&changed).await.unwrap();
+
+        let error = store.apply(stale).await.unwrap_err();
+        assert!(matches!(error, WritemagicError::Conflict { .. }), "{:?}", error);
+        assert!(documents.find_by_id(&document.id).await.unwrap().is_none());
+        let stored = projects.find_by_id(&project.id).await.unwrap().unwrap();
+        assert_eq!(stored.description.as_deref(), Some("Coastal stories"));
+        assert!(stored.document_ids.is_empty());
+
+        store.apply(writes(&stored)).await.unwrap();
+        assert!(documents.find_by_id(&document.id).await.unwrap().is_some());
+        let stored = projects.find_by_id(&project.id).await.unwrap().unwrap();
+        assert_eq!(stored.document_ids, vec![document.id]);
+        assert_eq!(stored.description.as_deref(), Some("Coastal stories"));
+    }
+}
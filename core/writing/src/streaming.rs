@@ -0,0 +1,359 @@
+//! Streaming AI output into documents with periodic persistence
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use writemagic_ai::StreamingResponse;
+use writemagic_shared::{EntityId, Result, WritemagicError};
+use crate::aggregates::DocumentAggregate;
+use crate::services::DocumentManagementService;
+use crate::value_objects::DocumentContent;
+
+/// How often streamed output is written to the document
+///
+/// Buffered output is flushed once either threshold is reached, so a
+/// disconnect loses at most one flush worth of text.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StreamFlushConfig {
+    /// Flush after this many characters have been buffered
+    pub flush_every_chars: usize,
+    /// Flush buffered output at least this often, in milliseconds
+    pub flush_interval_ms: u64,
+}
+
+impl Default for StreamFlushConfig {
+    fn default() -> Self {
+        Self {
+            flush_every_chars: 400,
+            flush_interval_ms: 2000,
+        }
+    }
+}
+
+/// Result of streaming a generation into a document
+#[derive(Debug, Clone)]
+pub struct StreamedGeneration {
+    pub document: DocumentAggregate,
+    /// Number of writes to the document, each a new version
+    pub flushes: usize,
+    /// Characters appended across all flushes
+    pub appended_chars: usize,
+    /// Whether the stream was cut off by the output limit
+    pub truncated: bool,
+}
+
+/// Appends streamed AI output to documents, persisting it as it arrives
+pub struct DocumentStreamWriter {
+    document_service: Arc<DocumentManagementService>,
+    config: StreamFlushConfig,
+}
+
+impl DocumentStreamWriter {
+    pub fn new(document_service: Arc<DocumentManagementService>, config: StreamFlushConfig) -> Self {
+        Self { document_service, config }
+    }
+
+    pub fn config(&self) -> &StreamFlushConfig {
+        &self.config
+    }
+
+    /// Append everything `stream` produces to the end of a document
+    ///
+    /// The document is marked as generating until the stream ends. If the
+    /// stream fails, output since the last flush is discarded, the flag is
+    /// cleared and the stream's error is returned. The flag is also cleared
+    /// if the returned future is dropped before the stream ends.
+    pub async fn write(
+        &self,
+        document_id: EntityId,
+        mut stream: Box<dyn StreamingResponse>,
+        updated_by: Option<EntityId>,
+    ) -> Result<StreamedGeneration> {
+        self.document_service.set_document_generating(document_id, true).await?;
+        let guard = GeneratingGuard {
+            document_service: self.document_service.clone(),
+            document_id,
+            cleared: false,
+        };
+
+        match self.pump(document_id, stream.as_mut(), updated_by).await {
+            Ok((flushes, appended_chars)) => {
+                let document = guard.clear().await?;
+                Ok(StreamedGeneration {
+                    document,
+                    flushes,
+                    appended_chars,
+                    truncated: stream.is_truncated(),
+                })
+            }
+            Err(error) => {
+                if let Err(clear_error) = guard.clear().await {
+                    log::warn!("Failed to clear generating flag of document {}: {}", document_id, clear_error);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Read the stream to its end, returning the flush count and characters written
+    async fn pump(
+        &self,
+        document_id: EntityId,
+        stream: &mut dyn StreamingResponse,
+        updated_by: Option<EntityId>,
+    ) -> Result<(usize, usize)> {
+        let interval = Duration::from_millis(self.config.flush_interval_ms);
+        let mut buffer = String::new();
+        let mut last_flush = Instant::now();
+        let mut flushes = 0;
+        let mut appended_chars = 0;
+
+        while let Some(chunk) = stream.next_chunk().await? {
+            buffer.push_str(&chunk.content);
+
+            let due = buffer.chars().count() >= self.config.flush_every_chars || last_flush.elapsed() >= interval;
+            if due && !buffer.is_empty() {
+                appended_chars += self.flush(document_id, &mut buffer, updated_by).await?;
+                flushes += 1;
+                last_flush = Instant::now();
+            }
+        }
+
+        if !buffer.is_empty() {
+            appended_chars += self.flush(document_id, &mut buffer, updated_by).await?;
+            flushes += 1;
+        }
+
+        Ok((flushes, appended_chars))
+    }
+
+    /// Append the buffered text to the stored content, emptying the buffer
+    async fn flush(&self, document_id: EntityId, buffer: &mut String, updated_by: Option<EntityId>) -> Result<usize> {
+        let document = self.document_service
+            .get_document(&document_id)
+            .await?
+            .ok_or_else(|| WritemagicError::repository("Document not found"))?;
+
+        let content = format!("{}{}", document.document().content, buffer);
+        self.document_service
+            .update_document_content(document_id, DocumentContent::new(content)?, None, updated_by)
+            .await?;
+
+        let appended = buffer.chars().count();
+        buffer.clear();
+        Ok(appended)
+    }
+}
+
+/// Clears a document's generating flag once its stream is done with it
+///
+/// A guard dropped without [`GeneratingGuard::clear`] succeeding, as when the
+/// writing future is cancelled mid-stream, clears the flag on a spawned task.
+struct GeneratingGuard {
+    document_service: Arc<DocumentManagementService>,
+    document_id: EntityId,
+    cleared: bool,
+}
+
+impl GeneratingGuard {
+    async fn clear(mut self) -> Result<DocumentAggregate> {
+        let result = self.document_service.set_document_generating(self.document_id, false).await;
+        self.cleared = result.is_ok();
+        result
+    }
+}
+
+impl Drop for GeneratingGuard {
+    fn drop(&mut self) {
+        if self.cleared {
+            return;
+        }
+
+        let document_service = self.document_service.clone();
+        let document_id = self.document_id;
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = document_service.set_document_generating(document_id, false).await {
+                        log::warn!("Failed to clear generating flag of document {}: {}", document_id, e);
+                    }
+                });
+            }
+            Err(_) => log::warn!("Document {} is left marked as generating: no runtime to clear it on", document_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use writemagic_ai::StreamingChunk;
+    use writemagic_shared::{ContentType, Repository};
+    use crate::entities::Document;
+    use crate::repositories::{DocumentRepository, InMemoryDocumentRepository};
+
+    /// Stored state of the document seen each time the stream is polled
+    #[derive(Debug, Clone, PartialEq)]
+    struct Snapshot {
+        content: String,
+        version: u64,
+        is_generating: bool,
+    }
+
+    /// Stream replaying scripted chunks, recording the stored document before each one
+    struct ScriptedStream {
+        chunks: VecDeque<Result<String>>,
+        repository: Arc<InMemoryDocumentRepository>,
+        document_id: EntityId,
+        snapshots: Arc<Mutex<Vec<Snapshot>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl StreamingResponse for ScriptedStream {
+        async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+            let document = self.repository.find_by_id(&self.document_id).await?.unwrap();
+            self.snapshots.lock().unwrap().push(Snapshot {
+                content: document.content,
+                version: document.version,
+                is_generating: document.is_generating,
+            });
+
+            match self.chunks.pop_front() {
+                Some(Ok(content)) => Ok(Some(StreamingChunk { content, finish_reason: None, usage: None })),
+                Some(Err(error)) => Err(error),
+                None => Ok(None),
+            }
+        }
+
+        fn is_complete(&self) -> bool {
+            self.chunks.is_empty()
+        }
+
+        fn get_partial_response(&self) -> String {
+            String::new()
+        }
+    }
+
+    /// Stream that never produces a chunk
+    struct StalledStream;
+
+    #[async_trait::async_trait]
+    impl StreamingResponse for StalledStream {
+        async fn next_chunk(&mut self) -> Result<Option<StreamingChunk>> {
+            futures::future::pending().await
+        }
+
+        fn is_complete(&self) -> bool {
+            false
+        }
+
+        fn get_partial_response(&self) -> String {
+            String::new()
+        }
+    }
+
+    async fn setup(flush_every_chars: usize) -> (Arc<InMemoryDocumentRepository>, DocumentStreamWriter, EntityId) {
+        let repository = Arc::new(InMemoryDocumentRepository::new());
+        let document = Document::new("Story".to_string(), "Once".to_string(), ContentType::PlainText, None);
+        repository.save(&document).await.unwrap();
+
+        let service = Arc::new(DocumentManagementService::new(repository.clone() as Arc<dyn DocumentRepository>));
+        let config = StreamFlushConfig { flush_every_chars, flush_interval_ms: u64::MAX };
+        (repository, DocumentStreamWriter::new(service, config), document.id)
+    }
+
+    fn scripted(
+        repository: &Arc<InMemoryDocumentRepository>,
+        document_id: EntityId,
+        chunks: Vec<Result<String>>,
+    ) -> (Box<dyn StreamingResponse>, Arc<Mutex<Vec<Snapshot>>>) {
+        let snapshots = Arc::new(Mutex::new(Vec::new()));
+        let stream = ScriptedStream {
+            chunks: chunks.into(),
+            repository: repository.clone(),
+            document_id,
+            snapshots: snapshots.clone(),
+        };
+        (Box::new(stream), snapshots)
+    }
+
+    fn contents(snapshots: &Mutex<Vec<Snapshot>>) -> Vec<String> {
+        snapshots.lock().unwrap().iter().map(|snapshot| snapshot.content.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_streamed_output_is_persisted_at_flush_points() {
+        let (repository, writer, document_id) = setup(6).await;
+        let chunks = [" upon", " a", " time", " there", " was"].into_iter().map(|chunk| Ok(chunk.to_string())).collect();
+        let (stream, snapshots) = scripted(&repository, document_id, chunks);
+
+        let generation = writer.write(document_id, stream, None).await.unwrap();
+
+        assert_eq!(contents(&snapshots), vec![
+            "Once",
+            "Once",
+            "Once upon a",
+            "Once upon a",
+            "Once upon a time there",
+            "Once upon a time there",
+        ]);
+        let versions: Vec<u64> = snapshots.lock().unwrap().iter().map(|snapshot| snapshot.version).collect();
+        assert_eq!(versions, vec![1, 1, 2, 2, 3, 3]);
+
+        assert_eq!(generation.flushes, 3);
+        assert_eq!(generation.appended_chars, " upon a time there was".len());
+        assert_eq!(generation.document.document().content, "Once upon a time there was");
+        assert_eq!(generation.document.document().version, 4);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_stream_keeps_last_flushed_content() {
+        let (repository, writer, document_id) = setup(6).await;
+        let chunks = vec![
+            Ok(" upon a".to_string()),
+            Ok(" time".to_string()),
+            Err(WritemagicError::network("connection reset")),
+        ];
+        let (stream, _) = scripted(&repository, document_id, chunks);
+
+        let result = writer.write(document_id, stream, None).await;
+        assert!(result.is_err());
+
+        let document = repository.find_by_id(&document_id).await.unwrap().unwrap();
+        assert_eq!(document.content, "Once upon a");
+        assert_eq!(document.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_generating_flag_is_cleared_on_completion_and_error() {
+        let (repository, writer, document_id) = setup(100).await;
+
+        let (stream, snapshots) = scripted(&repository, document_id, vec![Ok(" upon".to_string())]);
+        let generation = writer.write(document_id, stream, None).await.unwrap();
+        assert!(snapshots.lock().unwrap().iter().all(|snapshot| snapshot.is_generating));
+        assert!(!generation.document.document().is_generating);
+        assert!(!repository.find_by_id(&document_id).await.unwrap().unwrap().is_generating);
+
+        let (stream, snapshots) = scripted(&repository, document_id, vec![Err(WritemagicError::network("timeout"))]);
+        assert!(writer.write(document_id, stream, None).await.is_err());
+        assert!(snapshots.lock().unwrap()[0].is_generating);
+        assert!(!repository.find_by_id(&document_id).await.unwrap().unwrap().is_generating);
+    }
+
+    #[tokio::test]
+    async fn test_generating_flag_is_cleared_when_the_write_is_cancelled() {
+        let (repository, writer, document_id) = setup(100).await;
+
+        let write = writer.write(document_id, Box::new(StalledStream), None);
+        assert!(tokio::time::timeout(Duration::from_millis(20), write).await.is_err());
+
+        for _ in 0..100 {
+            if !repository.find_by_id(&document_id).await.unwrap().unwrap().is_generating {
+                return;
+            }
+            tokio::task::yield_now().await;
+        }
+        panic!("generating flag was left set after the write was cancelled");
+    }
+}
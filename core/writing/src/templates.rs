@@ -0,0 +1,229 @@
+//! Document templates: static scaffolding new documents start from
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use writemagic_shared::validation::{validate_document_content, validate_document_title, validate_tags, ValidationError};
+use writemagic_shared::{render_placeholders, ContentType, DocumentTag, EntityId, Timestamp, WritemagicError};
+
+/// Boilerplate a new document is created from
+///
+/// The body may open with YAML front-matter between `---` lines giving the
+/// document's `title` and `tags`. `{{name}}` placeholders are replaced with
+/// the caller's variables before the front-matter is read; placeholders
+/// naming no variable are left as written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentTemplate {
+    pub id: EntityId,
+    pub name: String,
+    pub body: String,
+    /// Content type of documents created from the template
+    pub content_type: ContentType,
+    /// Variables that must be supplied to render the template
+    pub required_variables: Vec<String>,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+/// Title, tags and content of a document rendered from a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedTemplate {
+    /// Title from the front-matter, if it gave one
+    pub title: Option<String>,
+    pub tags: Vec<DocumentTag>,
+    /// Rendered body without its front-matter
+    pub content: String,
+}
+
+/// Why a document could not be created from a template
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    /// Missing variables or invalid front-matter, one error per problem
+    #[error("Invalid template input: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<ValidationError>),
+    #[error(transparent)]
+    Repository(#[from] WritemagicError),
+}
+
+impl From<TemplateError> for WritemagicError {
+    fn from(error: TemplateError) -> Self {
+        match error {
+            TemplateError::Invalid(_) => WritemagicError::validation(error.to_string()),
+            TemplateError::Repository(error) => error,
+        }
+    }
+}
+
+/// Keys a template's front-matter may set
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl DocumentTemplate {
+    pub fn new(name: impl Into<String>, body: impl Into<String>, content_type: ContentType) -> Self {
+        let now = Timestamp::now();
+        Self {
+            id: EntityId::new(),
+            name: name.into(),
+            body: body.into(),
+            content_type,
+            required_variables: Vec::new(),
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    pub fn with_required_variables<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.required_variables = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Read the body's front-matter and render it and the content with `variables`, reporting every problem found
+    ///
+    /// The front-matter is parsed before anything is substituted, so a
+    /// variable can fill in the title, a tag or the content but never add
+    /// front-matter keys of its own.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<RenderedTemplate, Vec<ValidationError>> {
+        let missing: Vec<ValidationError> = self
+            .required_variables
+            .iter()
+            .filter(|name| variables.get(*name).map_or(true, |value| value.trim().is_empty()))
+            .map(|name| ValidationError::new(format!("variables.{}", name), "required", "must be provided"))
+            .collect();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+
+        let (front_matter, body) = match split_front_matter(&self.body) {
+            Some((yaml, body)) => {
+                let front_matter = serde_yaml::from_str::<Option<FrontMatter>>(yaml).map_err(|e| {
+                    vec![ValidationError::new("front_matter", "schema", e.to_string())]
+                })?;
+                (front_matter.unwrap_or_default(), body)
+            }
+            None => (FrontMatter::default(), self.body.as_str()),
+        };
+        let render = |text: &str| render_placeholders(text, |name| variables.get(name).map(|value| Cow::Borrowed(value.as_str())));
+        let front_matter = FrontMatter {
+            title: front_matter.title.as_deref().map(render),
+            tags: front_matter.tags.iter().map(|tag| render(tag)).collect(),
+        };
+        let content = render(body);
+        let content = content.as_str();
+
+        let mut errors = Vec::new();
+        if let Some(title) = &front_matter.title {
+            errors.extend(validate_document_title(title).err().map(in_front_matter));
+        }
+        if let Err(tag_errors) = validate_tags(&front_matter.tags) {
+            errors.extend(tag_errors.into_iter().map(in_front_matter));
+        }
+        errors.extend(validate_document_content(content).err());
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(RenderedTemplate {
+            title: front_matter.title,
+            tags: front_matter.tags.into_iter().filter_map(|tag| DocumentTag::new(tag).ok()).collect(),
+            content: content.to_string(),
+        })
+    }
+}
+
+/// Report a front-matter rule under `front_matter.<field>`
+fn in_front_matter(error: ValidationError) -> ValidationError {
+    ValidationError {
+        field: format!("front_matter.{}", error.field),
+        ..error
+    }
+}
+
+/// Split `---` delimited front-matter from the start of `text`, returning it and the remaining content
+fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let after_open = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in after_open.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == "---" {
+            return Some((&after_open[..offset], &after_open[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_substitutes_variables_and_reads_front_matter() {
+        let template = DocumentTemplate::new(
+            "Meeting notes",
+            "---\ntitle: \"{{team}} sync\"\ntags: [meeting, notes]\n---\n# {{team}} sync\n\nAttendees: {{ attendees }}\n{{unset}}\n",
+            ContentType::Markdown,
+        )
+        .with_required_variables(["team"]);
+
+        let rendered = template.render(&variables(&[("team", "Platform"), ("attendees", "Ana, Bo")])).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("Platform sync"));
+        assert_eq!(rendered.tags, vec![DocumentTag::new("meeting").unwrap(), DocumentTag::new("notes").unwrap()]);
+        assert_eq!(rendered.content, "# Platform sync\n\nAttendees: Ana, Bo\n{{unset}}\n");
+    }
+
+    #[test]
+    fn test_variables_cannot_add_front_matter() {
+        let template = DocumentTemplate::new(
+            "Injectable",
+            "---\ntitle: \"{{name}}\"\n---\n{{name}}",
+            ContentType::Markdown,
+        );
+        let name = "Notes\"\ntags: [injected]\n---\nbody";
+
+        let rendered = template.render(&variables(&[("name", name)])).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some(name));
+        assert!(rendered.tags.is_empty());
+        assert_eq!(rendered.content, name);
+    }
+
+    #[test]
+    fn test_render_without_front_matter_keeps_the_whole_body() {
+        let template = DocumentTemplate::new("Plain", "---no front-matter here", ContentType::PlainText);
+        let rendered = template.render(&HashMap::new()).unwrap();
+        assert_eq!(rendered.title, None);
+        assert!(rendered.tags.is_empty());
+        assert_eq!(rendered.content, "---no front-matter here");
+    }
+
+    #[test]
+    fn test_render_reports_field_level_errors() {
+        let template = DocumentTemplate::new("Notes", "body", ContentType::Markdown)
+            .with_required_variables(["team", "date"]);
+        let errors = template.render(&variables(&[("date", " ")])).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["variables.team", "variables.date"]);
+        assert!(errors.iter().all(|error| error.rule == "required"));
+
+        let template = DocumentTemplate::new("Notes", "---\ntitle: \"  \"\ntags: [ok, Not Ok]\n---\nbody", ContentType::Markdown);
+        let errors = template.render(&HashMap::new()).unwrap_err();
+        assert_eq!(errors[0].field, "front_matter.title");
+        assert_eq!(errors[1].field, "front_matter.tags");
+        assert_eq!(errors[1].rule, "charset");
+
+        let template = DocumentTemplate::new("Notes", "---\nauthor: me\n---\nbody", ContentType::Markdown);
+        let errors = template.render(&HashMap::new()).unwrap_err();
+        assert_eq!((errors[0].field.as_str(), errors[0].rule.as_str()), ("front_matter", "schema"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Per-tenant isolation of documents and projects
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use writemagic_shared::{ContentType, DocumentTag, EntityId, Pagination, Repository, Result, WritemagicError};
+use crate::entities::{Document, Project};
+use crate::fuzzy::rank_fuzzy;
+use crate::repositories::{
+    scan_where, ConcurrencyError, DocumentRepository, DocumentStatistics, ProjectRepository, ProjectStatistics,
+};
+
+/// The tenant whose data a repository or service may see
+///
+/// The default scope sees only data without a tenant, as written by
+/// single-tenant deployments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TenantScope {
+    tenant_id: Option<String>,
+}
+
+impl TenantScope {
+    pub fn tenant(tenant_id: impl Into<String>) -> Self {
+        Self { tenant_id: Some(tenant_id.into()) }
+    }
+
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    /// Whether data owned by `tenant_id` is visible in this scope
+    pub fn contains(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id.as_deref() == tenant_id
+    }
+}
+
+impl From<Option<String>> for TenantScope {
+    fn from(tenant_id: Option<String>) -> Self {
+        Self { tenant_id }
+    }
+}
+
+/// Entity owned by a tenant
+pub trait TenantOwned {
+    fn tenant_id(&self) -> Option<&str>;
+    fn set_tenant_id(&mut self, tenant_id: Option<String>);
+}
+
+impl TenantOwned for Document {
+    fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    fn set_tenant_id(&mut self, tenant_id: Option<String>) {
+        self.tenant_id = tenant_id;
+    }
+}
+
+impl TenantOwned for Project {
+    fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    fn set_tenant_id(&mut self, tenant_id: Option<String>) {
+        self.tenant_id = tenant_id;
+    }
+}
+
+/// Read pages from `fetch` until `window` of the entities in `scope` is filled,
+/// or every entity when `window` is `None`
+async fn scan<T, F, Fut>(scope: &TenantScope, window: Option<Pagination>, fetch: F) -> Result<Vec<T>>
+where
+    T: TenantOwned,
+    F: FnMut(Pagination) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    scan_where(window, |entity: &T| scope.contains(entity.tenant_id()), fetch).await
+}
+
+/// `inner` restricted to `scope`, in storage where the backend supports it
+pub fn scope_documents(inner: &Arc<dyn DocumentRepository>, scope: TenantScope) -> Arc<dyn DocumentRepository> {
+    inner
+        .scoped_to(&scope)
+        .unwrap_or_else(|| Arc::new(TenantScopedDocumentRepository::new(inner.clone(), scope)))
+}
+
+/// `inner` restricted to `scope`, in storage where the backend supports it
+pub fn scope_projects(inner: &Arc<dyn ProjectRepository>, scope: TenantScope) -> Arc<dyn ProjectRepository> {
+    inner
+        .scoped_to(&scope)
+        .unwrap_or_else(|| Arc::new(TenantScopedProjectRepository::new(inner.clone(), scope)))
+}
+
+/// Document repository restricted to one tenant by filtering another repository's results
+///
+/// For backends that can't filter by tenant themselves; reads scan the
+/// unscoped repository page by page. Documents of other tenants are never returned and cannot be overwritten or
+/// deleted; to callers they do not exist. Saved documents are stamped with the
+/// scope's tenant.
+pub struct TenantScopedDocumentRepository {
+    inner: Arc<dyn DocumentRepository>,
+    scope: TenantScope,
+}
+
+impl TenantScopedDocumentRepository {
+    pub fn new(inner: Arc<dyn DocumentRepository>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    pub fn scope(&self) -> &TenantScope {
+        &self.scope
+    }
+}
+
+#[async_trait]
+impl Repository<Document, EntityId> for TenantScopedDocumentRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Document>> {
+        Ok(self.inner.find_by_id(id).await?.filter(|document| self.scope.contains(document.tenant_id())))
+    }
+
+    async fn find_all(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_all(page)).await
+    }
+
+    async fn save(&self, entity: &Document) -> Result<Document> {
+        if let Some(existing) = self.inner.find_by_id(&entity.id).await? {
+            if !self.scope.contains(existing.tenant_id()) {
+                return Err(WritemagicError::not_found(format!("Document {}", entity.id)));
+            }
+        }
+
+        let mut document = entity.clone();
+        document.set_tenant_id(self.scope.tenant_id().map(str::to_string));
+        self.inner.save(&document).await
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        if self.find_by_id(id).await?.is_none() {
+            return Ok(false);
+        }
+        self.inner.delete(id).await
+    }
+
+    async fn exists(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    async fn count(&self) -> Result<u64> {
+        Ok(scan(&self.scope, None, |page| self.inner.find_all(page)).await?.len() as u64)
+    }
+}
+
+#[async_trait]
+impl DocumentRepository for TenantScopedDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        if let Some(existing) = self.inner.find_by_id(&document.id).await? {
+            if !self.scope.contains(existing.tenant_id()) {
+                return Err(WritemagicError::not_found(format!("Document {}", document.id)).into());
+            }
+        }
+
+        let mut scoped = document.clone();
+        scoped.set_tenant_id(self.scope.tenant_id().map(str::to_string));
+        self.inner.save_if_version(&scoped, expected_version).await
+    }
+
+    async fn find_by_ids(&self, ids: &[EntityId]) -> Result<Vec<Document>> {
+        let mut documents = self.inner.find_by_ids(ids).await?;
+        documents.retain(|document| self.scope.contains(document.tenant_id()));
+        Ok(documents)
+    }
+
+    async fn find_by_project_id(&self, project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_by_project_id(project_id, page)).await
+    }
+
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_by_tag(tag, page)).await
+    }
+
+    async fn find_by_content_type(&self, content_type: &ContentType, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_by_content_type(content_type, page)).await
+    }
+
+    async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.search_by_title(query, page)).await
+    }
+
+    async fn search_titles_fuzzy(&self, query: &str, limit: u32) -> Result<Vec<(Document, f32)>> {
+        let mut candidates = scan(&self.scope, None, |page| self.inner.find_all(page)).await?;
+        candidates.retain(|document| !document.is_deleted);
+        Ok(rank_fuzzy(query, candidates, |document| document.title.as_str(), limit))
+    }
+
+    async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.search_by_content(query, page)).await
+    }
+
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.search(query, page, include_deleted)).await
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_by_creator(user_id, page)).await
+    }
+
+    async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_recently_updated(page)).await
+    }
+
+    async fn find_deleted(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_deleted(page)).await
+    }
+
+    async fn get_statistics(&self) -> Result<DocumentStatistics> {
+        let mut documents = scan(&self.scope, None, |page| self.inner.find_all(page)).await?;
+        for deleted in scan(&self.scope, None, |page| self.inner.find_deleted(page)).await? {
+            if !documents.iter().any(|document| document.id == deleted.id) {
+                documents.push(deleted);
+            }
+        }
+        Ok(DocumentStatistics::from_documents(&documents))
+    }
+}
+
+/// Project repository restricted to one tenant by filtering another repository's results
+pub struct TenantScopedProjectRepository {
+    inner: Arc<dyn ProjectRepository>,
+    scope: TenantScope,
+}
+
+impl TenantScopedProjectRepository {
+    pub fn new(inner: Arc<dyn ProjectRepository>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    pub fn scope(&self) -> &TenantScope {
+        &self.scope
+    }
+}
+
+#[async_trait]
+impl Repository<Project, EntityId> for TenantScopedProjectRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Project>> {
+        Ok(self.inner.find_by_id(id).await?.filter(|project| self.scope.contains(project.tenant_id())))
+    }
+
+    async fn find_all(&self, pagination: Pagination) -> Result<Vec<Project>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_all(page)).await
+    }
+
+    async fn save(&self, entity: &Project) -> Result<Project> {
+        if let Some(existing) = self.inner.find_by_id(&entity.id).await? {
+            if !self.scope.contains(existing.tenant_id()) {
+                return Err(WritemagicError::not_found(format!("Project {}", entity.id)));
+            }
+        }
+
+        let mut project = entity.clone();
+        project.set_tenant_id(self.scope.tenant_id().map(str::to_string));
+        self.inner.save(&project).await
+    }
+
+    async fn delete(&self, id: &EntityId) -> Result<bool> {
+        if self.find_by_id(id).await?.is_none() {
+            return Ok(false);
+        }
+        self.inner.delete(id).await
+    }
+
+    async fn exists(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    async fn count(&self) -> Result<u64> {
+        Ok(scan(&self.scope, None, |page| self.inner.find_all(page)).await?.len() as u64)
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for TenantScopedProjectRepository {
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> Result<Project> {
+        if let Some(existing) = self.inner.find_by_id(&project.id).await? {
+            if !self.scope.contains(existing.tenant_id()) {
+                return Err(WritemagicError::not_found(format!("Project {}", project.id)));
+            }
+        }
+
+        let mut scoped = project.clone();
+        scoped.set_tenant_id(self.scope.tenant_id().map(str::to_string));
+        self.inner.save_if_version(&scoped, expected_version).await
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_by_creator(user_id, page)).await
+    }
+
+    async fn search_by_name(&self, query: &str, pagination: Pagination) -> Result<Vec<Project>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.search_by_name(query, page)).await
+    }
+
+    async fn find_containing_document(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_containing_document(document_id, page)).await
+    }
+
+    async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Project>> {
+        scan(&self.scope, Some(pagination), |page| self.inner.find_recently_updated(page)).await
+    }
+
+    async fn get_statistics(&self) -> Result<ProjectStatistics> {
+        let projects = scan(&self.scope, None, |page| self.inner.find_all(page)).await?;
+        Ok(ProjectStatistics::from_projects(&projects))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::InMemoryDocumentRepository;
+
+    #[tokio::test]
+    async fn test_pages_skip_other_tenants() {
+        let inner: Arc<dyn DocumentRepository> = Arc::new(InMemoryDocumentRepository::new());
+        let acme = TenantScopedDocumentRepository::new(inner.clone(), TenantScope::tenant("acme"));
+        let globex = TenantScopedDocumentRepository::new(inner.clone(), TenantScope::tenant("globex"));
+
+        for i in 0..5 {
+            let title = format!("Doc {}", i);
+            acme.save(&Document::new(title.clone(), String::new(), ContentType::Markdown, None)).await.unwrap();
+            globex.save(&Document::new(title, String::new(), ContentType::Markdown, None)).await.unwrap();
+        }
+
+        let page = acme.find_all(Pagination { offset: 1, limit: 3 }).await.unwrap();
+        assert_eq!(page.len(), 3);
+        assert!(page.iter().all(|document| document.tenant_id.as_deref() == Some("acme")));
+        assert_eq!(acme.count().await.unwrap(), 5);
+        assert_eq!(inner.count().await.unwrap(), 10);
+    }
+}
@@ -0,0 +1,111 @@
+//! Per-document undo and redo of content edits
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use writemagic_shared::EntityId;
+
+/// Undo configuration
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct UndoConfig {
+    /// Edits kept per document; the oldest are dropped beyond this
+    pub max_depth: usize,
+}
+
+impl Default for UndoConfig {
+    fn default() -> Self {
+        Self { max_depth: 100 }
+    }
+}
+
+/// Outcome of an undo or redo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoOutcome {
+    /// The document now holds `content` at `version`
+    Applied { content: String, version: u64 },
+    /// No earlier edit is recorded
+    NothingToUndo,
+    /// No undone edit is available to reapply
+    NothingToRedo,
+}
+
+#[derive(Debug, Default)]
+struct UndoStack {
+    /// Content before each recorded edit, oldest first
+    undo: VecDeque<String>,
+    /// Content undone, most recently undone last
+    redo: Vec<String>,
+}
+
+/// Bounded undo and redo stacks for each document
+#[derive(Debug)]
+pub struct UndoHistory {
+    max_depth: usize,
+    stacks: Mutex<HashMap<EntityId, UndoStack>>,
+}
+
+impl UndoHistory {
+    pub fn new(config: &UndoConfig) -> Self {
+        Self {
+            max_depth: config.max_depth,
+            stacks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a new edit away from `previous`, discarding anything undone
+    pub fn record_edit(&self, document_id: EntityId, previous: String) {
+        if self.max_depth == 0 {
+            return;
+        }
+        let mut stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        let stack = stacks.entry(document_id).or_default();
+        stack.redo.clear();
+        stack.undo.push_back(previous);
+        while stack.undo.len() > self.max_depth {
+            stack.undo.pop_front();
+        }
+    }
+
+    /// Content an undo would restore
+    pub fn peek_undo(&self, document_id: &EntityId) -> Option<String> {
+        let stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        stacks.get(document_id).and_then(|stack| stack.undo.back().cloned())
+    }
+
+    /// Content a redo would reapply
+    pub fn peek_redo(&self, document_id: &EntityId) -> Option<String> {
+        let stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        stacks.get(document_id).and_then(|stack| stack.redo.last().cloned())
+    }
+
+    /// Move the cursor back one edit after `replaced` was swapped for the undo content
+    pub fn commit_undo(&self, document_id: EntityId, replaced: String) {
+        let mut stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        let stack = stacks.entry(document_id).or_default();
+        stack.undo.pop_back();
+        stack.redo.push(replaced);
+    }
+
+    /// Move the cursor forward one edit after `replaced` was swapped for the redo content
+    pub fn commit_redo(&self, document_id: EntityId, replaced: String) {
+        let mut stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        let stack = stacks.entry(document_id).or_default();
+        stack.redo.pop();
+        stack.undo.push_back(replaced);
+        while stack.undo.len() > self.max_depth {
+            stack.undo.pop_front();
+        }
+    }
+
+    /// Forget the history of a document
+    pub fn clear(&self, document_id: &EntityId) {
+        let mut stacks = self.stacks.lock().unwrap_or_else(|e| e.into_inner());
+        stacks.remove(document_id);
+    }
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self::new(&UndoConfig::default())
+    }
+}
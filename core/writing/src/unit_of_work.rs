@@ -0,0 +1,438 @@
+//! Units of work that stage document and project writes and apply them all at once
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use writemagic_shared::{
+    ContentType, DocumentTag, EntityId, Pagination, ProjectDomainService, Repository, Result, UnitOfWork,
+    WritemagicError, WritingDomainService,
+};
+use crate::cross_domain::{ProjectServiceAdapter, WritingServiceAdapter};
+use crate::entities::{Document, Project};
+use crate::repositories::{
+    ConcurrencyError, DocumentRepository, DocumentStatistics, InMemoryDocumentRepository, InMemoryProjectRepository,
+    ProjectRepository, ProjectStatistics, project_version_mismatch,
+};
+use crate::services::{DocumentManagementService, ProjectManagementService};
+
+/// Entity written by a unit of work, with the version it was read at
+#[derive(Debug, Clone)]
+pub struct StagedWrite<T> {
+    pub entity: T,
+    /// Stored version when the unit of work first read the entity, or `None` if it did not exist
+    pub read_version: Option<u64>,
+}
+
+/// Document and project writes of one unit of work, at most one per entity
+#[derive(Debug, Clone, Default)]
+pub struct StagedWrites {
+    pub documents: Vec<StagedWrite<Document>>,
+    pub projects: Vec<StagedWrite<Project>>,
+}
+
+/// Storage a unit of work applies its writes to
+#[async_trait]
+pub trait StagedWriteStore: Send + Sync {
+    /// Apply every write or none of them
+    ///
+    /// Fails with a conflict when an entity's stored version is no longer its
+    /// `read_version`, so changes made outside the unit of work are never
+    /// overwritten.
+    async fn apply(&self, writes: StagedWrites) -> Result<()>;
+}
+
+fn changed_since_read(kind: &str, id: &EntityId) -> WritemagicError {
+    WritemagicError::conflict(format!("{} {} changed since the unit of work read it", kind, id))
+}
+
+/// Applies writes to the in-memory repositories under both of their locks
+pub struct InMemoryWriteStore {
+    documents: InMemoryDocumentRepository,
+    projects: InMemoryProjectRepository,
+}
+
+impl InMemoryWriteStore {
+    pub fn new(documents: InMemoryDocumentRepository, projects: InMemoryProjectRepository) -> Self {
+        Self { documents, projects }
+    }
+}
+
+#[async_trait]
+impl StagedWriteStore for InMemoryWriteStore {
+    async fn apply(&self, writes: StagedWrites) -> Result<()> {
+        self.documents.update(|documents| {
+            self.projects.update(|projects| {
+                for write in &writes.documents {
+                    if documents.get(&write.entity.id).map(|stored| stored.version) != write.read_version {
+                        return Err(changed_since_read("Document", &write.entity.id));
+                    }
+                }
+                for write in &writes.projects {
+                    if projects.get(&write.entity.id).map(|stored| stored.version) != write.read_version {
+                        return Err(changed_since_read("Project", &write.entity.id));
+                    }
+                }
+
+                for write in writes.documents {
+                    documents.insert(write.entity.id, write.entity);
+                }
+                for write in writes.projects {
+                    projects.insert(write.entity.id, write.entity);
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+/// Entities one transaction has read or written
+struct Overlay<T> {
+    /// Stored version of each entity when the transaction first read it
+    read_versions: HashMap<EntityId, Option<u64>>,
+    writes: HashMap<EntityId, T>,
+}
+
+impl<T> Default for Overlay<T> {
+    fn default() -> Self {
+        Self { read_versions: HashMap::new(), writes: HashMap::new() }
+    }
+}
+
+impl<T> Overlay<T> {
+    fn note_read(&mut self, id: EntityId, version: Option<u64>) {
+        self.read_versions.entry(id).or_insert(version);
+    }
+
+    fn into_writes(self) -> Vec<StagedWrite<T>> {
+        let read_versions = self.read_versions;
+        self.writes
+            .into_iter()
+            .map(|(id, entity)| StagedWrite { entity, read_version: read_versions.get(&id).copied().flatten() })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Staging {
+    documents: Overlay<Document>,
+    projects: Overlay<Project>,
+}
+
+type SharedStaging = Arc<Mutex<Staging>>;
+
+fn lock(staging: &SharedStaging) -> Result<MutexGuard<'_, Staging>> {
+    staging.lock().map_err(|_| WritemagicError::internal("Failed to acquire unit of work lock"))
+}
+
+fn not_in_unit_of_work(operation: &str) -> WritemagicError {
+    WritemagicError::not_implemented(format!("{} is not supported inside a unit of work", operation))
+}
+
+/// Documents as one transaction sees them: its own writes over the stored ones
+///
+/// Only lookups by ID see the transaction's writes; listings and searches
+/// read what is committed.
+struct StagingDocumentRepository {
+    base: Arc<dyn DocumentRepository>,
+    staging: SharedStaging,
+}
+
+impl StagingDocumentRepository {
+    /// Current version of `id` for this transaction, noting the stored one if it wasn't read yet
+    async fn current_version(&self, id: &EntityId) -> Result<Option<u64>> {
+        if let Some(document) = lock(&self.staging)?.documents.writes.get(id) {
+            return Ok(Some(document.version));
+        }
+        self.find_by_id(id).await.map(|document| document.map(|document| document.version))
+    }
+
+    fn stage(&self, document: &Document) -> Result<Document> {
+        lock(&self.staging)?.documents.writes.insert(document.id, document.clone());
+        Ok(document.clone())
+    }
+}
+
+#[async_trait]
+impl Repository<Document, EntityId> for StagingDocumentRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Document>> {
+        if let Some(document) = lock(&self.staging)?.documents.writes.get(id) {
+            return Ok(Some(document.clone()));
+        }
+        let document = self.base.find_by_id(id).await?;
+        lock(&self.staging)?.documents.note_read(*id, document.as_ref().map(|document| document.version));
+        Ok(document)
+    }
+
+    async fn find_all(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_all(pagination).await
+    }
+
+    async fn save(&self, entity: &Document) -> Result<Document> {
+        self.current_version(&entity.id).await?;
+        self.stage(entity)
+    }
+
+    async fn delete(&self, _id: &EntityId) -> Result<bool> {
+        Err(not_in_unit_of_work("Deleting documents"))
+    }
+
+    async fn exists(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    async fn count(&self) -> Result<u64> {
+        self.base.count().await
+    }
+}
+
+#[async_trait]
+impl DocumentRepository for StagingDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        let actual = self.current_version(&document.id).await?;
+        if actual != Some(expected_version) {
+            return Err(ConcurrencyError::VersionMismatch { document_id: document.id, expected: expected_version, actual });
+        }
+        Ok(self.stage(document)?)
+    }
+
+    async fn find_by_project_id(&self, project_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_by_project_id(project_id, pagination).await
+    }
+
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_by_tag(tag, pagination).await
+    }
+
+    async fn find_by_content_type(&self, content_type: &ContentType, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_by_content_type(content_type, pagination).await
+    }
+
+    async fn search_by_title(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.search_by_title(query, pagination).await
+    }
+
+    async fn search_by_content(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.search_by_content(query, pagination).await
+    }
+
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
+        self.base.search(query, pagination, include_deleted).await
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_by_creator(user_id, pagination).await
+    }
+
+    async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_recently_updated(pagination).await
+    }
+
+    async fn find_deleted(&self, pagination: Pagination) -> Result<Vec<Document>> {
+        self.base.find_deleted(pagination).await
+    }
+
+    async fn get_statistics(&self) -> Result<DocumentStatistics> {
+        self.base.get_statistics().await
+    }
+}
+
+/// Projects as one transaction sees them, like [`StagingDocumentRepository`]
+struct StagingProjectRepository {
+    base: Arc<dyn ProjectRepository>,
+    staging: SharedStaging,
+}
+
+#[async_trait]
+impl Repository<Project, EntityId> for StagingProjectRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Project>> {
+        if let Some(project) = lock(&self.staging)?.projects.writes.get(id) {
+            return Ok(Some(project.clone()));
+        }
+        let project = self.base.find_by_id(id).await?;
+        lock(&self.staging)?.projects.note_read(*id, project.as_ref().map(|project| project.version));
+        Ok(project)
+    }
+
+    async fn find_all(&self, pagination: Pagination) -> Result<Vec<Project>> {
+        self.base.find_all(pagination).await
+    }
+
+    async fn save(&self, entity: &Project) -> Result<Project> {
+        // Records the stored version for the commit's check
+        self.find_by_id(&entity.id).await?;
+        lock(&self.staging)?.projects.writes.insert(entity.id, entity.clone());
+        Ok(entity.clone())
+    }
+
+    async fn delete(&self, _id: &EntityId) -> Result<bool> {
+        Err(not_in_unit_of_work("Deleting projects"))
+    }
+
+    async fn exists(&self, id: &EntityId) -> Result<bool> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    async fn count(&self) -> Result<u64> {
+        self.base.count().await
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for StagingProjectRepository {
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> Result<Project> {
+        let actual = self.find_by_id(&project.id).await?.map(|stored| stored.version);
+        if actual != Some(expected_version) {
+            return Err(project_version_mismatch(project.id, expected_version, actual));
+        }
+        lock(&self.staging)?.projects.writes.insert(project.id, project.clone());
+        Ok(project.clone())
+    }
+
+    async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
+        self.base.find_by_creator(user_id, pagination).await
+    }
+
+    async fn search_by_name(&self, query: &str, pagination: Pagination) -> Result<Vec<Project>> {
+        self.base.search_by_name(query, pagination).await
+    }
+
+    async fn find_containing_document(&self, document_id: &EntityId, pagination: Pagination) -> Result<Vec<Project>> {
+        self.base.find_containing_document(document_id, pagination).await
+    }
+
+    async fn find_recently_updated(&self, pagination: Pagination) -> Result<Vec<Project>> {
+        self.base.find_recently_updated(pagination).await
+    }
+
+    async fn get_statistics(&self) -> Result<ProjectStatistics> {
+        self.base.get_statistics().await
+    }
+}
+
+/// Writes and services of the transaction in progress
+struct Transaction {
+    staging: SharedStaging,
+    writing_service: Arc<WritingServiceAdapter>,
+    project_service: Arc<ProjectServiceAdapter>,
+}
+
+/// Unit of work whose services stage writes until it commits
+///
+/// `begin` hands out writing and project services bound to the transaction.
+/// Their lookups by ID see the transaction's own writes, `commit` applies all
+/// of them through the store at once, and `rollback` discards them. The
+/// commit fails with a conflict if an entity changed since the transaction
+/// read it. Links, undo history and idempotency keys kept by the services are
+/// written straight away and are not covered.
+pub struct StagedUnitOfWork {
+    store: Arc<dyn StagedWriteStore>,
+    document_service: Arc<DocumentManagementService>,
+    project_service: Arc<ProjectManagementService>,
+    document_repository: Arc<dyn DocumentRepository>,
+    project_repository: Arc<dyn ProjectRepository>,
+    transaction: Option<Transaction>,
+}
+
+impl StagedUnitOfWork {
+    /// Unit of work over the repositories behind `document_service` and `project_service`
+    ///
+    /// `store` must write to the same storage as the repositories.
+    pub fn new(
+        store: Arc<dyn StagedWriteStore>,
+        document_service: Arc<DocumentManagementService>,
+        project_service: Arc<ProjectManagementService>,
+        document_repository: Arc<dyn DocumentRepository>,
+        project_repository: Arc<dyn ProjectRepository>,
+    ) -> Self {
+        Self { store, document_service, project_service, document_repository, project_repository, transaction: None }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    fn take_transaction(&mut self) -> Result<Transaction> {
+        self.transaction
+            .take()
+            .ok_or_else(|| WritemagicError::validation("No transaction in progress"))
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for StagedUnitOfWork {
+    async fn begin(&mut self) -> Result<()> {
+        if self.transaction.is_some() {
+            return Err(WritemagicError::validation("Transaction already in progress"));
+        }
+
+        let staging = SharedStaging::default();
+        let documents: Arc<dyn DocumentRepository> = Arc::new(StagingDocumentRepository {
+            base: self.document_repository.clone(),
+            staging: staging.clone(),
+        });
+        let projects: Arc<dyn ProjectRepository> = Arc::new(StagingProjectRepository {
+            base: self.project_repository.clone(),
+            staging: staging.clone(),
+        });
+
+        let document_service = Arc::new(self.document_service.with_staged_repositories(documents.clone(), projects.clone()));
+        let project_service = Arc::new(self.project_service.with_staged_repositories(projects.clone(), documents.clone()));
+        self.transaction = Some(Transaction {
+            staging,
+            writing_service: Arc::new(WritingServiceAdapter::new(document_service, documents.clone(), project_service.clone())),
+            project_service: Arc::new(ProjectServiceAdapter::new(project_service, projects, documents)),
+        });
+        Ok(())
+    }
+
+    async fn commit(&mut self) -> Result<()> {
+        let transaction = self.take_transaction()?;
+        // The services may still be referenced, so move the writes out rather than unwrapping the Arc
+        let staging = std::mem::take(&mut *lock(&transaction.staging)?);
+        self.store
+            .apply(StagedWrites {
+                documents: staging.documents.into_writes(),
+                projects: staging.projects.into_writes(),
+            })
+            .await
+    }
+
+    async fn rollback(&mut self) -> Result<()> {
+        self.take_transaction().map(|_| ())
+    }
+
+    fn writing_service(&self) -> Option<Arc<dyn WritingDomainService>> {
+        self.transaction
+            .as_ref()
+            .map(|transaction| transaction.writing_service.clone() as Arc<dyn WritingDomainService>)
+    }
+
+    fn project_service(&self) -> Option<Arc<dyn ProjectDomainService>> {
+        self.transaction
+            .as_ref()
+            .map(|transaction| transaction.project_service.clone() as Arc<dyn ProjectDomainService>)
+    }
+
+    async fn with_transaction<F, R>(&mut self, work: F) -> Result<R>
+    where
+        F: FnOnce(&mut Self) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<R>> + Send>> + Send,
+        R: Send,
+        Self: Sized,
+    {
+        self.begin().await?;
+        match work(self).await {
+            Ok(result) => {
+                self.commit().await?;
+                Ok(result)
+            }
+            Err(error) => {
+                self.rollback().await?;
+                Err(error)
+            }
+        }
+    }
+}
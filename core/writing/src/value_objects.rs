@@ -1,7 +1,7 @@
 //! Writing domain value objects
 
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use writemagic_shared::validation::{validate_document_title, validate_project_name, DOCUMENT_CONTENT_MAX_LENGTH};
 use writemagic_shared::{ValueObject, Result, WritemagicError};
 
 /// Word count value object
@@ -79,20 +79,18 @@ impl std::fmt::Display for CharacterCount {
 }
 
 /// Document title value object
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DocumentTitle {
-    #[validate(length(min = 1, max = 255))]
     pub value: String,
 }
 
 impl DocumentTitle {
     pub fn new(title: impl Into<String>) -> Result<Self> {
         let title = title.into().trim().to_string();
-        let document_title = Self { value: title };
-        document_title.validate().map_err(|e| {
-            WritemagicError::validation(format!("Invalid document title: {}", e))
+        validate_document_title(&title).map_err(|e| {
+            WritemagicError::validation(format!("Invalid document title: {}", e.message))
         })?;
-        Ok(document_title)
+        Ok(Self { value: title })
     }
 
     pub fn as_str(&self) -> &str {
@@ -109,20 +107,18 @@ impl std::fmt::Display for DocumentTitle {
 }
 
 /// Project name value object
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ProjectName {
-    #[validate(length(min = 1, max = 100))]
     pub value: String,
 }
 
 impl ProjectName {
     pub fn new(name: impl Into<String>) -> Result<Self> {
         let name = name.into().trim().to_string();
-        let project_name = Self { value: name };
-        project_name.validate().map_err(|e| {
-            WritemagicError::validation(format!("Invalid project name: {}", e))
+        validate_project_name(&name).map_err(|e| {
+            WritemagicError::validation(format!("Invalid project name: {}", e.message))
         })?;
-        Ok(project_name)
+        Ok(Self { value: name })
     }
 
     pub fn as_str(&self) -> &str {
@@ -139,20 +135,49 @@ impl std::fmt::Display for ProjectName {
 }
 
 /// Document content value object
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DocumentContent {
-    #[validate(length(max = 10485760))] // 10MB max
     pub value: String,
 }
 
+/// Content longer than [`DocumentContent::MAX_LEN`], with both lengths in characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid document content: {actual} characters, at most {allowed} allowed")]
+pub struct ContentTooLong {
+    pub actual: usize,
+    pub allowed: usize,
+}
+
+impl From<ContentTooLong> for WritemagicError {
+    fn from(error: ContentTooLong) -> Self {
+        WritemagicError::validation(error.to_string())
+    }
+}
+
 impl DocumentContent {
+    /// Longest content accepted, in characters
+    pub const MAX_LEN: usize = DOCUMENT_CONTENT_MAX_LENGTH;
+
     pub fn new(content: impl Into<String>) -> Result<Self> {
+        Ok(Self::try_new(content)?)
+    }
+
+    /// Like [`DocumentContent::new`], keeping the lengths of over-long content
+    pub fn try_new(content: impl Into<String>) -> std::result::Result<Self, ContentTooLong> {
         let content = content.into();
-        let document_content = Self { value: content };
-        document_content.validate().map_err(|e| {
-            WritemagicError::validation(format!("Invalid document content: {}", e))
-        })?;
-        Ok(document_content)
+        let actual = content.chars().count();
+        if actual > Self::MAX_LEN {
+            return Err(ContentTooLong { actual, allowed: Self::MAX_LEN });
+        }
+        Ok(Self { value: content })
+    }
+
+    /// Content cut to its first [`DocumentContent::MAX_LEN`] characters, and whether anything was cut
+    pub fn new_truncated(content: &str) -> (Self, bool) {
+        match content.char_indices().nth(Self::MAX_LEN) {
+            Some((end, _)) => (Self { value: content[..end].to_string() }, true),
+            None => (Self { value: content.to_string() }, false),
+        }
     }
 
     pub fn as_str(&self) -> &str {
@@ -270,4 +295,41 @@ impl std::fmt::Display for TextSelection {
             write!(f, "selection {}..{}", self.start, self.end)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_length_limit_boundaries() {
+        let max = "a".repeat(DocumentContent::MAX_LEN);
+        assert!(DocumentContent::new(max.as_str()).is_ok());
+        let (content, truncated) = DocumentContent::new_truncated(&max);
+        assert_eq!((content.value.len(), truncated), (DocumentContent::MAX_LEN, false));
+
+        let over = format!("{}b", max);
+        let error = DocumentContent::try_new(over.as_str()).unwrap_err();
+        assert_eq!(error, ContentTooLong { actual: DocumentContent::MAX_LEN + 1, allowed: DocumentContent::MAX_LEN });
+        assert!(matches!(DocumentContent::new(over.as_str()), Err(WritemagicError::Validation { .. })));
+        let (content, truncated) = DocumentContent::new_truncated(&over);
+        assert_eq!(content.value, max);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_content_length_counts_multibyte_characters() {
+        // Exactly MAX_LEN characters but far more bytes
+        let max = "é".repeat(DocumentContent::MAX_LEN);
+        assert!(DocumentContent::try_new(max.as_str()).is_ok());
+
+        // The last kept character and the first dropped one are both multibyte
+        let over = format!("{}é🦀", "a".repeat(DocumentContent::MAX_LEN - 1));
+        assert_eq!(DocumentContent::try_new(over.as_str()).unwrap_err().actual, DocumentContent::MAX_LEN + 1);
+        let (content, truncated) = DocumentContent::new_truncated(&over);
+        assert!(truncated);
+        assert!(content.value.ends_with('é'));
+        assert_eq!(content.value.chars().count(), DocumentContent::MAX_LEN);
+        assert!(DocumentContent::try_new(content.value).is_ok());
+    }
+}
@@ -0,0 +1,48 @@
+//! Content history of documents
+
+use serde::{Deserialize, Serialize};
+use writemagic_shared::{ContentHash, ContentType, EntityId, Timestamp};
+use crate::entities::Document;
+
+/// Version history configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersionHistoryConfig {
+    /// Prior versions kept per document, the oldest are dropped beyond this; 0 disables history
+    pub max_versions_per_document: usize,
+}
+
+impl Default for VersionHistoryConfig {
+    fn default() -> Self {
+        Self { max_versions_per_document: 50 }
+    }
+}
+
+/// Content of a document as it was at one of its versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentVersion {
+    pub document_id: EntityId,
+    pub version: u64,
+    pub content: String,
+    pub content_type: ContentType,
+    pub content_hash: ContentHash,
+    /// When this version was written
+    pub created_at: Timestamp,
+    /// Who wrote this version
+    pub created_by: Option<EntityId>,
+}
+
+impl DocumentVersion {
+    /// Snapshot the current content of `document`
+    pub fn snapshot(document: &Document) -> Self {
+        Self {
+            document_id: document.id,
+            version: document.version,
+            content: document.content.clone(),
+            content_type: document.content_type.clone(),
+            content_hash: document.content_hash.clone(),
+            created_at: document.updated_at.clone(),
+            created_by: document.updated_by.or(document.created_by),
+        }
+    }
+}
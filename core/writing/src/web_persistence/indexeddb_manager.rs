@@ -25,6 +25,9 @@ pub struct IndexedDbConfig {
     pub timeout_ms: u32,
     pub enable_logging: bool,
     pub auto_migrate: bool,
+    /// How to free space when a write exceeds the storage quota
+    #[serde(default)]
+    pub quota_eviction: QuotaEvictionPolicy,
 }
 
 impl Default for IndexedDbConfig {
@@ -35,10 +38,27 @@ impl Default for IndexedDbConfig {
             timeout_ms: 30000, // 30 seconds
             enable_logging: true,
             auto_migrate: true,
+            quota_eviction: QuotaEvictionPolicy::default(),
         }
     }
 }
 
+/// Space reclaimed before retrying a write that failed on a full quota
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum QuotaEvictionPolicy {
+    /// Fail the write without freeing any space
+    Disabled,
+    /// Permanently remove soft-deleted documents, oldest deletion first
+    OldestDeleted { max_documents: usize },
+}
+
+impl Default for QuotaEvictionPolicy {
+    fn default() -> Self {
+        Self::OldestDeleted { max_documents: 50 }
+    }
+}
+
 /// Information about the current database state
 #[derive(Debug, Clone)]
 pub struct DatabaseInfo {
@@ -68,6 +88,11 @@ impl IndexedDbManager {
         Self::new(IndexedDbConfig::default())
     }
     
+    /// Eviction policy applied when a write exceeds the storage quota
+    pub fn quota_eviction(&self) -> &QuotaEvictionPolicy {
+        &self.config.quota_eviction
+    }
+    
     /// Initialize the database connection
     pub async fn initialize(&mut self) -> Result<()> {
         if self.config.enable_logging {
@@ -11,17 +11,22 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::*;
 use js_sys::{Array, Object, Reflect, Promise};
 
-use writemagic_shared::{EntityId, Pagination, Repository, Result as SharedResult, WritemagicError, ContentType};
+use writemagic_shared::{
+    CheckpointId, ContextCheckpoint, ContextCheckpointStore, DocumentTag, EntityId, Pagination, Repository,
+    Result as SharedResult, Timestamp, WritemagicError, ContentType,
+};
 use crate::entities::{Document, Project};
-use crate::repositories::{DocumentRepository, ProjectRepository, DocumentStatistics, ProjectStatistics};
+use crate::repositories::{
+    project_version_mismatch, ConcurrencyError, DocumentRepository, ProjectRepository, DocumentStatistics, ProjectStatistics,
+};
 
-use super::indexeddb_manager::IndexedDbManager;
+use super::indexeddb_manager::{IndexedDbManager, QuotaEvictionPolicy};
 use super::schema::{ObjectStore, SearchConfig};
 use super::serialization::{IndexedDbDocument, IndexedDbProject, IndexedDbProjectDocument, BatchOperation, BatchOperationType};
-use super::{IndexedDbError, Result, js_error_to_indexeddb_error};
+use super::{IndexedDbError, Result, js_error_to_indexeddb_error, retry_after_eviction};
 
 /// Helper function to convert IdbRequest to Promise for JsFuture
-fn request_to_promise(request: IdbRequest) -> Promise {
+pub(super) fn request_to_promise(request: IdbRequest) -> Promise {
     Promise::new(&mut |resolve, reject| {
         let request_clone = request.clone();
         let success_closure = Closure::wrap(Box::new(move |_event: Event| {
@@ -41,6 +46,46 @@ fn request_to_promise(request: IdbRequest) -> Promise {
     })
 }
 
+/// Apply the manager's quota eviction policy, returning how many documents were removed
+async fn evict_deleted_documents(manager: &IndexedDbManager) -> Result<usize> {
+    let max_documents = match manager.quota_eviction() {
+        QuotaEvictionPolicy::Disabled => return Ok(0),
+        QuotaEvictionPolicy::OldestDeleted { max_documents } => *max_documents,
+    };
+    
+    let transaction = manager.write_transaction(&[ObjectStore::Documents])?;
+    let store = manager.object_store(&transaction, ObjectStore::Documents)?;
+    
+    let request = store.get_all()
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Getting documents for eviction"))?;
+    let result = JsFuture::from(request_to_promise(request)).await
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Eviction scan completion"))?;
+    
+    let array = Array::from(&result);
+    let mut deleted = Vec::new();
+    for i in 0..array.length() {
+        let indexed_doc = IndexedDbDocument::from_js_value(&array.get(i))?;
+        if indexed_doc.is_deleted {
+            let deleted_at = indexed_doc.deleted_at.unwrap_or(indexed_doc.updated_at);
+            deleted.push((deleted_at, indexed_doc.id));
+        }
+    }
+    
+    // Stored timestamps are zero-padded UTC strings, so they sort chronologically
+    deleted.sort();
+    deleted.truncate(max_documents);
+    
+    for (_, id) in &deleted {
+        let request = store.delete(&JsValue::from_str(id))
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Evicting deleted document"))?;
+        JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Eviction completion"))?;
+    }
+    
+    manager.execute_transaction(transaction).await?;
+    Ok(deleted.len())
+}
+
 /// IndexedDB implementation of DocumentRepository
 pub struct IndexedDbDocumentRepository {
     manager: std::sync::Arc<tokio::sync::Mutex<IndexedDbManager>>,
@@ -56,6 +101,29 @@ impl IndexedDbDocumentRepository {
         }
     }
     
+    /// Free space according to the configured quota eviction policy
+    ///
+    /// Returns the number of soft-deleted documents permanently removed.
+    pub async fn evict_for_quota(&self) -> Result<usize> {
+        let manager = self.manager.lock().await;
+        evict_deleted_documents(&manager).await
+    }
+    
+    /// Write a serialized document in its own transaction
+    async fn put_document(&self, js_doc: &JsValue) -> Result<()> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::Documents])?;
+        let store = manager.object_store(&transaction, ObjectStore::Documents)?;
+        
+        let request = store.put(js_doc)
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Save document"))?;
+        
+        JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Save completion"))?;
+        
+        manager.execute_transaction(transaction).await
+    }
+    
     /// Execute a batch of document operations
     pub async fn execute_batch(&self, batch: BatchOperation<IndexedDbDocument>) -> Result<Vec<Document>> {
         if batch.is_empty() {
@@ -105,7 +173,7 @@ impl IndexedDbDocumentRepository {
     }
     
     /// Search documents by text content with scoring
-    async fn search_documents_by_text(&self, query: &str, pagination: Pagination) -> Result<Vec<Document>> {
+    async fn search_documents_by_text(&self, query: &str, pagination: Pagination, include_deleted: bool) -> Result<Vec<Document>> {
         let search_tokens = self.search_config.prepare_query(query);
         
         if search_tokens.is_empty() {
@@ -129,6 +197,9 @@ impl IndexedDbDocumentRepository {
         for i in 0..array.length() {
             let js_doc = array.get(i);
             let indexed_doc = IndexedDbDocument::from_js_value(&js_doc)?;
+            if indexed_doc.is_deleted && !include_deleted {
+                continue;
+            }
             
             // Calculate relevance score
             let score = self.calculate_relevance_score(&indexed_doc, &search_tokens);
@@ -331,22 +402,11 @@ impl Repository<Document, EntityId> for IndexedDbDocumentRepository {
     }
     
     async fn save(&self, entity: &Document) -> SharedResult<Document> {
-        let manager = self.manager.lock().await;
-        let transaction = manager.write_transaction(&[ObjectStore::Documents])?;
-        let store = manager.object_store(&transaction, ObjectStore::Documents)?;
-        
         let indexed_doc = IndexedDbDocument::from(entity);
         let js_doc = indexed_doc.to_js_value()
             .map_err(|e| WritemagicError::internal(&format!("Document serialization failed: {}", e)))?;
         
-        let request = store.put(&js_doc)
-            .map_err(|e| WritemagicError::database(&format!("Save document failed: {:?}", e)))?;
-        
-        JsFuture::from(request_to_promise(request)).await
-            .map_err(|e| WritemagicError::database(&format!("Save completion failed: {:?}", e)))?;
-        
-        manager.execute_transaction(transaction).await
-            .map_err(|e| WritemagicError::database(&format!("Transaction commit failed: {:?}", e)))?;
+        retry_after_eviction(|| self.put_document(&js_doc), || self.evict_for_quota()).await?;
         
         Ok(entity.clone())
     }
@@ -393,6 +453,81 @@ impl Repository<Document, EntityId> for IndexedDbDocumentRepository {
 
 #[async_trait]
 impl DocumentRepository for IndexedDbDocumentRepository {
+    async fn save_if_version(
+        &self,
+        document: &Document,
+        expected_version: u64,
+    ) -> std::result::Result<Document, ConcurrencyError> {
+        let js_doc = IndexedDbDocument::from(document).to_js_value()
+            .map_err(|e| WritemagicError::internal(&format!("Document serialization failed: {}", e)))?;
+
+        // Read and write in one transaction so no other save can land in between
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::Documents]).map_err(WritemagicError::from)?;
+        let store = manager.object_store(&transaction, ObjectStore::Documents).map_err(WritemagicError::from)?;
+
+        let get_request = store.get(&JsValue::from_str(&document.id.to_string()))
+            .map_err(|e| WritemagicError::database(&format!("Find by ID failed: {:?}", e)))?;
+        let stored = JsFuture::from(request_to_promise(get_request)).await
+            .map_err(|e| WritemagicError::database(&format!("Find by ID completion failed: {:?}", e)))?;
+
+        let actual = if stored.is_undefined() || stored.is_null() {
+            None
+        } else {
+            let indexed_doc = IndexedDbDocument::from_js_value(&stored)
+                .map_err(|e| WritemagicError::internal(&format!("Document deserialization failed: {}", e)))?;
+            Some(indexed_doc.version)
+        };
+        if actual != Some(expected_version) {
+            return Err(ConcurrencyError::VersionMismatch {
+                document_id: document.id,
+                expected: expected_version,
+                actual,
+            });
+        }
+
+        let put_request = store.put(&js_doc)
+            .map_err(|e| WritemagicError::from(js_error_to_indexeddb_error(&e, "Save document")))?;
+        JsFuture::from(request_to_promise(put_request)).await
+            .map_err(|e| WritemagicError::from(js_error_to_indexeddb_error(&e, "Save completion")))?;
+
+        manager.execute_transaction(transaction).await.map_err(WritemagicError::from)?;
+
+        Ok(document.clone())
+    }
+
+    async fn find_by_ids(&self, ids: &[EntityId]) -> SharedResult<Vec<Document>> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.read_transaction(&[ObjectStore::Documents])?;
+        let store = manager.object_store(&transaction, ObjectStore::Documents)?;
+
+        // Issue every get before awaiting any so they run concurrently within the transaction
+        let pending = ids.iter()
+            .map(|id| {
+                store.get(&JsValue::from_str(&id.to_string()))
+                    .map(|request| JsFuture::from(request_to_promise(request)))
+                    .map_err(|e| WritemagicError::database(&format!("Get document failed: {:?}", e)))
+            })
+            .collect::<SharedResult<Vec<_>>>()?;
+
+        let mut documents = Vec::with_capacity(ids.len());
+        for result in futures::future::join_all(pending).await {
+            let result = result
+                .map_err(|e| WritemagicError::database(&format!("Get document completion failed: {:?}", e)))?;
+            if result.is_undefined() || result.is_null() {
+                continue;
+            }
+
+            let indexed_doc = IndexedDbDocument::from_js_value(&result)
+                .map_err(|e| WritemagicError::internal(&format!("Document deserialization failed: {}", e)))?;
+            let document = indexed_doc.try_into()
+                .map_err(|e| WritemagicError::internal(&format!("Document conversion failed: {}", e)))?;
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+
     async fn find_by_project_id(&self, project_id: &EntityId, pagination: Pagination) -> SharedResult<Vec<Document>> {
         // First get project-document relationships
         let manager = self.manager.lock().await;
@@ -454,6 +589,18 @@ impl DocumentRepository for IndexedDbDocumentRepository {
         Ok(documents)
     }
     
+    async fn find_by_tag(&self, tag: &DocumentTag, pagination: Pagination) -> SharedResult<Vec<Document>> {
+        // Tags have no index in the documents store, so scan
+        let documents = self.find_all(Pagination { offset: 0, limit: u32::MAX }).await?;
+        
+        Ok(documents
+            .into_iter()
+            .filter(|doc| !doc.is_deleted && doc.tags.contains(tag))
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect())
+    }
+    
     async fn find_by_content_type(&self, content_type: &ContentType, pagination: Pagination) -> SharedResult<Vec<Document>> {
         self.get_documents_by_index("content_type", &JsValue::from_str(&content_type.to_string()), pagination).await
             .map_err(|e| WritemagicError::database(&format!("Find by content type failed: {:?}", e)))
@@ -510,10 +657,15 @@ impl DocumentRepository for IndexedDbDocumentRepository {
     }
     
     async fn search_by_content(&self, query: &str, pagination: Pagination) -> SharedResult<Vec<Document>> {
-        self.search_documents_by_text(query, pagination).await
+        self.search_documents_by_text(query, pagination, true).await
             .map_err(|e| WritemagicError::database(&format!("Content search failed: {:?}", e)))
     }
     
+    async fn search(&self, query: &str, pagination: Pagination, include_deleted: bool) -> SharedResult<Vec<Document>> {
+        self.search_documents_by_text(query, pagination, include_deleted).await
+            .map_err(|e| WritemagicError::database(&format!("Document search failed: {:?}", e)))
+    }
+    
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> SharedResult<Vec<Document>> {
         self.get_documents_by_index("created_by", &JsValue::from_str(&user_id.to_string()), pagination).await
             .map_err(|e| WritemagicError::database(&format!("Find by creator failed: {:?}", e)))
@@ -640,6 +792,88 @@ impl IndexedDbProjectRepository {
         Self { manager }
     }
     
+    /// Free space according to the configured quota eviction policy
+    ///
+    /// Projects are never evicted; space comes from soft-deleted documents.
+    pub async fn evict_for_quota(&self) -> Result<usize> {
+        let manager = self.manager.lock().await;
+        evict_deleted_documents(&manager).await
+    }
+    
+    /// Write a project and replace its document relationships in one transaction
+    ///
+    /// With `expected_version` nothing is written unless the stored project is
+    /// at that version; the version found instead is returned as the inner error.
+    async fn put_project(
+        &self,
+        entity: &Project,
+        expected_version: Option<u64>,
+    ) -> Result<std::result::Result<(), Option<u64>>> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::Projects, ObjectStore::ProjectDocuments])?;
+        let projects_store = manager.object_store(&transaction, ObjectStore::Projects)?;
+        let project_docs_store = manager.object_store(&transaction, ObjectStore::ProjectDocuments)?;
+        
+        if let Some(expected_version) = expected_version {
+            let get_request = projects_store.get(&JsValue::from_str(&entity.id.to_string()))
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Find project"))?;
+            let stored = JsFuture::from(request_to_promise(get_request)).await
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Find project completion"))?;
+            let actual = if stored.is_undefined() || stored.is_null() {
+                None
+            } else {
+                Some(IndexedDbProject::from_js_value(&stored)?.version)
+            };
+            if actual != Some(expected_version) {
+                return Ok(Err(actual));
+            }
+        }
+        
+        // Save project
+        let js_proj = IndexedDbProject::from(entity).to_js_value()?;
+        
+        let save_request = projects_store.put(&js_proj)
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Save project"))?;
+        
+        JsFuture::from(request_to_promise(save_request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Save project completion"))?;
+        
+        // Clear existing project-document relationships
+        let index = project_docs_store.index("project_id")
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Getting project_id index"))?;
+        
+        let get_relationships_request = index.get_all_with_key(&JsValue::from_str(&entity.id.to_string()))
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Get project relationships"))?;
+        
+        let relationships_result = JsFuture::from(request_to_promise(get_relationships_request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Get relationships completion"))?;
+        
+        let relationships_array = Array::from(&relationships_result);
+        for i in 0..relationships_array.length() {
+            let relationship = IndexedDbProjectDocument::from_js_value(&relationships_array.get(i))?;
+            
+            let delete_request = project_docs_store.delete(&JsValue::from_str(&relationship.composite_key))
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Delete old relationship"))?;
+            
+            JsFuture::from(request_to_promise(delete_request)).await
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Delete relationship completion"))?;
+        }
+        
+        // Add new project-document relationships
+        for doc_id in &entity.document_ids {
+            let js_rel = IndexedDbProjectDocument::new(&entity.id, doc_id).to_js_value()?;
+            
+            let add_request = project_docs_store.add(&js_rel)
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Add relationship"))?;
+            
+            JsFuture::from(request_to_promise(add_request)).await
+                .map_err(|e| js_error_to_indexeddb_error(&e, "Add relationship completion"))?;
+        }
+        
+        manager.execute_transaction(transaction).await?;
+        Ok(Ok(()))
+    }
+    
     /// Add a document to a project (manage relationship)
     pub async fn add_document_to_project(&self, project_id: &EntityId, document_id: &EntityId) -> SharedResult<()> {
         let manager = self.manager.lock().await;
@@ -795,60 +1029,8 @@ impl Repository<Project, EntityId> for IndexedDbProjectRepository {
     }
     
     async fn save(&self, entity: &Project) -> SharedResult<Project> {
-        let manager = self.manager.lock().await;
-        let transaction = manager.write_transaction(&[ObjectStore::Projects, ObjectStore::ProjectDocuments])?;
-        let projects_store = manager.object_store(&transaction, ObjectStore::Projects)?;
-        let project_docs_store = manager.object_store(&transaction, ObjectStore::ProjectDocuments)?;
-        
-        // Save project
-        let indexed_proj = IndexedDbProject::from(entity);
-        let js_proj = indexed_proj.to_js_value()
-            .map_err(|e| WritemagicError::internal(&format!("Project serialization failed: {}", e)))?;
-        
-        let save_request = projects_store.put(&js_proj)
-            .map_err(|e| WritemagicError::database(&format!("Save project failed: {:?}", e)))?;
-        
-        JsFuture::from(save_request).await
-            .map_err(|e| WritemagicError::database(&format!("Save project completion failed: {:?}", e)))?;
-        
-        // Clear existing project-document relationships
-        let index = project_docs_store.index("project_id")
-            .map_err(|e| WritemagicError::database(&format!("Getting project_id index failed: {:?}", e)))?;
-        
-        let get_relationships_request = index.get_all_with_key(&JsValue::from_str(&entity.id.to_string()))
-            .map_err(|e| WritemagicError::database(&format!("Get project relationships failed: {:?}", e)))?;
-        
-        let relationships_result = JsFuture::from(get_relationships_request).await
-            .map_err(|e| WritemagicError::database(&format!("Get relationships completion failed: {:?}", e)))?;
-        
-        let relationships_array = Array::from(&relationships_result);
-        for i in 0..relationships_array.length() {
-            let js_rel = relationships_array.get(i);
-            let relationship = IndexedDbProjectDocument::from_js_value(&js_rel)
-                .map_err(|e| WritemagicError::internal(&format!("Relationship deserialization failed: {}", e)))?;
-            
-            let delete_request = project_docs_store.delete(&JsValue::from_str(&relationship.composite_key))
-                .map_err(|e| WritemagicError::database(&format!("Delete old relationship failed: {:?}", e)))?;
-            
-            JsFuture::from(delete_request).await
-                .map_err(|e| WritemagicError::database(&format!("Delete relationship completion failed: {:?}", e)))?;
-        }
-        
-        // Add new project-document relationships
-        for doc_id in &entity.document_ids {
-            let relationship = IndexedDbProjectDocument::new(&entity.id, doc_id);
-            let js_rel = relationship.to_js_value()
-                .map_err(|e| WritemagicError::internal(&format!("Relationship serialization failed: {}", e)))?;
-            
-            let add_request = project_docs_store.add(&js_rel)
-                .map_err(|e| WritemagicError::database(&format!("Add relationship failed: {:?}", e)))?;
-            
-            JsFuture::from(add_request).await
-                .map_err(|e| WritemagicError::database(&format!("Add relationship completion failed: {:?}", e)))?;
-        }
-        
-        manager.execute_transaction(transaction).await
-            .map_err(|e| WritemagicError::database(&format!("Transaction commit failed: {:?}", e)))?;
+        // Without an expected version the write is never refused
+        let _ = retry_after_eviction(|| self.put_project(entity, None), || self.evict_for_quota()).await?;
         
         Ok(entity.clone())
     }
@@ -941,6 +1123,13 @@ impl Repository<Project, EntityId> for IndexedDbProjectRepository {
 
 #[async_trait]
 impl ProjectRepository for IndexedDbProjectRepository {
+    async fn save_if_version(&self, project: &Project, expected_version: u64) -> SharedResult<Project> {
+        retry_after_eviction(|| self.put_project(project, Some(expected_version)), || self.evict_for_quota()).await?
+            .map_err(|actual| project_version_mismatch(project.id, expected_version, actual))?;
+        
+        Ok(project.clone())
+    }
+    
     async fn find_by_creator(&self, user_id: &EntityId, pagination: Pagination) -> SharedResult<Vec<Project>> {
         let manager = self.manager.lock().await;
         let transaction = manager.read_transaction(&[ObjectStore::Projects, ObjectStore::ProjectDocuments])?;
@@ -1170,6 +1359,121 @@ impl ProjectRepository for IndexedDbProjectRepository {
     }
 }
 
+/// IndexedDB implementation of ContextCheckpointStore
+pub struct IndexedDbContextCheckpointStore {
+    manager: std::sync::Arc<tokio::sync::Mutex<IndexedDbManager>>,
+}
+
+impl IndexedDbContextCheckpointStore {
+    pub fn new(manager: std::sync::Arc<tokio::sync::Mutex<IndexedDbManager>>) -> Self {
+        Self { manager }
+    }
+
+    /// Load every stored checkpoint
+    async fn all_checkpoints(&self) -> SharedResult<Vec<ContextCheckpoint>> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.read_transaction(&[ObjectStore::ContextCheckpoints])?;
+        let store = manager.object_store(&transaction, ObjectStore::ContextCheckpoints)?;
+
+        let request = store.get_all()
+            .map_err(|e| WritemagicError::database(&format!("Get all checkpoints failed: {:?}", e)))?;
+
+        let result = JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| WritemagicError::database(&format!("Get all checkpoints completion failed: {:?}", e)))?;
+
+        let array = Array::from(&result);
+        (0..array.length())
+            .map(|i| {
+                serde_wasm_bindgen::from_value(array.get(i))
+                    .map_err(|e| WritemagicError::internal(&format!("Checkpoint deserialization failed: {}", e)))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ContextCheckpointStore for IndexedDbContextCheckpointStore {
+    async fn save(&self, checkpoint: &ContextCheckpoint) -> SharedResult<()> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::ContextCheckpoints])?;
+        let store = manager.object_store(&transaction, ObjectStore::ContextCheckpoints)?;
+
+        let js_checkpoint = serde_wasm_bindgen::to_value(checkpoint)
+            .map_err(|e| WritemagicError::internal(&format!("Checkpoint serialization failed: {}", e)))?;
+
+        let request = store.put(&js_checkpoint)
+            .map_err(|e| WritemagicError::database(&format!("Save checkpoint failed: {:?}", e)))?;
+
+        JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| WritemagicError::database(&format!("Save checkpoint completion failed: {:?}", e)))?;
+
+        manager.execute_transaction(transaction).await
+            .map_err(|e| WritemagicError::database(&format!("Transaction commit failed: {:?}", e)))?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &CheckpointId) -> SharedResult<Option<ContextCheckpoint>> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.read_transaction(&[ObjectStore::ContextCheckpoints])?;
+        let store = manager.object_store(&transaction, ObjectStore::ContextCheckpoints)?;
+
+        let request = store.get(&JsValue::from_str(&id.to_string()))
+            .map_err(|e| WritemagicError::database(&format!("Find checkpoint failed: {:?}", e)))?;
+
+        let result = JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| WritemagicError::database(&format!("Find checkpoint completion failed: {:?}", e)))?;
+
+        if result.is_undefined() || result.is_null() {
+            return Ok(None);
+        }
+
+        serde_wasm_bindgen::from_value(result)
+            .map(Some)
+            .map_err(|e| WritemagicError::internal(&format!("Checkpoint deserialization failed: {}", e)))
+    }
+
+    async fn find_by_session(&self, session_id: &EntityId) -> SharedResult<Vec<ContextCheckpoint>> {
+        let mut checkpoints: Vec<ContextCheckpoint> = self.all_checkpoints().await?
+            .into_iter()
+            .filter(|checkpoint| checkpoint.session_id == *session_id)
+            .collect();
+        checkpoints.sort_by(|a, b| b.created_at.as_datetime().cmp(&a.created_at.as_datetime()));
+        Ok(checkpoints)
+    }
+
+    async fn delete(&self, id: &CheckpointId) -> SharedResult<bool> {
+        if self.find_by_id(id).await?.is_none() {
+            return Ok(false);
+        }
+
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::ContextCheckpoints])?;
+        let store = manager.object_store(&transaction, ObjectStore::ContextCheckpoints)?;
+
+        let request = store.delete(&JsValue::from_str(&id.to_string()))
+            .map_err(|e| WritemagicError::database(&format!("Delete checkpoint failed: {:?}", e)))?;
+
+        JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| WritemagicError::database(&format!("Delete checkpoint completion failed: {:?}", e)))?;
+
+        manager.execute_transaction(transaction).await
+            .map_err(|e| WritemagicError::database(&format!("Transaction commit failed: {:?}", e)))?;
+
+        Ok(true)
+    }
+
+    async fn delete_older_than(&self, session_id: &EntityId, cutoff: &Timestamp) -> SharedResult<u64> {
+        let mut deleted = 0;
+        for checkpoint in self.find_by_session(session_id).await? {
+            if checkpoint.created_at.as_datetime() < cutoff.as_datetime() && self.delete(&checkpoint.id).await? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1196,6 +1500,13 @@ mod tests {
             file_path: None,
             word_count: 8,
             character_count: 42,
+            tags: Vec::new(),
+            language: "en".to_string(),
+            language_override: None,
+            tenant_id: None,
+            is_pinned: false,
+            is_generating: false,
+            is_archived: false,
             created_at: Timestamp::now().to_string(),
             updated_at: Timestamp::now().to_string(),
             created_by: None,
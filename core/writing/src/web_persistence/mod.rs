@@ -8,12 +8,14 @@ pub mod indexeddb_repositories;
 pub mod schema;
 pub mod serialization;
 pub mod migrations;
+pub mod sync_queue;
 
-pub use indexeddb_manager::{IndexedDbManager, IndexedDbConfig, DatabaseInfo};
-pub use indexeddb_repositories::{IndexedDbDocumentRepository, IndexedDbProjectRepository};
+pub use indexeddb_manager::{IndexedDbManager, IndexedDbConfig, DatabaseInfo, QuotaEvictionPolicy};
+pub use indexeddb_repositories::{IndexedDbDocumentRepository, IndexedDbProjectRepository, IndexedDbContextCheckpointStore};
 pub use schema::{WRITEMAGIC_DB_NAME, WRITEMAGIC_DB_VERSION, ObjectStore, Index};
 pub use serialization::{IndexedDbDocument, IndexedDbProject, SerializationError};
 pub use migrations::{MigrationManager, Migration, MigrationError};
+pub use sync_queue::{SyncQueue, SyncEntry, SyncOperation, SyncReport, UploadOutcome};
 
 /// Web-specific error types for IndexedDB operations
 #[derive(Debug, thiserror::Error)]
@@ -41,6 +43,9 @@ pub enum IndexedDbError {
     
     #[error("Data integrity error: {message}")]
     DataIntegrity { message: String },
+    
+    #[error("Storage quota exceeded: {message}")]
+    QuotaExceeded { message: String },
 }
 
 impl From<IndexedDbError> for writemagic_shared::WritemagicError {
@@ -62,6 +67,8 @@ impl From<IndexedDbError> for writemagic_shared::WritemagicError {
                 writemagic_shared::WritemagicError::configuration(&format!("Unsupported feature: {}", feature)),
             IndexedDbError::DataIntegrity { message } => 
                 writemagic_shared::WritemagicError::internal(&message),
+            IndexedDbError::QuotaExceeded { message } => 
+                writemagic_shared::WritemagicError::storage_quota_exceeded(message),
         }
     }
 }
@@ -69,8 +76,25 @@ impl From<IndexedDbError> for writemagic_shared::WritemagicError {
 /// Result type for IndexedDB operations
 pub type Result<T> = std::result::Result<T, IndexedDbError>;
 
+/// Name of the DOMException browsers raise when an origin runs out of storage
+pub const QUOTA_EXCEEDED_ERROR_NAME: &str = "QuotaExceededError";
+
+/// Legacy DOMException code for quota errors, still the only signal in some browsers
+const QUOTA_EXCEEDED_ERROR_CODE: f64 = 22.0;
+
 /// Utility function to convert JavaScript errors to IndexedDbError
 pub fn js_error_to_indexeddb_error(js_value: &wasm_bindgen::JsValue, context: &str) -> IndexedDbError {
+    if is_quota_exceeded_error(js_value) {
+        let message = js_sys::Reflect::get(js_value, &"message".into())
+            .ok()
+            .and_then(|message| message.as_string())
+            .unwrap_or_else(|| QUOTA_EXCEEDED_ERROR_NAME.to_string());
+        
+        return IndexedDbError::QuotaExceeded {
+            message: format!("{}: {}", context, message)
+        };
+    }
+    
     let message = js_value
         .as_string()
         .unwrap_or_else(|| format!("Unknown JavaScript error: {:?}", js_value));
@@ -80,6 +104,50 @@ pub fn js_error_to_indexeddb_error(js_value: &wasm_bindgen::JsValue, context: &s
     }
 }
 
+/// Check whether a JavaScript error reports that storage is full
+pub fn is_quota_exceeded_error(js_value: &wasm_bindgen::JsValue) -> bool {
+    if !js_value.is_object() {
+        return false;
+    }
+    
+    let name = js_sys::Reflect::get(js_value, &"name".into())
+        .ok()
+        .and_then(|name| name.as_string());
+    if name.as_deref() == Some(QUOTA_EXCEEDED_ERROR_NAME) {
+        return true;
+    }
+    
+    js_sys::Reflect::get(js_value, &"code".into())
+        .ok()
+        .and_then(|code| code.as_f64())
+        == Some(QUOTA_EXCEEDED_ERROR_CODE)
+}
+
+/// Run a write, freeing space and retrying once if it fails because storage is full
+///
+/// `evict` returns how many records it removed. When it frees nothing the
+/// original quota error is returned without retrying.
+pub async fn retry_after_eviction<T, W, WF, E, EF>(mut write: W, evict: E) -> Result<T>
+where
+    W: FnMut() -> WF,
+    WF: std::future::Future<Output = Result<T>>,
+    E: FnOnce() -> EF,
+    EF: std::future::Future<Output = Result<usize>>,
+{
+    match write().await {
+        Err(IndexedDbError::QuotaExceeded { message }) => {
+            let evicted = evict().await?;
+            if evicted == 0 {
+                return Err(IndexedDbError::QuotaExceeded { message });
+            }
+            
+            log::info!("Evicted {} records after a storage quota error, retrying write", evicted);
+            write().await
+        }
+        result => result,
+    }
+}
+
 /// Utility function for safe IndexedDB feature detection
 pub fn check_indexeddb_support() -> Result<()> {
     use wasm_bindgen::JsCast;
@@ -108,6 +176,8 @@ pub fn check_indexeddb_support() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
+    use wasm_bindgen_test::*;
     
     #[test]
     fn test_error_conversion() {
@@ -117,4 +187,77 @@ mod tests {
         let writemagic_error: writemagic_shared::WritemagicError = js_error.into();
         assert!(matches!(writemagic_error, writemagic_shared::WritemagicError::DatabaseError(_)));
     }
+    
+    fn quota_error() -> wasm_bindgen::JsValue {
+        let error = js_sys::Error::new("The quota has been exceeded.");
+        error.set_name(QUOTA_EXCEEDED_ERROR_NAME);
+        error.into()
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_quota_error_maps_to_dedicated_variant() {
+        let error = js_error_to_indexeddb_error(&quota_error(), "Save document");
+        assert!(matches!(error, IndexedDbError::QuotaExceeded { .. }));
+        
+        let writemagic_error: writemagic_shared::WritemagicError = error.into();
+        assert!(matches!(writemagic_error, writemagic_shared::WritemagicError::StorageQuotaExceeded { .. }));
+        
+        let other = js_error_to_indexeddb_error(&js_sys::Error::new("Constraint failed").into(), "Save document");
+        assert!(matches!(other, IndexedDbError::JavaScript { .. }));
+    }
+    
+    #[wasm_bindgen_test]
+    async fn test_write_is_retried_after_eviction_frees_space() {
+        let attempts = Cell::new(0);
+        let evictions = Cell::new(0);
+        
+        let result = retry_after_eviction(
+            || {
+                attempts.set(attempts.get() + 1);
+                let quota_full = attempts.get() == 1;
+                async move {
+                    if quota_full {
+                        Err(js_error_to_indexeddb_error(&quota_error(), "Save document"))
+                    } else {
+                        Ok("saved")
+                    }
+                }
+            },
+            || {
+                evictions.set(evictions.get() + 1);
+                async { Ok(3) }
+            },
+        ).await;
+        
+        assert_eq!(result.unwrap(), "saved");
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(evictions.get(), 1);
+    }
+    
+    #[wasm_bindgen_test]
+    async fn test_quota_error_is_returned_when_nothing_is_evicted() {
+        let attempts = Cell::new(0);
+        
+        let result: Result<()> = retry_after_eviction(
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err(js_error_to_indexeddb_error(&quota_error(), "Save document")) }
+            },
+            || async { Ok(0) },
+        ).await;
+        
+        assert!(matches!(result, Err(IndexedDbError::QuotaExceeded { .. })));
+        assert_eq!(attempts.get(), 1);
+    }
+    
+    #[test]
+    fn test_quota_eviction_policy_defaults_when_missing() {
+        let config: IndexedDbConfig = serde_json::from_str(
+            r#"{"database_name":"wm","version":1,"timeout_ms":1000,"enable_logging":false,"auto_migrate":true}"#
+        ).unwrap();
+        assert_eq!(config.quota_eviction, QuotaEvictionPolicy::OldestDeleted { max_documents: 50 });
+        
+        let policy: QuotaEvictionPolicy = serde_json::from_str(r#"{"strategy":"disabled"}"#).unwrap();
+        assert_eq!(policy, QuotaEvictionPolicy::Disabled);
+    }
 }
\ No newline at end of file
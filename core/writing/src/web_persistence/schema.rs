@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 pub const WRITEMAGIC_DB_NAME: &str = "WritemagicDB";
 
 /// Current database version
-pub const WRITEMAGIC_DB_VERSION: u32 = 1;
+pub const WRITEMAGIC_DB_VERSION: u32 = 3;
 
 /// Object store names
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -19,6 +19,8 @@ pub enum ObjectStore {
     ProjectDocuments,
     Settings,
     Metadata,
+    ContextCheckpoints,
+    SyncQueue,
 }
 
 impl ObjectStore {
@@ -29,6 +31,8 @@ impl ObjectStore {
             ObjectStore::ProjectDocuments => "project_documents",
             ObjectStore::Settings => "settings",
             ObjectStore::Metadata => "metadata",
+            ObjectStore::ContextCheckpoints => "context_checkpoints",
+            ObjectStore::SyncQueue => "sync_queue",
         }
     }
     
@@ -39,6 +43,8 @@ impl ObjectStore {
             ObjectStore::ProjectDocuments,
             ObjectStore::Settings,
             ObjectStore::Metadata,
+            ObjectStore::ContextCheckpoints,
+            ObjectStore::SyncQueue,
         ]
     }
 }
@@ -105,6 +111,21 @@ pub fn project_document_indexes() -> Vec<Index> {
     ]
 }
 
+/// Context checkpoint store indexes
+pub fn context_checkpoint_indexes() -> Vec<Index> {
+    vec![
+        Index::new("session_id", "session_id", false),
+        Index::new("created_at", "created_at", false),
+    ]
+}
+
+/// Sync queue store indexes
+pub fn sync_queue_indexes() -> Vec<Index> {
+    vec![
+        Index::new("queued_at", "queued_at", false),
+    ]
+}
+
 /// Database schema configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchemaConfig {
@@ -185,6 +206,18 @@ pub fn get_schema() -> SchemaConfig {
                 auto_increment: false,
                 indexes: vec![],
             },
+            StoreConfig {
+                name: ObjectStore::ContextCheckpoints.as_str().to_string(),
+                key_path: Some("id".to_string()),
+                auto_increment: false,
+                indexes: context_checkpoint_indexes().into_iter().map(IndexConfig::from).collect(),
+            },
+            StoreConfig {
+                name: ObjectStore::SyncQueue.as_str().to_string(),
+                key_path: Some("document_id".to_string()),
+                auto_increment: false,
+                indexes: sync_queue_indexes().into_iter().map(IndexConfig::from).collect(),
+            },
         ],
     }
 }
@@ -255,6 +288,7 @@ mod tests {
         assert_eq!(ObjectStore::Documents.as_str(), "documents");
         assert_eq!(ObjectStore::Projects.as_str(), "projects");
         assert_eq!(ObjectStore::ProjectDocuments.as_str(), "project_documents");
+        assert_eq!(ObjectStore::SyncQueue.as_str(), "sync_queue");
     }
     
     #[test]
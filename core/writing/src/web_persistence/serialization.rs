@@ -5,7 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
-use writemagic_shared::{EntityId, Timestamp, ContentType, ContentHash, FilePath};
+use writemagic_shared::{EntityId, Timestamp, ContentType, ContentHash, DocumentTag, FilePath};
 use crate::entities::{Document, Project};
 
 /// Error type for serialization operations
@@ -38,6 +38,20 @@ pub struct IndexedDbDocument {
     pub file_path: Option<String>,
     pub word_count: u32,
     pub character_count: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[serde(default)]
+    pub language_override: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub is_pinned: bool,
+    #[serde(default)]
+    pub is_generating: bool,
+    #[serde(default)]
+    pub is_archived: bool,
     pub created_at: String,
     pub updated_at: String,
     pub created_by: Option<String>,
@@ -129,6 +143,13 @@ impl From<&Document> for IndexedDbDocument {
             file_path: doc.file_path.as_ref().map(|p| p.to_string()),
             word_count: doc.word_count,
             character_count: doc.character_count,
+            tags: doc.tags.iter().map(|t| t.to_string()).collect(),
+            language: doc.language.clone(),
+            language_override: doc.language_override.clone(),
+            tenant_id: doc.tenant_id.clone(),
+            is_pinned: doc.is_pinned,
+            is_generating: doc.is_generating,
+            is_archived: doc.is_archived,
             created_at: doc.created_at.to_string(),
             updated_at: doc.updated_at.to_string(),
             created_by: doc.created_by.as_ref().map(|id| id.to_string()),
@@ -207,6 +228,15 @@ impl TryFrom<IndexedDbDocument> for Document {
                 message: format!("Invalid timestamp: {}", e),
             })?;
         
+        let tags = doc.tags
+            .into_iter()
+            .map(DocumentTag::new)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| SerializationError::InvalidEntityData {
+                field: "tags".to_string(),
+                message: format!("Invalid tag: {}", e),
+            })?;
+        
         Ok(Document {
             id,
             title: doc.title,
@@ -216,6 +246,13 @@ impl TryFrom<IndexedDbDocument> for Document {
             file_path,
             word_count: doc.word_count,
             character_count: doc.character_count,
+            tags,
+            language: doc.language,
+            language_override: doc.language_override,
+            tenant_id: doc.tenant_id,
+            is_pinned: doc.is_pinned,
+            is_generating: doc.is_generating,
+            is_archived: doc.is_archived,
             created_at,
             updated_at,
             created_by,
@@ -227,6 +264,10 @@ impl TryFrom<IndexedDbDocument> for Document {
     }
 }
 
+fn default_language() -> String {
+    crate::language::DEFAULT_LANGUAGE.to_string()
+}
+
 /// Project structure for IndexedDB storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedDbProject {
@@ -234,6 +275,8 @@ pub struct IndexedDbProject {
     pub name: String,
     pub description: Option<String>,
     pub document_ids: Vec<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
     pub created_at: String,
     pub updated_at: String,
     pub created_by: Option<String>,
@@ -241,6 +284,12 @@ pub struct IndexedDbProject {
     pub version: u64,
     pub is_deleted: bool,
     pub deleted_at: Option<String>,
+    #[serde(default)]
+    pub is_archived: bool,
+    #[serde(default)]
+    pub archived_at: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
     
     // Search fields
     pub search_name: String,
@@ -296,6 +345,7 @@ impl From<&Project> for IndexedDbProject {
             name: proj.name.clone(),
             description: proj.description.clone(),
             document_ids: proj.document_ids.iter().map(|id| id.to_string()).collect(),
+            tenant_id: proj.tenant_id.clone(),
             created_at: proj.created_at.to_string(),
             updated_at: proj.updated_at.to_string(),
             created_by: proj.created_by.as_ref().map(|id| id.to_string()),
@@ -303,6 +353,9 @@ impl From<&Project> for IndexedDbProject {
             version: proj.version,
             is_deleted: proj.is_deleted,
             deleted_at: proj.deleted_at.as_ref().map(|t| t.to_string()),
+            is_archived: proj.is_archived,
+            archived_at: proj.archived_at.as_ref().map(|t| t.to_string()),
+            system_prompt: proj.system_prompt.clone(),
             search_name: proj.name.to_lowercase(),
             search_description,
         }
@@ -365,11 +418,20 @@ impl TryFrom<IndexedDbProject> for Project {
                 message: format!("Invalid timestamp: {}", e),
             })?;
         
+        let archived_at = proj.archived_at
+            .map(|ts| Timestamp::from_string(&ts))
+            .transpose()
+            .map_err(|e| SerializationError::InvalidEntityData {
+                field: "archived_at".to_string(),
+                message: format!("Invalid timestamp: {}", e),
+            })?;
+        
         Ok(Project {
             id,
             name: proj.name,
             description: proj.description,
             document_ids,
+            tenant_id: proj.tenant_id,
             created_at,
             updated_at,
             created_by,
@@ -377,6 +439,9 @@ impl TryFrom<IndexedDbProject> for Project {
             version: proj.version,
             is_deleted: proj.is_deleted,
             deleted_at,
+            is_archived: proj.is_archived,
+            archived_at,
+            system_prompt: proj.system_prompt,
         })
     }
 }
@@ -485,6 +550,7 @@ mod tests {
             file_path: None,
             word_count: 8,
             character_count: 42,
+            tags: Vec::new(),
             created_at: Timestamp::now(),
             updated_at: Timestamp::now(),
             created_by: None,
@@ -0,0 +1,339 @@
+//! Offline sync queue for reconciling IndexedDB data with a server
+//!
+//! Local document mutations are recorded in the `sync_queue` object store,
+//! keyed by document so each document has at most one pending entry. When
+//! connectivity returns, `SyncQueue::drain` replays the entries through an
+//! uploader and resolves conflicts last-write-wins on the document version.
+
+use js_sys::Array;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::IdbObjectStore;
+use writemagic_shared::{EntityId, Timestamp};
+
+use crate::entities::Document;
+use super::indexeddb_manager::IndexedDbManager;
+use super::indexeddb_repositories::request_to_promise;
+use super::schema::ObjectStore;
+use super::{IndexedDbError, Result, js_error_to_indexeddb_error};
+
+/// Kind of local mutation waiting to be uploaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A local document mutation waiting to be uploaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncEntry {
+    pub document_id: String,
+    pub operation: SyncOperation,
+    /// Document version the mutation produced
+    pub version: u64,
+    pub queued_at: String,
+    /// Failed upload attempts so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// Set once the server turned down a newer local version; the uploader must replace the remote copy
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+impl SyncEntry {
+    pub fn new(document_id: &EntityId, operation: SyncOperation, version: u64) -> Self {
+        Self {
+            document_id: document_id.to_string(),
+            operation,
+            version,
+            queued_at: Timestamp::now().to_string(),
+            attempts: 0,
+            overwrite: false,
+        }
+    }
+
+    /// Entry for a mutation that left `document` in its current state
+    pub fn for_document(document: &Document, operation: SyncOperation) -> Self {
+        Self::new(&document.id, operation, document.version)
+    }
+
+    /// Fold this entry into the one already queued for the same document
+    ///
+    /// The higher version wins. A document created and then edited before it
+    /// was ever uploaded still has to be created on the server, and one
+    /// created and then deleted before it was uploaded leaves nothing to sync.
+    pub fn merge(self, queued: Option<SyncEntry>) -> Option<SyncEntry> {
+        match queued {
+            Some(queued) if queued.version > self.version => Some(queued),
+            Some(queued) if queued.operation == SyncOperation::Create => match self.operation {
+                SyncOperation::Delete => None,
+                _ => Some(SyncEntry { operation: SyncOperation::Create, ..self }),
+            },
+            _ => Some(self),
+        }
+    }
+
+    /// Decide what an upload outcome means for this entry
+    fn resolve(&self, outcome: &UploadOutcome) -> Resolution {
+        match outcome {
+            UploadOutcome::Applied => Resolution::Uploaded,
+            UploadOutcome::Conflict { remote_version } if *remote_version >= self.version => Resolution::RemoteWins,
+            UploadOutcome::Conflict { .. } => Resolution::Overwrite,
+        }
+    }
+}
+
+/// Server response to an uploaded entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UploadOutcome {
+    /// The server accepted the mutation
+    Applied,
+    /// The server kept its own copy, which is at `remote_version`
+    Conflict { remote_version: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    Uploaded,
+    /// The remote copy is at least as new, so the local mutation is dropped
+    RemoteWins,
+    /// The local copy is newer and must be sent again with `overwrite` set
+    Overwrite,
+}
+
+/// Summary of one `SyncQueue::drain`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub uploaded: usize,
+    /// Documents whose local mutation lost to a newer server copy; callers should refresh them
+    pub superseded: Vec<String>,
+    /// Entries left queued because their upload failed
+    pub failed: usize,
+}
+
+/// Queue of local mutations persisted in IndexedDB
+#[derive(Clone)]
+pub struct SyncQueue {
+    manager: std::sync::Arc<tokio::sync::Mutex<IndexedDbManager>>,
+}
+
+impl SyncQueue {
+    pub fn new(manager: std::sync::Arc<tokio::sync::Mutex<IndexedDbManager>>) -> Self {
+        Self { manager }
+    }
+
+    /// Record a mutation, merging it with any entry already queued for the document
+    pub async fn record(&self, entry: SyncEntry) -> Result<()> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::SyncQueue])?;
+        let store = manager.object_store(&transaction, ObjectStore::SyncQueue)?;
+
+        let document_id = entry.document_id.clone();
+        let queued = get_entry(&store, &document_id).await?;
+        match entry.merge(queued) {
+            Some(merged) => put_entry(&store, &merged).await?,
+            None => delete_entry(&store, &document_id).await?,
+        }
+
+        manager.execute_transaction(transaction).await
+    }
+
+    /// Queued entries, oldest first
+    pub async fn pending(&self) -> Result<Vec<SyncEntry>> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.read_transaction(&[ObjectStore::SyncQueue])?;
+        let store = manager.object_store(&transaction, ObjectStore::SyncQueue)?;
+
+        let request = store.get_all()
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Get queued mutations"))?;
+        let result = JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Get queued mutations completion"))?;
+
+        let array = Array::from(&result);
+        let mut entries = (0..array.length())
+            .map(|i| entry_from_js(array.get(i)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Stored timestamps are zero-padded UTC strings, so they sort chronologically
+        entries.sort_by(|a, b| a.queued_at.cmp(&b.queued_at));
+        Ok(entries)
+    }
+
+    /// Number of documents with a mutation waiting to be uploaded
+    pub async fn pending_count(&self) -> Result<u32> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.read_transaction(&[ObjectStore::SyncQueue])?;
+        let store = manager.object_store(&transaction, ObjectStore::SyncQueue)?;
+
+        let request = store.count()
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Count queued mutations"))?;
+        let result = JsFuture::from(request_to_promise(request)).await
+            .map_err(|e| js_error_to_indexeddb_error(&e, "Count queued mutations completion"))?;
+
+        Ok(result.as_f64().unwrap_or(0.0) as u32)
+    }
+
+    /// Upload every queued entry, oldest first
+    ///
+    /// Entries are removed once the server applied them or holds a copy at
+    /// least as new. When the server turns down a newer local version the
+    /// entry is sent once more with `overwrite` set. Entries whose upload
+    /// fails stay queued for the next drain. Mutations recorded while
+    /// draining are never discarded.
+    pub async fn drain<F, Fut>(&self, uploader: F) -> Result<SyncReport>
+    where
+        F: Fn(&SyncEntry) -> Fut,
+        Fut: Future<Output = writemagic_shared::Result<UploadOutcome>>,
+    {
+        let mut report = SyncReport::default();
+
+        for mut entry in self.pending().await? {
+            let resolution = loop {
+                match uploader(&entry).await {
+                    Ok(outcome) => match entry.resolve(&outcome) {
+                        Resolution::Overwrite if !entry.overwrite => entry.overwrite = true,
+                        resolution => break Some(resolution),
+                    },
+                    Err(error) => {
+                        log::warn!("Uploading queued mutation of document {} failed: {}", entry.document_id, error);
+                        break None;
+                    }
+                }
+            };
+
+            match resolution {
+                Some(Resolution::Uploaded) => {
+                    self.remove_if_unchanged(&entry).await?;
+                    report.uploaded += 1;
+                }
+                Some(Resolution::RemoteWins) => {
+                    self.remove_if_unchanged(&entry).await?;
+                    report.superseded.push(entry.document_id);
+                }
+                Some(Resolution::Overwrite) | None => {
+                    entry.attempts += 1;
+                    self.replace_if_unchanged(&entry).await?;
+                    report.failed += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Remove an uploaded entry unless a newer mutation replaced it meanwhile
+    async fn remove_if_unchanged(&self, entry: &SyncEntry) -> Result<()> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::SyncQueue])?;
+        let store = manager.object_store(&transaction, ObjectStore::SyncQueue)?;
+
+        if get_entry(&store, &entry.document_id).await?.map(|queued| queued.version) == Some(entry.version) {
+            delete_entry(&store, &entry.document_id).await?;
+        }
+
+        manager.execute_transaction(transaction).await
+    }
+
+    /// Store the retry state of a failed entry unless a newer mutation replaced it meanwhile
+    async fn replace_if_unchanged(&self, entry: &SyncEntry) -> Result<()> {
+        let manager = self.manager.lock().await;
+        let transaction = manager.write_transaction(&[ObjectStore::SyncQueue])?;
+        let store = manager.object_store(&transaction, ObjectStore::SyncQueue)?;
+
+        if get_entry(&store, &entry.document_id).await?.map(|queued| queued.version) == Some(entry.version) {
+            put_entry(&store, entry).await?;
+        }
+
+        manager.execute_transaction(transaction).await
+    }
+}
+
+async fn get_entry(store: &IdbObjectStore, document_id: &str) -> Result<Option<SyncEntry>> {
+    let request = store.get(&JsValue::from_str(document_id))
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Get queued mutation"))?;
+    let result = JsFuture::from(request_to_promise(request)).await
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Get queued mutation completion"))?;
+
+    if result.is_undefined() || result.is_null() {
+        return Ok(None);
+    }
+    entry_from_js(result).map(Some)
+}
+
+async fn put_entry(store: &IdbObjectStore, entry: &SyncEntry) -> Result<()> {
+    let js_entry = serde_wasm_bindgen::to_value(entry)
+        .map_err(|e| IndexedDbError::DataIntegrity { message: format!("Queued mutation serialization failed: {}", e) })?;
+    let request = store.put(&js_entry)
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Queue mutation"))?;
+    JsFuture::from(request_to_promise(request)).await
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Queue mutation completion"))?;
+    Ok(())
+}
+
+async fn delete_entry(store: &IdbObjectStore, document_id: &str) -> Result<()> {
+    let request = store.delete(&JsValue::from_str(document_id))
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Remove queued mutation"))?;
+    JsFuture::from(request_to_promise(request)).await
+        .map_err(|e| js_error_to_indexeddb_error(&e, "Remove queued mutation completion"))?;
+    Ok(())
+}
+
+fn entry_from_js(value: JsValue) -> Result<SyncEntry> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|e| IndexedDbError::DataIntegrity { message: format!("Invalid queued mutation: {}", e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(operation: SyncOperation, version: u64) -> SyncEntry {
+        SyncEntry::new(&EntityId::new(), operation, version)
+    }
+
+    #[test]
+    fn test_merge_keeps_the_newest_version() {
+        let created = entry(SyncOperation::Create, 1);
+        let document_id = created.document_id.clone();
+        let updated = SyncEntry { document_id: document_id.clone(), ..entry(SyncOperation::Update, 3) };
+
+        let merged = updated.clone().merge(Some(created)).unwrap();
+        assert_eq!(merged.operation, SyncOperation::Create);
+        assert_eq!(merged.version, 3);
+
+        let stale = SyncEntry { document_id: document_id.clone(), ..entry(SyncOperation::Update, 2) };
+        assert_eq!(stale.merge(Some(merged.clone())), Some(merged));
+
+        let uploaded = SyncEntry { document_id: document_id.clone(), ..entry(SyncOperation::Update, 3) };
+        let deleted = SyncEntry { document_id, ..entry(SyncOperation::Delete, 4) };
+        assert_eq!(deleted.clone().merge(Some(uploaded)).unwrap().operation, SyncOperation::Delete);
+    }
+
+    #[test]
+    fn test_document_created_and_deleted_offline_leaves_nothing_to_sync() {
+        let created = entry(SyncOperation::Create, 1);
+        let edited = SyncEntry { document_id: created.document_id.clone(), ..entry(SyncOperation::Update, 2) };
+        let merged = edited.merge(Some(created));
+
+        let deleted = SyncEntry { document_id: merged.as_ref().unwrap().document_id.clone(), ..entry(SyncOperation::Delete, 3) };
+        assert_eq!(deleted.merge(merged), None);
+    }
+
+    #[test]
+    fn test_conflicts_resolve_last_write_wins_on_version() {
+        let local = entry(SyncOperation::Update, 5);
+
+        assert_eq!(local.resolve(&UploadOutcome::Applied), Resolution::Uploaded);
+        assert_eq!(local.resolve(&UploadOutcome::Conflict { remote_version: 5 }), Resolution::RemoteWins);
+        assert_eq!(local.resolve(&UploadOutcome::Conflict { remote_version: 7 }), Resolution::RemoteWins);
+        assert_eq!(local.resolve(&UploadOutcome::Conflict { remote_version: 4 }), Resolution::Overwrite);
+
+        let outcome: UploadOutcome = serde_json::from_str(r#"{"status":"conflict","remote_version":9}"#).unwrap();
+        assert_eq!(outcome, UploadOutcome::Conflict { remote_version: 9 });
+    }
+}
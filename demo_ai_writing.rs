@@ -42,7 +42,7 @@ pub async fn demo_basic_ai_assistance() -> Result<()> {
     )?;
     
     let doc_aggregate = doc_service
-        .create_document(title, initial_content, ContentType::Markdown, None)
+        .create_document(title, initial_content, ContentType::Markdown, None, None)
         .await?;
     
     let document_id = doc_aggregate.document().id;
@@ -222,7 +222,7 @@ pub async fn demo_project_workflow() -> Result<()> {
         let doc_content = DocumentContent::new(content)?;
         
         let doc_aggregate = doc_service
-            .create_document(doc_title, doc_content, ContentType::Markdown, None)
+            .create_document(doc_title, doc_content, ContentType::Markdown, None, None)
             .await?;
         
         let doc_id = doc_aggregate.document().id;
@@ -274,7 +274,7 @@ pub async fn demo_advanced_features() -> Result<()> {
     )?;
     
     let doc_aggregate = doc_service
-        .create_document(title, content, ContentType::Markdown, None)
+        .create_document(title, content, ContentType::Markdown, None, None)
         .await?;
     
     let document_id = doc_aggregate.document().id;
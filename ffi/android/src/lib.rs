@@ -1,14 +1,15 @@
 //! Android FFI bindings for WriteMagic core - Thread-safe and performance optimized
 
-use jni::objects::{JClass, JString};
-use jni::sys::{jboolean, jstring};
+use jni::objects::{JClass, JObject, JObjectArray, JString};
+use jni::sys::{jboolean, jbyteArray, jint, jlong, jstring};
 use jni::JNIEnv;
 use std::sync::{Arc, RwLock, OnceLock};
 use std::collections::HashMap;
 use tokio::runtime::Runtime;
-use writemagic_shared::{EntityId, ContentType, Pagination, Result, WritemagicError};
+use writemagic_shared::{DocumentTag, EntityId, ContentType, FFIError, FFIErrorKind, Pagination, RequestContext, Result, ShutdownCoordinator, WritemagicError, EventDispatcher, with_pooled_buffer};
 use writemagic_writing::{
-    CoreEngine, ApplicationConfigBuilder,
+    CoreEngine, ApplicationConfigBuilder, CompletionParams, EventSubscriptions, TimestampFormat, WireTimestamp, DocumentExportFormat,
+    entities::Document,
     value_objects::{DocumentTitle, DocumentContent, ProjectName},
 };
 
@@ -51,18 +52,32 @@ impl<T> FFIResult<T> {
     }
 }
 
+/// `nativeShutdownWithStatus` result: pending writes were flushed and the engine released
+pub const SHUTDOWN_CLEAN: jint = 1;
+/// `nativeShutdownWithStatus` result: the engine was released before pending writes finished
+pub const SHUTDOWN_TIMED_OUT: jint = 2;
+
+/// Longest a shutdown waits for pending writes, short enough to finish
+/// before Android reclaims a backgrounded process
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
 /// Thread-safe instance manager for CoreEngine lifecycle
 pub struct FFIInstanceManager {
     engine: Arc<RwLock<CoreEngine>>,
     runtime: Arc<Runtime>,
-    _instance_id: String,
+    instance_id: String,
+    /// Flushes autosaves and closes the database on shutdown
+    shutdown: tokio::sync::Mutex<ShutdownCoordinator>,
+    event_subscriptions: EventSubscriptions,
 }
 
 impl FFIInstanceManager {
+    /// Build the engine, applying pending migrations first when `run_migrations` is set
     pub async fn new(
         claude_key: Option<String>, 
         openai_key: Option<String>,
-        _instance_id: String,
+        instance_id: String,
+        run_migrations: bool,
     ) -> Result<Self> {
         let runtime = Arc::new(
             Runtime::new()
@@ -76,14 +91,31 @@ impl FFIInstanceManager {
                 .with_openai_key(openai_key.unwrap_or_default())
                 .with_log_level("info".to_string())
                 .with_content_filtering(true)
+                .with_auto_migrate(false)
                 .build()
                 .await
         })?;
+
+        if run_migrations {
+            let applied = runtime.block_on(engine.run_migrations())?;
+            if !applied.is_empty() {
+                let names: Vec<&str> = applied.iter().map(|migration| migration.name.as_str()).collect();
+                log::info!("Applied {} pending migrations: {}", names.len(), names.join(", "));
+            }
+        }
+        
+        let shutdown = ShutdownCoordinator::new();
+        {
+            let _context = runtime.enter();
+            engine.register_shutdown(&shutdown);
+        }
         
         Ok(Self {
+            event_subscriptions: EventSubscriptions::new(engine.event_bus()),
             engine: Arc::new(RwLock::new(engine)),
             runtime,
-            _instance_id,
+            instance_id,
+            shutdown: tokio::sync::Mutex::new(shutdown),
         })
     }
     
@@ -94,6 +126,33 @@ impl FFIInstanceManager {
     pub fn runtime(&self) -> &Arc<Runtime> {
         &self.runtime
     }
+
+    /// Run `future` on the runtime as one request with a fresh correlation id
+    pub fn block_on_request<F: std::future::Future>(&self, operation: &str, future: F) -> F::Output {
+        self.runtime.block_on(RequestContext::generate().scope(operation, future))
+    }
+    
+    /// Flush pending writes and end event subscriptions, returning `false` if
+    /// the writes did not finish within `timeout`
+    pub fn shutdown(&self, timeout: std::time::Duration) -> bool {
+        let flushed = self.runtime.block_on(async {
+            self.shutdown.lock().await.shutdown(timeout).await
+        });
+        self.runtime.block_on(self.event_subscriptions.unsubscribe_all());
+        flushed
+    }
+
+    /// Send document and project events to `dispatcher` until unsubscribed, returning the subscription handle
+    pub fn subscribe_events(&self, dispatcher: EventDispatcher) -> u64 {
+        self.runtime.block_on(self.event_subscriptions.subscribe(dispatcher))
+    }
+
+    /// End a subscription, returning whether it was active
+    ///
+    /// Once this returns its dispatcher delivers nothing more.
+    pub fn unsubscribe_events(&self, handle: u64) -> bool {
+        self.runtime.block_on(self.event_subscriptions.unsubscribe(handle))
+    }
 }
 
 /// Thread-safe global instance registry
@@ -164,6 +223,26 @@ fn create_jni_string(env: &mut JNIEnv, value: String) -> jstring {
     }
 }
 
+/// Serialize `value` into a Java string, building the JSON in a pooled buffer
+///
+/// The pooled buffer is reused across calls from any Java thread, so rapid
+/// calls such as `nativeListDocuments` don't churn the allocator.
+fn serialize_to_jni_string<T: serde::Serialize + ?Sized>(env: &mut JNIEnv, value: &T) -> FFIResult<jstring> {
+    with_pooled_buffer(|buffer| {
+        if let Err(e) = serde_json::to_writer(&mut *buffer, value) {
+            return FFIResult::error(FFIErrorCode::SerializationError, format!("JSON serialization failed: {}", e));
+        }
+        let json = match std::str::from_utf8(buffer) {
+            Ok(json) => json,
+            Err(e) => return FFIResult::error(FFIErrorCode::SerializationError, format!("JSON is not UTF-8: {}", e)),
+        };
+        match env.new_string(json) {
+            Ok(jstr) => FFIResult::success(jstr.into_raw()),
+            Err(e) => FFIResult::error(FFIErrorCode::MemoryError, format!("Failed to create JNI string: {}", e)),
+        }
+    })
+}
+
 /// Initialize logging (called once)
 fn init_logging() {
     use std::sync::Once;
@@ -185,10 +264,24 @@ fn init_logging() {
 /// Initialize the WriteMagic core engine with enhanced error handling and lifecycle management
 #[no_mangle]
 pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeInitialize(
+    env: JNIEnv,
+    class: JClass,
+    claude_key: JString,
+    openai_key: JString,
+) -> jboolean {
+    Java_com_writemagic_core_WriteMagicCore_nativeInitializeWithOptions(env, class, claude_key, openai_key, true as jboolean)
+}
+
+/// Initialize the WriteMagic core engine, applying pending database migrations
+/// first when `run_migrations` is true so an upgraded app brings an older
+/// database up to date. A failed migration fails initialization.
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeInitializeWithOptions(
     mut env: JNIEnv,
     _class: JClass,
     claude_key: JString,
     openai_key: JString,
+    run_migrations: jboolean,
 ) -> jboolean {
     init_logging();
     log::info!("Initializing WriteMagic core for Android with enhanced FFI safety");
@@ -239,6 +332,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeInitialize(
                             claude_api_key,
                             openai_api_key,
                             "default".to_string(),
+                            run_migrations != 0,
                         ).await
                     });
                     
@@ -267,7 +361,163 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeInitialize(
     }
 }
 
+/// Engine instance for a structured-error JNI call
+fn default_instance() -> std::result::Result<Arc<FFIInstanceManager>, FFIError> {
+    get_default_instance().into_ffi_result()
+}
+
+/// Read a required Java string argument for a structured-error JNI call
+fn string_arg(env: &mut JNIEnv, jstr: &JString) -> std::result::Result<String, FFIError> {
+    if jstr.is_null() {
+        return Err(FFIError::NullPointer);
+    }
+    java_string_to_rust(env, jstr).into_ffi_result()
+}
+
+/// Read a nullable Java string argument for a structured-error JNI call
+fn optional_string_arg(env: &mut JNIEnv, jstr: &JString) -> std::result::Result<Option<String>, FFIError> {
+    if jstr.is_null() {
+        return Ok(None);
+    }
+    string_arg(env, jstr).map(Some)
+}
+
+/// Parse a document ID, reporting a malformed one as `InvalidId`
+fn parse_document_id(document_id: &str) -> std::result::Result<EntityId, FFIError> {
+    uuid::Uuid::parse_str(document_id).map(EntityId::from_uuid).map_err(|e| {
+        FFIError::detailed(
+            FFIErrorKind::InvalidId,
+            format!("Invalid document ID format: {}", e),
+            serde_json::json!({ "document_id": document_id }),
+        )
+    })
+}
+
+/// Build document content, reporting over-long content as `InvalidInput` with its lengths
+fn parse_document_content(content: &str) -> std::result::Result<DocumentContent, FFIError> {
+    DocumentContent::try_new(content).map_err(|e| {
+        FFIError::detailed(
+            FFIErrorKind::InvalidInput,
+            e.to_string(),
+            serde_json::json!({ "field": "content", "actual_length": e.actual, "max_length": e.allowed }),
+        )
+    })
+}
+
+/// Store a failed call's error JSON in the first slot of Java's `errorOut` array, if one was passed
+fn write_error<T>(env: &mut JNIEnv, error_out: &JObjectArray, result: &std::result::Result<T, FFIError>) {
+    let Err(error) = result else {
+        return;
+    };
+    if error_out.is_null() {
+        return;
+    }
+    let stored = env
+        .new_string(error.to_json().to_string())
+        .and_then(|json| env.set_object_array_element(error_out, 0, &json));
+    if let Err(e) = stored {
+        log::error!("Failed to report error to Java: {}", e);
+    }
+}
+
+impl<T> FFIResult<T> {
+    /// The value, or the error as a structured `FFIError`
+    fn into_ffi_result(self) -> std::result::Result<T, FFIError> {
+        match self {
+            FFIResult { value: Some(value), .. } => Ok(value),
+            FFIResult { error_code, error_message, .. } => {
+                let message = error_message.unwrap_or_else(|| format!("{:?}", error_code));
+                Err(match error_code {
+                    FFIErrorCode::NotInitialized => FFIError::detailed(FFIErrorKind::NotInitialized, message, serde_json::Value::Null),
+                    FFIErrorCode::InvalidInput => FFIError::InvalidInput(message),
+                    _ => FFIError::InternalError(message),
+                })
+            }
+        }
+    }
+}
+
+fn create_document(
+    env: &mut JNIEnv,
+    title: &JString,
+    content: &JString,
+    content_type: &JString,
+    idempotency_key: &JString,
+) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let title_str = string_arg(env, title)?;
+    let content_str = string_arg(env, content)?;
+    let content_type_str = string_arg(env, content_type)?;
+    let idempotency_key = optional_string_arg(env, idempotency_key)?;
+
+    manager.block_on_request("ffi.create_document", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_title = DocumentTitle::new(&title_str)?;
+        let document_content = parse_document_content(&content_str)?;
+        let content_type = match content_type_str.as_str() {
+            "markdown" => ContentType::Markdown,
+            "plain_text" => ContentType::PlainText,
+            "html" => ContentType::Html,
+            _ => ContentType::PlainText,
+        };
+
+        let aggregate = engine_guard.document_management_service().create_document(
+            document_title,
+            document_content,
+            content_type,
+            None, // created_by - set from authentication context
+            idempotency_key,
+        ).await?;
+
+        let document = aggregate.document();
+        let response_data = serde_json::json!({
+            "id": document.id.to_string(),
+            "title": document.title,
+            "content": document.content,
+            "contentType": document.content_type.to_string(),
+            "wordCount": document.word_count,
+            "characterCount": document.character_count,
+            "language": document.effective_language(),
+            "tags": document.tags.iter().map(DocumentTag::as_str).collect::<Vec<_>>(),
+            "createdAt": engine_guard.config().timestamp_format.encode(&document.created_at),
+            "updatedAt": engine_guard.config().timestamp_format.encode(&document.updated_at),
+            "version": document.version
+        });
+        Ok(response_data.to_string())
+    })
+}
+
+/// Create a new document; on failure returns null and puts the error JSON in `errorOut[0]`
+///
+/// A retry with the same non-null `idempotencyKey` returns the document first
+/// created under it while the key lives.
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocumentWithError(
+    mut env: JNIEnv,
+    _class: JClass,
+    title: JString,
+    content: JString,
+    content_type: JString,
+    idempotency_key: JString,
+    error_out: JObjectArray,
+) -> jstring {
+    init_logging();
+
+    let result = create_document(&mut env, &title, &content, &content_type, &idempotency_key);
+    write_error(&mut env, &error_out, &result);
+    match result {
+        Ok(json) => create_jni_string(&mut env, json),
+        Err(e) => {
+            log::error!("Document creation failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
 /// Create a new document with enhanced error handling and performance optimization
+#[deprecated(note = "use nativeCreateDocumentWithError to learn why creation failed")]
 #[no_mangle]
 pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocument(
     mut env: JNIEnv,
@@ -277,8 +527,205 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocum
     content_type: JString,
 ) -> jstring {
     init_logging();
+
+    match create_document(&mut env, &title, &content, &content_type, &JString::from(JObject::null())) {
+        Ok(json) => create_jni_string(&mut env, json),
+        Err(e) => {
+            log::error!("Document creation failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn update_document_content(
+    env: &mut JNIEnv,
+    document_id: &JString,
+    content: &JString,
+) -> std::result::Result<(), FFIError> {
+    let manager = default_instance()?;
+    let document_id_str = string_arg(env, document_id)?;
+    let content_str = string_arg(env, content)?;
+
+    manager.block_on_request("ffi.update_document_content", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_id = parse_document_id(&document_id_str)?;
+        let document_content = parse_document_content(&content_str)?;
+
+        engine_guard.document_management_service().update_document_content(
+            document_id,
+            document_content,
+            None, // text selection
+            None, // updated_by - set from authentication context
+        ).await?;
+
+        log::info!("Successfully updated document {}", document_id_str);
+        Ok(())
+    })
+}
+
+/// Update document content; on failure returns false and puts the error JSON in `errorOut[0]`
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeUpdateDocumentContentWithError(
+    mut env: JNIEnv,
+    _class: JClass,
+    document_id: JString,
+    content: JString,
+    error_out: JObjectArray,
+) -> jboolean {
+    init_logging();
+
+    let result = update_document_content(&mut env, &document_id, &content);
+    write_error(&mut env, &error_out, &result);
+    if let Err(e) = &result {
+        log::error!("Failed to update document content: {}", e.message());
+    }
+    result.is_ok() as jboolean
+}
+
+/// Update document content with optimized performance and error handling
+#[deprecated(note = "use nativeUpdateDocumentContentWithError to learn why the update failed")]
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeUpdateDocumentContent(
+    mut env: JNIEnv,
+    _class: JClass,
+    document_id: JString,
+    content: JString,
+) -> jboolean {
+    init_logging();
+
+    let result = update_document_content(&mut env, &document_id, &content);
+    if let Err(e) = &result {
+        log::error!("Failed to update document content: {}", e.message());
+    }
+    result.is_ok() as jboolean
+}
+
+/// Standard JSON representation of a document returned to Java
+fn document_json(document: &Document, timestamp_format: &TimestampFormat) -> serde_json::Value {
+    serde_json::json!({
+        "id": document.id.to_string(),
+        "title": document.title,
+        "content": document.content,
+        "contentType": document.content_type.to_string(),
+        "wordCount": document.word_count,
+        "characterCount": document.character_count,
+        "language": document.effective_language(),
+        "tags": document.tags.iter().map(DocumentTag::as_str).collect::<Vec<_>>(),
+        "createdAt": timestamp_format.encode(&document.created_at),
+        "updatedAt": timestamp_format.encode(&document.updated_at),
+        "version": document.version,
+        "isDeleted": document.is_deleted
+    })
+}
+
+/// Document listing entry, without the content
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentSummary<'a> {
+    id: EntityId,
+    title: &'a str,
+    content_type: String,
+    word_count: u32,
+    character_count: u32,
+    language: &'a str,
+    tags: Vec<&'a str>,
+    created_at: WireTimestamp,
+    updated_at: WireTimestamp,
+    version: u64,
+    is_deleted: bool,
+}
+
+impl<'a> DocumentSummary<'a> {
+    fn new(document: &'a Document, timestamp_format: TimestampFormat) -> Self {
+        Self {
+            id: document.id,
+            title: &document.title,
+            content_type: document.content_type.to_string(),
+            word_count: document.word_count,
+            character_count: document.character_count,
+            language: document.effective_language(),
+            tags: document.tags.iter().map(DocumentTag::as_str).collect(),
+            created_at: timestamp_format.encode(&document.created_at),
+            updated_at: timestamp_format.encode(&document.updated_at),
+            version: document.version,
+            is_deleted: document.is_deleted,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DocumentList<'a> {
+    documents: Vec<DocumentSummary<'a>>,
+    count: usize,
+}
+
+fn get_document(env: &mut JNIEnv, document_id: &JString) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let document_id_str = string_arg(env, document_id)?;
+
+    manager.block_on_request("ffi.get_document", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_id = parse_document_id(&document_id_str)?;
+        let document = engine_guard.document_repository().find_by_id(&document_id).await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Document {}", document_id_str)))?;
+
+        Ok(document_json(&document, &engine_guard.config().timestamp_format).to_string())
+    })
+}
+
+/// Get document by ID; on failure returns null and puts the error JSON in `errorOut[0]`
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetDocumentWithError(
+    mut env: JNIEnv,
+    _class: JClass,
+    document_id: JString,
+    error_out: JObjectArray,
+) -> jstring {
+    init_logging();
+
+    let result = get_document(&mut env, &document_id);
+    write_error(&mut env, &error_out, &result);
+    match result {
+        Ok(json) => create_jni_string(&mut env, json),
+        Err(e) => {
+            log::error!("Get document failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Get document by ID with enhanced performance and error handling
+#[deprecated(note = "use nativeGetDocumentWithError to tell a missing document from other failures")]
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetDocument(
+    mut env: JNIEnv,
+    _class: JClass,
+    document_id: JString,
+) -> jstring {
+    init_logging();
+
+    match get_document(&mut env, &document_id) {
+        Ok(json) => create_jni_string(&mut env, json),
+        Err(e) => {
+            log::error!("Get document failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Restore a soft-deleted document, returning its JSON
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeRestoreDocument(
+    mut env: JNIEnv,
+    _class: JClass,
+    document_id: JString,
+) -> jstring {
+    init_logging();
     
-    // Get instance manager with proper error handling
     let manager = match get_default_instance() {
         FFIResult { value: Some(mgr), .. } => mgr,
         FFIResult { error_message, .. } => {
@@ -287,34 +734,15 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocum
         }
     };
     
-    // Extract parameters with enhanced error handling
-    let title_str = match java_string_to_rust(&mut env, &title) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract title: {:?}", error_message);
-            return std::ptr::null_mut();
-        }
-    };
-    
-    let content_str = match java_string_to_rust(&mut env, &content) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content: {:?}", error_message);
-            return std::ptr::null_mut();
-        }
-    };
-    
-    let content_type_str = match java_string_to_rust(&mut env, &content_type) {
+    let document_id_str = match java_string_to_rust(&mut env, &document_id) {
         FFIResult { value: Some(s), .. } => s,
         FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content_type: {:?}", error_message);
+            log::error!("Failed to extract document_id: {:?}", error_message);
             return std::ptr::null_mut();
         }
     };
     
-    // Use shared runtime instead of spawning new thread
-    let result = manager.runtime().block_on(async {
-        // Get read lock on engine
+    let result = manager.block_on_request("ffi.restore_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -325,60 +753,31 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocum
             }
         };
         
-        // Create value objects with validation
-        let document_title = match DocumentTitle::new(&title_str) {
-            Ok(title) => title,
-            Err(e) => {
-                return FFIResult::error(
-                    FFIErrorCode::InvalidInput,
-                    format!("Invalid document title: {}", e)
-                );
-            }
-        };
-        
-        let document_content = match DocumentContent::new(&content_str) {
-            Ok(content) => content,
+        let document_id = match uuid::Uuid::parse_str(&document_id_str) {
+            Ok(uuid) => EntityId::from_uuid(uuid),
             Err(e) => {
                 return FFIResult::error(
                     FFIErrorCode::InvalidInput,
-                    format!("Invalid document content: {}", e)
+                    format!("Invalid document ID format: {}", e)
                 );
             }
         };
         
-        let content_type = match content_type_str.as_str() {
-            "markdown" => ContentType::Markdown,
-            "plain_text" => ContentType::PlainText,
-            "html" => ContentType::Html,
-            _ => ContentType::PlainText,
-        };
-        
-        // Create document through service layer
-        match engine_guard.document_management_service().create_document(
-            document_title,
-            document_content,
-            content_type,
-            None, // created_by - set from authentication context
+        match engine_guard.document_management_service().restore_document(
+            document_id,
+            None, // restored_by - set from authentication context
         ).await {
             Ok(aggregate) => {
-                let document = aggregate.document();
-                let response_data = serde_json::json!({
-                    "id": document.id.to_string(),
-                    "title": document.title,
-                    "content": document.content,
-                    "contentType": document.content_type.to_string(),
-                    "wordCount": document.word_count,
-                    "characterCount": document.character_count,
-                    "createdAt": document.created_at.to_string(),
-                    "updatedAt": document.updated_at.to_string(),
-                    "version": document.version
-                });
-                
+                let response_data = document_json(aggregate.document(), &engine_guard.config().timestamp_format);
                 FFIResult::success(response_data.to_string())
             }
+            Err(e @ WritemagicError::Validation { .. }) => FFIResult::error(
+                FFIErrorCode::InvalidInput,
+                format!("Failed to restore document: {}", e)
+            ),
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
-                format!("Failed to create document: {}", e)
+                format!("Failed to restore document: {}", e)
             )
         }
     });
@@ -386,102 +785,94 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateDocum
     match result {
         FFIResult { value: Some(json), .. } => create_jni_string(&mut env, json),
         FFIResult { error_message, .. } => {
-            log::error!("Document creation failed: {:?}", error_message);
+            log::error!("Restore document failed: {:?}", error_message);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Update document content with optimized performance and error handling
+/// Analyze a document's text, returning counts, reading time and grade level as JSON
 #[no_mangle]
-pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeUpdateDocumentContent(
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeAnalyzeDocument(
     mut env: JNIEnv,
     _class: JClass,
     document_id: JString,
-    content: JString,
-) -> jboolean {
+) -> jstring {
     init_logging();
     
-    // Get instance manager
     let manager = match get_default_instance() {
         FFIResult { value: Some(mgr), .. } => mgr,
         FFIResult { error_message, .. } => {
             log::error!("Failed to get CoreEngine instance: {:?}", error_message);
-            return false as jboolean;
+            return std::ptr::null_mut();
         }
     };
     
-    // Extract parameters
     let document_id_str = match java_string_to_rust(&mut env, &document_id) {
         FFIResult { value: Some(s), .. } => s,
         FFIResult { error_message, .. } => {
             log::error!("Failed to extract document_id: {:?}", error_message);
-            return false as jboolean;
-        }
-    };
-    
-    let content_str = match java_string_to_rust(&mut env, &content) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content: {:?}", error_message);
-            return false as jboolean;
+            return std::ptr::null_mut();
         }
     };
     
-    // Use shared runtime for async operation
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.analyze_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
-                log::error!("Failed to acquire engine read lock: {}", e);
-                return false;
+                return FFIResult::error(
+                    FFIErrorCode::ThreadingError,
+                    format!("Failed to acquire engine read lock: {}", e)
+                );
             }
         };
         
-        // Parse document ID
         let document_id = match uuid::Uuid::parse_str(&document_id_str) {
             Ok(uuid) => EntityId::from_uuid(uuid),
             Err(e) => {
-                log::error!("Invalid document ID format: {}", e);
-                return false;
-            }
-        };
-        
-        let document_content = match DocumentContent::new(&content_str) {
-            Ok(content) => content,
-            Err(e) => {
-                log::error!("Invalid document content: {}", e);
-                return false;
+                return FFIResult::error(
+                    FFIErrorCode::InvalidInput,
+                    format!("Invalid document ID format: {}", e)
+                );
             }
         };
         
-        match engine_guard.document_management_service().update_document_content(
-            document_id,
-            document_content,
-            None, // text selection
-            None, // updated_by - set from authentication context
-        ).await {
-            Ok(_) => {
-                log::info!("Successfully updated document {}", document_id_str);
-                true
-            }
-            Err(e) => {
-                log::error!("Failed to update document content: {}", e);
-                false
-            }
+        match engine_guard.analyze_document(document_id).await {
+            Ok(statistics) => match serde_json::to_string(&statistics) {
+                Ok(json) => FFIResult::success(json),
+                Err(e) => FFIResult::error(
+                    FFIErrorCode::SerializationError,
+                    format!("Failed to serialize document statistics: {}", e)
+                ),
+            },
+            Err(e @ (WritemagicError::NotFound { .. } | WritemagicError::Validation { .. })) => FFIResult::error(
+                FFIErrorCode::InvalidInput,
+                format!("Failed to analyze document: {}", e)
+            ),
+            Err(e) => FFIResult::error(
+                FFIErrorCode::EngineError,
+                format!("Failed to analyze document: {}", e)
+            )
         }
     });
     
-    result as jboolean
+    match result {
+        FFIResult { value: Some(json), .. } => create_jni_string(&mut env, json),
+        FFIResult { error_message, .. } => {
+            log::error!("Analyze document failed: {:?}", error_message);
+            std::ptr::null_mut()
+        }
+    }
 }
 
-/// Get document by ID with enhanced performance and error handling
+/// Export a document as "markdown", "html", "plain_text" or "pdf", returning the bytes
 #[no_mangle]
-pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetDocument(
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeExportDocument(
     mut env: JNIEnv,
     _class: JClass,
     document_id: JString,
-) -> jstring {
+    format: JString,
+) -> jbyteArray {
     init_logging();
     
     let manager = match get_default_instance() {
@@ -500,7 +891,15 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetDocument
         }
     };
     
-    let result = manager.runtime().block_on(async {
+    let format_str = match java_string_to_rust(&mut env, &format) {
+        FFIResult { value: Some(s), .. } => s,
+        FFIResult { error_message, .. } => {
+            log::error!("Failed to extract format: {:?}", error_message);
+            return std::ptr::null_mut();
+        }
+    };
+    
+    let result = manager.block_on_request("ffi.export_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -521,38 +920,34 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetDocument
             }
         };
         
-        match engine_guard.document_repository().find_by_id(&document_id).await {
-            Ok(Some(document)) => {
-                let response_data = serde_json::json!({
-                    "id": document.id.to_string(),
-                    "title": document.title,
-                    "content": document.content,
-                    "contentType": document.content_type.to_string(),
-                    "wordCount": document.word_count,
-                    "characterCount": document.character_count,
-                    "createdAt": document.created_at.to_string(),
-                    "updatedAt": document.updated_at.to_string(),
-                    "version": document.version,
-                    "isDeleted": document.is_deleted
-                });
-                
-                FFIResult::success(response_data.to_string())
-            }
-            Ok(None) => FFIResult::error(
+        let format = match DocumentExportFormat::from_string(&format_str) {
+            Ok(format) => format,
+            Err(e) => return FFIResult::error(FFIErrorCode::InvalidInput, e.to_string()),
+        };
+        
+        match engine_guard.export_document(document_id, format).await {
+            Ok(bytes) => FFIResult::success(bytes),
+            Err(e @ WritemagicError::NotFound { .. }) => FFIResult::error(
                 FFIErrorCode::InvalidInput,
-                format!("Document {} not found", document_id_str)
+                format!("Failed to export document: {}", e)
             ),
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
-                format!("Failed to retrieve document: {}", e)
+                format!("Failed to export document: {}", e)
             )
         }
     });
     
     match result {
-        FFIResult { value: Some(json), .. } => create_jni_string(&mut env, json),
+        FFIResult { value: Some(bytes), .. } => match env.byte_array_from_slice(&bytes) {
+            Ok(array) => array.into_raw(),
+            Err(e) => {
+                log::error!("Failed to create JNI byte array: {}", e);
+                std::ptr::null_mut()
+            }
+        },
         FFIResult { error_message, .. } => {
-            log::error!("Get document failed: {:?}", error_message);
+            log::error!("Export document failed: {:?}", error_message);
             std::ptr::null_mut()
         }
     }
@@ -592,7 +987,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateProje
         }
     };
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.create_project", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -631,8 +1026,8 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCreateProje
                     "name": project.name,
                     "description": project.description,
                     "documentIds": project.document_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
-                    "createdAt": project.created_at.to_string(),
-                    "updatedAt": project.updated_at.to_string(),
+                    "createdAt": engine_guard.config().timestamp_format.encode(&project.created_at),
+                    "updatedAt": engine_guard.config().timestamp_format.encode(&project.updated_at),
                     "version": project.version
                 });
                 
@@ -679,7 +1074,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetProject(
         }
     };
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.get_project", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -707,8 +1102,8 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeGetProject(
                     "name": project.name,
                     "description": project.description,
                     "documentIds": project.document_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
-                    "createdAt": project.created_at.to_string(),
-                    "updatedAt": project.updated_at.to_string(),
+                    "createdAt": engine_guard.config().timestamp_format.encode(&project.created_at),
+                    "updatedAt": engine_guard.config().timestamp_format.encode(&project.updated_at),
                     "version": project.version,
                     "isDeleted": project.is_deleted
                 });
@@ -761,7 +1156,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeListDocumen
         }
     };
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.list_documents", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -772,29 +1167,10 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeListDocumen
             }
         };
         
-        match engine_guard.document_repository().find_all(pagination).await {
-            Ok(documents) => {
-                let documents_json: Vec<serde_json::Value> = documents
-                    .iter()
-                    .map(|doc| serde_json::json!({
-                        "id": doc.id.to_string(),
-                        "title": doc.title,
-                        "contentType": doc.content_type.to_string(),
-                        "wordCount": doc.word_count,
-                        "characterCount": doc.character_count,
-                        "createdAt": doc.created_at.to_string(),
-                        "updatedAt": doc.updated_at.to_string(),
-                        "version": doc.version,
-                        "isDeleted": doc.is_deleted
-                    }))
-                    .collect();
-                
-                let response_data = serde_json::json!({
-                    "documents": documents_json,
-                    "count": documents.len()
-                });
-                
-                FFIResult::success(response_data.to_string())
+        match engine_guard.document_management_service().list_documents(pagination).await {
+            Ok(aggregates) => {
+                let documents: Vec<Document> = aggregates.iter().map(|aggregate| aggregate.document().clone()).collect();
+                FFIResult::success((documents, engine_guard.config().timestamp_format))
             }
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
@@ -803,8 +1179,20 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeListDocumen
         }
     });
     
+    let result = match result {
+        FFIResult { value: Some((documents, timestamp_format)), .. } => serialize_to_jni_string(&mut env, &DocumentList {
+            documents: documents.iter().map(|doc| DocumentSummary::new(doc, timestamp_format)).collect(),
+            count: documents.len(),
+        }),
+        FFIResult { error_code, error_message, .. } => FFIResult {
+            value: None,
+            error_code,
+            error_message,
+        },
+    };
+    
     match result {
-        FFIResult { value: Some(json), .. } => create_jni_string(&mut env, json),
+        FFIResult { value: Some(jstr), .. } => jstr,
         FFIResult { error_message, .. } => {
             log::error!("List documents failed: {:?}", error_message);
             std::ptr::null_mut()
@@ -845,7 +1233,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCompleteTex
     
     log::info!("Completing text with model {:?} and prompt: {}", model_str, prompt_str);
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.complete_text", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -867,6 +1255,7 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCompleteTex
             Err(e) => {
                 let error_response = serde_json::json!({
                     "error": e.to_string(),
+                    "provider_error": e.provider_error(),
                     "success": false
                 });
                 // Return structured error instead of failing
@@ -884,26 +1273,211 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCompleteTex
     }
 }
 
-/// Cleanup and shutdown - proper resource management
+fn complete_text_ex(
+    env: &mut JNIEnv,
+    prompt: &JString,
+    model: &JString,
+    params_json: &JString,
+) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let prompt_str = string_arg(env, prompt)?;
+    let model_str = if model.is_null() {
+        None
+    } else {
+        Some(string_arg(env, model)?).filter(|m| !m.trim().is_empty())
+    };
+    let params: CompletionParams = if params_json.is_null() {
+        CompletionParams::default()
+    } else {
+        let json = string_arg(env, params_json)?;
+        if json.trim().is_empty() {
+            CompletionParams::default()
+        } else {
+            serde_json::from_str(&json)
+                .map_err(|e| FFIError::InvalidInput(format!("Invalid completion params: {}", e)))?
+        }
+    };
+    log::info!("Completing text with {:?} and {:?}", model_str, params);
+
+    manager.block_on_request("ffi.complete_text", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let completion = engine_guard.complete_text_with_params(prompt_str, model_str, params).await?;
+        Ok(serde_json::json!({
+            "completion": completion,
+            "success": true
+        }).to_string())
+    })
+}
+
+/// Complete text using AI with sampling parameters given as JSON
+///
+/// `paramsJson` may hold `max_tokens`, `temperature`, `top_p` and `stop_sequences`;
+/// null or omitted fields keep the defaults. On failure, including out-of-range
+/// values, returns null and puts the error JSON in `errorOut[0]`.
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeCompleteTextEx(
+    mut env: JNIEnv,
+    _class: JClass,
+    prompt: JString,
+    model: JString,
+    params_json: JString,
+    error_out: JObjectArray,
+) -> jstring {
+    init_logging();
+
+    let result = complete_text_ex(&mut env, &prompt, &model, &params_json);
+    write_error(&mut env, &error_out, &result);
+    match result {
+        Ok(json) => create_jni_string(&mut env, json),
+        Err(e) => {
+            log::error!("AI completion failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn subscribe_events(env: &mut JNIEnv, listener: &JObject) -> std::result::Result<u64, FFIError> {
+    let manager = default_instance()?;
+    if listener.is_null() {
+        return Err(FFIError::NullPointer);
+    }
+    let vm = env.get_java_vm()
+        .map_err(|e| FFIError::InternalError(format!("Failed to get the Java VM: {}", e)))?;
+    let listener = env.new_global_ref(listener)
+        .map_err(|e| FFIError::InternalError(format!("Failed to keep the event listener: {}", e)))?;
+
+    let dispatcher = EventDispatcher::spawn("writemagic-events", move || {
+        move |json: &str| {
+            // Attaches on the first event; the thread detaches when it exits
+            let mut env = match vm.attach_current_thread_permanently() {
+                Ok(env) => env,
+                Err(e) => {
+                    log::error!("Failed to attach the event thread to the Java VM: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = deliver_event(&mut env, listener.as_obj(), json) {
+                log::error!("Failed to deliver event to the listener: {}", e);
+            }
+        }
+    })?;
+    Ok(manager.subscribe_events(dispatcher))
+}
+
+/// Call `listener.onEvent(json)`, clearing anything it throws
+fn deliver_event(env: &mut JNIEnv, listener: &JObject, json: &str) -> jni::errors::Result<()> {
+    let json = env.new_string(json)?;
+    let result = env.call_method(listener, "onEvent", "(Ljava/lang/String;)V", &[(&json).into()]);
+    if env.exception_check()? {
+        // A throwing listener must not leave the exception pending on the event thread
+        env.exception_describe()?;
+        env.exception_clear()?;
+    }
+    // The thread stays attached, so local references are never freed for us
+    env.delete_local_ref(json)?;
+    result.map(|_| ())
+}
+
+/// Call `listener.onEvent(String eventJson)` with each document event until unsubscribed
+///
+/// Events arrive in order on a dedicated thread attached to the VM, never
+/// the caller's. Returns the subscription handle for `nativeUnsubscribeEvents`,
+/// 0 on failure with the error JSON in `errorOut[0]`.
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeSubscribeEvents(
+    mut env: JNIEnv,
+    _class: JClass,
+    listener: JObject,
+    error_out: JObjectArray,
+) -> jlong {
+    init_logging();
+
+    let result = subscribe_events(&mut env, &listener);
+    write_error(&mut env, &error_out, &result);
+    match result {
+        Ok(subscription) => subscription as jlong,
+        Err(e) => {
+            log::error!("Failed to subscribe to events: {}", e.message());
+            0
+        }
+    }
+}
+
+/// End a subscription from `nativeSubscribeEvents`
+///
+/// Once this returns the listener is not called again; its global reference
+/// is released as the event thread ends. It may be called from `onEvent` itself.
+/// Returns true if the subscription was active.
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeUnsubscribeEvents(
+    _env: JNIEnv,
+    _class: JClass,
+    subscription: jlong,
+) -> jboolean {
+    init_logging();
+
+    match default_instance() {
+        Ok(manager) => manager.unsubscribe_events(subscription as u64) as jboolean,
+        Err(_) => false as jboolean,
+    }
+}
+
+/// Flush pending writes and release every engine instance
+///
+/// Waits a bounded time for autosaves and the database to flush, so it is
+/// safe to call from `onStop` before the process may be reclaimed.
+/// Returns true only if everything was flushed; use
+/// `nativeShutdownWithStatus` to tell a timeout from a failure.
 #[no_mangle]
 pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeShutdown(
     _env: JNIEnv,
     _class: JClass,
 ) -> jboolean {
+    (shutdown_instances() == SHUTDOWN_CLEAN) as jboolean
+}
+
+/// Flush pending writes and release every engine instance, like `nativeShutdown`
+///
+/// Returns SHUTDOWN_CLEAN once everything was flushed, SHUTDOWN_TIMED_OUT
+/// if the flush was cut short, 0 on failure
+#[no_mangle]
+pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeShutdownWithStatus(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    shutdown_instances()
+}
+
+/// Shut down every registered instance within `SHUTDOWN_TIMEOUT`, returning a shutdown status code
+fn shutdown_instances() -> jint {
     init_logging();
     log::info!("Shutting down WriteMagic core engine");
     
-    let registry = get_instance_registry();
-    match registry.write() {
-        Ok(mut map) => {
-            map.clear();
-            log::info!("WriteMagic core engine shutdown completed");
-            true as jboolean
-        }
+    // Take the instances out first, so no new work reaches an engine being shut down
+    let instances: Vec<Arc<FFIInstanceManager>> = match get_instance_registry().write() {
+        Ok(mut map) => map.drain().map(|(_, instance)| instance).collect(),
         Err(e) => {
             log::error!("Failed to shutdown cleanly: {}", e);
-            false as jboolean
+            return 0;
         }
+    };
+    
+    let mut clean = true;
+    for instance in instances {
+        if !instance.shutdown(SHUTDOWN_TIMEOUT) {
+            log::warn!("Instance '{}' did not flush within {:?}", instance.instance_id, SHUTDOWN_TIMEOUT);
+            clean = false;
+        }
+    }
+    
+    if clean {
+        log::info!("WriteMagic core engine shutdown completed");
+        SHUTDOWN_CLEAN
+    } else {
+        log::warn!("WriteMagic core engine shutdown timed out, pending writes may be lost");
+        SHUTDOWN_TIMED_OUT
     }
 }
 
@@ -916,22 +1490,35 @@ pub extern "system" fn Java_com_writemagic_core_WriteMagicCore_nativeMemoryStatu
     init_logging();
     
     let registry = get_instance_registry();
-    let status = match registry.read() {
-        Ok(map) => {
+    let (mut status, default_instance) = match registry.read() {
+        Ok(map) => (
             serde_json::json!({
                 "activeInstances": map.len(),
                 "memoryHealthy": true,
                 "registryStatus": "ok"
-            })
-        }
-        Err(e) => {
+            }),
+            map.get("default").cloned(),
+        ),
+        Err(e) => (
             serde_json::json!({
                 "activeInstances": 0,
                 "memoryHealthy": false,
                 "registryStatus": format!("error: {}", e)
-            })
-        }
+            }),
+            None,
+        ),
     };
     
+    // Provider health and circuit breaker state, so the app can show why a provider is skipped
+    if let Some(manager) = default_instance {
+        let providers = manager.block_on_request("ffi.memory_status", async {
+            let engine_guard = manager.engine().read().ok()?;
+            engine_guard.get_ai_provider_stats().await.ok()
+        });
+        status["aiProviders"] = serde_json::json!(providers.unwrap_or_default());
+        let cache = manager.engine().read().ok().and_then(|engine| engine.ai_cache_stats());
+        status["aiResponseCache"] = serde_json::json!(cache);
+    }
+    
     create_jni_string(&mut env, status.to_string())
 }
\ No newline at end of file
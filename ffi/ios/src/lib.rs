@@ -1,13 +1,14 @@
 //! iOS FFI bindings for WriteMagic core - Thread-safe and performance optimized
 
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_void};
 use std::sync::{Arc, RwLock, OnceLock};
 use std::collections::HashMap;
 use tokio::runtime::Runtime;
-use writemagic_shared::{EntityId, ContentType, Pagination, Result, WritemagicError};
+use writemagic_shared::{DocumentTag, EntityId, ContentType, FFIError, FFIErrorKind, FFIErrorReport, Pagination, RequestContext, Result, ShutdownCoordinator, WritemagicError, EventDispatcher, with_pooled_buffer};
 use writemagic_writing::{
-    CoreEngine, ApplicationConfigBuilder,
+    CoreEngine, ApplicationConfigBuilder, CompletionParams, EventSubscriptions, TimestampFormat, WireTimestamp, DocumentExportFormat,
+    entities::Document,
     value_objects::{DocumentTitle, DocumentContent},
 };
 
@@ -50,18 +51,32 @@ impl<T> FFIResult<T> {
     }
 }
 
+/// `writemagic_shutdown` result: pending writes were flushed and the engine released
+pub const WRITEMAGIC_SHUTDOWN_CLEAN: c_int = 1;
+/// `writemagic_shutdown` result: the engine was released before pending writes finished
+pub const WRITEMAGIC_SHUTDOWN_TIMED_OUT: c_int = 2;
+
+/// Longest `writemagic_shutdown` waits for pending writes, well inside the
+/// time iOS gives a backgrounded app before suspending it
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
 /// Thread-safe instance manager for CoreEngine lifecycle
 pub struct FFIInstanceManager {
     engine: Arc<RwLock<CoreEngine>>,
     runtime: Arc<Runtime>,
     instance_id: String,
+    /// Flushes autosaves and closes the database on shutdown
+    shutdown: tokio::sync::Mutex<ShutdownCoordinator>,
+    event_subscriptions: EventSubscriptions,
 }
 
 impl FFIInstanceManager {
+    /// Build the engine, applying pending migrations first when `run_migrations` is set
     pub async fn new(
         claude_key: Option<String>, 
         openai_key: Option<String>,
         instance_id: String,
+        run_migrations: bool,
     ) -> Result<Self> {
         let runtime = Arc::new(
             Runtime::new()
@@ -75,14 +90,31 @@ impl FFIInstanceManager {
                 .with_openai_key(openai_key.unwrap_or_default())
                 .with_log_level("info".to_string())
                 .with_content_filtering(true)
+                .with_auto_migrate(false)
                 .build()
                 .await
         })?;
+
+        if run_migrations {
+            let applied = runtime.block_on(engine.run_migrations())?;
+            if !applied.is_empty() {
+                let names: Vec<&str> = applied.iter().map(|migration| migration.name.as_str()).collect();
+                log::info!("Applied {} pending migrations: {}", names.len(), names.join(", "));
+            }
+        }
+        
+        let shutdown = ShutdownCoordinator::new();
+        {
+            let _context = runtime.enter();
+            engine.register_shutdown(&shutdown);
+        }
         
         Ok(Self {
+            event_subscriptions: EventSubscriptions::new(engine.event_bus()),
             engine: Arc::new(RwLock::new(engine)),
             runtime,
             instance_id,
+            shutdown: tokio::sync::Mutex::new(shutdown),
         })
     }
     
@@ -93,6 +125,33 @@ impl FFIInstanceManager {
     pub fn runtime(&self) -> &Arc<Runtime> {
         &self.runtime
     }
+
+    /// Run `future` on the runtime as one request with a fresh correlation id
+    pub fn block_on_request<F: std::future::Future>(&self, operation: &str, future: F) -> F::Output {
+        self.runtime.block_on(RequestContext::generate().scope(operation, future))
+    }
+    
+    /// Flush pending writes and end event subscriptions, returning `false` if
+    /// the writes did not finish within `timeout`
+    pub fn shutdown(&self, timeout: std::time::Duration) -> bool {
+        let flushed = self.runtime.block_on(async {
+            self.shutdown.lock().await.shutdown(timeout).await
+        });
+        self.runtime.block_on(self.event_subscriptions.unsubscribe_all());
+        flushed
+    }
+
+    /// Send document and project events to `dispatcher` until unsubscribed, returning the subscription handle
+    pub fn subscribe_events(&self, dispatcher: EventDispatcher) -> u64 {
+        self.runtime.block_on(self.event_subscriptions.subscribe(dispatcher))
+    }
+
+    /// End a subscription, returning whether it was active
+    ///
+    /// Once this returns its dispatcher delivers nothing more.
+    pub fn unsubscribe_events(&self, handle: u64) -> bool {
+        self.runtime.block_on(self.event_subscriptions.unsubscribe(handle))
+    }
 }
 
 /// Thread-safe global instance registry
@@ -154,6 +213,22 @@ fn create_c_string(value: String) -> *mut c_char {
     }
 }
 
+/// Serialize `value` into a C string, building the JSON in a pooled buffer
+///
+/// Only the returned string is allocated per call, which keeps rapid calls
+/// such as `writemagic_list_documents` from churning the allocator.
+fn serialize_to_c_string<T: serde::Serialize + ?Sized>(value: &T) -> FFIResult<CString> {
+    with_pooled_buffer(|buffer| {
+        if let Err(e) = serde_json::to_writer(&mut *buffer, value) {
+            return FFIResult::error(FFIErrorCode::SerializationError, format!("JSON serialization failed: {}", e));
+        }
+        match CString::new(buffer.as_slice()) {
+            Ok(c_string) => FFIResult::success(c_string),
+            Err(e) => FFIResult::error(FFIErrorCode::SerializationError, format!("Failed to create C string: {}", e)),
+        }
+    })
+}
+
 /// Initialize logging (called once)
 fn init_logging() {
     use std::sync::Once;
@@ -180,9 +255,26 @@ fn init_logging() {
 /// Returns 1 for success, 0 for failure
 #[no_mangle]
 pub extern "C" fn writemagic_initialize_with_ai(
+    use_sqlite: c_int,
+    claude_key: *const c_char,
+    openai_key: *const c_char,
+) -> c_int {
+    writemagic_initialize_with_options(use_sqlite, claude_key, openai_key, 1)
+}
+
+/// Initialize the WriteMagic core engine
+/// use_sqlite: 1 to use SQLite, 0 to use in-memory storage
+/// claude_key: Claude API key (can be NULL)
+/// openai_key: OpenAI API key (can be NULL)
+/// run_migrations: 1 to apply pending database migrations before returning,
+/// so an upgraded app brings an older database up to date; 0 to skip them
+/// Returns 1 for success, 0 for failure, including a failed migration
+#[no_mangle]
+pub extern "C" fn writemagic_initialize_with_options(
     _use_sqlite: c_int,
     claude_key: *const c_char,
     openai_key: *const c_char,
+    run_migrations: c_int,
 ) -> c_int {
     init_logging();
     log::info!("Initializing WriteMagic core for iOS with enhanced FFI safety");
@@ -233,6 +325,7 @@ pub extern "C" fn writemagic_initialize_with_ai(
                             claude_api_key,
                             openai_api_key,
                             "default".to_string(),
+                            run_migrations != 0,
                         ).await
                     });
                     
@@ -269,22 +362,321 @@ pub extern "C" fn writemagic_initialize(use_sqlite: c_int) -> c_int {
     writemagic_initialize_with_ai(use_sqlite, std::ptr::null(), std::ptr::null())
 }
 
+/// Engine instance for a structured-error FFI call
+fn default_instance() -> std::result::Result<Arc<FFIInstanceManager>, FFIError> {
+    get_default_instance().into_ffi_result()
+}
+
+/// Read a required C string argument for a structured-error FFI call
+fn string_arg(c_str: *const c_char) -> std::result::Result<String, FFIError> {
+    if c_str.is_null() {
+        return Err(FFIError::NullPointer);
+    }
+    c_string_to_rust(c_str).into_ffi_result()
+}
+
+/// Read an optional C string argument; NULL means absent
+fn optional_string_arg(c_str: *const c_char) -> std::result::Result<Option<String>, FFIError> {
+    if c_str.is_null() {
+        return Ok(None);
+    }
+    c_string_to_rust(c_str).into_ffi_result().map(Some)
+}
+
+/// Parse a document ID, reporting a malformed one as `InvalidId`
+fn parse_document_id(document_id: &str) -> std::result::Result<EntityId, FFIError> {
+    uuid::Uuid::parse_str(document_id).map(EntityId::from_uuid).map_err(|e| {
+        FFIError::detailed(
+            FFIErrorKind::InvalidId,
+            format!("Invalid document ID format: {}", e),
+            serde_json::json!({ "document_id": document_id }),
+        )
+    })
+}
+
+/// Build document content, reporting over-long content as `InvalidInput` with its lengths
+fn parse_document_content(content: &str) -> std::result::Result<DocumentContent, FFIError> {
+    DocumentContent::try_new(content).map_err(|e| {
+        FFIError::detailed(
+            FFIErrorKind::InvalidInput,
+            e.to_string(),
+            serde_json::json!({ "field": "content", "actual_length": e.actual, "max_length": e.allowed }),
+        )
+    })
+}
+
+impl<T> FFIResult<T> {
+    /// The value, or the error as a structured `FFIError`
+    fn into_ffi_result(self) -> std::result::Result<T, FFIError> {
+        match self {
+            FFIResult { value: Some(value), .. } => Ok(value),
+            FFIResult { error_code, error_message, .. } => {
+                let message = error_message.unwrap_or_else(|| format!("{:?}", error_code));
+                Err(match error_code {
+                    FFIErrorCode::NotInitialized => FFIError::detailed(FFIErrorKind::NotInitialized, message, serde_json::Value::Null),
+                    FFIErrorCode::InvalidInput => FFIError::InvalidInput(message),
+                    _ => FFIError::InternalError(message),
+                })
+            }
+        }
+    }
+}
+
+fn create_document(
+    title: *const c_char,
+    content: *const c_char,
+    content_type: *const c_char,
+    idempotency_key: *const c_char,
+) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let title_str = string_arg(title)?;
+    let content_str = string_arg(content)?;
+    let content_type_str = string_arg(content_type)?;
+    let idempotency_key = optional_string_arg(idempotency_key)?;
+
+    log::info!("Creating document: {} ({})", title_str, content_type_str);
+
+    manager.block_on_request("ffi.create_document", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_title = DocumentTitle::new(&title_str)?;
+        let document_content = parse_document_content(&content_str)?;
+        let content_type = match content_type_str.as_str() {
+            "markdown" => ContentType::Markdown,
+            "plain_text" => ContentType::PlainText,
+            "html" => ContentType::Html,
+            _ => ContentType::PlainText,
+        };
+
+        let aggregate = engine_guard.document_management_service().create_document(
+            document_title,
+            document_content,
+            content_type,
+            None, // created_by - set from authentication context
+            idempotency_key,
+        ).await?;
+
+        let document = aggregate.document();
+        log::info!("Document created successfully: {}", document.id);
+        Ok(document.id.to_string())
+    })
+}
+
+/// Create a new document, reporting failures through `error_out`
+/// Returns document ID as C string (must be freed by caller), or NULL on failure.
+/// A retry with the same `idempotency_key` returns the document first created
+/// under it while the key lives; `idempotency_key` may be NULL for a plain create.
+/// `error_out` may be NULL; when set, free it with writemagic_free_error_report.
+#[no_mangle]
+pub extern "C" fn writemagic_create_document_with_error(
+    title: *const c_char,
+    content: *const c_char,
+    content_type: *const c_char,
+    idempotency_key: *const c_char,
+    error_out: *mut FFIErrorReport,
+) -> *mut c_char {
+    init_logging();
+
+    let result = create_document(title, content, content_type, idempotency_key);
+    unsafe { FFIErrorReport::write_to(error_out, &result) };
+    match result {
+        Ok(doc_id) => create_c_string(doc_id),
+        Err(e) => {
+            log::error!("Document creation failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
 /// Create a new document with enhanced error handling and performance
 /// Returns document ID as C string (must be freed by caller)
+#[deprecated(note = "use writemagic_create_document_with_error to learn why creation failed")]
 #[no_mangle]
 pub extern "C" fn writemagic_create_document(
     title: *const c_char,
     content: *const c_char,
     content_type: *const c_char,
+) -> *mut c_char {
+    writemagic_create_document_with_error(title, content, content_type, std::ptr::null(), std::ptr::null_mut())
+}
+
+fn update_document_content(document_id: *const c_char, content: *const c_char) -> std::result::Result<(), FFIError> {
+    let manager = default_instance()?;
+    let document_id_str = string_arg(document_id)?;
+    let content_str = string_arg(content)?;
+
+    log::info!("Updating document {} with new content", document_id_str);
+
+    manager.block_on_request("ffi.update_document_content", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_id = parse_document_id(&document_id_str)?;
+        let document_content = parse_document_content(&content_str)?;
+
+        engine_guard.document_management_service().update_document_content(
+            document_id,
+            document_content,
+            None, // text selection
+            None, // updated_by - set from authentication context
+        ).await?;
+
+        log::info!("Successfully updated document {}", document_id_str);
+        Ok(())
+    })
+}
+
+/// Update document content, reporting failures through `error_out`
+/// Returns 1 for success, 0 for failure.
+/// `error_out` may be NULL; when set, free it with writemagic_free_error_report.
+#[no_mangle]
+pub extern "C" fn writemagic_update_document_content_with_error(
+    document_id: *const c_char,
+    content: *const c_char,
+    error_out: *mut FFIErrorReport,
+) -> c_int {
+    init_logging();
+
+    let result = update_document_content(document_id, content);
+    unsafe { FFIErrorReport::write_to(error_out, &result) };
+    match result {
+        Ok(()) => 1,
+        Err(e) => {
+            log::error!("Failed to update document content: {}", e.message());
+            0
+        }
+    }
+}
+
+/// Update document content with enhanced performance and error handling
+/// Returns 1 for success, 0 for failure
+#[deprecated(note = "use writemagic_update_document_content_with_error to learn why the update failed")]
+#[no_mangle]
+pub extern "C" fn writemagic_update_document_content(
+    document_id: *const c_char,
+    content: *const c_char,
+) -> c_int {
+    writemagic_update_document_content_with_error(document_id, content, std::ptr::null_mut())
+}
+
+/// Standard JSON representation of a document returned across the FFI boundary
+fn document_json(document: &Document, timestamp_format: &TimestampFormat) -> serde_json::Value {
+    serde_json::json!({
+        "id": document.id.to_string(),
+        "title": document.title,
+        "content": document.content,
+        "contentType": document.content_type.to_string(),
+        "wordCount": document.word_count,
+        "characterCount": document.character_count,
+        "language": document.effective_language(),
+        "tags": document.tags.iter().map(DocumentTag::as_str).collect::<Vec<_>>(),
+        "createdAt": timestamp_format.encode(&document.created_at),
+        "updatedAt": timestamp_format.encode(&document.updated_at),
+        "version": document.version,
+        "isDeleted": document.is_deleted
+    })
+}
+
+/// Document listing entry, without the content
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentSummary<'a> {
+    id: EntityId,
+    title: &'a str,
+    content_type: String,
+    word_count: u32,
+    character_count: u32,
+    language: &'a str,
+    tags: Vec<&'a str>,
+    created_at: WireTimestamp,
+    updated_at: WireTimestamp,
+    version: u64,
+    is_deleted: bool,
+}
+
+impl<'a> DocumentSummary<'a> {
+    fn new(document: &'a Document, timestamp_format: TimestampFormat) -> Self {
+        Self {
+            id: document.id,
+            title: &document.title,
+            content_type: document.content_type.to_string(),
+            word_count: document.word_count,
+            character_count: document.character_count,
+            language: document.effective_language(),
+            tags: document.tags.iter().map(DocumentTag::as_str).collect(),
+            created_at: timestamp_format.encode(&document.created_at),
+            updated_at: timestamp_format.encode(&document.updated_at),
+            version: document.version,
+            is_deleted: document.is_deleted,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct DocumentList<'a> {
+    documents: Vec<DocumentSummary<'a>>,
+    count: usize,
+}
+
+fn get_document(document_id: *const c_char) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let document_id_str = string_arg(document_id)?;
+
+    log::info!("Getting document {}", document_id_str);
+
+    manager.block_on_request("ffi.get_document", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let document_id = parse_document_id(&document_id_str)?;
+        let document = engine_guard.document_repository().find_by_id(&document_id).await?
+            .ok_or_else(|| WritemagicError::not_found(format!("Document {}", document_id_str)))?;
+
+        Ok(document_json(&document, &engine_guard.config().timestamp_format).to_string())
+    })
+}
+
+/// Get document by ID, reporting failures through `error_out`
+/// Returns document JSON as C string (must be freed by caller), or NULL on failure.
+/// `error_out` may be NULL; when set, free it with writemagic_free_error_report.
+#[no_mangle]
+pub extern "C" fn writemagic_get_document_with_error(
+    document_id: *const c_char,
+    error_out: *mut FFIErrorReport,
 ) -> *mut c_char {
     init_logging();
+
+    let result = get_document(document_id);
+    unsafe { FFIErrorReport::write_to(error_out, &result) };
+    match result {
+        Ok(json_str) => create_c_string(json_str),
+        Err(e) => {
+            log::error!("Get document failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Get document by ID with enhanced performance and error handling
+/// Returns document JSON as C string (must be freed by caller)
+#[deprecated(note = "use writemagic_get_document_with_error to tell a missing document from other failures")]
+#[no_mangle]
+pub extern "C" fn writemagic_get_document(document_id: *const c_char) -> *mut c_char {
+    writemagic_get_document_with_error(document_id, std::ptr::null_mut())
+}
+
+/// Restore a soft-deleted document
+/// Returns the restored document JSON as C string (must be freed by caller)
+#[no_mangle]
+pub extern "C" fn writemagic_restore_document(document_id: *const c_char) -> *mut c_char {
+    init_logging();
     
-    if title.is_null() || content.is_null() || content_type.is_null() {
-        log::error!("Null pointer passed to writemagic_create_document");
+    if document_id.is_null() {
+        log::error!("Null pointer passed to writemagic_restore_document");
         return std::ptr::null_mut();
     }
     
-    // Get instance manager
     let manager = match get_default_instance() {
         FFIResult { value: Some(mgr), .. } => mgr,
         FFIResult { error_message, .. } => {
@@ -293,35 +685,17 @@ pub extern "C" fn writemagic_create_document(
         }
     };
     
-    // Extract parameters with error handling
-    let title_str = match c_string_to_rust(title) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract title: {:?}", error_message);
-            return std::ptr::null_mut();
-        }
-    };
-    
-    let content_str = match c_string_to_rust(content) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content: {:?}", error_message);
-            return std::ptr::null_mut();
-        }
-    };
-    
-    let content_type_str = match c_string_to_rust(content_type) {
+    let document_id_str = match c_string_to_rust(document_id) {
         FFIResult { value: Some(s), .. } => s,
         FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content_type: {:?}", error_message);
+            log::error!("Failed to extract document_id: {:?}", error_message);
             return std::ptr::null_mut();
         }
     };
     
-    log::info!("Creating document: {} ({})", title_str, content_type_str);
+    log::info!("Restoring document {}", document_id_str);
     
-    // Use shared runtime instead of creating new one
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.restore_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -332,79 +706,60 @@ pub extern "C" fn writemagic_create_document(
             }
         };
         
-        let document_title = match DocumentTitle::new(&title_str) {
-            Ok(title) => title,
-            Err(e) => {
-                return FFIResult::error(
-                    FFIErrorCode::InvalidInput,
-                    format!("Invalid document title: {}", e)
-                );
-            }
-        };
-        
-        let document_content = match DocumentContent::new(&content_str) {
-            Ok(content) => content,
+        let document_id = match uuid::Uuid::parse_str(&document_id_str) {
+            Ok(uuid) => EntityId::from_uuid(uuid),
             Err(e) => {
                 return FFIResult::error(
                     FFIErrorCode::InvalidInput,
-                    format!("Invalid document content: {}", e)
+                    format!("Invalid document ID format: {}", e)
                 );
             }
         };
         
-        let content_type = match content_type_str.as_str() {
-            "markdown" => ContentType::Markdown,
-            "plain_text" => ContentType::PlainText,
-            "html" => ContentType::Html,
-            _ => ContentType::PlainText,
-        };
-        
-        match engine_guard.document_management_service().create_document(
-            document_title,
-            document_content,
-            content_type,
-            None, // created_by - set from authentication context
+        match engine_guard.document_management_service().restore_document(
+            document_id,
+            None, // restored_by - set from authentication context
         ).await {
             Ok(aggregate) => {
-                let document = aggregate.document();
-                log::info!("Document created successfully: {}", document.id);
-                FFIResult::success(document.id.to_string())
+                let response = document_json(aggregate.document(), &engine_guard.config().timestamp_format);
+                FFIResult::success(response.to_string())
             }
+            Err(e @ WritemagicError::Validation { .. }) => FFIResult::error(
+                FFIErrorCode::InvalidInput,
+                format!("Failed to restore document: {}", e)
+            ),
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
-                format!("Failed to create document: {}", e)
+                format!("Failed to restore document: {}", e)
             )
         }
     });
     
     match result {
-        FFIResult { value: Some(doc_id), .. } => create_c_string(doc_id),
+        FFIResult { value: Some(json_str), .. } => create_c_string(json_str),
         FFIResult { error_message, .. } => {
-            log::error!("Document creation failed: {:?}", error_message);
+            log::error!("Restore document failed: {:?}", error_message);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Update document content with enhanced performance and error handling
-/// Returns 1 for success, 0 for failure
+/// Analyze a document's text: counts, reading time and grade level
+/// Returns the statistics JSON as C string (must be freed by caller)
 #[no_mangle]
-pub extern "C" fn writemagic_update_document_content(
-    document_id: *const c_char,
-    content: *const c_char,
-) -> c_int {
+pub extern "C" fn writemagic_analyze_document(document_id: *const c_char) -> *mut c_char {
     init_logging();
     
-    if document_id.is_null() || content.is_null() {
-        log::error!("Null pointer passed to writemagic_update_document_content");
-        return 0;
+    if document_id.is_null() {
+        log::error!("Null pointer passed to writemagic_analyze_document");
+        return std::ptr::null_mut();
     }
     
     let manager = match get_default_instance() {
         FFIResult { value: Some(mgr), .. } => mgr,
         FFIResult { error_message, .. } => {
             log::error!("Failed to get CoreEngine instance: {:?}", error_message);
-            return 0;
+            return std::ptr::null_mut();
         }
     };
     
@@ -412,76 +767,71 @@ pub extern "C" fn writemagic_update_document_content(
         FFIResult { value: Some(s), .. } => s,
         FFIResult { error_message, .. } => {
             log::error!("Failed to extract document_id: {:?}", error_message);
-            return 0;
-        }
-    };
-    
-    let content_str = match c_string_to_rust(content) {
-        FFIResult { value: Some(s), .. } => s,
-        FFIResult { error_message, .. } => {
-            log::error!("Failed to extract content: {:?}", error_message);
-            return 0;
+            return std::ptr::null_mut();
         }
     };
     
-    log::info!("Updating document {} with new content", document_id_str);
+    log::info!("Analyzing document {}", document_id_str);
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.analyze_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
-                log::error!("Failed to acquire engine read lock: {}", e);
-                return false;
+                return FFIResult::error(
+                    FFIErrorCode::ThreadingError,
+                    format!("Failed to acquire engine read lock: {}", e)
+                );
             }
         };
         
-        // Parse document ID
         let document_id = match uuid::Uuid::parse_str(&document_id_str) {
             Ok(uuid) => EntityId::from_uuid(uuid),
             Err(e) => {
-                log::error!("Invalid document ID format: {}", e);
-                return false;
-            }
-        };
-        
-        let document_content = match DocumentContent::new(&content_str) {
-            Ok(content) => content,
-            Err(e) => {
-                log::error!("Invalid document content: {}", e);
-                return false;
+                return FFIResult::error(
+                    FFIErrorCode::InvalidInput,
+                    format!("Invalid document ID format: {}", e)
+                );
             }
         };
         
-        match engine_guard.document_management_service().update_document_content(
-            document_id,
-            document_content,
-            None, // text selection
-            None, // updated_by - set from authentication context
-        ).await {
-            Ok(_) => {
-                log::info!("Successfully updated document {}", document_id_str);
-                true
-            }
-            Err(e) => {
-                log::error!("Failed to update document content: {}", e);
-                false
-            }
+        match engine_guard.analyze_document(document_id).await {
+            Ok(statistics) => serialize_to_c_string(&statistics),
+            Err(e @ (WritemagicError::NotFound { .. } | WritemagicError::Validation { .. })) => FFIResult::error(
+                FFIErrorCode::InvalidInput,
+                format!("Failed to analyze document: {}", e)
+            ),
+            Err(e) => FFIResult::error(
+                FFIErrorCode::EngineError,
+                format!("Failed to analyze document: {}", e)
+            )
         }
     });
     
-    if result { 1 } else { 0 }
+    match result {
+        FFIResult { value: Some(c_string), .. } => c_string.into_raw(),
+        FFIResult { error_message, .. } => {
+            log::error!("Analyze document failed: {:?}", error_message);
+            std::ptr::null_mut()
+        }
+    }
 }
 
-/// Get document by ID with enhanced performance and error handling
-/// Returns document JSON as C string (must be freed by caller)
+/// Export a document as "markdown", "html", "plain_text" or "pdf"
+/// Returns the exported bytes and writes their length to `out_len`;
+/// the buffer must be freed with writemagic_free_bytes
 #[no_mangle]
-pub extern "C" fn writemagic_get_document(document_id: *const c_char) -> *mut c_char {
+pub extern "C" fn writemagic_export_document(
+    document_id: *const c_char,
+    format: *const c_char,
+    out_len: *mut usize,
+) -> *mut u8 {
     init_logging();
     
-    if document_id.is_null() {
-        log::error!("Null pointer passed to writemagic_get_document");
+    if document_id.is_null() || format.is_null() || out_len.is_null() {
+        log::error!("Null pointer passed to writemagic_export_document");
         return std::ptr::null_mut();
     }
+    unsafe { *out_len = 0 };
     
     let manager = match get_default_instance() {
         FFIResult { value: Some(mgr), .. } => mgr,
@@ -499,9 +849,17 @@ pub extern "C" fn writemagic_get_document(document_id: *const c_char) -> *mut c_
         }
     };
     
-    log::info!("Getting document {}", document_id_str);
+    let format_str = match c_string_to_rust(format) {
+        FFIResult { value: Some(s), .. } => s,
+        FFIResult { error_message, .. } => {
+            log::error!("Failed to extract format: {:?}", error_message);
+            return std::ptr::null_mut();
+        }
+    };
     
-    let result = manager.runtime().block_on(async {
+    log::info!("Exporting document {} as {}", document_id_str, format_str);
+    
+    let result = manager.block_on_request("ffi.export_document", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -512,7 +870,6 @@ pub extern "C" fn writemagic_get_document(document_id: *const c_char) -> *mut c_
             }
         };
         
-        // Parse document ID
         let document_id = match uuid::Uuid::parse_str(&document_id_str) {
             Ok(uuid) => EntityId::from_uuid(uuid),
             Err(e) => {
@@ -523,43 +880,49 @@ pub extern "C" fn writemagic_get_document(document_id: *const c_char) -> *mut c_
             }
         };
         
-        match engine_guard.document_repository().find_by_id(&document_id).await {
-            Ok(Some(document)) => {
-                let response = serde_json::json!({
-                    "id": document.id.to_string(),
-                    "title": document.title,
-                    "content": document.content,
-                    "contentType": document.content_type.to_string(),
-                    "wordCount": document.word_count,
-                    "characterCount": document.character_count,
-                    "createdAt": document.created_at.to_string(),
-                    "updatedAt": document.updated_at.to_string(),
-                    "version": document.version,
-                    "isDeleted": document.is_deleted
-                });
-                
-                FFIResult::success(response.to_string())
-            }
-            Ok(None) => FFIResult::error(
+        let format = match DocumentExportFormat::from_string(&format_str) {
+            Ok(format) => format,
+            Err(e) => return FFIResult::error(FFIErrorCode::InvalidInput, e.to_string()),
+        };
+        
+        match engine_guard.export_document(document_id, format).await {
+            Ok(bytes) => FFIResult::success(bytes),
+            Err(e @ WritemagicError::NotFound { .. }) => FFIResult::error(
                 FFIErrorCode::InvalidInput,
-                format!("Document {} not found", document_id_str)
+                format!("Failed to export document: {}", e)
             ),
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
-                format!("Failed to retrieve document: {}", e)
+                format!("Failed to export document: {}", e)
             )
         }
     });
     
     match result {
-        FFIResult { value: Some(json_str), .. } => create_c_string(json_str),
+        FFIResult { value: Some(bytes), .. } => {
+            let bytes = bytes.into_boxed_slice();
+            unsafe { *out_len = bytes.len() };
+            Box::into_raw(bytes) as *mut u8
+        }
         FFIResult { error_message, .. } => {
-            log::error!("Get document failed: {:?}", error_message);
+            log::error!("Export document failed: {:?}", error_message);
             std::ptr::null_mut()
         }
     }
 }
 
+/// Free a buffer returned by writemagic_export_document
+///
+/// # Safety
+/// `bytes` and `len` must come from a single call to writemagic_export_document,
+/// and the buffer must not be used or freed again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn writemagic_free_bytes(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
+
 /// Complete text using AI with enhanced error handling and performance optimization
 /// Returns completion JSON as C string (must be freed by caller)
 #[no_mangle]
@@ -601,7 +964,7 @@ pub extern "C" fn writemagic_complete_text(
     
     log::info!("Completing text with model {:?} and prompt: {}", model_str, prompt_str);
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.complete_text", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -624,6 +987,7 @@ pub extern "C" fn writemagic_complete_text(
                 log::error!("AI completion failed: {}", e);
                 let error_response = serde_json::json!({
                     "error": e.to_string(),
+                    "provider_error": e.provider_error(),
                     "success": false
                 });
                 // Return structured error instead of failing
@@ -646,6 +1010,58 @@ pub extern "C" fn writemagic_complete_text(
     }
 }
 
+fn complete_text_ex(
+    prompt: *const c_char,
+    model: *const c_char,
+    params_json: *const c_char,
+) -> std::result::Result<String, FFIError> {
+    let manager = default_instance()?;
+    let prompt_str = string_arg(prompt)?;
+    let model_str = optional_string_arg(model)?.filter(|m| !m.trim().is_empty());
+    let params: CompletionParams = match optional_string_arg(params_json)? {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str(&json)
+            .map_err(|e| FFIError::InvalidInput(format!("Invalid completion params: {}", e)))?,
+        _ => CompletionParams::default(),
+    };
+    log::info!("Completing text with {:?} and {:?}", model_str, params);
+
+    manager.block_on_request("ffi.complete_text", async {
+        let engine_guard = manager.engine().read()
+            .map_err(|e| FFIError::InternalError(format!("Failed to acquire engine read lock: {}", e)))?;
+
+        let completion = engine_guard.complete_text_with_params(prompt_str, model_str, params).await?;
+        Ok(serde_json::json!({
+            "completion": completion,
+            "success": true
+        }).to_string())
+    })
+}
+
+/// Complete text using AI with sampling parameters given as JSON
+/// `params_json` may hold `max_tokens`, `temperature`, `top_p` and `stop_sequences`;
+/// NULL or omitted fields keep the defaults. Out-of-range values fail with InvalidInput.
+/// Returns completion JSON as C string (must be freed by caller), or NULL on failure.
+/// `error_out` may be NULL; when set, free it with writemagic_free_error_report.
+#[no_mangle]
+pub extern "C" fn writemagic_complete_text_ex(
+    prompt: *const c_char,
+    model: *const c_char,
+    params_json: *const c_char,
+    error_out: *mut FFIErrorReport,
+) -> *mut c_char {
+    init_logging();
+
+    let result = complete_text_ex(prompt, model, params_json);
+    unsafe { FFIErrorReport::write_to(error_out, &result) };
+    match result {
+        Ok(json_str) => create_c_string(json_str),
+        Err(e) => {
+            log::error!("AI completion failed: {}", e.message());
+            std::ptr::null_mut()
+        }
+    }
+}
+
 /// List all documents with pagination and enhanced performance
 /// Returns document list JSON as C string (must be freed by caller)
 #[no_mangle]
@@ -671,7 +1087,7 @@ pub extern "C" fn writemagic_list_documents(
         }
     };
     
-    let result = manager.runtime().block_on(async {
+    let result = manager.block_on_request("ffi.list_documents", async {
         let engine_guard = match manager.engine().read() {
             Ok(guard) => guard,
             Err(e) => {
@@ -682,29 +1098,13 @@ pub extern "C" fn writemagic_list_documents(
             }
         };
         
-        match engine_guard.document_repository().find_all(pagination).await {
-            Ok(documents) => {
-                let documents_json: Vec<serde_json::Value> = documents
-                    .iter()
-                    .map(|doc| serde_json::json!({
-                        "id": doc.id.to_string(),
-                        "title": doc.title,
-                        "contentType": doc.content_type.to_string(),
-                        "wordCount": doc.word_count,
-                        "characterCount": doc.character_count,
-                        "createdAt": doc.created_at.to_string(),
-                        "updatedAt": doc.updated_at.to_string(),
-                        "version": doc.version,
-                        "isDeleted": doc.is_deleted
-                    }))
-                    .collect();
-                
-                let response = serde_json::json!({
-                    "documents": documents_json,
-                    "count": documents.len()
-                });
-                
-                FFIResult::success(response.to_string())
+        match engine_guard.document_management_service().list_documents(pagination).await {
+            Ok(aggregates) => {
+                let timestamp_format = engine_guard.config().timestamp_format;
+                serialize_to_c_string(&DocumentList {
+                    documents: aggregates.iter().map(|aggregate| DocumentSummary::new(aggregate.document(), timestamp_format)).collect(),
+                    count: aggregates.len(),
+                })
             }
             Err(e) => FFIResult::error(
                 FFIErrorCode::EngineError,
@@ -714,7 +1114,7 @@ pub extern "C" fn writemagic_list_documents(
     });
     
     match result {
-        FFIResult { value: Some(json_str), .. } => create_c_string(json_str),
+        FFIResult { value: Some(c_string), .. } => c_string.into_raw(),
         FFIResult { error_message, .. } => {
             log::error!("List documents failed: {:?}", error_message);
             std::ptr::null_mut()
@@ -722,49 +1122,148 @@ pub extern "C" fn writemagic_list_documents(
     }
 }
 
-/// Cleanup and shutdown - proper resource management
+/// Flush pending writes and release every engine instance
+///
+/// Waits a bounded time for autosaves and the database to flush, so it is
+/// safe to call when the app moves to the background.
+/// Returns WRITEMAGIC_SHUTDOWN_CLEAN once everything was flushed,
+/// WRITEMAGIC_SHUTDOWN_TIMED_OUT if the flush was cut short, 0 on failure
 #[no_mangle]
 pub extern "C" fn writemagic_shutdown() -> c_int {
     init_logging();
     log::info!("Shutting down WriteMagic core engine");
     
-    let registry = get_instance_registry();
-    match registry.write() {
-        Ok(mut map) => {
-            map.clear();
-            log::info!("WriteMagic core engine shutdown completed");
-            1
-        }
+    // Take the instances out first, so no new work reaches an engine being shut down
+    let instances: Vec<Arc<FFIInstanceManager>> = match get_instance_registry().write() {
+        Ok(mut map) => map.drain().map(|(_, instance)| instance).collect(),
         Err(e) => {
             log::error!("Failed to shutdown cleanly: {}", e);
+            return 0;
+        }
+    };
+    
+    let mut clean = true;
+    for instance in instances {
+        if !instance.shutdown(SHUTDOWN_TIMEOUT) {
+            log::warn!("Instance '{}' did not flush within {:?}", instance.instance_id, SHUTDOWN_TIMEOUT);
+            clean = false;
+        }
+    }
+    
+    if clean {
+        log::info!("WriteMagic core engine shutdown completed");
+        WRITEMAGIC_SHUTDOWN_CLEAN
+    } else {
+        log::warn!("WriteMagic core engine shutdown timed out, pending writes may be lost");
+        WRITEMAGIC_SHUTDOWN_TIMED_OUT
+    }
+}
+
+/// Receives one document event as NUL-terminated JSON, valid only during the
+/// call, and the `user_data` given to writemagic_subscribe_events
+pub type WritemagicEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+/// The app's `user_data`, handed back to its callback on the dispatch thread
+struct CallbackUserData(*mut c_void);
+
+// SAFETY: writemagic_subscribe_events requires `user_data` to be usable from any thread
+unsafe impl Send for CallbackUserData {}
+
+fn subscribe_events(
+    callback: Option<WritemagicEventCallback>,
+    user_data: *mut c_void,
+) -> std::result::Result<u64, FFIError> {
+    let manager = default_instance()?;
+    let callback = callback.ok_or(FFIError::NullPointer)?;
+    let user_data = CallbackUserData(user_data);
+
+    let dispatcher = EventDispatcher::spawn("writemagic-events", move || {
+        let user_data = user_data;
+        move |json: &str| match CString::new(json) {
+            Ok(json) => callback(json.as_ptr(), user_data.0),
+            Err(e) => log::error!("Dropping event that is not a valid C string: {}", e),
+        }
+    })?;
+    Ok(manager.subscribe_events(dispatcher))
+}
+
+/// Call `callback` with each document event as JSON until writemagic_unsubscribe_events
+///
+/// Events arrive in order on a dedicated thread, never the caller's.
+/// `user_data` is passed back untouched; it must be usable from that thread
+/// and stay valid until unsubscribed.
+/// Returns the subscription handle, 0 on failure.
+/// `error_out` may be NULL; when set, free it with writemagic_free_error_report.
+#[no_mangle]
+pub extern "C" fn writemagic_subscribe_events(
+    callback: Option<WritemagicEventCallback>,
+    user_data: *mut c_void,
+    error_out: *mut FFIErrorReport,
+) -> u64 {
+    init_logging();
+
+    let result = subscribe_events(callback, user_data);
+    unsafe { FFIErrorReport::write_to(error_out, &result) };
+    match result {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            log::error!("Failed to subscribe to events: {}", e.message());
             0
         }
     }
 }
 
+/// End a subscription from writemagic_subscribe_events
+///
+/// Once this returns the callback is not called again, so its `user_data`
+/// may be freed. It may be called from the callback itself.
+/// Returns 1 if the subscription was active, 0 otherwise.
+#[no_mangle]
+pub extern "C" fn writemagic_unsubscribe_events(subscription: u64) -> c_int {
+    init_logging();
+
+    match default_instance() {
+        Ok(manager) if manager.unsubscribe_events(subscription) => 1,
+        _ => 0,
+    }
+}
+
 /// Memory leak detection helper - for debugging
 #[no_mangle]
 pub extern "C" fn writemagic_memory_status() -> *mut c_char {
     init_logging();
     
     let registry = get_instance_registry();
-    let status = match registry.read() {
-        Ok(map) => {
+    let (mut status, default_instance) = match registry.read() {
+        Ok(map) => (
             serde_json::json!({
                 "activeInstances": map.len(),
                 "memoryHealthy": true,
                 "registryStatus": "ok"
-            })
-        }
-        Err(e) => {
+            }),
+            map.get("default").cloned(),
+        ),
+        Err(e) => (
             serde_json::json!({
                 "activeInstances": 0,
                 "memoryHealthy": false,
                 "registryStatus": format!("error: {}", e)
-            })
-        }
+            }),
+            None,
+        ),
     };
     
+    // Provider health and circuit breaker state, so the app can show why a provider is skipped
+    if let Some(manager) = default_instance {
+        let providers = manager.block_on_request("ffi.memory_status", async {
+            let engine_guard = manager.engine().read().ok()?;
+            engine_guard.get_ai_provider_stats().await.ok()
+        });
+        status["aiProviders"] = serde_json::json!(providers.unwrap_or_default());
+        let cache = manager.engine().read().ok().and_then(|engine| engine.ai_cache_stats());
+        status["aiResponseCache"] = serde_json::json!(cache);
+    }
+    
     create_c_string(status.to_string())
 }
 
@@ -25,6 +25,28 @@ class WriteMagicCore: ObservableObject {
         let success: Bool
     }
     
+    /// Why a core call failed, as reported through its error out-param
+    struct CoreError: Error, Decodable {
+        /// Error kind such as "NOT_FOUND" or "INVALID_INPUT"
+        let code: String
+        let message: String
+    }
+    
+    /// Error of the most recent failed core call
+    private(set) static var lastError: CoreError?
+    
+    /// Take the error out of a report filled in by a `*_with_error` call, freeing its strings
+    private static func takeError(_ report: inout WriteMagicErrorReport) -> CoreError {
+        defer { writemagic_free_error_report(&report) }
+        
+        let fallback = report.message.map { String(cString: $0) } ?? "Unknown error (code \(report.code))"
+        guard let details = report.details,
+              let error = try? JSONDecoder().decode(CoreError.self, from: Data(String(cString: details).utf8)) else {
+            return CoreError(code: "INTERNAL", message: fallback)
+        }
+        return error
+    }
+    
     /// Initialize the WriteMagic core engine with persistent SQLite
     static func initialize(claudeKey: String = "", openaiKey: String = "") async -> Bool {
         if isInitialized {
@@ -56,7 +78,9 @@ class WriteMagicCore: ObservableObject {
     }
     
     /// Create a new document
-    static func createDocument(title: String, content: String = "", contentType: String = "markdown") async -> Document? {
+    ///
+    /// Retrying with the same `idempotencyKey` returns the document the first attempt created.
+    static func createDocument(title: String, content: String = "", contentType: String = "markdown", idempotencyKey: String? = nil) async -> Document? {
         guard isInitialized else {
             print("WriteMagic core not initialized")
             return nil
@@ -65,15 +89,20 @@ class WriteMagicCore: ObservableObject {
         let titlePtr = strdup(title)
         let contentPtr = strdup(content)
         let contentTypePtr = strdup(contentType)
+        let idempotencyKeyPtr = idempotencyKey.flatMap { strdup($0) }
         
         defer {
             if let ptr = titlePtr { free(ptr) }
             if let ptr = contentPtr { free(ptr) }
             if let ptr = contentTypePtr { free(ptr) }
+            if let ptr = idempotencyKeyPtr { free(ptr) }
         }
         
-        guard let resultPtr = writemagic_create_document(titlePtr, contentPtr, contentTypePtr) else {
-            print("Failed to create document")
+        var report = WriteMagicErrorReport()
+        guard let resultPtr = writemagic_create_document_with_error(titlePtr, contentPtr, contentTypePtr, idempotencyKeyPtr, &report) else {
+            let error = takeError(&report)
+            lastError = error
+            print("Failed to create document: \(error.message)")
             return nil
         }
         
@@ -105,10 +134,13 @@ class WriteMagicCore: ObservableObject {
             if let ptr = contentPtr { free(ptr) }
         }
         
-        let result = writemagic_update_document_content(idPtr, contentPtr) == 1
+        var report = WriteMagicErrorReport()
+        let result = writemagic_update_document_content_with_error(idPtr, contentPtr, &report) == 1
         
         if !result {
-            print("Failed to update document \(id)")
+            let error = takeError(&report)
+            lastError = error
+            print("Failed to update document \(id): \(error.message)")
         }
         
         return result
@@ -124,8 +156,40 @@ class WriteMagicCore: ObservableObject {
         let idPtr = strdup(id)
         defer { if let ptr = idPtr { free(ptr) } }
         
-        guard let resultPtr = writemagic_get_document(idPtr) else {
-            print("Document \(id) not found")
+        var report = WriteMagicErrorReport()
+        guard let resultPtr = writemagic_get_document_with_error(idPtr, &report) else {
+            let error = takeError(&report)
+            lastError = error
+            print(error.code == "NOT_FOUND" ? "Document \(id) not found" : "Failed to load document \(id): \(error.message)")
+            return nil
+        }
+        
+        defer { writemagic_free_string(resultPtr) }
+        
+        let jsonString = String(cString: resultPtr)
+        
+        do {
+            let data = jsonString.data(using: .utf8)!
+            let document = try JSONDecoder().decode(Document.self, from: data)
+            return document
+        } catch {
+            print("Error parsing document JSON: \(error)")
+            return nil
+        }
+    }
+    
+    /// Restore a deleted document
+    static func restoreDocument(id: String) async -> Document? {
+        guard isInitialized else {
+            print("WriteMagic core not initialized")
+            return nil
+        }
+        
+        let idPtr = strdup(id)
+        defer { if let ptr = idPtr { free(ptr) } }
+        
+        guard let resultPtr = writemagic_restore_document(idPtr) else {
+            print("Failed to restore document \(id)")
             return nil
         }
         
@@ -184,14 +248,27 @@ class WriteMagicCore: ObservableObject {
 @_silgen_name("writemagic_initialize_with_ai")
 func writemagic_initialize_with_ai(_ use_sqlite: Int32, _ claude_key: UnsafePointer<CChar>?, _ openai_key: UnsafePointer<CChar>?) -> Int32
 
-@_silgen_name("writemagic_create_document")
-func writemagic_create_document(_ title: UnsafePointer<CChar>, _ content: UnsafePointer<CChar>, _ content_type: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>?
+/// Mirrors the C `FFIErrorReport` filled in by the `*_with_error` functions
+struct WriteMagicErrorReport {
+    var code: Int32 = 0
+    var message: UnsafeMutablePointer<CChar>? = nil
+    var details: UnsafeMutablePointer<CChar>? = nil
+}
+
+@_silgen_name("writemagic_create_document_with_error")
+func writemagic_create_document_with_error(_ title: UnsafePointer<CChar>, _ content: UnsafePointer<CChar>, _ content_type: UnsafePointer<CChar>, _ idempotency_key: UnsafePointer<CChar>?, _ error_out: UnsafeMutablePointer<WriteMagicErrorReport>?) -> UnsafeMutablePointer<CChar>?
+
+@_silgen_name("writemagic_update_document_content_with_error")
+func writemagic_update_document_content_with_error(_ document_id: UnsafePointer<CChar>, _ content: UnsafePointer<CChar>, _ error_out: UnsafeMutablePointer<WriteMagicErrorReport>?) -> Int32
+
+@_silgen_name("writemagic_get_document_with_error")
+func writemagic_get_document_with_error(_ document_id: UnsafePointer<CChar>, _ error_out: UnsafeMutablePointer<WriteMagicErrorReport>?) -> UnsafeMutablePointer<CChar>?
 
-@_silgen_name("writemagic_update_document_content")
-func writemagic_update_document_content(_ document_id: UnsafePointer<CChar>, _ content: UnsafePointer<CChar>) -> Int32
+@_silgen_name("writemagic_free_error_report")
+func writemagic_free_error_report(_ report: UnsafeMutablePointer<WriteMagicErrorReport>?)
 
-@_silgen_name("writemagic_get_document")
-func writemagic_get_document(_ document_id: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>?
+@_silgen_name("writemagic_restore_document")
+func writemagic_restore_document(_ document_id: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>?
 
 @_silgen_name("writemagic_complete_text")
 func writemagic_complete_text(_ prompt: UnsafePointer<CChar>, _ model: UnsafePointer<CChar>?) -> UnsafeMutablePointer<CChar>?
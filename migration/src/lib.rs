@@ -3,6 +3,7 @@ pub use sea_orm_migration::prelude::*;
 mod m20250101_000001_create_users_table;
 mod m20250101_000002_create_documents_table;
 mod m20250101_000003_create_projects_table;
+mod m20250101_000004_add_user_tenant;
 
 pub struct Migrator;
 
@@ -13,6 +14,7 @@ impl MigratorTrait for Migrator {
             Box::new(m20250101_000001_create_users_table::Migration),
             Box::new(m20250101_000002_create_documents_table::Migration),
             Box::new(m20250101_000003_create_projects_table::Migration),
+            Box::new(m20250101_000004_add_user_tenant::Migration),
         ]
     }
 }
\ No newline at end of file
@@ -4,7 +4,10 @@
 # Usage:
 #   ./scripts/test-wasm.sh
 #
-# This script tests the WASM module in a headless browser environment
+# This script tests the WASM module in a headless browser environment, checks
+# that every feature set compiles, and fails if the minimal release build is
+# over its size budget (MINIMAL_WASM_MAX_KB, 1024 KB by default) or if either
+# build is missing a binding it should export
 
 set -euo pipefail
 
@@ -66,14 +69,62 @@ else
     print_warning "Chrome tests failed or Chrome is not available"
 fi
 
-# Test basic compilation
-print_info "Testing basic compilation..."
-if cargo check --target wasm32-unknown-unknown; then
-    print_success "WASM target compilation check passed!"
-else
-    print_error "WASM target compilation check failed!"
+# Every feature set must compile on its own, not just the default one
+print_info "Checking each feature set compiles..."
+for features in "minimal" "minimal export" "minimal analysis" "full"; do
+    if cargo check --target wasm32-unknown-unknown --no-default-features --features "$features"; then
+        print_success "Feature set '$features' compiles"
+    else
+        print_error "Feature set '$features' does not compile"
+        exit 1
+    fi
+done
+
+# Bundle size and exported API of the minimal and full builds
+print_info "Checking bundle size and exports..."
+MAX_KB="${MINIMAL_WASM_MAX_KB:-1024}"
+SIZE_DIR="target/size-check"
+
+# Bindings every build exports
+CORE_EXPORTS=("create_document" "get_document" "update_document" "delete_document" "list_documents" "create_project" "pending_sync_count" "sync_pending_changes" "version")
+# Bindings that only exist when their feature is enabled
+OPTIONAL_EXPORTS=("export_document" "analyze_document" "get_document_statistics")
+
+wasm-pack build --target web --release --out-dir "$SIZE_DIR/minimal" --out-name writemagic_wasm \
+    -- --no-default-features --features minimal
+wasm-pack build --target web --release --out-dir "$SIZE_DIR/full" --out-name writemagic_wasm
+
+MINIMAL_KB=$(( $(wc -c < "$SIZE_DIR/minimal/writemagic_wasm_bg.wasm") / 1024 ))
+FULL_KB=$(( $(wc -c < "$SIZE_DIR/full/writemagic_wasm_bg.wasm") / 1024 ))
+print_info "Minimal build: ${MINIMAL_KB} KB (budget ${MAX_KB} KB), full build: ${FULL_KB} KB"
+
+status=0
+if [ "$MINIMAL_KB" -gt "$MAX_KB" ]; then
+    print_error "Minimal build is ${MINIMAL_KB} KB, over the ${MAX_KB} KB budget"
+    status=1
+fi
+for name in "${CORE_EXPORTS[@]}"; do
+    for build in minimal full; do
+        if ! grep -qw "$name" "$SIZE_DIR/$build/writemagic_wasm.d.ts"; then
+            print_error "The $build build does not export $name"
+            status=1
+        fi
+    done
+done
+for name in "${OPTIONAL_EXPORTS[@]}"; do
+    if ! grep -qw "$name" "$SIZE_DIR/full/writemagic_wasm.d.ts"; then
+        print_error "The full build does not export $name"
+        status=1
+    fi
+    if grep -qw "$name" "$SIZE_DIR/minimal/writemagic_wasm.d.ts"; then
+        print_error "The minimal build still exports $name"
+        status=1
+    fi
+done
+if [ "$status" -ne 0 ]; then
     exit 1
 fi
+print_success "Bundle size and exports check passed!"
 
 # Return to project root
 cd - > /dev/null
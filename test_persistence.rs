@@ -33,6 +33,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_connections: 1,
         enable_wal: true,
         enable_foreign_keys: true,
+        ..Default::default()
     };
     
     let app_config = writemagic_writing::ApplicationConfig {
@@ -89,6 +90,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_connections: 1,
         enable_wal: true,
         enable_foreign_keys: true,
+        ..Default::default()
     };
     
     let app_config2 = writemagic_writing::ApplicationConfig {
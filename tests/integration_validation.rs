@@ -397,7 +397,7 @@ impl IntegrationValidator {
             let content = DocumentContent::new("This is a test document for AI integration.")?;
             
             let doc_aggregate = doc_service
-                .create_document(title, content, ContentType::Markdown, None)
+                .create_document(title, content, ContentType::Markdown, None, None)
                 .await?;
 
             let document_id = doc_aggregate.document().id;
@@ -516,7 +516,7 @@ impl IntegrationValidator {
             let content = DocumentContent::new(&format!("Benchmark content {}", i))?;
             
             let result = doc_service
-                .create_document(title, content, ContentType::Markdown, None)
+                .create_document(title, content, ContentType::Markdown, None, None)
                 .await;
 
             let elapsed = start.elapsed().as_secs_f64() * 1000.0;
@@ -728,7 +728,7 @@ impl IntegrationValidator {
         let title = DocumentTitle::new("Android Test Document")?;
         let content = DocumentContent::new("Content from Android app")?;
         
-        match doc_service.create_document(title, content, ContentType::Markdown, None).await {
+        match doc_service.create_document(title, content, ContentType::Markdown, None, None).await {
             Ok(aggregate) => {
                 results.core_engine_tests.pass();
                 
@@ -779,7 +779,7 @@ impl IntegrationValidator {
         let title = DocumentTitle::new("iOS Test Document")?;
         let content = DocumentContent::new("Content from iOS app")?;
         
-        match doc_service.create_document(title, content, ContentType::Markdown, None).await {
+        match doc_service.create_document(title, content, ContentType::Markdown, None, None).await {
             Ok(aggregate) => {
                 results.core_engine_tests.pass();
                 
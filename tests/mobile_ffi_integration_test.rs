@@ -62,6 +62,7 @@ async fn test_mobile_ffi_document_persistence() {
                 document_content,
                 ContentType::Markdown,
                 None, // created_by
+                None,
             ).await
         }).expect("Failed to create document");
         
@@ -171,6 +172,7 @@ async fn test_mobile_ffi_document_listing() {
                 document_content,
                 ContentType::PlainText,
                 None,
+                None,
             ).await
         }).expect("Failed to create document");
         
@@ -40,6 +40,7 @@ pub fn bench_document_creation(c: &mut Criterion) {
                     file_path: None,
                     word_count: size as u32 / 5,
                     character_count: size as u32,
+                    tags: Vec::new(),
                     created_at: Timestamp::now(),
                     updated_at: Timestamp::now(),
                     created_by: None,
@@ -69,6 +70,7 @@ pub fn bench_document_retrieval(c: &mut Criterion) {
         file_path: None,
         word_count: 10,
         character_count: 50,
+        tags: Vec::new(),
         created_at: writemagic_shared::Timestamp::now(),
         updated_at: writemagic_shared::Timestamp::now(),
         created_by: None,
@@ -119,6 +121,8 @@ pub fn bench_ai_completion(c: &mut Criterion) {
                     timeout: None,
                     compress_response: false,
                     batchable: false,
+                    credentials_override: None,
+                    few_shot: Vec::new(),
                 };
                 black_box(request)
             });
@@ -147,6 +151,8 @@ pub fn bench_ai_completion(c: &mut Criterion) {
             timeout: None,
             compress_response: false,
             batchable: false,
+            credentials_override: None,
+            few_shot: Vec::new(),
         };
         
         b.iter(|| {
@@ -205,6 +211,7 @@ pub fn bench_database_operations(c: &mut Criterion) {
                     file_path: None,
                     word_count: 2,
                     character_count: 10,
+                    tags: Vec::new(),
                     created_at: Timestamp::now(),
                     updated_at: Timestamp::now(),
                     created_by: None,
@@ -229,6 +236,7 @@ pub fn bench_database_operations(c: &mut Criterion) {
             file_path: None,
             word_count: 2,
             character_count: 12,
+            tags: Vec::new(),
             created_at: writemagic_shared::Timestamp::now(),
             updated_at: writemagic_shared::Timestamp::now(),
             created_by: None,
@@ -279,6 +287,7 @@ pub fn bench_memory_operations(c: &mut Criterion) {
                     file_path: None,
                     word_count: 2,
                     character_count: 10,
+                    tags: Vec::new(),
                     created_at: Timestamp::now(),
                     updated_at: Timestamp::now(),
                     created_by: None,
@@ -322,6 +331,7 @@ pub fn bench_ffi_operations(c: &mut Criterion) {
                 file_path: None,
                 word_count: 5,
                 character_count: 30,
+                tags: Vec::new(),
                 created_at: Timestamp::now(),
                 updated_at: Timestamp::now(),
                 created_by: None,
@@ -169,7 +169,7 @@ impl PerformanceValidator {
             let content = DocumentContent::new("Warmup content")?;
             
             let _ = doc_service
-                .create_document(title, content, ContentType::PlainText, None)
+                .create_document(title, content, ContentType::PlainText, None, None)
                 .await?;
         }
 
@@ -196,7 +196,7 @@ impl PerformanceValidator {
             let title = DocumentTitle::new(&format!("Perf Test Doc {}", i))?;
             let content = DocumentContent::new(&format!("Performance test content for document {}", i))?;
             
-            match doc_service.create_document(title, content, ContentType::Markdown, None).await {
+            match doc_service.create_document(title, content, ContentType::Markdown, None, None).await {
                 Ok(_) => {
                     let duration = start.elapsed().as_secs_f64() * 1000.0;
                     operation_times.push(duration);
@@ -452,7 +452,7 @@ impl PerformanceValidator {
                 let title = DocumentTitle::new(&format!("Concurrent Doc {}", i)).unwrap();
                 let content = DocumentContent::new(&format!("Concurrent content {}", i)).unwrap();
                 
-                match service.create_document(title, content, ContentType::Markdown, None).await {
+                match service.create_document(title, content, ContentType::Markdown, None, None).await {
                     Ok(_) => {
                         let duration = op_start.elapsed().as_secs_f64() * 1000.0;
                         times.write().await.push(duration);
@@ -509,7 +509,7 @@ impl PerformanceValidator {
             let title = DocumentTitle::new(&format!("Large Doc {} ({}MB)", i, self.config.large_document_size_mb))?;
             let content = DocumentContent::new(&large_content)?;
             
-            match doc_service.create_document(title, content, ContentType::PlainText, None).await {
+            match doc_service.create_document(title, content, ContentType::PlainText, None, None).await {
                 Ok(aggregate) => {
                     let create_duration = start.elapsed().as_secs_f64() * 1000.0;
                     
@@ -678,7 +678,7 @@ impl PerformanceValidator {
             let title = DocumentTitle::new(&format!("Stress Doc {}", i))?;
             let content = DocumentContent::new(&"X".repeat(content_size))?;
             
-            match doc_service.create_document(title, content, ContentType::PlainText, None).await {
+            match doc_service.create_document(title, content, ContentType::PlainText, None, None).await {
                 Ok(aggregate) => {
                     let duration = start.elapsed().as_secs_f64() * 1000.0;
                     operation_times.push(duration);
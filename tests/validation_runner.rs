@@ -247,7 +247,7 @@ impl ValidationSuiteRunner {
             let doc_content = DocumentContent::new(content)?;
             
             let doc_aggregate = doc_service
-                .create_document(doc_title, doc_content, ContentType::Markdown, None)
+                .create_document(doc_title, doc_content, ContentType::Markdown, None, None)
                 .await?;
 
             // Add to project
@@ -300,7 +300,7 @@ impl ValidationSuiteRunner {
         let content = DocumentContent::new("# Collaborative Document\n\nShared content")?;
         
         let doc_aggregate = doc_service
-            .create_document(title, content, ContentType::Markdown, None)
+            .create_document(title, content, ContentType::Markdown, None, None)
             .await?;
         
         let doc_id = doc_aggregate.document().id;
@@ -356,7 +356,7 @@ impl ValidationSuiteRunner {
             let content = DocumentContent::new(&format!("Content for document {} with detailed information", i + 1))?;
             
             let doc_aggregate = doc_service
-                .create_document(title, content, ContentType::Markdown, None)
+                .create_document(title, content, ContentType::Markdown, None, None)
                 .await?;
 
             proj_service
@@ -422,7 +422,7 @@ impl ValidationSuiteRunner {
             let content = DocumentContent::new(&format!("Quick note {} from mobile", i + 1))?;
             
             let doc_aggregate = doc_service
-                .create_document(title, content, ContentType::PlainText, None)
+                .create_document(title, content, ContentType::PlainText, None, None)
                 .await?;
             
             document_ids.push(doc_aggregate.document().id);
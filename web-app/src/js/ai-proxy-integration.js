@@ -686,8 +686,6 @@ async function integrateAIProxy() {
     try {
         // Create WriteMagic instance
         const writeMagic = new WriteMagic({
-            claude_api_key: null, // Will use proxy instead
-            openai_api_key: null, // Will use proxy instead
             default_model: 'claude-3-sonnet-20240229',
             enable_analytics: true,
         });
@@ -22,9 +22,8 @@ class WriteMagicApp {
             
             // Initialize WriteMagic with configuration
             this.writeMagic = new WriteMagic({
-                // AI Configuration (optional - works offline without these)
-                claude_api_key: process.env.CLAUDE_API_KEY,
-                openai_api_key: process.env.OPENAI_API_KEY,
+                // AI runs through a backend proxy holding the provider keys
+                // (see ai-proxy-integration.js); the browser never sees them
                 default_model: "claude-3-haiku-20240307",
                 
                 // Writer Experience
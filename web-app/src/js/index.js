@@ -26,8 +26,6 @@ import WritingSession from './writing-session.js';
  */
 export const DEFAULT_CONFIG = {
     // Engine configuration
-    claude_api_key: null,
-    openai_api_key: null,
     default_model: "claude-3-haiku-20240307",
     log_level: "info",
     enable_content_filtering: true,
@@ -131,7 +129,7 @@ export class WriteMagic {
             
             // Create and initialize WASM engine
             this.wasmEngine = new WriteMagicEngine();
-            await this.wasmEngine.initialize(JSON.stringify(this.config));
+            await this.wasmEngine.initialize({ logging: { level: this.config.log_level } });
             
             // Initialize content utilities (no dependencies)
             this.contentUtils = new ContentUtilities({
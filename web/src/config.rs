@@ -1,12 +1,21 @@
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::time::Duration;
 
+use crate::middleware::RateLimitKeyStrategy;
+use crate::utils::network::IpNetwork;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub auth: AuthConfig,
     pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub request_log: RequestLogConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,8 +24,16 @@ pub struct ServerConfig {
     pub port: u16,
     pub request_timeout_secs: u64,
     pub body_limit_bytes: usize,
+    /// How long `/health/ready` waits on each dependency before reporting it down
+    #[serde(default = "default_health_check_timeout_ms")]
+    pub health_check_timeout_ms: u64,
+}
+
+fn default_health_check_timeout_ms() -> u64 {
+    2000
 }
 
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub url: String,
@@ -38,6 +55,112 @@ pub struct AuthConfig {
 pub struct CorsConfig {
     pub allowed_origins: Vec<String>,
     pub max_age_secs: u64,
+    /// Let browsers send cookies and credentials on cross-origin requests
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// Policies for route groups that differ from the one above
+    #[serde(default)]
+    pub route_overrides: Vec<CorsRouteOverride>,
+}
+
+/// CORS policy for the routes under a path prefix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsRouteOverride {
+    /// Path prefix such as `/api/v1/public`, a trailing `/*` is allowed
+    pub path: String,
+    /// Origins allowed on these routes, `*` for any
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+impl CorsRouteOverride {
+    /// The path prefix without a trailing `/*`
+    pub fn prefix(&self) -> &str {
+        self.path.trim_end_matches('*').trim_end_matches('/')
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub max_requests: u32,
+    pub window_secs: u64,
+    pub cleanup_interval_secs: u64,
+    /// Whether requests are limited per IP, per authenticated user, or both
+    #[serde(default)]
+    pub key_strategy: RateLimitKeyStrategy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    pub enabled: bool,
+    /// Fraction of requests logged, from 0.0 to 1.0
+    pub sample_rate: f64,
+    /// Log server errors even when they are not sampled
+    pub always_log_errors: bool,
+    pub include_headers: bool,
+    /// Headers whose values are replaced before logging, matched case-insensitively
+    pub redacted_headers: Vec<String>,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_rate: 1.0,
+            always_log_errors: true,
+            include_headers: false,
+            redacted_headers: vec![
+                "authorization".to_string(),
+                "proxy-authorization".to_string(),
+                "cookie".to_string(),
+                "set-cookie".to_string(),
+                "x-api-key".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// Serve the `/metrics` route; off unless enabled, e.g. with `METRICS_ENABLED=true`
+    pub metrics_enabled: bool,
+    /// Networks, in CIDR notation, whose peers may scrape `/metrics`
+    ///
+    /// Matched against the TCP peer, so behind a proxy this is the proxy's address.
+    pub metrics_allowed_networks: Vec<String>,
+    /// Only allow cross-origin requests from HTTPS origins
+    pub force_https: bool,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            metrics_enabled: false,
+            metrics_allowed_networks: vec![
+                "127.0.0.0/8".to_string(),
+                "::1/128".to_string(),
+                "10.0.0.0/8".to_string(),
+                "172.16.0.0/12".to_string(),
+                "192.168.0.0/16".to_string(),
+            ],
+            force_https: false,
+        }
+    }
+}
+
+impl SecurityConfig {
+    /// Whether a peer at `ip` may scrape `/metrics`
+    pub fn metrics_allowed_from(&self, ip: IpAddr) -> bool {
+        self.metrics_enabled
+            && self
+                .metrics_allowed_networks
+                .iter()
+                .filter_map(|network| network.parse::<IpNetwork>().ok())
+                .any(|network| network.contains(ip))
+    }
 }
 
 impl Config {
@@ -58,6 +181,61 @@ impl Config {
             config.auth.jwt_secret = jwt_secret;
         }
         
+        if let Ok(timeout_ms) = std::env::var("HEALTH_CHECK_TIMEOUT_MS") {
+            config.server.health_check_timeout_ms = timeout_ms.parse()?;
+        }
+        
+        if let Ok(max_requests) = std::env::var("RATE_LIMIT_MAX_REQUESTS") {
+            config.rate_limit.max_requests = max_requests.parse()?;
+        }
+        
+        if let Ok(window_secs) = std::env::var("RATE_LIMIT_WINDOW_SECS") {
+            config.rate_limit.window_secs = window_secs.parse()?;
+        }
+        
+        if let Ok(cleanup_interval_secs) = std::env::var("RATE_LIMIT_CLEANUP_INTERVAL_SECS") {
+            config.rate_limit.cleanup_interval_secs = cleanup_interval_secs.parse()?;
+        }
+        
+        if let Ok(key_strategy) = std::env::var("RATE_LIMIT_KEY_STRATEGY") {
+            config.rate_limit.key_strategy = key_strategy.parse()?;
+        }
+        
+        if let Ok(sample_rate) = std::env::var("REQUEST_LOG_SAMPLE_RATE") {
+            config.request_log.sample_rate = sample_rate.parse()?;
+        }
+        
+        if let Ok(include_headers) = std::env::var("REQUEST_LOG_INCLUDE_HEADERS") {
+            config.request_log.include_headers = include_headers.parse()?;
+        }
+        
+        if let Ok(metrics_enabled) = std::env::var("METRICS_ENABLED") {
+            config.security.metrics_enabled = metrics_enabled.parse()?;
+        }
+        
+        if let Ok(networks) = std::env::var("METRICS_ALLOWED_NETWORKS") {
+            let networks: Vec<String> = networks
+                .split(',')
+                .map(|network| network.trim().to_string())
+                .filter(|network| !network.is_empty())
+                .collect();
+            for network in &networks {
+                network.parse::<IpNetwork>().map_err(anyhow::Error::msg)?;
+            }
+            config.security.metrics_allowed_networks = networks;
+        }
+        
+        if let Ok(force_https) = std::env::var("FORCE_HTTPS") {
+            config.security.force_https = force_https.parse()?;
+        }
+        
+        if let Ok(overrides) = std::env::var("CORS_ROUTE_OVERRIDES") {
+            config.cors.route_overrides = serde_json::from_str(&overrides)?;
+        }
+        
+        config.cors.validate(config.security.force_https)?;
+        config.rate_limit.validate()?;
+        
         Ok(config)
     }
     
@@ -69,6 +247,7 @@ impl Config {
                 port: 0, // Random port for testing
                 request_timeout_secs: 30,
                 body_limit_bytes: 10 * 1024 * 1024, // 10MB
+                health_check_timeout_ms: default_health_check_timeout_ms(),
             },
             database: DatabaseConfig {
                 url: ":memory:".to_string(), // SQLite in-memory for tests
@@ -86,7 +265,17 @@ impl Config {
             cors: CorsConfig {
                 allowed_origins: vec!["http://localhost:3000".to_string()],
                 max_age_secs: 3600,
+                allow_credentials: false,
+                route_overrides: Vec::new(),
             },
+            rate_limit: RateLimitConfig {
+                max_requests: 100,
+                window_secs: 60,
+                cleanup_interval_secs: 1,
+                key_strategy: RateLimitKeyStrategy::default(),
+            },
+            request_log: RequestLogConfig::default(),
+            security: SecurityConfig::default(),
         }
     }
 }
@@ -99,6 +288,7 @@ impl Default for Config {
                 port: 8080,
                 request_timeout_secs: 30,
                 body_limit_bytes: 10 * 1024 * 1024, // 10MB
+                health_check_timeout_ms: default_health_check_timeout_ms(),
             },
             database: DatabaseConfig {
                 url: "sqlite:writemagic.db".to_string(),
@@ -119,7 +309,17 @@ impl Default for Config {
                     "http://localhost:8080".to_string(),
                 ],
                 max_age_secs: 3600,
+                allow_credentials: false,
+                route_overrides: Vec::new(),
+            },
+            rate_limit: RateLimitConfig {
+                max_requests: 100,
+                window_secs: 60,
+                cleanup_interval_secs: 120,
+                key_strategy: RateLimitKeyStrategy::default(),
             },
+            request_log: RequestLogConfig::default(),
+            security: SecurityConfig::default(),
         }
     }
 }
@@ -128,6 +328,10 @@ impl ServerConfig {
     pub fn request_timeout(&self) -> Duration {
         Duration::from_secs(self.request_timeout_secs)
     }
+
+    pub fn health_check_timeout(&self) -> Duration {
+        Duration::from_millis(self.health_check_timeout_ms)
+    }
 }
 
 impl DatabaseConfig {
@@ -147,6 +351,65 @@ impl DatabaseConfig {
     }
 }
 
+impl CorsConfig {
+    /// Reject policies browsers would refuse or that undercut `force_https`
+    pub fn validate(&self, force_https: bool) -> anyhow::Result<()> {
+        validate_cors_origins("default", &self.allowed_origins, self.allow_credentials, force_https)?;
+
+        let mut prefixes = std::collections::HashSet::new();
+        for route in &self.route_overrides {
+            if !route.path.starts_with('/') {
+                anyhow::bail!("CORS override path '{}' must start with '/'", route.path);
+            }
+            if !prefixes.insert(route.prefix()) {
+                anyhow::bail!("CORS override for '{}' is defined more than once", route.path);
+            }
+            validate_cors_origins(&route.path, &route.allowed_origins, route.allow_credentials, force_https)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_cors_origins(group: &str, origins: &[String], allow_credentials: bool, force_https: bool) -> anyhow::Result<()> {
+    if origins.iter().any(|origin| origin == "*") {
+        if origins.len() > 1 {
+            anyhow::bail!("CORS policy for {} mixes '*' with specific origins", group);
+        }
+        // Browsers ignore a wildcard origin on credentialed requests
+        if allow_credentials {
+            anyhow::bail!("CORS policy for {} allows credentials from any origin", group);
+        }
+        return Ok(());
+    }
+
+    for origin in origins {
+        origin
+            .parse::<axum::http::HeaderValue>()
+            .map_err(|_| anyhow::anyhow!("Invalid CORS origin '{}' for {}", origin, group))?;
+        if force_https && !origin.starts_with("https://") {
+            anyhow::bail!("CORS origin '{}' for {} is not HTTPS but force_https is set", origin, group);
+        }
+    }
+    Ok(())
+}
+
+impl RateLimitConfig {
+    /// Reject zero windows and cleanup intervals, which the limiter can't run with
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.window_secs == 0 {
+            anyhow::bail!("Rate limit window_secs must be greater than zero");
+        }
+        if self.cleanup_interval_secs == 0 {
+            anyhow::bail!("Rate limit cleanup_interval_secs must be greater than zero");
+        }
+        Ok(())
+    }
+
+    pub fn cleanup_interval(&self) -> Duration {
+        Duration::from_secs(self.cleanup_interval_secs)
+    }
+}
+
 impl AuthConfig {
     #[allow(dead_code)]
     pub fn access_token_duration(&self) -> Duration {
@@ -157,4 +420,21 @@ impl AuthConfig {
     pub fn refresh_token_duration(&self) -> Duration {
         Duration::from_secs(self.refresh_token_duration_secs as u64)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_validation_rejects_zero_durations() {
+        let mut rate_limit = Config::test_default().rate_limit;
+        assert!(rate_limit.validate().is_ok());
+
+        rate_limit.cleanup_interval_secs = 0;
+        assert!(rate_limit.validate().is_err());
+
+        rate_limit.cleanup_interval_secs = 60;
+        rate_limit.window_secs = 0;
+        assert!(rate_limit.validate().is_err());
+    }
+}
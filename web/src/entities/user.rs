@@ -25,6 +25,9 @@ pub struct Model {
     
     #[sea_orm(default_value = "user")]
     pub role: String,
+
+    /// Tenant whose documents and projects the user works with
+    pub tenant_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -56,6 +59,7 @@ impl ActiveModelBehavior for ActiveModel {
             updated_at: Set(chrono::Utc::now()),
             is_active: Set(true),
             role: Set("user".to_string()),
+            tenant_id: Set(None),
             ..ActiveModelTrait::default()
         }
     }
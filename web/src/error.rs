@@ -78,6 +78,31 @@ struct ErrorDetails {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_code, error_message, details) = match &self {
+            AppError::Database(writemagic_shared::WritemagicError::FeatureDisabled { feature }) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "FEATURE_DISABLED",
+                format!("Feature disabled: {}", feature),
+                Some(serde_json::json!({ "feature": feature })),
+            ),
+            AppError::Database(e @ (writemagic_shared::WritemagicError::Conflict { .. }
+            | writemagic_shared::WritemagicError::VersionConflict { .. })) => (
+                StatusCode::CONFLICT,
+                "CONFLICT",
+                e.to_string(),
+                None,
+            ),
+            AppError::Database(writemagic_shared::WritemagicError::NotFound { resource }) => (
+                StatusCode::NOT_FOUND,
+                "NOT_FOUND",
+                format!("Resource not found: {}", resource),
+                None,
+            ),
+            AppError::Database(writemagic_shared::WritemagicError::Validation { message }) => (
+                StatusCode::BAD_REQUEST,
+                "VALIDATION_ERROR",
+                message.clone(),
+                None,
+            ),
             AppError::Database(e) => {
                 tracing::error!("Database error: {:?}", e);
                 (
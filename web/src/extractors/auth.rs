@@ -3,9 +3,11 @@ use axum::{
     http::{request::Parts, StatusCode},
     response::{IntoResponse, Response},
 };
+use crate::middleware::RequestLogContext;
 use crate::services::auth::UserInfo;
 use crate::state::AppState;
 use crate::utils::crypto::Claims;
+use writemagic_writing::TenantScope;
 
 /// Authenticated user extractor
 /// This extractor validates JWT tokens and provides user information
@@ -13,6 +15,7 @@ use crate::utils::crypto::Claims;
 pub struct AuthenticatedUser {
     pub user_id: String,
     pub username: String,
+    pub tenant_id: Option<String>,
     pub claims: Claims,
 }
 
@@ -21,10 +24,16 @@ impl AuthenticatedUser {
         Self {
             user_id: claims.sub.clone(),
             username: claims.username.clone(),
+            tenant_id: claims.tenant_id.clone(),
             claims,
         }
     }
 
+    /// Documents and projects this user may see
+    pub fn tenant_scope(&self) -> TenantScope {
+        TenantScope::from(self.tenant_id.clone())
+    }
+
     /// Convert to UserInfo for responses
     pub fn to_user_info(&self) -> UserInfo {
         UserInfo {
@@ -70,7 +79,12 @@ where
             return Err(AuthError::InvalidToken);
         }
 
-        Ok(AuthenticatedUser::new(claims))
+        let user = AuthenticatedUser::new(claims);
+        if let Some(context) = parts.extensions.get::<RequestLogContext>() {
+            context.record_user(&user.user_id);
+        }
+
+        Ok(user)
     }
 }
 
@@ -165,6 +179,7 @@ mod tests {
             iat: chrono::Utc::now().timestamp() as usize,
             jti: "test_jti".to_string(),
             token_type: TokenType::Access,
+            tenant_id: None,
         }
     }
 
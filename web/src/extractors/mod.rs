@@ -5,4 +5,4 @@ pub mod validated_json;
 // Re-exports for convenience
 pub use auth::AuthenticatedUser;
 pub use request_id::{request_id_middleware, RequestId};
-pub use validated_json::{Pagination, ValidatedJson};
\ No newline at end of file
+pub use validated_json::{FieldValidate, Pagination, ValidatedJson};
\ No newline at end of file
@@ -5,6 +5,26 @@ use axum::{
     response::Response,
 };
 use uuid::Uuid;
+use writemagic_shared::RequestContext;
+
+/// Longest client-supplied request ID that is accepted
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Request ID sent by the client, if it is short and made of safe characters
+///
+/// Anything else is ignored so an arbitrary header value never ends up in
+/// logs, spans and response headers.
+fn client_request_id(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        })
+        .map(String::from)
+}
 
 /// Request ID extractor
 /// Extracts the request ID from headers or generates a new one
@@ -36,11 +56,8 @@ where
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         // Try to extract request ID from headers
-        let request_id = parts
-            .headers
-            .get("x-request-id")
-            .and_then(|value| value.to_str().ok())
-            .map(|id| RequestId(id.to_string()))
+        let request_id = client_request_id(&parts.headers)
+            .map(RequestId)
             .unwrap_or_else(RequestId::new);
 
         Ok(request_id)
@@ -48,26 +65,27 @@ where
 }
 
 /// Middleware to add request ID to all requests
+///
+/// The handler runs inside a `RequestContext` carrying the id, so core service
+/// spans and AI provider logs for this request share it.
 pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
-    let request_id = request
-        .headers()
-        .get("x-request-id")
-        .and_then(|value| value.to_str().ok())
-        .map(String::from)
+    let request_id = client_request_id(request.headers())
         .unwrap_or_else(|| Uuid::new_v4().to_string());
 
     // Add request ID to request extensions for easy access
     request.extensions_mut().insert(RequestId(request_id.clone()));
 
-    // Add request ID to headers if not present
-    if !request.headers().contains_key("x-request-id") {
-        request.headers_mut().insert(
-            "x-request-id",
-            request_id.parse().expect("Generated UUID should be valid"),
-        );
-    }
+    // Replace a missing or rejected client ID in the headers
+    request.headers_mut().insert(
+        "x-request-id",
+        request_id.parse().expect("Request ID should be valid"),
+    );
 
-    let mut response = next.run(request).await;
+    // Core services pick the id up from the task-local request context
+    let operation = format!("{} {}", request.method(), request.uri().path());
+    let mut response = RequestContext::new(request_id.clone())
+        .scope(&operation, next.run(request))
+        .await;
 
     // Add request ID to response headers
     response.headers_mut().insert(
@@ -142,4 +160,45 @@ mod tests {
         let response_id = response.headers().get("x-request-id").unwrap();
         assert_eq!(response_id.to_str().unwrap(), existing_id);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_request_id_middleware_replaces_oversized_or_unsafe_ids() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(from_fn(request_id_middleware));
+
+        for rejected in ["x".repeat(MAX_REQUEST_ID_LEN + 1), "two words".to_string()] {
+            let request = Request::builder()
+                .uri("/")
+                .header("x-request-id", rejected.as_str())
+                .body(Body::empty())
+                .unwrap();
+
+            let response = app.clone().oneshot(request).await.unwrap();
+            let response_id = response.headers().get("x-request-id").unwrap().to_str().unwrap();
+            assert_ne!(response_id, rejected);
+            assert!(Uuid::parse_str(response_id).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_id_middleware_sets_request_context() {
+        async fn handler() -> String {
+            writemagic_shared::current_request_id().unwrap_or_default()
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(from_fn(request_id_middleware));
+
+        let request = Request::builder()
+            .uri("/")
+            .header("x-request-id", "trace-me")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"trace-me");
+    }
+}
@@ -7,17 +7,29 @@ use axum::{
 use garde::Validate;
 use serde::de::DeserializeOwned;
 use validator::Validate as ValidatorValidate;
+use writemagic_shared::validation::ValidationError as FieldError;
+
+/// Domain rules checked on a request payload before it reaches the service layer
+///
+/// Implementations call the `writemagic_shared::validation` functions for the
+/// fields they carry; the default accepts every payload.
+pub trait FieldValidate {
+    fn validate_fields(&self) -> Vec<FieldError> {
+        Vec::new()
+    }
+}
 
 
 /// JSON extractor with validation using `garde`
-/// This extractor deserializes JSON and validates it using garde validation rules
+/// This extractor deserializes JSON, validates it using garde validation rules
+/// and then checks the domain rules from [`FieldValidate`]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ValidatedJson<T>(pub T);
 
 #[axum::async_trait]
 impl<T, S> FromRequest<S> for ValidatedJson<T>
 where
-    T: DeserializeOwned + Validate,
+    T: DeserializeOwned + Validate + FieldValidate,
     T::Context: Default,
     S: Send + Sync,
 {
@@ -30,6 +42,11 @@ where
 
         value.validate().map_err(ValidationError::Validation)?;
 
+        let field_errors = value.validate_fields();
+        if !field_errors.is_empty() {
+            return Err(ValidationError::Fields(field_errors));
+        }
+
         Ok(ValidatedJson(value))
     }
 }
@@ -64,6 +81,7 @@ pub enum ValidationError {
     JsonExtraction(JsonRejection),
     Validation(garde::Report),
     ValidatorValidation(validator::ValidationErrors),
+    Fields(Vec<FieldError>),
 }
 
 impl IntoResponse for ValidationError {
@@ -114,6 +132,16 @@ impl IntoResponse for ValidationError {
                     Some(error_messages.join(", ")),
                 )
             }
+            ValidationError::Fields(errors) => {
+                let status = StatusCode::UNPROCESSABLE_ENTITY;
+                let body = serde_json::json!({
+                    "error": "VALIDATION_ERROR",
+                    "message": "Request validation failed",
+                    "fields": errors,
+                    "status": status.as_u16()
+                });
+                return (status, Json(body)).into_response();
+            }
         };
 
         let body = serde_json::json!({
@@ -245,4 +273,18 @@ mod tests {
         let response = json_error.into_response();
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_field_errors_are_returned_as_422() {
+        let error = ValidationError::Fields(vec![FieldError::new("title", "required", "must not be empty")]);
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["fields"][0]["field"], "title");
+        assert_eq!(json["fields"][0]["rule"], "required");
+        assert_eq!(json["fields"][0]["message"], "must not be empty");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,306 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tokio::sync::mpsc;
+use writemagic_ai::CircuitBreakerStatus;
+use writemagic_shared::{EntityId, FeatureFlagsSnapshot, FeatureFlagsUpdate, HistoryFilter, Timestamp};
+use writemagic_writing::{ExportFormat, ExportRange, SearchIndexProgress, DEFAULT_SEARCH_INDEX_CONCURRENCY};
+
+use crate::error::{AppError, Result as AppResult};
+use crate::extractors::auth::AdminUser;
+use crate::state::AppState;
+
+/// Query parameters of the export endpoints
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default = "default_export_format")]
+    pub format: ExportFormat,
+}
+
+fn default_export_format() -> ExportFormat {
+    ExportFormat::Json
+}
+
+impl ExportQuery {
+    fn range(&self) -> AppResult<ExportRange> {
+        if self.end < self.start {
+            return Err(AppError::BadRequest("Export range ends before it starts".to_string()));
+        }
+        Ok(ExportRange::new(self.start, self.end))
+    }
+}
+
+/// Query parameters of the completion history export
+#[derive(Debug, Deserialize)]
+pub struct CompletionHistoryExportQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default = "default_export_format")]
+    pub format: ExportFormat,
+    pub model: Option<String>,
+    pub document_id: Option<EntityId>,
+    /// Write prompt text for records that kept it
+    #[serde(default)]
+    pub include_prompts: bool,
+}
+
+impl CompletionHistoryExportQuery {
+    fn filter(&self) -> AppResult<HistoryFilter> {
+        if self.end < self.start {
+            return Err(AppError::BadRequest("Export range ends before it starts".to_string()));
+        }
+        Ok(HistoryFilter {
+            start: Some(Timestamp::from_datetime(self.start)),
+            end: Some(Timestamp::from_datetime(self.end)),
+            model: self.model.clone(),
+            document_id: self.document_id,
+            include_prompts: self.include_prompts,
+        })
+    }
+}
+
+/// Bytes gathered before an export chunk is sent to the client
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks of an export queued ahead of a slow client
+const EXPORT_QUEUED_CHUNKS: usize = 4;
+
+/// Writer sending an export to the response body in chunks, waiting while the client is behind
+struct ChunkWriter {
+    sender: mpsc::Sender<std::io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= EXPORT_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export client went away"))
+    }
+}
+
+/// Response streaming what `write` produces on a blocking thread
+///
+/// A failure part way through aborts the body, so the client never mistakes
+/// a truncated export for a complete one.
+fn streamed_export<F>(format: ExportFormat, write: F) -> Response
+where
+    F: FnOnce(&mut ChunkWriter) -> writemagic_shared::Result<()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(EXPORT_QUEUED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChunkWriter { sender: sender.clone(), buffer: Vec::with_capacity(EXPORT_CHUNK_SIZE) };
+        if let Err(e) = write(&mut writer).and_then(|()| writer.flush().map_err(Into::into)) {
+            tracing::error!("Export failed part way through: {}", e);
+            let _ = sender.blocking_send(Err(std::io::Error::other(e.to_string())));
+        }
+    });
+
+    let chunks = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    ([(header::CONTENT_TYPE, format.content_type())], Body::from_stream(chunks)).into_response()
+}
+
+/// Read the current runtime feature flags
+pub async fn get_feature_flags(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> AppResult<Json<FeatureFlagsSnapshot>> {
+    tracing::debug!("Reading feature flags for admin {}", admin.user.user_id);
+
+    Ok(Json(state.feature_flags.snapshot()))
+}
+
+/// Flip runtime feature flags; omitted flags keep their current value
+pub async fn update_feature_flags(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Json(update): Json<FeatureFlagsUpdate>,
+) -> AppResult<Json<FeatureFlagsSnapshot>> {
+    tracing::info!("Admin {} updating feature flags: {:?}", admin.user.user_id, update);
+
+    state.feature_flags.apply(&update);
+
+    Ok(Json(state.feature_flags.snapshot()))
+}
+
+/// Export security audit metadata for a time range
+pub async fn export_audit(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Query(query): Query<ExportQuery>,
+) -> AppResult<Response> {
+    tracing::info!("Admin {} exporting audit log: {:?}", admin.user.user_id, query);
+
+    let range = query.range()?;
+    let engine = state.core_engine.clone();
+    Ok(streamed_export(query.format, move |writer| engine.write_audit_export(range, query.format, writer)))
+}
+
+/// Export AI usage records for a time range
+pub async fn export_ai_usage(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Query(query): Query<ExportQuery>,
+) -> AppResult<Response> {
+    tracing::info!("Admin {} exporting AI usage: {:?}", admin.user.user_id, query);
+
+    let range = query.range()?;
+    let engine = state.core_engine.clone();
+    Ok(streamed_export(query.format, move |writer| engine.write_ai_usage_export(range, query.format, writer)))
+}
+
+/// Export AI completion history for a time range, optionally narrowed to a model or document
+pub async fn export_completion_history(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Query(query): Query<CompletionHistoryExportQuery>,
+) -> AppResult<Response> {
+    tracing::info!("Admin {} exporting completion history: {:?}", admin.user.user_id, query);
+
+    let filter = query.filter()?;
+    let engine = state.core_engine.clone();
+    let runtime = tokio::runtime::Handle::current();
+    Ok(streamed_export(query.format, move |writer| {
+        // History is read page by page from the database, so the runtime is
+        // entered for its queries while this thread waits on them; a plain
+        // executor keeps `ChunkWriter` free to block on a slow client.
+        let _runtime = runtime.enter();
+        futures::executor::block_on(engine.write_completion_history_export(filter, query.format, writer))
+    }))
+}
+
+/// Body of the provider control endpoint
+#[derive(Debug, Deserialize)]
+pub struct ProviderControlRequest {
+    pub enabled: bool,
+}
+
+/// Circuit breaker state of a provider after a manual change
+#[derive(Debug, Serialize)]
+pub struct ProviderCircuitResponse {
+    pub provider: String,
+    pub state: &'static str,
+    /// Milliseconds until a tripped provider is tried again on its own
+    pub retry_in_ms: Option<u64>,
+}
+
+impl ProviderCircuitResponse {
+    fn new(provider: String, status: &CircuitBreakerStatus) -> Self {
+        Self {
+            provider,
+            state: status.state.label(),
+            retry_in_ms: status.retry_in.map(|retry_in| retry_in.as_millis() as u64),
+        }
+    }
+}
+
+/// Trip or reset an AI provider's circuit breaker during an incident
+pub async fn set_provider_enabled(
+    State(state): State<AppState>,
+    admin: AdminUser,
+    Path(provider): Path<String>,
+    Json(request): Json<ProviderControlRequest>,
+) -> AppResult<Json<ProviderCircuitResponse>> {
+    tracing::warn!(
+        "Admin {} {} AI provider {}",
+        admin.user.user_id,
+        if request.enabled { "enabling" } else { "disabling" },
+        provider
+    );
+
+    let status = state.core_engine.set_provider_enabled(&provider, request.enabled)?;
+
+    Ok(Json(ProviderCircuitResponse::new(provider, &status)))
+}
+
+/// Rebuild the full-text search index, answering once it is done
+pub async fn rebuild_search_index(
+    State(state): State<AppState>,
+    admin: AdminUser,
+) -> AppResult<Json<SearchIndexProgress>> {
+    tracing::warn!("Admin {} rebuilding the search index", admin.user.user_id);
+
+    let progress = state.core_engine.rebuild_search_index(DEFAULT_SEARCH_INDEX_CONCURRENCY, |progress| {
+        tracing::debug!(
+            "Search index rebuild at {}/{}{}",
+            progress.indexed,
+            progress.total,
+            if progress.resumed { " (resumed)" } else { "" }
+        );
+    }).await?;
+
+    Ok(Json(progress))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_flag_update_deserializes() {
+        let update: FeatureFlagsUpdate = serde_json::from_str(r#"{"ai": false}"#).unwrap();
+        assert_eq!(update.ai, Some(false));
+
+        let update: FeatureFlagsUpdate = serde_json::from_str("{}").unwrap();
+        assert_eq!(update.ai, None);
+    }
+
+    #[test]
+    fn test_provider_circuit_response_reports_retry_time_in_millis() {
+        let status = CircuitBreakerStatus {
+            state: writemagic_ai::CircuitState::Open { opened_at: std::time::Instant::now() },
+            retry_in: Some(std::time::Duration::from_secs(30)),
+        };
+        let json = serde_json::to_value(ProviderCircuitResponse::new("claude".to_string(), &status)).unwrap();
+        assert_eq!(json, serde_json::json!({"provider": "claude", "state": "open", "retry_in_ms": 30000}));
+    }
+
+    #[test]
+    fn test_export_query_defaults_to_json_and_rejects_inverted_range() {
+        let query: ExportQuery = serde_json::from_str(
+            r#"{"start": "2025-03-02T00:00:00Z", "end": "2025-03-01T00:00:00Z"}"#,
+        ).unwrap();
+        assert_eq!(query.format, ExportFormat::Json);
+        assert!(matches!(query.range(), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_completion_history_query_builds_filter() {
+        let document_id = EntityId::new();
+        let query: CompletionHistoryExportQuery = serde_json::from_value(serde_json::json!({
+            "start": "2025-03-01T00:00:00Z",
+            "end": "2025-03-02T00:00:00Z",
+            "format": "jsonl",
+            "document_id": document_id,
+            "include_prompts": true,
+        })).unwrap();
+        assert_eq!(query.format, ExportFormat::Jsonl);
+
+        let filter = query.filter().unwrap();
+        assert_eq!(filter.document_id, Some(document_id));
+        assert!(filter.include_prompts);
+        assert_eq!(filter.model, None);
+    }
+}
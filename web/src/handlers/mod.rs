@@ -1,6 +1,6 @@
+pub mod admin;
 pub mod auth;
 pub mod documents;
+pub mod projects;
 
-// Additional handler modules will be added here as needed
-// For example:
-// pub mod projects;
\ No newline at end of file
+// Additional handler modules will be added here as needed
\ No newline at end of file
@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use garde::Validate;
+use serde::Deserialize;
+
+use crate::error::{AppError, Result as AppResult};
+use crate::extractors::{AuthenticatedUser, FieldValidate, ValidatedJson};
+use crate::state::AppState;
+use writemagic_shared::validation::{validate_project_name, ValidationError as FieldError};
+use writemagic_writing::{ProjectDto, ProjectName, TypeConverter};
+
+/// Web-specific project creation request
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateProjectRequest {
+    #[garde(length(min = 1, max = 255))]
+    pub name: String,
+
+    #[garde(skip)]
+    pub description: Option<String>,
+}
+
+impl FieldValidate for CreateProjectRequest {
+    fn validate_fields(&self) -> Vec<FieldError> {
+        validate_project_name(&self.name).err().into_iter().collect()
+    }
+}
+
+/// Web-specific project rename request
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenameProjectRequest {
+    #[garde(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+impl FieldValidate for RenameProjectRequest {
+    fn validate_fields(&self) -> Vec<FieldError> {
+        validate_project_name(&self.name).err().into_iter().collect()
+    }
+}
+
+/// Create a new project
+pub async fn create_project(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    ValidatedJson(request): ValidatedJson<CreateProjectRequest>,
+) -> AppResult<(StatusCode, Json<ProjectDto>)> {
+    tracing::info!("Creating project for user {}: {}", user.user_id, request.name);
+
+    let user_entity_id = TypeConverter::string_to_entity_id(&user.user_id)
+        .map_err(|e| AppError::BadRequest(format!("Invalid user ID: {}", e)))?;
+    let name = ProjectName::new(request.name).map_err(AppError::Database)?;
+
+    let project_aggregate = state.core_engine
+        .project_management_service()
+        .scoped(user.tenant_scope())
+        .create_project(name, request.description, Some(user_entity_id))
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok((StatusCode::CREATED, Json(ProjectDto::from_aggregate(&project_aggregate))))
+}
+
+/// Rename a project
+pub async fn rename_project(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(project_id): Path<String>,
+    ValidatedJson(request): ValidatedJson<RenameProjectRequest>,
+) -> AppResult<Json<ProjectDto>> {
+    tracing::info!("Renaming project {} for user {}", project_id, user.user_id);
+
+    let project_id = TypeConverter::string_to_entity_id(&project_id)
+        .map_err(|e| AppError::BadRequest(format!("Invalid project ID: {}", e)))?;
+    let user_entity_id = TypeConverter::string_to_entity_id(&user.user_id)
+        .map_err(|e| AppError::BadRequest(format!("Invalid user ID: {}", e)))?;
+    let name = ProjectName::new(request.name).map_err(AppError::Database)?;
+
+    let project_aggregate = state.core_engine
+        .project_management_service()
+        .scoped(user.tenant_scope())
+        .update_project_name(project_id, name, Some(user_entity_id))
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok(Json(ProjectDto::from_aggregate(&project_aggregate)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_requests_check_domain_rules() {
+        let request = CreateProjectRequest { name: "Novel".to_string(), description: None };
+        assert!(request.validate(&()).is_ok());
+        assert!(request.validate_fields().is_empty());
+
+        // Passes garde's length check but is blank once trimmed
+        let request = RenameProjectRequest { name: "   ".to_string() };
+        assert!(request.validate(&()).is_ok());
+        let errors = request.validate_fields();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "name");
+        assert_eq!(errors[0].rule, "required");
+    }
+}
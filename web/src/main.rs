@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 
 mod config;
 mod entities;
@@ -15,12 +16,17 @@ mod telemetry;
 mod websocket;
 
 use crate::{config::Config, state::AppState, routes::create_router};
+use writemagic_writing::core_engine::CoreEngine;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize structured tracing
     telemetry::init_tracing()?;
 
+    if std::env::args().skip(1).any(|arg| arg == "--self-check") {
+        run_self_check().await;
+    }
+
     // Load configuration
     let config = Config::from_env()?;
     
@@ -28,9 +34,21 @@ async fn main() -> anyhow::Result<()> {
     let state = AppState::new(config.clone()).await?;
     
     // Start background tasks
+    let background_shutdown = CancellationToken::new();
     let metrics_task = tokio::spawn(telemetry::metrics_collection_task(state.clone()));
     let rate_limit_cleanup_task = tokio::spawn(
-        crate::middleware::rate_limit::rate_limit_cleanup_task(state.rate_limiter.clone())
+        crate::middleware::rate_limit::rate_limit_cleanup_task(
+            state.rate_limiter.clone(),
+            state.metrics.clone(),
+            background_shutdown.clone(),
+        )
+    );
+    let idempotency_cleanup_task = tokio::spawn(
+        writemagic_writing::idempotency_cleanup_task(
+            state.core_engine.document_management_service(),
+            state.core_engine.config().idempotency.cleanup_interval(),
+            background_shutdown.clone().cancelled_owned(),
+        )
     );
     
     // Create router
@@ -47,7 +65,9 @@ async fn main() -> anyhow::Result<()> {
     );
     
     // Start server with graceful shutdown
-    let server = axum::serve(listener, app).with_graceful_shutdown(shutdown_signal());
+    // The peer address gates routes such as `/metrics` to internal networks
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal());
     
     // Wait for either the server to finish or background tasks to complete
     tokio::select! {
@@ -59,9 +79,15 @@ async fn main() -> anyhow::Result<()> {
         _ = metrics_task => {
             tracing::warn!("Metrics task completed unexpectedly");
         }
-        _ = rate_limit_cleanup_task => {
-            tracing::warn!("Rate limit cleanup task completed unexpectedly");
-        }
+    }
+    
+    // Stop background tasks and wait for them to finish
+    background_shutdown.cancel();
+    if let Err(e) = rate_limit_cleanup_task.await {
+        tracing::error!("Rate limit cleanup task failed: {}", e);
+    }
+    if let Err(e) = idempotency_cleanup_task.await {
+        tracing::error!("Idempotency key cleanup task failed: {}", e);
     }
     
     // Graceful shutdown
@@ -71,6 +97,31 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Run the engine self-check, print its report and exit non-zero on failure
+///
+/// Exits without dropping the engine, whose runtime can't be dropped from
+/// within this one.
+async fn run_self_check() -> ! {
+    let engine = match CoreEngine::initialize().await {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("[FAIL] startup: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match engine.self_check().await {
+        Ok(report) => {
+            print!("{}", report);
+            std::process::exit(if report.is_healthy() { 0 } else { 1 });
+        }
+        Err(e) => {
+            eprintln!("[FAIL] self-check: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         if let Err(e) = tokio::signal::ctrl_c().await {
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::{sync::Arc, time::Duration};
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// CORS layers by route group
+///
+/// A request gets the policy of the longest override prefix covering its
+/// path, or the default policy when none does.
+#[derive(Clone)]
+pub struct CorsRoutes {
+    default: CorsLayer,
+    /// Sorted longest prefix first
+    overrides: Vec<(String, CorsLayer)>,
+}
+
+impl CorsRoutes {
+    /// Build the layers of a validated `CorsConfig`
+    pub fn new(config: &CorsConfig) -> Self {
+        let max_age = Duration::from_secs(config.max_age_secs);
+        let mut overrides: Vec<(String, CorsLayer)> = config
+            .route_overrides
+            .iter()
+            .map(|route| {
+                let layer = cors_layer(&route.allowed_origins, route.allow_credentials, max_age);
+                (route.prefix().to_string(), layer)
+            })
+            .collect();
+        overrides.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        Self {
+            default: cors_layer(&config.allowed_origins, config.allow_credentials, max_age),
+            overrides,
+        }
+    }
+
+    fn layer_for(&self, path: &str) -> &CorsLayer {
+        self.overrides
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map_or(&self.default, |(_, layer)| layer)
+    }
+}
+
+fn cors_layer(origins: &[String], allow_credentials: bool, max_age: Duration) -> CorsLayer {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(origins.iter().map(|origin| origin.parse().expect("Invalid CORS origin")))
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            header::ACCEPT,
+            HeaderName::from_static(crate::handlers::documents::IDEMPOTENCY_KEY_HEADER),
+        ])
+        .allow_credentials(allow_credentials)
+        .max_age(max_age)
+}
+
+/// Apply the CORS policy of the route group a request falls in
+pub async fn cors_middleware(
+    State(routes): State<Arc<CorsRoutes>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cors = routes.layer_for(request.uri().path()).clone();
+    match cors.layer(next).oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CorsRouteOverride;
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get, Router};
+
+    fn config() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec!["http://localhost:3000".to_string()],
+            max_age_secs: 600,
+            allow_credentials: false,
+            route_overrides: vec![
+                CorsRouteOverride {
+                    path: "/api/v1/public/*".to_string(),
+                    allowed_origins: vec!["*".to_string()],
+                    allow_credentials: false,
+                },
+                CorsRouteOverride {
+                    path: "/api/v1/auth".to_string(),
+                    allowed_origins: vec!["https://app.writemagic.com".to_string()],
+                    allow_credentials: true,
+                },
+            ],
+        }
+    }
+
+    fn app(config: &CorsConfig) -> Router {
+        Router::new()
+            .route("/api/v1/public/stats", get(|| async { "OK" }))
+            .route("/api/v1/publications", get(|| async { "OK" }))
+            .route("/api/v1/auth/me", get(|| async { "OK" }))
+            .layer(from_fn_with_state(Arc::new(CorsRoutes::new(config)), cors_middleware))
+    }
+
+    async fn allowed_origin(method: Method, path: &str, origin: &str) -> Option<String> {
+        let mut request = Request::builder().method(method.clone()).uri(path).header(header::ORIGIN, origin);
+        if method == Method::OPTIONS {
+            request = request.header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET");
+        }
+
+        let response = app(&config()).oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_each_route_group_gets_its_own_origins() {
+        assert_eq!(allowed_origin(Method::GET, "/api/v1/public/stats", "https://other.example").await.as_deref(), Some("*"));
+
+        assert_eq!(allowed_origin(Method::GET, "/api/v1/auth/me", "https://other.example").await, None);
+        assert_eq!(
+            allowed_origin(Method::OPTIONS, "/api/v1/auth/me", "https://app.writemagic.com").await.as_deref(),
+            Some("https://app.writemagic.com")
+        );
+
+        // Prefixes match whole path segments only, so this falls back to the default
+        assert_eq!(allowed_origin(Method::GET, "/api/v1/publications", "https://other.example").await, None);
+        assert_eq!(
+            allowed_origin(Method::GET, "/api/v1/publications", "http://localhost:3000").await.as_deref(),
+            Some("http://localhost:3000")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_only_allowed_where_configured() {
+        let request = |path: &str| {
+            Request::builder()
+                .uri(path)
+                .header(header::ORIGIN, "https://app.writemagic.com")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let auth = app(&config()).oneshot(request("/api/v1/auth/me")).await.unwrap();
+        assert_eq!(auth.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+
+        let public = app(&config()).oneshot(request("/api/v1/public/stats")).await.unwrap();
+        assert!(public.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+
+    #[test]
+    fn test_validate_rejects_contradicting_overrides() {
+        assert!(config().validate(false).is_ok());
+
+        let mut credentialed_wildcard = config();
+        credentialed_wildcard.route_overrides[0].allow_credentials = true;
+        assert!(credentialed_wildcard.validate(false).is_err());
+
+        let mut plain_http = config();
+        plain_http.allowed_origins = vec!["https://writemagic.com".to_string()];
+        assert!(plain_http.validate(true).is_ok());
+        plain_http.route_overrides[1].allowed_origins = vec!["http://app.writemagic.com".to_string()];
+        assert!(plain_http.validate(true).is_err());
+
+        let mut duplicate = config();
+        duplicate.route_overrides[1].path = "/api/v1/public".to_string();
+        assert!(duplicate.validate(false).is_err());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod cors;
 pub mod rate_limit;
 pub mod request_id;
+pub mod request_log;
 
-pub use rate_limit::RateLimitState;
\ No newline at end of file
+pub use cors::{cors_middleware, CorsRoutes};
+pub use rate_limit::{rate_limit_middleware, RateLimitKeyStrategy, RateLimitState};
+pub use request_log::{request_log_middleware, RequestLogContext};
\ No newline at end of file
@@ -6,24 +6,78 @@ use axum::{
     Json,
 };
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use std::{
-    sync::Arc,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
+use tokio_util::sync::CancellationToken;
+use writemagic_shared::MetricsCollector;
+
+use crate::extractors::{auth::OptionalUser, RequestId};
+use crate::state::AppState;
+
+/// What each rate limit bucket is keyed by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKeyStrategy {
+    /// One bucket per client IP
+    PerIp,
+    /// One bucket per authenticated user, whichever IP it comes from
+    ///
+    /// Anonymous requests all share one bucket, so unauthenticated traffic as
+    /// a whole is held to a single limit however many IPs it comes from.
+    PerUser,
+    /// Authenticated users get their own bucket, anonymous requests are keyed by IP
+    #[default]
+    PerUserThenIp,
+}
 
-use crate::extractors::RequestId;
+impl RateLimitKeyStrategy {
+    /// Bucket key for a request, prefixed so user and IP buckets never collide
+    pub fn key(self, user_id: Option<&str>, client_ip: &str) -> String {
+        match (self, user_id) {
+            (Self::PerIp, _) | (Self::PerUserThenIp, None) => format!("ip:{}", client_ip),
+            (Self::PerUser, None) => "anonymous".to_string(),
+            (Self::PerUser | Self::PerUserThenIp, Some(user_id)) => format!("user:{}", user_id),
+        }
+    }
+}
+
+impl FromStr for RateLimitKeyStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "per_ip" => Ok(Self::PerIp),
+            "per_user" => Ok(Self::PerUser),
+            "per_user_then_ip" => Ok(Self::PerUserThenIp),
+            _ => Err(anyhow::anyhow!("Unknown rate limit key strategy: {}", s)),
+        }
+    }
+}
 
 /// Rate limiting state that tracks requests per IP/user
 #[derive(Clone)]
 pub struct RateLimitState {
-    /// Tracks rate limits by key (IP address or user ID)
+    /// Tracks rate limits by key (`ip:<address>` or `user:<id>`)
     limits: Arc<DashMap<String, RateLimitEntry>>,
+    /// How requests are mapped to buckets
+    key_strategy: RateLimitKeyStrategy,
     /// Maximum requests per window
     max_requests: u32,
     /// Time window for rate limiting
     window_duration: Duration,
-    /// How long to remember rate limit entries after they expire
+    /// How often the background task evicts expired entries
     cleanup_interval: Duration,
+    /// Total number of entries evicted by cleanup passes
+    evicted_total: Arc<AtomicU64>,
+    /// Total number of requests rejected by the limiter
+    limited_requests_total: Arc<AtomicU64>,
 }
 
 /// Individual rate limit entry
@@ -42,12 +96,33 @@ impl RateLimitState {
     pub fn new(max_requests: u32, window_seconds: u64) -> Self {
         Self {
             limits: Arc::new(DashMap::new()),
+            key_strategy: RateLimitKeyStrategy::default(),
             max_requests,
             window_duration: Duration::from_secs(window_seconds),
             cleanup_interval: Duration::from_secs(window_seconds * 2),
+            evicted_total: Arc::new(AtomicU64::new(0)),
+            limited_requests_total: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Set how often the background cleanup task runs; must not be zero
+    pub fn with_cleanup_interval(mut self, cleanup_interval: Duration) -> Self {
+        assert!(!cleanup_interval.is_zero(), "rate limit cleanup interval must not be zero");
+        self.cleanup_interval = cleanup_interval;
+        self
+    }
+
+    /// Choose what buckets are keyed by
+    pub fn with_key_strategy(mut self, key_strategy: RateLimitKeyStrategy) -> Self {
+        self.key_strategy = key_strategy;
+        self
+    }
+
+    /// Bucket key for a request from `client_ip`, made by `user_id` if authenticated
+    pub fn key_for(&self, user_id: Option<&str>, client_ip: &str) -> String {
+        self.key_strategy.key(user_id, client_ip)
+    }
+
     /// Check if a request should be rate limited
     pub fn check_rate_limit(&self, key: &str) -> RateLimitResult {
         let now = Instant::now();
@@ -70,6 +145,7 @@ impl RateLimitState {
 
         // Check if limit is exceeded
         if entry.count >= self.max_requests {
+            self.limited_requests_total.fetch_add(1, Ordering::Relaxed);
             let reset_time = entry.window_start + self.window_duration;
             let retry_after = reset_time.saturating_duration_since(now);
             
@@ -88,20 +164,54 @@ impl RateLimitState {
         }
     }
 
-    /// Clean up expired entries to prevent memory leaks
-    pub fn cleanup_expired(&self) {
-        let cutoff = Instant::now() - self.cleanup_interval;
-        self.limits.retain(|_key, entry| entry.last_access > cutoff);
+    /// Evict entries whose window has elapsed, returning how many were removed
+    ///
+    /// An entry past its window would be reset on the next request anyway, so
+    /// dropping it does not change limiting behaviour.
+    pub fn cleanup_expired(&self) -> usize {
+        let now = Instant::now();
+        let before = self.limits.len();
+        self.limits.retain(|_key, entry| now.duration_since(entry.window_start) < self.window_duration);
+        let evicted = before.saturating_sub(self.limits.len());
+
+        self.evicted_total.fetch_add(evicted as u64, Ordering::Relaxed);
+        evicted
     }
 
     /// Get current statistics
     pub fn stats(&self) -> RateLimitStats {
+        let now = Instant::now();
+        let limited_entries = self
+            .limits
+            .iter()
+            .filter(|entry| {
+                entry.count >= self.max_requests
+                    && now.duration_since(entry.window_start) < self.window_duration
+            })
+            .count();
+
+        let user_entries = self.limits.iter().filter(|entry| entry.key().starts_with("user:")).count();
+
         RateLimitStats {
             active_entries: self.limits.len(),
+            user_entries,
+            evicted_entries: self.evicted_total.load(Ordering::Relaxed),
+            limited_entries,
+            limited_requests_total: self.limited_requests_total.load(Ordering::Relaxed),
             max_requests: self.max_requests,
             window_seconds: self.window_duration.as_secs(),
+            cleanup_interval_seconds: self.cleanup_interval.as_secs(),
         }
     }
+
+    /// Publish current statistics to a metrics collector
+    pub async fn record_metrics(&self, metrics: &MetricsCollector) {
+        let stats = self.stats();
+        metrics.set_gauge("rate_limit_active_buckets", stats.active_entries as f64).await;
+        metrics.set_gauge("rate_limit_evicted_buckets_total", stats.evicted_entries as f64).await;
+        metrics.set_gauge("rate_limit_limited_buckets", stats.limited_entries as f64).await;
+        metrics.set_gauge("rate_limit_limited_requests_total", stats.limited_requests_total as f64).await;
+    }
 }
 
 /// Result of rate limit check
@@ -121,23 +231,36 @@ pub enum RateLimitResult {
 #[derive(Debug, serde::Serialize)]
 pub struct RateLimitStats {
     pub active_entries: usize,
+    /// Active buckets keyed by user; the rest are keyed by IP or shared by anonymous requests
+    pub user_entries: usize,
+    pub evicted_entries: u64,
+    pub limited_entries: usize,
+    pub limited_requests_total: u64,
     pub max_requests: u32,
     pub window_seconds: u64,
+    pub cleanup_interval_seconds: u64,
 }
 
 /// Rate limiting middleware
+///
+/// Buckets are keyed by the authenticated user and/or client IP according to
+/// the limiter's `RateLimitKeyStrategy`.
 pub async fn rate_limit_middleware(
-    State(rate_limiter): State<RateLimitState>,
+    State(state): State<AppState>,
     request_id: RequestId,
+    OptionalUser(user): OptionalUser,
     mut request: Request,
     next: Next,
 ) -> Result<Response, RateLimitError> {
+    let rate_limiter = &state.rate_limiter;
+
     // Extract client identifier (IP address from headers or connection info)
     let client_ip = extract_client_ip(request.headers())
         .unwrap_or_else(|| "unknown".to_string());
+    let key = rate_limiter.key_for(user.as_ref().map(|user| user.user_id.as_str()), &client_ip);
 
     // Check rate limit
-    let result = rate_limiter.check_rate_limit(&client_ip);
+    let result = rate_limiter.check_rate_limit(&key);
 
     match result {
         RateLimitResult::Allowed { remaining, reset_time } => {
@@ -170,8 +293,8 @@ pub async fn rate_limit_middleware(
         }
         RateLimitResult::Limited { retry_after, reset_time } => {
             tracing::warn!(
-                "Rate limit exceeded for client: {} (request_id: {})",
-                client_ip,
+                "Rate limit exceeded for {} (request_id: {})",
+                key,
                 request_id.get()
             );
 
@@ -245,6 +368,9 @@ impl IntoResponse for RateLimitError {
                 if let Ok(retry_header) = retry_after.to_string().parse() {
                     response.headers_mut().insert("retry-after", retry_header);
                 }
+                if let Ok(remaining_header) = "0".parse() {
+                    response.headers_mut().insert("x-ratelimit-remaining", remaining_header);
+                }
                 if let Ok(reset_header) = reset_time.to_string().parse() {
                     response.headers_mut().insert("x-ratelimit-reset", reset_header);
                 }
@@ -259,19 +385,33 @@ impl IntoResponse for RateLimitError {
 }
 
 /// Background task to clean up expired rate limit entries
-pub async fn rate_limit_cleanup_task(rate_limiter: RateLimitState) {
+///
+/// Runs until `shutdown` is cancelled.
+pub async fn rate_limit_cleanup_task(
+    rate_limiter: RateLimitState,
+    metrics: Arc<MetricsCollector>,
+    shutdown: CancellationToken,
+) {
     let cleanup_interval = rate_limiter.cleanup_interval;
     let mut interval = tokio::time::interval(cleanup_interval);
 
     loop {
-        interval.tick().await;
-        rate_limiter.cleanup_expired();
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        let evicted = rate_limiter.cleanup_expired();
+        rate_limiter.record_metrics(&metrics).await;
         
         tracing::debug!(
-            "Rate limiter cleanup completed. Active entries: {}",
+            "Rate limiter cleanup completed. Evicted: {}, active entries: {}",
+            evicted,
             rate_limiter.limits.len()
         );
     }
+
+    tracing::info!("Rate limit cleanup task stopped");
 }
 
 #[cfg(test)]
@@ -328,6 +468,88 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_cleanup_evicts_expired_buckets() {
+        let rate_limiter = RateLimitState::new(5, 1);
+
+        rate_limiter.check_rate_limit("stale_client");
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        rate_limiter.check_rate_limit("fresh_client");
+
+        assert_eq!(rate_limiter.cleanup_expired(), 1);
+        assert!(!rate_limiter.limits.contains_key("stale_client"));
+        assert!(rate_limiter.limits.contains_key("fresh_client"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_reflect_active_and_evicted_buckets() {
+        let rate_limiter = RateLimitState::new(1, 1);
+        let metrics = MetricsCollector::new();
+
+        rate_limiter.check_rate_limit("stale_client");
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        rate_limiter.check_rate_limit("limited_client");
+        rate_limiter.check_rate_limit("limited_client");
+        rate_limiter.cleanup_expired();
+
+        let stats = rate_limiter.stats();
+        assert_eq!(stats.active_entries, 1);
+        assert_eq!(stats.evicted_entries, 1);
+        assert_eq!(stats.limited_entries, 1);
+        assert_eq!(stats.limited_requests_total, 1);
+
+        rate_limiter.record_metrics(&metrics).await;
+        let exported = metrics.export_json().await;
+        assert_eq!(exported["gauges"]["rate_limit_active_buckets"], 1.0);
+        assert_eq!(exported["gauges"]["rate_limit_evicted_buckets_total"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_task_stops_on_shutdown() {
+        let rate_limiter = RateLimitState::new(5, 60).with_cleanup_interval(Duration::from_millis(10));
+        let shutdown = CancellationToken::new();
+
+        let handle = tokio::spawn(rate_limit_cleanup_task(
+            rate_limiter,
+            Arc::new(MetricsCollector::new()),
+            shutdown.clone(),
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.cancel();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        assert!(matches!(result, Ok(Ok(()))));
+    }
+
+    #[tokio::test]
+    async fn test_user_and_ip_buckets_are_separate_and_both_expire() {
+        let rate_limiter = RateLimitState::new(1, 1);
+        let behind_nat = "198.51.100.7";
+
+        let alice = rate_limiter.key_for(Some("alice"), behind_nat);
+        let bob = rate_limiter.key_for(Some("bob"), behind_nat);
+        let anonymous = rate_limiter.key_for(None, behind_nat);
+        assert_eq!((alice.as_str(), anonymous.as_str()), ("user:alice", "ip:198.51.100.7"));
+
+        rate_limiter.check_rate_limit(&alice);
+        assert!(matches!(rate_limiter.check_rate_limit(&alice), RateLimitResult::Limited { .. }));
+        assert!(matches!(rate_limiter.check_rate_limit(&bob), RateLimitResult::Allowed { .. }));
+        assert!(matches!(rate_limiter.check_rate_limit(&anonymous), RateLimitResult::Allowed { .. }));
+        assert_eq!(rate_limiter.stats().user_entries, 2);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(rate_limiter.cleanup_expired(), 3);
+
+        assert_eq!(RateLimitKeyStrategy::PerIp.key(Some("alice"), behind_nat), "ip:198.51.100.7");
+        // Anonymous clients share one bucket per user, but get one per IP when users fall back to IPs
+        assert_eq!(RateLimitKeyStrategy::PerUser.key(None, behind_nat), "anonymous");
+        assert_eq!(RateLimitKeyStrategy::PerUser.key(None, "203.0.113.9"), RateLimitKeyStrategy::PerUser.key(None, behind_nat));
+        assert_ne!(RateLimitKeyStrategy::PerUserThenIp.key(None, "203.0.113.9"), RateLimitKeyStrategy::PerUserThenIp.key(None, behind_nat));
+        assert_eq!(RateLimitKeyStrategy::PerUser.key(Some("alice"), behind_nat), "user:alice");
+        assert_eq!("per_user".parse::<RateLimitKeyStrategy>().unwrap(), RateLimitKeyStrategy::PerUser);
+    }
+
     #[test]
     fn test_client_ip_extraction() {
         let mut headers = HeaderMap::new();
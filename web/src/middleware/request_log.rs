@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::config::RequestLogConfig;
+use crate::extractors::RequestId;
+
+/// Tracing target of the per-request log events
+pub const REQUEST_LOG_TARGET: &str = "writemagic_web::request";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Request details filled in while the request is handled
+///
+/// The logging middleware places this in the request extensions, and the
+/// `AuthenticatedUser` extractor records the user it authenticated.
+#[derive(Debug, Clone, Default)]
+pub struct RequestLogContext {
+    user_id: Arc<Mutex<Option<String>>>,
+}
+
+impl RequestLogContext {
+    pub fn record_user(&self, user_id: &str) {
+        if let Ok(mut slot) = self.user_id.lock() {
+            *slot = Some(user_id.to_string());
+        }
+    }
+
+    pub fn user_id(&self) -> Option<String> {
+        self.user_id.lock().ok().and_then(|slot| slot.clone())
+    }
+}
+
+/// Emit one structured event per request with its method, path, status,
+/// latency, user and request id
+pub async fn request_log_middleware(
+    State(config): State<Arc<RequestLogConfig>>,
+    request_id: RequestId,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !config.enabled {
+        return next.run(request).await;
+    }
+
+    let sampled = config.sample_rate >= 1.0 || rand::random::<f64>() < config.sample_rate;
+    if !sampled && !config.always_log_errors {
+        return next.run(request).await;
+    }
+
+    let context = RequestLogContext::default();
+    request.extensions_mut().insert(context.clone());
+
+    let method = request.method().clone();
+    // The query string is left out as it may carry tokens
+    let path = request.uri().path().to_string();
+    let headers = config
+        .include_headers
+        .then(|| redact_headers(request.headers(), &config.redacted_headers));
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let status = response.status();
+    if !sampled && !status.is_server_error() {
+        return response;
+    }
+
+    let user_id = context.user_id();
+    tracing::info!(
+        target: REQUEST_LOG_TARGET,
+        method = %method,
+        path = %path,
+        status = status.as_u16(),
+        latency_ms,
+        user_id = user_id.as_deref().unwrap_or("anonymous"),
+        request_id = request_id.get(),
+        headers = headers.as_deref().unwrap_or(""),
+        "request completed"
+    );
+
+    response
+}
+
+/// Render headers as `name=value` pairs, hiding sensitive values
+fn redact_headers(headers: &HeaderMap, redacted: &[String]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if redacted.iter().any(|r| r.eq_ignore_ascii_case(name.as_str())) {
+                REDACTED
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}={}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractors::request_id_middleware;
+    use axum::{
+        body::Body,
+        extract::Extension,
+        middleware::{from_fn, from_fn_with_state},
+        routing::get,
+        Router,
+    };
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::{layer::{Context, SubscriberExt}, Layer};
+
+    type Events = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+    /// Collects the fields of every request log event
+    struct CaptureLayer(Events);
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for CaptureLayer {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            if event.metadata().target() == REQUEST_LOG_TARGET {
+                let mut fields = HashMap::new();
+                event.record(&mut FieldVisitor(&mut fields));
+                self.0.lock().unwrap().push(fields);
+            }
+        }
+    }
+
+    fn app(config: RequestLogConfig) -> Router {
+        // Stands in for a handler taking `AuthenticatedUser`
+        async fn handler(Extension(context): Extension<RequestLogContext>) -> &'static str {
+            context.record_user("user-42");
+            "OK"
+        }
+
+        Router::new()
+            .route("/documents", get(handler))
+            .layer(from_fn_with_state(Arc::new(config), request_log_middleware))
+            .layer(from_fn(request_id_middleware))
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> (Response, Vec<HashMap<String, String>>) {
+        let events = Events::default();
+        let subscriber = tracing_subscriber::registry().with(CaptureLayer(events.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let response = app.oneshot(request).await.unwrap();
+        let captured = events.lock().unwrap().clone();
+        (response, captured)
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_request_logs_expected_fields() {
+        let config = RequestLogConfig { include_headers: true, ..RequestLogConfig::default() };
+        let request = Request::builder()
+            .uri("/documents?token=secret")
+            .header("authorization", "Bearer secret-token")
+            .body(Body::empty())
+            .unwrap();
+
+        let (response, events) = send(app(config), request).await;
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event["method"], "GET");
+        assert_eq!(event["path"], "/documents");
+        assert_eq!(event["status"], "200");
+        assert!(event.contains_key("latency_ms"));
+        assert_eq!(event["user_id"], "user-42");
+        assert!(event["headers"].contains("authorization=[REDACTED]"));
+        assert!(!event["headers"].contains("secret-token"));
+
+        let response_id = response.headers().get("x-request-id").unwrap().to_str().unwrap();
+        assert_eq!(event["request_id"], response_id);
+    }
+
+    #[tokio::test]
+    async fn test_unsampled_successful_request_is_not_logged() {
+        let config = RequestLogConfig { sample_rate: 0.0, ..RequestLogConfig::default() };
+        let request = Request::builder()
+            .uri("/documents")
+            .header("x-request-id", "client-supplied-id")
+            .body(Body::empty())
+            .unwrap();
+
+        let (response, events) = send(app(config), request).await;
+
+        assert!(events.is_empty());
+        assert_eq!(response.headers().get("x-request-id").unwrap(), "client-supplied-id");
+    }
+}
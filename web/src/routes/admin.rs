@@ -0,0 +1,18 @@
+use axum::{
+    routing::{get, post, put},
+    Router,
+};
+
+use crate::{handlers::admin, state::AppState};
+
+/// Create administration routes
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/flags", get(admin::get_feature_flags))
+        .route("/flags", post(admin::update_feature_flags))
+        .route("/exports/audit", get(admin::export_audit))
+        .route("/exports/ai-usage", get(admin::export_ai_usage))
+        .route("/exports/completions", get(admin::export_completion_history))
+        .route("/providers/:provider", put(admin::set_provider_enabled))
+        .route("/search-index/rebuild", post(admin::rebuild_search_index))
+}
@@ -1,13 +1,14 @@
 use axum::Router;
 
-use crate::{routes::{auth, documents}, state::AppState};
+use crate::{routes::{admin, auth, documents, projects}, state::AppState};
 
 /// Create API v1 routes
 pub fn router() -> Router<AppState> {
     Router::new()
         .nest("/auth", auth::router())
         .nest("/documents", documents::router())
+        .nest("/admin", admin::router())
+        .nest("/projects", projects::router())
         // Add more API endpoints here as they are implemented
-        // .nest("/projects", projects::router())
         // .nest("/ai", ai::router())
 }
\ No newline at end of file
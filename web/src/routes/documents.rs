@@ -1,5 +1,5 @@
 use axum::{
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
@@ -10,7 +10,13 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(documents::list_documents))
         .route("/", post(documents::create_document))
+        .route("/", patch(documents::move_documents))
+        .route("/query", get(documents::query_documents))
+        .route("/import", post(documents::import_documents))
         .route("/:id", get(documents::get_document))
         .route("/:id", put(documents::update_document))
         .route("/:id", delete(documents::delete_document))
+        .route("/:id/tags", put(documents::set_document_tags))
+        .route("/:id/links", get(documents::get_outgoing_links))
+        .route("/:id/backlinks", get(documents::get_backlinks))
 }
\ No newline at end of file
@@ -1,18 +1,33 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use writemagic_shared::{ComponentHealth, HealthChecker, ReadinessReport};
 
-use crate::{error::Result, state::AppState, telemetry::MetricsCollector};
+use crate::{
+    error::{AppError, Result},
+    state::AppState,
+    telemetry::MetricsCollector,
+};
 
 /// Health check routes
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/health", get(health_check))
+        .route("/health/live", get(health_check))
         .route("/health/ready", get(readiness_check))
         .route("/metrics", get(metrics_endpoint))
 }
 
-/// Basic health check endpoint
-/// Returns 200 OK if the service is running
+/// Liveness probe
+/// Returns 200 OK if the service is running, without touching dependencies
 async fn health_check() -> impl IntoResponse {
     (
         StatusCode::OK,
@@ -25,39 +40,146 @@ async fn health_check() -> impl IntoResponse {
     )
 }
 
-/// Readiness check endpoint  
-/// Returns 200 OK if the service is ready to accept traffic
-/// This includes checking database connectivity and other dependencies
+/// Readiness check endpoint
+///
+/// Probes the databases and AI providers, each bounded by the configured
+/// health check timeout. Returns 503 if a critical component is down.
 async fn readiness_check(State(state): State<AppState>) -> Result<impl IntoResponse> {
-    let collector = MetricsCollector::new(state);
-    let health = collector.health_check().await;
-    
-    let status_code = if health.healthy {
+    let checker = HealthChecker::new().with_timeout(state.config.server.health_check_timeout());
+    let mut components = BTreeMap::new();
+
+    let web_database = checker.check_component(true, async {
+        use sea_orm::{ConnectionTrait, Statement};
+        state.db
+            .execute(Statement::from_string(sea_orm::DatabaseBackend::Sqlite, "SELECT 1".to_string()))
+            .await
+            .map(|_| ())
+    });
+    components.insert("database".to_string(), web_database.await);
+
+    if let Some(database) = state.core_engine.database_manager() {
+        components.insert("document_store".to_string(), checker.check_database(database).await);
+    }
+
+    for (name, health) in checker.check_ai_providers(state.core_engine.check_ai_provider_health()).await {
+        components.insert(format!("ai:{}", name), health);
+    }
+
+    let rate_limiter_entries = state.rate_limiter.stats().active_entries;
+    components.insert(
+        "rate_limiter".to_string(),
+        if rate_limiter_entries < 100_000 {
+            ComponentHealth::healthy(false, Default::default())
+        } else {
+            ComponentHealth::unhealthy(false, format!("{} tracked clients", rate_limiter_entries), Default::default())
+        },
+    );
+
+    let cache_entries = state.cache.len();
+    components.insert(
+        "cache".to_string(),
+        if cache_entries < 50_000 {
+            ComponentHealth::healthy(false, Default::default())
+        } else {
+            ComponentHealth::unhealthy(false, format!("{} cached entries", cache_entries), Default::default())
+        },
+    );
+
+    let report = ReadinessReport::new(components);
+    let status_code = if report.is_ready() {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
     };
-    
+
     Ok((
         status_code,
         Json(json!({
-            "status": if health.healthy { "ready" } else { "not_ready" },
-            "checks": {
-                "database": health.database,
-                "rate_limiter": health.rate_limiter,
-                "cache": health.cache,
-            },
+            "status": if report.is_ready() { "ready" } else { "not_ready" },
+            "health": report.status,
+            "components": report.components,
             "service": "writemagic-web",
-            "version": health.version,
-            "timestamp": health.timestamp.to_rfc3339()
+            "version": env!("CARGO_PKG_VERSION"),
+            "timestamp": chrono::Utc::now().to_rfc3339()
         })),
     ))
 }
 
+/// Format of the `/metrics` response
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MetricsFormat {
+    #[default]
+    Json,
+    Prometheus,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricsQuery {
+    #[serde(default)]
+    format: MetricsFormat,
+}
+
 /// Metrics endpoint for application monitoring
-async fn metrics_endpoint(State(state): State<AppState>) -> impl IntoResponse {
-    let collector = MetricsCollector::new(state);
-    let metrics = collector.get_metrics();
-    
-    (StatusCode::OK, Json(metrics))
+///
+/// JSON by default: the application metrics with the operation snapshot
+/// under `metrics`. Prometheus text is opt-in with `?format=prometheus`.
+/// Only peers in the configured internal networks may scrape it.
+async fn metrics_endpoint(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Response> {
+    let security = &state.config.security;
+    if !security.metrics_enabled {
+        return Err(AppError::NotFound("metrics".to_string()));
+    }
+    match peer {
+        Some(ConnectInfo(addr)) if security.metrics_allowed_from(addr.ip()) => {}
+        _ => return Err(AppError::Forbidden),
+    }
+
+    refresh_scrape_gauges(&state).await;
+    let snapshot = state.metrics.snapshot().await;
+
+    match query.format {
+        MetricsFormat::Json => {
+            let mut body = serde_json::to_value(MetricsCollector::new(state).get_metrics())?;
+            body["metrics"] = serde_json::to_value(&snapshot)?;
+            Ok((StatusCode::OK, Json(body)).into_response())
+        }
+        MetricsFormat::Prometheus => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            snapshot.to_prometheus(),
+        )
+            .into_response()),
+    }
+}
+
+/// Update the gauges read from live state rather than recorded as events
+async fn refresh_scrape_gauges(state: &AppState) {
+    state
+        .metrics
+        .set_gauge("websocket_active_connections", state.connection_manager.connection_count() as f64)
+        .await;
+    if let Some(database) = state.core_engine.database_manager() {
+        database.record_pool_metrics(&state.metrics).await;
+    }
+
+    // One series per provider and state, set to 1 for the state it is in
+    let breakers = state.core_engine.get_circuit_breaker_states().unwrap_or_default();
+    for (provider, status) in breakers {
+        for circuit_state in ["closed", "open", "half_open"] {
+            let value = if status.state.label() == circuit_state { 1.0 } else { 0.0 };
+            state
+                .metrics
+                .set_labeled_gauge(
+                    "ai_circuit_breaker_state",
+                    &[("provider", provider.as_str()), ("state", circuit_state)],
+                    value,
+                )
+                .await;
+        }
+    }
 }
\ No newline at end of file
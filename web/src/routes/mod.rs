@@ -1,45 +1,36 @@
 use axum::{
-    http::Method,
     middleware,
     Router,
 };
 use tower_http::{
     compression::CompressionLayer,
-    cors::CorsLayer,
     limit::RequestBodyLimitLayer,
     timeout::TimeoutLayer,
-    trace::{DefaultMakeSpan, DefaultOnResponse, DefaultOnRequest, TraceLayer},
+    trace::{DefaultMakeSpan, TraceLayer},
 };
+use std::sync::Arc;
 use tracing::Level;
 
 use crate::{
     extractors::request_id_middleware,
+    middleware::{cors_middleware, rate_limit_middleware, request_log_middleware, CorsRoutes},
     state::AppState,
+    telemetry::request_metrics_middleware,
     websocket,
 };
 
+pub mod admin;
 pub mod api;
 pub mod auth;
 pub mod documents;
 pub mod health;
+pub mod projects;
 
 /// Create the main application router with all middleware and routes
 /// Following the middleware layering order from the best practices guide
 pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(
-            state.config.cors.allowed_origins
-                .iter()
-                .map(|origin| origin.parse().expect("Invalid CORS origin"))
-                .collect::<Vec<_>>()
-        )
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH])
-        .allow_headers([
-            axum::http::header::CONTENT_TYPE,
-            axum::http::header::AUTHORIZATION,
-            axum::http::header::ACCEPT,
-        ])
-        .max_age(std::time::Duration::from_secs(state.config.cors.max_age_secs));
+    // Route groups such as public reads can override the default CORS policy
+    let cors = Arc::new(CorsRoutes::new(&state.config.cors));
 
     Router::new()
         .merge(health::router())
@@ -47,16 +38,23 @@ pub fn create_router(state: AppState) -> Router {
         .merge(websocket::handler::websocket_routes())
         // Add more route modules here as they are implemented
         // Apply middleware layers in the correct order
-        .layer(cors)
+        .layer(middleware::from_fn_with_state(cors, cors_middleware))
         .layer(RequestBodyLimitLayer::new(state.config.server.body_limit_bytes))
         .layer(CompressionLayer::new())
         .layer(TimeoutLayer::new(state.config.server.request_timeout()))
         .layer(
+            // Requests are logged once by `request_log_middleware`
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                .on_request(DefaultOnRequest::new().level(Level::INFO))
-                .on_response(DefaultOnResponse::new().level(Level::INFO))
+                .on_request(())
+                .on_response(())
         )
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
+        .layer(middleware::from_fn_with_state(state.metrics.clone(), request_metrics_middleware))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(state.config.request_log.clone()),
+            request_log_middleware,
+        ))
         .layer(middleware::from_fn(request_id_middleware))
         .with_state(state)
 }
\ No newline at end of file
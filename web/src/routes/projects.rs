@@ -0,0 +1,13 @@
+use axum::{
+    routing::{post, put},
+    Router,
+};
+
+use crate::{handlers::projects, state::AppState};
+
+/// Create project management routes
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(projects::create_project))
+        .route("/:id/name", put(projects::rename_project))
+}
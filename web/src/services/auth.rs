@@ -93,13 +93,14 @@ impl AuthService {
             updated_at: Set(chrono::Utc::now()),
             is_active: Set(true),
             role: Set("user".to_string()),
+            tenant_id: Set(None),
         };
 
         let user = user_model.insert(&state.db).await
             .map_err(|e| AppError::Database(writemagic_shared::WritemagicError::database(format!("Failed to create user: {}", e))))?;
 
         // Generate tokens
-        let tokens = TokenManager::generate_token_pair(&self.jwt_keys, &user.id, &user.username)?;
+        let tokens = TokenManager::generate_token_pair(&self.jwt_keys, &user.id, &user.username, user.tenant_id.as_deref())?;
 
         Ok(AuthResponse {
             user: UserInfo::from(user),
@@ -120,7 +121,7 @@ impl AuthService {
         }
 
         // Generate tokens
-        let tokens = TokenManager::generate_token_pair(&self.jwt_keys, &user.id, &user.username)?;
+        let tokens = TokenManager::generate_token_pair(&self.jwt_keys, &user.id, &user.username, user.tenant_id.as_deref())?;
 
         Ok(AuthResponse {
             user: UserInfo::from(user),
@@ -3,6 +3,7 @@ use std::time::Duration;
 use dashmap::DashMap;
 use sea_orm::{Database, DatabaseConnection};
 use sea_orm_migration::MigratorTrait;
+use writemagic_shared::{FeatureFlags, MetricsCollector};
 use writemagic_writing::core_engine::CoreEngine;
 use migration;
 use crate::config::Config;
@@ -31,6 +32,10 @@ pub struct AppState {
     pub rate_limiter: RateLimitState,
     /// WebSocket connection manager
     pub connection_manager: ConnectionManager,
+    /// Metrics collector for background tasks and middleware
+    pub metrics: Arc<MetricsCollector>,
+    /// Runtime feature flags shared with the core engine
+    pub feature_flags: Arc<FeatureFlags>,
 }
 
 /// Cached value with expiration
@@ -79,14 +84,25 @@ impl AppState {
         // Create cache with reasonable capacity
         let cache = Arc::new(DashMap::with_capacity(10_000));
         
-        // Initialize rate limiter (100 requests per minute)
-        let rate_limiter = RateLimitState::new(100, 60);
+        // Initialize rate limiter from configuration
+        let rate_limiter = RateLimitState::new(
+            config.rate_limit.max_requests,
+            config.rate_limit.window_secs,
+        )
+        .with_cleanup_interval(config.rate_limit.cleanup_interval())
+        .with_key_strategy(config.rate_limit.key_strategy);
         
         // Initialize WebSocket connection manager
-        let connection_manager = ConnectionManager::new();
+        let connection_manager = ConnectionManager::new()
+            .with_document_service(core_engine.document_management_service())
+            .with_completions(core_engine.clone(), rate_limiter.clone());
+        connection_manager.relay_document_changes(&core_engine.event_bus()).await;
         
         tracing::info!("Application state initialized successfully");
         
+        let feature_flags = core_engine.feature_flags();
+        let metrics = core_engine.metrics();
+
         Ok(Self {
             core_engine,
             db,
@@ -96,6 +112,8 @@ impl AppState {
             jwt_keys,
             rate_limiter,
             connection_manager,
+            metrics,
+            feature_flags,
         })
     }
     
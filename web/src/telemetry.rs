@@ -1,9 +1,10 @@
 use axum::{
-    extract::{Request, State},
+    extract::{MatchedPath, Request, State},
     http::StatusCode,
     middleware::Next,
     response::Response,
 };
+use std::sync::Arc;
 use std::time::Instant;
 use tracing::{info_span, Instrument};
 
@@ -100,6 +101,38 @@ pub async fn trace_middleware(
     .await
 }
 
+/// Count requests by route, method and status and record their latency
+///
+/// Routes are labelled with their template, e.g. `/api/documents/:id`, to
+/// keep the number of series bounded.
+pub async fn request_metrics_middleware(
+    State(metrics): State<Arc<writemagic_shared::MetricsCollector>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+
+    metrics
+        .increment_labeled_counter(
+            "http_requests_total",
+            &[("route", route.as_str()), ("method", method.as_str()), ("status", status.as_str())],
+            1,
+        )
+        .await;
+    metrics.record_latency(&format!("http {} {}", method, route), started.elapsed()).await;
+
+    response
+}
+
 /// Extract client IP from request headers
 fn extract_client_ip(headers: &axum::http::HeaderMap) -> String {
     let ip_headers = [
@@ -190,6 +223,8 @@ impl MetricsCollector {
             active_connections,
             cache_entries: cache.len(),
             rate_limit_entries: rate_limit_stats.active_entries,
+            rate_limit_evicted_entries: rate_limit_stats.evicted_entries,
+            rate_limit_limited_entries: rate_limit_stats.limited_entries,
             uptime_seconds: 0, // Would track actual uptime in real implementation
         }
     }
@@ -240,6 +275,8 @@ pub struct ApplicationMetrics {
     pub active_connections: u64,
     pub cache_entries: usize,
     pub rate_limit_entries: usize,
+    pub rate_limit_evicted_entries: u64,
+    pub rate_limit_limited_entries: usize,
     pub uptime_seconds: u64,
 }
 
@@ -269,6 +306,8 @@ pub async fn metrics_collection_task(app_state: AppState) {
         tracing::info!(
             cache_entries = metrics.cache_entries,
             rate_limit_entries = metrics.rate_limit_entries,
+            rate_limit_evicted_entries = metrics.rate_limit_evicted_entries,
+            rate_limit_limited_entries = metrics.rate_limit_limited_entries,
             healthy = health.healthy,
             "Metrics collected"
         );
@@ -18,6 +18,8 @@ pub struct Claims {
     pub iat: usize,        // Issued at
     pub jti: String,       // JWT ID for revocation
     pub token_type: TokenType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>, // Tenant the user's data belongs to
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -97,6 +99,7 @@ impl TokenManager {
         keys: &JwtKeys,
         user_id: &str,
         username: &str,
+        tenant_id: Option<&str>,
     ) -> AppResult<TokenPair> {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -112,6 +115,7 @@ impl TokenManager {
             iat: now,
             jti: Uuid::new_v4().to_string(),
             token_type: TokenType::Access,
+            tenant_id: tenant_id.map(str::to_string),
         };
 
         // Refresh token (7 days)
@@ -123,6 +127,7 @@ impl TokenManager {
             iat: now,
             jti: Uuid::new_v4().to_string(),
             token_type: TokenType::Refresh,
+            tenant_id: tenant_id.map(str::to_string),
         };
 
         let access_token = encode(&Header::default(), &access_claims, &keys.encoding)
@@ -168,7 +173,7 @@ impl TokenManager {
         }
 
         // Generate new token pair
-        Self::generate_token_pair(keys, &claims.sub, &claims.username)
+        Self::generate_token_pair(keys, &claims.sub, &claims.username, claims.tenant_id.as_deref())
     }
 }
 
@@ -200,7 +205,7 @@ mod tests {
         let username = "testuser";
         
         // Generate token pair
-        let token_pair = TokenManager::generate_token_pair(&keys, user_id, username).unwrap();
+        let token_pair = TokenManager::generate_token_pair(&keys, user_id, username, None).unwrap();
         assert!(!token_pair.access_token.is_empty());
         assert!(!token_pair.refresh_token.is_empty());
         assert_eq!(token_pair.expires_in, 15 * 60);
@@ -225,7 +230,7 @@ mod tests {
         let username = "testuser";
         
         // Generate initial token pair
-        let initial_tokens = TokenManager::generate_token_pair(&keys, user_id, username).unwrap();
+        let initial_tokens = TokenManager::generate_token_pair(&keys, user_id, username, None).unwrap();
         
         // Refresh using refresh token
         let new_tokens = TokenManager::refresh_token(&keys, &initial_tokens.refresh_token).unwrap();
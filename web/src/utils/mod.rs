@@ -1,4 +1,5 @@
 pub mod crypto;
+pub mod network;
 
 // Additional utility modules will be added here as needed
 // For example:
@@ -0,0 +1,87 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8`
+///
+/// A bare address is a network of just that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Whether `ip` lies in this network; IPv4-mapped IPv6 addresses match IPv4 networks
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(network) as u128, u32::from(ip) as u128, 32, self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(network), u128::from(ip), 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    let shift = u32::from(bits - prefix_len);
+    network >> shift == ip >> shift
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| format!("Invalid network address: {}", s))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| format!("Invalid network prefix length: {}", s))?,
+            None => max_prefix_len,
+        };
+
+        Ok(Self { address, prefix_len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_membership() {
+        let private: IpNetwork = "172.16.0.0/12".parse().unwrap();
+        assert!(private.contains("172.31.255.1".parse().unwrap()));
+        assert!(!private.contains("172.32.0.1".parse().unwrap()));
+        assert!(private.contains("::ffff:172.16.0.9".parse().unwrap()));
+
+        let loopback: IpNetwork = "::1".parse().unwrap();
+        assert!(loopback.contains("::1".parse().unwrap()));
+        assert!(!loopback.contains("127.0.0.1".parse().unwrap()));
+
+        assert!("0.0.0.0/0".parse::<IpNetwork>().unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("internal".parse::<IpNetwork>().is_err());
+    }
+}
@@ -2,6 +2,7 @@ use axum::extract::ws::{Message, WebSocket};
 use futures::stream::StreamExt;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::websocket::{ClientMessage, ServerMessage};
@@ -14,8 +15,14 @@ pub struct WebSocketConnection {
     pub id: ConnectionId,
     pub user_id: String,
     pub username: String,
+    /// Tenant the user belongs to, scoping the documents it can lock
+    pub tenant_id: Option<String>,
     sender: mpsc::UnboundedSender<ServerMessage>,
     subscriptions: Arc<RwLock<Vec<String>>>, // Document IDs
+    /// Documents this connection holds locks on, released when it goes away
+    held_locks: Arc<RwLock<Vec<String>>>,
+    /// Cancelled once the socket is gone, stopping work done on the client's behalf
+    closed: CancellationToken,
 }
 
 impl WebSocketConnection {
@@ -28,13 +35,17 @@ impl WebSocketConnection {
         let id = Uuid::new_v4().to_string();
         let (message_tx, message_rx) = mpsc::unbounded_channel();
         let (server_tx, server_rx) = mpsc::unbounded_channel();
+        let closed = CancellationToken::new();
 
         let connection = Self {
             id: id.clone(),
             user_id: user_id.clone(),
             username,
+            tenant_id: None,
             sender: server_tx,
             subscriptions: Arc::new(RwLock::new(Vec::new())),
+            held_locks: Arc::new(RwLock::new(Vec::new())),
+            closed: closed.clone(),
         };
 
         // Spawn task to handle WebSocket communication
@@ -44,11 +55,18 @@ impl WebSocketConnection {
             server_rx,
             id.clone(),
             user_id,
+            closed,
         ));
 
         (connection, message_rx)
     }
 
+    /// Scope the connection to `tenant_id`
+    pub fn with_tenant_id(mut self, tenant_id: Option<String>) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
     /// Send a message to the client
     pub async fn send_message(&self, message: ServerMessage) -> Result<(), String> {
         self.sender
@@ -56,6 +74,16 @@ impl WebSocketConnection {
             .map_err(|e| format!("Failed to send message: {}", e))
     }
 
+    /// Channel messages are queued on for the client, for tasks outliving a message handler
+    pub fn sender(&self) -> mpsc::UnboundedSender<ServerMessage> {
+        self.sender.clone()
+    }
+
+    /// Token cancelled when the client disconnects
+    pub fn closed(&self) -> CancellationToken {
+        self.closed.clone()
+    }
+
     /// Subscribe to document updates
     pub async fn subscribe_to_document(&self, document_id: String) {
         let mut subscriptions = self.subscriptions.write().await;
@@ -82,6 +110,25 @@ impl WebSocketConnection {
         subscriptions.clone()
     }
 
+    /// Remember that this connection holds a lock on a document
+    pub async fn hold_lock(&self, document_id: String) {
+        let mut held_locks = self.held_locks.write().await;
+        if !held_locks.contains(&document_id) {
+            held_locks.push(document_id);
+        }
+    }
+
+    /// Forget a lock this connection released
+    pub async fn drop_lock(&self, document_id: &str) {
+        let mut held_locks = self.held_locks.write().await;
+        held_locks.retain(|id| id != document_id);
+    }
+
+    /// Take the documents this connection holds locks on, forgetting them
+    pub async fn take_held_locks(&self) -> Vec<String> {
+        std::mem::take(&mut *self.held_locks.write().await)
+    }
+
     /// Handle WebSocket message communication
     async fn handle_websocket_messages(
         mut websocket: WebSocket,
@@ -89,6 +136,7 @@ impl WebSocketConnection {
         mut server_receiver: mpsc::UnboundedReceiver<ServerMessage>,
         connection_id: String,
         user_id: String,
+        closed: CancellationToken,
     ) {
         // Send initial connection confirmation
         let connected_message = ServerMessage::Connected {
@@ -173,6 +221,7 @@ impl WebSocketConnection {
         }
 
         // Clean shutdown
+        closed.cancel();
         let _ = websocket.close().await;
         tracing::info!("WebSocket connection {} handler terminated", connection_id);
     }
@@ -45,6 +45,7 @@ async fn handle_websocket_connection(
         user.user_id.clone(),
         user.username.clone(),
     );
+    let connection = connection.with_tenant_id(user.tenant_id.clone());
 
     // Add connection to the manager
     state.connection_manager.add_connection(connection, message_receiver).await;
@@ -1,18 +1,44 @@
 use dashmap::DashMap;
+use futures::stream::{BoxStream, StreamExt};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
+use writemagic_ai::StreamingChunk;
+use writemagic_shared::{DomainEvent, EntityId, InMemoryEventBus, RequestContext, WritemagicError};
+use writemagic_writing::core_engine::{CoreEngine, TextCompletionParams};
+use writemagic_writing::{DocumentEvent as WritingEvent, DocumentManagementService, TenantScope};
+
+use crate::middleware::rate_limit::RateLimitResult;
+use crate::middleware::{RateLimitKeyStrategy, RateLimitState};
 
 use crate::websocket::{
     connection::{ConnectionId, ConnectionStats},
-    messages::{ClientMessage, DocumentEvent, ServerMessage},
+    messages::{ClientMessage, DocumentEvent, EditOperation, ServerMessage},
     WebSocketConnection,
 };
 
+/// Lock TTL used when a client doesn't ask for one
+const DEFAULT_LOCK_TTL_SECS: u64 = 30;
+
+/// Pause in a document's edits before its links are refreshed
+const LINK_REFRESH_DEBOUNCE: Duration = Duration::from_secs(2);
+
 /// Manages all WebSocket connections and message broadcasting
 #[derive(Clone)]
 pub struct ConnectionManager {
     connections: Arc<DashMap<ConnectionId, Arc<WebSocketConnection>>>,
-    document_subscribers: Arc<DashMap<String, Vec<ConnectionId>>>, // document_id -> connection_ids
+    /// Connections subscribed to each document
+    document_subscribers: Arc<RwLock<HashMap<EntityId, HashSet<ConnectionId>>>>,
+    /// Service document locks are taken through
+    documents: Option<Arc<DocumentManagementService>>,
+    /// Engine streamed completions are run on
+    core_engine: Option<Arc<CoreEngine>>,
+    /// Limiter completions count against, per user
+    rate_limiter: Option<RateLimitState>,
+    /// Latest scheduled link refresh of each edited document
+    link_refreshes: Arc<DashMap<EntityId, u64>>,
 }
 
 impl ConnectionManager {
@@ -20,10 +46,27 @@ impl ConnectionManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(DashMap::new()),
-            document_subscribers: Arc::new(DashMap::new()),
+            document_subscribers: Arc::new(RwLock::new(HashMap::new())),
+            documents: None,
+            core_engine: None,
+            rate_limiter: None,
+            link_refreshes: Arc::new(DashMap::new()),
         }
     }
 
+    /// Serve `CompleteStream` requests from `core_engine`, limited per user by `rate_limiter`
+    pub fn with_completions(mut self, core_engine: Arc<CoreEngine>, rate_limiter: RateLimitState) -> Self {
+        self.core_engine = Some(core_engine);
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Take document locks requested by clients through `documents`
+    pub fn with_document_service(mut self, documents: Arc<DocumentManagementService>) -> Self {
+        self.documents = Some(documents);
+        self
+    }
+
     /// Add a new WebSocket connection
     pub async fn add_connection(
         &self,
@@ -53,9 +96,12 @@ impl ConnectionManager {
     /// Remove a WebSocket connection
     pub async fn remove_connection(&self, connection_id: &ConnectionId) {
         if let Some((_, connection)) = self.connections.remove(connection_id) {
+            // A dropped connection can't renew or release its locks, so release them now
+            self.release_held_locks(&connection).await;
+
             // Clean up document subscriptions
             let subscriptions = connection.get_subscriptions().await;
-            for document_id in subscriptions {
+            for document_id in subscriptions.iter().filter_map(|id| EntityId::from_string(id).ok()) {
                 self.remove_document_subscriber(&document_id, connection_id).await;
             }
             
@@ -73,18 +119,18 @@ impl ConnectionManager {
     }
 
     /// Subscribe a connection to document updates
-    pub async fn subscribe_to_document(&self, connection_id: &ConnectionId, document_id: String) {
+    pub async fn subscribe_to_document(&self, connection_id: &ConnectionId, document_id: EntityId) {
         if let Some(connection) = self.get_connection(connection_id) {
             // Add to connection's subscriptions
-            connection.subscribe_to_document(document_id.clone()).await;
+            connection.subscribe_to_document(document_id.to_string()).await;
             
             // Add to document subscribers
-            self.add_document_subscriber(document_id.clone(), connection_id.clone()).await;
+            self.add_document_subscriber(document_id, connection_id.clone()).await;
             
             // Send confirmation
             let subscriber_count = self.get_document_subscriber_count(&document_id).await;
             let confirmation = ServerMessage::SubscriptionConfirmed {
-                document_id: document_id.clone(),
+                document_id: document_id.to_string(),
                 user_count: subscriber_count,
             };
             
@@ -92,7 +138,7 @@ impl ConnectionManager {
             
             // Notify other users about the new subscriber
             let user_joined = ServerMessage::UserJoined {
-                document_id: document_id.clone(),
+                document_id: document_id.to_string(),
                 user_id: connection.user_id.clone(),
                 username: connection.username.clone(),
             };
@@ -108,10 +154,10 @@ impl ConnectionManager {
     }
 
     /// Unsubscribe a connection from document updates
-    pub async fn unsubscribe_from_document(&self, connection_id: &ConnectionId, document_id: &str) {
+    pub async fn unsubscribe_from_document(&self, connection_id: &ConnectionId, document_id: &EntityId) {
         if let Some(connection) = self.get_connection(connection_id) {
             // Remove from connection's subscriptions
-            connection.unsubscribe_from_document(document_id).await;
+            connection.unsubscribe_from_document(&document_id.to_string()).await;
             
             // Remove from document subscribers
             self.remove_document_subscriber(document_id, connection_id).await;
@@ -132,13 +178,63 @@ impl ConnectionManager {
         }
     }
 
+    /// Deliver a message to every connection subscribed to a document
+    pub async fn broadcast_to_document(&self, document_id: &EntityId, message: ServerMessage) {
+        self.broadcast_to_document_subscribers(document_id, message, None).await;
+    }
+
+    /// Announce the new version of each document the service layer changes to its subscribers
+    ///
+    /// Every save publishes a domain event, whether it came from the REST API,
+    /// a WebSocket edit, an import or a merge, so open editors hear about all
+    /// of them.
+    pub async fn relay_document_changes(&self, event_bus: &InMemoryEventBus) {
+        let manager = self.clone();
+        event_bus
+            .add_typed_subscription(move |event: &WritingEvent| {
+                // Nobody can be subscribed to a document that was just created
+                if !matches!(event, WritingEvent::DocumentCreated { .. }) {
+                    let manager = manager.clone();
+                    let document_id = event.aggregate_id();
+                    tokio::spawn(async move { manager.announce_new_version(document_id).await });
+                }
+                Ok(())
+            })
+            .await;
+    }
+
+    /// Send subscribers of a document its current version
+    async fn announce_new_version(&self, document_id: EntityId) {
+        let Some(documents) = &self.documents else {
+            return;
+        };
+        if self.get_document_subscriber_count(&document_id).await == 0 {
+            return;
+        }
+
+        match documents.get_document(&document_id).await {
+            Ok(Some(aggregate)) => {
+                let document = aggregate.document();
+                let message = ServerMessage::DocumentUpdated {
+                    document_id: document_id.to_string(),
+                    version: document.version,
+                    updated_by: document.updated_by.map(|id| id.to_string()),
+                    updated_at: document.updated_at.as_datetime(),
+                };
+                self.broadcast_to_document(&document_id, message).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(document_id = %document_id, error = %e, "Failed to load updated document"),
+        }
+    }
+
     /// Broadcast a document event to all subscribers
-    pub async fn broadcast_document_event(&self, event: DocumentEvent) {
+    pub async fn broadcast_document_event(&self, document_id: &EntityId, event: DocumentEvent) {
         let message = ServerMessage::DocumentEvent {
             event: event.clone(),
         };
         
-        self.broadcast_to_document_subscribers(&event.document_id, message, None).await;
+        self.broadcast_to_document_subscribers(document_id, message, None).await;
         
         tracing::debug!(
             document_id = %event.document_id,
@@ -166,8 +262,10 @@ impl ConnectionManager {
     }
 
     /// Get subscriber count for a document
-    pub async fn get_document_subscriber_count(&self, document_id: &str) -> usize {
+    pub async fn get_document_subscriber_count(&self, document_id: &EntityId) -> usize {
         self.document_subscribers
+            .read()
+            .await
             .get(document_id)
             .map(|subscribers| subscribers.len())
             .unwrap_or(0)
@@ -213,15 +311,16 @@ impl ConnectionManager {
     ) -> Result<(), String> {
         match message {
             ClientMessage::SubscribeDocument { document_id } => {
-                self.subscribe_to_document(&connection.id, document_id).await;
+                self.subscribe_to_document(&connection.id, parse_document_id(&document_id)?).await;
                 Ok(())
             }
             ClientMessage::UnsubscribeDocument { document_id } => {
-                self.unsubscribe_from_document(&connection.id, &document_id).await;
+                self.unsubscribe_from_document(&connection.id, &parse_document_id(&document_id)?).await;
                 Ok(())
             }
             ClientMessage::DocumentEdit {
                 document_id,
+                base_version,
                 operation,
                 timestamp,
             } => {
@@ -230,6 +329,10 @@ impl ConnectionManager {
                     return Err("Not subscribed to document".to_string());
                 }
 
+                // Persist the edit first so rejected edits are not relayed
+                let entity_id = parse_document_id(&document_id)?;
+                let (version, stats) = self.apply_edit(connection, entity_id, base_version, &operation).await?;
+
                 // Create document event
                 let event = DocumentEvent {
                     document_id,
@@ -237,11 +340,14 @@ impl ConnectionManager {
                     username: connection.username.clone(),
                     operation,
                     timestamp,
-                    version: 1, // In a real implementation, this would be managed properly
+                    version,
                 };
 
                 // Broadcast to other subscribers
-                self.broadcast_document_event(event).await;
+                self.broadcast_document_event(&entity_id, event).await;
+                if let Some(stats) = stats {
+                    self.broadcast_to_document(&entity_id, stats).await;
+                }
                 Ok(())
             }
             ClientMessage::CursorUpdate {
@@ -260,9 +366,55 @@ impl ConnectionManager {
                     position,
                 };
 
-                self.broadcast_to_document_subscribers(&document_id, cursor_message, Some(&connection.id)).await;
+                self.broadcast_to_document_subscribers(&parse_document_id(&document_id)?, cursor_message, Some(&connection.id)).await;
+                Ok(())
+            }
+            ClientMessage::AcquireLock { document_id, ttl_seconds } => {
+                let entity_id = parse_document_id(&document_id)?;
+                let (documents, holder) = self.lock_context(connection)?;
+                let ttl = Duration::from_secs(ttl_seconds.unwrap_or(DEFAULT_LOCK_TTL_SECS));
+
+                match documents.acquire_lock(entity_id, holder, ttl).await {
+                    Ok(lock) => {
+                        connection.hold_lock(document_id.clone()).await;
+                        let message = ServerMessage::LockAcquired {
+                            document_id,
+                            holder: connection.user_id.clone(),
+                            expires_at: lock.expires_at.0,
+                        };
+                        connection.send_message(message.clone()).await?;
+                        self.broadcast_to_document_subscribers(&entity_id, message, Some(&connection.id)).await;
+                        Ok(())
+                    }
+                    Err(e @ WritemagicError::Conflict { .. }) => {
+                        let message = ServerMessage::Error {
+                            message: e.to_string(),
+                            code: Some("DOCUMENT_LOCKED".to_string()),
+                        };
+                        connection.send_message(message).await
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            ClientMessage::ReleaseLock { document_id } => {
+                let entity_id = parse_document_id(&document_id)?;
+                let (documents, holder) = self.lock_context(connection)?;
+
+                let released = documents.release_lock(entity_id, holder).await.map_err(|e| e.to_string())?;
+                connection.drop_lock(&document_id).await;
+                if released {
+                    let message = ServerMessage::LockReleased {
+                        document_id,
+                        holder: connection.user_id.clone(),
+                    };
+                    connection.send_message(message.clone()).await?;
+                    self.broadcast_to_document_subscribers(&entity_id, message, Some(&connection.id)).await;
+                }
                 Ok(())
             }
+            ClientMessage::CompleteStream { stream_id, prompt, model } => {
+                self.start_completion(connection, stream_id, prompt, model).await
+            }
             ClientMessage::Ping { timestamp } => {
                 let pong = ServerMessage::Pong { timestamp };
                 connection.send_message(pong).await.map_err(|e| e.to_string())?;
@@ -271,26 +423,175 @@ impl ConnectionManager {
         }
     }
 
+    /// Document service scoped to the connection's tenant and holding its locks, and the connection's holder ID
+    ///
+    /// Locks belong to the connection rather than the user, so a second tab
+    /// of the same user can't write past the first one's lock.
+    fn lock_context(&self, connection: &WebSocketConnection) -> Result<(DocumentManagementService, EntityId), String> {
+        let documents = self.documents.as_ref().ok_or_else(|| "Document locking is not available".to_string())?;
+        let holder = EntityId::from_string(&connection.id)
+            .map_err(|_| format!("Invalid connection ID: {}", connection.id))?;
+        let documents = documents
+            .scoped(TenantScope::from(connection.tenant_id.clone()))
+            .as_lock_holder(holder);
+        Ok((documents, holder))
+    }
+
+    /// Release every lock `connection` still holds, telling the document's other subscribers
+    async fn release_held_locks(&self, connection: &WebSocketConnection) {
+        let held_locks = connection.take_held_locks().await;
+        if held_locks.is_empty() {
+            return;
+        }
+        let Ok((documents, holder)) = self.lock_context(connection) else {
+            return;
+        };
+
+        for document_id in held_locks {
+            let Ok(entity_id) = EntityId::from_string(&document_id) else {
+                continue;
+            };
+            match documents.release_lock(entity_id, holder).await {
+                Ok(true) => {
+                    let message = ServerMessage::LockReleased {
+                        document_id,
+                        holder: connection.user_id.clone(),
+                    };
+                    self.broadcast_to_document_subscribers(&entity_id, message, Some(&connection.id)).await;
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!(document_id = %entity_id, "Failed to release lock of closed connection: {}", e),
+            }
+        }
+    }
+
+    /// Start streaming a completion to the connection once the user's rate limit allows it
+    ///
+    /// The stream runs in its own task so the connection keeps handling
+    /// messages, and stops when the client disconnects.
+    async fn start_completion(
+        &self,
+        connection: &WebSocketConnection,
+        stream_id: String,
+        prompt: String,
+        model: Option<String>,
+    ) -> Result<(), String> {
+        let (core_engine, rate_limiter) = match (&self.core_engine, &self.rate_limiter) {
+            (Some(core_engine), Some(rate_limiter)) => (core_engine.clone(), rate_limiter),
+            _ => return Err("Completions are not available".to_string()),
+        };
+
+        let key = RateLimitKeyStrategy::PerUser.key(Some(&connection.user_id), "");
+        if let RateLimitResult::Limited { retry_after, .. } = rate_limiter.check_rate_limit(&key) {
+            let message = ServerMessage::Error {
+                message: format!("Rate limit exceeded, retry after {} seconds", retry_after),
+                code: Some("RATE_LIMITED".to_string()),
+            };
+            return connection.send_message(message).await;
+        }
+
+        let sender = connection.sender();
+        let closed = connection.closed();
+        tokio::spawn(RequestContext::generate().scope("websocket.complete_stream", async move {
+            let params = TextCompletionParams { model, ..TextCompletionParams::default() };
+            match core_engine.complete_text_chunks(prompt, params).await {
+                Ok(chunks) => forward_completion(&stream_id, chunks, closed, &sender).await,
+                Err(e) => {
+                    let _ = sender.send(ServerMessage::CompletionFailed { stream_id, message: e.to_string() });
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    /// Apply a relayed edit made to `base_version` through the document service, when one is configured
+    ///
+    /// Returns the document's version after the edit and the message
+    /// announcing its new statistics.
+    async fn apply_edit(
+        &self,
+        connection: &WebSocketConnection,
+        document_id: EntityId,
+        base_version: u64,
+        operation: &EditOperation,
+    ) -> Result<(u64, Option<ServerMessage>), String> {
+        let Some((start, old_len, text)) = operation.as_delta() else {
+            return Ok((base_version, None));
+        };
+        if self.documents.is_none() {
+            return Ok((base_version, None));
+        }
+
+        let (documents, _) = self.lock_context(connection)?;
+        let editor = EntityId::from_string(&connection.user_id)
+            .map_err(|_| format!("Invalid user ID: {}", connection.user_id))?;
+        let event = documents
+            .apply_content_delta(document_id, base_version, start, old_len, text, Some(editor))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(match event {
+            Some(WritingEvent::DocumentStatsChanged { word_count, character_count, .. }) => {
+                self.schedule_link_refresh(documents, document_id);
+                let stats = ServerMessage::DocumentStatsChanged {
+                    document_id: document_id.to_string(),
+                    word_count,
+                    character_count,
+                };
+                (base_version + 1, Some(stats))
+            }
+            _ => (base_version, None),
+        })
+    }
+
+    /// Refresh a document's links once its edits pause for [`LINK_REFRESH_DEBOUNCE`]
+    fn schedule_link_refresh(&self, documents: DocumentManagementService, document_id: EntityId) {
+        let generation = self.next_link_refresh(document_id);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(LINK_REFRESH_DEBOUNCE).await;
+            if !manager.take_link_refresh(document_id, generation) {
+                return;
+            }
+            if let Err(e) = documents.refresh_document_links(document_id).await {
+                tracing::warn!(document_id = %document_id, "Failed to refresh document links: {}", e);
+            }
+        });
+    }
+
+    /// Supersede any pending link refresh of `document_id`, returning the new one's generation
+    fn next_link_refresh(&self, document_id: EntityId) -> u64 {
+        let mut generation = self.link_refreshes.entry(document_id).or_insert(0);
+        *generation += 1;
+        *generation
+    }
+
+    /// Claim the refresh `generation` of `document_id`, unless a later edit superseded it
+    fn take_link_refresh(&self, document_id: EntityId, generation: u64) -> bool {
+        self.link_refreshes
+            .remove_if(&document_id, |_, latest| *latest == generation)
+            .is_some()
+    }
+
     /// Add a subscriber to a document
-    async fn add_document_subscriber(&self, document_id: String, connection_id: ConnectionId) {
-        let mut subscribers = self.document_subscribers
+    async fn add_document_subscriber(&self, document_id: EntityId, connection_id: ConnectionId) {
+        self.document_subscribers
+            .write()
+            .await
             .entry(document_id)
-            .or_insert_with(Vec::new);
-        
-        if !subscribers.contains(&connection_id) {
-            subscribers.push(connection_id);
-        }
+            .or_default()
+            .insert(connection_id);
     }
 
     /// Remove a subscriber from a document
-    async fn remove_document_subscriber(&self, document_id: &str, connection_id: &ConnectionId) {
-        if let Some(mut subscribers) = self.document_subscribers.get_mut(document_id) {
-            subscribers.retain(|id| id != connection_id);
+    async fn remove_document_subscriber(&self, document_id: &EntityId, connection_id: &ConnectionId) {
+        let mut document_subscribers = self.document_subscribers.write().await;
+        if let Some(subscribers) = document_subscribers.get_mut(document_id) {
+            subscribers.remove(connection_id);
             
-            // Clean up empty subscriber lists
+            // Clean up empty subscriber sets
             if subscribers.is_empty() {
-                drop(subscribers); // Release the mutable reference
-                self.document_subscribers.remove(document_id);
+                document_subscribers.remove(document_id);
             }
         }
     }
@@ -298,13 +599,18 @@ impl ConnectionManager {
     /// Broadcast a message to all document subscribers
     async fn broadcast_to_document_subscribers(
         &self,
-        document_id: &str,
+        document_id: &EntityId,
         message: ServerMessage,
         exclude_connection: Option<&ConnectionId>,
     ) {
-        if let Some(subscribers) = self.document_subscribers.get(document_id) {
-            let subscriber_ids: Vec<ConnectionId> = subscribers.clone();
-            
+        // Copy the subscriber set so the lock isn't held while sending
+        let subscriber_ids: Option<Vec<ConnectionId>> = self.document_subscribers
+            .read()
+            .await
+            .get(document_id)
+            .map(|subscribers| subscribers.iter().cloned().collect());
+
+        if let Some(subscriber_ids) = subscriber_ids {
             for connection_id in subscriber_ids {
                 // Skip excluded connection (usually the sender)
                 if let Some(exclude_id) = exclude_connection {
@@ -321,6 +627,57 @@ impl ConnectionManager {
     }
 }
 
+/// Send each non-empty chunk as a `CompletionDelta` of `stream_id`, then `CompletionDone`
+///
+/// Stops early, dropping the provider stream, when the stream fails, the
+/// client goes away or `closed` is cancelled.
+async fn forward_completion(
+    stream_id: &str,
+    mut chunks: BoxStream<'static, writemagic_shared::Result<StreamingChunk>>,
+    closed: CancellationToken,
+    sender: &mpsc::UnboundedSender<ServerMessage>,
+) {
+    let mut usage = None;
+    loop {
+        let chunk = tokio::select! {
+            _ = closed.cancelled() => {
+                tracing::debug!("Client disconnected, abandoning completion stream");
+                return;
+            }
+            chunk = chunks.next() => chunk,
+        };
+
+        match chunk {
+            Some(Ok(chunk)) => {
+                if chunk.usage.is_some() {
+                    usage = chunk.usage;
+                }
+                if !chunk.content.is_empty()
+                    && sender
+                        .send(ServerMessage::CompletionDelta { stream_id: stream_id.to_string(), text: chunk.content })
+                        .is_err()
+                {
+                    return;
+                }
+            }
+            Some(Err(e)) => {
+                let _ = sender.send(ServerMessage::CompletionFailed {
+                    stream_id: stream_id.to_string(),
+                    message: e.to_string(),
+                });
+                return;
+            }
+            None => break,
+        }
+    }
+
+    let _ = sender.send(ServerMessage::CompletionDone { stream_id: stream_id.to_string(), usage });
+}
+
+fn parse_document_id(document_id: &str) -> Result<EntityId, String> {
+    EntityId::from_string(document_id).map_err(|_| format!("Invalid document ID: {}", document_id))
+}
+
 impl Default for ConnectionManager {
     fn default() -> Self {
         Self::new()
@@ -339,14 +696,12 @@ pub struct ManagerStats {
 impl ConnectionManager {
     /// Get manager statistics
     pub async fn get_manager_stats(&self) -> ManagerStats {
-        let total_subscriptions: usize = self.document_subscribers
-            .iter()
-            .map(|entry| entry.value().len())
-            .sum();
+        let document_subscribers = self.document_subscribers.read().await;
+        let total_subscriptions: usize = document_subscribers.values().map(HashSet::len).sum();
 
         ManagerStats {
             total_connections: self.connection_count(),
-            active_documents: self.document_subscribers.len(),
+            active_documents: document_subscribers.len(),
             total_subscriptions,
             timestamp: chrono::Utc::now(),
         }
@@ -372,6 +727,115 @@ mod tests {
         assert_eq!(stats.total_subscriptions, 0);
     }
 
+    #[tokio::test]
+    async fn test_document_subscriptions_are_tracked_per_document() {
+        let manager = ConnectionManager::new();
+        let document_id = EntityId::new();
+
+        manager.add_document_subscriber(document_id, "conn_1".to_string()).await;
+        manager.add_document_subscriber(document_id, "conn_1".to_string()).await;
+        manager.add_document_subscriber(document_id, "conn_2".to_string()).await;
+        manager.add_document_subscriber(EntityId::new(), "conn_2".to_string()).await;
+
+        assert_eq!(manager.get_document_subscriber_count(&document_id).await, 2);
+        assert_eq!(manager.get_manager_stats().await.total_subscriptions, 3);
+
+        manager.remove_document_subscriber(&document_id, &"conn_1".to_string()).await;
+        manager.remove_document_subscriber(&document_id, &"conn_2".to_string()).await;
+        assert_eq!(manager.get_document_subscriber_count(&document_id).await, 0);
+        assert_eq!(manager.get_manager_stats().await.active_documents, 1);
+    }
+
+    #[test]
+    fn test_only_the_latest_link_refresh_of_a_document_runs() {
+        let manager = ConnectionManager::new();
+        let document_id = EntityId::new();
+        let other_document = EntityId::new();
+
+        let first = manager.next_link_refresh(document_id);
+        let second = manager.next_link_refresh(document_id);
+        let other = manager.next_link_refresh(other_document);
+
+        assert!(!manager.take_link_refresh(document_id, first));
+        assert!(manager.take_link_refresh(document_id, second));
+        assert!(!manager.take_link_refresh(document_id, second));
+        assert!(manager.take_link_refresh(other_document, other));
+    }
+
+    fn chunk(content: &str, usage: Option<writemagic_ai::Usage>) -> writemagic_shared::Result<StreamingChunk> {
+        Ok(StreamingChunk { content: content.to_string(), finish_reason: None, usage })
+    }
+
+    #[tokio::test]
+    async fn test_forward_completion_sends_deltas_then_usage() {
+        let usage = writemagic_ai::Usage { prompt_tokens: 3, completion_tokens: 2, total_tokens: 5 };
+        let chunks = futures::stream::iter(vec![chunk("Hel", None), chunk("", None), chunk("lo", Some(usage))]).boxed();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        forward_completion("s1", chunks, CancellationToken::new(), &sender).await;
+
+        assert!(matches!(receiver.recv().await, Some(ServerMessage::CompletionDelta { text, .. }) if text == "Hel"));
+        assert!(matches!(receiver.recv().await, Some(ServerMessage::CompletionDelta { text, .. }) if text == "lo"));
+        assert!(matches!(
+            receiver.recv().await,
+            Some(ServerMessage::CompletionDone { stream_id, usage: Some(usage) }) if stream_id == "s1" && usage.total_tokens == 5
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forward_completion_stops_when_client_disconnects() {
+        let closed = CancellationToken::new();
+        // A provider that never finishes after its first delta
+        let chunks = futures::stream::iter(vec![chunk("partial", None)])
+            .chain(futures::stream::pending())
+            .boxed();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let forwarding = tokio::spawn({
+            let closed = closed.clone();
+            async move { forward_completion("s1", chunks, closed, &sender).await }
+        });
+        assert!(matches!(receiver.recv().await, Some(ServerMessage::CompletionDelta { .. })));
+
+        closed.cancel();
+        tokio::time::timeout(Duration::from_secs(1), forwarding).await.unwrap().unwrap();
+        // The sender was dropped without a CompletionDone
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_completions_on_one_socket_are_told_apart_by_stream_id() {
+        let first = futures::stream::iter(vec![chunk("one ", None), chunk("two", None)]).boxed();
+        let second = futures::stream::iter(vec![
+            chunk("uno", None),
+            Err(WritemagicError::internal("provider went away")),
+        ])
+        .boxed();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        tokio::join!(
+            forward_completion("a", first, CancellationToken::new(), &sender),
+            forward_completion("b", second, CancellationToken::new(), &sender),
+        );
+        drop(sender);
+
+        let mut texts: HashMap<String, String> = HashMap::new();
+        let mut done = Vec::new();
+        let mut failed = Vec::new();
+        while let Some(message) = receiver.recv().await {
+            match message {
+                ServerMessage::CompletionDelta { stream_id, text } => texts.entry(stream_id).or_default().push_str(&text),
+                ServerMessage::CompletionDone { stream_id, .. } => done.push(stream_id),
+                ServerMessage::CompletionFailed { stream_id, .. } => failed.push(stream_id),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        assert_eq!(texts["a"], "one two");
+        assert_eq!(texts["b"], "uno");
+        assert_eq!(done, vec!["a"]);
+        assert_eq!(failed, vec!["b"]);
+    }
+
     #[test]
     fn test_manager_creation() {
         let manager = ConnectionManager::new();
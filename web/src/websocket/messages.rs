@@ -5,16 +5,20 @@ use serde::{Deserialize, Serialize};
 #[serde(tag = "type")]
 pub enum ClientMessage {
     /// Subscribe to document updates
+    #[serde(alias = "Subscribe")]
     SubscribeDocument {
         document_id: String,
     },
     /// Unsubscribe from document updates
+    #[serde(alias = "Unsubscribe")]
     UnsubscribeDocument {
         document_id: String,
     },
     /// Real-time document edit
     DocumentEdit {
         document_id: String,
+        /// Version of the document the edit was made to
+        base_version: u64,
         operation: EditOperation,
         timestamp: chrono::DateTime<chrono::Utc>,
     },
@@ -23,6 +27,25 @@ pub enum ClientMessage {
         document_id: String,
         position: CursorPosition,
     },
+    /// Take or renew the advisory edit lock on a document
+    AcquireLock {
+        document_id: String,
+        /// Seconds until the lock expires unless renewed, defaulting to 30
+        ttl_seconds: Option<u64>,
+    },
+    /// Give up the edit lock on a document
+    ReleaseLock {
+        document_id: String,
+    },
+    /// Stream a text completion back as `CompletionDelta` messages
+    ///
+    /// `stream_id` is chosen by the client and tags every message of the
+    /// stream, so several streams can share one socket.
+    CompleteStream {
+        stream_id: String,
+        prompt: String,
+        model: Option<String>,
+    },
     /// Ping to keep connection alive
     Ping {
         timestamp: chrono::DateTime<chrono::Utc>,
@@ -42,6 +65,19 @@ pub enum ServerMessage {
     DocumentEvent {
         event: DocumentEvent,
     },
+    /// A document was saved, so open editors should reload it
+    DocumentUpdated {
+        document_id: String,
+        version: u64,
+        updated_by: Option<String>,
+        updated_at: chrono::DateTime<chrono::Utc>,
+    },
+    /// A live edit changed a document's word and character counts
+    DocumentStatsChanged {
+        document_id: String,
+        word_count: u32,
+        character_count: u32,
+    },
     /// User joined document
     UserJoined {
         document_id: String,
@@ -60,6 +96,32 @@ pub enum ServerMessage {
         username: String,
         position: CursorPosition,
     },
+    /// A user took or renewed the edit lock on a document
+    LockAcquired {
+        document_id: String,
+        holder: String,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    },
+    /// A user gave up the edit lock on a document
+    LockReleased {
+        document_id: String,
+        holder: String,
+    },
+    /// Next piece of a streamed completion
+    CompletionDelta {
+        stream_id: String,
+        text: String,
+    },
+    /// A streamed completion finished; `usage` is set when the provider reported it
+    CompletionDone {
+        stream_id: String,
+        usage: Option<writemagic_ai::Usage>,
+    },
+    /// A streamed completion failed and sends nothing more
+    CompletionFailed {
+        stream_id: String,
+        message: String,
+    },
     /// Error message
     Error {
         message: String,
@@ -170,6 +232,18 @@ impl EditOperation {
         }
     }
 
+    /// The edit as start offset, removed length and inserted text; `None` for `SetContent`
+    pub fn as_delta(&self) -> Option<(usize, usize, &str)> {
+        match self {
+            EditOperation::Insert { position, text } => Some((*position as usize, 0, text)),
+            EditOperation::Delete { start, end } => Some((*start as usize, end.checked_sub(*start)? as usize, "")),
+            EditOperation::Replace { start, end, text } => {
+                Some((*start as usize, end.checked_sub(*start)? as usize, text))
+            }
+            EditOperation::SetContent { .. } => None,
+        }
+    }
+
     /// Check if this operation conflicts with another operation
     pub fn conflicts_with(&self, other: &EditOperation) -> bool {
         match (self, other) {
@@ -223,6 +297,14 @@ impl CursorPosition {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_operations_as_deltas() {
+        let replace = EditOperation::Replace { start: 2, end: 5, text: "xy".to_string() };
+        assert_eq!(replace.as_delta(), Some((2, 3, "xy")));
+        assert_eq!(EditOperation::Delete { start: 4, end: 2 }.as_delta(), None);
+        assert_eq!(EditOperation::SetContent { content: "all".to_string() }.as_delta(), None);
+    }
+
     #[test]
     fn test_insert_operation() {
         let content = "Hello world";
@@ -281,6 +363,15 @@ mod tests {
         assert!(insert1.conflicts_with(&delete));
     }
 
+    #[test]
+    fn test_subscription_messages_accept_short_names() {
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "Subscribe", "document_id": "doc_1"}"#).unwrap();
+        assert!(matches!(message, ClientMessage::SubscribeDocument { document_id } if document_id == "doc_1"));
+
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "Unsubscribe", "document_id": "doc_1"}"#).unwrap();
+        assert!(matches!(message, ClientMessage::UnsubscribeDocument { .. }));
+    }
+
     #[test]
     fn test_cursor_position() {
         let cursor = CursorPosition::at_offset(42);
@@ -61,6 +61,7 @@ impl TestApp {
             updated_at: sea_orm::Set(chrono::Utc::now()),
             is_active: sea_orm::Set(true),
             role: sea_orm::Set("user".to_string()),
+            tenant_id: sea_orm::Set(None),
         };
 
         user_model
@@ -83,6 +84,7 @@ impl TestApp {
             updated_at: sea_orm::Set(chrono::Utc::now()),
             is_active: sea_orm::Set(true),
             role: sea_orm::Set("admin".to_string()),
+            tenant_id: sea_orm::Set(None),
         };
 
         user_model